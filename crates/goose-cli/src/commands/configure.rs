@@ -3,7 +3,7 @@ use console::style;
 use goose::agents::{extension::Envs, ExtensionConfig};
 use goose::config::{Config, ConfigError, ExperimentManager, ExtensionEntry, ExtensionManager};
 use goose::message::Message;
-use goose::providers::{create, providers};
+use goose::providers::{create, fetch_metadata, providers};
 use mcp_core::Tool;
 use serde_json::{json, Value};
 use std::collections::HashMap;
@@ -285,8 +285,15 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
         }
     }
 
+    // Now that the provider is configured, it may be able to tell which models it serves
+    let recommended_model = fetch_metadata(provider_name)
+        .await
+        .map_or(provider_meta.default_model.clone(), |meta| {
+            meta.default_model
+        });
+
     // Select model, defaulting to the provider's recommended model UNLESS there is an env override
-    let default_model = std::env::var("GOOSE_MODEL").unwrap_or(provider_meta.default_model.clone());
+    let default_model = std::env::var("GOOSE_MODEL").unwrap_or(recommended_model);
     let model: String = cliclack::input("Enter a model from that provider:")
         .default_input(&default_model)
         .interact()?;
@@ -402,6 +409,11 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
             "Remote Extension",
             "Connect to a remote extension via SSE",
         )
+        .item(
+            "streamable_http",
+            "Remote Extension (Streamable HTTP)",
+            "Connect to a remote extension via streamable HTTP",
+        )
         .interact()?;
 
     match extension_type {
@@ -511,7 +523,7 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
 
             cliclack::outro(format!("Added {} extension", style(name).green()))?;
         }
-        "sse" => {
+        "sse" | "streamable_http" => {
             let extensions = ExtensionManager::get_all_names()?;
             let name: String = cliclack::input("What would you like to call this extension?")
                 .placeholder("my-remote-extension")
@@ -526,8 +538,16 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                 })
                 .interact()?;
 
-            let uri: String = cliclack::input("What is the SSE endpoint URI?")
-                .placeholder("http://localhost:8000/events")
+            let (prompt, placeholder) = if extension_type == "sse" {
+                (
+                    "What is the SSE endpoint URI?",
+                    "http://localhost:8000/events",
+                )
+            } else {
+                ("What is the MCP endpoint URI?", "http://localhost:8000/mcp")
+            };
+            let uri: String = cliclack::input(prompt)
+                .placeholder(placeholder)
                 .validate(|input: &String| {
                     if input.is_empty() {
                         Err("Please enter a URI")
@@ -561,13 +581,46 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                 }
             }
 
-            ExtensionManager::set(ExtensionEntry {
-                enabled: true,
-                config: ExtensionConfig::Sse {
+            let add_headers =
+                cliclack::confirm("Would you like to add HTTP headers, e.g. for authorization?")
+                    .interact()?;
+
+            let mut headers = HashMap::new();
+            if add_headers {
+                loop {
+                    let key: String = cliclack::input("Header name:")
+                        .placeholder("Authorization")
+                        .interact()?;
+
+                    let value: String = cliclack::password("Header value:").mask('▪').interact()?;
+
+                    headers.insert(key, value);
+
+                    if !cliclack::confirm("Add another header?").interact()? {
+                        break;
+                    }
+                }
+            }
+
+            let config = if extension_type == "sse" {
+                ExtensionConfig::Sse {
                     name: name.clone(),
                     uri,
                     envs: Envs::new(envs),
-                },
+                    headers,
+                }
+            } else {
+                ExtensionConfig::StreamableHttp {
+                    name: name.clone(),
+                    uri,
+                    envs: Envs::new(envs),
+                    headers,
+                }
+            };
+
+            ExtensionManager::set(ExtensionEntry {
+                enabled: true,
+                config,
             })?;
 
             cliclack::outro(format!("Added {} extension", style(name).green()))?;
@@ -681,6 +734,11 @@ pub fn configure_goose_mode_dialog() -> Result<(), Box<dyn Error>> {
             "Chat Mode",
             "Engage with the selected provider without using tools, extensions, or file modification"
         )
+        .item(
+            "plan",
+            "Plan Mode",
+            "Draft a plan for each request, which runs freely once you approve it"
+        )
         .interact()?;
 
     match mode {
@@ -696,6 +754,10 @@ pub fn configure_goose_mode_dialog() -> Result<(), Box<dyn Error>> {
             config.set("GOOSE_MODE", Value::String("chat".to_string()))?;
             cliclack::outro("Set to Chat Mode - no tools or modifications enabled")?;
         }
+        "plan" => {
+            config.set("GOOSE_MODE", Value::String("plan".to_string()))?;
+            cliclack::outro("Set to Plan Mode - plans require approval")?;
+        }
         _ => unreachable!(),
     };
     Ok(())
@@ -0,0 +1,28 @@
+use anyhow::Result;
+use goose::session::{Session, SessionStore, Transcript, TranscriptFormat};
+use std::path::PathBuf;
+
+pub fn handle_export(name: Option<String>, format: &str, output: Option<PathBuf>) -> Result<()> {
+    let format: TranscriptFormat = format.parse()?;
+    let store = SessionStore::global()?;
+    let id = match name {
+        Some(name) => name,
+        None => store
+            .list()?
+            .into_iter()
+            .next()
+            .map(|session| session.id)
+            .ok_or_else(|| anyhow::anyhow!("No sessions found"))?,
+    };
+
+    let session = Session::resume_in(store, &id)?;
+    let transcript = Transcript::from_session(&session).render(format);
+    match output {
+        Some(path) => {
+            std::fs::write(&path, transcript)?;
+            println!("Exported session {} to {}", id, path.display());
+        }
+        None => print!("{}", transcript),
+    }
+    Ok(())
+}
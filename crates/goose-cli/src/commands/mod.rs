@@ -1,4 +1,5 @@
 pub mod agent_version;
 pub mod configure;
+pub mod export;
 pub mod info;
 pub mod mcp;
@@ -2,14 +2,19 @@ use anyhow::Result;
 use clap::{CommandFactory, Parser, Subcommand};
 
 use console::style;
+use goose::agents::SessionLimits;
 use goose::config::Config;
+use goose::prompt_template::PromptLibrary;
 use goose_cli::commands::agent_version::AgentCommand;
 use goose_cli::commands::configure::handle_configure;
+use goose_cli::commands::export::handle_export;
 use goose_cli::commands::info::handle_info;
 use goose_cli::commands::mcp::run_server;
 use goose_cli::logging::setup_logging;
 use goose_cli::session::build_session;
+use std::collections::HashMap;
 use std::io::{self, Read};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(author, version, display_name = "", about, long_about = None)]
@@ -61,6 +66,25 @@ enum Command {
         )]
         resume: bool,
 
+        /// Fork a previous session into a new branch
+        #[arg(
+            long,
+            value_name = "SESSION",
+            conflicts_with = "resume",
+            help = "Start a new branch of a previous session",
+            long_help = "Start a new session that shares the history of a previous session, to explore an alternative without changing the original. Use --name to name the branch."
+        )]
+        fork: Option<String>,
+
+        /// Message to fork the session at
+        #[arg(
+            long,
+            value_name = "MESSAGE",
+            requires = "fork",
+            help = "Number of messages of the forked session to keep (defaults to all of them)"
+        )]
+        fork_at: Option<usize>,
+
         /// Add stdio extensions with environment variables and commands
         #[arg(
             long = "with-extension",
@@ -82,6 +106,38 @@ enum Command {
         builtin: Vec<String>,
     },
 
+    /// Export a session transcript
+    #[command(about = "Export a session to a Markdown or HTML transcript")]
+    Export {
+        /// Name of the session to export
+        #[arg(
+            short,
+            long,
+            value_name = "NAME",
+            help = "Name of the session to export (defaults to the most recent session)"
+        )]
+        name: Option<String>,
+
+        /// Format of the transcript
+        #[arg(
+            short,
+            long,
+            value_name = "FORMAT",
+            default_value = "md",
+            help = "Format of the transcript: md or html"
+        )]
+        format: String,
+
+        /// File to write the transcript to
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            help = "Write the transcript to this file instead of stdout"
+        )]
+        output: Option<PathBuf>,
+    },
+
     /// Execute commands from an instruction file
     #[command(about = "Execute commands from an instruction file or stdin")]
     Run {
@@ -106,6 +162,27 @@ enum Command {
         )]
         input_text: Option<String>,
 
+        /// Prompt template to render as the input
+        #[arg(
+            short = 'p',
+            long = "template",
+            value_name = "NAME",
+            help = "Prompt template to use as the input (e.g., 'review' for review.md)",
+            long_help = "Render a prompt template from the prompts directory in the goose config or data dir and use it as the input. Fill in its variables with --var.",
+            conflicts_with_all = ["instructions", "input_text"]
+        )]
+        template: Option<String>,
+
+        /// Variables for the prompt template
+        #[arg(
+            long = "var",
+            value_name = "KEY=VALUE",
+            help = "Set a variable of the prompt template (can be specified multiple times)",
+            requires = "template",
+            action = clap::ArgAction::Append
+        )]
+        vars: Vec<String>,
+
         /// Name for this run session
         #[arg(
             short,
@@ -145,6 +222,30 @@ enum Command {
             value_delimiter = ','
         )]
         builtin: Vec<String>,
+
+        /// Stop the run after this long
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            help = "Stop the run after this many seconds"
+        )]
+        max_duration: Option<u64>,
+
+        /// Stop the run after this many tokens
+        #[arg(
+            long,
+            value_name = "TOKENS",
+            help = "Stop the run once it has used this many tokens"
+        )]
+        max_tokens: Option<i64>,
+
+        /// Stop the run after spending this much
+        #[arg(
+            long,
+            value_name = "USD",
+            help = "Stop the run once it has spent this many dollars"
+        )]
+        max_cost: Option<f64>,
     },
 
     /// List available agent versions
@@ -177,29 +278,47 @@ async fn main() -> Result<()> {
         Some(Command::Session {
             name,
             resume,
+            fork,
+            fork_at,
             extension,
             builtin,
         }) => {
-            let mut session = build_session(name, resume, extension, builtin).await;
-            setup_logging(session.session_file().file_stem().and_then(|s| s.to_str()))?;
+            let fork = fork.map(|source| (source, fork_at));
+            let mut session = build_session(name, resume, fork, extension, builtin).await;
+            setup_logging(Some(session.id()))?;
             let _ = session.start().await;
             return Ok(());
         }
+        Some(Command::Export {
+            name,
+            format,
+            output,
+        }) => {
+            handle_export(name, &format, output)?;
+            return Ok(());
+        }
         Some(Command::Run {
             instructions,
             input_text,
+            template,
+            vars,
             name,
             resume,
             extension,
             builtin,
+            max_duration,
+            max_tokens,
+            max_cost,
         }) => {
             // Validate that we have some input source
-            if instructions.is_none() && input_text.is_none() {
-                eprintln!("Error: Must provide either --instructions or --text");
+            if instructions.is_none() && input_text.is_none() && template.is_none() {
+                eprintln!("Error: Must provide either --instructions, --text or --template");
                 std::process::exit(1);
             }
 
-            let contents = if let Some(file_name) = instructions {
+            let contents = if let Some(name) = template {
+                render_template(&name, &vars)?
+            } else if let Some(file_name) = instructions {
                 let file_path = std::path::Path::new(&file_name);
                 std::fs::read_to_string(file_path).expect("Failed to read the instruction file")
             } else if let Some(input_text) = input_text {
@@ -211,8 +330,17 @@ async fn main() -> Result<()> {
                     .expect("Failed to read from stdin");
                 stdin
             };
-            let mut session = build_session(name, resume, extension, builtin).await;
-            setup_logging(session.session_file().file_stem().and_then(|s| s.to_str()))?;
+            let mut session = build_session(name, resume, None, extension, builtin).await;
+            setup_logging(Some(session.id()))?;
+            let limits = SessionLimits {
+                deadline: max_duration
+                    .map(|secs| chrono::Utc::now() + chrono::Duration::seconds(secs as i64)),
+                max_tokens,
+                max_cost,
+            };
+            if !limits.is_empty() {
+                session.set_limits(limits).await;
+            }
             let _ = session.headless_start(contents.clone()).await;
             return Ok(());
         }
@@ -234,3 +362,22 @@ async fn main() -> Result<()> {
     }
     Ok(())
 }
+
+/// Render a prompt template from the library, with `KEY=VALUE` variables
+fn render_template(name: &str, vars: &[String]) -> Result<String> {
+    let mut context = HashMap::new();
+    for var in vars {
+        let (key, value) = var
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Expected KEY=VALUE, got {}", var))?;
+        context.insert(key.to_string(), value.to_string());
+    }
+
+    let library = PromptLibrary::global();
+    let name = if library.contains(name) {
+        name.to_string()
+    } else {
+        format!("{}.md", name)
+    };
+    Ok(library.render(&name, &context)?)
+}
@@ -2,17 +2,21 @@ use console::style;
 use goose::agents::extension::ExtensionError;
 use goose::agents::AgentFactory;
 use goose::config::{Config, ExtensionManager};
+use goose::session::{Session as History, SessionStore};
+use goose::usage::{ExportConfig, UsageExporter, UsageTracker};
 use mcp_client::transport::Error as McpClientError;
-use std::path::PathBuf;
+use std::path::Path;
 use std::process;
 
 use super::output;
 use super::storage;
 use super::Session;
 
+/// Build a session, either new, resumed, or forked from the given session at a message
 pub async fn build_session(
     name: Option<String>,
     resume: bool,
+    fork: Option<(String, Option<usize>)>,
     extensions: Vec<String>,
     builtins: Vec<String>,
 ) -> Session {
@@ -22,7 +26,6 @@ pub async fn build_session(
     let provider_name: String = config
         .get("GOOSE_PROVIDER")
         .expect("No provider configured. Run 'goose configure' first");
-    let session_dir = storage::ensure_session_dir().expect("Failed to create session directory");
 
     let model: String = config
         .get("GOOSE_MODEL")
@@ -63,37 +66,63 @@ pub async fn build_session(
         }
     }
 
-    // Handle session file resolution and resuming
-    let session_file = if resume {
-        if let Some(ref session_name) = name {
-            // Try to resume specific named session
-            let session_file = session_dir.join(format!("{}.jsonl", session_name));
-            if !session_file.exists() {
-                output::render_error(&format!(
-                    "Cannot resume session {} - no such session exists",
-                    style(session_name).cyan()
-                ));
-                process::exit(1);
-            }
-            session_file
-        } else {
-            // Try to resume most recent session
-            match storage::get_most_recent_session() {
-                Ok(file) => file,
+    // Handle session resolution and resuming
+    let forked = fork.is_some();
+    let history = if let Some((source, at)) = fork {
+        // Branch off a previous session under the provided or generated name
+        let session_name = name.unwrap_or_else(generate_session_name);
+        fork_session(&source, at, &session_name, &provider_name, &model).unwrap_or_else(|e| {
+            output::render_error(&format!(
+                "Cannot fork session {} - {}",
+                style(&source).cyan(),
+                e
+            ));
+            process::exit(1);
+        })
+    } else if resume {
+        let session_id = match name {
+            Some(session_name) => session_name,
+            None => match storage::get_most_recent_session_id() {
+                Ok(id) => id,
                 Err(_) => {
                     output::render_error("Cannot resume - no previous sessions found");
                     process::exit(1);
                 }
-            }
-        }
+            },
+        };
+        resume_session(&session_id, &provider_name, &model).unwrap_or_else(|e| {
+            output::render_error(&format!(
+                "Cannot resume session {} - {}",
+                style(&session_id).cyan(),
+                e
+            ));
+            process::exit(1);
+        })
     } else {
         // Create new session with provided or generated name
         let session_name = name.unwrap_or_else(generate_session_name);
-        create_new_session_file(&session_dir, &session_name)
+        History::create(&session_name, &provider_name, &model).unwrap_or_else(|e| {
+            eprintln!("Failed to create session '{}': {}", session_name, e);
+            process::exit(1);
+        })
     };
 
+    // Attribute provider usage to this session
+    UsageTracker::global().set_session(Some(history.id().to_string()));
+
+    // Periodically push usage to the configured bucket for chargeback
+    if let Some(exporter) = ExportConfig::from_config().and_then(UsageExporter::new) {
+        exporter.spawn();
+    }
+
     // Create new session
-    let mut session = Session::new(agent, session_file.clone());
+    let session_id = history.id().to_string();
+    let database = history
+        .store()
+        .path()
+        .unwrap_or(Path::new(":memory:"))
+        .to_path_buf();
+    let mut session = Session::new(agent, history);
 
     // Add extensions if provided
     for extension_str in extensions {
@@ -125,10 +154,55 @@ pub async fn build_session(
         session.agent.override_system_prompt(override_prompt).await;
     }
 
-    output::display_session_info(resume, &provider_name, &model, &session_file);
+    output::display_session_info(
+        resume || forked,
+        &provider_name,
+        &model,
+        &session_id,
+        &database,
+    );
+
+    // A session that ended while tools were running needs their calls answered first
+    if let Err(e) = session.recover_pending_tool_calls() {
+        eprintln!("Failed to recover interrupted tool calls: {}", e);
+        process::exit(1);
+    }
     session
 }
 
+/// Resume a stored session, importing it from its JSONL file if it predates the session store
+fn resume_session(id: &str, provider: &str, model: &str) -> anyhow::Result<History> {
+    let store = SessionStore::global()?;
+    if store.metadata(id)?.is_none()
+        && !storage::import_legacy_session(&store, id, provider, model)?
+    {
+        return Err(anyhow::anyhow!("no such session exists"));
+    }
+
+    let mut history = History::resume_in(store, id)?;
+    if history.metadata().provider != provider || history.metadata().model != model {
+        history.set_model(provider, model)?;
+    }
+    Ok(history)
+}
+
+/// Start a new branch from the first `at` messages of a stored session
+fn fork_session(
+    source: &str,
+    at: Option<usize>,
+    id: &str,
+    provider: &str,
+    model: &str,
+) -> anyhow::Result<History> {
+    let source = History::resume(source)?;
+    let at = at.unwrap_or(source.messages().len());
+    let mut history = source.fork(id, at)?;
+    if history.metadata().provider != provider || history.metadata().model != model {
+        history.set_model(provider, model)?;
+    }
+    Ok(history)
+}
+
 fn generate_session_name() -> String {
     use rand::{distributions::Alphanumeric, Rng};
     rand::thread_rng()
@@ -137,12 +211,3 @@ fn generate_session_name() -> String {
         .map(char::from)
         .collect()
 }
-
-fn create_new_session_file(session_dir: &std::path::Path, name: &str) -> PathBuf {
-    let session_file = session_dir.join(format!("{}.jsonl", name));
-    if session_file.exists() {
-        eprintln!("Session '{}' already exists", name);
-        process::exit(1);
-    }
-    session_file
-}
@@ -8,6 +8,11 @@ pub enum InputResult {
     AddExtension(String),
     AddBuiltin(String),
     ToggleTheme,
+    ToolTokens,
+    Undo,
+    ListCheckpoints,
+    Rewind(String),
+    Pin,
     Retry,
 }
 
@@ -59,6 +64,11 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
             Some(InputResult::Retry)
         }
         "/t" => Some(InputResult::ToggleTheme),
+        "/tokens" => Some(InputResult::ToolTokens),
+        "/undo" => Some(InputResult::Undo),
+        "/checkpoints" => Some(InputResult::ListCheckpoints),
+        s if s.starts_with("/rewind ") => Some(InputResult::Rewind(s[8..].trim().to_string())),
+        "/pin" => Some(InputResult::Pin),
         s if s.starts_with("/extension ") => Some(InputResult::AddExtension(s[11..].to_string())),
         s if s.starts_with("/builtin ") => Some(InputResult::AddBuiltin(s[9..].to_string())),
         _ => None,
@@ -70,6 +80,11 @@ fn print_help() {
         "Available commands:
 /exit or /quit - Exit the session
 /t - Toggle Light/Dark/Ansi theme
+/tokens - Show how many tokens each tool's results use in the conversation
+/undo - Undo the last turn, removing your last message and everything goose did after it
+/checkpoints - List the checkpoints taken before each of your messages
+/rewind <checkpoint> - Roll the conversation back to a checkpoint
+/pin - Pin your last message, so it is kept verbatim when the conversation is truncated or summarized
 /extension <command> - Add a stdio extension (format: ENV1=val1 command args...)
 /builtin <names> - Add builtin extensions by name (comma-separated)
 /? or /help - Display this help message
@@ -113,6 +128,29 @@ mod tests {
             Some(InputResult::ToggleTheme)
         ));
 
+        assert!(matches!(
+            handle_slash_command("/tokens"),
+            Some(InputResult::ToolTokens)
+        ));
+
+        assert!(matches!(
+            handle_slash_command("/undo"),
+            Some(InputResult::Undo)
+        ));
+        assert!(matches!(
+            handle_slash_command("/checkpoints"),
+            Some(InputResult::ListCheckpoints)
+        ));
+        if let Some(InputResult::Rewind(id)) = handle_slash_command("/rewind 3") {
+            assert_eq!(id, "3");
+        } else {
+            panic!("Expected Rewind");
+        }
+        assert!(matches!(
+            handle_slash_command("/pin"),
+            Some(InputResult::Pin)
+        ));
+
         // Test extension command
         if let Some(InputResult::AddExtension(cmd)) = handle_slash_command("/extension foo bar") {
             assert_eq!(cmd, "foo bar");
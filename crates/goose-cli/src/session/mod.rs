@@ -10,37 +10,28 @@ pub use builder::build_session;
 use anyhow::Result;
 use etcetera::choose_app_strategy;
 use goose::agents::extension::{Envs, ExtensionConfig};
-use goose::agents::Agent;
+use goose::agents::plan::PLAN_TOOL_NAME;
+use goose::agents::{Agent, ReplyEvent, SessionLimits};
+use goose::events::EventBus;
 use goose::message::{Message, MessageContent};
+use goose::session::Session as History;
 use mcp_core::handler::ToolError;
 use rand::{distributions::Alphanumeric, Rng};
 use rustyline::Editor;
-use std::path::PathBuf;
+use std::path::Path;
 use tokio;
+use tokio_util::sync::CancellationToken;
 
 use crate::log_usage::log_usage;
 
 pub struct Session {
     agent: Box<dyn Agent>,
-    messages: Vec<Message>,
-    session_file: PathBuf,
+    history: History,
 }
 
 impl Session {
-    pub fn new(agent: Box<dyn Agent>, session_file: PathBuf) -> Self {
-        let messages = match storage::read_messages(&session_file) {
-            Ok(msgs) => msgs,
-            Err(e) => {
-                eprintln!("Warning: Failed to load message history: {}", e);
-                Vec::new()
-            }
-        };
-
-        Session {
-            agent,
-            messages,
-            session_file,
-        }
+    pub fn new(agent: Box<dyn Agent>, history: History) -> Self {
+        Session { agent, history }
     }
 
     /// Add a stdio extension to the session
@@ -109,7 +100,8 @@ impl Session {
 
         // Load history from messages
         for msg in self
-            .messages
+            .history
+            .messages()
             .iter()
             .filter(|m| m.role == mcp_core::role::Role::User)
         {
@@ -125,8 +117,9 @@ impl Session {
         loop {
             match input::get_input(&mut editor)? {
                 input::InputResult::Message(content) => {
-                    self.messages.push(Message::user().with_text(&content));
-                    storage::persist_messages(&self.session_file, &self.messages)?;
+                    // A checkpoint before every user message, so the turn can be undone
+                    self.history.checkpoint(Some(&checkpoint_label(&content)))?;
+                    self.history.push(Message::user().with_text(&content))?;
 
                     output::show_thinking();
                     self.process_agent_response(&mut editor).await?;
@@ -145,6 +138,41 @@ impl Session {
                         Err(e) => output::render_builtin_error(&names, &e.to_string()),
                     }
                 }
+                input::InputResult::ToolTokens => {
+                    let usage = self.agent.tool_token_usage(self.history.messages()).await;
+                    output::render_tool_token_usage(&usage);
+                }
+                input::InputResult::Undo => match self.history.undo() {
+                    Ok(Some(checkpoint)) => output::render_rollback(&checkpoint),
+                    Ok(None) => output::render_error("Nothing to undo"),
+                    Err(e) => output::render_error(&e.to_string()),
+                },
+                input::InputResult::ListCheckpoints => match self.history.checkpoints() {
+                    Ok(checkpoints) => output::render_checkpoints(&checkpoints),
+                    Err(e) => output::render_error(&e.to_string()),
+                },
+                input::InputResult::Rewind(id) => {
+                    let result = id
+                        .parse::<usize>()
+                        .map_err(|_| anyhow::anyhow!("Checkpoints are numbered, not {}", id))
+                        .and_then(|id| self.history.rollback(id));
+                    match result {
+                        Ok(checkpoint) => output::render_rollback(&checkpoint),
+                        Err(e) => output::render_error(&e.to_string()),
+                    }
+                }
+                input::InputResult::Pin => {
+                    let last = self.history.messages().iter().rposition(|m| {
+                        m.role == mcp_core::role::Role::User && m.has_only_text_content()
+                    });
+                    let result = last
+                        .ok_or_else(|| anyhow::anyhow!("There is no message of yours to pin"))
+                        .and_then(|index| self.history.set_pinned(index, true).map(|_| index));
+                    match result {
+                        Ok(index) => output::render_pinned(&self.history.messages()[index]),
+                        Err(e) => output::render_error(&e.to_string()),
+                    }
+                }
                 input::InputResult::ToggleTheme => {
                     let current = output::get_theme();
                     let new_theme = match current {
@@ -171,23 +199,23 @@ impl Session {
         // Log usage and cleanup
         if let Ok(home_dir) = choose_app_strategy(crate::APP_STRATEGY.clone()) {
             let usage = self.agent.usage().await;
-            log_usage(
-                home_dir,
-                self.session_file.to_string_lossy().to_string(),
-                usage,
-            );
+            log_usage(home_dir, self.history.id().to_string(), usage);
             println!(
-                "\nClosing session. Recorded to {}",
-                self.session_file.display()
+                "\nClosing session {}. Recorded to {}",
+                self.history.id(),
+                self.history
+                    .store()
+                    .path()
+                    .unwrap_or(Path::new(":memory:"))
+                    .display()
             );
         }
         Ok(())
     }
 
     pub async fn headless_start(&mut self, initial_message: String) -> Result<()> {
-        self.messages
-            .push(Message::user().with_text(&initial_message));
-        storage::persist_messages(&self.session_file, &self.messages)?;
+        self.history
+            .push(Message::user().with_text(&initial_message))?;
         let mut editor = Editor::<(), rustyline::history::DefaultHistory>::new()?;
         self.process_agent_response(&mut editor).await?;
         Ok(())
@@ -197,21 +225,49 @@ impl Session {
         &mut self,
         editor: &mut Editor<(), rustyline::history::DefaultHistory>,
     ) -> Result<()> {
-        let mut stream = self.agent.reply(&self.messages).await?;
+        let mut events = EventBus::global().subscribe();
+        // Ctrl-C cancels the reply, which also stops a model generating in process
+        let cancel = CancellationToken::new();
+        let mut stream = self
+            .agent
+            .reply_cancellable(self.history.messages(), cancel.clone())
+            .await?;
+        // Whether the text of the message being generated has been printed as it streamed
+        let mut streamed = false;
 
         use futures::StreamExt;
         loop {
             tokio::select! {
+                Ok(event) = events.recv() => {
+                    if output::is_notable(&event) {
+                        output::hide_thinking();
+                        output::render_event(&event);
+                        if !streamed {
+                            output::show_thinking();
+                        }
+                    }
+                }
                 result = stream.next() => {
                     match result {
-                        Some(Ok(mut message)) => {
+                        Some(Ok(ReplyEvent::Delta(delta))) => {
+                            if !streamed {
+                                output::hide_thinking();
+                                streamed = true;
+                            }
+                            output::render_delta(&delta);
+                        }
+                        Some(Ok(ReplyEvent::Message(mut message))) => {
 
                             // Handle tool confirmation requests before rendering
                             if let Some(MessageContent::ToolConfirmationRequest(confirmation)) = message.content.first() {
                                 output::hide_thinking();
 
                                 // Format the confirmation prompt
-                                let prompt = "Goose would like to call the above tool. Allow? (y/n):".to_string();
+                                let prompt = if confirmation.tool_name == PLAN_TOOL_NAME {
+                                    "Goose would like to carry out the above plan. Allow? (y/n):".to_string()
+                                } else {
+                                    "Goose would like to call the above tool. Allow? (y/n):".to_string()
+                                };
 
                                 let confirmation_request = Message::user().with_tool_confirmation_request(
                                     confirmation.id.clone(),
@@ -236,18 +292,21 @@ impl Session {
 
                             // Only push the message if it's not a tool confirmation request
                             if !message.content.iter().any(|content| matches!(content, MessageContent::ToolConfirmationRequest(_))) {
-                                self.messages.push(message.clone());
+                                self.history.push(message.clone())?;
                             }
 
-                            storage::persist_messages(&self.session_file, &self.messages)?;
                             output::hide_thinking();
-                            output::render_message(&message);
+                            if std::mem::take(&mut streamed) {
+                                output::render_streamed_message(&message);
+                            } else {
+                                output::render_message(&message);
+                            }
                             output::show_thinking();
                         }
                         Some(Err(e)) => {
                             eprintln!("Error: {}", e);
                             drop(stream);
-                            self.handle_interrupted_messages(false);
+                            self.handle_interrupted_messages(false)?;
                             output::render_error(
                                 "The error above was an exception we were not able to handle.\n\
                                 These errors are often related to connection or authentication\n\
@@ -260,33 +319,31 @@ impl Session {
                     }
                 }
                 _ = tokio::signal::ctrl_c() => {
+                    cancel.cancel();
                     drop(stream);
-                    self.handle_interrupted_messages(true);
+                    self.handle_interrupted_messages(true)?;
                     break;
                 }
             }
         }
+
+        // Keep track of the plan in plan mode
+        if let Some(plan) = self.agent.plan().await {
+            self.history.set_plan(Some(&plan))?;
+        }
         Ok(())
     }
 
-    fn handle_interrupted_messages(&mut self, interrupt: bool) {
-        // First, get any tool requests from the last message if it exists
-        let tool_requests = self
-            .messages
-            .last()
-            .filter(|msg| msg.role == mcp_core::role::Role::Assistant)
-            .map_or(Vec::new(), |msg| {
-                msg.content
-                    .iter()
-                    .filter_map(|content| {
-                        if let MessageContent::ToolRequest(req) = content {
-                            Some((req.id.clone(), req.tool_call.clone()))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect()
-            });
+    /// Answer any tool calls left without a response, or drop an unanswered user message,
+    /// so the conversation can be sent to the provider again
+    fn handle_interrupted_messages(&mut self, interrupt: bool) -> Result<()> {
+        // First, get any tool requests that never got a response
+        let tool_requests: Vec<_> = self
+            .history
+            .pending_tool_requests()
+            .into_iter()
+            .map(|req| (req.id.clone(), req.tool_call.clone()))
+            .collect();
 
         if !tool_requests.is_empty() {
             // Interrupted during a tool request
@@ -308,28 +365,29 @@ impl Session {
                     Err(ToolError::ExecutionError(notification.clone())),
                 ));
             }
-            self.messages.push(response_message);
+            self.history.push(response_message)?;
 
             let prompt = format!(
                 "The existing call to {} was interrupted. How would you like to proceed?",
                 last_tool_name
             );
-            self.messages.push(Message::assistant().with_text(&prompt));
+            self.history.push(Message::assistant().with_text(&prompt))?;
             output::render_message(&Message::assistant().with_text(&prompt));
         } else {
             // An interruption occurred outside of a tool request-response.
-            if let Some(last_msg) = self.messages.last() {
+            if let Some(last_msg) = self.history.messages().last() {
                 if last_msg.role == mcp_core::role::Role::User {
                     match last_msg.content.first() {
                         Some(MessageContent::ToolResponse(_)) => {
                             // Interruption occurred after a tool had completed but not assistant reply
                             let prompt = "The tool calling loop was interrupted. How would you like to proceed?";
-                            self.messages.push(Message::assistant().with_text(prompt));
+                            self.history.push(Message::assistant().with_text(prompt))?;
                             output::render_message(&Message::assistant().with_text(prompt));
                         }
                         Some(_) => {
                             // A real users message
-                            self.messages.pop();
+                            let len = self.history.messages().len();
+                            self.history.truncate(len - 1)?;
                             let prompt = "Interrupted before the model replied and removed the last message.";
                             output::render_message(&Message::assistant().with_text(prompt));
                         }
@@ -338,9 +396,32 @@ impl Session {
                 }
             }
         }
+        Ok(())
     }
 
-    pub fn session_file(&self) -> PathBuf {
-        self.session_file.clone()
+    /// Pick up a resumed conversation that ended while tools were still running
+    pub fn recover_pending_tool_calls(&mut self) -> Result<()> {
+        if self.history.pending_tool_requests().is_empty() {
+            return Ok(());
+        }
+        self.handle_interrupted_messages(true)
+    }
+
+    pub fn id(&self) -> &str {
+        self.history.id()
+    }
+
+    /// Attach a deadline and budget to the session
+    pub async fn set_limits(&mut self, limits: SessionLimits) {
+        self.agent.set_session_limits(limits).await;
+    }
+}
+
+// The first line of the message, shortened
+fn checkpoint_label(message: &str) -> String {
+    let line = message.lines().next().unwrap_or_default();
+    match line.char_indices().nth(60) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
     }
 }
@@ -1,10 +1,15 @@
 use bat::WrappingMode;
 use console::style;
 use goose::config::Config;
+use goose::events::Event;
 use goose::message::{Message, MessageContent, ToolConfirmationRequest, ToolRequest, ToolResponse};
+use goose::providers::base::MessageDelta;
+use goose::session::Checkpoint;
+use goose::usage::ToolTokenUsage;
 use mcp_core::tool::ToolCall;
 use serde_json::Value;
 use std::cell::RefCell;
+use std::io::Write;
 use std::path::Path;
 
 // Re-export theme for use in main
@@ -88,21 +93,62 @@ pub fn hide_thinking() {
 
 pub fn render_message(message: &Message) {
     let theme = get_theme();
+    for content in &message.content {
+        render_content(content, theme);
+    }
+    println!();
+}
+
+/// Print part of a response as the model streams it, as plain text until it is complete
+pub fn render_delta(delta: &MessageDelta) {
+    match delta {
+        MessageDelta::Text(text) => print!("{}", text),
+        MessageDelta::Thinking(thinking) if thinking_shown() => {
+            print!("{}", style(thinking).dim().italic())
+        }
+        _ => return,
+    }
+    let _ = std::io::stdout().flush();
+}
 
+/// Render a message whose text and reasoning were already printed as they streamed in
+pub fn render_streamed_message(message: &Message) {
+    let theme = get_theme();
+    println!("\n");
     for content in &message.content {
         match content {
-            MessageContent::Text(text) => print_markdown(&text.text, theme),
-            MessageContent::ToolRequest(req) => render_tool_request(req, theme),
-            MessageContent::ToolResponse(resp) => render_tool_response(resp, theme),
-            MessageContent::ToolConfirmationRequest(req) => {
-                render_tool_confirmation_request(req, theme)
-            }
-            MessageContent::Image(image) => {
-                println!("Image: [data: {}, type: {}]", image.data, image.mime_type);
-            }
+            MessageContent::Text(_) | MessageContent::Thinking(_) => {}
+            content => render_content(content, theme),
         }
     }
-    println!();
+}
+
+fn render_content(content: &MessageContent, theme: Theme) {
+    match content {
+        MessageContent::Text(text) => print_markdown(&text.text, theme),
+        MessageContent::ToolRequest(req) => render_tool_request(req, theme),
+        MessageContent::ToolResponse(resp) => render_tool_response(resp, theme),
+        MessageContent::ToolConfirmationRequest(req) => {
+            render_tool_confirmation_request(req, theme)
+        }
+        MessageContent::Image(image) => {
+            println!("Image: [data: {}, type: {}]", image.data, image.mime_type);
+        }
+        MessageContent::Thinking(thinking) => render_thinking(&thinking.thinking),
+    }
+}
+
+// Reasoning is shown dimmed, or hidden with GOOSE_CLI_SHOW_THINKING set to false
+fn thinking_shown() -> bool {
+    Config::global()
+        .get::<bool>("GOOSE_CLI_SHOW_THINKING")
+        .unwrap_or(true)
+}
+
+fn render_thinking(thinking: &str) {
+    if thinking_shown() && !thinking.trim().is_empty() {
+        println!("{}\n", style(thinking.trim_end()).dim().italic());
+    }
 }
 
 fn render_tool_request(req: &ToolRequest, theme: Theme) {
@@ -211,6 +257,107 @@ pub fn render_builtin_error(names: &str, error: &str) {
     println!();
 }
 
+pub fn render_tool_token_usage(usage: &[ToolTokenUsage]) {
+    println!();
+    if usage.is_empty() {
+        println!(
+            "  {}",
+            style("No tool results in this conversation yet").dim()
+        );
+    }
+    for tool in usage {
+        println!(
+            "  {} {} tokens in {} result{}",
+            style(&tool.tool).cyan(),
+            tool.tokens,
+            tool.calls,
+            if tool.calls == 1 { "" } else { "s" }
+        );
+    }
+    println!();
+}
+
+/// Whether the event is worth showing while goose is working
+pub fn is_notable(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Retry { .. }
+            | Event::CircuitChanged { .. }
+            | Event::Fallback { .. }
+            | Event::BudgetWarning { .. }
+            | Event::CompactionPerformed { .. }
+            | Event::ContextThreshold { .. }
+    )
+}
+
+pub fn render_event(event: &Event) {
+    let text = match event {
+        Event::Retry {
+            attempt,
+            delay_ms,
+            reason,
+        } => format!(
+            "{}, retrying (attempt {}) in {}ms",
+            reason, attempt, delay_ms
+        ),
+        Event::CircuitChanged {
+            provider,
+            model,
+            state,
+        } => format!("circuit for {}/{} is {}", provider, model, state),
+        Event::Fallback { from, to, reason } => {
+            format!("{} failed ({}), falling back to {}", from, reason, to)
+        }
+        Event::BudgetWarning { scope, cost, .. } => {
+            format!("soft {} budget limit reached at ${:.2}", scope, cost)
+        }
+        Event::CompactionPerformed {
+            archived_messages, ..
+        } => format!(
+            "summarized {} older messages to save context",
+            archived_messages
+        ),
+        Event::ContextThreshold { threshold, .. } => {
+            format!("context window is {:.0}% full", threshold * 100.0)
+        }
+        _ => return,
+    };
+    println!("  {} {}", style("note:").yellow(), style(text).dim());
+}
+
+pub fn render_checkpoints(checkpoints: &[Checkpoint]) {
+    println!();
+    if checkpoints.is_empty() {
+        println!("  {}", style("No checkpoints in this session yet").dim());
+    }
+    for checkpoint in checkpoints {
+        println!(
+            "  {} {} {}",
+            style(checkpoint.id).cyan(),
+            style(checkpoint.created.format("%Y-%m-%d %H:%M:%S")).dim(),
+            checkpoint.label.as_deref().unwrap_or("")
+        );
+    }
+    println!();
+}
+
+pub fn render_pinned(message: &Message) {
+    println!();
+    println!("  {} {}", style("pinned").green(), message.as_concat_text());
+    println!();
+}
+
+pub fn render_rollback(checkpoint: &Checkpoint) {
+    println!();
+    println!(
+        "  {} to checkpoint {}, before: {}",
+        style("rolled back").green(),
+        style(checkpoint.id).cyan(),
+        checkpoint.label.as_deref().unwrap_or("")
+    );
+    println!();
+}
+
 fn render_text_editor_request(call: &ToolCall) {
     print_tool_header(call);
 
@@ -398,7 +545,13 @@ fn shorten_path(path: &str) -> String {
 }
 
 // Session display functions
-pub fn display_session_info(resume: bool, provider: &str, model: &str, session_file: &Path) {
+pub fn display_session_info(
+    resume: bool,
+    provider: &str,
+    model: &str,
+    session_id: &str,
+    database: &Path,
+) {
     let start_session_msg = if resume {
         "resuming session |"
     } else {
@@ -413,9 +566,11 @@ pub fn display_session_info(resume: bool, provider: &str, model: &str, session_f
         style(model).cyan().dim(),
     );
     println!(
-        "    {} {}",
+        "    {} {} {} {}",
+        style("session:").dim(),
+        style(session_id).cyan().dim(),
         style("logging to").dim(),
-        style(session_file.display()).dim().cyan(),
+        style(database.display()).dim().cyan(),
     );
 }
 
@@ -1,9 +1,14 @@
+//! Sessions are stored in the `goose::session::SessionStore`. Before that, every session was
+//! a JSONL file in the sessions directory, which are imported into the store on resume.
+
 use anyhow::Result;
 use etcetera::{choose_app_strategy, AppStrategy};
 use goose::message::Message;
-use std::fs::{self, File};
-use std::io::{self, BufRead, Write};
+use goose::session::{Session, SessionStore};
+use std::fs;
+use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Ensure the session directory exists and return its path
 pub fn ensure_session_dir() -> Result<PathBuf> {
@@ -46,6 +51,37 @@ pub fn get_most_recent_session() -> Result<PathBuf> {
     Ok(entries[0].path())
 }
 
+/// The id of the most recently updated session, falling back to the newest JSONL file
+pub fn get_most_recent_session_id() -> Result<String> {
+    if let Some(session) = SessionStore::global()?.list()?.into_iter().next() {
+        return Ok(session.id);
+    }
+    let file = get_most_recent_session()?;
+    file.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow::anyhow!("Invalid session file {}", file.display()))
+}
+
+/// Copy the JSONL file of a session into the store, returning false if there is none
+pub fn import_legacy_session(
+    store: &Arc<SessionStore>,
+    id: &str,
+    provider: &str,
+    model: &str,
+) -> Result<bool> {
+    let session_file = ensure_session_dir()?.join(format!("{}.jsonl", id));
+    if !session_file.exists() {
+        return Ok(false);
+    }
+
+    let messages = read_messages(&session_file)?;
+    let mut session = Session::create_in(store.clone(), id, provider, model)?;
+    for message in messages {
+        session.push(message)?;
+    }
+    Ok(true)
+}
+
 /// Read messages from a session file
 ///
 /// Creates the file if it doesn't exist, reads and deserializes all messages if it does.
@@ -70,8 +106,11 @@ pub fn read_messages(session_file: &Path) -> Result<Vec<Message>> {
 /// Write messages to a session file
 ///
 /// Overwrites the file with all messages in JSONL format.
+#[cfg(test)]
 pub fn persist_messages(session_file: &Path, messages: &[Message]) -> Result<()> {
-    let file = File::create(session_file)?;
+    use std::io::Write;
+
+    let file = fs::File::create(session_file)?;
     let mut writer = io::BufWriter::new(file);
 
     for message in messages {
@@ -1,10 +1,18 @@
 use crate::configuration;
 use crate::state;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use goose::agents::{Agent, AgentFactory};
+use goose::config::{Config, ExtensionManager};
+use goose::model::ModelConfig;
+use goose::providers;
+use goose::scheduler::Scheduler;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
 
-pub async fn run() -> Result<()> {
+// Accepted when GOOSE_SERVER__SECRET_KEY is unset, which only suits a local desktop app
+const DEFAULT_SECRET_KEY: &str = "test";
+
+pub async fn run(headless: bool) -> Result<()> {
     // Initialize logging
     crate::logging::setup_logging(Some("goosed"))?;
 
@@ -12,11 +20,20 @@ pub async fn run() -> Result<()> {
     let settings = configuration::Settings::new()?;
 
     // load secret key from GOOSE_SERVER__SECRET_KEY environment variable
-    let secret_key =
-        std::env::var("GOOSE_SERVER__SECRET_KEY").unwrap_or_else(|_| "test".to_string());
+    let secret_key = std::env::var("GOOSE_SERVER__SECRET_KEY")
+        .unwrap_or_else(|_| DEFAULT_SECRET_KEY.to_string());
+    // Anyone who can reach the port could drive the agent with the default key
+    if headless && (secret_key.is_empty() || secret_key == DEFAULT_SECRET_KEY) {
+        return Err(anyhow!(
+            "Set GOOSE_SERVER__SECRET_KEY to a key of your own to serve the agent headless"
+        ));
+    }
 
-    // Create app state - agent will start as None
+    // Create app state - agent will start as None unless headless
     let state = state::AppState::new(secret_key.clone()).await?;
+    if headless {
+        *state.agent.lock().await = Some(configured_agent().await?);
+    }
 
     // Create router with CORS support
     let cors = CorsLayer::new()
@@ -26,9 +43,38 @@ pub async fn run() -> Result<()> {
 
     let app = crate::routes::configure(state).layer(cors);
 
+    // Run the jobs of GOOSE_SCHEDULE for as long as the server runs
+    let scheduler = Scheduler::from_config()?;
+    let jobs = scheduler.jobs();
+    if !jobs.is_empty() {
+        info!("scheduled jobs: {}", jobs.join(", "));
+    }
+
     // Run server
     let listener = tokio::net::TcpListener::bind(settings.socket_addr()).await?;
     info!("listening on {}", listener.local_addr()?);
     axum::serve(listener, app).await?;
     Ok(())
 }
+
+// The agent of the goose config, with its provider, model and enabled extensions
+async fn configured_agent() -> Result<Box<dyn Agent>> {
+    let config = Config::global();
+    let provider_name: String = config.get("GOOSE_PROVIDER")?;
+    let model: String = config.get("GOOSE_MODEL")?;
+    let provider = providers::create(&provider_name, ModelConfig::new(model.clone()))?;
+
+    let version: String = config
+        .get("GOOSE_AGENT")
+        .unwrap_or_else(|_| AgentFactory::default_version().to_string());
+    let mut agent = AgentFactory::create(&version, provider)
+        .ok_or_else(|| anyhow!("No agent version {}", version))?;
+
+    for extension in ExtensionManager::get_all()? {
+        if extension.enabled {
+            agent.add_extension(extension.config).await?;
+        }
+    }
+    info!("serving agent {} with {} {}", version, provider_name, model);
+    Ok(agent)
+}
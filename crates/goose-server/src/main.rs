@@ -28,7 +28,13 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Run the agent server
-    Agent,
+    Agent {
+        /// Start with the agent of the goose config instead of waiting for a client to
+        /// create one, to serve the OpenAI compatible API on its own. Requires
+        /// GOOSE_SERVER__SECRET_KEY to be set.
+        #[arg(long)]
+        headless: bool,
+    },
     /// Run the MCP server
     Mcp {
         /// Name of the MCP server type
@@ -41,8 +47,8 @@ async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::Agent => {
-            commands::agent::run().await?;
+        Commands::Agent { headless } => {
+            commands::agent::run(*headless).await?;
         }
         Commands::Mcp { name } => {
             commands::mcp::run(name).await?;
@@ -6,7 +6,12 @@ use axum::{
     Json, Router,
 };
 use goose::config::Config;
-use goose::{agents::AgentFactory, model::ModelConfig, providers};
+use goose::{
+    agents::{AgentFactory, SessionLimits},
+    model::ModelConfig,
+    providers,
+    truncate::TruncationStrategyKind,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
@@ -32,6 +37,7 @@ struct CreateAgentRequest {
     version: Option<String>,
     provider: String,
     model: Option<String>,
+    truncation_strategy: Option<TruncationStrategyKind>,
 }
 
 #[derive(Serialize)]
@@ -95,6 +101,30 @@ async fn extend_prompt(
     }
 }
 
+async fn set_limits(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SessionLimits>,
+) -> Result<Json<ExtendPromptResponse>, StatusCode> {
+    // Verify secret key
+    let secret_key = headers
+        .get("X-Secret-Key")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if secret_key != state.secret_key {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let mut agent = state.agent.lock().await;
+    if let Some(ref mut agent) = *agent {
+        agent.set_session_limits(payload).await;
+        Ok(Json(ExtendPromptResponse { success: true }))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
 #[axum::debug_handler]
 async fn create_agent(
     State(state): State<AppState>,
@@ -124,7 +154,8 @@ async fn create_agent(
             .get("GOOSE_MODEL")
             .expect("Did not find a model on payload or in env")
     });
-    let model_config = ModelConfig::new(model);
+    let model_config =
+        ModelConfig::new(model).with_truncation_strategy(payload.truncation_strategy);
     let provider =
         providers::create(&payload.provider, model_config).expect("Failed to create provider");
 
@@ -168,6 +199,7 @@ pub fn routes(state: AppState) -> Router {
         .route("/agent/versions", get(get_versions))
         .route("/agent/providers", get(list_providers))
         .route("/agent/prompt", post(extend_prompt))
+        .route("/agent/limits", post(set_limits))
         .route("/agent", post(create_agent))
         .with_state(state)
 }
@@ -0,0 +1,104 @@
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event as SseEvent, KeepAlive, Sse},
+    routing::get,
+    Router,
+};
+use futures::{stream, Stream};
+use goose::events::EventBus;
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+
+// Stream the agent and provider events as they happen, one JSON object per server sent event
+async fn events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, StatusCode> {
+    // Verify secret key
+    let secret_key = headers
+        .get("X-Secret-Key")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if secret_key != state.secret_key {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let receiver = EventBus::global().subscribe();
+    let stream = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let data = serde_json::to_string(&event).unwrap_or_default();
+                    return Some((Ok(SseEvent::default().data(data)), receiver));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event stream fell behind, skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/events", get(events))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use futures::StreamExt;
+    use goose::events::Event;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_streams_events() {
+        let state = AppState {
+            config: Arc::new(Mutex::new(HashMap::new())),
+            agent: Arc::new(Mutex::new(None)),
+            secret_key: "test-secret".to_string(),
+        };
+        let request = |key: &str| {
+            Request::builder()
+                .uri("/events")
+                .header("x-secret-key", key)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = routes(state.clone())
+            .oneshot(request("wrong"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = routes(state).oneshot(request("test-secret")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        goose::events::emit(Event::Retry {
+            attempt: 1,
+            delay_ms: 0,
+            reason: "testing".to_string(),
+        });
+
+        // Other tests emit on the global bus too
+        let mut body = response.into_body().into_data_stream();
+        while let Some(chunk) = body.next().await {
+            let data = String::from_utf8(chunk.unwrap().to_vec()).unwrap();
+            if data.contains("\"reason\":\"testing\"") {
+                assert!(data.starts_with("data: {\"type\":\"retry\""));
+                return;
+            }
+        }
+        panic!("The event was not streamed");
+    }
+}
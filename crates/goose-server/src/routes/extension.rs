@@ -23,6 +23,23 @@ enum ExtensionConfigRequest {
         /// List of environment variable keys. The server will fetch their values from the keyring.
         #[serde(default)]
         env_keys: Vec<String>,
+        /// HTTP headers to send to the server, e.g. Authorization.
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Streamable HTTP extension.
+    #[serde(rename = "streamable_http")]
+    StreamableHttp {
+        /// The name to identify this extension
+        name: String,
+        /// The URI of the server's MCP endpoint.
+        uri: String,
+        /// List of environment variable keys. The server will fetch their values from the keyring.
+        #[serde(default)]
+        env_keys: Vec<String>,
+        /// HTTP headers to send to the server, e.g. Authorization.
+        #[serde(default)]
+        headers: HashMap<String, String>,
     },
     /// Standard I/O (stdio) extension.
     #[serde(rename = "stdio")]
@@ -84,6 +101,7 @@ async fn add_extension(
             name,
             uri,
             env_keys,
+            headers,
         } => {
             let mut env_map = HashMap::new();
             for key in env_keys {
@@ -111,6 +129,42 @@ async fn add_extension(
                 name,
                 uri,
                 envs: Envs::new(env_map),
+                headers,
+            }
+        }
+        ExtensionConfigRequest::StreamableHttp {
+            name,
+            uri,
+            env_keys,
+            headers,
+        } => {
+            let mut env_map = HashMap::new();
+            for key in env_keys {
+                match config.get_secret(&key) {
+                    Ok(value) => {
+                        env_map.insert(key, value);
+                    }
+                    Err(_) => {
+                        missing_keys.push(key);
+                    }
+                }
+            }
+
+            if !missing_keys.is_empty() {
+                return Ok(Json(ExtensionResponse {
+                    error: true,
+                    message: Some(format!(
+                        "Missing secrets for keys: {}",
+                        missing_keys.join(", ")
+                    )),
+                }));
+            }
+
+            ExtensionConfig::StreamableHttp {
+                name,
+                uri,
+                envs: Envs::new(env_map),
+                headers,
             }
         }
         ExtensionConfigRequest::Stdio {
@@ -1,4 +1,5 @@
 use axum::{routing::get, Json, Router};
+use goose::providers::circuit::{circuits, CircuitStatus};
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -11,7 +12,14 @@ async fn status() -> Json<StatusResponse> {
     Json(StatusResponse { status: "ok" })
 }
 
+/// The circuit breakers of the providers requests went to, and whether they are open
+async fn provider_circuits() -> Json<Vec<CircuitStatus>> {
+    Json(circuits())
+}
+
 /// Configure health check routes
 pub fn routes() -> Router {
-    Router::new().route("/status", get(status))
+    Router::new()
+        .route("/status", get(status))
+        .route("/providers/circuits", get(provider_circuits))
 }
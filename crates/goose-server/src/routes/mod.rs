@@ -2,8 +2,10 @@
 pub mod agent;
 pub mod config_management;
 pub mod configs;
+pub mod events;
 pub mod extension;
 pub mod health;
+pub mod openai;
 pub mod reply;
 
 use axum::Router;
@@ -16,5 +18,7 @@ pub fn configure(state: crate::state::AppState) -> Router {
         .merge(agent::routes(state.clone()))
         .merge(extension::routes(state.clone()))
         .merge(configs::routes(state.clone()))
+        .merge(events::routes(state.clone()))
+        .merge(openai::routes(state.clone()))
         .merge(config_management::routes(state))
 }
@@ -0,0 +1,518 @@
+//! An OpenAI compatible chat completions API for the agent
+//!
+//! Clients that speak the OpenAI API can talk to goose by pointing their base url at the
+//! server's `/v1`. The agent answers with its own provider, extensions and tool policy; tools
+//! sent by the client are ignored, and tool calls that need confirmation are declined as there
+//! is nobody to confirm them.
+
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use futures::StreamExt;
+use goose::agents;
+use goose::message::{Message, MessageContent};
+use goose::providers::base::{MessageDelta, ProviderUsage};
+use mcp_core::role::Role;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+// The model name the agent is listed under
+const MODEL: &str = "goose";
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionRequest {
+    #[serde(default)]
+    model: Option<String>,
+    messages: Vec<ChatMessage>,
+    #[serde(default)]
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    content: Option<ChatContent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ChatContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentPart {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+impl ChatContent {
+    fn text(&self) -> String {
+        match self {
+            ChatContent::Text(text) => text.clone(),
+            ChatContent::Parts(parts) => parts
+                .iter()
+                .filter(|part| part.kind == "text")
+                .filter_map(|part| part.text.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+struct CompletionUsage {
+    prompt_tokens: i64,
+    completion_tokens: i64,
+    total_tokens: i64,
+}
+
+impl CompletionUsage {
+    fn from_usage(usage: &[ProviderUsage]) -> Self {
+        let mut total = Self::default();
+        for usage in usage {
+            total.prompt_tokens += usage.usage.input_tokens.unwrap_or(0) as i64;
+            total.completion_tokens += usage.usage.output_tokens.unwrap_or(0) as i64;
+            total.total_tokens += usage.usage.total_tokens.unwrap_or(0) as i64;
+        }
+        total
+    }
+
+    fn since(&self, before: &Self) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens - before.prompt_tokens,
+            completion_tokens: self.completion_tokens - before.completion_tokens,
+            total_tokens: self.total_tokens - before.total_tokens,
+        }
+    }
+}
+
+// What the agent produces while it answers a request, with text in the order it is shown
+#[derive(Debug)]
+enum ReplyEvent {
+    Text(String),
+    Done(CompletionUsage),
+    Failed(String),
+}
+
+// Convert the OpenAI messages, which all become text as the client doesn't see goose's tools
+//
+// System messages are passed on as user messages, since the agent keeps its own system prompt,
+// and consecutive messages of the same role are merged for providers that require turns to
+// alternate.
+fn convert_messages(incoming: Vec<ChatMessage>) -> Result<Vec<Message>, String> {
+    let mut messages: Vec<Message> = Vec::new();
+    for message in incoming {
+        let text = message
+            .content
+            .map(|content| content.text())
+            .unwrap_or_default();
+        let role = match message.role.as_str() {
+            "user" | "system" | "developer" => Role::User,
+            "assistant" => Role::Assistant,
+            other => {
+                tracing::warn!("Ignoring a message with role {}", other);
+                continue;
+            }
+        };
+        if text.is_empty() {
+            continue;
+        }
+
+        match messages.last_mut() {
+            Some(last) if last.role == role => last.content.push(MessageContent::text(text)),
+            _ if role == Role::User => messages.push(Message::user().with_text(text)),
+            _ => messages.push(Message::assistant().with_text(text)),
+        }
+    }
+
+    match messages.last() {
+        Some(last) if last.role == Role::User => Ok(messages),
+        _ => Err("The last message must be from the user".to_string()),
+    }
+}
+
+// Run the agent in the background, sending the text of its replies as they come
+fn spawn_reply(state: AppState, messages: Vec<Message>) -> mpsc::Receiver<ReplyEvent> {
+    let (tx, rx) = mpsc::channel(100);
+    tokio::spawn(async move {
+        let agent = state.agent.lock().await;
+        let Some(agent) = agent.as_ref() else {
+            let _ = tx
+                .send(ReplyEvent::Failed("No agent configured".to_string()))
+                .await;
+            return;
+        };
+
+        // The agent is locked for the whole reply, so the difference is this request's usage
+        let before = CompletionUsage::from_usage(&agent.usage().await);
+        let mut stream = match agent.reply_streaming(&messages).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = tx.send(ReplyEvent::Failed(e.to_string())).await;
+                return;
+            }
+        };
+
+        // Whether any text was sent, and whether the current message's text was streamed
+        let mut sent = false;
+        let mut streamed = false;
+        while let Some(event) = stream.next().await {
+            let text = match event {
+                Ok(agents::ReplyEvent::Delta(MessageDelta::Text(text))) => {
+                    // Separate the agent's replies the way whole messages are
+                    let text = if sent && !streamed {
+                        format!("\n\n{}", text)
+                    } else {
+                        text
+                    };
+                    streamed = true;
+                    text
+                }
+                Ok(agents::ReplyEvent::Delta(_)) => continue,
+                Ok(agents::ReplyEvent::Message(message)) => {
+                    if let Some(request) = message
+                        .content
+                        .first()
+                        .and_then(|c| c.as_tool_confirmation_request())
+                    {
+                        tracing::warn!(
+                            "Declined to run {} without confirmation",
+                            request.tool_name
+                        );
+                        agent.handle_confirmation(request.id.clone(), false).await;
+                        continue;
+                    }
+                    if std::mem::take(&mut streamed) || message.role == Role::User {
+                        continue;
+                    }
+                    let text = message.as_concat_text();
+                    if sent && !text.is_empty() {
+                        format!("\n\n{}", text)
+                    } else {
+                        text
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.send(ReplyEvent::Failed(e.to_string())).await;
+                    return;
+                }
+            };
+            if text.is_empty() {
+                continue;
+            }
+            sent = true;
+            if tx.send(ReplyEvent::Text(text)).await.is_err() {
+                // The client went away
+                return;
+            }
+        }
+
+        let after = CompletionUsage::from_usage(&agent.usage().await);
+        let _ = tx.send(ReplyEvent::Done(after.since(&before))).await;
+    });
+    rx
+}
+
+fn completion_id() -> String {
+    format!("chatcmpl-{}", Utc::now().timestamp_nanos_opt().unwrap_or(0))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    let kind = if status.is_client_error() {
+        "invalid_request_error"
+    } else {
+        "server_error"
+    };
+    (
+        status,
+        Json(json!({"error": {"message": message, "type": kind}})),
+    )
+        .into_response()
+}
+
+// OpenAI clients send the key as a bearer token
+fn authorized(headers: &HeaderMap, state: &AppState) -> bool {
+    let bearer = headers
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let secret_key = headers
+        .get("X-Secret-Key")
+        .and_then(|value| value.to_str().ok());
+    bearer.or(secret_key) == Some(state.secret_key.as_str())
+}
+
+async fn chat_completions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ChatCompletionRequest>,
+) -> Response {
+    if !authorized(&headers, &state) {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid API key");
+    }
+    if state.agent.lock().await.is_none() {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "No agent configured");
+    }
+    let messages = match convert_messages(request.messages) {
+        Ok(messages) => messages,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+    };
+
+    let id = completion_id();
+    let created = Utc::now().timestamp();
+    let model = request.model.unwrap_or_else(|| MODEL.to_string());
+    let mut replies = spawn_reply(state, messages);
+
+    if request.stream {
+        let chunk = move |delta: Value, finish_reason: Option<&str>| {
+            json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": model,
+                "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+            })
+        };
+        let (tx, rx) = mpsc::channel(100);
+        tokio::spawn(async move {
+            let _ = tx.send(chunk(json!({"role": "assistant"}), None)).await;
+            while let Some(event) = replies.recv().await {
+                let data = match event {
+                    ReplyEvent::Text(text) => chunk(json!({"content": text}), None),
+                    ReplyEvent::Done(usage) => {
+                        let mut data = chunk(json!({}), Some("stop"));
+                        data["usage"] = json!(usage);
+                        data
+                    }
+                    ReplyEvent::Failed(message) => {
+                        json!({"error": {"message": message, "type": "server_error"}})
+                    }
+                };
+                if tx.send(data).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        let events = ReceiverStream::new(rx)
+            .map(|data| Ok::<_, Infallible>(Event::default().data(data.to_string())))
+            .chain(futures::stream::once(async {
+                Ok(Event::default().data("[DONE]"))
+            }));
+        return Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response();
+    }
+
+    let mut content = String::new();
+    let mut usage = CompletionUsage::default();
+    while let Some(event) = replies.recv().await {
+        match event {
+            ReplyEvent::Text(text) => content.push_str(&text),
+            ReplyEvent::Done(total) => usage = total,
+            ReplyEvent::Failed(message) => {
+                tracing::error!("Chat completion failed: {}", message);
+                return error_response(StatusCode::INTERNAL_SERVER_ERROR, &message);
+            }
+        }
+    }
+
+    Json(json!({
+        "id": id,
+        "object": "chat.completion",
+        "created": created,
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop",
+        }],
+        "usage": usage,
+    }))
+    .into_response()
+}
+
+async fn list_models(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if !authorized(&headers, &state) {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid API key");
+    }
+    Json(json!({
+        "object": "list",
+        "data": [{"id": MODEL, "object": "model", "created": 0, "owned_by": "goose"}],
+    }))
+    .into_response()
+}
+
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/v1/chat/completions", post(chat_completions))
+        .route("/v1/models", get(list_models))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use goose::agents::AgentFactory;
+    use goose::model::ModelConfig;
+    use goose::providers::base::{Provider, ProviderMetadata, Usage};
+    use goose::providers::errors::ProviderError;
+    use mcp_core::tool::Tool;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+    use tower::ServiceExt;
+
+    struct MockProvider;
+
+    #[async_trait::async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("test-model".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text("Mock response"),
+                ProviderUsage::new(
+                    "test-model".to_string(),
+                    Usage::new(Some(10), Some(5), Some(15)),
+                ),
+            ))
+        }
+    }
+
+    fn messages(value: Value) -> Vec<ChatMessage> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn state(agent: bool) -> AppState {
+        let agent =
+            agent.then(|| AgentFactory::create("reference", Box::new(MockProvider)).unwrap());
+        AppState {
+            config: Arc::new(Mutex::new(HashMap::new())),
+            agent: Arc::new(Mutex::new(agent)),
+            secret_key: "test-secret".to_string(),
+        }
+    }
+
+    fn request(body: Value, key: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/v1/chat/completions")
+            .method("POST")
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {}", key))
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn read_body(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_convert_messages() {
+        let converted = convert_messages(messages(json!([
+            {"role": "system", "content": "Be brief"},
+            {"role": "user", "content": [{"type": "text", "text": "Hello"}]},
+            {"role": "assistant", "content": "Hi"},
+            {"role": "tool", "content": "ignored"},
+            {"role": "user", "content": "How are you?"},
+        ])))
+        .unwrap();
+
+        assert_eq!(converted.len(), 3);
+        // The system message is merged into the first user message
+        assert_eq!(converted[0].content.len(), 2);
+        assert_eq!(converted[0].as_concat_text(), "Be brief\nHello");
+        assert_eq!(converted[1].role, Role::Assistant);
+
+        let result = convert_messages(messages(json!([{"role": "assistant", "content": "Hi"}])));
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_requests() {
+        let app = routes(state(false));
+        let body = json!({"messages": [{"role": "user", "content": "Hello"}]});
+
+        let response = app
+            .clone()
+            .oneshot(request(body.clone(), "wrong"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.oneshot(request(body, "test-secret")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion() {
+        let app = routes(state(true));
+        let body = json!({"model": "goose", "messages": [{"role": "user", "content": "Hello"}]});
+        let response = app.oneshot(request(body, "test-secret")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let completion: Value = serde_json::from_str(&read_body(response).await).unwrap();
+        assert_eq!(completion["object"], "chat.completion");
+        assert_eq!(
+            completion["choices"][0]["message"]["content"],
+            "Mock response"
+        );
+        assert_eq!(completion["usage"]["total_tokens"], 15);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_chat_completion() {
+        let app = routes(state(true));
+        let body = json!({"stream": true, "messages": [{"role": "user", "content": "Hello"}]});
+        let response = app.oneshot(request(body, "test-secret")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let events: Vec<String> = read_body(response)
+            .await
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(str::to_string)
+            .collect();
+        assert_eq!(events.last().unwrap(), "[DONE]");
+        let chunks: Vec<Value> = events[..events.len() - 1]
+            .iter()
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(chunks[1]["choices"][0]["delta"]["content"], "Mock response");
+        assert_eq!(chunks[2]["choices"][0]["finish_reason"], "stop");
+    }
+}
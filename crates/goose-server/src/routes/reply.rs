@@ -8,7 +8,9 @@ use axum::{
 };
 use bytes::Bytes;
 use futures::{stream::StreamExt, Stream};
+use goose::agents::ReplyEvent;
 use goose::message::{Message, MessageContent};
+use goose::providers::base::MessageDelta;
 
 use mcp_core::{content::Content, role::Role};
 use serde::Deserialize;
@@ -22,6 +24,7 @@ use std::{
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
 
 // Types matching the incoming JSON structure
 #[derive(Debug, Deserialize)]
@@ -179,8 +182,10 @@ impl ProtocolFormatter {
     }
 }
 
+// Send a message in the protocol, leaving out its text when it was already sent as it streamed
 async fn stream_message(
     message: Message,
+    streamed: bool,
     tx: &mpsc::Sender<String>,
 ) -> Result<(), mpsc::error::SendError<String>> {
     match message.role {
@@ -240,6 +245,7 @@ async fn stream_message(
                             }
                         }
                     }
+                    MessageContent::Text(_) if streamed => {}
                     MessageContent::Text(text) => {
                         for line in text.text.lines() {
                             let modified_line = format!("{}\n", line);
@@ -253,6 +259,9 @@ async fn stream_message(
                     MessageContent::Image(_) => {
                         // skip images
                     }
+                    MessageContent::Thinking(_) => {
+                        // the stream protocol used by the desktop app has no reasoning part
+                    }
                     MessageContent::ToolResponse(_) => {
                         // skip tool responses
                     }
@@ -309,7 +318,9 @@ async fn handler(
             }
         };
 
-        let mut stream = match agent.reply(&messages).await {
+        // Stopping in the app closes the connection, which cancels the reply
+        let cancel = CancellationToken::new();
+        let mut stream = match agent.reply_cancellable(&messages, cancel.clone()).await {
             Ok(stream) => stream,
             Err(e) => {
                 tracing::error!("Failed to start reply stream: {:?}", e);
@@ -320,14 +331,27 @@ async fn handler(
                 return;
             }
         };
+        // Whether the text of the message being generated was sent as it streamed
+        let mut streamed = false;
 
         loop {
             tokio::select! {
                 response = timeout(Duration::from_millis(500), stream.next()) => {
                     match response {
-                        Ok(Some(Ok(message))) => {
-                            if let Err(e) = stream_message(message, &tx).await {
+                        Ok(Some(Ok(ReplyEvent::Delta(delta)))) => {
+                            if let MessageDelta::Text(text) = delta {
+                                streamed = true;
+                                if tx.send(ProtocolFormatter::format_text(&text)).await.is_err() {
+                                    cancel.cancel();
+                                    break;
+                                }
+                            }
+                        }
+                        Ok(Some(Ok(ReplyEvent::Message(message)))) => {
+                            let streamed = std::mem::take(&mut streamed);
+                            if let Err(e) = stream_message(message, streamed, &tx).await {
                                 tracing::error!("Error sending message through channel: {}", e);
+                                cancel.cancel();
                                 let _ = tx.send(ProtocolFormatter::format_error(&e.to_string())).await;
                                 break;
                             }
@@ -342,9 +366,7 @@ async fn handler(
                         }
                         Err(_) => { // Heartbeat, used to detect disconnected clients and then end running tools.
                             if tx.is_closed() {
-                                // Kill any running processes when the client disconnects
-                                // TODO is this used? I suspect post MCP this is on the server instead
-                                // goose::process_store::kill_processes();
+                                cancel.cancel();
                                 break;
                             }
                             continue;
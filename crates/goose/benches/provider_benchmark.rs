@@ -0,0 +1,280 @@
+//! Benchmarks for building provider requests and parsing their responses
+//!
+//! `cargo bench -p goose --bench provider_benchmark` measures the local code paths of each
+//! format. Set `GOOSE_BENCH_LIVE` to a comma separated list of `provider:model` pairs to also
+//! send real requests and report end-to-end latency and output tokens per second, e.g.
+//!
+//! ```sh
+//! GOOSE_BENCH_LIVE=openai:gpt-4o,anthropic:claude-3-5-sonnet-latest \
+//!     cargo bench -p goose --bench provider_benchmark -- --skip .
+//! ```
+//!
+//! `GOOSE_BENCH_LIVE_RUNS` sets the number of requests per model (5 by default) and
+//! `GOOSE_BENCH_REPORT` a path to also write the live results to as JSON.
+
+use criterion::{black_box, BenchmarkId, Criterion};
+use goose::message::Message;
+use goose::model::ModelConfig;
+use goose::providers::formats::{anthropic, google, openai};
+use goose::providers::utils::ImageFormat;
+use mcp_core::content::Content;
+use mcp_core::tool::{Tool, ToolCall};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const SYSTEM: &str = "You are a helpful assistant.";
+
+fn conversation(turns: usize) -> Vec<Message> {
+    let mut messages = Vec::new();
+    for i in 0..turns {
+        let id = format!("call_{}", i);
+        messages.push(Message::user().with_text(format!("List the files in directory {}", i)));
+        messages.push(Message::assistant().with_tool_request(
+            &id,
+            Ok(ToolCall::new(
+                "developer__shell",
+                json!({"command": format!("ls dir{}", i)}),
+            )),
+        ));
+        messages.push(
+            Message::user().with_tool_response(&id, Ok(vec![Content::text("a.txt\nb.txt\n")])),
+        );
+        messages.push(Message::assistant().with_text("There are two files, a.txt and b.txt."));
+    }
+    messages.push(Message::user().with_text("Thanks!"));
+    messages
+}
+
+fn tools() -> Vec<Tool> {
+    (0..10)
+        .map(|i| {
+            Tool::new(
+                format!("extension__tool_{}", i),
+                "A tool that takes a single path argument",
+                json!({
+                    "type": "object",
+                    "required": ["path"],
+                    "properties": {"path": {"type": "string", "description": "The path"}}
+                }),
+            )
+        })
+        .collect()
+}
+
+fn openai_response() -> Value {
+    json!({
+        "choices": [{
+            "message": {
+                "role": "assistant",
+                "content": "Let me check.",
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "developer__shell", "arguments": "{\"command\":\"ls\"}"}
+                }]
+            }
+        }],
+        "usage": {"prompt_tokens": 1200, "completion_tokens": 40, "total_tokens": 1240}
+    })
+}
+
+fn anthropic_response() -> Value {
+    json!({
+        "content": [
+            {"type": "text", "text": "Let me check."},
+            {"type": "tool_use", "id": "toolu_1", "name": "developer__shell", "input": {"command": "ls"}}
+        ],
+        "usage": {"input_tokens": 1200, "output_tokens": 40}
+    })
+}
+
+fn google_response() -> Value {
+    json!({
+        "candidates": [{
+            "content": {
+                "parts": [
+                    {"text": "Let me check."},
+                    {"functionCall": {"name": "developer__shell", "args": {"command": "ls"}}}
+                ]
+            }
+        }],
+        "usageMetadata": {"promptTokenCount": 1200, "candidatesTokenCount": 40, "totalTokenCount": 1240}
+    })
+}
+
+fn benchmark_request_build(c: &mut Criterion) {
+    let model = ModelConfig::new("gpt-4o".to_string());
+    let tools = tools();
+    let mut group = c.benchmark_group("request_build");
+
+    for turns in [1, 10, 100] {
+        let messages = conversation(turns);
+        group.bench_with_input(
+            BenchmarkId::new("openai", turns),
+            &messages,
+            |b, messages| {
+                b.iter(|| {
+                    openai::create_request(
+                        &model,
+                        SYSTEM,
+                        black_box(messages),
+                        &tools,
+                        &ImageFormat::OpenAi,
+                    )
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("anthropic", turns),
+            &messages,
+            |b, messages| {
+                b.iter(|| anthropic::create_request(&model, SYSTEM, black_box(messages), &tools))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("google", turns),
+            &messages,
+            |b, messages| {
+                b.iter(|| google::create_request(&model, SYSTEM, black_box(messages), &tools))
+            },
+        );
+    }
+    group.finish();
+}
+
+fn benchmark_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    let response = openai_response();
+    group.bench_function("openai", |b| {
+        b.iter(|| {
+            let message = openai::response_to_message(black_box(response.clone()));
+            let usage = openai::get_usage(black_box(&response));
+            (message, usage)
+        })
+    });
+
+    let response = anthropic_response();
+    group.bench_function("anthropic", |b| {
+        b.iter(|| {
+            let message = anthropic::response_to_message(black_box(response.clone()));
+            let usage = anthropic::get_usage(black_box(&response));
+            (message, usage)
+        })
+    });
+
+    let response = google_response();
+    group.bench_function("google", |b| {
+        b.iter(|| {
+            let message = google::response_to_message(black_box(response.clone()));
+            let usage = google::get_usage(black_box(&response));
+            (message, usage)
+        })
+    });
+    group.finish();
+}
+
+/// Live results for a single provider and model
+#[derive(Debug, Serialize)]
+struct LiveResult {
+    provider: String,
+    model: String,
+    runs: usize,
+    errors: usize,
+    p50_ms: u128,
+    p95_ms: u128,
+    output_tokens_per_sec: f64,
+}
+
+async fn run_live(target: &str, runs: usize) -> anyhow::Result<LiveResult> {
+    let (provider_name, model_name) = target
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Expected provider:model, got {}", target))?;
+    let model = ModelConfig::new(model_name.to_string()).with_max_tokens(Some(256));
+    let provider = goose::providers::create(provider_name, model)?;
+    let messages = [Message::user().with_text("Write a short paragraph about benchmarks.")];
+
+    let mut latencies = Vec::new();
+    let mut output_tokens = 0;
+    let mut errors = 0;
+    for _ in 0..runs {
+        let start = Instant::now();
+        match provider.complete(SYSTEM, &messages, &[]).await {
+            Ok((_, usage)) => {
+                latencies.push(start.elapsed());
+                output_tokens += usage.usage.output_tokens.unwrap_or(0).max(0) as u64;
+            }
+            Err(e) => {
+                eprintln!("{}: {}", target, e);
+                errors += 1;
+            }
+        }
+    }
+
+    latencies.sort();
+    let percentile = |p: f64| {
+        latencies
+            .get(((latencies.len() as f64 - 1.0) * p).round() as usize)
+            .map_or(0, Duration::as_millis)
+    };
+    let total: Duration = latencies.iter().sum();
+    Ok(LiveResult {
+        provider: provider_name.to_string(),
+        model: model_name.to_string(),
+        runs,
+        errors,
+        p50_ms: percentile(0.5),
+        p95_ms: percentile(0.95),
+        output_tokens_per_sec: if total.is_zero() {
+            0.0
+        } else {
+            output_tokens as f64 / total.as_secs_f64()
+        },
+    })
+}
+
+fn benchmark_live(targets: &str) {
+    let runs = std::env::var("GOOSE_BENCH_LIVE_RUNS")
+        .ok()
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(5);
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start a runtime");
+
+    let mut results = Vec::new();
+    for target in targets.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        match runtime.block_on(run_live(target, runs)) {
+            Ok(result) => results.push(result),
+            Err(e) => eprintln!("Skipping {}: {}", target, e),
+        }
+    }
+
+    println!(
+        "\n{:<12} {:<32} {:>5} {:>7} {:>8} {:>8} {:>10}",
+        "provider", "model", "runs", "errors", "p50 ms", "p95 ms", "tokens/s"
+    );
+    for r in &results {
+        println!(
+            "{:<12} {:<32} {:>5} {:>7} {:>8} {:>8} {:>10.1}",
+            r.provider, r.model, r.runs, r.errors, r.p50_ms, r.p95_ms, r.output_tokens_per_sec
+        );
+    }
+
+    if let Ok(path) = std::env::var("GOOSE_BENCH_REPORT") {
+        let report = serde_json::to_string_pretty(&results).expect("results serialize");
+        if let Err(e) = std::fs::write(&path, report) {
+            eprintln!("Failed to write {}: {}", path, e);
+        }
+    }
+}
+
+fn main() {
+    let mut criterion = Criterion::default().configure_from_args();
+    benchmark_request_build(&mut criterion);
+    benchmark_parse(&mut criterion);
+    criterion.final_summary();
+
+    if let Ok(targets) = std::env::var("GOOSE_BENCH_LIVE") {
+        benchmark_live(&targets);
+    }
+}
@@ -0,0 +1,51 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use aws_sdk_bedrockruntime::types::TokenUsage;
+use goose::providers::formats::{anthropic, bedrock, google, openai};
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+#[derive(Debug, Arbitrary)]
+enum Input<'a> {
+    /// The body of an OpenAI, Anthropic or Google response
+    Json(&'a [u8]),
+    /// Bedrock reports usage through SDK types rather than JSON
+    Bedrock {
+        input_tokens: i32,
+        output_tokens: i32,
+        total_tokens: i32,
+        cache_read_input_tokens: Option<i32>,
+        cache_write_input_tokens: Option<i32>,
+    },
+}
+
+fuzz_target!(|input: Input| {
+    match input {
+        Input::Json(data) => {
+            let Ok(response) = serde_json::from_slice::<Value>(data) else {
+                return;
+            };
+            let _ = openai::get_usage(&response);
+            let _ = anthropic::get_usage(&response);
+            let _ = google::get_usage(&response);
+        }
+        Input::Bedrock {
+            input_tokens,
+            output_tokens,
+            total_tokens,
+            cache_read_input_tokens,
+            cache_write_input_tokens,
+        } => {
+            let usage = TokenUsage::builder()
+                .input_tokens(input_tokens)
+                .output_tokens(output_tokens)
+                .total_tokens(total_tokens)
+                .set_cache_read_input_tokens(cache_read_input_tokens)
+                .set_cache_write_input_tokens(cache_write_input_tokens)
+                .build()
+                .expect("all required fields are set");
+            let _ = bedrock::from_bedrock_usage(&usage);
+        }
+    }
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use goose::providers::formats::{anthropic, google, openai};
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+// Parsing may reject a response, but must never panic on one
+fuzz_target!(|data: &[u8]| {
+    let Ok(response) = serde_json::from_slice::<Value>(data) else {
+        return;
+    };
+
+    let _ = openai::response_to_message(response.clone());
+    let _ = anthropic::response_to_message(response.clone());
+    let _ = google::response_to_message(response);
+});
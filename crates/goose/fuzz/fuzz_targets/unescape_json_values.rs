@@ -0,0 +1,26 @@
+#![no_main]
+
+use goose::providers::utils::unescape_json_values;
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = serde_json::from_slice::<Value>(data) else {
+        return;
+    };
+
+    let unescaped = unescape_json_values(&value);
+    // Unescaping only rewrites strings, so the shape of the value is unchanged
+    assert_eq!(shape(&value), shape(&unescaped));
+});
+
+fn shape(value: &Value) -> Value {
+    match value {
+        Value::String(_) => Value::Null,
+        Value::Array(items) => Value::Array(items.iter().map(shape).collect()),
+        Value::Object(map) => {
+            Value::Object(map.iter().map(|(k, v)| (k.clone(), shape(v))).collect())
+        }
+        other => other.clone(),
+    }
+}
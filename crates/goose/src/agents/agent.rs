@@ -1,17 +1,65 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use futures::stream::BoxStream;
+use mcp_core::Resource;
 use serde_json::Value;
+use tokio_util::sync::CancellationToken;
 
 use super::extension::{ExtensionConfig, ExtensionResult};
+use super::limits::SessionLimits;
+use super::plan::Plan;
+use super::resources::AttachedResource;
 use crate::message::Message;
-use crate::providers::base::ProviderUsage;
+use crate::providers::base::{MessageDelta, ProviderUsage};
+use crate::usage::ToolTokenUsage;
+
+/// What an agent's reply yields as it is generated
+#[derive(Debug, Clone)]
+pub enum ReplyEvent {
+    /// Part of the model's response, as the provider streams it
+    Delta(MessageDelta),
+    /// A complete message; one that follows deltas is those deltas assembled
+    Message(Message),
+}
 
 /// Core trait defining the behavior of an Agent
 #[async_trait]
 pub trait Agent: Send + Sync {
+    /// Create a stream that yields the model's response as it arrives, and each message
+    /// once it is complete, ending as soon as `cancel` is cancelled
+    ///
+    /// Cancelling aborts the provider request in flight, including generation by a model
+    /// running in process, and the tool calls being run, so a stop button doesn't have to
+    /// wait for the model to finish.
+    async fn reply_cancellable(
+        &self,
+        messages: &[Message],
+        cancel: CancellationToken,
+    ) -> Result<BoxStream<'_, Result<ReplyEvent>>>;
+
+    /// Like `reply_cancellable`, for a reply that runs to its end
+    async fn reply_streaming(
+        &self,
+        messages: &[Message],
+    ) -> Result<BoxStream<'_, Result<ReplyEvent>>> {
+        self.reply_cancellable(messages, CancellationToken::new())
+            .await
+    }
+
     /// Create a stream that yields each message as it's generated by the agent
-    async fn reply(&self, messages: &[Message]) -> Result<BoxStream<'_, Result<Message>>>;
+    async fn reply(&self, messages: &[Message]) -> Result<BoxStream<'_, Result<Message>>> {
+        let stream = self.reply_streaming(messages).await?;
+        Ok(Box::pin(futures::StreamExt::filter_map(
+            stream,
+            |event| async move {
+                match event {
+                    Ok(ReplyEvent::Message(message)) => Some(Ok(message)),
+                    Ok(ReplyEvent::Delta(_)) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            },
+        )))
+    }
 
     /// Add a new MCP client to the agent
     async fn add_extension(&mut self, config: ExtensionConfig) -> ExtensionResult<()>;
@@ -23,12 +71,30 @@ pub trait Agent: Send + Sync {
     // TODO this needs to also include status so we can tell if extensions are dropped
     async fn list_extensions(&self) -> Vec<String>;
 
+    /// List the resources an extension offers
+    async fn list_resources(&self, extension: &str) -> ExtensionResult<Vec<Resource>>;
+
+    /// Include a resource in the context of every reply, refreshed when the extension reports changes
+    async fn attach_resource(&self, extension: &str, uri: &str) -> ExtensionResult<()>;
+
+    /// Stop including an attached resource
+    async fn detach_resource(&self, extension: &str, uri: &str) -> ExtensionResult<()>;
+
+    /// List the resources attached to the conversation
+    async fn attached_resources(&self) -> Vec<AttachedResource>;
+
     /// Pass through a JSON-RPC request to a specific extension
     async fn passthrough(&self, extension: &str, request: Value) -> ExtensionResult<Value>;
 
+    /// The plan of the last reply in plan mode, with how far it got
+    async fn plan(&self) -> Option<Plan>;
+
     /// Get the total usage of the agent
     async fn usage(&self) -> Vec<ProviderUsage>;
 
+    /// Get how many tokens each tool's results take up in the conversation
+    async fn tool_token_usage(&self, messages: &[Message]) -> Vec<ToolTokenUsage>;
+
     /// Add custom text to be included in the system prompt
     async fn extend_system_prompt(&mut self, extension: String);
 
@@ -37,4 +103,7 @@ pub trait Agent: Send + Sync {
 
     /// Override the system prompt with custom text
     async fn override_system_prompt(&mut self, template: String);
+
+    /// Attach a deadline and token and cost budget to the session
+    async fn set_session_limits(&mut self, limits: SessionLimits);
 }
@@ -4,16 +4,30 @@ use mcp_client::McpService;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::sync::LazyLock;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
-use tracing::{debug, instrument};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, instrument, warn};
 
+use super::concurrency::ToolConcurrency;
 use super::extension::{ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult};
+use super::limits::{EffectiveLimits, SessionLimits, ToolLimits};
+use super::resources::{resource_text, AttachedResource, AttachedResources};
+use super::sampling::SamplingHandler;
+use super::subagent::{self, Subagents, DELEGATE_TOOL_NAME};
+use crate::events::{self, Event};
+use crate::message::Message;
 use crate::prompt_template::{load_prompt, load_prompt_file};
-use crate::providers::base::{Provider, ProviderUsage};
-use mcp_client::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
-use mcp_client::transport::{SseTransport, StdioTransport, Transport};
-use mcp_core::{Content, Tool, ToolCall, ToolError, ToolResult};
+use crate::providers::base::{MessageStream, Provider, ProviderUsage};
+use crate::providers::errors::ProviderError;
+use crate::providers::oauth::McpOAuth;
+use mcp_client::client::{
+    ClientCapabilities, ClientInfo, McpClient, McpClientTrait, SamplingCapability,
+};
+use mcp_client::transport::{
+    SseTransport, StdioTransport, StreamableHttpTransport, Transport, TransportHandle,
+};
+use mcp_core::{Content, Resource, Tool, ToolCall, ToolError, ToolResult};
 use serde_json::Value;
 
 // By default, we set it to Jan 1, 2020 if the resource does not have a timestamp
@@ -21,17 +35,34 @@ use serde_json::Value;
 static DEFAULT_TIMESTAMP: LazyLock<DateTime<Utc>> =
     LazyLock::new(|| Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
 
-type McpClientBox = Arc<Mutex<Box<dyn McpClientTrait>>>;
+type McpClientBox = Arc<dyn McpClientTrait>;
+
+// Wrap a started transport in a client that delivers the server's notifications and requests
+fn new_client<H: TransportHandle>(handle: H, timeout: Duration) -> Box<dyn McpClientTrait> {
+    let notifications = handle.notifications();
+    let server_requests = handle.server_requests();
+    let service = McpService::with_timeout(handle, timeout);
+    Box::new(
+        McpClient::new(service)
+            .with_notifications(notifications)
+            .with_server_requests(server_requests),
+    )
+}
 
 /// Manages MCP clients and their interactions
 pub struct Capabilities {
     clients: HashMap<String, McpClientBox>,
     instructions: HashMap<String, String>,
     resource_capable_extensions: HashSet<String>,
-    provider: Box<dyn Provider>,
-    provider_usage: Mutex<Vec<ProviderUsage>>,
+    provider: Arc<dyn Provider>,
+    provider_usage: Arc<Mutex<Vec<ProviderUsage>>>,
     system_prompt_override: Option<String>,
     system_prompt_extensions: Vec<String>,
+    tool_limits: ToolLimits,
+    session_limits: SessionLimits,
+    tool_concurrency: ToolConcurrency,
+    attached_resources: AttachedResources,
+    subagents: Subagents,
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -81,6 +112,12 @@ fn normalize(input: String) -> String {
     result.to_lowercase()
 }
 
+fn deadline_passed() -> ProviderError {
+    ProviderError::BudgetExceeded(
+        "the session deadline passed while waiting for the model".to_string(),
+    )
+}
+
 impl Capabilities {
     /// Create a new Capabilities with the specified provider
     pub fn new(provider: Box<dyn Provider>) -> Self {
@@ -88,10 +125,15 @@ impl Capabilities {
             clients: HashMap::new(),
             instructions: HashMap::new(),
             resource_capable_extensions: HashSet::new(),
-            provider,
-            provider_usage: Mutex::new(Vec::new()),
+            provider: Arc::from(provider),
+            provider_usage: Arc::new(Mutex::new(Vec::new())),
             system_prompt_override: None,
             system_prompt_extensions: Vec::new(),
+            tool_limits: ToolLimits::from_config(),
+            session_limits: SessionLimits::default(),
+            tool_concurrency: ToolConcurrency::from_config(),
+            attached_resources: AttachedResources::default(),
+            subagents: Subagents::from_config(),
         }
     }
 
@@ -102,20 +144,37 @@ impl Capabilities {
     /// Add a new MCP extension based on the provided client type
     // TODO IMPORTANT need to ensure this times out if the extension command is broken!
     pub async fn add_extension(&mut self, config: ExtensionConfig) -> ExtensionResult<()> {
+        let timeout = self
+            .tool_limits
+            .request_timeout(&normalize(config.name().to_string()));
         let mut client: Box<dyn McpClientTrait> = match &config {
-            ExtensionConfig::Sse { uri, envs, .. } => {
-                let transport = SseTransport::new(uri, envs.get_env());
-                let handle = transport.start().await?;
-                let service = McpService::with_timeout(handle, Duration::from_secs(300));
-                Box::new(McpClient::new(service))
+            ExtensionConfig::Sse {
+                uri, envs, headers, ..
+            } => {
+                let transport =
+                    SseTransport::new(uri, envs.get_env()).with_headers(headers.clone());
+                new_client(transport.start().await?, timeout)
+            }
+            ExtensionConfig::StreamableHttp {
+                uri, envs, headers, ..
+            } => {
+                let mut transport =
+                    StreamableHttpTransport::new(uri, envs.get_env()).with_headers(headers.clone());
+                // Sign in with OAuth when the server asks for it, unless it has a configured
+                // Authorization header
+                if !headers
+                    .keys()
+                    .any(|k| k.eq_ignore_ascii_case("authorization"))
+                {
+                    transport = transport.with_auth(Arc::new(McpOAuth::new(uri)));
+                }
+                new_client(transport.start().await?, timeout)
             }
             ExtensionConfig::Stdio {
                 cmd, args, envs, ..
             } => {
                 let transport = StdioTransport::new(cmd, args.to_vec(), envs.get_env());
-                let handle = transport.start().await?;
-                let service = McpService::with_timeout(handle, Duration::from_secs(300));
-                Box::new(McpClient::new(service))
+                new_client(transport.start().await?, timeout)
             }
             ExtensionConfig::Builtin { name } => {
                 // For builtin extensions, we run the current executable with mcp and extension name
@@ -129,26 +188,31 @@ impl Capabilities {
                     vec!["mcp".to_string(), name.clone()],
                     HashMap::new(),
                 );
-                let handle = transport.start().await?;
-                let service = McpService::with_timeout(handle, Duration::from_secs(300));
-                Box::new(McpClient::new(service))
+                new_client(transport.start().await?, timeout)
             }
         };
 
-        // Initialize the client with default capabilities
+        let sanitized_name = normalize(config.name().to_string());
+        let sampling = SamplingHandler::new(
+            &sanitized_name,
+            Arc::clone(&self.provider),
+            Arc::clone(&self.provider_usage),
+        );
+
+        // Initialize the client, offering sampling unless it is disabled
         let info = ClientInfo {
             name: "goose".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
         };
-        let capabilities = ClientCapabilities::default();
+        let capabilities = ClientCapabilities {
+            sampling: sampling.enabled().then(SamplingCapability::default),
+        };
 
         let init_result = client
             .initialize(info, capabilities)
             .await
             .map_err(|e| ExtensionError::Initialization(config.clone(), e))?;
 
-        let sanitized_name = normalize(config.name().to_string());
-
         // Store instructions if provided
         if let Some(instructions) = init_result.instructions {
             self.instructions
@@ -161,9 +225,20 @@ impl Capabilities {
                 .insert(sanitized_name.clone());
         }
 
+        // Keep attached resources up to date as the extension reports changes
+        if let Some(notifications) = client.notifications() {
+            self.attached_resources
+                .watch(sanitized_name.clone(), notifications);
+        }
+
+        // Answer the extension's requests, such as for sampling, until its transport closes
+        if let Some(requests) = client.server_requests() {
+            sampling.serve(requests);
+        }
+
         // Store the client using the provided name
         self.clients
-            .insert(sanitized_name.clone(), Arc::new(Mutex::new(client)));
+            .insert(sanitized_name.clone(), Arc::from(client));
 
         Ok(())
     }
@@ -178,11 +253,94 @@ impl Capabilities {
         self.system_prompt_override = Some(template);
     }
 
+    /// Replace the limits on tool run time and output read from `GOOSE_TOOL_LIMITS`
+    ///
+    /// The servers of extensions added before keep the request timeout they were added with.
+    pub fn set_tool_limits(&mut self, limits: ToolLimits) {
+        self.tool_limits = limits;
+    }
+
+    /// Attach a deadline and budget to the session, replacing any attached before
+    pub fn set_session_limits(&mut self, limits: SessionLimits) {
+        self.session_limits = limits;
+    }
+
+    /// Replace the tool concurrency settings read from `GOOSE_TOOL_CONCURRENCY`
+    pub fn set_tool_concurrency(&mut self, concurrency: ToolConcurrency) {
+        self.tool_concurrency = concurrency;
+    }
+
+    /// Replace the subagents read from `GOOSE_SUBAGENTS`
+    pub fn set_subagents(&mut self, subagents: Subagents) {
+        self.subagents = subagents;
+    }
+
+    pub fn subagents(&self) -> &Subagents {
+        &self.subagents
+    }
+
     /// Get a reference to the provider
     pub fn provider(&self) -> &dyn Provider {
         &*self.provider
     }
 
+    /// Get a completion from the provider, within the limits attached to the session
+    pub async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let limits = self.session_limits;
+        limits.check(&self.provider_usage.lock().await)?;
+        let completion = self.provider.complete(system, messages, tools);
+        match limits.remaining() {
+            Some(remaining) => match tokio::time::timeout(remaining, completion).await {
+                Ok(result) => result,
+                Err(_) => Err(deadline_passed()),
+            },
+            None => completion.await,
+        }
+    }
+
+    /// Stream a completion from the provider, within the limits attached to the session
+    ///
+    /// The stream ends with `ProviderError::Cancelled` once `cancel` is cancelled.
+    pub async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel: &CancellationToken,
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let limits = self.session_limits;
+        limits.check(&self.provider_usage.lock().await)?;
+        let started = self
+            .provider
+            .stream_cancellable(system, messages, tools, cancel);
+        let Some(remaining) = limits.remaining() else {
+            return started.await;
+        };
+
+        let deadline = tokio::time::Instant::now() + remaining;
+        let mut stream = match tokio::time::timeout_at(deadline, started).await {
+            Ok(result) => result?,
+            Err(_) => return Err(deadline_passed()),
+        };
+        Ok(Box::pin(async_stream::stream! {
+            loop {
+                match tokio::time::timeout_at(deadline, stream.next()).await {
+                    Ok(Some(delta)) => yield delta,
+                    Ok(None) => break,
+                    Err(_) => {
+                        yield Err(deadline_passed());
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
     /// Record provider usage
     // TODO consider moving this off to the provider or as a form of logging
     pub async fn record_usage(&self, usage: ProviderUsage) {
@@ -196,6 +354,9 @@ impl Capabilities {
         self.clients.remove(&sanitized_name);
         self.instructions.remove(&sanitized_name);
         self.resource_capable_extensions.remove(&sanitized_name);
+        self.attached_resources
+            .remove_extension(&sanitized_name)
+            .await;
         Ok(())
     }
 
@@ -220,6 +381,24 @@ impl Capabilities {
                     e.usage.total_tokens = Some(
                         e.usage.total_tokens.unwrap_or(0) + usage.usage.total_tokens.unwrap_or(0),
                     );
+                    if let Some(cache_read) = usage.usage.cache_read_tokens {
+                        *e.usage.cache_read_tokens.get_or_insert(0) += cache_read;
+                    }
+                    if let Some(cache_write) = usage.usage.cache_write_tokens {
+                        *e.usage.cache_write_tokens.get_or_insert(0) += cache_write;
+                    }
+                    if let Some(reasoning) = usage.usage.reasoning_tokens {
+                        *e.usage.reasoning_tokens.get_or_insert(0) += reasoning;
+                    }
+                    if let Some(audio_input) = usage.usage.audio_input_tokens {
+                        *e.usage.audio_input_tokens.get_or_insert(0) += audio_input;
+                    }
+                    if let Some(audio_output) = usage.usage.audio_output_tokens {
+                        *e.usage.audio_output_tokens.get_or_insert(0) += audio_output;
+                    }
+                    if let Some(cost) = usage.cost {
+                        *e.cost.get_or_insert_with(Default::default) += cost;
+                    }
                 })
                 .or_insert_with(|| usage.clone());
         });
@@ -227,11 +406,10 @@ impl Capabilities {
     }
 
     /// Get all tools from all clients with proper prefixing
-    pub async fn get_prefixed_tools(&mut self) -> ExtensionResult<Vec<Tool>> {
+    pub async fn get_prefixed_tools(&self) -> ExtensionResult<Vec<Tool>> {
         let mut tools = Vec::new();
         for (name, client) in &self.clients {
-            let client_guard = client.lock().await;
-            let mut client_tools = client_guard.list_tools(None).await?;
+            let mut client_tools = client.list_tools(None).await?;
 
             loop {
                 for tool in client_tools.tools {
@@ -247,7 +425,7 @@ impl Capabilities {
                     break;
                 }
 
-                client_tools = client_guard.list_tools(client_tools.next_cursor).await?;
+                client_tools = client.list_tools(client_tools.next_cursor).await?;
             }
         }
         Ok(tools)
@@ -258,8 +436,7 @@ impl Capabilities {
         let mut result: Vec<ResourceItem> = Vec::new();
 
         for (name, client) in &self.clients {
-            let client_guard = client.lock().await;
-            let resources = client_guard.list_resources(None).await?;
+            let resources = client.list_resources(None).await?;
 
             for resource in resources.resources {
                 // Skip reading the resource if it's not marked active
@@ -268,7 +445,7 @@ impl Capabilities {
                     continue;
                 }
 
-                if let Ok(contents) = client_guard.read_resource(&resource.uri).await {
+                if let Ok(contents) = client.read_resource(&resource.uri).await {
                     for content in contents.contents {
                         let (uri, content_str) = match content {
                             mcp_core::resource::ResourceContents::TextResourceContents {
@@ -298,8 +475,96 @@ impl Capabilities {
         Ok(result)
     }
 
+    fn get_client(&self, extension: &str) -> ExtensionResult<McpClientBox> {
+        self.clients
+            .get(&normalize(extension.to_string()))
+            .cloned()
+            .ok_or_else(|| ExtensionError::UnknownExtension(extension.to_string()))
+    }
+
+    /// List every resource an extension offers
+    pub async fn list_extension_resources(
+        &self,
+        extension: &str,
+    ) -> ExtensionResult<Vec<Resource>> {
+        let client = self.get_client(extension)?;
+        let mut resources = Vec::new();
+        let mut next_cursor = None;
+        loop {
+            let page = client.list_resources(next_cursor).await?;
+            resources.extend(page.resources);
+            next_cursor = page.next_cursor;
+            if next_cursor.is_none() {
+                break;
+            }
+        }
+        Ok(resources)
+    }
+
+    /// Keep the contents of an extension resource in the system prompt
+    ///
+    /// When the extension supports subscriptions, the contents are read again whenever it
+    /// reports a change; otherwise they stay as they were when attached.
+    pub async fn attach_resource(&self, extension: &str, uri: &str) -> ExtensionResult<()> {
+        let client = self.get_client(extension)?;
+        let text = resource_text(client.read_resource(uri).await?);
+        let subscribed = match client.subscribe_resource(uri).await {
+            Ok(()) => true,
+            Err(e) => {
+                debug!("Not subscribed to {}: {}", uri, e);
+                false
+            }
+        };
+        self.attached_resources
+            .insert(AttachedResource::new(
+                &normalize(extension.to_string()),
+                uri,
+                text,
+                subscribed,
+            ))
+            .await;
+        Ok(())
+    }
+
+    /// Stop including a resource in the system prompt
+    pub async fn detach_resource(&self, extension: &str, uri: &str) -> ExtensionResult<()> {
+        let client = self.get_client(extension)?;
+        let removed = self
+            .attached_resources
+            .remove(&normalize(extension.to_string()), uri)
+            .await;
+        if removed.is_some_and(|r| r.subscribed) {
+            client.unsubscribe_resource(uri).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn attached_resources(&self) -> Vec<AttachedResource> {
+        self.attached_resources.list().await
+    }
+
+    /// Read the attached resources that changed since they were last read
+    async fn refresh_attached_resources(&self) {
+        for (extension, uri) in self.attached_resources.stale().await {
+            let Some(client) = self.clients.get(&extension) else {
+                continue;
+            };
+            match client.read_resource(&uri).await {
+                Ok(result) => {
+                    self.attached_resources
+                        .update(&extension, &uri, resource_text(result))
+                        .await
+                }
+                // Keep the old contents and try again next time
+                Err(e) => warn!("Failed to refresh resource {}: {}", uri, e),
+            }
+        }
+    }
+
     /// Get the extension prompt including client instructions
     pub async fn get_system_prompt(&self) -> String {
+        self.refresh_attached_resources().await;
+
         let mut context: HashMap<&str, Value> = HashMap::new();
 
         let extensions_info: Vec<ExtensionInfo> = self
@@ -316,6 +581,10 @@ impl Capabilities {
 
         context.insert("extensions", serde_json::to_value(extensions_info).unwrap());
         context.insert("current_date_time", Value::String(current_date_time));
+        context.insert(
+            "resources",
+            serde_json::to_value(self.attached_resources.list().await).unwrap(),
+        );
 
         // Conditionally load the override prompt or the default system prompt
         // and set the base prompt to the context
@@ -409,8 +678,7 @@ impl Capabilities {
             .get(extension_name)
             .ok_or(ToolError::InvalidParameters(error_msg))?;
 
-        let client_guard = client.lock().await;
-        let read_result = client_guard.read_resource(uri).await.map_err(|_| {
+        let read_result = client.read_resource(uri).await.map_err(|_| {
             ToolError::ExecutionError(format!("Could not read resource with uri: {}", uri))
         })?;
 
@@ -435,8 +703,7 @@ impl Capabilities {
             ToolError::InvalidParameters(format!("Extension {} is not valid", extension_name))
         })?;
 
-        let client_guard = client.lock().await;
-        client_guard
+        client
             .list_resources(None)
             .await
             .map_err(|e| {
@@ -508,13 +775,61 @@ impl Capabilities {
     }
 
     /// Dispatch a single tool call to the appropriate client
+    ///
+    /// Calls to an extension that run past their timeout are cancelled, and output over the
+    /// size limit is discarded; both come back as errors the model can react to.
     #[instrument(skip(self, tool_call), fields(input, output))]
     pub async fn dispatch_tool_call(&self, tool_call: ToolCall) -> ToolResult<Vec<Content>> {
-        let result = if tool_call.name == "platform__read_resource" {
+        let id = events::next_id();
+        events::emit(Event::ToolStarted {
+            id,
+            name: tool_call.name.clone(),
+        });
+        let start = Instant::now();
+
+        let limits = self.tool_limits.for_tool(&tool_call.name);
+        let result = self
+            .call_tool(&tool_call, &limits)
+            .await
+            .and_then(|output| limits.check_output(&tool_call.name, output));
+
+        debug!(
+            "input" = serde_json::to_string(&tool_call).unwrap(),
+            "output" = serde_json::to_string(&result).unwrap(),
+        );
+        events::emit(Event::ToolFinished {
+            id,
+            name: tool_call.name,
+            duration_ms: start.elapsed().as_millis() as u64,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        });
+
+        result
+    }
+
+    /// Dispatch the tool calls from one turn, concurrently where `GOOSE_TOOL_CONCURRENCY`
+    /// allows, returning their results in the order requested
+    pub async fn dispatch_tool_calls(
+        &self,
+        tool_calls: Vec<ToolCall>,
+    ) -> Vec<ToolResult<Vec<Content>>> {
+        self.tool_concurrency
+            .run(tool_calls, |tool_call| self.dispatch_tool_call(tool_call))
+            .await
+    }
+
+    async fn call_tool(
+        &self,
+        tool_call: &ToolCall,
+        limits: &EffectiveLimits,
+    ) -> ToolResult<Vec<Content>> {
+        if tool_call.name == "platform__read_resource" {
             // Check if the tool is read_resource and handle it separately
             self.read_resource(tool_call.arguments.clone()).await
         } else if tool_call.name == "platform__list_resources" {
             self.list_resources(tool_call.arguments.clone()).await
+        } else if tool_call.name == DELEGATE_TOOL_NAME {
+            subagent::delegate(self, tool_call.arguments.clone(), 0).await
         } else {
             // Else, dispatch tool call based on the prefix naming convention
             let (client_name, client) = self
@@ -528,27 +843,28 @@ impl Capabilities {
                 .and_then(|s| s.strip_prefix("__"))
                 .ok_or_else(|| ToolError::NotFound(tool_call.name.clone()))?;
 
-            let client_guard = client.lock().await;
-
-            client_guard
-                .call_tool(tool_name, tool_call.clone().arguments)
-                .await
-                .map(|result| result.content)
-                .map_err(|e| ToolError::ExecutionError(e.to_string()))
-        };
-
-        debug!(
-            "input" = serde_json::to_string(&tool_call).unwrap(),
-            "output" = serde_json::to_string(&result).unwrap(),
-        );
-
-        result
+            // Dropping the call on timeout tells the server to cancel it
+            let call = client.call_tool(tool_name, tool_call.arguments.clone());
+            match tokio::time::timeout(limits.timeout, call).await {
+                Ok(result) => result
+                    .map(|result| result.content)
+                    .map_err(|e| ToolError::ExecutionError(e.to_string())),
+                Err(_) => {
+                    warn!(
+                        "Tool call {} timed out after {:?}",
+                        tool_call.name, limits.timeout
+                    );
+                    Err(limits.timed_out(&tool_call.name))
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::limits::LimitOverride;
     use crate::message::Message;
     use crate::model::ModelConfig;
     use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
@@ -558,6 +874,7 @@ mod tests {
     use mcp_core::protocol::{
         CallToolResult, InitializeResult, ListResourcesResult, ListToolsResult, ReadResourceResult,
     };
+    use mcp_core::resource::ResourceContents;
     use serde_json::json;
 
     // Mock Provider implementation for testing
@@ -589,6 +906,10 @@ mod tests {
         }
     }
 
+    // Calls to the "rendezvous" tool wait here until a second one arrives
+    static RENDEZVOUS: LazyLock<tokio::sync::Barrier> =
+        LazyLock::new(|| tokio::sync::Barrier::new(2));
+
     struct MockClient {}
 
     #[async_trait::async_trait]
@@ -608,8 +929,25 @@ mod tests {
             Err(Error::NotInitialized)
         }
 
-        async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, Error> {
-            Err(Error::NotInitialized)
+        async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, Error> {
+            match uri {
+                "file:///notes" => Ok(ReadResourceResult {
+                    contents: vec![ResourceContents::TextResourceContents {
+                        uri: uri.to_string(),
+                        mime_type: None,
+                        text: "notes".to_string(),
+                    }],
+                }),
+                _ => Err(Error::NotInitialized),
+            }
+        }
+
+        async fn subscribe_resource(&self, _uri: &str) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn unsubscribe_resource(&self, _uri: &str) -> Result<(), Error> {
+            Ok(())
         }
 
         async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
@@ -622,6 +960,24 @@ mod tests {
                     content: vec![],
                     is_error: None,
                 }),
+                "slow" => {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(CallToolResult {
+                        content: vec![],
+                        is_error: None,
+                    })
+                }
+                "rendezvous" => {
+                    RENDEZVOUS.wait().await;
+                    Ok(CallToolResult {
+                        content: vec![],
+                        is_error: None,
+                    })
+                }
+                "large" => Ok(CallToolResult {
+                    content: vec![Content::text("x".repeat(1000))],
+                    is_error: None,
+                }),
                 _ => Err(Error::NotInitialized),
             }
         }
@@ -639,23 +995,21 @@ mod tests {
         // Add some mock clients
         capabilities.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        capabilities.clients.insert(
-            normalize("__client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        capabilities
+            .clients
+            .insert(normalize("__client".to_string()), Arc::new(MockClient {}));
 
         capabilities.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        capabilities.clients.insert(
-            normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        capabilities
+            .clients
+            .insert(normalize("client 🚀".to_string()), Arc::new(MockClient {}));
 
         // Test basic case
         assert!(capabilities
@@ -688,18 +1042,17 @@ mod tests {
         // Add some mock clients
         capabilities.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
         capabilities.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        capabilities.clients.insert(
-            normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        capabilities
+            .clients
+            .insert(normalize("client 🚀".to_string()), Arc::new(MockClient {}));
 
         // verify a normal tool call
         let tool_call = ToolCall {
@@ -766,4 +1119,120 @@ mod tests {
         let result = capabilities.dispatch_tool_call(invalid_tool_call).await;
         assert!(matches!(result.err().unwrap(), ToolError::NotFound(_)));
     }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_limits() {
+        let mock_model_config =
+            ModelConfig::new("test-model".to_string()).with_context_limit(200_000.into());
+
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        capabilities.clients.insert(
+            normalize("test_client".to_string()),
+            Arc::new(MockClient {}),
+        );
+        capabilities.set_tool_limits(ToolLimits {
+            max_output: 100,
+            tools: HashMap::from([(
+                "test_client__slow".to_string(),
+                LimitOverride {
+                    timeout: Some(1),
+                    max_output: None,
+                },
+            )]),
+            ..Default::default()
+        });
+
+        let result = capabilities
+            .dispatch_tool_call(ToolCall::new("test_client__slow", json!({})))
+            .await;
+        assert!(
+            matches!(result, Err(ToolError::ExecutionError(message)) if message.contains("timed out"))
+        );
+
+        let result = capabilities
+            .dispatch_tool_call(ToolCall::new("test_client__large", json!({})))
+            .await;
+        assert!(
+            matches!(result, Err(ToolError::ExecutionError(message)) if message.contains("1000 bytes"))
+        );
+
+        let result = capabilities
+            .dispatch_tool_call(ToolCall::new("test_client__tool", json!({})))
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_calls_to_one_extension_concurrently() {
+        let mock_model_config =
+            ModelConfig::new("test-model".to_string()).with_context_limit(200_000.into());
+
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        capabilities.clients.insert(
+            normalize("test_client".to_string()),
+            Arc::new(MockClient {}),
+        );
+
+        // Each call waits for the other, so both only finish if neither blocks the client
+        let (first, second) = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(
+                capabilities
+                    .dispatch_tool_call(ToolCall::new("test_client__rendezvous", json!({}))),
+                capabilities
+                    .dispatch_tool_call(ToolCall::new("test_client__rendezvous", json!({}))),
+            )
+        })
+        .await
+        .expect("calls to one extension ran one at a time");
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_attach_resource() {
+        let mock_model_config =
+            ModelConfig::new("test-model".to_string()).with_context_limit(200_000.into());
+
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        capabilities.clients.insert(
+            normalize("test_client".to_string()),
+            Arc::new(MockClient {}),
+        );
+
+        capabilities
+            .attach_resource("test_client", "file:///notes")
+            .await
+            .unwrap();
+        let attached = capabilities.attached_resources().await;
+        assert_eq!(attached.len(), 1);
+        assert_eq!(attached[0].text, "notes");
+        assert!(attached[0].subscribed);
+        assert!(capabilities
+            .get_system_prompt()
+            .await
+            .contains("file:///notes"));
+
+        assert!(capabilities
+            .attach_resource("test_client", "file:///missing")
+            .await
+            .is_err());
+        assert!(matches!(
+            capabilities
+                .attach_resource("unknown", "file:///notes")
+                .await,
+            Err(ExtensionError::UnknownExtension(_))
+        ));
+
+        capabilities
+            .detach_resource("test_client", "file:///notes")
+            .await
+            .unwrap();
+        assert!(capabilities.attached_resources().await.is_empty());
+    }
 }
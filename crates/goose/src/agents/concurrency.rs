@@ -0,0 +1,268 @@
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+use crate::config::Config;
+use mcp_core::{Content, ToolCall, ToolResult};
+
+/// Config key holding the `ToolConcurrency`
+pub const TOOL_CONCURRENCY_CONFIG_KEY: &str = "GOOSE_TOOL_CONCURRENCY";
+
+const DEFAULT_MAX_CONCURRENT: usize = 8;
+
+/// How the tool calls from a single turn run alongside each other
+///
+/// Calls run concurrently up to `max_concurrent` at a time, except where an ordering
+/// constraint applies:
+/// - an `exclusive` tool or extension waits for every call requested before it, and the
+///   calls requested after it wait for it to finish
+/// - calls to the same `ordered` tool or extension run one at a time, in the order requested
+///
+/// Results always go back to the model in the order the calls were requested.
+///
+/// ```yaml
+/// GOOSE_TOOL_CONCURRENCY:
+///   max_concurrent: 4
+///   exclusive: [developer__shell]
+///   ordered: [memory]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolConcurrency {
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    #[serde(default)]
+    pub exclusive: Vec<String>,
+    #[serde(default)]
+    pub ordered: Vec<String>,
+}
+
+fn default_max_concurrent() -> usize {
+    DEFAULT_MAX_CONCURRENT
+}
+
+impl Default for ToolConcurrency {
+    fn default() -> Self {
+        Self {
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+            exclusive: Vec::new(),
+            ordered: Vec::new(),
+        }
+    }
+}
+
+/// The tool name and its extension, the keys a constraint can be set on
+fn keys(name: &str) -> impl Iterator<Item = &str> {
+    name.split_once("__")
+        .map(|(extension, _)| extension)
+        .into_iter()
+        .chain([name])
+}
+
+impl ToolConcurrency {
+    pub fn from_config() -> Self {
+        Config::global()
+            .get(TOOL_CONCURRENCY_CONFIG_KEY)
+            .unwrap_or_default()
+    }
+
+    fn is_exclusive(&self, name: &str) -> bool {
+        keys(name).any(|key| self.exclusive.iter().any(|exclusive| exclusive == key))
+    }
+
+    // The ordered tool or extension a call belongs to, if any
+    fn ordered_key<'a>(&self, name: &'a str) -> Option<&'a str> {
+        keys(name).find(|key| self.ordered.iter().any(|ordered| ordered == key))
+    }
+
+    /// Whether `later` has to wait for `earlier`, a call requested before it
+    fn must_follow(&self, earlier: &ToolCall, later: &ToolCall) -> bool {
+        if self.is_exclusive(&earlier.name) || self.is_exclusive(&later.name) {
+            return true;
+        }
+        match (
+            self.ordered_key(&earlier.name),
+            self.ordered_key(&later.name),
+        ) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Run the calls through `dispatch`, returning their results in the order requested
+    pub async fn run<F, Fut>(
+        &self,
+        calls: Vec<ToolCall>,
+        dispatch: F,
+    ) -> Vec<ToolResult<Vec<Content>>>
+    where
+        F: Fn(ToolCall) -> Fut,
+        Fut: Future<Output = ToolResult<Vec<Content>>>,
+    {
+        let dependencies: Vec<Vec<usize>> = calls
+            .iter()
+            .enumerate()
+            .map(|(i, call)| {
+                (0..i)
+                    .filter(|&j| self.must_follow(&calls[j], call))
+                    .collect()
+            })
+            .collect();
+        let limit = self.max_concurrent.max(1);
+
+        let mut results: Vec<Option<ToolResult<Vec<Content>>>> =
+            calls.iter().map(|_| None).collect();
+        let mut started = vec![false; calls.len()];
+        let mut running = FuturesUnordered::new();
+        loop {
+            // Start the calls that are no longer waiting on another, earliest requested first
+            for (i, call) in calls.iter().enumerate() {
+                if running.len() >= limit {
+                    break;
+                }
+                if !started[i] && dependencies[i].iter().all(|&j| results[j].is_some()) {
+                    started[i] = true;
+                    let output = dispatch(call.clone());
+                    running.push(async move { (i, output.await) });
+                }
+            }
+            match running.next().await {
+                Some((i, output)) => results[i] = Some(output),
+                None => break,
+            }
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.expect("every call runs once its dependencies finish"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    // Runs the calls, recording when each starts and finishes; a call sleeps for the
+    // milliseconds in its `sleep` argument
+    async fn run(
+        concurrency: &ToolConcurrency,
+        calls: &[(&str, u64)],
+    ) -> (Vec<String>, Vec<String>) {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let calls = calls
+            .iter()
+            .map(|(name, sleep)| ToolCall::new(*name, json!({ "sleep": sleep })))
+            .collect();
+        let outputs = concurrency
+            .run(calls, |call| {
+                let log = log.clone();
+                async move {
+                    log.lock().unwrap().push(format!("start {}", call.name));
+                    let sleep = call.arguments["sleep"].as_u64().unwrap();
+                    tokio::time::sleep(Duration::from_millis(sleep)).await;
+                    log.lock().unwrap().push(format!("end {}", call.name));
+                    Ok(vec![Content::text(call.name)])
+                }
+            })
+            .await;
+        let outputs = outputs
+            .into_iter()
+            .map(|output| output.unwrap()[0].as_text().unwrap().to_string())
+            .collect();
+        let log = log.lock().unwrap().clone();
+        (outputs, log)
+    }
+
+    #[tokio::test]
+    async fn test_results_keep_request_order() {
+        let (outputs, log) = run(
+            &ToolConcurrency::default(),
+            &[("a__slow", 50), ("b__fast", 0)],
+        )
+        .await;
+        assert_eq!(outputs, vec!["a__slow", "b__fast"]);
+        // Both started before either finished, and the fast one finished first
+        assert_eq!(
+            log,
+            vec![
+                "start a__slow",
+                "start b__fast",
+                "end b__fast",
+                "end a__slow"
+            ]
+        );
+
+        let sequential = ToolConcurrency {
+            max_concurrent: 1,
+            ..Default::default()
+        };
+        let (outputs, log) = run(&sequential, &[("a__slow", 20), ("b__fast", 0)]).await;
+        assert_eq!(outputs, vec!["a__slow", "b__fast"]);
+        assert_eq!(
+            log,
+            vec![
+                "start a__slow",
+                "end a__slow",
+                "start b__fast",
+                "end b__fast"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ordering_constraints() {
+        let concurrency = ToolConcurrency {
+            exclusive: vec!["developer__shell".to_string()],
+            ordered: vec!["memory".to_string()],
+            ..Default::default()
+        };
+
+        // The shell call waits for the read before it, and the write waits for the shell
+        let (_, log) = run(
+            &concurrency,
+            &[
+                ("developer__read", 20),
+                ("developer__shell", 0),
+                ("developer__write", 0),
+            ],
+        )
+        .await;
+        assert_eq!(
+            log,
+            vec![
+                "start developer__read",
+                "end developer__read",
+                "start developer__shell",
+                "end developer__shell",
+                "start developer__write",
+                "end developer__write",
+            ]
+        );
+
+        // Memory calls run in order, while the other extension runs alongside them
+        let (outputs, log) = run(
+            &concurrency,
+            &[
+                ("memory__save", 20),
+                ("other__tool", 0),
+                ("memory__load", 0),
+            ],
+        )
+        .await;
+        assert_eq!(outputs, vec!["memory__save", "other__tool", "memory__load"]);
+        assert_eq!(
+            log,
+            vec![
+                "start memory__save",
+                "start other__tool",
+                "end other__tool",
+                "end memory__save",
+                "start memory__load",
+                "end memory__load",
+            ]
+        );
+    }
+}
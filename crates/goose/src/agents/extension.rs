@@ -15,6 +15,8 @@ pub enum ExtensionError {
     ContextLimit,
     #[error("Transport error: {0}")]
     Transport(#[from] mcp_client::transport::Error),
+    #[error("No extension named {0}")]
+    UnknownExtension(String),
 }
 
 pub type ExtensionResult<T> = Result<T, ExtensionError>;
@@ -52,6 +54,21 @@ pub enum ExtensionConfig {
         uri: String,
         #[serde(default)]
         envs: Envs,
+        /// HTTP headers sent with the connection and every message, e.g. Authorization
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Streamable HTTP client with a single endpoint URI, used by current remote servers
+    #[serde(rename = "streamable_http")]
+    StreamableHttp {
+        /// The name used to identify this extension
+        name: String,
+        uri: String,
+        #[serde(default)]
+        envs: Envs,
+        /// HTTP headers sent with every request, e.g. Authorization
+        #[serde(default)]
+        headers: HashMap<String, String>,
     },
     /// Standard I/O client with command and arguments
     #[serde(rename = "stdio")]
@@ -85,6 +102,47 @@ impl ExtensionConfig {
             name: name.into(),
             uri: uri.into(),
             envs: Envs::default(),
+            headers: HashMap::new(),
+        }
+    }
+
+    pub fn streamable_http<S: Into<String>>(name: S, uri: S) -> Self {
+        Self::StreamableHttp {
+            name: name.into(),
+            uri: uri.into(),
+            envs: Envs::default(),
+            headers: HashMap::new(),
+        }
+    }
+
+    pub fn with_headers<I, K, V>(self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let headers = headers
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        match self {
+            Self::Sse {
+                name, uri, envs, ..
+            } => Self::Sse {
+                name,
+                uri,
+                envs,
+                headers,
+            },
+            Self::StreamableHttp {
+                name, uri, envs, ..
+            } => Self::StreamableHttp {
+                name,
+                uri,
+                envs,
+                headers,
+            },
+            other => other,
         }
     }
 
@@ -119,6 +177,7 @@ impl ExtensionConfig {
     pub fn name(&self) -> &str {
         match self {
             Self::Sse { name, .. } => name,
+            Self::StreamableHttp { name, .. } => name,
             Self::Stdio { name, .. } => name,
             Self::Builtin { name } => name,
         }
@@ -129,6 +188,9 @@ impl std::fmt::Display for ExtensionConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ExtensionConfig::Sse { name, uri, .. } => write!(f, "SSE({}: {})", name, uri),
+            ExtensionConfig::StreamableHttp { name, uri, .. } => {
+                write!(f, "StreamableHttp({}: {})", name, uri)
+            }
             ExtensionConfig::Stdio {
                 name, cmd, args, ..
             } => {
@@ -0,0 +1,307 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::providers::base::ProviderUsage;
+use crate::providers::errors::ProviderError;
+use mcp_core::content::Content;
+use mcp_core::resource::ResourceContents;
+use mcp_core::ToolError;
+
+/// Config key holding the `ToolLimits`
+pub const TOOL_LIMITS_CONFIG_KEY: &str = "GOOSE_TOOL_LIMITS";
+
+// The timeout every request to an MCP server had before there were tool limits
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+// Roughly 50k tokens, enough for large files without crowding out the rest of the context
+const DEFAULT_MAX_OUTPUT: usize = 200_000;
+
+/// Overrides for a single extension or tool; unset fields fall back to the wider setting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LimitOverride {
+    /// Seconds the tool may run for
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Bytes of output the tool may return, counting text and encoded images
+    #[serde(default)]
+    pub max_output: Option<usize>,
+}
+
+/// The limits that apply to a single tool call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectiveLimits {
+    pub timeout: Duration,
+    pub max_output: usize,
+}
+
+/// Limits on how long tools may run and how much output they may return
+///
+/// Overrides can be set for a whole extension or for a single (prefixed) tool; the tool
+/// setting wins over the extension one, which wins over the default.
+///
+/// By default a tool call is cancelled after 300 seconds, when the request to its server
+/// timed out before these limits existed. Tools that run longer, such as a shell running a
+/// build, need a higher timeout, which also lets the requests to their server run longer.
+///
+/// ```yaml
+/// GOOSE_TOOL_LIMITS:
+///   timeout: 120
+///   max_output: 100000
+///   tools:
+///     developer: { timeout: 600 }
+///     developer__screen_capture: { max_output: 2000000 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolLimits {
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    #[serde(default = "default_max_output")]
+    pub max_output: usize,
+    #[serde(default)]
+    pub tools: HashMap<String, LimitOverride>,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+fn default_max_output() -> usize {
+    DEFAULT_MAX_OUTPUT
+}
+
+impl Default for ToolLimits {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT_SECS,
+            max_output: DEFAULT_MAX_OUTPUT,
+            tools: HashMap::new(),
+        }
+    }
+}
+
+impl ToolLimits {
+    pub fn from_config() -> Self {
+        Config::global()
+            .get(TOOL_LIMITS_CONFIG_KEY)
+            .unwrap_or_default()
+    }
+
+    /// The limits for a tool, by its prefixed name
+    pub fn for_tool(&self, name: &str) -> EffectiveLimits {
+        let mut timeout = self.timeout;
+        let mut max_output = self.max_output;
+        let extension = name.split_once("__").map(|(extension, _)| extension);
+        for key in extension.into_iter().chain([name]) {
+            if let Some(limits) = self.tools.get(key) {
+                timeout = limits.timeout.unwrap_or(timeout);
+                max_output = limits.max_output.unwrap_or(max_output);
+            }
+        }
+        EffectiveLimits {
+            timeout: Duration::from_secs(timeout),
+            max_output,
+        }
+    }
+
+    /// The timeout of every request to an extension's server, long enough for its slowest
+    /// tool so that the tool's own timeout is the one that ends it
+    pub fn request_timeout(&self, extension: &str) -> Duration {
+        let prefix = format!("{}__", extension);
+        let longest = self
+            .tools
+            .iter()
+            .filter(|(key, _)| key.as_str() == extension || key.starts_with(&prefix))
+            .filter_map(|(_, limits)| limits.timeout)
+            .fold(self.timeout, u64::max);
+        Duration::from_secs(longest)
+    }
+}
+
+impl EffectiveLimits {
+    pub fn timed_out(&self, name: &str) -> ToolError {
+        ToolError::ExecutionError(format!(
+            "The call to {} timed out after {} seconds and was cancelled. \
+            Try a smaller or faster operation, or run long tasks in the background.",
+            name,
+            self.timeout.as_secs()
+        ))
+    }
+
+    /// Check the output of a tool, turning it into an error when it is too large
+    pub fn check_output(
+        &self,
+        name: &str,
+        output: Vec<Content>,
+    ) -> Result<Vec<Content>, ToolError> {
+        let size: usize = output.iter().map(content_size).sum();
+        if size <= self.max_output {
+            return Ok(output);
+        }
+        Err(ToolError::ExecutionError(format!(
+            "The output of {} was {} bytes, more than the limit of {} bytes, and was discarded. \
+            Narrow the request, for example by reading part of a file or filtering the output.",
+            name, size, self.max_output
+        )))
+    }
+}
+
+fn content_size(content: &Content) -> usize {
+    match content {
+        Content::Text(text) => text.text.len(),
+        Content::Image(image) => image.data.len(),
+        Content::Resource(resource) => match &resource.resource {
+            ResourceContents::TextResourceContents { text, .. } => text.len(),
+            ResourceContents::BlobResourceContents { blob, .. } => blob.len(),
+        },
+    }
+}
+
+/// A wall clock deadline and a token and cost budget that a caller attaches to a session
+///
+/// The agent checks them before every call to the provider and stops with
+/// `ProviderError::BudgetExceeded` once one is reached, and a call still running at the
+/// deadline is cancelled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionLimits {
+    #[serde(default)]
+    pub deadline: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub max_tokens: Option<i64>,
+    /// Maximum spend in USD
+    #[serde(default)]
+    pub max_cost: Option<f64>,
+}
+
+impl SessionLimits {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the usage of the session so far, before another call to the provider
+    pub fn check(&self, usage: &[ProviderUsage]) -> Result<(), ProviderError> {
+        if let Some(deadline) = self.deadline.filter(|deadline| Utc::now() >= *deadline) {
+            return Err(ProviderError::BudgetExceeded(format!(
+                "the session deadline of {} has passed",
+                deadline.to_rfc3339()
+            )));
+        }
+        let tokens: i64 = usage
+            .iter()
+            .map(|usage| usage.usage.total_tokens.unwrap_or(0) as i64)
+            .sum();
+        if let Some(max_tokens) = self.max_tokens.filter(|max| tokens >= *max) {
+            return Err(ProviderError::BudgetExceeded(format!(
+                "the session used {} tokens of its {} token budget",
+                tokens, max_tokens
+            )));
+        }
+        let cost: f64 = usage
+            .iter()
+            .filter_map(|usage| usage.cost.as_ref().map(|cost| cost.total))
+            .sum();
+        if let Some(max_cost) = self.max_cost.filter(|max| cost >= *max) {
+            return Err(ProviderError::BudgetExceeded(format!(
+                "the session spent ${:.4} of its ${:.4} budget",
+                cost, max_cost
+            )));
+        }
+        Ok(())
+    }
+
+    /// How long until the deadline, if there is one
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| (deadline - Utc::now()).to_std().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides() {
+        let limits: ToolLimits = serde_yaml::from_str(
+            r#"
+            timeout: 120
+            tools:
+              developer: { timeout: 600 }
+              developer__screen_capture: { max_output: 2000000 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            limits.for_tool("github__search"),
+            EffectiveLimits {
+                timeout: Duration::from_secs(120),
+                max_output: DEFAULT_MAX_OUTPUT,
+            }
+        );
+        assert_eq!(
+            limits.for_tool("developer__shell").timeout,
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            limits.for_tool("developer__screen_capture"),
+            EffectiveLimits {
+                timeout: Duration::from_secs(600),
+                max_output: 2_000_000,
+            }
+        );
+
+        assert_eq!(
+            limits.request_timeout("developer"),
+            Duration::from_secs(600)
+        );
+        assert_eq!(limits.request_timeout("github"), Duration::from_secs(120));
+        assert_eq!(
+            ToolLimits::default().request_timeout("developer"),
+            Duration::from_secs(300)
+        );
+    }
+
+    #[test]
+    fn test_check_output() {
+        let limits = EffectiveLimits {
+            timeout: Duration::from_secs(1),
+            max_output: 10,
+        };
+        assert!(limits
+            .check_output("tool", vec![Content::text("12345"), Content::text("67890")])
+            .is_ok());
+
+        let result = limits.check_output("tool", vec![Content::text("12345678901")]);
+        assert!(
+            matches!(result, Err(ToolError::ExecutionError(message)) if message.contains("11 bytes"))
+        );
+    }
+
+    #[test]
+    fn test_session_limits() {
+        let usage = |tokens| {
+            ProviderUsage::new(
+                "gpt-4o".to_string(),
+                crate::providers::base::Usage::new(None, None, Some(tokens)),
+            )
+        };
+        let limits = SessionLimits {
+            max_tokens: Some(1000),
+            ..Default::default()
+        };
+        assert!(limits.check(&[usage(400), usage(500)]).is_ok());
+        assert!(matches!(
+            limits.check(&[usage(400), usage(600)]),
+            Err(ProviderError::BudgetExceeded(_))
+        ));
+
+        let expired = SessionLimits {
+            deadline: Some(Utc::now() - chrono::Duration::seconds(1)),
+            ..Default::default()
+        };
+        assert!(expired.check(&[]).is_err());
+        assert_eq!(expired.remaining(), Some(Duration::ZERO));
+    }
+}
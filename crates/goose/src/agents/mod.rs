@@ -1,13 +1,26 @@
 mod agent;
 mod capabilities;
+pub mod concurrency;
 pub mod extension;
 mod factory;
+pub mod limits;
 mod permission_judge;
+pub mod plan;
+pub mod policy;
 mod reference;
+mod resources;
+pub mod sampling;
+pub mod subagent;
 mod truncate;
 
-pub use agent::Agent;
+pub use agent::{Agent, ReplyEvent};
 pub use capabilities::Capabilities;
+pub use concurrency::ToolConcurrency;
 pub use extension::ExtensionConfig;
 pub use factory::{register_agent, AgentFactory};
+pub use limits::{SessionLimits, ToolLimits};
 pub use permission_judge::detect_read_only_tools;
+pub use plan::{Plan, PlanStatus};
+pub use policy::{ConfirmationHandler, PolicyDecision, ToolPolicy};
+pub use resources::AttachedResource;
+pub use subagent::{Handoff, HandoffResult, Subagents};
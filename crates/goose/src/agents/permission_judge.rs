@@ -56,10 +56,10 @@ fn create_read_only_tool() -> Tool {
 /// Builds the message to be sent to the LLM for detecting read-only operations.
 fn create_check_messages(tool_requests: Vec<&ToolRequest>) -> Vec<Message> {
     let mut check_messages = vec![];
-    check_messages.push(Message {
-        role: mcp_core::Role::User,
-        created: Utc::now().timestamp(),
-        content: vec![MessageContent::Text(TextContent {
+    check_messages.push(Message::new(
+        mcp_core::Role::User,
+        Utc::now().timestamp(),
+        vec![MessageContent::Text(TextContent {
             text: format!(
                 "Here are the tool requests: {:?}\n\nAnalyze the tool requests and list the tools that perform read-only operations. \
                 \n\nGuidelines for Read-Only Operations: \
@@ -71,7 +71,7 @@ fn create_check_messages(tool_requests: Vec<&ToolRequest>) -> Vec<Message> {
             ),
             annotations: None,
         })],
-    });
+    ));
     check_messages
 }
 
@@ -163,10 +163,10 @@ mod tests {
             _tools: &[Tool],
         ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
             Ok((
-                Message {
-                    role: Role::Assistant,
-                    created: Utc::now().timestamp(),
-                    content: vec![MessageContent::ToolRequest(ToolRequest {
+                Message::new(
+                    Role::Assistant,
+                    Utc::now().timestamp(),
+                    vec![MessageContent::ToolRequest(ToolRequest {
                         id: "mock_tool_request".to_string(),
                         tool_call: ToolResult::Ok(ToolCall {
                             name: "platform__tool_by_tool_permission".to_string(),
@@ -175,7 +175,7 @@ mod tests {
                             }),
                         }),
                     })],
-                },
+                ),
                 ProviderUsage::new("mock".to_string(), Usage::default()),
             ))
         }
@@ -221,10 +221,10 @@ mod tests {
 
     #[test]
     fn test_extract_read_only_tools() {
-        let message = Message {
-            role: Role::Assistant,
-            created: Utc::now().timestamp(),
-            content: vec![MessageContent::ToolRequest(ToolRequest {
+        let message = Message::new(
+            Role::Assistant,
+            Utc::now().timestamp(),
+            vec![MessageContent::ToolRequest(ToolRequest {
                 id: "tool_2".to_string(),
                 tool_call: ToolResult::Ok(ToolCall {
                     name: "platform__tool_by_tool_permission".to_string(),
@@ -233,7 +233,7 @@ mod tests {
                     }),
                 }),
             })],
-        };
+        );
 
         let result = extract_read_only_tools(&message);
         assert!(result.is_some());
@@ -0,0 +1,236 @@
+use anyhow::{anyhow, Result};
+use mcp_core::Tool;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::LazyLock;
+
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::prompt_template::load_prompt_file;
+use crate::providers;
+use crate::providers::base::{Provider, ProviderUsage};
+
+/// Config key holding the `PlannerConfig`
+pub const PLANNER_CONFIG_KEY: &str = "GOOSE_PLANNER";
+
+/// Name under which a plan is put to the user for approval
+pub const PLAN_TOOL_NAME: &str = "platform__plan";
+
+// Trailing commas, which models copy from the examples in the prompt
+static TRAILING_COMMA: LazyLock<Regex> = LazyLock::new(|| Regex::new(r",(\s*[\]}])").unwrap());
+
+/// The model that drafts plans in `plan` mode, read from `GOOSE_PLANNER`
+///
+/// Planning needs less than carrying the plan out, so a cheaper model of the same or another
+/// provider can do it; unset fields fall back to the agent's provider and model.
+///
+/// ```yaml
+/// GOOSE_MODE: plan
+/// GOOSE_PLANNER:
+///   provider: openai
+///   model: gpt-4o-mini
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlannerConfig {
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl PlannerConfig {
+    pub fn from_config() -> Self {
+        Config::global().get(PLANNER_CONFIG_KEY).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStatus {
+    /// Waiting for the user to approve it
+    Proposed,
+    Approved,
+    Rejected,
+    /// The agent finished carrying it out
+    Completed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub description: String,
+}
+
+/// The steps the agent means to take for the user's last request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+    pub status: PlanStatus,
+}
+
+impl Plan {
+    pub fn new(steps: Vec<PlanStep>) -> Self {
+        Self {
+            steps,
+            status: PlanStatus::Proposed,
+        }
+    }
+
+    /// Read the JSON list of steps out of the planner's reply
+    pub fn parse(reply: &str) -> Result<Self> {
+        let start = reply.find('[');
+        let end = reply.rfind(']');
+        let json = match (start, end) {
+            (Some(start), Some(end)) if start < end => &reply[start..=end],
+            _ => return Err(anyhow!("The planner did not reply with a plan")),
+        };
+        let steps: Vec<PlanStep> = serde_json::from_str(&TRAILING_COMMA.replace_all(json, "$1"))
+            .map_err(|e| anyhow!("The planner replied with an invalid plan: {}", e))?;
+        if steps.is_empty() {
+            return Err(anyhow!("The planner replied with an empty plan"));
+        }
+        Ok(Self::new(steps))
+    }
+
+    /// The plan as a numbered markdown list
+    pub fn to_markdown(&self) -> String {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| format!("{}. {}", i + 1, step.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Add the approved plan to the system prompt of the agent carrying it out
+    pub fn extend_system_prompt(&self, system_prompt: &str) -> String {
+        format!(
+            "{}\n\n# Plan\n\nThe user approved this plan for their last request. Carry it out step \
+            by step, and tell the user if a step turns out to be wrong or impossible instead of \
+            quietly doing something else.\n\n{}",
+            system_prompt,
+            self.to_markdown()
+        )
+    }
+}
+
+/// Drafts plans with the planning model, which is shown the tools but cannot call them
+pub struct Planner {
+    provider: Box<dyn Provider>,
+}
+
+impl Planner {
+    pub fn new(provider: Box<dyn Provider>) -> Self {
+        Self { provider }
+    }
+
+    /// The planner of `GOOSE_PLANNER`, falling back to the agent's provider and model
+    ///
+    /// A planner provider configured without a model uses that provider's default model.
+    pub fn from_config(agent_provider: &dyn Provider) -> Result<Self> {
+        let config = PlannerConfig::from_config();
+        let name: String = match &config.provider {
+            Some(name) => name.clone(),
+            None => Config::global().get("GOOSE_PROVIDER")?,
+        };
+        let model = planner_model(&config, &name, agent_provider)?;
+        let provider: Box<dyn Provider> = providers::create(&name, ModelConfig::new(model))?;
+        Ok(Self::new(provider))
+    }
+
+    /// Plan the user's last request in the conversation
+    pub async fn plan(
+        &self,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Plan, ProviderUsage)> {
+        let system = load_prompt_file("plan.md", &json!({ "tools": tools }))?;
+        // No tools are offered, so planning has no side effects
+        let (reply, usage) = self.provider.complete(&system, messages, &[]).await?;
+        Ok((Plan::parse(&reply.as_concat_text())?, usage))
+    }
+}
+
+fn planner_model(
+    config: &PlannerConfig,
+    name: &str,
+    agent_provider: &dyn Provider,
+) -> Result<String> {
+    match (&config.provider, &config.model) {
+        (_, Some(model)) => Ok(model.clone()),
+        // Another provider is unlikely to serve the agent's model
+        (Some(_), None) => providers::providers()
+            .into_iter()
+            .find(|metadata| metadata.name == name)
+            .map(|metadata| metadata.default_model)
+            .ok_or_else(|| anyhow!("Unknown provider {}", name)),
+        (None, None) => Ok(agent_provider.get_model_config().model_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    #[test]
+    fn test_planner_model() {
+        let agent = MockProvider::new(ModelConfig::new("gpt-4o".to_string()));
+        let model = |provider: Option<&str>, model: Option<&str>| {
+            let config = PlannerConfig {
+                provider: provider.map(str::to_string),
+                model: model.map(str::to_string),
+            };
+            planner_model(&config, provider.unwrap_or("openai"), &agent)
+        };
+
+        assert_eq!(model(None, None).unwrap(), "gpt-4o");
+        assert_eq!(model(None, Some("o1")).unwrap(), "o1");
+        assert_eq!(
+            model(Some("anthropic"), None).unwrap(),
+            crate::providers::anthropic::ANTHROPIC_DEFAULT_MODEL
+        );
+        assert_eq!(
+            model(Some("anthropic"), Some("claude-3-opus")).unwrap(),
+            "claude-3-opus"
+        );
+        assert!(model(Some("no_such_provider"), None).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let plan = Plan::parse(indoc::indoc! {r#"
+            ```json
+            [
+                {"description": "list the files"},
+                {"description": "summarize them"},
+            ]
+            ```
+        "#})
+        .unwrap();
+        assert_eq!(plan.status, PlanStatus::Proposed);
+        assert_eq!(plan.to_markdown(), "1. list the files\n2. summarize them");
+
+        assert!(Plan::parse("I would rather not").is_err());
+        assert!(Plan::parse("[]").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plan_offers_no_tools() {
+        let provider =
+            MockProvider::default().with_text(r#"[{"description": "reply to the user"}]"#);
+        let planner = Planner::new(Box::new(provider.clone()));
+        let tools = [Tool::new("developer__shell", "Run a command", json!({}))];
+
+        let (plan, _) = planner
+            .plan(&[Message::user().with_text("hi")], &tools)
+            .await
+            .unwrap();
+        assert_eq!(plan.steps[0].description, "reply to the user");
+
+        let request = &provider.requests()[0];
+        assert!(request.tools.is_empty());
+        assert!(request.system.contains("developer__shell: Run a command"));
+    }
+}
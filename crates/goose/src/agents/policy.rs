@@ -0,0 +1,412 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::Config;
+use mcp_core::tool::ToolCall;
+use mcp_core::ToolError;
+
+/// Config key holding the `ToolPolicy`
+pub const TOOL_POLICY_CONFIG_KEY: &str = "GOOSE_TOOL_POLICY";
+
+/// What to do with a tool call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyDecision {
+    /// Run the tool without asking, even in approve mode
+    Allow,
+    /// Refuse to run the tool, telling the model why
+    Deny,
+    /// Run the tool only once the user confirms, even in auto mode
+    Ask,
+}
+
+/// A pattern for a single argument of a tool call; every field that is set must match
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArgumentPattern {
+    /// The argument is exactly this value
+    #[serde(default)]
+    pub equals: Option<Value>,
+    /// The argument is a string starting with this prefix
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// The argument is a path inside this directory, compared component by component after
+    /// resolving `.` and `..`, so `/home/me/project-x` is not inside `/home/me/project`
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// The argument is a string matching this glob, where `*` matches any run of characters
+    /// and `?` a single one
+    #[serde(default)]
+    pub glob: Option<String>,
+}
+
+impl ArgumentPattern {
+    fn matches(&self, value: Option<&Value>) -> bool {
+        let Some(value) = value else {
+            return false;
+        };
+        if self
+            .equals
+            .as_ref()
+            .is_some_and(|expected| expected != value)
+        {
+            return false;
+        }
+        let text = value.as_str();
+        if let Some(prefix) = &self.prefix {
+            if !text.is_some_and(|t| t.starts_with(prefix.as_str())) {
+                return false;
+            }
+        }
+        if let Some(prefix) = &self.path_prefix {
+            if !text
+                .is_some_and(|t| normalize(Path::new(t)).starts_with(normalize(Path::new(prefix))))
+            {
+                return false;
+            }
+        }
+        if let Some(glob) = &self.glob {
+            if !text.is_some_and(|t| glob_matches(glob, t)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A rule of the policy; every field that is set must match the tool call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    /// Glob for the tool name, matched with and without the extension prefix
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Glob for the extension (server) that provides the tool
+    #[serde(default)]
+    pub server: Option<String>,
+    /// Patterns for the top level arguments of the call, by name
+    #[serde(default)]
+    pub arguments: BTreeMap<String, ArgumentPattern>,
+    pub decision: PolicyDecision,
+    /// Shown to the user when asking, and to the model when the call is denied
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl PolicyRule {
+    fn matches(&self, server: Option<&str>, name: &str, call: &ToolCall) -> bool {
+        if let Some(tool) = &self.tool {
+            if !glob_matches(tool, &call.name) && !glob_matches(tool, name) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.server {
+            if !server.is_some_and(|s| glob_matches(pattern, s)) {
+                return false;
+            }
+        }
+        self.arguments
+            .iter()
+            .all(|(argument, pattern)| pattern.matches(call.arguments.get(argument)))
+    }
+}
+
+/// The outcome of checking a tool call against the policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyVerdict {
+    pub decision: PolicyDecision,
+    pub reason: Option<String>,
+    /// The index of the rule that matched, None when the default applied
+    pub rule: Option<usize>,
+}
+
+impl PolicyVerdict {
+    /// The error returned to the model for a denied call
+    pub fn denial(&self, call: &ToolCall) -> ToolError {
+        ToolError::ExecutionError(match &self.reason {
+            Some(reason) => format!(
+                "The call to {} was denied by the tool policy: {}",
+                call.name, reason
+            ),
+            None => format!("The call to {} was denied by the tool policy", call.name),
+        })
+    }
+}
+
+/// Asks the user whether a tool call may run, for calls the policy marks as `ask`
+#[async_trait]
+pub trait ConfirmationHandler: Send + Sync {
+    async fn confirm(&self, call: &ToolCall, verdict: &PolicyVerdict) -> bool;
+}
+
+/// Decides which tool calls may run before they are handed to the extension
+///
+/// Rules are checked in order and the first one that matches decides. Calls that match no
+/// rule get the default decision; without one, the policy has no say and the `GOOSE_MODE`
+/// applies as usual.
+///
+/// ```yaml
+/// GOOSE_TOOL_POLICY:
+///   rules:
+///     - tool: developer__shell
+///       arguments:
+///         command: { glob: "rm *" }
+///       decision: deny
+///       reason: Deleting files is not allowed
+///     - server: developer
+///       tool: text_editor
+///       arguments:
+///         path: { path_prefix: /home/me/project }
+///       decision: allow
+///     - server: github
+///       decision: ask
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+    #[serde(default)]
+    pub default: Option<PolicyDecision>,
+}
+
+impl ToolPolicy {
+    /// Read `GOOSE_TOOL_POLICY`, returning None when it is not set
+    pub fn from_config() -> Option<Self> {
+        Config::global().get(TOOL_POLICY_CONFIG_KEY).ok()
+    }
+
+    /// Check a call against the rules, None if no rule matches and there is no default
+    pub fn evaluate(&self, call: &ToolCall) -> Option<PolicyVerdict> {
+        // Tools are prefixed with the name of their extension, e.g. developer__shell
+        let (server, name) = match call.name.split_once("__") {
+            Some((server, name)) => (Some(server), name),
+            None => (None, call.name.as_str()),
+        };
+
+        self.rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(server, name, call))
+            .map(|(i, rule)| PolicyVerdict {
+                decision: rule.decision,
+                reason: rule.reason.clone(),
+                rule: Some(i),
+            })
+            .or_else(|| {
+                self.default.map(|decision| PolicyVerdict {
+                    decision,
+                    reason: None,
+                    rule: None,
+                })
+            })
+    }
+
+    /// Check a call, asking the handler when the policy says to
+    ///
+    /// For embedders that run tool calls themselves; returns the error to hand back to the
+    /// model when the call may not run.
+    pub async fn authorize(
+        &self,
+        call: &ToolCall,
+        handler: &dyn ConfirmationHandler,
+    ) -> Result<(), ToolError> {
+        let Some(verdict) = self.evaluate(call) else {
+            return Ok(());
+        };
+        match verdict.decision {
+            PolicyDecision::Allow => Ok(()),
+            PolicyDecision::Deny => Err(verdict.denial(call)),
+            PolicyDecision::Ask => {
+                if handler.confirm(call, &verdict).await {
+                    Ok(())
+                } else {
+                    Err(ToolError::ExecutionError(format!(
+                        "The user declined the call to {}",
+                        call.name
+                    )))
+                }
+            }
+        }
+    }
+}
+
+// Resolve `.` and `..` without touching the file system, which may not have the path yet
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and how much of the text it has taken so far
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn policy() -> ToolPolicy {
+        serde_yaml::from_str(
+            r#"
+            rules:
+              - tool: developer__shell
+                arguments:
+                  command: { glob: "rm *" }
+                decision: deny
+                reason: Deleting files is not allowed
+              - server: developer
+                tool: text_editor
+                arguments:
+                  path: { path_prefix: /home/me/project }
+                decision: allow
+              - server: github
+                decision: ask
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn call(name: &str, arguments: Value) -> ToolCall {
+        ToolCall::new(name, arguments)
+    }
+
+    #[test]
+    fn test_first_matching_rule_decides() {
+        let policy = policy();
+
+        let verdict = policy
+            .evaluate(&call("developer__shell", json!({"command": "rm -rf /"})))
+            .unwrap();
+        assert_eq!(verdict.decision, PolicyDecision::Deny);
+        assert_eq!(verdict.rule, Some(0));
+
+        let verdict = policy
+            .evaluate(&call(
+                "developer__text_editor",
+                json!({"path": "/home/me/project/src/main.rs"}),
+            ))
+            .unwrap();
+        assert_eq!(verdict.decision, PolicyDecision::Allow);
+
+        let verdict = policy
+            .evaluate(&call("github__create_issue", json!({})))
+            .unwrap();
+        assert_eq!(verdict.decision, PolicyDecision::Ask);
+
+        // Nothing matches and there is no default
+        assert!(policy
+            .evaluate(&call("developer__shell", json!({"command": "ls"})))
+            .is_none());
+    }
+
+    #[test]
+    fn test_path_prefix_compares_components() {
+        let policy = policy();
+        for path in [
+            "/home/me/project-x/secrets",
+            "/home/me/project/../.ssh/id_rsa",
+            "relative/path",
+        ] {
+            let verdict = policy.evaluate(&call("developer__text_editor", json!({"path": path})));
+            assert!(verdict.is_none(), "{} should not be allowed", path);
+        }
+    }
+
+    #[test]
+    fn test_default_decision() {
+        let policy = ToolPolicy {
+            default: Some(PolicyDecision::Ask),
+            ..policy()
+        };
+        let verdict = policy
+            .evaluate(&call("developer__shell", json!({"command": "ls"})))
+            .unwrap();
+        assert_eq!(verdict.decision, PolicyDecision::Ask);
+        assert_eq!(verdict.rule, None);
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(glob_matches("rm *", "rm -rf /"));
+        assert!(glob_matches("*__read_*", "developer__read_file"));
+        assert!(glob_matches("file?.txt", "file1.txt"));
+        assert!(!glob_matches("rm *", "git rm x"));
+        assert!(!glob_matches("file?.txt", "file10.txt"));
+    }
+
+    struct CountingHandler {
+        answer: bool,
+        asked: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ConfirmationHandler for CountingHandler {
+        async fn confirm(&self, _call: &ToolCall, _verdict: &PolicyVerdict) -> bool {
+            self.asked.fetch_add(1, Ordering::SeqCst);
+            self.answer
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorize() {
+        let policy = policy();
+        let handler = CountingHandler {
+            answer: false,
+            asked: AtomicUsize::new(0),
+        };
+
+        let denied = policy
+            .authorize(
+                &call("developer__shell", json!({"command": "rm x"})),
+                &handler,
+            )
+            .await;
+        assert!(matches!(denied, Err(ToolError::ExecutionError(m)) if m.contains("not allowed")));
+        assert_eq!(handler.asked.load(Ordering::SeqCst), 0);
+
+        let declined = policy
+            .authorize(&call("github__create_issue", json!({})), &handler)
+            .await;
+        assert!(declined.is_err());
+        assert_eq!(handler.asked.load(Ordering::SeqCst), 1);
+
+        let unmatched = policy
+            .authorize(
+                &call("developer__shell", json!({"command": "ls"})),
+                &handler,
+            )
+            .await;
+        assert!(unmatched.is_ok());
+    }
+}
@@ -1,34 +1,44 @@
 /// A simplified agent implementation used as a reference
 /// It makes no attempt to handle context limits, and cannot read resources
 use async_trait::async_trait;
-use futures::stream::BoxStream;
+use futures::stream::{BoxStream, StreamExt};
+use std::sync::Arc;
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, instrument};
 
-use super::Agent;
+use super::policy::ToolPolicy;
+use super::subagent::authorize;
+use super::{Agent, ReplyEvent};
 use crate::agents::capabilities::Capabilities;
 use crate::agents::extension::{ExtensionConfig, ExtensionResult};
+use crate::agents::limits::SessionLimits;
+use crate::agents::{AttachedResource, Plan};
 use crate::message::{Message, ToolRequest};
 use crate::providers::base::Provider;
 use crate::providers::base::ProviderUsage;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::streaming::MessageAssembler;
 use crate::register_agent;
 use crate::token_counter::TokenCounter;
+use crate::usage::{tool_token_usage, ToolTokenUsage};
 use indoc::indoc;
-use mcp_core::tool::Tool;
+use mcp_core::tool::{Tool, ToolCall};
+use mcp_core::{Resource, ToolResult};
 use serde_json::{json, Value};
 
 /// Reference implementation of an Agent
 pub struct ReferenceAgent {
     capabilities: Mutex<Capabilities>,
-    _token_counter: TokenCounter,
+    token_counter: Arc<TokenCounter>,
 }
 
 impl ReferenceAgent {
     pub fn new(provider: Box<dyn Provider>) -> Self {
-        let token_counter = TokenCounter::new(provider.get_model_config().tokenizer_name());
+        let token_counter = provider.get_model_config().token_counter();
         Self {
             capabilities: Mutex::new(Capabilities::new(provider)),
-            _token_counter: token_counter,
+            token_counter,
         }
     }
 }
@@ -56,6 +66,26 @@ impl Agent for ReferenceAgent {
             .expect("Failed to list extensions")
     }
 
+    async fn list_resources(&self, extension: &str) -> ExtensionResult<Vec<Resource>> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.list_extension_resources(extension).await
+    }
+
+    async fn attach_resource(&self, extension: &str, uri: &str) -> ExtensionResult<()> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.attach_resource(extension, uri).await
+    }
+
+    async fn detach_resource(&self, extension: &str, uri: &str) -> ExtensionResult<()> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.detach_resource(extension, uri).await
+    }
+
+    async fn attached_resources(&self) -> Vec<AttachedResource> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.attached_resources().await
+    }
+
     async fn passthrough(&self, _extension: &str, _request: Value) -> ExtensionResult<Value> {
         // TODO implement
         Ok(Value::Null)
@@ -66,13 +96,14 @@ impl Agent for ReferenceAgent {
     }
 
     #[instrument(skip(self, messages), fields(user_message))]
-    async fn reply(
+    async fn reply_cancellable(
         &self,
         messages: &[Message],
-    ) -> anyhow::Result<BoxStream<'_, anyhow::Result<Message>>> {
+        cancel: CancellationToken,
+    ) -> anyhow::Result<BoxStream<'_, anyhow::Result<ReplyEvent>>> {
         let mut messages = messages.to_vec();
         let reply_span = tracing::Span::current();
-        let mut capabilities = self.capabilities.lock().await;
+        let capabilities = self.capabilities.lock().await;
         let mut tools = capabilities.get_prefixed_tools().await?;
         // we add in the read_resource tool by default
         // TODO: make sure there is no collision with another extension's tool name
@@ -118,8 +149,10 @@ impl Agent for ReferenceAgent {
             tools.push(read_resource_tool);
             tools.push(list_resources_tool);
         }
+        tools.extend(capabilities.subagents().tool(0));
 
         let system_prompt = capabilities.get_system_prompt().await;
+        let policy = ToolPolicy::from_config();
 
         // Set the user_message field in the span instead of creating a new event
         if let Some(content) = messages
@@ -130,19 +163,27 @@ impl Agent for ReferenceAgent {
             debug!("user_message" = &content);
         }
 
-        Ok(Box::pin(async_stream::try_stream! {
+        let stop = cancel.clone();
+        let reply = async_stream::try_stream! {
             let _reply_guard = reply_span.enter();
             loop {
-                // Get completion from provider
-                let (response, usage) = capabilities.provider().complete(
-                    &system_prompt,
-                    &messages,
-                    &tools,
-                ).await?;
+                // Stream the completion from the provider, then assemble it
+                let mut stream = capabilities.stream(&system_prompt, &messages, &tools, &cancel).await?;
+                let mut assembler = MessageAssembler::default();
+                while let Some(delta) = stream.next().await {
+                    let delta = match delta {
+                        Err(ProviderError::Cancelled) => return,
+                        delta => delta?,
+                    };
+                    assembler.push(delta.clone());
+                    yield ReplyEvent::Delta(delta);
+                }
+                let model = capabilities.provider().get_model_config().model_name;
+                let (response, usage) = assembler.finish(&model);
                 capabilities.record_usage(usage).await;
 
                 // Yield the assistant's response
-                yield response.clone();
+                yield ReplyEvent::Message(response.clone());
 
                 tokio::task::yield_now().await;
 
@@ -156,32 +197,50 @@ impl Agent for ReferenceAgent {
                     break;
                 }
 
-                // Then dispatch each in parallel
-                let futures: Vec<_> = tool_requests
+                // Check them against the tool policy, which refuses `ask` as there is nobody
+                // to confirm a call here
+                let checked: Vec<ToolResult<ToolCall>> = tool_requests
                     .iter()
-                    .filter_map(|request| request.tool_call.clone().ok())
-                    .map(|tool_call| capabilities.dispatch_tool_call(tool_call))
+                    .map(|request| {
+                        let call = request.tool_call.clone()?;
+                        authorize(policy.as_ref(), &call).map(|()| call)
+                    })
                     .collect();
 
-                // Process all the futures in parallel but wait until all are finished
-                let outputs = futures::future::join_all(futures).await;
+                // Then dispatch them, in parallel where the tool concurrency settings allow
+                let tool_calls: Vec<ToolCall> = checked
+                    .iter()
+                    .filter_map(|call| call.clone().ok())
+                    .collect();
+                let mut outputs = capabilities.dispatch_tool_calls(tool_calls).await.into_iter();
 
                 // Create a message with the responses
                 let mut message_tool_response = Message::user();
                 // Now combine these into MessageContent::ToolResponse using the original ID
-                for (request, output) in tool_requests.iter().zip(outputs.into_iter()) {
+                for (request, checked) in tool_requests.iter().zip(checked) {
+                    let output = match checked {
+                        Ok(_) => outputs.next().expect("one output per permitted tool call"),
+                        Err(e) => Err(e),
+                    };
                     message_tool_response = message_tool_response.with_tool_response(
                         request.id.clone(),
                         output,
                     );
                 }
 
-                yield message_tool_response.clone();
+                yield ReplyEvent::Message(message_tool_response.clone());
 
                 messages.push(response);
                 messages.push(message_tool_response);
             }
-        }))
+        };
+        // Ending the stream drops whatever it is waiting on, like the tool calls
+        Ok(Box::pin(reply.take_until(stop.cancelled_owned())))
+    }
+
+    async fn plan(&self) -> Option<Plan> {
+        // The reference agent has no plan mode
+        None
     }
 
     async fn usage(&self) -> Vec<ProviderUsage> {
@@ -189,6 +248,10 @@ impl Agent for ReferenceAgent {
         capabilities.get_usage().await
     }
 
+    async fn tool_token_usage(&self, messages: &[Message]) -> Vec<ToolTokenUsage> {
+        tool_token_usage(messages, &self.token_counter)
+    }
+
     async fn extend_system_prompt(&mut self, extension: String) {
         let mut capabilities = self.capabilities.lock().await;
         capabilities.add_system_prompt_extension(extension);
@@ -198,6 +261,11 @@ impl Agent for ReferenceAgent {
         let mut capabilities = self.capabilities.lock().await;
         capabilities.set_system_prompt_override(template);
     }
+
+    async fn set_session_limits(&mut self, limits: SessionLimits) {
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities.set_session_limits(limits);
+    }
 }
 
 register_agent!("reference", ReferenceAgent);
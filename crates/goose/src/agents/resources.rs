@@ -0,0 +1,192 @@
+use mcp_core::protocol::{JsonRpcNotification, ReadResourceResult};
+use mcp_core::resource::ResourceContents;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+
+const RESOURCE_UPDATED: &str = "notifications/resources/updated";
+
+/// An extension resource whose contents are kept in the system prompt
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttachedResource {
+    pub extension: String,
+    pub uri: String,
+    pub text: String,
+    /// Whether the extension reports changes to the resource, so it is kept up to date
+    pub subscribed: bool,
+    // Changed since it was last read
+    #[serde(skip)]
+    stale: bool,
+}
+
+impl AttachedResource {
+    pub fn new(extension: &str, uri: &str, text: String, subscribed: bool) -> Self {
+        Self {
+            extension: extension.to_string(),
+            uri: uri.to_string(),
+            text,
+            subscribed,
+            stale: false,
+        }
+    }
+}
+
+/// The resources attached to the conversation, shared with the tasks that watch for changes
+#[derive(Clone, Default)]
+pub struct AttachedResources {
+    resources: Arc<Mutex<Vec<AttachedResource>>>,
+}
+
+impl AttachedResources {
+    pub async fn list(&self) -> Vec<AttachedResource> {
+        self.resources.lock().await.clone()
+    }
+
+    /// Add a resource, replacing an earlier copy of it
+    pub async fn insert(&self, resource: AttachedResource) {
+        let mut resources = self.resources.lock().await;
+        match resources
+            .iter_mut()
+            .find(|r| r.extension == resource.extension && r.uri == resource.uri)
+        {
+            Some(existing) => *existing = resource,
+            None => resources.push(resource),
+        }
+    }
+
+    pub async fn remove(&self, extension: &str, uri: &str) -> Option<AttachedResource> {
+        let mut resources = self.resources.lock().await;
+        let index = resources
+            .iter()
+            .position(|r| r.extension == extension && r.uri == uri)?;
+        Some(resources.remove(index))
+    }
+
+    pub async fn remove_extension(&self, extension: &str) {
+        self.resources
+            .lock()
+            .await
+            .retain(|r| r.extension != extension);
+    }
+
+    /// The (extension, uri) of every resource that changed since it was read
+    pub async fn stale(&self) -> Vec<(String, String)> {
+        self.resources
+            .lock()
+            .await
+            .iter()
+            .filter(|r| r.stale)
+            .map(|r| (r.extension.clone(), r.uri.clone()))
+            .collect()
+    }
+
+    /// Store freshly read contents
+    pub async fn update(&self, extension: &str, uri: &str, text: String) {
+        let mut resources = self.resources.lock().await;
+        if let Some(resource) = resources
+            .iter_mut()
+            .find(|r| r.extension == extension && r.uri == uri)
+        {
+            resource.text = text;
+            resource.stale = false;
+        }
+    }
+
+    async fn mark_stale(&self, extension: &str, uri: Option<&str>) {
+        for resource in self.resources.lock().await.iter_mut() {
+            if resource.extension == extension && uri.is_none_or(|uri| resource.uri == uri) {
+                resource.stale = true;
+            }
+        }
+    }
+
+    /// Mark resources of the extension stale as it reports changes, until its notifications end
+    pub fn watch(
+        &self,
+        extension: String,
+        mut notifications: broadcast::Receiver<JsonRpcNotification>,
+    ) -> JoinHandle<()> {
+        let resources = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match notifications.recv().await {
+                    Ok(notification) if notification.method == RESOURCE_UPDATED => {
+                        let uri = notification
+                            .params
+                            .as_ref()
+                            .and_then(|p| p.get("uri"))
+                            .and_then(|u| u.as_str());
+                        if let Some(uri) = uri {
+                            resources.mark_stale(&extension, Some(uri)).await;
+                        }
+                    }
+                    Ok(_) => {}
+                    // Some updates were missed, so any of them may have changed
+                    Err(RecvError::Lagged(_)) => resources.mark_stale(&extension, None).await,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+/// The text of a resource; binary contents are only described, to keep them out of the context
+pub fn resource_text(result: ReadResourceResult) -> String {
+    result
+        .contents
+        .into_iter()
+        .map(|content| match content {
+            ResourceContents::TextResourceContents { text, .. } => text,
+            ResourceContents::BlobResourceContents {
+                mime_type, blob, ..
+            } => format!(
+                "[binary {} content, {} bytes base64 encoded]",
+                mime_type.as_deref().unwrap_or("unknown"),
+                blob.len()
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn updated(uri: &str) -> JsonRpcNotification {
+        JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: RESOURCE_UPDATED.to_string(),
+            params: Some(json!({ "uri": uri })),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_marks_updated_resources_stale() {
+        let resources = AttachedResources::default();
+        resources
+            .insert(AttachedResource::new("docs", "file:///a", "a".into(), true))
+            .await;
+        resources
+            .insert(AttachedResource::new("docs", "file:///b", "b".into(), true))
+            .await;
+
+        let (sender, receiver) = broadcast::channel(8);
+        let watcher = resources.watch("docs".to_string(), receiver);
+        sender.send(updated("file:///b")).unwrap();
+        drop(sender);
+        watcher.await.unwrap();
+
+        assert_eq!(
+            resources.stale().await,
+            vec![("docs".to_string(), "file:///b".to_string())]
+        );
+
+        resources.update("docs", "file:///b", "b2".into()).await;
+        assert!(resources.stale().await.is_empty());
+        assert_eq!(resources.list().await[1].text, "b2");
+    }
+}
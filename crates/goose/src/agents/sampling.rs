@@ -0,0 +1,496 @@
+use mcp_client::transport::ServerRequests;
+use mcp_core::protocol::{
+    CreateMessageParams, CreateMessageResult, ErrorData, JsonRpcError, JsonRpcMessage,
+    JsonRpcRequest, JsonRpcResponse, ModelPreferences, SamplingMessage, INTERNAL_ERROR,
+    INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND,
+};
+use mcp_core::{Content, Role, ToolCall};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use super::policy::{PolicyDecision, ToolPolicy};
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers;
+use crate::providers::base::{Provider, ProviderUsage};
+use crate::providers::pricing::get_pricing;
+
+/// Config key holding the `SamplingConfig`
+pub const SAMPLING_CONFIG_KEY: &str = "GOOSE_SAMPLING";
+
+const CREATE_MESSAGE: &str = "sampling/createMessage";
+
+/// How extensions may ask for completions through MCP sampling, read from `GOOSE_SAMPLING`
+///
+/// The agent's model answers unless a model hint of the request names one of `models`, which
+/// then answers through the same provider. Without a matching hint, the request's priorities
+/// choose by price among the models with known pricing: the cheapest when cost or speed
+/// outweighs intelligence, the most expensive when intelligence does. The request's
+/// `maxTokens` and `temperature` apply whichever model answers. Sampling goes through the tool policy as a call to
+/// the extension's `sampling` tool, e.g. `server: github, tool: sampling, decision: deny`, and
+/// counts towards the budget like any other completion. The conversation is never shared
+/// with the extension, whatever context the request asks to include.
+///
+/// ```yaml
+/// GOOSE_SAMPLING:
+///   enabled: true
+///   models: [gpt-4o-mini, o3-mini]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Other models of the configured provider that extensions may ask for
+    #[serde(default)]
+    pub models: Vec<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            models: Vec::new(),
+        }
+    }
+}
+
+impl SamplingConfig {
+    pub fn from_config() -> Self {
+        Config::global()
+            .get(SAMPLING_CONFIG_KEY)
+            .unwrap_or_default()
+    }
+}
+
+/// Answers the sampling requests of an extension with the agent's provider
+#[derive(Clone)]
+pub struct SamplingHandler {
+    extension: String,
+    provider: Arc<dyn Provider>,
+    // The provider to create with other models or settings, that of `GOOSE_PROVIDER`
+    provider_name: Option<String>,
+    provider_usage: Arc<Mutex<Vec<ProviderUsage>>>,
+    config: SamplingConfig,
+    policy: Option<ToolPolicy>,
+}
+
+impl SamplingHandler {
+    pub fn new(
+        extension: &str,
+        provider: Arc<dyn Provider>,
+        provider_usage: Arc<Mutex<Vec<ProviderUsage>>>,
+    ) -> Self {
+        Self {
+            extension: extension.to_string(),
+            provider,
+            provider_name: Config::global().get("GOOSE_PROVIDER").ok(),
+            provider_usage,
+            config: SamplingConfig::from_config(),
+            policy: ToolPolicy::from_config(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Answer the extension's requests until its transport closes
+    pub fn serve(self, mut requests: ServerRequests) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let responder = requests.responder();
+            while let Some(request) = requests.recv().await {
+                let handler = self.clone();
+                let responder = responder.clone();
+                // Completions take a while, so each request gets its own task
+                tokio::spawn(async move {
+                    let method = request.method.clone();
+                    let response = handler.handle(request).await;
+                    if let Err(e) = responder.respond(response).await {
+                        warn!(
+                            "Failed to answer {} from {}: {}",
+                            method, handler.extension, e
+                        );
+                    }
+                });
+            }
+        })
+    }
+
+    async fn handle(&self, request: JsonRpcRequest) -> JsonRpcMessage {
+        debug!(request = ?request, "Request from {}", self.extension);
+        let result = match request.method.as_str() {
+            "ping" => Ok(json!({})),
+            CREATE_MESSAGE => match serde_json::from_value(request.params.unwrap_or(Value::Null)) {
+                Ok(params) => self
+                    .create_message(params)
+                    .await
+                    .and_then(|result| serde_json::to_value(result).map_err(internal_error)),
+                Err(e) => Err(error_data(INVALID_PARAMS, e.to_string())),
+            },
+            method => Err(error_data(
+                METHOD_NOT_FOUND,
+                format!("Unsupported method {}", method),
+            )),
+        };
+
+        match result {
+            Ok(result) => JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                result: Some(result),
+                error: None,
+            }),
+            Err(error) => JsonRpcMessage::Error(JsonRpcError {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                error,
+            }),
+        }
+    }
+
+    /// Complete a sampling request, if the config and the tool policy allow it
+    pub async fn create_message(
+        &self,
+        params: CreateMessageParams,
+    ) -> Result<CreateMessageResult, ErrorData> {
+        if !self.config.enabled {
+            return Err(error_data(
+                INVALID_REQUEST,
+                "Sampling is disabled".to_string(),
+            ));
+        }
+
+        let model = self.choose_model(params.model_preferences.as_ref());
+        self.authorize(&model)?;
+
+        let messages: Vec<Message> = params.messages.into_iter().map(to_message).collect();
+        let system = params.system_prompt.unwrap_or_default();
+        let current = self.provider.get_model_config();
+        let model_config =
+            request_model_config(&current, model, params.temperature, params.max_tokens);
+        let provider: Arc<dyn Provider> = if model_config.model_name == current.model_name
+            && model_config.temperature == current.temperature
+            && model_config.max_tokens == current.max_tokens
+        {
+            Arc::clone(&self.provider)
+        } else {
+            let name = self.provider_name.as_deref().ok_or_else(|| {
+                internal_error("GOOSE_PROVIDER is not set, so the request can't be answered")
+            })?;
+            let provider: Box<dyn Provider> =
+                providers::create(name, model_config).map_err(internal_error)?;
+            Arc::from(provider)
+        };
+
+        let (message, usage) = provider
+            .complete(&system, &messages, &[])
+            .await
+            .map_err(internal_error)?;
+        self.provider_usage.lock().await.push(usage.clone());
+
+        Ok(CreateMessageResult {
+            role: Role::Assistant,
+            content: Content::text(message.as_concat_text()),
+            model: usage.model,
+            stop_reason: Some("endTurn".to_string()),
+        })
+    }
+
+    /// The first hint that names the agent's model or one of the configured ones picks it,
+    /// then the priorities, otherwise the agent's model answers
+    fn choose_model(&self, preferences: Option<&ModelPreferences>) -> String {
+        let current = self.provider.get_model_config().model_name;
+        let Some(preferences) = preferences else {
+            return current;
+        };
+        let candidates = || std::iter::once(&current).chain(&self.config.models);
+        for hint in preferences
+            .hints
+            .iter()
+            .filter_map(|hint| hint.name.as_deref())
+        {
+            if let Some(model) = candidates().find(|model| model.contains(hint)) {
+                return model.clone();
+            }
+        }
+
+        let thrift = preferences
+            .cost_priority
+            .unwrap_or(0.0)
+            .max(preferences.speed_priority.unwrap_or(0.0));
+        let intelligence = preferences.intelligence_priority.unwrap_or(0.0);
+        let priced = candidates()
+            .filter_map(|model| get_pricing(model).map(|pricing| (model, pricing.input)));
+        let chosen = match thrift.total_cmp(&intelligence) {
+            Ordering::Greater => priced.min_by(|(_, a), (_, b)| a.total_cmp(b)),
+            Ordering::Less => priced.max_by(|(_, a), (_, b)| a.total_cmp(b)),
+            Ordering::Equal => None,
+        };
+        chosen.map(|(model, _)| model.clone()).unwrap_or(current)
+    }
+
+    // Requests arrive while a tool runs, so there is no way to ask the user; `ask` refuses
+    fn authorize(&self, model: &str) -> Result<(), ErrorData> {
+        let Some(policy) = &self.policy else {
+            return Ok(());
+        };
+        let call = ToolCall::new(
+            format!("{}__sampling", self.extension),
+            json!({ "model": model }),
+        );
+        match policy.evaluate(&call) {
+            Some(verdict) if verdict.decision != PolicyDecision::Allow => Err(error_data(
+                INVALID_REQUEST,
+                match verdict.reason {
+                    Some(reason) => format!("Sampling was denied by the tool policy: {}", reason),
+                    None => "Sampling was denied by the tool policy".to_string(),
+                },
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+// The agent's settings for its own model, with the request's limits and temperature
+fn request_model_config(
+    current: &ModelConfig,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: i32,
+) -> ModelConfig {
+    let base = if model == current.model_name {
+        current.clone()
+    } else {
+        ModelConfig::new(model)
+    };
+    let temperature = temperature.or(base.temperature);
+    base.with_temperature(temperature)
+        .with_max_tokens(Some(max_tokens))
+}
+
+fn to_message(message: SamplingMessage) -> Message {
+    let base = match message.role {
+        Role::User => Message::user(),
+        Role::Assistant => Message::assistant(),
+    };
+    match message.content {
+        Content::Text(text) => base.with_text(text.text),
+        Content::Image(image) => base.with_image(image.data, image.mime_type),
+        Content::Resource(resource) => base.with_text(resource.get_text()),
+    }
+}
+
+fn error_data(code: i32, message: String) -> ErrorData {
+    ErrorData {
+        code,
+        message,
+        data: None,
+    }
+}
+
+fn internal_error(error: impl std::fmt::Display) -> ErrorData {
+    error_data(INTERNAL_ERROR, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::policy::PolicyRule;
+    use crate::providers::base::{ProviderMetadata, Usage};
+    use crate::providers::errors::ProviderError;
+    use crate::providers::mock::MockProvider;
+    use mcp_core::protocol::ModelHint;
+    use mcp_core::tool::Tool;
+
+    struct EchoProvider;
+
+    #[async_trait::async_trait]
+    impl Provider for EchoProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("gpt-4o".to_string()).with_max_tokens(Some(100))
+        }
+
+        async fn complete(
+            &self,
+            system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text(format!(
+                    "{}: {}",
+                    system,
+                    messages.last().unwrap().as_concat_text()
+                )),
+                ProviderUsage::new("gpt-4o".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    fn handler(config: SamplingConfig, policy: Option<ToolPolicy>) -> SamplingHandler {
+        SamplingHandler {
+            extension: "search".to_string(),
+            provider: Arc::new(EchoProvider),
+            provider_name: None,
+            provider_usage: Arc::new(Mutex::new(Vec::new())),
+            config,
+            policy,
+        }
+    }
+
+    fn params(hints: &[&str]) -> CreateMessageParams {
+        CreateMessageParams {
+            messages: vec![SamplingMessage {
+                role: Role::User,
+                content: Content::text("summarize this"),
+            }],
+            model_preferences: Some(ModelPreferences {
+                hints: hints
+                    .iter()
+                    .map(|name| ModelHint {
+                        name: Some(name.to_string()),
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+            system_prompt: Some("Be brief".to_string()),
+            include_context: None,
+            temperature: None,
+            max_tokens: 100,
+            stop_sequences: vec![],
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_message() {
+        let handler = handler(SamplingConfig::default(), None);
+        let result = handler.create_message(params(&[])).await.unwrap();
+        assert_eq!(result.role, Role::Assistant);
+        assert_eq!(result.content, Content::text("Be brief: summarize this"));
+        assert_eq!(result.model, "gpt-4o");
+        assert_eq!(handler.provider_usage.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_settings_apply_to_agent_model() {
+        let mock = MockProvider::default().with_text("short");
+        mock.register("sampling-settings");
+        let handler = SamplingHandler {
+            provider_name: Some("sampling-settings".to_string()),
+            ..handler(SamplingConfig::default(), None)
+        };
+        let result = handler
+            .create_message(CreateMessageParams {
+                max_tokens: 20,
+                temperature: Some(0.2),
+                ..params(&[])
+            })
+            .await;
+        MockProvider::unregister("sampling-settings");
+
+        // The agent's model, created again since its own provider would ignore the limit
+        assert_eq!(result.unwrap().model, "gpt-4o");
+        assert_eq!(mock.requests().len(), 1);
+
+        let config = request_model_config(
+            &EchoProvider.get_model_config(),
+            "gpt-4o".to_string(),
+            None,
+            20,
+        );
+        assert_eq!(config.max_tokens, Some(20));
+        assert_eq!(config.context_limit, Some(128_000));
+        let other = request_model_config(
+            &EchoProvider.get_model_config(),
+            "gpt-4o-mini".to_string(),
+            Some(0.2),
+            20,
+        );
+        assert_eq!(other.model_name, "gpt-4o-mini");
+        assert_eq!(other.temperature, Some(0.2));
+    }
+
+    #[test]
+    fn test_choose_model_from_priorities() {
+        let handler = handler(
+            SamplingConfig {
+                models: vec!["gpt-4o-mini".to_string()],
+                ..Default::default()
+            },
+            None,
+        );
+        let chosen = |cost: Option<f32>, intelligence: Option<f32>| {
+            handler.choose_model(Some(&ModelPreferences {
+                cost_priority: cost,
+                intelligence_priority: intelligence,
+                ..Default::default()
+            }))
+        };
+
+        assert_eq!(chosen(Some(0.8), Some(0.2)), "gpt-4o-mini");
+        assert_eq!(chosen(Some(0.2), Some(0.8)), "gpt-4o");
+        assert_eq!(chosen(None, None), "gpt-4o");
+    }
+
+    #[test]
+    fn test_choose_model_from_hints() {
+        let handler = handler(
+            SamplingConfig {
+                models: vec!["gpt-4o-mini".to_string()],
+                ..Default::default()
+            },
+            None,
+        );
+        let chosen =
+            |hints: &[&str]| handler.choose_model(params(hints).model_preferences.as_ref());
+
+        assert_eq!(chosen(&[]), "gpt-4o");
+        assert_eq!(chosen(&["claude", "mini"]), "gpt-4o-mini");
+        // The agent's own model comes first when a hint matches several
+        assert_eq!(chosen(&["gpt-4o"]), "gpt-4o");
+        assert_eq!(chosen(&["claude"]), "gpt-4o");
+    }
+
+    #[tokio::test]
+    async fn test_sampling_follows_policy() {
+        let policy = ToolPolicy {
+            rules: vec![PolicyRule {
+                server: Some("search".to_string()),
+                tool: Some("sampling".to_string()),
+                arguments: Default::default(),
+                decision: PolicyDecision::Ask,
+                reason: Some("costs money".to_string()),
+            }],
+            default: None,
+        };
+        let error = handler(SamplingConfig::default(), Some(policy))
+            .create_message(params(&[]))
+            .await
+            .unwrap_err();
+        assert!(error.message.contains("costs money"));
+
+        let disabled = SamplingConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(handler(disabled, None)
+            .create_message(params(&[]))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,527 @@
+use chrono::Utc;
+use indoc::formatdoc;
+use mcp_core::{Content, Tool, ToolCall, ToolError, ToolResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use tracing::{debug, warn};
+
+use super::capabilities::Capabilities;
+use super::policy::{PolicyDecision, ToolPolicy};
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::prompt_template::load_prompt_file;
+use crate::providers;
+use crate::providers::base::{Provider, Usage};
+
+/// Config key holding the `Subagents`
+pub const SUBAGENTS_CONFIG_KEY: &str = "GOOSE_SUBAGENTS";
+
+/// The platform tool through which the agent hands tasks to its subagents
+pub const DELEGATE_TOOL_NAME: &str = "platform__delegate";
+
+const DEFAULT_MAX_DEPTH: usize = 1;
+const DEFAULT_MAX_TURNS: usize = 20;
+
+/// A named agent the agent can hand tasks to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubagentConfig {
+    /// What the subagent is good at, shown to the agent choosing whom to delegate to
+    pub description: String,
+    /// Provider to use instead of the agent's, with its default model unless `model` is set
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Added to the subagent's system prompt
+    #[serde(default)]
+    pub instructions: Option<String>,
+    /// Extensions whose tools the subagent may use, all of the agent's when unset
+    #[serde(default)]
+    pub extensions: Option<Vec<String>>,
+}
+
+/// The subagents the agent can delegate to, read from `GOOSE_SUBAGENTS`
+///
+/// Subagents share the agent's extensions, limited to the listed ones, but run their own
+/// conversation with their own model until they reply without calling a tool. Their usage
+/// counts towards the agent's. `max_depth` is how many levels of subagents there can be, so
+/// at the default of 1 subagents cannot delegate further, and `max_turns` caps the
+/// completions of each handoff.
+///
+/// Subagents never ask for confirmation: approving the handoff approves the work, and tool
+/// calls the tool policy wants confirmed are refused. The handoff is a tool call, so set
+/// its timeout with `GOOSE_TOOL_LIMITS` when subagents need more time than other tools.
+///
+/// ```yaml
+/// GOOSE_SUBAGENTS:
+///   max_depth: 1
+///   max_turns: 20
+///   agents:
+///     researcher:
+///       description: Searches the web and summarizes what it finds
+///       provider: openai
+///       model: gpt-4o-mini
+///       extensions: [fetch]
+///     reviewer:
+///       description: Reviews code changes for bugs
+///       instructions: Only report problems you are sure about.
+///       extensions: [developer]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subagents {
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    #[serde(default = "default_max_turns")]
+    pub max_turns: usize,
+    #[serde(default)]
+    pub agents: BTreeMap<String, SubagentConfig>,
+}
+
+fn default_max_depth() -> usize {
+    DEFAULT_MAX_DEPTH
+}
+
+fn default_max_turns() -> usize {
+    DEFAULT_MAX_TURNS
+}
+
+impl Default for Subagents {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_turns: DEFAULT_MAX_TURNS,
+            agents: BTreeMap::new(),
+        }
+    }
+}
+
+impl Subagents {
+    pub fn from_config() -> Self {
+        Config::global()
+            .get(SUBAGENTS_CONFIG_KEY)
+            .unwrap_or_default()
+    }
+
+    /// The delegate tool for an agent at `depth`, where the top-level agent is at 0, if it
+    /// may still delegate
+    pub fn tool(&self, depth: usize) -> Option<Tool> {
+        if self.agents.is_empty() || depth >= self.max_depth {
+            return None;
+        }
+
+        let agents: Vec<String> = self
+            .agents
+            .iter()
+            .map(|(name, agent)| format!("- {}: {}", name, agent.description))
+            .collect();
+        Some(Tool::new(
+            DELEGATE_TOOL_NAME.to_string(),
+            formatdoc! {r#"
+                Hand a task to a subagent and wait for its result.
+
+                The subagent starts without this conversation, so describe the task fully and
+                pass along anything it needs to know as context. It works on the task on its
+                own and replies with the result. Delegate tasks that suit a subagent better
+                or that would otherwise fill this conversation with intermediate steps.
+
+                The subagents are:
+                {}
+            "#, agents.join("\n")},
+            json!({
+                "type": "object",
+                "required": ["agent", "task"],
+                "properties": {
+                    "agent": {
+                        "type": "string",
+                        "enum": self.agents.keys().collect::<Vec<_>>(),
+                        "description": "Name of the subagent"
+                    },
+                    "task": {"type": "string", "description": "What the subagent should do"},
+                    "context": {"type": "string", "description": "Optional background for the task"}
+                }
+            }),
+        ))
+    }
+}
+
+/// The message that hands a task to a subagent, as the arguments of the delegate tool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handoff {
+    pub agent: String,
+    pub task: String,
+    #[serde(default)]
+    pub context: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandoffStatus {
+    Completed,
+    /// The subagent ran out of turns; the result is its last reply
+    TurnLimit,
+}
+
+/// The message a subagent hands back, as the output of the delegate tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandoffResult {
+    pub agent: String,
+    pub model: String,
+    pub status: HandoffStatus,
+    pub result: String,
+    pub turns: usize,
+    /// Tokens used by the subagent and any subagents it delegated to
+    pub usage: Usage,
+}
+
+type HandoffFuture<'a> = Pin<Box<dyn Future<Output = ToolResult<HandoffResult>> + Send + 'a>>;
+
+/// Run the handoff in `arguments` for an agent at `depth`
+pub async fn delegate(
+    capabilities: &Capabilities,
+    arguments: Value,
+    depth: usize,
+) -> ToolResult<Vec<Content>> {
+    let handoff: Handoff = serde_json::from_value(arguments)
+        .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
+    let result = run(capabilities, handoff, depth + 1).await?;
+    let text = serde_json::to_string_pretty(&result)
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+    Ok(vec![Content::text(text)])
+}
+
+// Boxed, as subagents may delegate in turn
+fn run(capabilities: &Capabilities, handoff: Handoff, depth: usize) -> HandoffFuture<'_> {
+    Box::pin(async move {
+        let subagents = capabilities.subagents();
+        if depth > subagents.max_depth {
+            return Err(ToolError::ExecutionError(format!(
+                "Subagents cannot be nested more than {} levels deep",
+                subagents.max_depth
+            )));
+        }
+        let config = subagents.agents.get(&handoff.agent).ok_or_else(|| {
+            ToolError::InvalidParameters(format!(
+                "No subagent named {}, the subagents are: {}",
+                handoff.agent,
+                subagents
+                    .agents
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?;
+
+        // Use the agent's provider unless the subagent has its own
+        let own_provider = subagent_provider(config, capabilities.provider())?;
+        let provider = own_provider.as_deref().unwrap_or(capabilities.provider());
+        let model = provider.get_model_config().model_name;
+
+        let mut tools: Vec<Tool> = capabilities
+            .get_prefixed_tools()
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+            .into_iter()
+            .filter(|tool| allows(config, &tool.name))
+            .collect();
+        tools.extend(subagents.tool(depth));
+
+        let system_prompt = load_prompt_file(
+            "subagent.md",
+            &json!({
+                "name": handoff.agent,
+                "description": config.description,
+                "instructions": config.instructions,
+                "current_date_time": Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            }),
+        )
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        let task = match &handoff.context {
+            Some(context) => format!("{}\n\n# Context\n\n{}", handoff.task, context),
+            None => handoff.task.clone(),
+        };
+        let mut messages = vec![Message::user().with_text(task)];
+        let policy = ToolPolicy::from_config();
+        let mut usage = Usage::default();
+
+        debug!(
+            "Handing off to {} ({}) at depth {}",
+            handoff.agent, model, depth
+        );
+        for turn in 1..=subagents.max_turns {
+            let (response, turn_usage) = provider
+                .complete(&system_prompt, &messages, &tools)
+                .await
+                .map_err(|e| {
+                    ToolError::ExecutionError(format!("Subagent {} failed: {}", handoff.agent, e))
+                })?;
+            add_usage(&mut usage, &turn_usage.usage);
+            capabilities.record_usage(turn_usage).await;
+
+            let requests: Vec<_> = response
+                .content
+                .iter()
+                .filter_map(|content| content.as_tool_request())
+                .cloned()
+                .collect();
+            if requests.is_empty() || turn == subagents.max_turns {
+                let status = if requests.is_empty() {
+                    HandoffStatus::Completed
+                } else {
+                    warn!("Subagent {} ran out of turns", handoff.agent);
+                    HandoffStatus::TurnLimit
+                };
+                return Ok(HandoffResult {
+                    agent: handoff.agent,
+                    model,
+                    status,
+                    result: response.as_concat_text(),
+                    turns: turn,
+                    usage,
+                });
+            }
+
+            let mut tool_responses = Message::user();
+            for request in requests {
+                let output = match request.tool_call {
+                    Ok(call) if !tools.iter().any(|tool| tool.name == call.name) => {
+                        Err(ToolError::NotFound(call.name))
+                    }
+                    Ok(call) if call.name == DELEGATE_TOOL_NAME => {
+                        match serde_json::from_value(call.arguments) {
+                            Ok(nested) => {
+                                run(capabilities, nested, depth + 1).await.map(|result| {
+                                    add_usage(&mut usage, &result.usage);
+                                    vec![Content::text(
+                                        serde_json::to_string_pretty(&result).unwrap_or_default(),
+                                    )]
+                                })
+                            }
+                            Err(e) => Err(ToolError::InvalidParameters(e.to_string())),
+                        }
+                    }
+                    Ok(call) => match authorize(policy.as_ref(), &call) {
+                        Ok(()) => capabilities.dispatch_tool_call(call).await,
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                };
+                tool_responses = tool_responses.with_tool_response(request.id, output);
+            }
+            messages.push(response);
+            messages.push(tool_responses);
+        }
+
+        // Only reached with max_turns set to 0
+        Err(ToolError::ExecutionError(format!(
+            "Subagent {} is not allowed any turns",
+            handoff.agent
+        )))
+    })
+}
+
+fn subagent_provider(
+    config: &SubagentConfig,
+    parent: &dyn Provider,
+) -> ToolResult<Option<Box<dyn Provider>>> {
+    let name = match (&config.provider, &config.model) {
+        (None, None) => return Ok(None),
+        (Some(name), _) => name.clone(),
+        (None, Some(_)) => Config::global()
+            .get("GOOSE_PROVIDER")
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?,
+    };
+    let model = match (&config.provider, &config.model) {
+        (_, Some(model)) => model.clone(),
+        // Another provider is unlikely to serve the agent's model
+        (Some(_), None) => providers::providers()
+            .into_iter()
+            .find(|metadata| metadata.name == name)
+            .map(|metadata| metadata.default_model)
+            .ok_or_else(|| ToolError::ExecutionError(format!("Unknown provider {}", name)))?,
+        (None, None) => parent.get_model_config().model_name,
+    };
+    let provider: Box<dyn Provider> = providers::create(&name, ModelConfig::new(model))
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+    Ok(Some(provider))
+}
+
+// Whether the (prefixed) tool belongs to one of the subagent's extensions
+fn allows(config: &SubagentConfig, tool: &str) -> bool {
+    match &config.extensions {
+        Some(extensions) => extensions
+            .iter()
+            .any(|extension| tool.starts_with(&format!("{}__", extension))),
+        None => true,
+    }
+}
+
+// Nobody can confirm a subagent's calls, so `ask` refuses like `deny`
+pub(super) fn authorize(policy: Option<&ToolPolicy>, call: &ToolCall) -> ToolResult<()> {
+    match policy.and_then(|policy| policy.evaluate(call)) {
+        Some(verdict) if verdict.decision != PolicyDecision::Allow => Err(verdict.denial(call)),
+        _ => Ok(()),
+    }
+}
+
+fn add_usage(total: &mut Usage, usage: &Usage) {
+    for (sum, value) in [
+        (&mut total.input_tokens, usage.input_tokens),
+        (&mut total.output_tokens, usage.output_tokens),
+        (&mut total.total_tokens, usage.total_tokens),
+    ] {
+        if let Some(value) = value {
+            *sum.get_or_insert(0) += value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::{ProviderMetadata, ProviderUsage};
+    use crate::providers::errors::ProviderError;
+    use std::sync::Mutex;
+
+    // Calls the tool it is told to in the task, then reports what came back
+    struct ToolCallingProvider {
+        calls: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for ToolCallingProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("gpt-4o".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            tools: &[Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            self.calls.lock().unwrap().push(tools.len());
+            let usage = Usage::new(Some(10), Some(5), Some(15));
+            let last = messages.last().unwrap();
+            let response = match last.content.first().and_then(|c| c.as_tool_response()) {
+                Some(response) => Message::assistant().with_text(match &response.tool_result {
+                    Ok(_) => "done".to_string(),
+                    Err(e) => format!("failed: {}", e),
+                }),
+                None => Message::assistant()
+                    .with_tool_request("1", Ok(ToolCall::new(last.as_concat_text(), json!({})))),
+            };
+            Ok((response, ProviderUsage::new("gpt-4o".to_string(), usage)))
+        }
+    }
+
+    fn subagents(max_depth: usize) -> Subagents {
+        let agent = SubagentConfig {
+            description: "Does research".to_string(),
+            provider: None,
+            model: None,
+            instructions: None,
+            extensions: Some(vec!["fetch".to_string()]),
+        };
+        Subagents {
+            max_depth,
+            agents: BTreeMap::from([("researcher".to_string(), agent)]),
+            ..Default::default()
+        }
+    }
+
+    fn capabilities(subagents: Subagents) -> Capabilities {
+        let mut capabilities = Capabilities::new(Box::new(ToolCallingProvider {
+            calls: Mutex::new(Vec::new()),
+        }));
+        capabilities.set_subagents(subagents);
+        capabilities
+    }
+
+    #[test]
+    fn test_tool_respects_depth() {
+        assert!(Subagents::default().tool(0).is_none());
+
+        let tool = subagents(2).tool(1).unwrap();
+        assert_eq!(tool.name, DELEGATE_TOOL_NAME);
+        assert!(tool.description.contains("- researcher: Does research"));
+        assert!(subagents(2).tool(2).is_none());
+    }
+
+    #[test]
+    fn test_allows_only_listed_extensions() {
+        let config = &subagents(1).agents["researcher"];
+        assert!(allows(config, "fetch__get"));
+        assert!(!allows(config, "developer__shell"));
+        assert!(!allows(config, "fetcher__get"));
+    }
+
+    #[tokio::test]
+    async fn test_delegate_aggregates_usage() {
+        let capabilities = capabilities(subagents(1));
+        let output = capabilities
+            .dispatch_tool_call(ToolCall::new(
+                DELEGATE_TOOL_NAME,
+                json!({"agent": "researcher", "task": "developer__shell"}),
+            ))
+            .await
+            .unwrap();
+        let result: HandoffResult = serde_json::from_str(output[0].as_text().unwrap()).unwrap();
+
+        assert_eq!(result.status, HandoffStatus::Completed);
+        assert_eq!(result.turns, 2);
+        // The tool is outside of the subagent's extensions
+        assert!(result.result.starts_with("failed:"));
+        assert_eq!(result.usage.total_tokens, Some(30));
+        assert_eq!(
+            capabilities.get_usage().await[0].usage.total_tokens,
+            Some(30)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delegate_limits_depth() {
+        let capabilities = capabilities(subagents(1));
+        let output = capabilities
+            .dispatch_tool_call(ToolCall::new(
+                DELEGATE_TOOL_NAME,
+                json!({"agent": "researcher", "task": DELEGATE_TOOL_NAME}),
+            ))
+            .await
+            .unwrap();
+        let result: HandoffResult = serde_json::from_str(output[0].as_text().unwrap()).unwrap();
+        // Subagents are not offered the delegate tool at the maximum depth
+        assert_eq!(result.result, "failed: Tool not found: platform__delegate");
+
+        let error = run(
+            &capabilities,
+            Handoff {
+                agent: "researcher".to_string(),
+                task: "anything".to_string(),
+                context: None,
+            },
+            2,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("nested more than 1 levels"));
+
+        let error = capabilities
+            .dispatch_tool_call(ToolCall::new(
+                DELEGATE_TOOL_NAME,
+                json!({"agent": "writer", "task": "anything"}),
+            ))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("the subagents are: researcher"));
+    }
+}
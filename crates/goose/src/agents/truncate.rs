@@ -1,25 +1,36 @@
 /// A truncate agent that truncates the conversation history when it exceeds the model's context limit
 /// It makes no attempt to handle context limits, and cannot read resources
 use async_trait::async_trait;
-use futures::stream::BoxStream;
+use futures::stream::{BoxStream, StreamExt};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, instrument, warn};
 
 use super::detect_read_only_tools;
-use super::Agent;
+use super::plan::{Plan, PlanStatus, Planner, PLAN_TOOL_NAME};
+use super::policy::{PolicyDecision, PolicyVerdict, ToolPolicy};
+use super::{Agent, ReplyEvent};
 use crate::agents::capabilities::Capabilities;
 use crate::agents::extension::{ExtensionConfig, ExtensionResult};
+use crate::agents::limits::SessionLimits;
+use crate::agents::AttachedResource;
 use crate::config::Config;
+use crate::context_window::ContextWindowTracker;
+use crate::events::{self, Event};
 use crate::message::{Message, ToolRequest};
 use crate::providers::base::Provider;
 use crate::providers::base::ProviderUsage;
 use crate::providers::errors::ProviderError;
+use crate::providers::formats::streaming::MessageAssembler;
 use crate::register_agent;
 use crate::token_counter::TokenCounter;
-use crate::truncate::{truncate_messages, OldestFirstTruncation};
+use crate::truncate::{truncate_messages, TruncationStrategy, TruncationStrategyKind};
+use crate::usage::{tool_token_usage, ToolTokenUsage};
 use indoc::indoc;
-use mcp_core::{tool::Tool, Content};
+use mcp_core::tool::{Tool, ToolCall};
+use mcp_core::{Content, Resource, ToolResult};
 use serde_json::{json, Value};
 
 const MAX_TRUNCATION_ATTEMPTS: usize = 3;
@@ -28,22 +39,29 @@ const ESTIMATE_FACTOR_DECAY: f32 = 0.9;
 /// Truncate implementation of an Agent
 pub struct TruncateAgent {
     capabilities: Mutex<Capabilities>,
-    token_counter: TokenCounter,
+    token_counter: Arc<TokenCounter>,
+    context_window: ContextWindowTracker,
+    truncation_strategy: Box<dyn TruncationStrategy + Send + Sync>,
     confirmation_tx: mpsc::Sender<(String, bool)>, // (request_id, confirmed)
     confirmation_rx: Mutex<mpsc::Receiver<(String, bool)>>,
+    plan: Mutex<Option<Plan>>,
 }
 
 impl TruncateAgent {
     pub fn new(provider: Box<dyn Provider>) -> Self {
-        let token_counter = TokenCounter::new(provider.get_model_config().tokenizer_name());
+        let model_config = provider.get_model_config();
+        let token_counter = model_config.token_counter();
         // Create channel with buffer size 32 (adjust if needed)
         let (tx, rx) = mpsc::channel(32);
 
         Self {
             capabilities: Mutex::new(Capabilities::new(provider)),
             token_counter,
+            context_window: ContextWindowTracker::from_config(),
+            truncation_strategy: TruncationStrategyKind::for_model(&model_config).strategy(),
             confirmation_tx: tx,
             confirmation_rx: Mutex::new(rx),
+            plan: Mutex::new(None),
         }
     }
 
@@ -98,9 +116,50 @@ impl TruncateAgent {
             messages,
             &mut token_counts,
             context_limit,
-            &OldestFirstTruncation,
+            self.truncation_strategy.as_ref(),
         )
     }
+
+    /// Wait for the user to answer the confirmation request for a tool call
+    async fn wait_for_confirmation(&self, request_id: &str) -> bool {
+        let mut rx = self.confirmation_rx.lock().await;
+        match rx.recv().await {
+            Some((req_id, confirmed)) => req_id == request_id && confirmed,
+            None => false,
+        }
+    }
+}
+
+fn confirmation_request(
+    request: &ToolRequest,
+    tool_call: &ToolCall,
+    verdict: Option<&PolicyVerdict>,
+) -> Message {
+    let prompt = match verdict.and_then(|v| v.reason.as_ref()) {
+        Some(reason) => format!(
+            "Goose would like to call the tool: {}\n{}\nAllow? (y/n): ",
+            tool_call.name, reason
+        ),
+        None => format!(
+            "Goose would like to call the tool: {}\nAllow? (y/n): ",
+            tool_call.name
+        ),
+    };
+    Message::user().with_tool_confirmation_request(
+        request.id.clone(),
+        tool_call.name.clone(),
+        tool_call.arguments.clone(),
+        Some(prompt),
+    )
+}
+
+fn plan_confirmation_request(request_id: &str, plan: &Plan) -> Message {
+    Message::user().with_tool_confirmation_request(
+        request_id.to_string(),
+        PLAN_TOOL_NAME.to_string(),
+        json!(plan),
+        Some("Goose would like to carry out the plan above.\nAllow? (y/n): ".to_string()),
+    )
 }
 
 #[async_trait]
@@ -126,6 +185,26 @@ impl Agent for TruncateAgent {
             .expect("Failed to list extensions")
     }
 
+    async fn list_resources(&self, extension: &str) -> ExtensionResult<Vec<Resource>> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.list_extension_resources(extension).await
+    }
+
+    async fn attach_resource(&self, extension: &str, uri: &str) -> ExtensionResult<()> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.attach_resource(extension, uri).await
+    }
+
+    async fn detach_resource(&self, extension: &str, uri: &str) -> ExtensionResult<()> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.detach_resource(extension, uri).await
+    }
+
+    async fn attached_resources(&self) -> Vec<AttachedResource> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.attached_resources().await
+    }
+
     async fn passthrough(&self, _extension: &str, _request: Value) -> ExtensionResult<Value> {
         // TODO implement
         Ok(Value::Null)
@@ -139,10 +218,11 @@ impl Agent for TruncateAgent {
     }
 
     #[instrument(skip(self, messages), fields(user_message))]
-    async fn reply(
+    async fn reply_cancellable(
         &self,
         messages: &[Message],
-    ) -> anyhow::Result<BoxStream<'_, anyhow::Result<Message>>> {
+        cancel: CancellationToken,
+    ) -> anyhow::Result<BoxStream<'_, anyhow::Result<ReplyEvent>>> {
         let mut messages = messages.to_vec();
         let reply_span = tracing::Span::current();
         let mut capabilities = self.capabilities.lock().await;
@@ -151,7 +231,8 @@ impl Agent for TruncateAgent {
 
         // Load settings from config
         let config = Config::global();
-        let goose_mode = config.get("GOOSE_MODE").unwrap_or("auto".to_string());
+        let mut goose_mode = config.get("GOOSE_MODE").unwrap_or("auto".to_string());
+        let policy = ToolPolicy::from_config();
 
         // we add in the 2 resource tools if any extensions support resources
         // TODO: make sure there is no collision with another extension's tool name
@@ -197,8 +278,9 @@ impl Agent for TruncateAgent {
             tools.push(read_resource_tool);
             tools.push(list_resources_tool);
         }
+        tools.extend(capabilities.subagents().tool(0));
 
-        let system_prompt = capabilities.get_system_prompt().await;
+        let mut system_prompt = capabilities.get_system_prompt().await;
 
         // Set the user_message field in the span instead of creating a new event
         if let Some(content) = messages
@@ -209,14 +291,82 @@ impl Agent for TruncateAgent {
             debug!("user_message" = &content);
         }
 
-        Ok(Box::pin(async_stream::try_stream! {
+        let stop = cancel.clone();
+        let reply = async_stream::try_stream! {
             let _reply_guard = reply_span.enter();
+
+            // In plan mode the planner drafts a plan first, which runs without further
+            // confirmation once the user approves it
+            let mut plan = None;
+            if goose_mode == "plan" {
+                let planner = Planner::from_config(capabilities.provider())?;
+                let (mut proposed, usage) = planner.plan(&messages, &tools).await?;
+                capabilities.record_usage(usage).await;
+                *self.plan.lock().await = Some(proposed.clone());
+                yield ReplyEvent::Message(Message::assistant().with_text(proposed.to_markdown()));
+
+                let request_id = format!("plan_{}", nanoid::nanoid!(8));
+                yield ReplyEvent::Message(plan_confirmation_request(&request_id, &proposed));
+                if !self.wait_for_confirmation(&request_id).await {
+                    proposed.status = PlanStatus::Rejected;
+                    *self.plan.lock().await = Some(proposed);
+                    yield ReplyEvent::Message(Message::assistant().with_text("The plan was declined. Tell me what to change and I will plan again."));
+                    return;
+                }
+                proposed.status = PlanStatus::Approved;
+                *self.plan.lock().await = Some(proposed.clone());
+                system_prompt = proposed.extend_system_prompt(&system_prompt);
+                plan = Some(proposed);
+                goose_mode = "auto".to_string();
+            }
+
             loop {
-                match capabilities.provider().complete(
-                    &system_prompt,
-                    &messages,
-                    &tools,
-                ).await {
+                // Warn as we approach the model's context limit, before it becomes a hard failure
+                let used_tokens = self.token_counter.count_chat_tokens(&system_prompt, &messages, &tools);
+                let context_limit = capabilities.provider().get_model_config().context_limit();
+                self.context_window.update(used_tokens, context_limit);
+
+                // Truncate up front when we already know the request will not fit, rather
+                // than waiting for the provider to reject it
+                if used_tokens > context_limit {
+                    warn!("Estimated {} tokens exceeds context limit of {}. Truncating.", used_tokens, context_limit);
+
+                    // release the lock before truncation to prevent deadlock
+                    drop(capabilities);
+
+                    if let Err(err) = self.truncate_messages(&mut messages, ESTIMATE_FACTOR_DECAY, &system_prompt, &mut tools).await {
+                        yield ReplyEvent::Message(Message::assistant().with_text(format!("Error: Unable to truncate messages to stay within context limit. \n\nRan into this error: {}.\n\nPlease start a new session with fresh context and try again.", err)));
+                        break;
+                    }
+
+                    capabilities = self.capabilities.lock().await;
+                }
+
+                // Pass the response on as it streams in, and act on it once it is complete
+                let streamed = match capabilities.stream(&system_prompt, &messages, &tools, &cancel).await {
+                    Ok(mut stream) => {
+                        let mut assembler = MessageAssembler::default();
+                        let mut failed = None;
+                        while let Some(delta) = stream.next().await {
+                            match delta {
+                                Ok(delta) => {
+                                    assembler.push(delta.clone());
+                                    yield ReplyEvent::Delta(delta);
+                                }
+                                Err(e) => {
+                                    failed = Some(e);
+                                    break;
+                                }
+                            }
+                        }
+                        match failed {
+                            Some(e) => Err(e),
+                            None => Ok(assembler.finish(&capabilities.provider().get_model_config().model_name)),
+                        }
+                    }
+                    Err(e) => Err(e),
+                };
+                match streamed {
                     Ok((response, usage)) => {
                         capabilities.record_usage(usage).await;
 
@@ -224,7 +374,7 @@ impl Agent for TruncateAgent {
                         truncation_attempt = 0;
 
                         // Yield the assistant's response
-                        yield response.clone();
+                        yield ReplyEvent::Message(response.clone());
 
                         tokio::task::yield_now().await;
 
@@ -235,118 +385,122 @@ impl Agent for TruncateAgent {
                             .collect();
 
                         if tool_requests.is_empty() {
+                            if plan.is_some() {
+                                if let Some(plan) = self.plan.lock().await.as_mut() {
+                                    plan.status = PlanStatus::Completed;
+                                }
+                            }
                             break;
                         }
 
                         let read_only_tools = detect_read_only_tools(&capabilities, tool_requests.clone()).await;
 
-                        // Process tool requests depending on goose_mode
-                        let mut message_tool_response = Message::user();
-                        // Clone goose_mode once before the match to avoid move issues
-                        let mode = goose_mode.clone();
-                        match mode.as_str() {
-                            "approve" => {
-                                // Process each tool request sequentially with confirmation
-                                for request in &tool_requests {
-                                    if let Ok(tool_call) = request.tool_call.clone() {
-                                        // Skip confirmation if the tool_call.name is in the read_only_tools list
-                                        if read_only_tools.contains(&tool_call.name) {
-                                            let output = capabilities.dispatch_tool_call(tool_call).await;
-                                                    message_tool_response = message_tool_response.with_tool_response(
-                                                        request.id.clone(),
-                                                        output,
-                                                    );
-                                        } else {
-                                            let confirmation = Message::user().with_tool_confirmation_request(
-                                                request.id.clone(),
-                                                tool_call.name.clone(),
-                                                tool_call.arguments.clone(),
-                                                Some("Goose would like to call the tool: {}\nAllow? (y/n): ".to_string()),
-                                            );
-                                            yield confirmation;
-
-                                            // Wait for confirmation response through the channel
-                                            let mut rx = self.confirmation_rx.lock().await;
-                                            if let Some((req_id, confirmed)) = rx.recv().await {
-                                                if req_id == request.id {
-                                                    if confirmed {
-                                                        // User approved - dispatch the tool call
-                                                        let output = capabilities.dispatch_tool_call(tool_call).await;
-                                                        message_tool_response = message_tool_response.with_tool_response(
-                                                            request.id.clone(),
-                                                            output,
-                                                        );
-                                                    } else {
-                                                        // User declined - add declined response
-                                                        message_tool_response = message_tool_response.with_tool_response(
-                                                            request.id.clone(),
-                                                            Ok(vec![Content::text("User declined to run this tool.")]),
-                                                        );
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            },
-                            "chat" => {
-                                // Skip all tool calls in chat mode
-                                for request in &tool_requests {
-                                    message_tool_response = message_tool_response.with_tool_response(
-                                        request.id.clone(),
-                                        Ok(vec![Content::text(
-                                            "The following tool call was skipped in Goose chat mode. \
-                                            In chat mode, you cannot run tool calls, instead, you can \
-                                            only provide a detailed plan to the user. Provide an \
-                                            explanation of the proposed tool call as if it were a plan. \
-                                            Only if the user asks, provide a short explanation to the \
-                                            user that they could consider running the tool above on \
-                                            their own or with a different goose mode."
-                                        )]),
-                                    );
+                        // Responses by position, so they go back in the order the model asked for them
+                        let mut responses: Vec<Option<ToolResult<Vec<Content>>>> =
+                            tool_requests.iter().map(|_| None).collect();
+
+                        // The tool policy is applied before the mode, so denied calls never run
+                        let mut permitted: Vec<(usize, ToolCall, Option<PolicyVerdict>)> = Vec::new();
+                        for (i, request) in tool_requests.iter().enumerate() {
+                            let tool_call = match &request.tool_call {
+                                Ok(tool_call) => tool_call.clone(),
+                                Err(e) => {
+                                    responses[i] = Some(Err(e.clone()));
+                                    continue;
                                 }
-                            },
-                            _ => {
-                                if mode != "auto" {
-                                    warn!("Unknown GOOSE_MODE: {mode:?}. Defaulting to 'auto' mode.");
+                            };
+                            match policy.as_ref().and_then(|policy| policy.evaluate(&tool_call)) {
+                                Some(verdict) if verdict.decision == PolicyDecision::Deny => {
+                                    responses[i] = Some(Err(verdict.denial(&tool_call)));
                                 }
-                                // Process tool requests in parallel
-                                let mut tool_futures = Vec::new();
-                                for request in &tool_requests {
-                                    if let Ok(tool_call) = request.tool_call.clone() {
-                                        tool_futures.push(async {
-                                            let output = capabilities.dispatch_tool_call(tool_call).await;
-                                            (request.id.clone(), output)
-                                        });
+                                verdict => permitted.push((i, tool_call, verdict)),
+                            }
+                        }
+
+                        // Clone goose_mode once before the match to avoid move issues
+                        let mode = goose_mode.clone();
+                        if mode == "chat" {
+                            // Skip all tool calls in chat mode
+                            for (i, _, _) in &permitted {
+                                responses[*i] = Some(Ok(vec![Content::text(
+                                    "The following tool call was skipped in Goose chat mode. \
+                                    In chat mode, you cannot run tool calls, instead, you can \
+                                    only provide a detailed plan to the user. Provide an \
+                                    explanation of the proposed tool call as if it were a plan. \
+                                    Only if the user asks, provide a short explanation to the \
+                                    user that they could consider running the tool above on \
+                                    their own or with a different goose mode."
+                                )]));
+                            }
+                        } else {
+                            if mode != "auto" && mode != "approve" {
+                                warn!("Unknown GOOSE_MODE: {mode:?}. Defaulting to 'auto' mode.");
+                            }
+                            // Ask first for every call that needs confirmation, so the approved
+                            // ones can run in parallel with the rest
+                            let mut approved = Vec::new();
+                            for (i, tool_call, verdict) in permitted {
+                                // Approve mode skips confirmation for read only tools, unless the policy says otherwise
+                                let needs_confirmation = match verdict.as_ref().map(|v| v.decision) {
+                                    Some(PolicyDecision::Allow) => false,
+                                    Some(PolicyDecision::Ask) => true,
+                                    _ => mode == "approve" && !read_only_tools.contains(&tool_call.name),
+                                };
+                                if needs_confirmation {
+                                    let request = tool_requests[i];
+                                    yield ReplyEvent::Message(confirmation_request(request, &tool_call, verdict.as_ref()));
+                                    if !self.wait_for_confirmation(&request.id).await {
+                                        responses[i] = Some(Ok(vec![Content::text("User declined to run this tool.")]));
+                                        continue;
                                     }
                                 }
-                                // Wait for all tool calls to complete
-                                let results = futures::future::join_all(tool_futures).await;
-                                for (request_id, output) in results {
-                                    message_tool_response = message_tool_response.with_tool_response(
-                                        request_id,
-                                        output,
-                                    );
-                                }
+                                approved.push((i, tool_call));
+                            }
+
+                            let (positions, tool_calls): (Vec<usize>, Vec<ToolCall>) = approved.into_iter().unzip();
+                            let outputs = capabilities.dispatch_tool_calls(tool_calls).await;
+                            for (i, output) in positions.into_iter().zip(outputs) {
+                                responses[i] = Some(output);
+                            }
+                        }
+
+                        let mut message_tool_response = Message::user();
+                        for (request, response) in tool_requests.iter().zip(responses) {
+                            if let Some(response) = response {
+                                message_tool_response = message_tool_response.with_tool_response(
+                                    request.id.clone(),
+                                    response,
+                                );
                             }
                         }
 
-                        yield message_tool_response.clone();
+                        yield ReplyEvent::Message(message_tool_response.clone());
 
                         messages.push(response);
                         messages.push(message_tool_response);
+
+                        // Tools may have changed attached resources
+                        system_prompt = capabilities.get_system_prompt().await;
+                        if let Some(plan) = &plan {
+                            system_prompt = plan.extend_system_prompt(&system_prompt);
+                        }
                     },
                     Err(ProviderError::ContextLengthExceeded(_)) => {
                         if truncation_attempt >= MAX_TRUNCATION_ATTEMPTS {
                             // Create an error message & terminate the stream
                             // the previous message would have been a user message (e.g. before any tool calls, this is just after the input message.
                             // at the start of a loop after a tool call, it would be after a tool_use assistant followed by a tool_result user)
-                            yield Message::assistant().with_text("Error: Context length exceeds limits even after multiple attempts to truncate. Please start a new session with fresh context and try again.");
+                            yield ReplyEvent::Message(Message::assistant().with_text("Error: Context length exceeds limits even after multiple attempts to truncate. Please start a new session with fresh context and try again."));
                             break;
                         }
 
                         truncation_attempt += 1;
                         warn!("Context length exceeded. Truncation Attempt: {}/{}.", truncation_attempt, MAX_TRUNCATION_ATTEMPTS);
+                        events::emit(Event::Retry {
+                            attempt: truncation_attempt as u32,
+                            delay_ms: 0,
+                            reason: "context length exceeded, truncated the conversation".to_string(),
+                        });
 
                         // Decay the estimate factor as we make more truncation attempts
                         // Estimate factor decays like this over time: 0.9, 0.81, 0.729, ...
@@ -356,7 +510,7 @@ impl Agent for TruncateAgent {
                         drop(capabilities);
 
                         if let Err(err) = self.truncate_messages(&mut messages, estimate_factor, &system_prompt, &mut tools).await {
-                            yield Message::assistant().with_text(format!("Error: Unable to truncate messages to stay within context limit. \n\nRan into this error: {}.\n\nPlease start a new session with fresh context and try again.", err));
+                            yield ReplyEvent::Message(Message::assistant().with_text(format!("Error: Unable to truncate messages to stay within context limit. \n\nRan into this error: {}.\n\nPlease start a new session with fresh context and try again.", err)));
                             break;
                         }
 
@@ -367,10 +521,26 @@ impl Agent for TruncateAgent {
                         // Retry the loop after truncation
                         continue;
                     },
+                    Err(ProviderError::BudgetExceeded(status)) => {
+                        warn!("Budget exceeded: {}", status);
+                        yield ReplyEvent::Message(Message::assistant().with_text(format!("Stopping: {status}.\n\nRaise the limits under GOOSE_BUDGET in your config, or those of the session, to continue.")));
+                        break;
+                    },
+                    Err(ProviderError::QuotaExceeded(status)) => {
+                        warn!("Quota exceeded: {}", status);
+                        yield ReplyEvent::Message(Message::assistant().with_text(format!("Stopping: {status}.\n\nWait for the quota to reset or raise it under GOOSE_QUOTAS in your config to continue.")));
+                        break;
+                    },
+                    Err(ProviderError::Cancelled) => break,
+                    Err(ProviderError::GuardrailBlocked(reason)) => {
+                        warn!("Guardrail blocked the request: {}", reason);
+                        yield ReplyEvent::Message(Message::assistant().with_text(format!("Stopping: the guardrail blocked the request, it {reason}.\n\nRemove the sensitive data from the conversation or change its action under GOOSE_GUARDRAIL in your config to continue.")));
+                        break;
+                    },
                     Err(e) => {
                         // Create an error message & terminate the stream
                         error!("Error: {}", e);
-                        yield Message::assistant().with_text(format!("Ran into this error: {e}.\n\nPlease retry if you think this is a transient or recoverable error."));
+                        yield ReplyEvent::Message(Message::assistant().with_text(format!("Ran into this error: {e}.\n\nPlease retry if you think this is a transient or recoverable error.")));
                         break;
                     }
                 }
@@ -378,7 +548,13 @@ impl Agent for TruncateAgent {
                 // Yield control back to the scheduler to prevent blocking
                 tokio::task::yield_now().await;
             }
-        }))
+        };
+        // Ending the stream drops whatever it is waiting on, a tool call or a confirmation
+        Ok(Box::pin(reply.take_until(stop.cancelled_owned())))
+    }
+
+    async fn plan(&self) -> Option<Plan> {
+        self.plan.lock().await.clone()
     }
 
     async fn usage(&self) -> Vec<ProviderUsage> {
@@ -386,6 +562,10 @@ impl Agent for TruncateAgent {
         capabilities.get_usage().await
     }
 
+    async fn tool_token_usage(&self, messages: &[Message]) -> Vec<ToolTokenUsage> {
+        tool_token_usage(messages, &self.token_counter)
+    }
+
     async fn extend_system_prompt(&mut self, extension: String) {
         let mut capabilities = self.capabilities.lock().await;
         capabilities.add_system_prompt_extension(extension);
@@ -395,6 +575,11 @@ impl Agent for TruncateAgent {
         let mut capabilities = self.capabilities.lock().await;
         capabilities.set_system_prompt_override(template);
     }
+
+    async fn set_session_limits(&mut self, limits: SessionLimits) {
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities.set_session_limits(limits);
+    }
 }
 
 register_agent!("truncate", TruncateAgent);
@@ -90,7 +90,11 @@ impl ExtensionManager {
 
     /// Get all extensions and their configurations
     pub fn get_all() -> Result<Vec<ExtensionEntry>> {
-        let config = Config::global();
+        Self::get_all_in(Config::global())
+    }
+
+    /// Get all extensions and their configurations from the given config
+    pub fn get_all_in(config: &Config) -> Result<Vec<ExtensionEntry>> {
         let extensions: HashMap<String, ExtensionEntry> =
             config.get("extensions").unwrap_or_default();
         Ok(Vec::from_iter(extensions.values().cloned()))
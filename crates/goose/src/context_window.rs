@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::events::{self, Event};
+
+/// Fractions of the context window at which an event is emitted, unless overridden
+/// with `GOOSE_CONTEXT_THRESHOLDS` (e.g. `[0.5, 0.8]`)
+pub const DEFAULT_CONTEXT_THRESHOLDS: &[f32] = &[0.7, 0.9];
+
+/// Emitted when the tokens sent to the model cross one of the configured thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ContextWindowEvent {
+    /// The threshold that was crossed, as a fraction of the context limit
+    pub threshold: f32,
+    pub used_tokens: usize,
+    pub context_limit: usize,
+}
+
+impl ContextWindowEvent {
+    /// The fraction of the context window in use
+    pub fn ratio(&self) -> f32 {
+        self.used_tokens as f32 / self.context_limit as f32
+    }
+}
+
+/// Tracks how full the active model's context window is across turns
+///
+/// An event is emitted once each time usage rises past a threshold. If usage drops again
+/// (e.g. after truncation) the thresholds re-arm, so the next crossing is reported too.
+pub struct ContextWindowTracker {
+    thresholds: Vec<f32>,
+    // Index into thresholds of the highest one currently crossed
+    crossed: Mutex<Option<usize>>,
+}
+
+impl ContextWindowTracker {
+    pub fn new(mut thresholds: Vec<f32>) -> Self {
+        thresholds.retain(|t| *t > 0.0);
+        thresholds.sort_by(|a, b| a.total_cmp(b));
+        thresholds.dedup();
+        Self {
+            thresholds,
+            crossed: Mutex::new(None),
+        }
+    }
+
+    pub fn from_config() -> Self {
+        let thresholds: Vec<f32> = Config::global()
+            .get("GOOSE_CONTEXT_THRESHOLDS")
+            .unwrap_or_else(|_| DEFAULT_CONTEXT_THRESHOLDS.to_vec());
+        Self::new(thresholds)
+    }
+
+    /// Update with the tokens about to be sent, returning an event if a new threshold
+    /// was crossed
+    pub fn update(&self, used_tokens: usize, context_limit: usize) -> Option<ContextWindowEvent> {
+        if context_limit == 0 {
+            return None;
+        }
+
+        let ratio = used_tokens as f32 / context_limit as f32;
+        let current = self.thresholds.iter().rposition(|t| ratio >= *t);
+
+        let mut crossed = self.crossed.lock().unwrap();
+        let previous = std::mem::replace(&mut *crossed, current);
+        let index = match (previous, current) {
+            (_, None) => return None,
+            (Some(previous), Some(current)) if current <= previous => return None,
+            (_, Some(current)) => current,
+        };
+
+        let event = ContextWindowEvent {
+            threshold: self.thresholds[index],
+            used_tokens,
+            context_limit,
+        };
+        tracing::warn!(
+            threshold = event.threshold,
+            used_tokens = event.used_tokens,
+            context_limit = event.context_limit,
+            "Context window is {:.0}% full",
+            event.ratio() * 100.0
+        );
+        events::emit(Event::ContextThreshold {
+            threshold: event.threshold,
+            used_tokens,
+            context_limit,
+        });
+        Some(event)
+    }
+
+    /// The highest threshold currently crossed, if any
+    pub fn crossed_threshold(&self) -> Option<f32> {
+        self.crossed.lock().unwrap().map(|i| self.thresholds[i])
+    }
+}
+
+impl Default for ContextWindowTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_CONTEXT_THRESHOLDS.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emits_once_per_threshold() {
+        let tracker = ContextWindowTracker::default();
+
+        assert!(tracker.update(500, 1000).is_none());
+
+        let event = tracker.update(750, 1000).unwrap();
+        assert_eq!(event.threshold, 0.7);
+        assert_eq!(event.ratio(), 0.75);
+        assert!(tracker.update(800, 1000).is_none());
+
+        // Skipping straight past several thresholds reports the highest one
+        let tracker = ContextWindowTracker::default();
+        let event = tracker.update(950, 1000).unwrap();
+        assert_eq!(event.threshold, 0.9);
+        assert!(tracker.update(960, 1000).is_none());
+        assert_eq!(tracker.crossed_threshold(), Some(0.9));
+    }
+
+    #[test]
+    fn test_rearms_after_drop() {
+        let tracker = ContextWindowTracker::new(vec![0.9, 0.5]);
+
+        assert!(tracker.update(600, 1000).is_some());
+        // Truncation brought us back under all thresholds
+        assert!(tracker.update(100, 1000).is_none());
+        assert_eq!(tracker.crossed_threshold(), None);
+
+        let event = tracker.update(550, 1000).unwrap();
+        assert_eq!(event.threshold, 0.5);
+    }
+}
@@ -0,0 +1,163 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast;
+
+use crate::providers::base::Usage;
+
+static GLOBAL_BUS: Lazy<EventBus> = Lazy::new(EventBus::default);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+// Events are dropped for subscribers that fall this far behind
+const CAPACITY: usize = 1024;
+
+/// Something that happened in an agent or provider, for embedders and UIs to observe
+///
+/// Events that start and finish share an `id`, so concurrent completions and tool calls can
+/// be told apart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    CompletionStarted {
+        id: u64,
+        provider: String,
+        model: String,
+        messages: usize,
+        tools: usize,
+    },
+    CompletionFinished {
+        id: u64,
+        provider: String,
+        model: String,
+        duration_ms: u64,
+        /// None when the request failed
+        usage: Option<Usage>,
+        error: Option<String>,
+    },
+    ToolStarted {
+        id: u64,
+        name: String,
+    },
+    ToolFinished {
+        id: u64,
+        name: String,
+        duration_ms: u64,
+        error: Option<String>,
+    },
+    /// A request is sent again after failing
+    Retry {
+        attempt: u32,
+        delay_ms: u64,
+        reason: String,
+    },
+    /// The circuit breaker of a provider and model opened or closed
+    CircuitChanged {
+        provider: String,
+        model: String,
+        state: String,
+    },
+    /// A request went to the next provider of the fallback chain
+    Fallback {
+        from: String,
+        to: String,
+        reason: String,
+    },
+    /// Spend crossed a soft budget limit
+    BudgetWarning {
+        scope: String,
+        cost: f64,
+        tokens: i64,
+    },
+    /// Older turns were summarized to make room in the context window
+    CompactionPerformed {
+        archived_messages: usize,
+        used_tokens: usize,
+        context_limit: usize,
+    },
+    /// The conversation crossed one of the context window thresholds
+    ContextThreshold {
+        threshold: f32,
+        used_tokens: usize,
+        context_limit: usize,
+    },
+}
+
+/// Broadcasts `Event`s to every subscriber
+///
+/// Emitting never blocks: without subscribers events are dropped, and a subscriber that
+/// falls behind skips the oldest events it missed, which its receiver reports as lagged.
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+}
+
+impl EventBus {
+    /// The process wide bus that goose emits its events on
+    pub fn global() -> &'static EventBus {
+        &GLOBAL_BUS
+    }
+
+    /// Receive every event emitted from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    pub fn emit(&self, event: Event) {
+        // Only fails when nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Emit an event on the global bus
+pub fn emit(event: Event) {
+    EventBus::global().emit(event);
+}
+
+/// A new id to correlate the start and finish of something
+pub fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribers_receive_events() {
+        let bus = EventBus::default();
+        // Nobody is listening yet
+        bus.emit(Event::ToolStarted {
+            id: 1,
+            name: "developer__shell".to_string(),
+        });
+
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        bus.emit(Event::Retry {
+            attempt: 1,
+            delay_ms: 100,
+            reason: "rate limited".to_string(),
+        });
+
+        for receiver in [&mut first, &mut second] {
+            let event = receiver.recv().await.unwrap();
+            assert!(matches!(event, Event::Retry { attempt: 1, .. }));
+        }
+        assert!(first.try_recv().is_err());
+
+        let json = serde_json::to_value(Event::ToolStarted {
+            id: 2,
+            name: "developer__shell".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json["type"], "tool_started");
+        assert_ne!(next_id(), next_id());
+    }
+}
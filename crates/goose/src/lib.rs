@@ -1,9 +1,14 @@
 pub mod agents;
 pub mod config;
+pub mod context_window;
+pub mod events;
 pub mod message;
 pub mod model;
 pub mod prompt_template;
 pub mod providers;
+pub mod scheduler;
+pub mod session;
 pub mod token_counter;
 pub mod tracing;
 pub mod truncate;
+pub mod usage;
@@ -34,6 +34,12 @@ pub struct ToolConfirmationRequest {
     pub prompt: Option<String>,
 }
 
+/// The reasoning a model did before answering, kept apart from the answer itself
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ThinkingContent {
+    pub thinking: String,
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 /// Content passed inside a message, which can be both simple content and tool content
 pub enum MessageContent {
@@ -42,6 +48,7 @@ pub enum MessageContent {
     ToolRequest(ToolRequest),
     ToolResponse(ToolResponse),
     ToolConfirmationRequest(ToolConfirmationRequest),
+    Thinking(ThinkingContent),
 }
 
 impl MessageContent {
@@ -87,6 +94,13 @@ impl MessageContent {
             prompt,
         })
     }
+
+    pub fn thinking<S: Into<String>>(thinking: S) -> Self {
+        MessageContent::Thinking(ThinkingContent {
+            thinking: thinking.into(),
+        })
+    }
+
     pub fn as_tool_request(&self) -> Option<&ToolRequest> {
         if let MessageContent::ToolRequest(ref tool_request) = self {
             Some(tool_request)
@@ -133,6 +147,13 @@ impl MessageContent {
             _ => None,
         }
     }
+
+    pub fn as_thinking(&self) -> Option<&str> {
+        match self {
+            MessageContent::Thinking(thinking) => Some(&thinking.thinking),
+            _ => None,
+        }
+    }
 }
 
 impl From<Content> for MessageContent {
@@ -154,25 +175,36 @@ pub struct Message {
     pub role: Role,
     pub created: i64,
     pub content: Vec<MessageContent>,
+    /// Kept verbatim when the conversation is truncated or summarized to fit the context
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
 }
 
 impl Message {
-    /// Create a new user message with the current timestamp
-    pub fn user() -> Self {
+    /// Create a new, unpinned message
+    pub fn new(role: Role, created: i64, content: Vec<MessageContent>) -> Self {
         Message {
-            role: Role::User,
-            created: Utc::now().timestamp(),
-            content: Vec::new(),
+            role,
+            created,
+            content,
+            pinned: false,
         }
     }
 
+    /// Create a new user message with the current timestamp
+    pub fn user() -> Self {
+        Message::new(Role::User, Utc::now().timestamp(), Vec::new())
+    }
+
     /// Create a new assistant message with the current timestamp
     pub fn assistant() -> Self {
-        Message {
-            role: Role::Assistant,
-            created: Utc::now().timestamp(),
-            content: Vec::new(),
-        }
+        Message::new(Role::Assistant, Utc::now().timestamp(), Vec::new())
+    }
+
+    /// Pin or unpin the message, see `pinned`
+    pub fn with_pinned(mut self, pinned: bool) -> Self {
+        self.pinned = pinned;
+        self
     }
 
     /// Add any MessageContent to the message
@@ -222,6 +254,18 @@ impl Message {
         ))
     }
 
+    /// Add the model's reasoning to the message
+    pub fn with_thinking<S: Into<String>>(self, thinking: S) -> Self {
+        self.with_content(MessageContent::thinking(thinking))
+    }
+
+    /// The message without the model's reasoning, e.g. to keep it out of later requests
+    pub fn without_thinking(mut self) -> Self {
+        self.content
+            .retain(|content| !matches!(content, MessageContent::Thinking(_)));
+        self
+    }
+
     /// Get the concatenated text content of the message, separated by newlines
     pub fn as_concat_text(&self) -> String {
         self.content
@@ -1,4 +1,9 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::token_counter::TokenCounter;
+use crate::truncate::TruncationStrategyKind;
 
 const DEFAULT_CONTEXT_LIMIT: usize = 128_000;
 
@@ -6,6 +11,31 @@ const DEFAULT_CONTEXT_LIMIT: usize = 128_000;
 pub const GPT_4O_TOKENIZER: &str = "Xenova--gpt-4o";
 pub const CLAUDE_TOKENIZER: &str = "Xenova--claude-tokenizer";
 
+/// Sampling and constrained decoding settings that only some providers support
+///
+/// Providers that don't support a setting leave it out of their requests.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DecodingConfig {
+    /// A JSON schema the output has to follow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guided_json: Option<Value>,
+    /// A regular expression the output has to match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guided_regex: Option<String>,
+    /// How many candidates to generate, returning the most likely one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_of: Option<u32>,
+    /// The least probability a token can have, relative to the most likely token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+}
+
+impl DecodingConfig {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
 /// Configuration for model-specific settings and limits
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
@@ -21,6 +51,12 @@ pub struct ModelConfig {
     pub temperature: Option<f32>,
     /// Optional maximum tokens to generate
     pub max_tokens: Option<i32>,
+    /// Optional settings for providers with more control over decoding
+    #[serde(default)]
+    pub decoding: DecodingConfig,
+    /// Optional truncation strategy, falling back to `GOOSE_TRUNCATION_STRATEGY` if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncation_strategy: Option<TruncationStrategyKind>,
 }
 
 impl ModelConfig {
@@ -40,6 +76,8 @@ impl ModelConfig {
             context_limit,
             temperature: None,
             max_tokens: None,
+            decoding: DecodingConfig::default(),
+            truncation_strategy: None,
         }
     }
 
@@ -66,6 +104,15 @@ impl ModelConfig {
             // Meta Llama models, https://github.com/meta-llama/llama-models/tree/main?tab=readme-ov-file#llama-models-1
             name if name.contains("llama3.2") => Some(128_000),
             name if name.contains("llama3.3") => Some(128_000),
+
+            // AI21 Jamba models, https://docs.ai21.com/docs/jamba-foundation-models
+            name if name.contains("jamba") => Some(256_000),
+
+            // Moonshot Kimi models, https://platform.moonshot.ai/docs/pricing/chat
+            name if name.contains("moonshot-v1-8k") => Some(8_000),
+            name if name.contains("moonshot-v1-32k") => Some(32_000),
+            name if name.contains("moonshot-v1-128k") => Some(128_000),
+            name if name.contains("kimi") => Some(128_000),
             _ => None,
         }
     }
@@ -93,11 +140,32 @@ impl ModelConfig {
         self
     }
 
+    /// Set the decoding settings
+    pub fn with_decoding(mut self, decoding: DecodingConfig) -> Self {
+        self.decoding = decoding;
+        self
+    }
+
+    /// Set the truncation strategy
+    pub fn with_truncation_strategy(mut self, strategy: Option<TruncationStrategyKind>) -> Self {
+        self.truncation_strategy = strategy;
+        self
+    }
+
     // Get the tokenizer name
     pub fn tokenizer_name(&self) -> &str {
         &self.tokenizer_name
     }
 
+    /// The token counter for this model, shared across the process
+    ///
+    /// Counts are local: tiktoken for OpenAI models when the `tiktoken` feature is enabled,
+    /// the model's HuggingFace tokenizer otherwise, and an estimate from the length of the
+    /// text when neither can be loaded.
+    pub fn token_counter(&self) -> Arc<TokenCounter> {
+        TokenCounter::for_model(self)
+    }
+
     /// Get the context_limit for the current model
     /// If none are defined, use the DEFAULT_CONTEXT_LIMIT
     pub fn context_limit(&self) -> usize {
@@ -139,4 +207,13 @@ mod tests {
         assert_eq!(config.max_tokens, Some(1000));
         assert_eq!(config.context_limit, Some(50_000));
     }
+
+    #[test]
+    fn test_model_config_token_counter() {
+        let config = ModelConfig::new("gpt-4o".to_string());
+        let counter = config.token_counter();
+        assert!(counter.count_tokens("Hello, how are you?") > 0);
+        // Counters are built once per model
+        assert!(Arc::ptr_eq(&counter, &config.clone().token_counter()));
+    }
 }
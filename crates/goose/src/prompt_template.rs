@@ -1,11 +1,21 @@
+use etcetera::{choose_app_strategy, AppStrategy};
 use include_dir::{include_dir, Dir};
+use once_cell::sync::Lazy;
 use serde::Serialize;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use tera::{Context, Error as TeraError, Tera};
 
+use crate::config::{Config, APP_STRATEGY};
+
 // The prompts directory needs to be embedded in the binary (so it works when distributed)
 static PROMPTS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/prompts");
 
+static GLOBAL_LIBRARY: Lazy<PromptLibrary> = Lazy::new(PromptLibrary::from_config);
+
+/// Config key naming the profile whose prompt overrides are used
+pub const PROMPT_PROFILE_CONFIG_KEY: &str = "GOOSE_PROMPT_PROFILE";
+
 pub fn load_prompt<T: Serialize>(template: &str, context_data: &T) -> Result<String, TeraError> {
     let mut tera = Tera::default();
     tera.add_raw_template("inline_template", template)?;
@@ -14,27 +24,140 @@ pub fn load_prompt<T: Serialize>(template: &str, context_data: &T) -> Result<Str
     Ok(rendered.trim().to_string())
 }
 
+/// Render a named template from the global `PromptLibrary`
 pub fn load_prompt_file<T: Serialize>(
     template_file: impl Into<PathBuf>,
     context_data: &T,
 ) -> Result<String, TeraError> {
     let template_path = template_file.into();
+    PromptLibrary::global().render(&template_path.to_string_lossy(), context_data)
+}
 
-    // Get the file content from the embedded directory
-    let template_content = if let Some(file) = PROMPTS_DIR.get_file(template_path.to_str().unwrap())
-    {
-        String::from_utf8_lossy(file.contents()).into_owned()
-    } else {
-        return Err(TeraError::chain(
-            "Failed to find template file",
-            std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Template file not found in embedded directory",
-            ),
-        ));
-    };
+/// A set of named prompt templates, used for system prompts and recurring task prompts
+///
+/// Templates use Tera syntax: `{{ variable }}` is substituted from the context, and
+/// `{% include "name.md" %}` pulls in any other template of the library as a partial. A
+/// template is looked up in order of precedence:
+///
+/// 1. `prompts/<profile>/<name>` in the user directories, when `GOOSE_PROMPT_PROFILE` is set
+/// 2. `prompts/<name>` in the user directories
+/// 3. the prompts built into goose
+///
+/// The user directories are the config dir (e.g. ~/.config/goose/prompts), which takes
+/// precedence, and the data dir (e.g. ~/.local/share/goose/prompts). Templates are rendered
+/// each time they are used, so the context can reflect the state at the time of the request.
+pub struct PromptLibrary {
+    tera: Tera,
+}
+
+impl PromptLibrary {
+    /// A library with only the prompts built into goose
+    pub fn builtin() -> Self {
+        Self::from_templates(builtin_templates()).expect("built-in prompts should parse")
+    }
+
+    /// Load the built-in prompts overridden by the templates in `dirs`, in increasing order
+    /// of precedence, and then by those of the profile
+    pub fn load(dirs: &[PathBuf], profile: Option<&str>) -> Result<Self, TeraError> {
+        let mut templates = builtin_templates();
+        for dir in dirs {
+            templates.extend(read_templates(dir)?);
+        }
+        if let Some(profile) = profile {
+            for dir in dirs {
+                templates.extend(read_templates(&dir.join(profile))?);
+            }
+        }
+        Self::from_templates(templates)
+    }
+
+    /// Load the library from the default directories and the configured profile, falling
+    /// back to the built-in prompts if a template is invalid
+    pub fn from_config() -> Self {
+        let profile: Option<String> = Config::global().get(PROMPT_PROFILE_CONFIG_KEY).ok();
+        Self::load(&Self::default_dirs(), profile.as_deref()).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load prompt templates, using the defaults: {}", e);
+            Self::builtin()
+        })
+    }
+
+    /// The process wide library, loaded on first use
+    pub fn global() -> &'static PromptLibrary {
+        &GLOBAL_LIBRARY
+    }
+
+    /// The user prompt directories, in increasing order of precedence
+    ///
+    /// - macOS/Linux: ~/.local/share/goose/prompts, ~/.config/goose/prompts
+    /// - Windows:     ~\AppData\Roaming\Block\goose\data\prompts, ~\AppData\Roaming\Block\goose\config\prompts
+    pub fn default_dirs() -> Vec<PathBuf> {
+        let strategy =
+            choose_app_strategy(APP_STRATEGY.clone()).expect("goose requires a home dir");
+        vec![
+            strategy.in_data_dir("prompts"),
+            strategy.in_config_dir("prompts"),
+        ]
+    }
+
+    fn from_templates(templates: BTreeMap<String, String>) -> Result<Self, TeraError> {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(templates)?;
+        Ok(Self { tera })
+    }
+
+    /// Add or replace a template
+    pub fn add_template(&mut self, name: &str, content: &str) -> Result<(), TeraError> {
+        self.tera.add_raw_template(name, content)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tera.get_template_names().any(|n| n == name)
+    }
 
-    load_prompt(&template_content, context_data)
+    /// The names of all templates, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tera.get_template_names().map(str::to_string).collect();
+        names.sort();
+        names
+    }
+
+    /// Render a template, failing if it references a variable missing from the context
+    pub fn render<T: Serialize>(&self, name: &str, context_data: &T) -> Result<String, TeraError> {
+        let context = Context::from_serialize(context_data)?;
+        let rendered = self.tera.render(name, &context)?;
+        Ok(rendered.trim().to_string())
+    }
+}
+
+fn builtin_templates() -> BTreeMap<String, String> {
+    PROMPTS_DIR
+        .files()
+        .filter_map(|file| {
+            let name = file.path().to_str()?.to_string();
+            Some((name, String::from_utf8_lossy(file.contents()).into_owned()))
+        })
+        .collect()
+}
+
+// The templates directly inside a directory, which need not exist
+fn read_templates(dir: &Path) -> Result<BTreeMap<String, String>, TeraError> {
+    let mut templates = BTreeMap::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(templates);
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| TeraError::chain(format!("Failed to read {}", path.display()), e))?;
+        templates.insert(name.to_string(), content);
+    }
+    Ok(templates)
 }
 
 #[cfg(test)]
@@ -124,6 +247,53 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_library_overrides_and_partials() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().to_path_buf();
+        std::fs::create_dir_all(base.join("terse")).unwrap();
+        std::fs::write(base.join("_rules.md"), "Be kind.").unwrap();
+        std::fs::write(
+            base.join("review.md"),
+            "Review {{ file }}. {% include \"_rules.md\" %}",
+        )
+        .unwrap();
+        std::fs::write(base.join("terse").join("_rules.md"), "Be brief.").unwrap();
+
+        let context = HashMap::from([("file", "main.rs")]);
+        let library = PromptLibrary::load(std::slice::from_ref(&base), None).unwrap();
+        assert_eq!(
+            library.render("review.md", &context).unwrap(),
+            "Review main.rs. Be kind."
+        );
+        // The built-in prompts are still there
+        assert!(library.contains("system.md"));
+
+        // The profile overrides the partial used by the template
+        let library = PromptLibrary::load(&[base], Some("terse")).unwrap();
+        assert_eq!(
+            library.render("review.md", &context).unwrap(),
+            "Review main.rs. Be brief."
+        );
+    }
+
+    #[test]
+    fn test_library_later_dirs_take_precedence() {
+        let data = tempfile::tempdir().unwrap();
+        let config = tempfile::tempdir().unwrap();
+        std::fs::write(data.path().join("mock.md"), "data {{ name }}").unwrap();
+        std::fs::write(config.path().join("mock.md"), "config {{ name }}").unwrap();
+
+        let library = PromptLibrary::load(
+            &[data.path().to_path_buf(), config.path().to_path_buf()],
+            None,
+        )
+        .unwrap();
+        let context = HashMap::from([("name", "Alice")]);
+        assert_eq!(library.render("mock.md", &context).unwrap(), "config Alice");
+        assert!(library.render("missing.md", &context).is_err());
+    }
+
     #[test]
     fn test_load_prompt_with_empty_tools() {
         let template = "### Tool Descriptions\n{% for tool in tools %}\n{{tool.name}}: {{tool.description}}{% endfor %}";
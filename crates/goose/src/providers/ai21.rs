@@ -0,0 +1,124 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::ai21::{create_request, get_usage, response_to_message};
+use super::keys::KeyPool;
+use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const AI21_API_HOST: &str = "https://api.ai21.com";
+pub const AI21_DEFAULT_MODEL: &str = "jamba-large";
+pub const AI21_KNOWN_MODELS: &[&str] = &["jamba-large", "jamba-mini"];
+
+pub const AI21_DOC_URL: &str = "https://docs.ai21.com/reference/jamba-1-6-api-ref";
+
+#[derive(serde::Serialize)]
+pub struct Ai21Provider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_keys: KeyPool,
+    model: ModelConfig,
+}
+
+impl Default for Ai21Provider {
+    fn default() -> Self {
+        let model = ModelConfig::new(Ai21Provider::metadata().default_model);
+        Ai21Provider::from_env(model).expect("Failed to initialize AI21 provider")
+    }
+}
+
+impl Ai21Provider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_keys = KeyPool::from_secret("AI21_API_KEY")?;
+        let host: String = config
+            .get("AI21_HOST")
+            .unwrap_or_else(|_| AI21_API_HOST.to_string());
+
+        let client = http_client("AI21")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_keys,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("studio/v1/chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Authorization", format!("Bearer {}", key))
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for Ai21Provider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "ai21",
+            "AI21 Labs",
+            "Jamba models from AI21 Labs",
+            AI21_DEFAULT_MODEL,
+            AI21_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            AI21_DOC_URL,
+            vec![
+                ConfigKey::new("AI21_API_KEY", true, true, None),
+                ConfigKey::new("AI21_HOST", false, false, Some(AI21_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = vcr::post("ai21", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
@@ -1,15 +1,20 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use reqwest::{Client, StatusCode};
-use serde_json::Value;
-use std::time::Duration;
+use reqwest::{Client, Response, StatusCode};
+use serde_json::{json, Value};
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    message_deltas, ConfigKey, MessageDelta, MessageStream, Provider, ProviderMetadata,
+    ProviderUsage, Usage,
+};
 use super::errors::ProviderError;
-use super::formats::anthropic::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model};
+use super::formats::anthropic::{create_request, get_usage, response_to_message, StreamState};
+use super::keys::KeyPool;
+use super::utils::{emit_debug_trace, get_model, http_client, sse_data};
+use super::{retry, vcr};
 use crate::message::Message;
 use crate::model::ModelConfig;
+use futures::StreamExt;
 use mcp_core::tool::Tool;
 
 pub const ANTHROPIC_DEFAULT_MODEL: &str = "claude-3-5-sonnet-latest";
@@ -26,7 +31,7 @@ pub struct AnthropicProvider {
     #[serde(skip)]
     client: Client,
     host: String,
-    api_key: String,
+    api_keys: KeyPool,
     model: ModelConfig,
 }
 
@@ -40,76 +45,84 @@ impl Default for AnthropicProvider {
 impl AnthropicProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
         let config = crate::config::Config::global();
-        let api_key: String = config.get_secret("ANTHROPIC_API_KEY")?;
+        let api_keys = KeyPool::from_secret("ANTHROPIC_API_KEY")?;
         let host: String = config
             .get("ANTHROPIC_HOST")
             .unwrap_or_else(|_| "https://api.anthropic.com".to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = http_client("ANTHROPIC")?;
 
         Ok(Self {
             client,
             host,
-            api_key,
+            api_keys,
             model,
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn send(&self, payload: &Value) -> Result<Response, ProviderError> {
         let base_url = url::Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
         let url = base_url.join("v1/messages").map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
         })?;
 
-        let response = self
-            .client
-            .post(url)
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .json(&payload)
-            .send()
-            .await?;
-
-        let status = response.status();
-        let payload: Option<Value> = response.json().await.ok();
-
-        // https://docs.anthropic.com/en/api/errors
-        match status {
-            StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
-            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
-                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
-                    Status: {}. Response: {:?}", status, payload)))
-            }
-            StatusCode::BAD_REQUEST => {
-                let mut error_msg = "Unknown error".to_string();
-                if let Some(payload) = &payload {
-                    if let Some(error) = payload.get("error") {
-                    tracing::debug!("Bad Request Error: {error:?}");
-                    error_msg = error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error").to_string();
-                    if error_msg.to_lowercase().contains("too long") || error_msg.to_lowercase().contains("too many") {
-                        return Err(ProviderError::ContextLengthExceeded(error_msg.to_string()));
-                    }
-                }}
-                tracing::debug!(
-                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
-                );
-                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
-            }
-            StatusCode::TOO_MANY_REQUESTS => {
-                Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
-            }
-            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
-                Err(ProviderError::ServerError(format!("{:?}", payload)))
-            }
-            _ => {
-                tracing::debug!(
-                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
-                );
-                Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
-            }
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("x-api-key", key)
+                .header("anthropic-version", "2023-06-01")
+                .json(payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+        Ok(response)
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send(&payload).await?;
+        handle_response(response).await
+    }
+}
+
+/// Map Anthropic's error responses onto provider errors, for the Messages API wherever it
+/// is served from
+pub(super) async fn handle_response(response: Response) -> Result<Value, ProviderError> {
+    let status = response.status();
+    let payload: Option<Value> = response.json().await.ok();
+
+    // https://docs.anthropic.com/en/api/errors
+    match status {
+        StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
+                Status: {}. Response: {:?}", status, payload)))
+        }
+        StatusCode::BAD_REQUEST => {
+            let mut error_msg = "Unknown error".to_string();
+            if let Some(payload) = &payload {
+                if let Some(error) = payload.get("error") {
+                tracing::debug!("Bad Request Error: {error:?}");
+                error_msg = error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error").to_string();
+                if error_msg.to_lowercase().contains("too long") || error_msg.to_lowercase().contains("too many") {
+                    return Err(ProviderError::ContextLengthExceeded(error_msg.to_string()));
+                }
+            }}
+            tracing::debug!(
+                "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+            );
+            Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
+        }
+        StatusCode::TOO_MANY_REQUESTS => {
+            Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+        }
+        StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+            Err(ProviderError::ServerError(format!("{:?}", payload)))
+        }
+        _ => {
+            tracing::debug!(
+                "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+            );
+            Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
         }
     }
 }
@@ -156,7 +169,7 @@ impl Provider for AnthropicProvider {
         let payload = create_request(&self.model, system, messages, tools)?;
 
         // Make request
-        let response = self.post(payload.clone()).await?;
+        let response = vcr::post("anthropic", &payload, || self.post(payload.clone())).await?;
 
         // Parse response
         let message = response_to_message(response.clone())?;
@@ -166,4 +179,48 @@ impl Provider for AnthropicProvider {
         emit_debug_trace(self, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        if vcr::is_active() {
+            let (message, usage) = self.complete(system, messages, tools).await?;
+            let deltas = message_deltas(message, usage).into_iter().map(Ok);
+            return Ok(Box::pin(futures::stream::iter(deltas)));
+        }
+
+        let mut payload = create_request(&self.model, system, messages, tools)?;
+        payload["stream"] = json!(true);
+
+        let response = self.send(&payload).await?;
+        if response.status() != StatusCode::OK {
+            // Errors before the stream starts come back as a whole
+            return Err(match handle_response(response).await {
+                Err(e) => e,
+                Ok(body) => ProviderError::RequestFailed(format!("Unexpected response: {}", body)),
+            });
+        }
+
+        let mut events = sse_data(response);
+        Ok(Box::pin(async_stream::try_stream! {
+            let mut state = StreamState::default();
+            while let Some(data) = events.next().await {
+                let event: Value = serde_json::from_str(&data?).map_err(|e| {
+                    ProviderError::RequestFailed(format!("Invalid event in response stream: {e}"))
+                })?;
+                for delta in state.event_to_deltas(&event)? {
+                    yield delta;
+                }
+            }
+            let usage = state.usage().unwrap_or_else(|e| {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            });
+            let model = state.model.take().unwrap_or_else(|| self.model.model_name.clone());
+            yield MessageDelta::Usage(ProviderUsage::new(model, usage));
+        }))
+    }
 }
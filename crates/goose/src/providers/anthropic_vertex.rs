@@ -0,0 +1,198 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use url::Url;
+
+use super::anthropic::handle_response;
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::anthropic::{create_request, get_usage, response_to_message};
+use super::gcpauth::{GcpAuth, GcpCredentials};
+use super::utils::{emit_debug_trace, get_model, http_client};
+use super::vertexai::endpoint_host;
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+// Claude is served from fewer regions than Gemini
+pub const ANTHROPIC_VERTEX_DEFAULT_LOCATION: &str = "us-east5";
+pub const ANTHROPIC_VERTEX_DEFAULT_MODEL: &str = "claude-3-7-sonnet@20250219";
+pub const ANTHROPIC_VERTEX_KNOWN_MODELS: &[&str] = &[
+    "claude-sonnet-4@20250514",
+    "claude-opus-4@20250514",
+    "claude-3-7-sonnet@20250219",
+    "claude-3-5-sonnet-v2@20241022",
+    "claude-3-5-haiku@20241022",
+];
+
+pub const ANTHROPIC_VERTEX_DOC_URL: &str =
+    "https://cloud.google.com/vertex-ai/generative-ai/docs/partner-models/use-claude";
+
+// The version of the Messages API that Vertex AI serves, sent in the body instead of a header
+const ANTHROPIC_VERTEX_VERSION: &str = "vertex-2023-10-16";
+
+/// Claude models on Google Cloud's Vertex AI, authenticated with application default
+/// credentials instead of an Anthropic API key
+///
+/// Requests and responses are those of Anthropic's Messages API; the model is named in the
+/// URL rather than the body.
+#[derive(serde::Serialize)]
+pub struct AnthropicVertexProvider {
+    #[serde(skip)]
+    client: Client,
+    #[serde(skip)]
+    auth: GcpAuth,
+    project_id: String,
+    location: String,
+    model: ModelConfig,
+}
+
+impl Default for AnthropicVertexProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(AnthropicVertexProvider::metadata().default_model);
+        AnthropicVertexProvider::from_env(model)
+            .expect("Failed to initialize Anthropic on Vertex AI provider")
+    }
+}
+
+impl AnthropicVertexProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let project_id: String = config.get("GCP_PROJECT_ID")?;
+        let location: String = config
+            .get("GCP_LOCATION")
+            .unwrap_or_else(|_| ANTHROPIC_VERTEX_DEFAULT_LOCATION.to_string());
+        let credentials_path: Option<String> = config.get("GOOGLE_APPLICATION_CREDENTIALS").ok();
+        let credentials = GcpCredentials::application_default(credentials_path.as_deref())?;
+
+        let client = http_client("ANTHROPIC_VERTEX")?;
+
+        Ok(Self {
+            client,
+            auth: GcpAuth::new(credentials),
+            project_id,
+            location,
+            model,
+        })
+    }
+
+    fn url(&self) -> Result<Url, ProviderError> {
+        let url = format!(
+            "{}/v1/projects/{}/locations/{}/publishers/anthropic/models/{}:rawPredict",
+            endpoint_host(&self.location),
+            self.project_id,
+            self.location,
+            self.model.model_name
+        );
+        Url::parse(&url).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let token = self
+            .auth
+            .token()
+            .await
+            .map_err(|e| ProviderError::Authentication(e.to_string()))?;
+
+        let request = self
+            .client
+            .post(self.url()?)
+            .bearer_auth(token)
+            .json(&payload);
+        let response = retry::send(request).await?;
+
+        handle_response(response).await
+    }
+}
+
+/// Turn a Messages API request into one for Vertex AI, which takes the API version in the
+/// body and the model in the URL
+fn vertex_payload(mut payload: Value) -> Value {
+    if let Some(payload) = payload.as_object_mut() {
+        payload.remove("model");
+        payload.insert(
+            "anthropic_version".to_string(),
+            json!(ANTHROPIC_VERTEX_VERSION),
+        );
+    }
+    payload
+}
+
+#[async_trait]
+impl Provider for AnthropicVertexProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "anthropic_vertex",
+            "Anthropic on Vertex AI",
+            "Claude models on Google Cloud, with your Google Cloud credentials",
+            ANTHROPIC_VERTEX_DEFAULT_MODEL,
+            ANTHROPIC_VERTEX_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            ANTHROPIC_VERTEX_DOC_URL,
+            vec![
+                ConfigKey::new("GCP_PROJECT_ID", true, false, None),
+                ConfigKey::new(
+                    "GCP_LOCATION",
+                    true,
+                    false,
+                    Some(ANTHROPIC_VERTEX_DEFAULT_LOCATION),
+                ),
+                ConfigKey::new("GOOGLE_APPLICATION_CREDENTIALS", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = vertex_payload(create_request(&self.model, system, messages, tools)?);
+
+        let response =
+            vcr::post("anthropic_vertex", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = get_usage(&response)?;
+
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vertex_payload() -> Result<()> {
+        let model = ModelConfig::new(ANTHROPIC_VERTEX_DEFAULT_MODEL.to_string());
+        let payload = vertex_payload(create_request(
+            &model,
+            "Be brief.",
+            &[Message::user().with_text("Hi")],
+            &[],
+        )?);
+        assert!(payload.get("model").is_none());
+        assert_eq!(payload["anthropic_version"], ANTHROPIC_VERTEX_VERSION);
+        assert_eq!(payload["messages"][0]["content"][0]["text"], "Hi");
+        assert_eq!(payload["max_tokens"], 4096);
+        Ok(())
+    }
+}
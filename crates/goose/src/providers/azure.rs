@@ -2,12 +2,15 @@ use anyhow::Result;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::Value;
-use std::time::Duration;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use super::formats::openai::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
+use super::keys::KeyPool;
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -23,7 +26,7 @@ pub struct AzureProvider {
     #[serde(skip)]
     client: Client,
     endpoint: String,
-    api_key: String,
+    api_keys: KeyPool,
     deployment_name: String,
     api_version: String,
     model: ModelConfig,
@@ -39,21 +42,19 @@ impl Default for AzureProvider {
 impl AzureProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
         let config = crate::config::Config::global();
-        let api_key: String = config.get_secret("AZURE_OPENAI_API_KEY")?;
+        let api_keys = KeyPool::from_secret("AZURE_OPENAI_API_KEY")?;
         let endpoint: String = config.get("AZURE_OPENAI_ENDPOINT")?;
         let deployment_name: String = config.get("AZURE_OPENAI_DEPLOYMENT_NAME")?;
         let api_version: String = config
             .get("AZURE_OPENAI_API_VERSION")
             .unwrap_or_else(|_| AZURE_DEFAULT_API_VERSION.to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = http_client("AZURE_OPENAI")?;
 
         Ok(Self {
             client,
             endpoint,
-            api_key,
+            api_keys,
             deployment_name,
             api_version,
             model,
@@ -70,13 +71,13 @@ impl AzureProvider {
         ));
         base_url.set_query(Some(&format!("api-version={}", self.api_version)));
 
-        let response: reqwest::Response = self
-            .client
-            .post(base_url)
-            .header("api-key", &self.api_key)
-            .json(&payload)
-            .send()
-            .await?;
+        let request = |key: &str| {
+            self.client
+                .post(base_url.clone())
+                .header("api-key", key)
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
 
         handle_response_openai_compat(response).await
     }
@@ -129,7 +130,7 @@ impl Provider for AzureProvider {
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
-        let response = self.post(payload.clone()).await?;
+        let response = vcr::post("azure_openai", &payload, || self.post(payload.clone())).await?;
 
         let message = response_to_message(response.clone())?;
         let usage = match get_usage(&response) {
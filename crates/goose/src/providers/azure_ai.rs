@@ -0,0 +1,204 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const AZURE_AI_DEFAULT_MODEL: &str = "Llama-3.3-70B-Instruct";
+pub const AZURE_AI_KNOWN_MODELS: &[&str] = &[
+    "Llama-3.3-70B-Instruct",
+    "Meta-Llama-3.1-405B-Instruct",
+    "Llama-4-Maverick-17B-128E-Instruct-FP8",
+    "Phi-4",
+    "Mistral-Large-2411",
+    "Mistral-small-2503",
+    "DeepSeek-V3-0324",
+];
+
+pub const AZURE_AI_DOC_URL: &str =
+    "https://learn.microsoft.com/en-us/azure/ai-foundry/concepts/models-inference-examples";
+
+// The version of the Azure AI model inference API, which is versioned apart from Azure OpenAI
+pub const AZURE_AI_DEFAULT_API_VERSION: &str = "2024-05-01-preview";
+
+/// Models deployed serverless on Azure AI Foundry, billed per token as Models-as-a-Service
+///
+/// Two kinds of endpoint are accepted. A serverless deployment has an endpoint of its own,
+/// `https://{deployment}.{region}.models.ai.azure.com`, serving one model and taking its key
+/// as a bearer token. A Foundry resource's `https://{resource}.services.ai.azure.com` serves
+/// every model deployed to it under `models/`, picked by the model in the request, and takes
+/// its key in an `api-key` header as Azure OpenAI does.
+#[derive(Debug, serde::Serialize)]
+pub struct AzureAiProvider {
+    #[serde(skip)]
+    client: Client,
+    endpoint: String,
+    #[serde(skip)]
+    api_key: String,
+    api_version: String,
+    model: ModelConfig,
+}
+
+impl Default for AzureAiProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(AzureAiProvider::metadata().default_model);
+        AzureAiProvider::from_env(model).expect("Failed to initialize Azure AI Foundry provider")
+    }
+}
+
+impl AzureAiProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("AZURE_AI_API_KEY")?;
+        let endpoint: String = config.get("AZURE_AI_ENDPOINT")?;
+        let api_version: String = config
+            .get("AZURE_AI_API_VERSION")
+            .unwrap_or_else(|_| AZURE_AI_DEFAULT_API_VERSION.to_string());
+
+        let client = http_client("AZURE_AI")?;
+
+        Ok(Self {
+            client,
+            endpoint,
+            api_key,
+            api_version,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let (url, serverless) = chat_url(&self.endpoint, &self.api_version)?;
+
+        let request = self.client.post(url);
+        let request = if serverless {
+            request.bearer_auth(&self.api_key)
+        } else {
+            request.header("api-key", &self.api_key)
+        };
+        let request = request
+            // Pass parameters a model doesn't know on to it rather than rejecting the request
+            .header("extra-parameters", "pass-through")
+            .json(&payload);
+        let response = retry::send(request).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+/// The chat completions URL for an endpoint, and whether it is a serverless deployment's
+/// own endpoint rather than a Foundry resource's
+fn chat_url(endpoint: &str, api_version: &str) -> Result<(Url, bool), ProviderError> {
+    let mut url = Url::parse(endpoint)
+        .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+    let serverless = url
+        .host_str()
+        .is_some_and(|host| host.ends_with(".models.ai.azure.com"));
+
+    let path = if serverless {
+        "chat/completions"
+    } else {
+        "models/chat/completions"
+    };
+    url.set_path(path);
+    url.set_query(Some(&format!("api-version={}", api_version)));
+    Ok((url, serverless))
+}
+
+#[async_trait]
+impl Provider for AzureAiProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "azure_ai",
+            "Azure AI Foundry",
+            "Llama, Phi, Mistral and other models deployed serverless on Azure AI Foundry",
+            AZURE_AI_DEFAULT_MODEL,
+            AZURE_AI_KNOWN_MODELS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            AZURE_AI_DOC_URL,
+            vec![
+                ConfigKey::new("AZURE_AI_API_KEY", true, true, None),
+                ConfigKey::new("AZURE_AI_ENDPOINT", true, false, None),
+                ConfigKey::new(
+                    "AZURE_AI_API_VERSION",
+                    false,
+                    false,
+                    Some(AZURE_AI_DEFAULT_API_VERSION),
+                ),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        let response = vcr::post("azure_ai", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_url() {
+        let (url, serverless) = chat_url(
+            "https://Llama-3-3-70B-abcd.eastus2.models.ai.azure.com/",
+            AZURE_AI_DEFAULT_API_VERSION,
+        )
+        .unwrap();
+        assert!(serverless);
+        assert_eq!(
+            url.as_str(),
+            "https://llama-3-3-70b-abcd.eastus2.models.ai.azure.com/chat/completions?api-version=2024-05-01-preview"
+        );
+
+        let (url, serverless) = chat_url(
+            "https://my-foundry.services.ai.azure.com/models",
+            "2025-05-01",
+        )
+        .unwrap();
+        assert!(!serverless);
+        assert_eq!(
+            url.as_str(),
+            "https://my-foundry.services.ai.azure.com/models/chat/completions?api-version=2025-05-01"
+        );
+    }
+}
@@ -1,10 +1,14 @@
 use anyhow::Result;
+use futures::stream::BoxStream;
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
 use super::errors::ProviderError;
-use crate::message::Message;
+use super::pricing::{calculate_cost, Cost};
+use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
+use mcp_core::ToolError;
 
 /// Metadata about a provider's configuration requirements and capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,11 +87,16 @@ impl ConfigKey {
 pub struct ProviderUsage {
     pub model: String,
     pub usage: Usage,
+    /// Cost in USD, if pricing for the model is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<Cost>,
 }
 
 impl ProviderUsage {
+    /// Create a new ProviderUsage, computing the cost from the pricing table
     pub fn new(model: String, usage: Usage) -> Self {
-        Self { model, usage }
+        let cost = calculate_cost(&model, &usage);
+        Self { model, usage, cost }
     }
 }
 
@@ -96,6 +105,21 @@ pub struct Usage {
     pub input_tokens: Option<i32>,
     pub output_tokens: Option<i32>,
     pub total_tokens: Option<i32>,
+    /// Input tokens served from the provider's prompt cache, included in `input_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_tokens: Option<i32>,
+    /// Input tokens written to the provider's prompt cache, included in `input_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_tokens: Option<i32>,
+    /// Output tokens the model spent reasoning before it answered, included in `output_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<i32>,
+    /// Input tokens of audio, included in `input_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_input_tokens: Option<i32>,
+    /// Output tokens of audio, included in `output_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_output_tokens: Option<i32>,
 }
 
 impl Usage {
@@ -108,8 +132,102 @@ impl Usage {
             input_tokens,
             output_tokens,
             total_tokens,
+            cache_read_tokens: None,
+            cache_write_tokens: None,
+            reasoning_tokens: None,
+            audio_input_tokens: None,
+            audio_output_tokens: None,
         }
     }
+
+    pub fn with_cache_tokens(
+        mut self,
+        cache_read_tokens: Option<i32>,
+        cache_write_tokens: Option<i32>,
+    ) -> Self {
+        self.cache_read_tokens = cache_read_tokens;
+        self.cache_write_tokens = cache_write_tokens;
+        self
+    }
+
+    pub fn with_reasoning_tokens(mut self, reasoning_tokens: Option<i32>) -> Self {
+        self.reasoning_tokens = reasoning_tokens;
+        self
+    }
+
+    pub fn with_audio_tokens(
+        mut self,
+        audio_input_tokens: Option<i32>,
+        audio_output_tokens: Option<i32>,
+    ) -> Self {
+        self.audio_input_tokens = audio_input_tokens;
+        self.audio_output_tokens = audio_output_tokens;
+        self
+    }
+}
+
+/// A piece of a message as the model generates it, from `Provider::stream`
+#[derive(Debug, Clone)]
+pub enum MessageDelta {
+    /// Text to append to the message
+    Text(String),
+    /// Reasoning to append to the message's thinking
+    Thinking(String),
+    /// A fragment of the tool call at `index` in the message
+    ///
+    /// The first fragment of a call has its id and name, and the fragments' arguments are
+    /// pieces of JSON text that make up the call's arguments once appended.
+    ToolCall {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    },
+    /// A tool call the model made that couldn't be interpreted
+    InvalidToolCall { id: String, error: ToolError },
+    /// The usage of the whole message, once it is generated
+    Usage(ProviderUsage),
+}
+
+/// The deltas of a message from `Provider::stream`
+pub type MessageStream<'a> = BoxStream<'a, Result<MessageDelta, ProviderError>>;
+
+/// Split a complete message into the deltas that would have streamed it
+pub fn message_deltas(message: Message, usage: ProviderUsage) -> Vec<MessageDelta> {
+    let mut deltas = Vec::new();
+    let mut index = 0;
+    for content in message.content {
+        match content {
+            MessageContent::Text(text) => deltas.push(MessageDelta::Text(text.text)),
+            MessageContent::Thinking(thinking) => {
+                deltas.push(MessageDelta::Thinking(thinking.thinking))
+            }
+            MessageContent::ToolRequest(request) => match request.tool_call {
+                Ok(call) => {
+                    deltas.push(MessageDelta::ToolCall {
+                        index,
+                        id: Some(request.id),
+                        name: Some(call.name),
+                        arguments: call.arguments.to_string(),
+                    });
+                    index += 1;
+                }
+                Err(error) => deltas.push(MessageDelta::InvalidToolCall {
+                    id: request.id,
+                    error,
+                }),
+            },
+            _ => {}
+        }
+    }
+    deltas.push(MessageDelta::Usage(usage));
+    deltas
+}
+
+/// A stream of a complete message, as `Provider::stream` returns it all at once
+pub fn message_stream<'a>(message: Message, usage: ProviderUsage) -> MessageStream<'a> {
+    let deltas = message_deltas(message, usage).into_iter().map(Ok);
+    Box::pin(futures::stream::iter(deltas))
 }
 
 use async_trait::async_trait;
@@ -142,6 +260,68 @@ pub trait Provider: Send + Sync {
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError>;
 
+    /// Generate the next message like `complete`, returning its pieces as they are generated
+    ///
+    /// Providers whose APIs stream override this; by default the message is completed and
+    /// returned as a stream all at once. The stream ends with the `MessageDelta::Usage`.
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let (message, usage) = self.complete(system, messages, tools).await?;
+        Ok(message_stream(message, usage))
+    }
+
+    /// Generate the next message like `complete`, giving up as soon as `cancel` is cancelled
+    ///
+    /// A request is aborted when its future is dropped, so by default the completion is
+    /// raced against the token and dropped if the token wins.
+    async fn complete_cancellable(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel: &CancellationToken,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(ProviderError::Cancelled),
+            result = self.complete(system, messages, tools) => result,
+        }
+    }
+
+    /// Stream the next message like `stream`, ending with `ProviderError::Cancelled` as soon as
+    /// `cancel` is cancelled
+    async fn stream_cancellable(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel: &CancellationToken,
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let mut stream = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return Err(ProviderError::Cancelled),
+            stream = self.stream(system, messages, tools) => stream?,
+        };
+        let cancel = cancel.clone();
+        Ok(Box::pin(async_stream::try_stream! {
+            loop {
+                let delta = tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => Err(ProviderError::Cancelled)?,
+                    delta = futures::StreamExt::next(&mut stream) => delta,
+                };
+                match delta {
+                    Some(delta) => yield delta?,
+                    None => break,
+                }
+            }
+        }))
+    }
+
     /// Get the model config from the provider
     fn get_model_config(&self) -> ModelConfig;
 }
@@ -178,4 +358,64 @@ mod tests {
 
         Ok(())
     }
+
+    struct CompleteOnly;
+
+    #[async_trait]
+    impl Provider for CompleteOnly {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let message = Message::assistant()
+                .with_text("Listing.")
+                .with_tool_request(
+                    "1",
+                    Ok(mcp_core::ToolCall::new("shell", json!({"command": "ls"}))),
+                );
+            let usage = Usage::new(Some(10), Some(5), Some(15));
+            Ok((message, ProviderUsage::new("mock".to_string(), usage)))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("mock".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_completion() {
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = CompleteOnly
+            .complete_cancellable("", &[], &[], &cancel)
+            .await;
+        assert!(matches!(result, Err(ProviderError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_stream_falls_back_to_complete() -> Result<()> {
+        use futures::TryStreamExt;
+
+        let deltas: Vec<MessageDelta> = CompleteOnly
+            .stream("", &[], &[])
+            .await?
+            .try_collect()
+            .await?;
+        assert!(matches!(&deltas[0], MessageDelta::Text(text) if text == "Listing."));
+        assert!(matches!(
+            &deltas[1],
+            MessageDelta::ToolCall { index: 0, id: Some(id), arguments, .. }
+                if id == "1" && arguments == r#"{"command":"ls"}"#
+        ));
+        assert!(
+            matches!(&deltas[2], MessageDelta::Usage(usage) if usage.usage.total_tokens == Some(15))
+        );
+        Ok(())
+    }
 }
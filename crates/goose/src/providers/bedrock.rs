@@ -1,10 +1,12 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use aws_sdk_bedrockruntime::operation::converse::ConverseError;
+use aws_sdk_bedrockruntime::operation::converse_stream::ConverseStreamError;
+use aws_sdk_bedrockruntime::types::error::ConverseStreamOutputError;
 use aws_sdk_bedrockruntime::{types as bedrock, Client};
 use mcp_core::Tool;
 
-use super::base::{Provider, ProviderMetadata, ProviderUsage};
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
 use crate::message::Message;
 use crate::model::ModelConfig;
@@ -12,7 +14,7 @@ use crate::providers::utils::emit_debug_trace;
 
 // Import the migrated helper functions from providers/formats/bedrock.rs
 use super::formats::bedrock::{
-    from_bedrock_message, from_bedrock_usage, to_bedrock_message, to_bedrock_tool_config,
+    create_request, from_bedrock_message, from_bedrock_usage, BedrockStreamState,
 };
 
 pub const BEDROCK_DOC_LINK: &str =
@@ -77,23 +79,18 @@ impl Provider for BedrockProvider {
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         let model_name = &self.model.model_name;
 
-        let mut request = self
+        let request = create_request(&self.model, system, messages, tools)?;
+        let debug_payload = request.to_json(model_name)?;
+        let response = self
             .client
             .converse()
-            .system(bedrock::SystemContentBlock::Text(system.to_string()))
             .model_id(model_name.to_string())
-            .set_messages(Some(
-                messages
-                    .iter()
-                    .map(to_bedrock_message)
-                    .collect::<Result<_>>()?,
-            ));
-
-        if !tools.is_empty() {
-            request = request.tool_config(to_bedrock_tool_config(tools)?);
-        }
-
-        let response = request.send().await;
+            .set_system(Some(request.system))
+            .set_messages(Some(request.messages))
+            .set_tool_config(request.tool_config)
+            .set_inference_config(request.inference_config)
+            .send()
+            .await;
 
         let response = match response {
             Ok(response) => response,
@@ -143,12 +140,6 @@ impl Provider for BedrockProvider {
 
         let message = from_bedrock_message(&message)?;
 
-        // Add debug trace with input context
-        let debug_payload = serde_json::json!({
-            "system": system,
-            "messages": messages,
-            "tools": tools
-        });
         emit_debug_trace(
             &self.model,
             &debug_payload,
@@ -159,4 +150,81 @@ impl Provider for BedrockProvider {
         let provider_usage = ProviderUsage::new(model_name.to_string(), usage);
         Ok((message, provider_usage))
     }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let model_name = &self.model.model_name;
+
+        let request = create_request(&self.model, system, messages, tools)?;
+        let request = self
+            .client
+            .converse_stream()
+            .model_id(model_name.to_string())
+            .set_system(Some(request.system))
+            .set_messages(Some(request.messages))
+            .set_tool_config(request.tool_config)
+            .set_inference_config(request.inference_config);
+
+        let mut response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => {
+                return Err(match err.into_service_error() {
+                    ConverseStreamError::AccessDeniedException(err) => {
+                        ProviderError::Authentication(format!("Failed to call Bedrock: {:?}", err))
+                    }
+                    ConverseStreamError::ThrottlingException(err) => {
+                        ProviderError::RateLimitExceeded(format!(
+                            "Failed to call Bedrock: {:?}",
+                            err
+                        ))
+                    }
+                    ConverseStreamError::ValidationException(err)
+                        if err
+                            .message()
+                            .unwrap_or_default()
+                            .contains("Input is too long for requested model.") =>
+                    {
+                        ProviderError::ContextLengthExceeded(format!(
+                            "Failed to call Bedrock: {:?}",
+                            err
+                        ))
+                    }
+                    ConverseStreamError::ModelErrorException(err) => {
+                        ProviderError::ExecutionError(format!("Failed to call Bedrock: {:?}", err))
+                    }
+                    err => {
+                        ProviderError::ServerError(format!("Failed to call Bedrock: {:?}", err,))
+                    }
+                });
+            }
+        };
+
+        Ok(Box::pin(async_stream::try_stream! {
+            let mut state = BedrockStreamState::default();
+            loop {
+                let event = match response.stream.recv().await {
+                    Ok(Some(event)) => event,
+                    Ok(None) => break,
+                    // Errors can also come once the stream has started
+                    Err(err) => Err(match err.into_service_error() {
+                        ConverseStreamOutputError::ThrottlingException(err) => {
+                            ProviderError::RateLimitExceeded(format!("Bedrock stream failed: {:?}", err))
+                        }
+                        ConverseStreamOutputError::ValidationException(err) => {
+                            ProviderError::RequestFailed(format!("Bedrock stream failed: {:?}", err))
+                        }
+                        err => ProviderError::ServerError(format!("Bedrock stream failed: {:?}", err)),
+                    })?,
+                };
+                for delta in state.event_to_deltas(&event) {
+                    yield delta;
+                }
+            }
+            yield state.finish(model_name.to_string());
+        }))
+    }
 }
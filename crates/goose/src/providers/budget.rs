@@ -0,0 +1,412 @@
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::inspect_stream;
+use crate::config::Config;
+use crate::events::{self, Event};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::usage::UsageTracker;
+use mcp_core::tool::Tool;
+
+/// Config key holding the `BudgetConfig`
+pub const BUDGET_CONFIG_KEY: &str = "GOOSE_BUDGET";
+
+// Spend across every budgeted provider in this process
+static GLOBAL_SPEND: Lazy<Mutex<Spend>> = Lazy::new(|| Mutex::new(Spend::default()));
+
+// Spend per session across every budgeted provider, keyed by session id
+static SESSION_SPEND: Lazy<Mutex<HashMap<Option<String>, Spend>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Cost (USD) and token limits for a single scope
+///
+/// Crossing a soft limit logs a warning, reaching a hard limit stops further requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetLimits {
+    pub soft_cost: Option<f64>,
+    pub hard_cost: Option<f64>,
+    pub soft_tokens: Option<i64>,
+    pub hard_tokens: Option<i64>,
+}
+
+impl BudgetLimits {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn soft_exceeded(&self, spend: &Spend) -> bool {
+        self.soft_cost.is_some_and(|limit| spend.cost >= limit)
+            || self.soft_tokens.is_some_and(|limit| spend.tokens >= limit)
+    }
+
+    fn hard_exceeded(&self, spend: &Spend) -> bool {
+        self.hard_cost.is_some_and(|limit| spend.cost >= limit)
+            || self.hard_tokens.is_some_and(|limit| spend.tokens >= limit)
+    }
+}
+
+/// Budgets for the current session and for the whole process, read from `GOOSE_BUDGET`
+///
+/// ```yaml
+/// GOOSE_BUDGET:
+///   session:
+///     soft_cost: 1.0
+///     hard_cost: 5.0
+///   global:
+///     hard_tokens: 2000000
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    #[serde(default)]
+    pub session: BudgetLimits,
+    #[serde(default)]
+    pub global: BudgetLimits,
+}
+
+impl BudgetConfig {
+    pub fn from_config() -> Self {
+        Config::global().get(BUDGET_CONFIG_KEY).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.session.is_empty() && self.global.is_empty()
+    }
+}
+
+/// Accumulated spend for a budget scope
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Spend {
+    pub cost: f64,
+    pub tokens: i64,
+}
+
+impl Spend {
+    fn record(&mut self, usage: &ProviderUsage) {
+        let tokens = usage.usage.total_tokens.unwrap_or_else(|| {
+            usage.usage.input_tokens.unwrap_or(0) + usage.usage.output_tokens.unwrap_or(0)
+        });
+        self.tokens += tokens as i64;
+        self.cost += usage.cost.map(|c| c.total).unwrap_or(0.0);
+    }
+}
+
+/// Which budget a status refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetScope {
+    Session,
+    Global,
+}
+
+impl std::fmt::Display for BudgetScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetScope::Session => write!(f, "session"),
+            BudgetScope::Global => write!(f, "global"),
+        }
+    }
+}
+
+/// The state of a budget when a limit is reached
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub scope: BudgetScope,
+    pub spend: Spend,
+    pub limits: BudgetLimits,
+}
+
+impl std::fmt::Display for BudgetStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} budget reached: spent ${:.4} and {} tokens",
+            self.scope, self.spend.cost, self.spend.tokens
+        )
+    }
+}
+
+/// Called when a hard limit is reached; returning true allows the session to continue
+/// past its budget.
+pub type BudgetConfirmation = Arc<dyn Fn(&BudgetStatus) -> bool + Send + Sync>;
+
+/// A provider wrapper that enforces `BudgetConfig` on every request
+///
+/// Session spend is shared by every budgeted provider working in the same session, which is
+/// the one `UsageTracker::global()` attributes requests to unless set with `with_session`.
+pub struct BudgetProvider {
+    inner: Box<dyn Provider>,
+    config: BudgetConfig,
+    session: Option<String>,
+    confirmation: Option<BudgetConfirmation>,
+    approved: Mutex<HashSet<Option<String>>>,
+}
+
+impl BudgetProvider {
+    pub fn new(inner: Box<dyn Provider>, config: BudgetConfig) -> Self {
+        Self {
+            inner,
+            config,
+            session: None,
+            confirmation: None,
+            approved: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Charge every request to the given session instead of the tracked one
+    pub fn with_session(mut self, session_id: String) -> Self {
+        self.session = Some(session_id);
+        self
+    }
+
+    /// Ask for confirmation at the hard limit instead of failing immediately
+    pub fn with_confirmation(mut self, confirmation: BudgetConfirmation) -> Self {
+        self.confirmation = Some(confirmation);
+        self
+    }
+
+    /// The session that requests are charged to
+    pub fn session_id(&self) -> Option<String> {
+        self.session
+            .clone()
+            .or_else(|| UsageTracker::global().session())
+    }
+
+    /// Spend recorded by every budgeted provider in the current session so far
+    pub fn session_spend(&self) -> Spend {
+        SESSION_SPEND
+            .lock()
+            .unwrap()
+            .get(&self.session_id())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Spend recorded by every budgeted provider in this process
+    pub fn global_spend() -> Spend {
+        *GLOBAL_SPEND.lock().unwrap()
+    }
+
+    fn statuses(&self) -> [BudgetStatus; 2] {
+        [
+            BudgetStatus {
+                scope: BudgetScope::Session,
+                spend: self.session_spend(),
+                limits: self.config.session,
+            },
+            BudgetStatus {
+                scope: BudgetScope::Global,
+                spend: Self::global_spend(),
+                limits: self.config.global,
+            },
+        ]
+    }
+
+    fn check_hard_limits(&self) -> Result<(), ProviderError> {
+        let session_id = self.session_id();
+        if self.approved.lock().unwrap().contains(&session_id) {
+            return Ok(());
+        }
+
+        for status in self.statuses() {
+            if !status.limits.hard_exceeded(&status.spend) {
+                continue;
+            }
+            match &self.confirmation {
+                Some(confirm) if confirm(&status) => {
+                    self.approved.lock().unwrap().insert(session_id);
+                    return Ok(());
+                }
+                _ => return Err(ProviderError::BudgetExceeded(status.to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&self, usage: &ProviderUsage) {
+        let before = self.statuses();
+        SESSION_SPEND
+            .lock()
+            .unwrap()
+            .entry(self.session_id())
+            .or_default()
+            .record(usage);
+        GLOBAL_SPEND.lock().unwrap().record(usage);
+
+        // Only warn on the request that crosses the soft limit
+        for (before, after) in before.iter().zip(self.statuses()) {
+            if !before.limits.soft_exceeded(&before.spend)
+                && after.limits.soft_exceeded(&after.spend)
+            {
+                tracing::warn!(
+                    scope = %after.scope,
+                    cost = after.spend.cost,
+                    tokens = after.spend.tokens,
+                    "Soft {} budget limit reached",
+                    after.scope
+                );
+                events::emit(Event::BudgetWarning {
+                    scope: after.scope.to_string(),
+                    cost: after.spend.cost,
+                    tokens: after.spend.tokens,
+                });
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for BudgetProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.check_hard_limits()?;
+        let (message, usage) = self.inner.complete(system, messages, tools).await?;
+        self.record(&usage);
+        Ok((message, usage))
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        self.check_hard_limits()?;
+        let stream = self.inner.stream(system, messages, tools).await?;
+        let model = self.get_model_config().model_name;
+        Ok(inspect_stream(stream, model, |result| {
+            if let Ok((_, usage)) = result {
+                self.record(usage);
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+
+    struct MockProvider;
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("mock".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text("Mock response"),
+                ProviderUsage::new("mock".to_string(), Usage::new(Some(6), Some(4), Some(10))),
+            ))
+        }
+    }
+
+    fn session_limits(soft_tokens: i64, hard_tokens: i64) -> BudgetConfig {
+        BudgetConfig {
+            session: BudgetLimits {
+                soft_tokens: Some(soft_tokens),
+                hard_tokens: Some(hard_tokens),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hard_limit_returns_budget_exceeded() {
+        let provider = BudgetProvider::new(Box::new(MockProvider), session_limits(10, 20))
+            .with_session("hard-limit".to_string());
+
+        provider.complete("", &[], &[]).await.unwrap();
+        provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(provider.session_spend().tokens, 20);
+
+        let result = provider.complete("", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::BudgetExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_allows_continuing() {
+        let provider = BudgetProvider::new(Box::new(MockProvider), session_limits(5, 10))
+            .with_session("confirmed".to_string())
+            .with_confirmation(Arc::new(|status| status.scope == BudgetScope::Session));
+
+        for _ in 0..3 {
+            provider.complete("", &[], &[]).await.unwrap();
+        }
+        assert_eq!(provider.session_spend().tokens, 30);
+    }
+
+    #[tokio::test]
+    async fn test_declined_confirmation() {
+        let provider = BudgetProvider::new(Box::new(MockProvider), session_limits(5, 10))
+            .with_session("declined".to_string())
+            .with_confirmation(Arc::new(|_| false));
+
+        provider.complete("", &[], &[]).await.unwrap();
+        let result = provider.complete("", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::BudgetExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn test_spend_is_shared_within_a_session() {
+        let first = BudgetProvider::new(Box::new(MockProvider), session_limits(10, 20))
+            .with_session("shared".to_string());
+        let second = BudgetProvider::new(Box::new(MockProvider), session_limits(10, 20))
+            .with_session("shared".to_string());
+        let other = BudgetProvider::new(Box::new(MockProvider), session_limits(10, 20))
+            .with_session("other".to_string());
+
+        first.complete("", &[], &[]).await.unwrap();
+        second.complete("", &[], &[]).await.unwrap();
+        assert_eq!(first.session_spend().tokens, 20);
+        assert_eq!(second.session_spend().tokens, 20);
+
+        let result = second.complete("", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::BudgetExceeded(_))));
+
+        // A different session starts with its own budget
+        other.complete("", &[], &[]).await.unwrap();
+        assert_eq!(other.session_spend().tokens, 10);
+    }
+
+    #[test]
+    fn test_budget_config_deserialization() {
+        let config: BudgetConfig = serde_json::from_value(serde_json::json!({
+            "session": {"soft_cost": 1.0, "hard_cost": 5.0},
+        }))
+        .unwrap();
+
+        assert_eq!(config.session.hard_cost, Some(5.0));
+        assert!(config.global.is_empty());
+        assert!(!config.is_empty());
+    }
+}
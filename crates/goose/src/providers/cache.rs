@@ -0,0 +1,420 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::base::{
+    message_stream, MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
+use super::errors::ProviderError;
+use super::formats::streaming::inspect_stream;
+use super::vcr::{request_hash, request_payload};
+use crate::config::{Config, APP_STRATEGY};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the `ResponseCacheConfig`
+pub const RESPONSE_CACHE_CONFIG_KEY: &str = "GOOSE_RESPONSE_CACHE";
+
+/// Config key holding how many responses the in-memory cache keeps, 0 to turn it off
+pub const MEMORY_CACHE_CONFIG_KEY: &str = "GOOSE_MEMORY_CACHE_SIZE";
+
+const DEFAULT_MEMORY_CACHE_SIZE: usize = 256;
+
+// The responses shared by every deterministic provider in this process
+static MEMORY: OnceCell<Arc<Mutex<MemoryCache>>> = OnceCell::new();
+
+/// How long cached responses are kept and where
+///
+/// ```yaml
+/// GOOSE_RESPONSE_CACHE:
+///   ttl_secs: 86400
+///   dir: ./responses
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseCacheConfig {
+    pub ttl_secs: u64,
+    /// The directory holding one file per cached request, the default cache dir when not set
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 24 * 60 * 60,
+            dir: None,
+        }
+    }
+}
+
+impl ResponseCacheConfig {
+    /// Read `GOOSE_RESPONSE_CACHE`, returning None when it is not set
+    pub fn from_config() -> Option<Self> {
+        Config::global().get(RESPONSE_CACHE_CONFIG_KEY).ok()
+    }
+
+    /// The configured directory, or the default one, which needs a home dir
+    ///
+    /// - macOS/Linux: ~/.cache/goose/responses
+    /// - Windows:     ~\AppData\Local\Block\goose\cache\responses
+    pub fn dir(&self) -> Result<PathBuf> {
+        match &self.dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(choose_app_strategy(APP_STRATEGY.clone())?.in_cache_dir("responses")),
+        }
+    }
+}
+
+/// A response stored in the cache, as `{hash}.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResponse {
+    created: DateTime<Utc>,
+    message: Message,
+    usage: ProviderUsage,
+}
+
+impl CachedResponse {
+    fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// A provider wrapper that answers repeated requests from a cache on disk
+///
+/// Responses are stored under the hash of the provider, model, system prompt, messages and
+/// tools, the same hash cassettes are replayed by, so re-running a recipe gets the same answers without
+/// waiting or paying for them again. Only successful responses are cached, and a cached
+/// response reports no usage since nothing was billed for it.
+pub struct CachingProvider {
+    inner: Box<dyn Provider>,
+    name: String,
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl CachingProvider {
+    /// Cache the responses in `dir`, see `ResponseCacheConfig::dir`
+    pub fn new(inner: Box<dyn Provider>, name: &str, dir: PathBuf, ttl_secs: u64) -> Self {
+        Self {
+            inner,
+            name: name.to_string(),
+            dir,
+            ttl: Duration::seconds(ttl_secs as i64),
+        }
+    }
+
+    fn lookup(&self, path: &Path) -> Option<CachedResponse> {
+        let cached = CachedResponse::load(path).ok()?;
+        if cached.created + self.ttl <= Utc::now() {
+            let _ = std::fs::remove_file(path);
+            return None;
+        }
+        Some(cached)
+    }
+
+    fn path(&self, system: &str, messages: &[Message], tools: &[Tool]) -> PathBuf {
+        let model = self.inner.get_model_config().model_name;
+        let request = request_payload(&self.name, &model, system, messages, tools);
+        self.dir.join(format!("{}.json", request_hash(&request)))
+    }
+
+    fn cached(&self, path: &Path) -> Option<(Message, ProviderUsage)> {
+        let cached = self.lookup(path)?;
+        tracing::debug!("Answered from the response cache at {}", path.display());
+        Some((
+            cached.message,
+            ProviderUsage::new(cached.usage.model, Usage::default()),
+        ))
+    }
+
+    fn store(path: &Path, message: &Message, usage: &ProviderUsage) {
+        let cached = CachedResponse {
+            created: Utc::now(),
+            message: message.clone(),
+            usage: usage.clone(),
+        };
+        if let Err(e) = cached.save(path) {
+            tracing::warn!(
+                "Failed to write {} to the response cache: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CachingProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let path = self.path(system, messages, tools);
+        if let Some(cached) = self.cached(&path) {
+            return Ok(cached);
+        }
+
+        let (message, usage) = self.inner.complete(system, messages, tools).await?;
+        Self::store(&path, &message, &usage);
+        Ok((message, usage))
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let path = self.path(system, messages, tools);
+        if let Some((message, usage)) = self.cached(&path) {
+            return Ok(message_stream(message, usage));
+        }
+
+        let stream = self.inner.stream(system, messages, tools).await?;
+        let model = self.get_model_config().model_name;
+        Ok(inspect_stream(stream, model, move |result| {
+            if let Ok((message, usage)) = result {
+                Self::store(&path, message, usage);
+            }
+        }))
+    }
+}
+
+/// The in-memory cache shared across the process, or None when `GOOSE_MEMORY_CACHE_SIZE` is 0
+pub fn memory_cache() -> Option<Arc<Mutex<MemoryCache>>> {
+    let capacity: usize = Config::global()
+        .get(MEMORY_CACHE_CONFIG_KEY)
+        .unwrap_or(DEFAULT_MEMORY_CACHE_SIZE);
+    if capacity == 0 {
+        return None;
+    }
+    Some(
+        MEMORY
+            .get_or_init(|| Arc::new(Mutex::new(MemoryCache::new(capacity))))
+            .clone(),
+    )
+}
+
+/// Responses by request hash, dropping the least recently used once full
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    entries: HashMap<String, (Message, String)>,
+    /// Hashes from the least to the most recently used
+    order: VecDeque<String>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// The message and model of a cached response
+    fn get(&mut self, hash: &str) -> Option<(Message, String)> {
+        let entry = self.entries.get(hash)?.clone();
+        self.touch(hash);
+        Some(entry)
+    }
+
+    fn insert(&mut self, hash: String, message: Message, model: String) {
+        if self
+            .entries
+            .insert(hash.clone(), (message, model))
+            .is_some()
+        {
+            self.touch(&hash);
+            return;
+        }
+        self.order.push_back(hash);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn touch(&mut self, hash: &str) {
+        if let Some(position) = self.order.iter().position(|h| h == hash) {
+            if let Some(hash) = self.order.remove(position) {
+                self.order.push_back(hash);
+            }
+        }
+    }
+}
+
+/// A provider wrapper that answers repeated requests from memory
+///
+/// Meant for temperature 0 models, such as the ones summarizing or classifying for goose
+/// itself, where the same prompt gets the same answer and asking again within a session
+/// only costs time and money. Nothing is written to disk, so the cache is gone with the
+/// process, and a cached response reports no usage.
+pub struct MemoizingProvider {
+    inner: Box<dyn Provider>,
+    name: String,
+    cache: Arc<Mutex<MemoryCache>>,
+}
+
+impl MemoizingProvider {
+    pub fn new(inner: Box<dyn Provider>, name: &str, cache: Arc<Mutex<MemoryCache>>) -> Self {
+        Self {
+            inner,
+            name: name.to_string(),
+            cache,
+        }
+    }
+
+    fn hash(&self, system: &str, messages: &[Message], tools: &[Tool]) -> String {
+        let model = self.inner.get_model_config().model_name;
+        request_hash(&request_payload(
+            &self.name, &model, system, messages, tools,
+        ))
+    }
+
+    fn cached(&self, hash: &str) -> Option<(Message, ProviderUsage)> {
+        let (message, model) = self.cache.lock().unwrap().get(hash)?;
+        Some((message, ProviderUsage::new(model, Usage::default())))
+    }
+
+    fn store(&self, hash: String, message: &Message, usage: &ProviderUsage) {
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(hash, message.clone(), usage.model.clone());
+    }
+}
+
+#[async_trait]
+impl Provider for MemoizingProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let hash = self.hash(system, messages, tools);
+        if let Some(cached) = self.cached(&hash) {
+            return Ok(cached);
+        }
+
+        let (message, usage) = self.inner.complete(system, messages, tools).await?;
+        self.store(hash, &message, &usage);
+        Ok((message, usage))
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let hash = self.hash(system, messages, tools);
+        if let Some((message, usage)) = self.cached(&hash) {
+            return Ok(message_stream(message, usage));
+        }
+
+        let stream = self.inner.stream(system, messages, tools).await?;
+        let model = self.get_model_config().model_name;
+        Ok(inspect_stream(stream, model, move |result| {
+            if let Ok((message, usage)) = result {
+                self.store(hash, message, usage);
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    #[tokio::test]
+    async fn test_repeated_request_is_cached() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mock = MockProvider::default()
+            .with_usage(Usage::new(Some(10), Some(5), Some(15)))
+            .with_text("first")
+            .with_text("second");
+        let provider =
+            CachingProvider::new(Box::new(mock.clone()), "mock", dir.path().to_path_buf(), 60);
+
+        let hello = [Message::user().with_text("hello")];
+        let (message, usage) = provider.complete("system", &hello, &[]).await?;
+        assert_eq!(message.as_concat_text(), "first");
+        assert_eq!(usage.usage.total_tokens, Some(15));
+
+        let (message, usage) = provider.complete("system", &hello, &[]).await?;
+        assert_eq!(message.as_concat_text(), "first");
+        assert_eq!(usage.usage.total_tokens, None);
+        assert_eq!(mock.requests().len(), 1);
+
+        // Another request isn't answered from the cache
+        let bye = [Message::user().with_text("bye")];
+        let (message, _) = provider.complete("system", &bye, &[]).await?;
+        assert_eq!(message.as_concat_text(), "second");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memoized_until_evicted() -> Result<()> {
+        let mock = MockProvider::default()
+            .with_usage(Usage::new(Some(10), Some(5), Some(15)))
+            .with_text("title")
+            .with_text("summary")
+            .with_text("title again");
+        let cache = Arc::new(Mutex::new(MemoryCache::new(1)));
+        let provider = MemoizingProvider::new(Box::new(mock.clone()), "mock", cache);
+
+        let title = [Message::user().with_text("title this")];
+        provider.complete("system", &title, &[]).await?;
+        let (message, usage) = provider.complete("system", &title, &[]).await?;
+        assert_eq!(message.as_concat_text(), "title");
+        assert_eq!(usage.usage.total_tokens, None);
+        assert_eq!(mock.requests().len(), 1);
+
+        // Only one response fits, so the summary evicts the title
+        let summary = [Message::user().with_text("summarize this")];
+        provider.complete("system", &summary, &[]).await?;
+        let (message, _) = provider.complete("system", &title, &[]).await?;
+        assert_eq!(message.as_concat_text(), "title again");
+        assert_eq!(mock.requests().len(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,513 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use candle_core::quantized::gguf_file;
+use candle_core::{Device, Tensor};
+use candle_transformers::generation::LogitsProcessor;
+use candle_transformers::models::{quantized_llama, quantized_qwen2};
+use etcetera::{choose_app_strategy, AppStrategy};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+use tokio::sync::OnceCell;
+use tokio_util::sync::CancellationToken;
+
+use super::base::{
+    message_stream, MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
+use super::errors::ProviderError;
+use super::formats::chat_template::{render_prompt, response_to_message, ChatTemplate};
+use super::utils::emit_debug_trace;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const CANDLE_DEFAULT_MODEL: &str = "qwen2.5-1.5b-instruct";
+pub const CANDLE_DOC_URL: &str = "https://github.com/huggingface/candle";
+
+/// Config key holding the `CandleOptions`, only needed for models outside the known ones
+///
+/// ```yaml
+/// CANDLE_OPTIONS:
+///   architecture: llama
+///   template: llama3
+///   gguf_repo: bartowski/Llama-3.2-3B-Instruct-GGUF
+///   gguf_file: Llama-3.2-3B-Instruct-Q4_K_M.gguf
+///   tokenizer_repo: unsloth/Llama-3.2-3B-Instruct
+/// ```
+pub const CANDLE_OPTIONS_CONFIG_KEY: &str = "CANDLE_OPTIONS";
+
+// Tokens generated when the model config doesn't set max_tokens
+const DEFAULT_MAX_TOKENS: usize = 2048;
+const DEFAULT_SEED: u64 = 299792458;
+
+/// The model families the quantized weights can be loaded as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandleArchitecture {
+    /// Llama and the models sharing its layout, such as Mistral and SmolLM
+    Llama,
+    #[default]
+    Qwen2,
+}
+
+/// Where to get a model's weights and tokenizer, and how to prompt it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CandleOptions {
+    #[serde(default)]
+    pub architecture: CandleArchitecture,
+    #[serde(default)]
+    pub template: ChatTemplate,
+    /// The Hugging Face repository holding the GGUF weights
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gguf_repo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gguf_file: Option<String>,
+    /// The Hugging Face repository holding `tokenizer.json`, usually the unquantized model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer_repo: Option<String>,
+    /// Local weights to load instead of downloading them, for machines without network access
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gguf_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer_path: Option<PathBuf>,
+    /// Where downloaded models are kept, the goose cache directory by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+struct KnownModel {
+    name: &'static str,
+    architecture: CandleArchitecture,
+    template: ChatTemplate,
+    gguf_repo: &'static str,
+    gguf_file: &'static str,
+    tokenizer_repo: &'static str,
+}
+
+// Small instruct models that run on a laptop CPU, quantized to 4 bits
+const KNOWN_MODELS: &[KnownModel] = &[
+    KnownModel {
+        name: "qwen2.5-0.5b-instruct",
+        architecture: CandleArchitecture::Qwen2,
+        template: ChatTemplate::Chatml,
+        gguf_repo: "Qwen/Qwen2.5-0.5B-Instruct-GGUF",
+        gguf_file: "qwen2.5-0.5b-instruct-q4_k_m.gguf",
+        tokenizer_repo: "Qwen/Qwen2.5-0.5B-Instruct",
+    },
+    KnownModel {
+        name: "qwen2.5-1.5b-instruct",
+        architecture: CandleArchitecture::Qwen2,
+        template: ChatTemplate::Chatml,
+        gguf_repo: "Qwen/Qwen2.5-1.5B-Instruct-GGUF",
+        gguf_file: "qwen2.5-1.5b-instruct-q4_k_m.gguf",
+        tokenizer_repo: "Qwen/Qwen2.5-1.5B-Instruct",
+    },
+    KnownModel {
+        name: "qwen2.5-coder-3b-instruct",
+        architecture: CandleArchitecture::Qwen2,
+        template: ChatTemplate::Chatml,
+        gguf_repo: "Qwen/Qwen2.5-Coder-3B-Instruct-GGUF",
+        gguf_file: "qwen2.5-coder-3b-instruct-q4_k_m.gguf",
+        tokenizer_repo: "Qwen/Qwen2.5-Coder-3B-Instruct",
+    },
+    KnownModel {
+        name: "llama-3.2-1b-instruct",
+        architecture: CandleArchitecture::Llama,
+        template: ChatTemplate::Llama3,
+        gguf_repo: "bartowski/Llama-3.2-1B-Instruct-GGUF",
+        gguf_file: "Llama-3.2-1B-Instruct-Q4_K_M.gguf",
+        tokenizer_repo: "unsloth/Llama-3.2-1B-Instruct",
+    },
+    KnownModel {
+        name: "llama-3.2-3b-instruct",
+        architecture: CandleArchitecture::Llama,
+        template: ChatTemplate::Llama3,
+        gguf_repo: "bartowski/Llama-3.2-3B-Instruct-GGUF",
+        gguf_file: "Llama-3.2-3B-Instruct-Q4_K_M.gguf",
+        tokenizer_repo: "unsloth/Llama-3.2-3B-Instruct",
+    },
+];
+
+impl CandleOptions {
+    /// Fill in what isn't configured from the known model of that name
+    fn resolve(mut self, model_name: &str) -> Self {
+        if let Some(known) = KNOWN_MODELS.iter().find(|m| m.name == model_name) {
+            if self.gguf_repo.is_none() && self.gguf_path.is_none() {
+                self.architecture = known.architecture;
+                self.template = known.template;
+                self.gguf_repo = Some(known.gguf_repo.to_string());
+                self.gguf_file = Some(known.gguf_file.to_string());
+            }
+            if self.tokenizer_repo.is_none() && self.tokenizer_path.is_none() {
+                self.tokenizer_repo = Some(known.tokenizer_repo.to_string());
+            }
+        }
+        self
+    }
+
+    fn cache_dir(&self) -> Result<PathBuf> {
+        match &self.cache_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(
+                choose_app_strategy(crate::config::APP_STRATEGY.clone())?.in_cache_dir("models")
+            ),
+        }
+    }
+
+    /// The local paths of the weights and tokenizer, downloading them on first use
+    async fn fetch(&self) -> Result<(PathBuf, PathBuf)> {
+        let api = hf_hub::api::tokio::ApiBuilder::new()
+            .with_cache_dir(self.cache_dir()?)
+            .with_progress(false)
+            .build()?;
+
+        let gguf = match (&self.gguf_path, &self.gguf_repo, &self.gguf_file) {
+            (Some(path), _, _) => path.clone(),
+            (None, Some(repo), Some(file)) => {
+                tracing::info!("Fetching {} from {}", file, repo);
+                api.model(repo.clone()).get(file).await?
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Set gguf_path, or gguf_repo and gguf_file, in {}",
+                    CANDLE_OPTIONS_CONFIG_KEY
+                ))
+            }
+        };
+        let tokenizer = match (&self.tokenizer_path, &self.tokenizer_repo) {
+            (Some(path), _) => path.clone(),
+            (None, Some(repo)) => api.model(repo.clone()).get("tokenizer.json").await?,
+            _ => {
+                return Err(anyhow!(
+                    "Set tokenizer_path or tokenizer_repo in {}",
+                    CANDLE_OPTIONS_CONFIG_KEY
+                ))
+            }
+        };
+        Ok((gguf, tokenizer))
+    }
+}
+
+#[derive(Clone)]
+enum Weights {
+    Llama(quantized_llama::ModelWeights),
+    Qwen2(quantized_qwen2::ModelWeights),
+}
+
+impl Weights {
+    fn forward(&mut self, input: &Tensor, position: usize) -> candle_core::Result<Tensor> {
+        match self {
+            Weights::Llama(weights) => weights.forward(input, position),
+            Weights::Qwen2(weights) => weights.forward(input, position),
+        }
+    }
+}
+
+/// A loaded model, shared by all requests
+struct Engine {
+    weights: Weights,
+    tokenizer: Tokenizer,
+    device: Device,
+    context_length: Option<usize>,
+    end_of_turn: Vec<u32>,
+}
+
+impl Engine {
+    fn load(gguf: PathBuf, tokenizer: PathBuf, options: &CandleOptions) -> Result<Self> {
+        let device = Device::cuda_if_available(0)?;
+        let mut file = std::fs::File::open(&gguf)
+            .map_err(|e| anyhow!("Failed to open {}: {}", gguf.display(), e))?;
+        let content = gguf_file::Content::read(&mut file)?;
+
+        let architecture = match options.architecture {
+            CandleArchitecture::Llama => "llama",
+            CandleArchitecture::Qwen2 => "qwen2",
+        };
+        let context_length = content
+            .metadata
+            .get(&format!("{}.context_length", architecture))
+            .and_then(|value| value.to_u32().ok())
+            .map(|length| length as usize);
+
+        let weights = match options.architecture {
+            CandleArchitecture::Llama => Weights::Llama(quantized_llama::ModelWeights::from_gguf(
+                content, &mut file, &device,
+            )?),
+            CandleArchitecture::Qwen2 => Weights::Qwen2(quantized_qwen2::ModelWeights::from_gguf(
+                content, &mut file, &device,
+            )?),
+        };
+        let tokenizer = Tokenizer::from_file(&tokenizer)
+            .map_err(|e| anyhow!("Failed to load {}: {}", tokenizer.display(), e))?;
+
+        let mut end_of_turn = Vec::new();
+        for token in [
+            options.template.end_of_turn(),
+            "<|endoftext|>",
+            "<|end_of_text|>",
+        ] {
+            if let Some(id) = tokenizer.token_to_id(token) {
+                end_of_turn.push(id);
+            }
+        }
+
+        Ok(Self {
+            weights,
+            tokenizer,
+            device,
+            context_length,
+            end_of_turn,
+        })
+    }
+
+    /// Generate a reply to the prompt, returning it with the prompt and reply token counts
+    ///
+    /// Each request runs on its own copy of the weights, whose tensors are shared, so it
+    /// starts from an empty key/value cache. Generation stops between tokens once `stop` is
+    /// cancelled.
+    fn generate(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        stop: &CancellationToken,
+    ) -> Result<(String, usize, usize), ProviderError> {
+        let execution = |e: candle_core::Error| ProviderError::ExecutionError(e.to_string());
+
+        let prompt_tokens = self
+            .tokenizer
+            .encode(prompt, false)
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+            .get_ids()
+            .to_vec();
+        let max_tokens = model
+            .max_tokens
+            .map(|tokens| tokens.max(1) as usize)
+            .unwrap_or(DEFAULT_MAX_TOKENS);
+        let limit = self.context_length.unwrap_or(model.context_limit());
+        if prompt_tokens.len() + max_tokens > limit {
+            return Err(ProviderError::ContextLengthExceeded(format!(
+                "The prompt has {} tokens and up to {} more are generated, but the context is {}",
+                prompt_tokens.len(),
+                max_tokens,
+                limit
+            )));
+        }
+
+        let mut weights = self.weights.clone();
+        let mut sampler = LogitsProcessor::new(
+            DEFAULT_SEED,
+            model.temperature.map(|t| t as f64).filter(|t| *t > 0.0),
+            None,
+        );
+
+        let input = Tensor::new(prompt_tokens.as_slice(), &self.device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(execution)?;
+        let mut logits = weights.forward(&input, 0).map_err(execution)?;
+        let mut generated: Vec<u32> = Vec::new();
+        while generated.len() < max_tokens {
+            if stop.is_cancelled() {
+                return Err(ProviderError::Cancelled);
+            }
+            let next = logits
+                .squeeze(0)
+                .and_then(|l| sampler.sample(&l))
+                .map_err(execution)?;
+            if self.end_of_turn.contains(&next) {
+                break;
+            }
+            generated.push(next);
+
+            let input = Tensor::new(&[next], &self.device)
+                .and_then(|t| t.unsqueeze(0))
+                .map_err(execution)?;
+            logits = weights
+                .forward(&input, prompt_tokens.len() + generated.len() - 1)
+                .map_err(execution)?;
+        }
+
+        let text = self
+            .tokenizer
+            .decode(&generated, true)
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))?;
+        Ok((text, prompt_tokens.len(), generated.len()))
+    }
+}
+
+/// Small models run in process with candle, without a server
+///
+/// The GGUF weights and tokenizer are downloaded from Hugging Face into the cache the
+/// first time the model is used, then loaded once for the life of the provider. Only
+/// available when goose is built with the `candle` feature.
+#[derive(serde::Serialize)]
+pub struct CandleProvider {
+    model: ModelConfig,
+    options: CandleOptions,
+    #[serde(skip)]
+    engine: OnceCell<Arc<Engine>>,
+}
+
+impl Default for CandleProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(CandleProvider::metadata().default_model);
+        CandleProvider::from_env(model).expect("Failed to initialize candle provider")
+    }
+}
+
+impl CandleProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let options: CandleOptions = config.get(CANDLE_OPTIONS_CONFIG_KEY).unwrap_or_default();
+        Ok(Self::new(model, options))
+    }
+
+    pub fn new(model: ModelConfig, options: CandleOptions) -> Self {
+        let options = options.resolve(&model.model_name);
+        Self {
+            model,
+            options,
+            engine: OnceCell::new(),
+        }
+    }
+
+    async fn engine(&self) -> Result<Arc<Engine>, ProviderError> {
+        self.engine
+            .get_or_try_init(|| async {
+                let (gguf, tokenizer) = self
+                    .options
+                    .fetch()
+                    .await
+                    .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
+                let options = self.options.clone();
+                tokio::task::spawn_blocking(move || Engine::load(gguf, tokenizer, &options))
+                    .await
+                    .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+                    .map(Arc::new)
+                    .map_err(|e| ProviderError::ExecutionError(e.to_string()))
+            })
+            .await
+            .cloned()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools, cancel),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn run(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel: &CancellationToken,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let prompt = render_prompt(self.options.template, system, messages, tools)?;
+        let engine = self.engine().await?;
+
+        // Generation stops when the request is cancelled, or when this future is dropped
+        let stop = cancel.child_token();
+        let _stop_on_drop = stop.clone().drop_guard();
+        let model = self.model.clone();
+        let request = prompt.clone();
+        let (text, input_tokens, output_tokens) =
+            tokio::task::spawn_blocking(move || engine.generate(&request, &model, &stop))
+                .await
+                .map_err(|e| ProviderError::ExecutionError(e.to_string()))??;
+
+        let message = response_to_message(&text)?;
+        let usage = Usage::new(
+            Some(input_tokens as i32),
+            Some(output_tokens as i32),
+            Some((input_tokens + output_tokens) as i32),
+        );
+        emit_debug_trace(
+            self,
+            &json!({ "prompt": prompt }),
+            &json!({ "text": text }),
+            &usage,
+        );
+        Ok((
+            message,
+            ProviderUsage::new(self.model.model_name.clone(), usage),
+        ))
+    }
+}
+
+#[async_trait]
+impl Provider for CandleProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "candle",
+            "Candle",
+            "Small local models run inside goose, without a server",
+            CANDLE_DEFAULT_MODEL,
+            KNOWN_MODELS.iter().map(|m| m.name.to_string()).collect(),
+            CANDLE_DOC_URL,
+            // Everything but the model name is optional and lives in CANDLE_OPTIONS
+            vec![],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.run(system, messages, tools, &CancellationToken::new())
+            .await
+    }
+
+    async fn complete_cancellable(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel: &CancellationToken,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.run(system, messages, tools, cancel).await
+    }
+
+    async fn stream_cancellable(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel: &CancellationToken,
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let (message, usage) = self.run(system, messages, tools, cancel).await?;
+        Ok(message_stream(message, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_resolve_known_model() {
+        let options = CandleOptions::default().resolve("llama-3.2-1b-instruct");
+        assert_eq!(options.architecture, CandleArchitecture::Llama);
+        assert_eq!(options.template, ChatTemplate::Llama3);
+        assert_eq!(
+            options.gguf_file.as_deref(),
+            Some("Llama-3.2-1B-Instruct-Q4_K_M.gguf")
+        );
+
+        // Local weights are used as configured
+        let options: CandleOptions =
+            serde_yaml::from_str("gguf_path: /models/smollm.gguf\narchitecture: llama").unwrap();
+        let options = options.resolve("qwen2.5-1.5b-instruct");
+        assert_eq!(options.architecture, CandleArchitecture::Llama);
+        assert!(options.gguf_repo.is_none());
+        assert_eq!(
+            options.tokenizer_repo.as_deref(),
+            Some("Qwen/Qwen2.5-1.5B-Instruct")
+        );
+    }
+}
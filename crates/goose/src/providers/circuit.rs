@@ -0,0 +1,311 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::inspect_stream;
+use crate::config::Config;
+use crate::events::{self, Event};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the `CircuitBreakerConfig`
+pub const CIRCUIT_BREAKER_CONFIG_KEY: &str = "GOOSE_CIRCUIT_BREAKER";
+
+// The circuits of every provider and model in this process, shared so UIs can list them
+static CIRCUITS: Lazy<Mutex<HashMap<(String, String), Circuit>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// When a circuit opens and how long it stays open
+///
+/// ```yaml
+/// GOOSE_CIRCUIT_BREAKER:
+///   failure_threshold: 5
+///   cooldown_secs: 30
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit fails requests before letting one through to try again
+    pub cooldown_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown_secs: 30,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// Read `GOOSE_CIRCUIT_BREAKER`, returning None when it is not set
+    pub fn from_config() -> Option<Self> {
+        Config::global().get(CIRCUIT_BREAKER_CONFIG_KEY).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests go through
+    Closed,
+    /// Requests fail without being sent until the cooldown is over
+    Open,
+    /// The cooldown is over, and the next request decides whether the circuit closes again
+    HalfOpen,
+}
+
+impl std::fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CircuitState::Closed => write!(f, "closed"),
+            CircuitState::Open => write!(f, "open"),
+            CircuitState::HalfOpen => write!(f, "half open"),
+        }
+    }
+}
+
+/// The circuit of a provider and model, as UIs show it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CircuitStatus {
+    pub provider: String,
+    pub model: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    /// When an open circuit lets a request through again
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    failures: u32,
+    /// When the open circuit lets a request through, None while it is closed
+    retry_at: Option<DateTime<Utc>>,
+}
+
+impl Circuit {
+    fn state(&self, now: DateTime<Utc>) -> CircuitState {
+        match self.retry_at {
+            None => CircuitState::Closed,
+            Some(retry_at) if now < retry_at => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+}
+
+/// The status of every circuit a request has gone through
+pub fn circuits() -> Vec<CircuitStatus> {
+    let now = Utc::now();
+    let mut statuses: Vec<_> = CIRCUITS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|((provider, model), circuit)| CircuitStatus {
+            provider: provider.clone(),
+            model: model.clone(),
+            state: circuit.state(now),
+            consecutive_failures: circuit.failures,
+            retry_at: circuit.retry_at,
+        })
+        .collect();
+    statuses.sort_by(|a, b| (&a.provider, &a.model).cmp(&(&b.provider, &b.model)));
+    statuses
+}
+
+/// Whether an error says the provider is unavailable, rather than that the request was wrong
+fn is_failure(error: &ProviderError) -> bool {
+    matches!(
+        error,
+        ProviderError::RateLimitExceeded(_)
+            | ProviderError::ServerError(_)
+            | ProviderError::RequestFailed(_)
+            | ProviderError::ExecutionError(_)
+    )
+}
+
+/// A provider wrapper that stops sending requests to a model after it keeps failing
+///
+/// After `failure_threshold` requests in a row fail with a server error, rate limit or
+/// timeout, the circuit of the provider and model opens and requests fail right away with
+/// `ProviderError::CircuitOpen`. Once the cooldown is over a single request is let through:
+/// the circuit closes if it succeeds, and opens for another cooldown if it fails.
+pub struct CircuitBreakerProvider {
+    inner: Box<dyn Provider>,
+    provider: String,
+    config: CircuitBreakerConfig,
+}
+
+impl CircuitBreakerProvider {
+    pub fn new(inner: Box<dyn Provider>, provider: &str, config: CircuitBreakerConfig) -> Self {
+        Self {
+            inner,
+            provider: provider.to_string(),
+            config,
+        }
+    }
+
+    fn key(&self) -> (String, String) {
+        let model = self.inner.get_model_config().model_name;
+        (self.provider.clone(), model)
+    }
+
+    fn cooldown_from(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::seconds(self.config.cooldown_secs as i64)
+    }
+
+    fn acquire(&self) -> Result<(), ProviderError> {
+        let now = Utc::now();
+        let (provider, model) = self.key();
+        let mut circuits = CIRCUITS.lock().unwrap();
+        let circuit = circuits
+            .entry((provider.clone(), model.clone()))
+            .or_default();
+        match (circuit.state(now), circuit.retry_at) {
+            (CircuitState::Open, Some(retry_at)) => Err(ProviderError::CircuitOpen(format!(
+                "{}/{} failed {} times in a row, trying again at {}",
+                provider,
+                model,
+                circuit.failures,
+                retry_at.to_rfc3339()
+            ))),
+            (CircuitState::HalfOpen, _) => {
+                // Requests made while this one is trying fail until another cooldown is over
+                circuit.retry_at = Some(self.cooldown_from(now));
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record<T>(&self, result: Result<&T, &ProviderError>) {
+        let key = self.key();
+        let mut circuits = CIRCUITS.lock().unwrap();
+        let circuit = circuits.entry(key.clone()).or_default();
+        let was_open = circuit.retry_at.is_some();
+        match result {
+            Err(error) if is_failure(error) => {
+                circuit.failures += 1;
+                if circuit.failures >= self.config.failure_threshold {
+                    circuit.retry_at = Some(self.cooldown_from(Utc::now()));
+                }
+            }
+            // Errors of the request itself say nothing about the provider
+            Err(_) => return,
+            Ok(_) => *circuit = Circuit::default(),
+        }
+
+        let is_open = circuit.retry_at.is_some();
+        if was_open != is_open {
+            let state = if is_open {
+                CircuitState::Open
+            } else {
+                CircuitState::Closed
+            };
+            tracing::warn!("Circuit for {}/{} is {}", key.0, key.1, state);
+            events::emit(Event::CircuitChanged {
+                provider: key.0,
+                model: key.1,
+                state: state.to_string(),
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CircuitBreakerProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.acquire()?;
+        let result = self.inner.complete(system, messages, tools).await;
+        self.record(result.as_ref());
+        result
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        self.acquire()?;
+        match self.inner.stream(system, messages, tools).await {
+            Ok(stream) => {
+                let model = self.get_model_config().model_name;
+                Ok(inspect_stream(stream, model, |result| self.record(result)))
+            }
+            Err(error) => {
+                self.record::<()>(Err(&error));
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    #[tokio::test]
+    async fn test_opens_after_consecutive_failures() {
+        let mock = MockProvider::new(ModelConfig::new("circuit-test".to_string()))
+            .with_error(ProviderError::ServerError("down".to_string()))
+            .with_error(ProviderError::ContextLengthExceeded("too long".to_string()))
+            .with_error(ProviderError::ServerError("down".to_string()))
+            .with_text("unused");
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown_secs: 60,
+        };
+        let provider = CircuitBreakerProvider::new(Box::new(mock.clone()), "mock", config);
+        let status = || {
+            circuits()
+                .into_iter()
+                .find(|status| status.model == "circuit-test")
+                .unwrap()
+        };
+
+        // An error of the request itself doesn't count towards the threshold
+        for _ in 0..3 {
+            assert!(provider.complete("", &[], &[]).await.is_err());
+        }
+        assert_eq!(status().state, CircuitState::Open);
+        assert_eq!(status().consecutive_failures, 2);
+
+        let result = provider.complete("", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::CircuitOpen(_))));
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[test]
+    fn test_half_open_after_cooldown() {
+        let now = Utc::now();
+        let circuit = Circuit {
+            failures: 5,
+            retry_at: Some(now - Duration::seconds(1)),
+        };
+        assert_eq!(circuit.state(now), CircuitState::HalfOpen);
+        assert_eq!(Circuit::default().state(now), CircuitState::Closed);
+    }
+}
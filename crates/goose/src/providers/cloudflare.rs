@@ -0,0 +1,181 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::cloudflare::{create_request, get_usage, response_to_message};
+use super::utils::{emit_debug_trace, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const CLOUDFLARE_API_HOST: &str = "https://api.cloudflare.com";
+pub const CLOUDFLARE_DEFAULT_MODEL: &str = "@cf/meta/llama-3.3-70b-instruct-fp8-fast";
+pub const CLOUDFLARE_KNOWN_MODELS: &[&str] = &[
+    "@cf/meta/llama-3.3-70b-instruct-fp8-fast",
+    "@cf/meta/llama-3.1-8b-instruct",
+    "@hf/nousresearch/hermes-2-pro-mistral-7b",
+    "@cf/qwen/qwq-32b",
+];
+
+pub const CLOUDFLARE_DOC_URL: &str = "https://developers.cloudflare.com/workers-ai/models/";
+
+#[derive(serde::Serialize)]
+pub struct CloudflareProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    account_id: String,
+    api_token: String,
+    model: ModelConfig,
+}
+
+impl Default for CloudflareProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(CloudflareProvider::metadata().default_model);
+        CloudflareProvider::from_env(model).expect("Failed to initialize Cloudflare provider")
+    }
+}
+
+impl CloudflareProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let account_id: String = config.get("CLOUDFLARE_ACCOUNT_ID")?;
+        let api_token: String = config.get_secret("CLOUDFLARE_API_TOKEN")?;
+        let host: String = config
+            .get("CLOUDFLARE_HOST")
+            .unwrap_or_else(|_| CLOUDFLARE_API_HOST.to_string());
+
+        let client = http_client("CLOUDFLARE")?;
+
+        Ok(Self {
+            client,
+            host,
+            account_id,
+            api_token,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url
+            .join(&format!(
+                "client/v4/accounts/{}/ai/run/{}",
+                self.account_id, self.model.model_name
+            ))
+            .map_err(|e| {
+                ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+            })?;
+
+        let request = self
+            .client
+            .post(url)
+            .bearer_auth(&self.api_token)
+            .json(&payload);
+        let response = retry::send(request).await?;
+
+        let status = response.status();
+        let payload: Option<Value> = response.json().await.ok();
+        // Errors come as a list under `errors` in Cloudflare's API envelope
+        let message = payload
+            .as_ref()
+            .and_then(|p| p["errors"].as_array())
+            .map(|errors| {
+                errors
+                    .iter()
+                    .filter_map(|e| e["message"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })
+            .filter(|message| !message.is_empty())
+            .unwrap_or_else(|| "Unknown error".to_string());
+
+        // https://developers.cloudflare.com/workers-ai/platform/errors/
+        match status {
+            StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API token is valid and has the Workers AI permission. \
+                    Status: {}. Message: {}", status, message)))
+            }
+            StatusCode::BAD_REQUEST | StatusCode::PAYLOAD_TOO_LARGE => {
+                if message.contains("context window") || message.contains("too long") {
+                    return Err(ProviderError::ContextLengthExceeded(message));
+                }
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, message)))
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                Err(ProviderError::RateLimitExceeded(message))
+            }
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                Err(ProviderError::ServerError(message))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                );
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, message)))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CloudflareProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "cloudflare",
+            "Cloudflare Workers AI",
+            "Open source models on Cloudflare's network",
+            CLOUDFLARE_DEFAULT_MODEL,
+            CLOUDFLARE_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            CLOUDFLARE_DOC_URL,
+            vec![
+                ConfigKey::new("CLOUDFLARE_ACCOUNT_ID", true, false, None),
+                ConfigKey::new("CLOUDFLARE_API_TOKEN", true, true, None),
+                ConfigKey::new("CLOUDFLARE_HOST", false, false, Some(CLOUDFLARE_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = vcr::post("cloudflare", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        // Workers AI doesn't echo the model back
+        let model = self.model.model_name.clone();
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
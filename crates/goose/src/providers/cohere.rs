@@ -0,0 +1,164 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::cohere::{create_request, get_usage, response_to_message};
+use super::keys::KeyPool;
+use super::utils::{emit_debug_trace, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const COHERE_API_HOST: &str = "https://api.cohere.com";
+pub const COHERE_DEFAULT_MODEL: &str = "command-r-plus";
+pub const COHERE_KNOWN_MODELS: &[&str] = &[
+    "command-a-03-2025",
+    "command-r-plus",
+    "command-r",
+    "command-r7b-12-2024",
+];
+
+pub const COHERE_DOC_URL: &str = "https://docs.cohere.com/docs/models";
+
+#[derive(serde::Serialize)]
+pub struct CohereProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_keys: KeyPool,
+    model: ModelConfig,
+}
+
+impl Default for CohereProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(CohereProvider::metadata().default_model);
+        CohereProvider::from_env(model).expect("Failed to initialize Cohere provider")
+    }
+}
+
+impl CohereProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_keys = KeyPool::from_secret("COHERE_API_KEY")?;
+        let host: String = config
+            .get("COHERE_HOST")
+            .unwrap_or_else(|_| COHERE_API_HOST.to_string());
+
+        let client = http_client("COHERE")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_keys,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("v2/chat").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Authorization", format!("Bearer {}", key))
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+
+        let status = response.status();
+        let payload: Option<Value> = response.json().await.ok();
+
+        // https://docs.cohere.com/reference/errors
+        match status {
+            StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
+                    Status: {}. Response: {:?}", status, payload)))
+            }
+            StatusCode::BAD_REQUEST => {
+                let message = payload
+                    .as_ref()
+                    .and_then(|p| p.get("message"))
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("Unknown error")
+                    .to_string();
+                if message.contains("too many tokens") || message.contains("context length") {
+                    return Err(ProviderError::ContextLengthExceeded(message));
+                }
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, message)))
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+            }
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                Err(ProviderError::ServerError(format!("{:?}", payload)))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                );
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CohereProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "cohere",
+            "Cohere",
+            "Command models from Cohere",
+            COHERE_DEFAULT_MODEL,
+            COHERE_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            COHERE_DOC_URL,
+            vec![
+                ConfigKey::new("COHERE_API_KEY", true, true, None),
+                ConfigKey::new("COHERE_HOST", false, false, Some(COHERE_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = vcr::post("cohere", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        // Cohere doesn't echo the model back
+        let model = self.model.model_name.clone();
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
@@ -0,0 +1,503 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use crate::config::Config;
+use crate::events::{self, Event};
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::prompt_template::load_prompt_file;
+use crate::token_counter::TokenCounter;
+use crate::truncate::with_tool_pair;
+use mcp_core::role::Role;
+use mcp_core::tool::Tool;
+
+/// Config key holding the `CompactionConfig`; compaction is disabled when it is not set
+pub const COMPACTION_CONFIG_KEY: &str = "GOOSE_COMPACTION";
+
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:";
+
+fn default_threshold() -> f32 {
+    0.8
+}
+
+fn default_keep_fraction() -> f32 {
+    0.3
+}
+
+/// Settings for compacting conversations by summarization
+///
+/// ```yaml
+/// GOOSE_COMPACTION:
+///   threshold: 0.8
+///   provider: openai
+///   model: gpt-4o-mini
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompactionConfig {
+    /// Fraction of the context window at which older turns are summarized
+    #[serde(default = "default_threshold")]
+    pub threshold: f32,
+    /// Fraction of the context window to keep verbatim as recent turns
+    #[serde(default = "default_keep_fraction")]
+    pub keep_fraction: f32,
+    /// Provider used for summarizing, defaults to the provider being compacted
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Model used for summarizing, defaults to the summarizing provider's known model with
+    /// the lowest price. When none are priced, it is the model being compacted for the same
+    /// provider and the provider's default for another one
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: default_threshold(),
+            keep_fraction: default_keep_fraction(),
+            provider: None,
+            model: None,
+        }
+    }
+}
+
+impl CompactionConfig {
+    pub fn from_config() -> Option<Self> {
+        Config::global().get(COMPACTION_CONFIG_KEY).ok()
+    }
+}
+
+// The most recent compaction, reused while the caller keeps sending the same history
+struct Compaction {
+    archived_len: usize,
+    fingerprint: String,
+    summary: String,
+}
+
+/// A provider wrapper that summarizes older turns once the conversation nears the
+/// context limit
+///
+/// Callers keep sending their full history; the wrapper replaces the archived prefix with
+/// the summary before forwarding the request, so compaction is invisible to them.
+pub struct CompactingProvider {
+    inner: Box<dyn Provider>,
+    summarizer: Box<dyn Provider>,
+    config: CompactionConfig,
+    token_counter: Arc<TokenCounter>,
+    compaction: Mutex<Option<Compaction>>,
+    archive: Mutex<Vec<Message>>,
+}
+
+impl CompactingProvider {
+    pub fn new(
+        inner: Box<dyn Provider>,
+        summarizer: Box<dyn Provider>,
+        config: CompactionConfig,
+        token_counter: Arc<TokenCounter>,
+    ) -> Self {
+        Self {
+            inner,
+            summarizer,
+            config,
+            token_counter,
+            compaction: Mutex::new(None),
+            archive: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The original messages that have been replaced by the summary, oldest first
+    pub fn archived_messages(&self) -> Vec<Message> {
+        self.archive.lock().unwrap().clone()
+    }
+
+    /// The summary currently standing in for the archived messages
+    pub fn summary(&self) -> Option<String> {
+        self.compaction
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|c| c.summary.clone())
+    }
+
+    /// Returns the messages to send, summarizing older turns first if needed
+    async fn compact(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<Vec<Message>, ProviderError> {
+        let context_limit = self.inner.get_model_config().context_limit();
+        let threshold = (context_limit as f32 * self.config.threshold) as usize;
+
+        // Reuse the previous summary as long as the history still starts with what it replaced
+        let previous = self.current_compaction(messages);
+        let archived_len = previous.as_ref().map(|(len, _)| *len).unwrap_or(0);
+        let previous_summary = previous.map(|(_, summary)| summary);
+
+        let view = with_summary(previous_summary.as_deref(), messages, archived_len);
+        let used_tokens = self.token_counter.count_chat_tokens(system, &view, tools);
+        if used_tokens <= threshold {
+            return Ok(view);
+        }
+
+        let keep_tokens = (context_limit as f32 * self.config.keep_fraction) as usize;
+        let Some(split) = self.find_split(messages, archived_len, keep_tokens) else {
+            // Nothing left that can be summarized, let the provider decide
+            return Ok(view);
+        };
+
+        tracing::info!(
+            used_tokens,
+            context_limit,
+            "Compacting {} messages into a summary",
+            split - archived_len
+        );
+        // Pinned messages are sent as they are, so there is no need to summarize them
+        let pinned = pinned_indices(messages, split);
+        let summarized: Vec<Message> = (archived_len..split)
+            .filter(|i| !pinned.contains(i))
+            .map(|i| messages[i].clone())
+            .collect();
+        if summarized.is_empty() {
+            return Ok(view);
+        }
+        let summary = self
+            .summarize(previous_summary.as_deref(), &summarized)
+            .await?;
+
+        self.archive
+            .lock()
+            .unwrap()
+            .extend_from_slice(&messages[archived_len..split]);
+        events::emit(Event::CompactionPerformed {
+            archived_messages: split - archived_len,
+            used_tokens,
+            context_limit,
+        });
+        let view = with_summary(Some(&summary), messages, split);
+        *self.compaction.lock().unwrap() = Some(Compaction {
+            archived_len: split,
+            fingerprint: fingerprint(&messages[..split]),
+            summary,
+        });
+        Ok(view)
+    }
+
+    fn current_compaction(&self, messages: &[Message]) -> Option<(usize, String)> {
+        let compaction = self.compaction.lock().unwrap();
+        let compaction = compaction.as_ref()?;
+        if compaction.archived_len < messages.len()
+            && fingerprint(&messages[..compaction.archived_len]) == compaction.fingerprint
+        {
+            Some((compaction.archived_len, compaction.summary.clone()))
+        } else {
+            None
+        }
+    }
+
+    /// Find where the recent turns start: the earliest user text message after `start`
+    /// whose suffix fits in `keep_tokens`, or the last one if none do. Splitting before a
+    /// user text message never separates a tool request from its response.
+    fn find_split(&self, messages: &[Message], start: usize, keep_tokens: usize) -> Option<usize> {
+        let candidates: Vec<usize> = (start + 1..messages.len())
+            .filter(|&i| messages[i].role == Role::User && messages[i].has_only_text_content())
+            .collect();
+
+        candidates
+            .iter()
+            .copied()
+            .find(|&i| {
+                self.token_counter
+                    .count_chat_tokens("", &messages[i..], &[])
+                    <= keep_tokens
+            })
+            .or_else(|| candidates.last().copied())
+    }
+
+    async fn summarize(
+        &self,
+        previous_summary: Option<&str>,
+        messages: &[Message],
+    ) -> Result<String, ProviderError> {
+        let prompt = load_prompt_file(
+            "summarize.md",
+            &json!({
+                "previous_summary": previous_summary,
+                "transcript": render_transcript(messages),
+            }),
+        )
+        .map_err(|e| ProviderError::ExecutionError(e.to_string()))?;
+
+        let (response, _usage) = self
+            .summarizer
+            .complete(
+                "You summarize conversations.",
+                &[Message::user().with_text(prompt)],
+                &[],
+            )
+            .await?;
+        Ok(response.as_concat_text())
+    }
+}
+
+#[async_trait]
+impl Provider for CompactingProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let messages = self.compact(system, messages, tools).await?;
+        self.inner.complete(system, &messages, tools).await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let messages = self.compact(system, messages, tools).await?;
+        self.inner.stream(system, &messages, tools).await
+    }
+}
+
+/// The messages to send in place of the history: the pinned messages among the first
+/// `archived_len` and the messages after them, with the summary in front
+fn with_summary(summary: Option<&str>, messages: &[Message], archived_len: usize) -> Vec<Message> {
+    let mut view: Vec<Message> = pinned_indices(messages, archived_len)
+        .into_iter()
+        .chain(archived_len..messages.len())
+        .map(|i| messages[i].clone())
+        .collect();
+    if let Some(summary) = summary {
+        let summary = MessageContent::text(format!("{}\n{}", SUMMARY_PREFIX, summary));
+        match view.first_mut() {
+            // Recent turns start with a user text message, so this is the usual case
+            Some(first)
+                if first.role == Role::User && first.has_only_text_content() && !first.pinned =>
+            {
+                first.content.insert(0, summary)
+            }
+            _ => view.insert(0, Message::user().with_content(summary)),
+        }
+    }
+    view
+}
+
+/// The pinned messages among the first `len`, along with the other half of their tool calls
+fn pinned_indices(messages: &[Message], len: usize) -> BTreeSet<usize> {
+    let archived = &messages[..len];
+    archived
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.pinned)
+        .flat_map(|(i, _)| with_tool_pair(archived, i))
+        .collect()
+}
+
+fn fingerprint(messages: &[Message]) -> String {
+    let serialized = serde_json::to_vec(messages).unwrap_or_default();
+    format!("{:x}", Sha256::digest(serialized))
+}
+
+fn render_transcript(messages: &[Message]) -> String {
+    let mut lines = Vec::new();
+    for message in messages {
+        let role = match message.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+        for content in &message.content {
+            if let Some(text) = content.as_text() {
+                lines.push(format!("{}: {}", role, text));
+            } else if let Some(request) = content.as_tool_request() {
+                if let Ok(call) = &request.tool_call {
+                    lines.push(format!(
+                        "{} called tool {} with {}",
+                        role, call.name, call.arguments
+                    ));
+                }
+            } else if let Some(response) = content.as_tool_response() {
+                match &response.tool_result {
+                    Ok(_) => lines.push(format!(
+                        "tool result: {}",
+                        content.as_tool_response_text().unwrap_or_default()
+                    )),
+                    Err(e) => lines.push(format!("tool error: {}", e)),
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+
+    type Received = Arc<Mutex<Vec<Vec<Message>>>>;
+
+    // Records what it was sent and replies with a fixed text
+    struct MockProvider {
+        reply: String,
+        context_limit: usize,
+        received: Received,
+    }
+
+    impl MockProvider {
+        fn new(reply: &str, context_limit: usize) -> Self {
+            Self {
+                reply: reply.to_string(),
+                context_limit,
+                received: Arc::new(Mutex::new(Vec::new())),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("mock".to_string()).with_context_limit(Some(self.context_limit))
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.received.lock().unwrap().push(messages.to_vec());
+            Ok((
+                Message::assistant().with_text(&self.reply),
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    fn conversation(turns: usize) -> Vec<Message> {
+        let mut messages = Vec::new();
+        for i in 0..turns {
+            messages.push(Message::user().with_text(format!("question {} {}", i, "x".repeat(36))));
+            messages.push(Message::assistant().with_text(format!(
+                "answer {} {}",
+                i,
+                "y".repeat(36)
+            )));
+        }
+        messages.push(Message::user().with_text("last question"));
+        messages
+    }
+
+    fn provider() -> (CompactingProvider, Received, Received) {
+        let inner = MockProvider::new("reply", 200);
+        let summarizer = MockProvider::new("the summary", 200);
+        let inner_received = inner.received.clone();
+        let summarizer_received = summarizer.received.clone();
+        let provider = CompactingProvider::new(
+            Box::new(inner),
+            Box::new(summarizer),
+            CompactionConfig::default(),
+            Arc::new(TokenCounter::approximate()),
+        );
+        (provider, inner_received, summarizer_received)
+    }
+
+    #[tokio::test]
+    async fn test_no_compaction_below_threshold() {
+        let (provider, inner, summarizer) = provider();
+        let messages = conversation(1);
+
+        provider.complete("", &messages, &[]).await.unwrap();
+
+        assert_eq!(inner.lock().unwrap()[0], messages);
+        assert!(summarizer.lock().unwrap().is_empty());
+        assert!(provider.summary().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_compacts_and_reuses_summary() {
+        let (provider, inner, summarizer) = provider();
+        let mut messages = conversation(10);
+
+        provider.complete("", &messages, &[]).await.unwrap();
+
+        let sent = inner.lock().unwrap()[0].clone();
+        assert!(sent.len() < messages.len());
+        assert!(sent[0].as_concat_text().starts_with(SUMMARY_PREFIX));
+        assert!(sent[0].as_concat_text().contains("the summary"));
+        assert_eq!(sent.last(), messages.last());
+        assert_eq!(summarizer.lock().unwrap().len(), 1);
+
+        // Archived + kept messages make up the original history
+        let archived = provider.archived_messages();
+        assert_eq!(archived.len() + sent.len(), messages.len());
+        assert_eq!(archived[..], messages[..archived.len()]);
+
+        // The next turn reuses the summary without summarizing again
+        messages.push(Message::assistant().with_text("reply"));
+        messages.push(Message::user().with_text("follow up"));
+        provider.complete("", &messages, &[]).await.unwrap();
+        assert_eq!(summarizer.lock().unwrap().len(), 1);
+        let sent = inner.lock().unwrap()[1].clone();
+        assert!(sent[0].as_concat_text().starts_with(SUMMARY_PREFIX));
+        assert_eq!(sent.last(), messages.last());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_messages_are_not_summarized() {
+        let (provider, inner, summarizer) = provider();
+        let mut messages = conversation(10);
+        messages[2].pinned = true;
+
+        provider.complete("", &messages, &[]).await.unwrap();
+
+        let sent = inner.lock().unwrap()[0].clone();
+        assert!(sent[0].as_concat_text().starts_with(SUMMARY_PREFIX));
+        assert_eq!(sent[1], messages[2]);
+        let prompt = summarizer.lock().unwrap()[0][0].as_concat_text();
+        assert!(prompt.contains("question 0"));
+        assert!(!prompt.contains("question 1"));
+    }
+
+    #[test]
+    fn test_render_transcript() {
+        let messages = vec![
+            Message::user().with_text("hello"),
+            Message::assistant().with_tool_request(
+                "1",
+                Ok(mcp_core::tool::ToolCall::new(
+                    "shell",
+                    json!({"command": "ls"}),
+                )),
+            ),
+            Message::user().with_tool_response("1", Ok(vec![mcp_core::Content::text("a.txt")])),
+        ];
+
+        let transcript = render_transcript(&messages);
+        assert_eq!(
+            transcript,
+            "user: hello\nassistant called tool shell with {\"command\":\"ls\"}\ntool result: a.txt"
+        );
+    }
+}
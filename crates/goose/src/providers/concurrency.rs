@@ -0,0 +1,163 @@
+use async_trait::async_trait;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::inspect_stream;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the `ConcurrencyConfig`
+pub const CONCURRENCY_CONFIG_KEY: &str = "GOOSE_CONCURRENCY";
+
+// The permits shared by every provider in this process, and by each provider by name,
+// sized by the config when they are first used
+static GLOBAL: OnceCell<Arc<Semaphore>> = OnceCell::new();
+static PER_PROVIDER: Lazy<Mutex<HashMap<String, Arc<Semaphore>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// How many completions can be in flight at once
+///
+/// ```yaml
+/// GOOSE_CONCURRENCY:
+///   max_in_flight: 8
+///   providers:
+///     openai: 4
+///     ollama: 1
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConcurrencyConfig {
+    /// The limit across every provider
+    #[serde(default)]
+    pub max_in_flight: Option<usize>,
+    /// The limit of each provider by name
+    #[serde(default)]
+    pub providers: HashMap<String, usize>,
+}
+
+impl ConcurrencyConfig {
+    /// Read `GOOSE_CONCURRENCY`, returning None when it is not set
+    pub fn from_config() -> Option<Self> {
+        Config::global().get(CONCURRENCY_CONFIG_KEY).ok()
+    }
+
+    /// The semaphores limiting every provider and this one, where they are limited
+    pub fn semaphores(&self, provider: &str) -> (Option<Arc<Semaphore>>, Option<Arc<Semaphore>>) {
+        let global = self
+            .max_in_flight
+            .map(|max| GLOBAL.get_or_init(|| Arc::new(Semaphore::new(max))).clone());
+        let own = self.providers.get(provider).map(|&max| {
+            PER_PROVIDER
+                .lock()
+                .unwrap()
+                .entry(provider.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(max)))
+                .clone()
+        });
+        (global, own)
+    }
+}
+
+/// A provider wrapper that waits for a free slot before sending a completion
+///
+/// The slots are shared by every provider created in the process, so parallel model calls
+/// started by tools or subagents queue up rather than all hitting the provider at once.
+pub struct ConcurrencyLimitedProvider {
+    inner: Box<dyn Provider>,
+    global: Option<Arc<Semaphore>>,
+    own: Option<Arc<Semaphore>>,
+}
+
+impl ConcurrencyLimitedProvider {
+    pub fn new(
+        inner: Box<dyn Provider>,
+        global: Option<Arc<Semaphore>>,
+        own: Option<Arc<Semaphore>>,
+    ) -> Self {
+        Self { inner, global, own }
+    }
+}
+
+async fn acquire(
+    semaphore: &Option<Arc<Semaphore>>,
+) -> Result<Option<OwnedSemaphorePermit>, ProviderError> {
+    match semaphore {
+        Some(semaphore) => semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map(Some)
+            .map_err(|e| ProviderError::ExecutionError(e.to_string())),
+        None => Ok(None),
+    }
+}
+
+#[async_trait]
+impl Provider for ConcurrencyLimitedProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        // The provider's own slot first, so a request waiting on it doesn't hold a global one
+        let _own = acquire(&self.own).await?;
+        let _global = acquire(&self.global).await?;
+        self.inner.complete(system, messages, tools).await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let own = acquire(&self.own).await?;
+        let global = acquire(&self.global).await?;
+        let stream = self.inner.stream(system, messages, tools).await?;
+        // The slots are held until the stream ends or is dropped
+        let model = self.get_model_config().model_name;
+        Ok(inspect_stream(stream, model, move |_| drop((own, global))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_waits_for_a_free_slot() {
+        let mock = MockProvider::default()
+            .with_latency(Duration::from_millis(100))
+            .with_text("first")
+            .with_text("second");
+        let global = Arc::new(Semaphore::new(2));
+        let own = Arc::new(Semaphore::new(1));
+        let provider = ConcurrencyLimitedProvider::new(Box::new(mock), Some(global), Some(own));
+
+        let started = Instant::now();
+        let (first, second) = tokio::join!(
+            provider.complete("", &[], &[]),
+            provider.complete("", &[], &[])
+        );
+        assert!(first.is_ok() && second.is_ok());
+        // The provider's single slot made the second request wait for the first
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+}
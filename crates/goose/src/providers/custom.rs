@@ -0,0 +1,301 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::lmstudio::repair_response;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the list of `CustomProviderConfig`
+///
+/// ```yaml
+/// GOOSE_CUSTOM_PROVIDERS:
+///   - name: gateway
+///     display_name: Team gateway
+///     base_url: https://llm.internal.example.com/v1/
+///     api_key: GATEWAY_API_KEY
+///     auth_header: X-Api-Key
+///     default_model: llama-3.3-70b
+///     models: [llama-3.3-70b, qwen2.5-coder-32b]
+///     quirks:
+///       system_as_user: true
+/// ```
+pub const CUSTOM_PROVIDERS_CONFIG_KEY: &str = "GOOSE_CUSTOM_PROVIDERS";
+
+/// Differences from OpenAI's API that self hosted gateways commonly have
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomQuirks {
+    /// Send the system prompt with the user role, for servers that reject the system role
+    #[serde(default)]
+    pub system_as_user: bool,
+    /// Send the output limit as `max_completion_tokens` rather than `max_tokens`
+    #[serde(default)]
+    pub max_completion_tokens: bool,
+    /// Leave out the temperature, for servers that reject it
+    #[serde(default)]
+    pub no_temperature: bool,
+    /// Recover tool calls the model wrote into the text, as LM Studio does
+    #[serde(default)]
+    pub repair_tool_calls: bool,
+}
+
+/// A user declared provider for an OpenAI compatible endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomProviderConfig {
+    /// The name the provider is selected by, which can't be one of the built in providers
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// The URL that `chat/completions` is relative to
+    pub base_url: String,
+    /// The name of the secret holding the API key; requests are unauthenticated without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// The header the API key is sent in; `Authorization` with a `Bearer` prefix by default,
+    /// any other header gets the bare key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_header: Option<String>,
+    pub default_model: String,
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub quirks: CustomQuirks,
+}
+
+impl CustomProviderConfig {
+    /// The configured custom providers, leaving out any named after a built in provider
+    pub fn from_config() -> Vec<Self> {
+        let configured: Vec<Self> = Config::global()
+            .get(CUSTOM_PROVIDERS_CONFIG_KEY)
+            .unwrap_or_default();
+        configured
+            .into_iter()
+            .filter(|custom| {
+                let shadows = super::factory::is_builtin(&custom.name);
+                if shadows {
+                    tracing::warn!(
+                        "Ignoring the custom provider {}, a built in provider has that name",
+                        custom.name
+                    );
+                }
+                !shadows
+            })
+            .collect()
+    }
+
+    pub fn metadata(&self) -> ProviderMetadata {
+        let mut models = self.models.clone();
+        if !models.contains(&self.default_model) {
+            models.insert(0, self.default_model.clone());
+        }
+        let config_keys = self
+            .api_key
+            .iter()
+            .map(|key| ConfigKey::new(key, true, true, None))
+            .collect();
+        ProviderMetadata::new(
+            &self.name,
+            self.display_name.as_deref().unwrap_or(&self.name),
+            &format!("OpenAI compatible endpoint at {}", self.base_url),
+            &self.default_model,
+            models,
+            "",
+            config_keys,
+        )
+    }
+}
+
+/// Apply the quirks of an endpoint to an OpenAI request payload
+fn apply_quirks(payload: &mut Value, quirks: &CustomQuirks) {
+    if quirks.system_as_user {
+        if let Some(system) = payload["messages"]
+            .as_array_mut()
+            .and_then(|messages| messages.first_mut())
+            .filter(|message| message["role"] == "system")
+        {
+            system["role"] = json!("user");
+        }
+    }
+    let Some(payload) = payload.as_object_mut() else {
+        return;
+    };
+    if quirks.max_completion_tokens {
+        if let Some(max_tokens) = payload.remove("max_tokens") {
+            payload.insert("max_completion_tokens".to_string(), max_tokens);
+        }
+    }
+    if quirks.no_temperature {
+        payload.remove("temperature");
+    }
+}
+
+/// A provider declared in `GOOSE_CUSTOM_PROVIDERS`, speaking the OpenAI format
+///
+/// Each configured entry is its own provider with its own name, so several self hosted
+/// gateways can be set up side by side.
+#[derive(serde::Serialize)]
+pub struct CustomProvider {
+    #[serde(skip)]
+    client: Client,
+    config: CustomProviderConfig,
+    #[serde(skip)]
+    api_key: Option<String>,
+    model: ModelConfig,
+}
+
+impl CustomProvider {
+    /// Create the custom provider with the given name, if one is configured
+    pub fn named(name: &str, model: ModelConfig) -> Option<Result<Self>> {
+        CustomProviderConfig::from_config()
+            .into_iter()
+            .find(|custom| custom.name == name)
+            .map(|custom| Self::new(custom, model))
+    }
+
+    pub fn new(config: CustomProviderConfig, model: ModelConfig) -> Result<Self> {
+        let api_key = match &config.api_key {
+            Some(key) => Some(Config::global().get_secret(key)?),
+            None => None,
+        };
+        let client = http_client(&config.name.to_uppercase().replace('-', "_"))?;
+
+        Ok(Self {
+            client,
+            config,
+            api_key,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.config.base_url)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let mut request = self.client.post(url);
+        if let Some(api_key) = &self.api_key {
+            request = match &self.config.auth_header {
+                Some(header) if !header.eq_ignore_ascii_case("authorization") => {
+                    request.header(header, api_key)
+                }
+                _ => request.bearer_auth(api_key),
+            };
+        }
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+        let response = retry::send(request.json(&payload)).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for CustomProvider {
+    /// Custom providers are described by their config, see `CustomProviderConfig::metadata`
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        apply_quirks(&mut payload, &self.config.quirks);
+
+        let mut response =
+            vcr::post(&self.config.name, &payload, || self.post(payload.clone())).await?;
+        if self.config.quirks.repair_tool_calls {
+            response = repair_response(response);
+        }
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_and_quirks() {
+        let configs: Vec<CustomProviderConfig> = serde_yaml::from_str(
+            r#"
+- name: gateway
+  base_url: https://llm.internal.example.com/v1/
+  api_key: GATEWAY_API_KEY
+  default_model: llama-3.3-70b
+  models: [qwen2.5-coder-32b]
+  quirks: {system_as_user: true, max_completion_tokens: true, no_temperature: true}
+- name: lab
+  base_url: http://10.0.0.5:8000/v1/
+  default_model: mistral-small
+"#,
+        )
+        .unwrap();
+
+        let metadata = configs[0].metadata();
+        assert_eq!(metadata.name, "gateway");
+        assert_eq!(metadata.display_name, "gateway");
+        assert_eq!(
+            metadata.known_models,
+            vec!["llama-3.3-70b", "qwen2.5-coder-32b"]
+        );
+        assert_eq!(metadata.config_keys[0].name, "GATEWAY_API_KEY");
+        assert!(configs[1].metadata().config_keys.is_empty());
+        assert_eq!(configs[1].quirks, CustomQuirks::default());
+
+        let mut payload = json!({
+            "model": "llama-3.3-70b",
+            "messages": [{"role": "system", "content": "Be brief."}, {"role": "user", "content": "hi"}],
+            "max_tokens": 100,
+            "temperature": 0.2,
+        });
+        apply_quirks(&mut payload, &configs[0].quirks);
+        assert_eq!(payload["messages"][0]["role"], "user");
+        assert_eq!(payload["max_completion_tokens"], 100);
+        assert!(payload.get("max_tokens").is_none());
+        assert!(payload.get("temperature").is_none());
+    }
+}
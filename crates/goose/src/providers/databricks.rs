@@ -3,13 +3,13 @@ use async_trait::async_trait;
 use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::time::Duration;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use super::formats::openai::{create_request, get_usage, response_to_message};
 use super::oauth;
-use super::utils::{get_model, ImageFormat};
+use super::utils::{get_model, http_client, ImageFormat};
+use super::{retry, vcr};
 use crate::config::ConfigError;
 use crate::message::Message;
 use crate::model::ModelConfig;
@@ -96,9 +96,7 @@ impl DatabricksProvider {
 
         let host = host?;
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = http_client("DATABRICKS")?;
 
         // If we find a databricks token we prefer that
         if let Ok(api_key) = config.get_secret("DATABRICKS_TOKEN") {
@@ -146,13 +144,12 @@ impl DatabricksProvider {
         })?;
 
         let auth_header = self.ensure_auth_header().await?;
-        let response = self
+        let request = self
             .client
             .post(url)
             .header("Authorization", auth_header)
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = retry::send(request).await?;
 
         let status = response.status();
         let payload: Option<Value> = response.json().await.ok();
@@ -255,7 +252,7 @@ impl Provider for DatabricksProvider {
             .expect("payload should have model key")
             .remove("model");
 
-        let response = self.post(payload.clone()).await?;
+        let response = vcr::post("databricks", &payload, || self.post(payload.clone())).await?;
 
         // Parse response
         let message = response_to_message(response.clone())?;
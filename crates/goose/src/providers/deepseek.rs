@@ -0,0 +1,135 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::keys::KeyPool;
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const DEEPSEEK_API_HOST: &str = "https://api.deepseek.com";
+pub const DEEPSEEK_DEFAULT_MODEL: &str = "deepseek-chat";
+pub const DEEPSEEK_KNOWN_MODELS: &[&str] = &["deepseek-chat", "deepseek-reasoner"];
+
+pub const DEEPSEEK_DOC_URL: &str = "https://api-docs.deepseek.com/quick_start/pricing";
+
+#[derive(serde::Serialize)]
+pub struct DeepSeekProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_keys: KeyPool,
+    model: ModelConfig,
+}
+
+impl Default for DeepSeekProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(DeepSeekProvider::metadata().default_model);
+        DeepSeekProvider::from_env(model).expect("Failed to initialize DeepSeek provider")
+    }
+}
+
+impl DeepSeekProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_keys = KeyPool::from_secret("DEEPSEEK_API_KEY")?;
+        let host: String = config
+            .get("DEEPSEEK_HOST")
+            .unwrap_or_else(|_| DEEPSEEK_API_HOST.to_string());
+
+        let client = http_client("DEEPSEEK")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_keys,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Authorization", format!("Bearer {}", key))
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for DeepSeekProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "deepseek",
+            "DeepSeek",
+            "Open source models hosted by Together AI",
+            DEEPSEEK_DEFAULT_MODEL,
+            DEEPSEEK_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            DEEPSEEK_DOC_URL,
+            vec![
+                ConfigKey::new("DEEPSEEK_API_KEY", true, true, None),
+                ConfigKey::new("DEEPSEEK_HOST", false, false, Some(DEEPSEEK_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        let response = vcr::post("deepseek", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            // DeepSeek reports its context cache hits outside the OpenAI usage fields
+            Ok(usage) => usage.with_cache_tokens(
+                response["usage"]["prompt_cache_hit_tokens"]
+                    .as_i64()
+                    .map(|v| v as i32),
+                None,
+            ),
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
@@ -23,6 +23,21 @@ pub enum ProviderError {
 
     #[error("Usage data error: {0}")]
     UsageError(String),
+
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    #[error("Blocked by the guardrail: {0}")]
+    GuardrailBlocked(String),
+
+    #[error("Request cancelled")]
+    Cancelled,
+
+    #[error("Circuit open: {0}")]
+    CircuitOpen(String),
 }
 
 impl From<anyhow::Error> for ProviderError {
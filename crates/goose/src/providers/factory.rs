@@ -1,43 +1,373 @@
 use super::{
+    ai21::Ai21Provider,
     anthropic::AnthropicProvider,
+    anthropic_vertex::AnthropicVertexProvider,
     azure::AzureProvider,
+    azure_ai::AzureAiProvider,
     base::{Provider, ProviderMetadata},
     bedrock::BedrockProvider,
+    budget::{BudgetConfig, BudgetProvider},
+    cache::{memory_cache, CachingProvider, MemoizingProvider, ResponseCacheConfig},
+    circuit::{CircuitBreakerConfig, CircuitBreakerProvider},
+    cloudflare::CloudflareProvider,
+    cohere::CohereProvider,
+    compaction::{CompactingProvider, CompactionConfig},
+    concurrency::{ConcurrencyConfig, ConcurrencyLimitedProvider},
+    custom::{CustomProvider, CustomProviderConfig},
     databricks::DatabricksProvider,
+    deepseek::DeepSeekProvider,
+    fallback::{FallbackEntry, FallbackProvider},
+    faults::{FaultConfig, FaultProvider},
+    fireworks::FireworksProvider,
+    github::GitHubModelsProvider,
     google::GoogleProvider,
     groq::GroqProvider,
+    guardrail::{GuardrailConfig, GuardrailProvider},
+    hedge::{HedgeConfig, HedgedProvider},
+    huggingface::HuggingFaceProvider,
+    koboldcpp::KoboldCppProvider,
+    llamacpp::LlamaCppProvider,
+    lmstudio::LmStudioProvider,
+    moonshot::MoonshotProvider,
+    nvidia::NvidiaProvider,
+    oci::OciProvider,
     ollama::OllamaProvider,
     openai::OpenAiProvider,
     openrouter::OpenRouterProvider,
+    perplexity::PerplexityProvider,
+    pricing::get_pricing,
+    quota::{Quota, QuotaProvider},
+    qwen::QwenProvider,
+    ratelimit::{RateLimit, RateLimitedProvider},
+    replicate::ReplicateProvider,
+    router::RouterProvider,
+    sagemaker::SageMakerProvider,
+    scripted::ScriptedProvider,
+    snowflake::SnowflakeProvider,
+    tgi::TgiProvider,
+    together::TogetherProvider,
+    tracking::TrackedProvider,
+    vcr::{self, VcrMode, VcrProvider},
+    vertexai::VertexAiProvider,
+    vllm::VllmProvider,
+    watsonx::WatsonxProvider,
+    xai::XaiProvider,
+    zhipu::ZhipuProvider,
 };
 use crate::model::ModelConfig;
 use anyhow::Result;
 
+/// The built in providers and then the custom ones declared in `GOOSE_CUSTOM_PROVIDERS`
 pub fn providers() -> Vec<ProviderMetadata> {
-    vec![
+    let mut providers = builtin_providers();
+    providers.extend(
+        CustomProviderConfig::from_config()
+            .iter()
+            .map(CustomProviderConfig::metadata),
+    );
+    providers
+}
+
+/// Whether a built in provider has this name
+pub(super) fn is_builtin(name: &str) -> bool {
+    builtin_providers()
+        .iter()
+        .any(|metadata| metadata.name == name)
+}
+
+fn builtin_providers() -> Vec<ProviderMetadata> {
+    #[allow(unused_mut)]
+    let mut providers = vec![
+        Ai21Provider::metadata(),
         AnthropicProvider::metadata(),
+        AnthropicVertexProvider::metadata(),
         AzureProvider::metadata(),
+        AzureAiProvider::metadata(),
         BedrockProvider::metadata(),
+        CloudflareProvider::metadata(),
+        CohereProvider::metadata(),
         DatabricksProvider::metadata(),
+        DeepSeekProvider::metadata(),
+        FireworksProvider::metadata(),
+        GitHubModelsProvider::metadata(),
         GoogleProvider::metadata(),
         GroqProvider::metadata(),
+        HuggingFaceProvider::metadata(),
+        KoboldCppProvider::metadata(),
+        LlamaCppProvider::metadata(),
+        LmStudioProvider::metadata(),
+        MoonshotProvider::metadata(),
+        NvidiaProvider::metadata(),
+        OciProvider::metadata(),
         OllamaProvider::metadata(),
         OpenAiProvider::metadata(),
         OpenRouterProvider::metadata(),
-    ]
+        PerplexityProvider::metadata(),
+        QwenProvider::metadata(),
+        ReplicateProvider::metadata(),
+        RouterProvider::metadata(),
+        SageMakerProvider::metadata(),
+        ScriptedProvider::metadata(),
+        SnowflakeProvider::metadata(),
+        TgiProvider::metadata(),
+        TogetherProvider::metadata(),
+        VertexAiProvider::metadata(),
+        VllmProvider::metadata(),
+        WatsonxProvider::metadata(),
+        XaiProvider::metadata(),
+        ZhipuProvider::metadata(),
+    ];
+    #[cfg(feature = "candle")]
+    providers.push(super::candle::CandleProvider::metadata());
+    #[cfg(feature = "llama-cpp")]
+    providers.push(super::llamacpp_embedded::EmbeddedLlamaCppProvider::metadata());
+    providers
+}
+
+/// The metadata for a provider, with the models it lists at runtime where the provider can
+/// tell which models it serves
+pub async fn fetch_metadata(name: &str) -> Option<ProviderMetadata> {
+    match name {
+        "github_models" => Some(GitHubModelsProvider::fetch_metadata().await),
+        "lmstudio" => Some(LmStudioProvider::fetch_metadata().await),
+        _ => providers()
+            .into_iter()
+            .find(|metadata| metadata.name == name),
+    }
 }
 
 pub fn create(name: &str, model: ModelConfig) -> Result<Box<dyn Provider + Send + Sync>> {
+    let model_config = model.clone();
+    let mut primary: Box<dyn Provider> =
+        Box::new(TrackedProvider::new(create_resilient(name, model)?, name));
+    if let Some(hedge) = HedgeConfig::from_config() {
+        let hedge_name = hedge.provider.as_deref().unwrap_or(name);
+        let hedge_model = match &hedge.model {
+            Some(model) => model.clone(),
+            None if hedge_name == name => model_config.model_name.clone(),
+            None => default_model(hedge_name)?,
+        };
+        let secondary = TrackedProvider::new(
+            create_resilient(hedge_name, ModelConfig::new(hedge_model))?,
+            hedge_name,
+        );
+        primary = Box::new(HedgedProvider::new(
+            primary,
+            Box::new(secondary),
+            hedge.delay(),
+        ));
+    }
+    let fallbacks = FallbackEntry::from_config();
+    let mut provider: Box<dyn Provider> = if fallbacks.is_empty() {
+        primary
+    } else {
+        let mut chain: Vec<(String, Box<dyn Provider>)> = vec![(name.to_string(), primary)];
+        for fallback in fallbacks {
+            let model = match fallback.model {
+                Some(model) => model,
+                None => default_model(&fallback.provider)?,
+            };
+            let inner = create_resilient(&fallback.provider, ModelConfig::new(model))?;
+            let tracked = TrackedProvider::new(inner, &fallback.provider);
+            chain.push((fallback.provider, Box::new(tracked)));
+        }
+        Box::new(FallbackProvider::new(chain))
+    };
+
+    if let Some(compaction) = CompactionConfig::from_config() {
+        let summarizer_name = compaction.provider.as_deref().unwrap_or(name);
+        let summarizer_model = match (&compaction.model, cheapest_model(summarizer_name)) {
+            (Some(model), _) => model.clone(),
+            (None, Some(cheapest)) => cheapest,
+            (None, None) if summarizer_name == name => model_config.model_name.clone(),
+            (None, None) => default_model(summarizer_name)?,
+        };
+        // Summaries are asked for at temperature 0, so a repeated one comes from memory
+        let summarizer_model = ModelConfig::new(summarizer_model).with_temperature(Some(0.0));
+        let mut summarizer = create_provider(summarizer_name, summarizer_model)?;
+        if let Some(memory) = memory_cache() {
+            summarizer = Box::new(MemoizingProvider::new(summarizer, summarizer_name, memory));
+        }
+        let summarizer = Box::new(TrackedProvider::new(summarizer, summarizer_name));
+        provider = Box::new(CompactingProvider::new(
+            provider,
+            summarizer,
+            compaction,
+            model_config.token_counter(),
+        ));
+    }
+
+    let quotas = Quota::from_config();
+    if !quotas.is_empty() {
+        provider = Box::new(QuotaProvider::new(provider, name, quotas));
+    }
+
+    let budget = BudgetConfig::from_config();
+    if !budget.is_empty() {
+        provider = Box::new(BudgetProvider::new(provider, budget));
+    }
+
+    // Outermost, so nothing is sent or counted for a blocked request
+    match GuardrailConfig::from_config() {
+        Some(guardrail) => Ok(Box::new(GuardrailProvider::new(provider, guardrail)?)),
+        None => Ok(provider),
+    }
+}
+
+/// The provider by this name, with the recording, faults, rate and concurrency limits, circuit
+/// breaker and response caches that are configured
+fn create_resilient(name: &str, model: ModelConfig) -> Result<Box<dyn Provider + Send + Sync>> {
+    let deterministic = model.temperature == Some(0.0);
+    let mut inner = match vcr::configured() {
+        // Replaying doesn't need the real provider, or its credentials
+        Some(vcr) if vcr.mode() == VcrMode::Replay => {
+            Box::new(VcrProvider::replay(name, model, vcr)) as Box<dyn Provider + Send + Sync>
+        }
+        Some(vcr) => Box::new(VcrProvider::new(create_provider(name, model)?, name, vcr)),
+        None => create_provider(name, model)?,
+    };
+    // Injected faults sit directly around the provider so everything above sees them as real
+    if let Some(faults) = FaultConfig::from_config() {
+        inner = Box::new(FaultProvider::new(inner, faults));
+    }
+    if let Some(limit) = RateLimit::for_provider(name) {
+        inner = Box::new(RateLimitedProvider::new(inner, name, limit));
+    }
+    if let Some(concurrency) = ConcurrencyConfig::from_config() {
+        let (global, own) = concurrency.semaphores(name);
+        inner = Box::new(ConcurrencyLimitedProvider::new(inner, global, own));
+    }
+    if let Some(circuit) = CircuitBreakerConfig::from_config() {
+        inner = Box::new(CircuitBreakerProvider::new(inner, name, circuit));
+    }
+    // Outermost, so a cached response skips the limits and circuit
+    if let Some(cache) = ResponseCacheConfig::from_config() {
+        match cache.dir() {
+            Ok(dir) => inner = Box::new(CachingProvider::new(inner, name, dir, cache.ttl_secs)),
+            Err(e) => tracing::warn!("Not caching responses, as there is no cache dir: {}", e),
+        }
+    }
+    // The same request gets the same answer at temperature 0, so it is kept in memory too
+    if deterministic {
+        if let Some(memory) = memory_cache() {
+            inner = Box::new(MemoizingProvider::new(inner, name, memory));
+        }
+    }
+    Ok(inner)
+}
+
+/// The default model of a provider, for fallbacks that don't name one
+pub(super) fn default_model(name: &str) -> Result<String> {
+    providers()
+        .into_iter()
+        .find(|metadata| metadata.name == name)
+        .map(|metadata| metadata.default_model)
+        .ok_or_else(|| anyhow::anyhow!("Unknown provider: {}", name))
+}
+
+/// The known model of a provider with the lowest input price, for work like summarizing
+/// that reads much more than it writes
+fn cheapest_model(name: &str) -> Option<String> {
+    let metadata = providers()
+        .into_iter()
+        .find(|metadata| metadata.name == name)?;
+    metadata
+        .known_models
+        .into_iter()
+        .filter_map(|model| get_pricing(&model).map(|pricing| (model, pricing.input)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(model, _)| model)
+}
+
+pub(super) fn create_provider(
+    name: &str,
+    model: ModelConfig,
+) -> Result<Box<dyn Provider + Send + Sync>> {
     match name {
         "openai" => Ok(Box::new(OpenAiProvider::from_env(model)?)),
+        "ai21" => Ok(Box::new(Ai21Provider::from_env(model)?)),
         "anthropic" => Ok(Box::new(AnthropicProvider::from_env(model)?)),
+        "anthropic_vertex" => Ok(Box::new(AnthropicVertexProvider::from_env(model)?)),
         "azure_openai" => Ok(Box::new(AzureProvider::from_env(model)?)),
+        "azure_ai" => Ok(Box::new(AzureAiProvider::from_env(model)?)),
         "bedrock" => Ok(Box::new(BedrockProvider::from_env(model)?)),
+        #[cfg(feature = "candle")]
+        "candle" => Ok(Box::new(super::candle::CandleProvider::from_env(model)?)),
+        "cloudflare" => Ok(Box::new(CloudflareProvider::from_env(model)?)),
+        "cohere" => Ok(Box::new(CohereProvider::from_env(model)?)),
         "databricks" => Ok(Box::new(DatabricksProvider::from_env(model)?)),
+        "deepseek" => Ok(Box::new(DeepSeekProvider::from_env(model)?)),
+        "fireworks" => Ok(Box::new(FireworksProvider::from_env(model)?)),
+        "github_models" => Ok(Box::new(GitHubModelsProvider::from_env(model)?)),
         "groq" => Ok(Box::new(GroqProvider::from_env(model)?)),
+        "huggingface" => Ok(Box::new(HuggingFaceProvider::from_env(model)?)),
+        "koboldcpp" => Ok(Box::new(KoboldCppProvider::from_env(model)?)),
+        "llamacpp" => Ok(Box::new(LlamaCppProvider::from_env(model)?)),
+        #[cfg(feature = "llama-cpp")]
+        "llamacpp_embedded" => Ok(Box::new(
+            super::llamacpp_embedded::EmbeddedLlamaCppProvider::from_env(model)?,
+        )),
+        "lmstudio" => Ok(Box::new(LmStudioProvider::from_env(model)?)),
+        "moonshot" => Ok(Box::new(MoonshotProvider::from_env(model)?)),
+        "nvidia" => Ok(Box::new(NvidiaProvider::from_env(model)?)),
+        "oci" => Ok(Box::new(OciProvider::from_env(model)?)),
         "ollama" => Ok(Box::new(OllamaProvider::from_env(model)?)),
         "openrouter" => Ok(Box::new(OpenRouterProvider::from_env(model)?)),
+        "perplexity" => Ok(Box::new(PerplexityProvider::from_env(model)?)),
+        "qwen" => Ok(Box::new(QwenProvider::from_env(model)?)),
+        "replicate" => Ok(Box::new(ReplicateProvider::from_env(model)?)),
+        "router" => Ok(Box::new(RouterProvider::from_env(model)?)),
+        "sagemaker" => Ok(Box::new(SageMakerProvider::from_env(model)?)),
         "google" => Ok(Box::new(GoogleProvider::from_env(model)?)),
-        _ => Err(anyhow::anyhow!("Unknown provider: {}", name)),
+        "scripted" | "mock" => Ok(Box::new(ScriptedProvider::from_env(model)?)),
+        "snowflake" => Ok(Box::new(SnowflakeProvider::from_env(model)?)),
+        "tgi" => Ok(Box::new(TgiProvider::from_env(model)?)),
+        "together" => Ok(Box::new(TogetherProvider::from_env(model)?)),
+        "vertex_ai" => Ok(Box::new(VertexAiProvider::from_env(model)?)),
+        "vllm" => Ok(Box::new(VllmProvider::from_env(model)?)),
+        "watsonx" => Ok(Box::new(WatsonxProvider::from_env(model)?)),
+        "xai" => Ok(Box::new(XaiProvider::from_env(model)?)),
+        "zhipu" => Ok(Box::new(ZhipuProvider::from_env(model)?)),
+        _ => {
+            #[cfg(any(test, feature = "test-utils"))]
+            if let Some(mock) = super::mock::MockProvider::registered(name, model.clone()) {
+                return Ok(Box::new(mock));
+            }
+            match CustomProvider::named(name, model) {
+                Some(custom) => Ok(Box::new(custom?)),
+                None => Err(anyhow::anyhow!("Unknown provider: {}", name)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+    use crate::providers::scripted::SCENARIO_CONFIG_KEY;
+
+    #[test]
+    fn test_cheapest_model() {
+        assert_eq!(cheapest_model("openai"), Some("gpt-4o-mini".to_string()));
+        assert_eq!(cheapest_model("no_such_provider"), None);
+    }
+
+    #[tokio::test]
+    async fn test_registered_mock_never_shadows_builtin() {
+        let scenario = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(scenario.path(), "steps:\n  - text: scripted\n").unwrap();
+        std::env::set_var(SCENARIO_CONFIG_KEY, scenario.path().to_str().unwrap());
+
+        let mock = MockProvider::default().with_text("shadowed");
+        mock.register("mock");
+        let created = create_provider("mock", ModelConfig::new("scripted".to_string()));
+        MockProvider::unregister("mock");
+        std::env::remove_var(SCENARIO_CONFIG_KEY);
+
+        // `mock` is the scripted provider, answering from its scenario rather than the mock
+        let (message, _) = created.unwrap().complete("", &[], &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "scripted");
+        assert!(mock.requests().is_empty());
     }
 }
@@ -0,0 +1,167 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::start_stream;
+use crate::config::Config;
+use crate::events::{self, Event};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the list of `FallbackEntry`s
+pub const FALLBACK_CONFIG_KEY: &str = "GOOSE_FALLBACK";
+
+/// A provider to send requests to when the ones before it in the chain can't take them
+///
+/// ```yaml
+/// GOOSE_FALLBACK:
+///   - provider: openrouter
+///     model: anthropic/claude-3.5-sonnet
+///   - provider: ollama
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FallbackEntry {
+    pub provider: String,
+    /// The model to use, the provider's default when not set
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl FallbackEntry {
+    /// Read `GOOSE_FALLBACK`, returning an empty chain when it is not set
+    pub fn from_config() -> Vec<Self> {
+        Config::global()
+            .get(FALLBACK_CONFIG_KEY)
+            .unwrap_or_default()
+    }
+}
+
+/// Whether an error means another provider could still take the request
+fn should_fall_back(error: &ProviderError) -> bool {
+    matches!(
+        error,
+        ProviderError::RateLimitExceeded(_)
+            | ProviderError::ServerError(_)
+            | ProviderError::ContextLengthExceeded(_)
+            | ProviderError::CircuitOpen(_)
+    )
+}
+
+/// A provider wrapper that tries an ordered chain of providers until one answers
+///
+/// A request goes to the first provider, and moves on to the next when it is rate limited,
+/// overloaded, or the conversation is too long for its context window. Every provider
+/// formats the conversation for its own API, so the chain can mix providers freely. Any
+/// other error is returned right away, as is the error of the last provider.
+pub struct FallbackProvider {
+    chain: Vec<(String, Box<dyn Provider>)>,
+}
+
+impl FallbackProvider {
+    /// Chain the providers, by name, in the order they are tried; the first is the primary
+    pub fn new(chain: Vec<(String, Box<dyn Provider>)>) -> Self {
+        assert!(!chain.is_empty(), "A fallback chain needs a provider");
+        Self { chain }
+    }
+}
+
+#[async_trait]
+impl Provider for FallbackProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.chain[0].1.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut providers = self.chain.iter().peekable();
+        loop {
+            let (name, provider) = providers.next().expect("the chain is not empty");
+            let error = match provider.complete(system, messages, tools).await {
+                Err(error) if should_fall_back(&error) => error,
+                result => return result,
+            };
+            let Some((next, _)) = providers.peek() else {
+                return Err(error);
+            };
+            report_fallback(name, next, &error);
+        }
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let mut providers = self.chain.iter().peekable();
+        loop {
+            let (name, provider) = providers.next().expect("the chain is not empty");
+            // Wait for the first delta so errors the provider only reports in the
+            // response body can still fall back; once output has started it can't
+            let result = match provider.stream(system, messages, tools).await {
+                Ok(stream) => start_stream(stream).await,
+                Err(error) => Err(error),
+            };
+            let error = match result {
+                Err(error) if should_fall_back(&error) => error,
+                result => return result,
+            };
+            let Some((next, _)) = providers.peek() else {
+                return Err(error);
+            };
+            report_fallback(name, next, &error);
+        }
+    }
+}
+
+fn report_fallback(from: &str, to: &str, error: &ProviderError) {
+    tracing::warn!("{} failed, falling back to {}: {}", from, to, error);
+    events::emit(Event::Fallback {
+        from: from.to_string(),
+        to: to.to_string(),
+        reason: error.to_string(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    #[tokio::test]
+    async fn test_falls_back_in_order() {
+        let primary = MockProvider::default()
+            .with_error(ProviderError::RateLimitExceeded("slow down".to_string()))
+            .with_error(ProviderError::Authentication("bad key".to_string()));
+        let secondary = MockProvider::default()
+            .with_error(ProviderError::ContextLengthExceeded("too long".to_string()));
+        let local = MockProvider::default().with_text("from the last provider");
+        let provider = FallbackProvider::new(vec![
+            (
+                "anthropic".to_string(),
+                Box::new(primary) as Box<dyn Provider>,
+            ),
+            ("openrouter".to_string(), Box::new(secondary.clone())),
+            ("ollama".to_string(), Box::new(local.clone())),
+        ]);
+
+        let (message, _) = provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "from the last provider");
+        assert_eq!(secondary.requests().len(), 1);
+
+        // Errors that another provider wouldn't fix are returned as they are
+        let result = provider.complete("", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::Authentication(_))));
+        assert_eq!(local.requests().len(), 1);
+    }
+}
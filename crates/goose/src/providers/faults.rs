@@ -0,0 +1,321 @@
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::base::{message_stream, MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::collect_stream;
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+use mcp_core::ToolError;
+
+/// Config key holding the `FaultConfig`
+pub const FAULTS_CONFIG_KEY: &str = "GOOSE_FAULTS";
+
+/// How often each kind of fault is injected, as probabilities between 0 and 1
+///
+/// ```yaml
+/// GOOSE_FAULTS:
+///   latency_ms: [200, 2000]
+///   rate_limit: 0.1
+///   server_error: 0.05
+///   truncated_json: 0.05
+///   malformed_tool_call: 0.2
+///   seed: 42
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FaultConfig {
+    /// Extra latency added to every request, picked uniformly from this range
+    #[serde(default)]
+    pub latency_ms: Option<(u64, u64)>,
+    /// Fail with a 429 before the request is sent
+    #[serde(default)]
+    pub rate_limit: f64,
+    /// Fail with a 500 before the request is sent
+    #[serde(default)]
+    pub server_error: f64,
+    /// Send the request, then fail as if the response body had been cut off
+    #[serde(default)]
+    pub truncated_json: f64,
+    /// Corrupt the arguments of every tool call in the response
+    #[serde(default)]
+    pub malformed_tool_call: f64,
+    /// Seed for a reproducible sequence of faults
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl FaultConfig {
+    /// Read `GOOSE_FAULTS`, returning None when it is not set
+    pub fn from_config() -> Option<Self> {
+        Config::global().get(FAULTS_CONFIG_KEY).ok()
+    }
+}
+
+/// A fault that was injected into a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    RateLimit,
+    ServerError,
+    TruncatedJson,
+    MalformedToolCall,
+}
+
+/// A provider wrapper that injects latency, errors and corrupted responses
+///
+/// This exercises the error handling around providers (retries, fallbacks, tool call repair)
+/// against a real or mock provider. At most one fault is injected per request; the
+/// probabilities are checked in the order of the `FaultConfig` fields.
+pub struct FaultProvider {
+    inner: Box<dyn Provider>,
+    config: FaultConfig,
+    rng: Mutex<StdRng>,
+    injected: Mutex<Vec<Fault>>,
+}
+
+impl FaultProvider {
+    pub fn new(inner: Box<dyn Provider>, config: FaultConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self {
+            inner,
+            config,
+            rng: Mutex::new(rng),
+            injected: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Every fault injected so far, in order
+    pub fn injected(&self) -> Vec<Fault> {
+        self.injected.lock().unwrap().clone()
+    }
+
+    fn latency(&self) -> Option<Duration> {
+        let (min, max) = self.config.latency_ms?;
+        let ms = self.rng.lock().unwrap().gen_range(min..=max.max(min));
+        Some(Duration::from_millis(ms))
+    }
+
+    fn pick_fault(&self) -> Option<Fault> {
+        let mut rng = self.rng.lock().unwrap();
+        let fault = [
+            (Fault::RateLimit, self.config.rate_limit),
+            (Fault::ServerError, self.config.server_error),
+            (Fault::TruncatedJson, self.config.truncated_json),
+            (Fault::MalformedToolCall, self.config.malformed_tool_call),
+        ]
+        .into_iter()
+        .find(|(_, probability)| rng.gen_bool(probability.clamp(0.0, 1.0)))
+        .map(|(fault, _)| fault)?;
+
+        self.injected.lock().unwrap().push(fault);
+        Some(fault)
+    }
+
+    /// Add the latency and pick the fault of a request, failing it when the fault is an error
+    async fn before_request(&self) -> Result<Option<Fault>, ProviderError> {
+        if let Some(latency) = self.latency() {
+            tokio::time::sleep(latency).await;
+        }
+
+        let fault = self.pick_fault();
+        if let Some(fault) = fault {
+            tracing::debug!("Injecting provider fault: {:?}", fault);
+        }
+
+        match fault {
+            Some(Fault::RateLimit) => Err(ProviderError::RateLimitExceeded(
+                "Injected fault: 429 Too Many Requests".to_string(),
+            )),
+            Some(Fault::ServerError) => Err(ProviderError::ServerError(
+                "Injected fault: 500 Internal Server Error".to_string(),
+            )),
+            _ => Ok(fault),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for FaultProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let fault = self.before_request().await?;
+        let (message, usage) = self.inner.complete(system, messages, tools).await?;
+        corrupt(fault, message, usage)
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let fault = self.before_request().await?;
+        let stream = self.inner.stream(system, messages, tools).await?;
+        match fault {
+            // Corrupting a response needs all of it, so it is replayed once it is complete
+            Some(Fault::TruncatedJson | Fault::MalformedToolCall) => {
+                let model = self.get_model_config().model_name;
+                let (message, usage) = collect_stream(stream, &model).await?;
+                let (message, usage) = corrupt(fault, message, usage)?;
+                Ok(message_stream(message, usage))
+            }
+            _ => Ok(stream),
+        }
+    }
+}
+
+// Apply the faults that corrupt a response the provider returned
+fn corrupt(
+    fault: Option<Fault>,
+    message: Message,
+    usage: ProviderUsage,
+) -> Result<(Message, ProviderUsage), ProviderError> {
+    match fault {
+        // Fail the same way a provider does when the body of a response can't be decoded
+        Some(Fault::TruncatedJson) => Err(ProviderError::RequestFailed(format!(
+            "Injected fault: error decoding response body: {}",
+            truncated_json_error(&message)
+        ))),
+        Some(Fault::MalformedToolCall) => Ok((malform_tool_calls(message), usage)),
+        _ => Ok((message, usage)),
+    }
+}
+
+// The parse error for the first half of the serialized message
+fn truncated_json_error(message: &Message) -> serde_json::Error {
+    let json = serde_json::to_string(message).unwrap_or_default();
+    let cut = json
+        .char_indices()
+        .map(|(i, _)| i)
+        .find(|i| *i >= json.len() / 2)
+        .unwrap_or(0);
+    serde_json::from_str::<serde_json::Value>(&json[..cut])
+        .expect_err("half of a JSON object does not parse")
+}
+
+// Replace every tool call with the error providers return when its arguments aren't valid JSON
+fn malform_tool_calls(mut message: Message) -> Message {
+    for content in message.content.iter_mut() {
+        if let MessageContent::ToolRequest(request) = content {
+            let arguments = request
+                .tool_call
+                .as_ref()
+                .map(|call| call.arguments.to_string())
+                .unwrap_or_default();
+            let truncated: String = arguments
+                .chars()
+                .take(arguments.chars().count() / 2)
+                .collect();
+            let error = serde_json::from_str::<serde_json::Value>(&truncated)
+                .err()
+                .map(|e| e.to_string())
+                .unwrap_or_else(|| "invalid JSON".to_string());
+            request.tool_call = Err(ToolError::InvalidParameters(format!(
+                "Could not interpret tool use parameters for id {}: {}",
+                request.id, error
+            )));
+        }
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_errors_before_request() {
+        let mock = MockProvider::default().with_text("unused");
+        let config = FaultConfig {
+            rate_limit: 1.0,
+            ..Default::default()
+        };
+        let provider = FaultProvider::new(Box::new(mock.clone()), config);
+
+        let result = provider.complete("", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::RateLimitExceeded(_))));
+        assert!(mock.requests().is_empty());
+        assert_eq!(provider.injected(), vec![Fault::RateLimit]);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_json() {
+        let mock = MockProvider::default().with_text("a reply that is lost");
+        let config = FaultConfig {
+            truncated_json: 1.0,
+            ..Default::default()
+        };
+        let provider = FaultProvider::new(Box::new(mock.clone()), config);
+
+        match provider.complete("", &[], &[]).await {
+            Err(ProviderError::RequestFailed(message)) => {
+                assert!(message.contains("EOF while parsing"));
+            }
+            other => panic!("Expected RequestFailed, got {:?}", other.map(|r| r.0)),
+        }
+        // The request still reached the provider
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_tool_call() {
+        let mock =
+            MockProvider::default().with_tool_call("developer__shell", json!({"command": "ls"}));
+        let config = FaultConfig {
+            malformed_tool_call: 1.0,
+            ..Default::default()
+        };
+        let provider = FaultProvider::new(Box::new(mock), config);
+
+        let (message, _) = provider.complete("", &[], &[]).await.unwrap();
+        let request = message.content[0].as_tool_request().unwrap();
+        assert!(matches!(
+            &request.tool_call,
+            Err(ToolError::InvalidParameters(m)) if m.contains("Could not interpret")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_seeded_faults_are_reproducible() {
+        let config = FaultConfig {
+            rate_limit: 0.3,
+            server_error: 0.3,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let run = || async {
+            let mock = (0..20).fold(MockProvider::default(), |mock, _| mock.with_text("ok"));
+            let provider = FaultProvider::new(Box::new(mock), config.clone());
+            for _ in 0..20 {
+                let _ = provider.complete("", &[], &[]).await;
+            }
+            provider.injected()
+        };
+
+        let first = run().await;
+        assert!(!first.is_empty());
+        assert_eq!(first, run().await);
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::fireworks::{create_request, get_usage, model_name, response_to_message};
+use super::keys::KeyPool;
+use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const FIREWORKS_API_HOST: &str = "https://api.fireworks.ai";
+pub const FIREWORKS_DEFAULT_MODEL: &str = "llama-v3p1-405b-instruct";
+// Models that support function calling, relative to accounts/fireworks/models/
+pub const FIREWORKS_KNOWN_MODELS: &[&str] = &[
+    "llama-v3p1-405b-instruct",
+    "llama-v3p1-70b-instruct",
+    "llama-v3p3-70b-instruct",
+    "qwen2p5-72b-instruct",
+    "deepseek-v3",
+    "firefunction-v2",
+];
+
+pub const FIREWORKS_DOC_URL: &str = "https://docs.fireworks.ai/guides/function-calling";
+
+#[derive(serde::Serialize)]
+pub struct FireworksProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_keys: KeyPool,
+    model: ModelConfig,
+}
+
+impl Default for FireworksProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(FireworksProvider::metadata().default_model);
+        FireworksProvider::from_env(model).expect("Failed to initialize Fireworks provider")
+    }
+}
+
+impl FireworksProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_keys = KeyPool::from_secret("FIREWORKS_API_KEY")?;
+        let host: String = config
+            .get("FIREWORKS_HOST")
+            .unwrap_or_else(|_| FIREWORKS_API_HOST.to_string());
+
+        let client = http_client("FIREWORKS")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_keys,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url
+            .join("inference/v1/chat/completions")
+            .map_err(|e| {
+                ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+            })?;
+
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Authorization", format!("Bearer {}", key))
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for FireworksProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "fireworks",
+            "Fireworks AI",
+            "Fast open source and fine-tuned models on Fireworks AI",
+            FIREWORKS_DEFAULT_MODEL,
+            FIREWORKS_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            FIREWORKS_DOC_URL,
+            vec![
+                ConfigKey::new("FIREWORKS_API_KEY", true, true, None),
+                ConfigKey::new("FIREWORKS_HOST", false, false, Some(FIREWORKS_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = vcr::post("fireworks", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = model_name(&get_model(&response)).to_string();
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
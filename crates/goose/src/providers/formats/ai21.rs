@@ -0,0 +1,126 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai;
+use crate::providers::utils::ImageFormat;
+use anyhow::Result;
+use mcp_core::Tool;
+use serde_json::{json, Value};
+
+/// Create a request payload for AI21's chat completions API
+///
+/// Jamba takes the OpenAI message and tool format, but message content has to be a plain
+/// string: there are no content parts or images, and an assistant message with tool calls
+/// still needs a (possibly empty) content.
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut payload =
+        openai::create_request(model_config, system, messages, tools, &ImageFormat::OpenAi)?;
+
+    if let Some(messages) = payload["messages"].as_array_mut() {
+        for message in messages {
+            match &message["content"] {
+                Value::Array(parts) => {
+                    let text = parts
+                        .iter()
+                        .map(|part| match part["type"].as_str() {
+                            Some("text") => part["text"].as_str().unwrap_or_default(),
+                            _ => "[This message included an image, which Jamba can't see]",
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    message["content"] = json!(text);
+                }
+                Value::Null => message["content"] = json!(""),
+                _ => {}
+            }
+        }
+    }
+    Ok(payload)
+}
+
+/// Convert an AI21 chat completion to internal Message format
+///
+/// Tool call arguments usually come as an encoded string like OpenAI's, but can also come
+/// as an object.
+pub fn response_to_message(mut response: Value) -> Result<Message> {
+    if let Some(tool_calls) = response["choices"][0]["message"]["tool_calls"].as_array_mut() {
+        for tool_call in tool_calls {
+            let arguments = &mut tool_call["function"]["arguments"];
+            if arguments.is_object() {
+                *arguments = json!(arguments.to_string());
+            }
+        }
+    }
+    openai::response_to_message(response)
+}
+
+/// Extract usage information from an AI21 chat completion
+pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
+    openai::get_usage(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::Content;
+
+    #[test]
+    fn test_function_calling_round_trip() -> Result<()> {
+        let response = json!({
+            "id": "chat-6a9b1b2a",
+            "model": "jamba-large",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_5f1d",
+                        "type": "function",
+                        "function": { "name": "get_weather", "arguments": { "city": "Tel Aviv" } }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": { "prompt_tokens": 120, "completion_tokens": 21, "total_tokens": 141 }
+        });
+
+        let message = response_to_message(response.clone())?;
+        let request = message.content[0].as_tool_request().unwrap();
+        let tool_call = request.tool_call.as_ref().unwrap();
+        assert_eq!(tool_call.name, "get_weather");
+        assert_eq!(tool_call.arguments, json!({ "city": "Tel Aviv" }));
+        assert_eq!(get_usage(&response)?.total_tokens, Some(141));
+
+        let messages = vec![
+            Message::user().with_text("What's the weather in Tel Aviv?"),
+            message,
+            Message::user().with_tool_response("call_5f1d", Ok(vec![Content::text("Sunny")])),
+        ];
+        let payload = create_request(
+            &ModelConfig::new("jamba-large".to_string()),
+            "system",
+            &messages,
+            &[Tool::new(
+                "get_weather",
+                "Get the weather",
+                json!({ "type": "object", "properties": { "city": { "type": "string" } } }),
+            )],
+        )?;
+        let spec = payload["messages"].as_array().unwrap();
+        assert_eq!(spec[2]["role"], "assistant");
+        assert_eq!(spec[2]["content"], "");
+        assert_eq!(spec[2]["tool_calls"][0]["id"], "call_5f1d");
+        assert_eq!(spec[3]["role"], "tool");
+        assert_eq!(spec[3]["content"], "Sunny");
+        assert_eq!(payload["tools"][0]["function"]["name"], "get_weather");
+
+        Ok(())
+    }
+}
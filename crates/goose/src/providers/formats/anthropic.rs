@@ -1,13 +1,13 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{MessageDelta, Usage};
 use crate::providers::errors::ProviderError;
 use anyhow::{anyhow, Result};
 use mcp_core::content::Content;
 use mcp_core::role::Role;
 use mcp_core::tool::{Tool, ToolCall};
 use serde_json::{json, Value};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Convert internal Message format to Anthropic's API message specification
 pub fn format_messages(messages: &[Message]) -> Vec<Value> {
@@ -60,6 +60,9 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::ToolConfirmationRequest(_tool_confirmation_request) => {
                     // Skip tool confirmation requests
                 }
+                MessageContent::Thinking(_) => {
+                    // Reasoning from earlier turns isn't sent back
+                }
                 MessageContent::Image(_) => continue, // Anthropic doesn't support image content yet
             }
         }
@@ -194,29 +197,35 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
         // - input_tokens (fresh/uncached)
         // - cache_creation_input_tokens (being written to cache)
         // - cache_read_input_tokens (read from cache)
+        let cache_write_tokens = usage
+            .get("cache_creation_input_tokens")
+            .and_then(|v| v.as_u64());
+        let cache_read_tokens = usage
+            .get("cache_read_input_tokens")
+            .and_then(|v| v.as_u64());
         let total_input_tokens = usage
             .get("input_tokens")
             .and_then(|v| v.as_u64())
             .unwrap_or(0)
-            + usage
-                .get("cache_creation_input_tokens")
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0)
-            + usage
-                .get("cache_read_input_tokens")
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0);
+            .saturating_add(cache_write_tokens.unwrap_or(0))
+            .saturating_add(cache_read_tokens.unwrap_or(0));
+        let total_input_tokens = i32::try_from(total_input_tokens).unwrap_or(i32::MAX);
 
-        let input_tokens = Some(total_input_tokens as i32);
+        let input_tokens = Some(total_input_tokens);
 
         let output_tokens = usage
             .get("output_tokens")
             .and_then(|v| v.as_u64())
             .map(|v| v as i32);
 
-        let total_tokens = output_tokens.map(|o| total_input_tokens as i32 + o);
+        let total_tokens = output_tokens.map(|o| total_input_tokens.saturating_add(o));
 
-        Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+        Ok(
+            Usage::new(input_tokens, output_tokens, total_tokens).with_cache_tokens(
+                cache_read_tokens.map(|v| v as i32),
+                cache_write_tokens.map(|v| v as i32),
+            ),
+        )
     } else {
         tracing::debug!(
             "Failed to get usage data: {}",
@@ -227,6 +236,104 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
     }
 }
 
+/// What is known of a streamed message across the events of the stream
+///
+/// Content blocks are numbered across text and tool use, while `MessageDelta::ToolCall`
+/// counts only tool calls, and the usage is split between the first and last events.
+#[derive(Debug, Default)]
+pub struct StreamState {
+    /// The tool call index of each tool_use block, by block index
+    tool_calls: HashMap<u64, usize>,
+    pub model: Option<String>,
+    usage: serde_json::Map<String, Value>,
+}
+
+impl StreamState {
+    /// Convert an event of a streamed message to the deltas of the message
+    pub fn event_to_deltas(&mut self, event: &Value) -> Result<Vec<MessageDelta>, ProviderError> {
+        let mut deltas = Vec::new();
+        match event["type"].as_str() {
+            Some("message_start") => {
+                let message = &event["message"];
+                self.model = message["model"].as_str().map(String::from);
+                self.merge_usage(&message["usage"]);
+            }
+            Some("content_block_start") => {
+                let block = &event["content_block"];
+                if block["type"] == "tool_use" {
+                    let index = self.tool_calls.len();
+                    let block_index = event["index"].as_u64().unwrap_or_default();
+                    self.tool_calls.insert(block_index, index);
+                    deltas.push(MessageDelta::ToolCall {
+                        index,
+                        id: block["id"].as_str().map(String::from),
+                        name: block["name"].as_str().map(String::from),
+                        arguments: String::new(),
+                    });
+                }
+            }
+            Some("content_block_delta") => {
+                let delta = &event["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        let text = delta["text"].as_str().unwrap_or_default();
+                        deltas.push(MessageDelta::Text(text.to_string()));
+                    }
+                    Some("thinking_delta") => {
+                        let thinking = delta["thinking"].as_str().unwrap_or_default();
+                        deltas.push(MessageDelta::Thinking(thinking.to_string()));
+                    }
+                    Some("input_json_delta") => {
+                        let block_index = event["index"].as_u64().unwrap_or_default();
+                        if let Some(&index) = self.tool_calls.get(&block_index) {
+                            deltas.push(MessageDelta::ToolCall {
+                                index,
+                                id: None,
+                                name: None,
+                                arguments: delta["partial_json"]
+                                    .as_str()
+                                    .unwrap_or_default()
+                                    .to_string(),
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Some("message_delta") => self.merge_usage(&event["usage"]),
+            Some("error") => {
+                let message = event["error"]["message"]
+                    .as_str()
+                    .unwrap_or("Unknown error")
+                    .to_string();
+                return Err(match event["error"]["type"].as_str() {
+                    Some("overloaded_error" | "api_error") => ProviderError::ServerError(message),
+                    Some("rate_limit_error") => ProviderError::RateLimitExceeded(message),
+                    _ => ProviderError::RequestFailed(message),
+                });
+            }
+            // message_stop, content_block_stop and ping
+            _ => {}
+        }
+        Ok(deltas)
+    }
+
+    /// The usage of the message, once the stream is over
+    pub fn usage(&self) -> Result<Usage> {
+        get_usage(&json!({ "usage": self.usage }))
+    }
+
+    fn merge_usage(&mut self, usage: &Value) {
+        if let Some(usage) = usage.as_object() {
+            for (key, value) in usage {
+                if !value.is_null() {
+                    self.usage.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
 /// Create a complete request payload for Anthropic's API
 pub fn create_request(
     model_config: &ModelConfig,
@@ -314,6 +421,8 @@ mod tests {
         assert_eq!(usage.input_tokens, Some(24)); // 12 + 12 + 0
         assert_eq!(usage.output_tokens, Some(15));
         assert_eq!(usage.total_tokens, Some(39)); // 24 + 15
+        assert_eq!(usage.cache_read_tokens, Some(0));
+        assert_eq!(usage.cache_write_tokens, Some(12));
 
         Ok(())
     }
@@ -436,4 +545,61 @@ mod tests {
         assert_eq!(spec_array[0]["text"], system);
         assert!(spec_array[0].get("cache_control").is_some());
     }
+
+    #[test]
+    fn test_get_usage_saturates() -> Result<()> {
+        let response = json!({
+            "usage": {
+                "input_tokens": u64::MAX,
+                "cache_read_input_tokens": 10,
+                "output_tokens": 10
+            }
+        });
+
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(i32::MAX));
+        assert_eq!(usage.total_tokens, Some(i32::MAX));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_events_to_deltas() -> Result<()> {
+        let events = [
+            json!({"type": "message_start", "message": {"model": "claude-3-5-sonnet-20241022", "usage": {"input_tokens": 25, "cache_read_input_tokens": 100, "output_tokens": 1}}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Checking."}}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "shell", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"command\": "}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"ls\"}"}}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 30}}),
+            json!({"type": "message_stop"}),
+        ];
+        let mut state = StreamState::default();
+        let mut deltas = Vec::new();
+        for event in &events {
+            deltas.extend(state.event_to_deltas(event)?);
+        }
+
+        assert!(matches!(&deltas[0], MessageDelta::Text(text) if text == "Checking."));
+        assert!(matches!(
+            &deltas[1],
+            MessageDelta::ToolCall { index: 0, id: Some(id), .. } if id == "toolu_1"
+        ));
+        assert!(matches!(
+            &deltas[3],
+            MessageDelta::ToolCall { index: 0, arguments, .. } if arguments == "\"ls\"}"
+        ));
+        assert_eq!(state.model.as_deref(), Some("claude-3-5-sonnet-20241022"));
+        let usage = state.usage()?;
+        assert_eq!(usage.input_tokens, Some(125));
+        assert_eq!(usage.output_tokens, Some(30));
+
+        let error = json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}});
+        assert!(matches!(
+            state.event_to_deltas(&error),
+            Err(ProviderError::ServerError(_))
+        ));
+        Ok(())
+    }
 }
@@ -4,12 +4,165 @@ use std::path::Path;
 use anyhow::{anyhow, bail, Result};
 use aws_sdk_bedrockruntime::types as bedrock;
 use aws_smithy_types::{Document, Number};
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 use chrono::Utc;
 use mcp_core::{Content, ResourceContents, Role, Tool, ToolCall, ToolError, ToolResult};
-use serde_json::Value;
+use serde_json::{json, Value};
 
-use super::super::base::Usage;
+use super::super::base::{MessageDelta, ProviderUsage, Usage};
 use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+
+/// The parts of a Converse request that come from the conversation, shared by `converse`
+/// and `converse_stream`
+#[derive(Debug, Clone)]
+pub struct ConverseRequest {
+    pub system: Vec<bedrock::SystemContentBlock>,
+    pub messages: Vec<bedrock::Message>,
+    pub tool_config: Option<bedrock::ToolConfiguration>,
+    pub inference_config: Option<bedrock::InferenceConfiguration>,
+}
+
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<ConverseRequest> {
+    let inference_config =
+        (model_config.max_tokens.is_some() || model_config.temperature.is_some()).then(|| {
+            bedrock::InferenceConfiguration::builder()
+                .set_max_tokens(model_config.max_tokens)
+                .set_temperature(model_config.temperature)
+                .build()
+        });
+    Ok(ConverseRequest {
+        inference_config,
+        system: vec![bedrock::SystemContentBlock::Text(system.to_string())],
+        messages: messages
+            .iter()
+            .map(to_bedrock_message)
+            .collect::<Result<_>>()?,
+        tool_config: match tools {
+            [] => None,
+            tools => Some(to_bedrock_tool_config(tools)?),
+        },
+    })
+}
+
+impl ConverseRequest {
+    /// The request body as the Converse API sends it, for debug traces and golden files
+    pub fn to_json(&self, model_id: &str) -> Result<Value> {
+        let system: Vec<Value> = self
+            .system
+            .iter()
+            .filter_map(|block| match block {
+                bedrock::SystemContentBlock::Text(text) => Some(json!({ "text": text })),
+                _ => None,
+            })
+            .collect();
+        let messages = self
+            .messages
+            .iter()
+            .map(|message| {
+                Ok(json!({
+                    "role": message.role().as_str(),
+                    "content": message
+                        .content()
+                        .iter()
+                        .map(content_block_json)
+                        .collect::<Result<Vec<_>>>()?,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut request = json!({
+            "modelId": model_id,
+            "system": system,
+            "messages": messages,
+        });
+        if let Some(tool_config) = &self.tool_config {
+            let tools = tool_config
+                .tools()
+                .iter()
+                .filter_map(|tool| match tool {
+                    bedrock::Tool::ToolSpec(spec) => Some(spec),
+                    _ => None,
+                })
+                .map(|spec| {
+                    let schema = match spec.input_schema() {
+                        Some(bedrock::ToolInputSchema::Json(schema)) => from_bedrock_json(schema)?,
+                        _ => Value::Null,
+                    };
+                    Ok(json!({
+                        "toolSpec": {
+                            "name": spec.name(),
+                            "description": spec.description(),
+                            "inputSchema": { "json": schema },
+                        }
+                    }))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            request["toolConfig"] = json!({ "tools": tools });
+        }
+        if let Some(inference_config) = &self.inference_config {
+            let mut config = json!({});
+            if let Some(max_tokens) = inference_config.max_tokens() {
+                config["maxTokens"] = json!(max_tokens);
+            }
+            if let Some(temperature) = inference_config.temperature() {
+                config["temperature"] = json!(temperature);
+            }
+            request["inferenceConfig"] = config;
+        }
+        Ok(request)
+    }
+}
+
+fn content_block_json(block: &bedrock::ContentBlock) -> Result<Value> {
+    Ok(match block {
+        bedrock::ContentBlock::Text(text) => json!({ "text": text }),
+        bedrock::ContentBlock::ToolUse(tool_use) => json!({
+            "toolUse": {
+                "toolUseId": tool_use.tool_use_id(),
+                "name": tool_use.name(),
+                "input": from_bedrock_json(tool_use.input())?,
+            }
+        }),
+        bedrock::ContentBlock::ToolResult(tool_result) => json!({
+            "toolResult": {
+                "toolUseId": tool_result.tool_use_id(),
+                "content": tool_result
+                    .content()
+                    .iter()
+                    .map(|content| match content {
+                        bedrock::ToolResultContentBlock::Text(text) => json!({ "text": text }),
+                        bedrock::ToolResultContentBlock::Document(document) => {
+                            json!({ "document": document_json(document) })
+                        }
+                        _ => Value::Null,
+                    })
+                    .collect::<Vec<_>>(),
+                "status": tool_result.status().map(|status| status.as_str()),
+            }
+        }),
+        bedrock::ContentBlock::Document(document) => json!({ "document": document_json(document) }),
+        _ => Value::Null,
+    })
+}
+
+fn document_json(document: &bedrock::DocumentBlock) -> Value {
+    let bytes = match document.source() {
+        Some(bedrock::DocumentSource::Bytes(bytes)) => Some(BASE64_STANDARD.encode(bytes.as_ref())),
+        _ => None,
+    };
+    json!({
+        "format": document.format().as_str(),
+        "name": document.name(),
+        "source": { "bytes": bytes },
+    })
+}
 
 pub fn to_bedrock_message(message: &Message) -> Result<bedrock::Message> {
     bedrock::Message::builder()
@@ -18,18 +171,23 @@ pub fn to_bedrock_message(message: &Message) -> Result<bedrock::Message> {
             message
                 .content
                 .iter()
-                .map(to_bedrock_message_content)
+                .filter_map(|content| to_bedrock_message_content(content).transpose())
                 .collect::<Result<_>>()?,
         ))
         .build()
         .map_err(|err| anyhow!("Failed to construct Bedrock message: {}", err))
 }
 
-pub fn to_bedrock_message_content(content: &MessageContent) -> Result<bedrock::ContentBlock> {
-    Ok(match content {
+/// Convert message content to a Bedrock content block, None for content that isn't sent
+pub fn to_bedrock_message_content(
+    content: &MessageContent,
+) -> Result<Option<bedrock::ContentBlock>> {
+    Ok(Some(match content {
         MessageContent::Text(text) => bedrock::ContentBlock::Text(text.text.to_string()),
-        MessageContent::ToolConfirmationRequest(_tool_confirmation_request) => {
-            bedrock::ContentBlock::Text("".to_string())
+        // Confirmations are between goose and the user, and reasoning from earlier turns
+        // isn't sent back
+        MessageContent::ToolConfirmationRequest(_) | MessageContent::Thinking(_) => {
+            return Ok(None)
         }
         MessageContent::Image(_) => {
             bail!("Image content is not supported by Bedrock provider yet")
@@ -71,7 +229,7 @@ pub fn to_bedrock_message_content(content: &MessageContent) -> Result<bedrock::C
                     .build()?,
             )
         }
-    })
+    }))
 }
 
 pub fn to_bedrock_tool_result_content_block(
@@ -185,11 +343,7 @@ pub fn from_bedrock_message(message: &bedrock::Message) -> Result<Message> {
         .collect::<Result<Vec<_>>>()?;
     let created = Utc::now().timestamp();
 
-    Ok(Message {
-        role,
-        content,
-        created,
-    })
+    Ok(Message::new(role, created, content))
 }
 
 pub fn from_bedrock_content_block(block: &bedrock::ContentBlock) -> Result<MessageContent> {
@@ -242,10 +396,81 @@ pub fn from_bedrock_role(role: &bedrock::ConversationRole) -> Result<Role> {
 }
 
 pub fn from_bedrock_usage(usage: &bedrock::TokenUsage) -> Usage {
-    Usage {
-        input_tokens: Some(usage.input_tokens),
-        output_tokens: Some(usage.output_tokens),
-        total_tokens: Some(usage.total_tokens),
+    // Like Anthropic, bedrock reports cached tokens separately from input_tokens
+    let cache_read_tokens = usage.cache_read_input_tokens;
+    let cache_write_tokens = usage.cache_write_input_tokens;
+    let input_tokens = usage
+        .input_tokens
+        .saturating_add(cache_read_tokens.unwrap_or(0))
+        .saturating_add(cache_write_tokens.unwrap_or(0));
+    Usage::new(
+        Some(input_tokens),
+        Some(usage.output_tokens),
+        Some(usage.total_tokens),
+    )
+    .with_cache_tokens(cache_read_tokens, cache_write_tokens)
+}
+
+/// What is known of a message streamed by ConverseStream across its events
+///
+/// Content blocks are numbered across text and tool use, while `MessageDelta::ToolCall`
+/// counts only tool calls.
+#[derive(Debug, Default)]
+pub struct BedrockStreamState {
+    /// The tool call index of each tool use block, by block index
+    tool_calls: HashMap<i32, usize>,
+    /// The usage from the metadata event, which comes last
+    usage: Usage,
+}
+
+impl BedrockStreamState {
+    /// Convert a ConverseStream event to the deltas of the message, keeping the usage for
+    /// `finish`
+    pub fn event_to_deltas(&mut self, event: &bedrock::ConverseStreamOutput) -> Vec<MessageDelta> {
+        match event {
+            bedrock::ConverseStreamOutput::Metadata(metadata) => {
+                if let Some(usage) = &metadata.usage {
+                    self.usage = from_bedrock_usage(usage);
+                }
+                vec![]
+            }
+            bedrock::ConverseStreamOutput::ContentBlockStart(event) => match &event.start {
+                Some(bedrock::ContentBlockStart::ToolUse(tool_use)) => {
+                    let index = self.tool_calls.len();
+                    self.tool_calls.insert(event.content_block_index, index);
+                    vec![MessageDelta::ToolCall {
+                        index,
+                        id: Some(tool_use.tool_use_id.clone()),
+                        name: Some(tool_use.name.clone()),
+                        arguments: String::new(),
+                    }]
+                }
+                _ => vec![],
+            },
+            bedrock::ConverseStreamOutput::ContentBlockDelta(event) => match &event.delta {
+                Some(bedrock::ContentBlockDelta::Text(text)) => {
+                    vec![MessageDelta::Text(text.clone())]
+                }
+                Some(bedrock::ContentBlockDelta::ToolUse(tool_use)) => {
+                    match self.tool_calls.get(&event.content_block_index) {
+                        Some(&index) => vec![MessageDelta::ToolCall {
+                            index,
+                            id: None,
+                            name: None,
+                            arguments: tool_use.input.clone(),
+                        }],
+                        None => vec![],
+                    }
+                }
+                _ => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
+    /// The usage delta that ends the message, once the stream is done
+    pub fn finish(self, model: String) -> MessageDelta {
+        MessageDelta::Usage(ProviderUsage::new(model, self.usage))
     }
 }
 
@@ -271,3 +496,110 @@ pub fn from_bedrock_json(document: &Document) -> Result<Value> {
         ),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::formats::streaming::MessageAssembler;
+
+    #[test]
+    fn test_thinking_is_not_sent() -> Result<()> {
+        let message = Message::assistant()
+            .with_thinking("The user wants a listing.")
+            .with_text("Let me look.")
+            .with_tool_confirmation_request(
+                "call_1",
+                "developer__shell".to_string(),
+                json!({}),
+                None,
+            );
+
+        let message = to_bedrock_message(&message)?;
+        assert_eq!(message.content().len(), 1);
+        assert_eq!(
+            message.content()[0].as_text().map(String::as_str),
+            Ok("Let me look.")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_events() -> Result<()> {
+        let text = |index, text: &str| -> Result<bedrock::ConverseStreamOutput> {
+            Ok(bedrock::ConverseStreamOutput::ContentBlockDelta(
+                bedrock::ContentBlockDeltaEvent::builder()
+                    .content_block_index(index)
+                    .delta(bedrock::ContentBlockDelta::Text(text.to_string()))
+                    .build()?,
+            ))
+        };
+        let input = |index, input: &str| -> Result<bedrock::ConverseStreamOutput> {
+            Ok(bedrock::ConverseStreamOutput::ContentBlockDelta(
+                bedrock::ContentBlockDeltaEvent::builder()
+                    .content_block_index(index)
+                    .delta(bedrock::ContentBlockDelta::ToolUse(
+                        bedrock::ToolUseBlockDelta::builder().input(input).build()?,
+                    ))
+                    .build()?,
+            ))
+        };
+        let events = vec![
+            text(0, "Let me ")?,
+            text(0, "check.")?,
+            bedrock::ConverseStreamOutput::ContentBlockStart(
+                bedrock::ContentBlockStartEvent::builder()
+                    .content_block_index(1)
+                    .start(bedrock::ContentBlockStart::ToolUse(
+                        bedrock::ToolUseBlockStart::builder()
+                            .tool_use_id("tooluse_1")
+                            .name("developer__shell")
+                            .build()?,
+                    ))
+                    .build()?,
+            ),
+            input(1, "{\"command\":")?,
+            input(1, " \"ls\"}")?,
+            bedrock::ConverseStreamOutput::MessageStop(
+                bedrock::MessageStopEvent::builder()
+                    .stop_reason(bedrock::StopReason::ToolUse)
+                    .build()?,
+            ),
+            bedrock::ConverseStreamOutput::Metadata(
+                bedrock::ConverseStreamMetadataEvent::builder()
+                    .usage(
+                        bedrock::TokenUsage::builder()
+                            .input_tokens(10)
+                            .cache_read_input_tokens(90)
+                            .output_tokens(20)
+                            .total_tokens(120)
+                            .build()?,
+                    )
+                    .build(),
+            ),
+        ];
+
+        let mut state = BedrockStreamState::default();
+        let mut assembler = MessageAssembler::default();
+        for event in &events {
+            for delta in state.event_to_deltas(event) {
+                assembler.push(delta);
+            }
+        }
+        assembler.push(state.finish("claude-sonnet".to_string()));
+        let (message, usage) = assembler.finish("unused");
+
+        assert_eq!(message.content.len(), 2);
+        assert_eq!(message.content[0].as_text(), Some("Let me check."));
+        let request = message.content[1].as_tool_request().unwrap();
+        assert_eq!(request.id, "tooluse_1");
+        let call = request.tool_call.as_ref().unwrap();
+        assert_eq!(call.name, "developer__shell");
+        assert_eq!(call.arguments, json!({ "command": "ls" }));
+
+        assert_eq!(usage.model, "claude-sonnet");
+        assert_eq!(usage.usage.input_tokens, Some(100));
+        assert_eq!(usage.usage.output_tokens, Some(20));
+        assert_eq!(usage.usage.total_tokens, Some(120));
+        Ok(())
+    }
+}
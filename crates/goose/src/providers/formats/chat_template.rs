@@ -0,0 +1,208 @@
+use crate::message::Message;
+use crate::providers::formats::{lmstudio, openai};
+use crate::providers::utils::ImageFormat;
+use anyhow::Result;
+use mcp_core::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// The tool instructions Qwen2.5 and Hermes models were trained with, which other instruct
+// models follow well enough
+const TOOLS_PREAMBLE: &str = "# Tools\n\nYou may call one or more functions to assist with the user query.\n\nYou are provided with function signatures within <tools></tools> XML tags:\n<tools>";
+const TOOLS_POSTAMBLE: &str = "</tools>\n\nFor each function call, return a json object with function name and arguments within <tool_call></tool_call> XML tags:\n<tool_call>\n{\"name\": <function-name>, \"arguments\": <args-json-object>}\n</tool_call>";
+
+/// The prompt format a model running in process was trained on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatTemplate {
+    /// `<|im_start|>role ... <|im_end|>`, used by Qwen, Hermes and SmolLM
+    #[default]
+    Chatml,
+    /// `<|start_header_id|>role<|end_header_id|> ... <|eot_id|>`, used by Llama 3
+    Llama3,
+}
+
+impl ChatTemplate {
+    /// The text that ends a turn, where generation stops
+    pub fn end_of_turn(&self) -> &'static str {
+        match self {
+            ChatTemplate::Chatml => "<|im_end|>",
+            ChatTemplate::Llama3 => "<|eot_id|>",
+        }
+    }
+
+    fn begin(&self) -> &'static str {
+        match self {
+            ChatTemplate::Chatml => "",
+            ChatTemplate::Llama3 => "<|begin_of_text|>",
+        }
+    }
+
+    fn turn(&self, role: &str, content: &str) -> String {
+        match self {
+            ChatTemplate::Chatml => format!("<|im_start|>{}\n{}<|im_end|>\n", role, content),
+            ChatTemplate::Llama3 => format!(
+                "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                role, content
+            ),
+        }
+    }
+
+    fn generation_prompt(&self) -> &'static str {
+        match self {
+            ChatTemplate::Chatml => "<|im_start|>assistant\n",
+            ChatTemplate::Llama3 => "<|start_header_id|>assistant<|end_header_id|>\n\n",
+        }
+    }
+
+    /// The role tool results are sent back with
+    fn tool_role(&self) -> &'static str {
+        match self {
+            ChatTemplate::Chatml => "user",
+            ChatTemplate::Llama3 => "ipython",
+        }
+    }
+}
+
+/// Flatten a conversation into `(role, text)` turns for a model without a chat API
+///
+/// Tools are described in the system prompt and the model is asked to call them between
+/// `<tool_call>` tags, which `response_to_message` turns back into tool requests. Tool
+/// results come back as `tool` turns wrapped in `<tool_response>` tags. Images can't be
+/// passed to a text model and are left out.
+pub fn conversation_turns(
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Vec<(String, String)>> {
+    let mut system = system.to_string();
+    if !tools.is_empty() {
+        system.push_str("\n\n");
+        system.push_str(TOOLS_PREAMBLE);
+        for tool in openai::format_tools(tools)? {
+            system.push('\n');
+            system.push_str(&tool.to_string());
+        }
+        system.push('\n');
+        system.push_str(TOOLS_POSTAMBLE);
+    }
+
+    let mut turns = vec![("system".to_string(), system)];
+    for message in openai::format_messages(messages, &ImageFormat::OpenAi) {
+        let content = text_content(&message["content"]);
+        match message["role"].as_str() {
+            Some("tool") => {
+                let response = format!("<tool_response>\n{}\n</tool_response>", content);
+                turns.push(("tool".to_string(), response));
+            }
+            Some("assistant") => {
+                let mut turn = content;
+                for call in message["tool_calls"].as_array().into_iter().flatten() {
+                    let arguments: Value =
+                        serde_json::from_str(call["function"]["arguments"].as_str().unwrap_or(""))
+                            .unwrap_or_else(|_| json!({}));
+                    let call = json!({ "name": call["function"]["name"], "arguments": arguments });
+                    if !turn.is_empty() {
+                        turn.push('\n');
+                    }
+                    turn.push_str(&format!("<tool_call>\n{}\n</tool_call>", call));
+                }
+                turns.push(("assistant".to_string(), turn));
+            }
+            _ if content.is_empty() => {}
+            _ => turns.push(("user".to_string(), content)),
+        }
+    }
+    Ok(turns)
+}
+
+/// Render a conversation into a raw prompt with the given template, see `conversation_turns`
+pub fn render_prompt(
+    template: ChatTemplate,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<String> {
+    let mut prompt = template.begin().to_string();
+    for (role, content) in conversation_turns(system, messages, tools)? {
+        let role = if role == "tool" {
+            template.tool_role()
+        } else {
+            role.as_str()
+        };
+        prompt.push_str(&template.turn(role, &content));
+    }
+    prompt.push_str(template.generation_prompt());
+    Ok(prompt)
+}
+
+/// The text of an OpenAI message content, which is either a string or a list of parts
+fn text_content(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Convert the text a model generated for a `render_prompt` prompt to internal Message format
+pub fn response_to_message(text: &str) -> Result<Message> {
+    let response = json!({
+        "choices": [{ "message": { "role": "assistant", "content": text.trim() } }]
+    });
+    lmstudio::response_to_message(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::{Content, ToolCall};
+
+    #[test]
+    fn test_render_prompt_round_trip() -> Result<()> {
+        let tool = Tool::new(
+            "developer__shell",
+            "Run a command",
+            json!({"type": "object", "properties": {"command": {"type": "string"}}}),
+        );
+        let messages = vec![
+            Message::user().with_text("List the files"),
+            Message::assistant().with_tool_request(
+                "call_0",
+                Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+            ),
+            Message::user().with_tool_response("call_0", Ok(vec![Content::text("a.txt")])),
+        ];
+
+        let prompt = render_prompt(ChatTemplate::Chatml, "Be brief.", &messages, &[tool])?;
+        assert!(prompt.starts_with("<|im_start|>system\nBe brief.\n\n# Tools"));
+        assert!(prompt.contains("\"name\":\"developer__shell\""));
+        assert!(prompt.contains("<|im_start|>user\nList the files<|im_end|>"));
+        assert!(prompt.contains("<|im_start|>assistant\n<tool_call>\n{"));
+        assert!(prompt.contains("\"arguments\":{\"command\":\"ls\"}"));
+        assert!(
+            prompt.contains("<|im_start|>user\n<tool_response>\na.txt\n</tool_response><|im_end|>")
+        );
+        assert!(prompt.ends_with("<|im_start|>assistant\n"));
+
+        let prompt = render_prompt(ChatTemplate::Llama3, "Be brief.", &messages, &[])?;
+        assert!(prompt.starts_with("<|begin_of_text|><|start_header_id|>system"));
+        assert!(!prompt.contains("# Tools"));
+        assert!(prompt.contains("<|start_header_id|>ipython<|end_header_id|>"));
+        assert!(prompt.ends_with("<|start_header_id|>assistant<|end_header_id|>\n\n"));
+
+        let message = response_to_message(
+            "Sure.\n<tool_call>\n{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"pwd\"}}\n</tool_call>",
+        )?;
+        assert_eq!(message.as_concat_text(), "Sure.");
+        let request = message.content[1].as_tool_request().unwrap();
+        let call = request.tool_call.as_ref().unwrap();
+        assert_eq!(call.name, "developer__shell");
+        assert_eq!(call.arguments, json!({"command": "pwd"}));
+        Ok(())
+    }
+}
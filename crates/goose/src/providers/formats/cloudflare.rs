@@ -0,0 +1,154 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai;
+use crate::providers::utils::{is_valid_function_name, ImageFormat};
+use anyhow::{anyhow, Result};
+use mcp_core::{Tool, ToolCall, ToolError};
+use serde_json::{json, Value};
+
+/// Create a request payload for Workers AI's `ai/run` endpoint
+///
+/// The messages and tools follow the OpenAI schema, but the model is part of the URL.
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut payload =
+        openai::create_request(model_config, system, messages, tools, &ImageFormat::OpenAi)?;
+    if let Some(payload) = payload.as_object_mut() {
+        payload.remove("model");
+    }
+    Ok(payload)
+}
+
+/// The `result` of a Workers AI response, or the errors it reports instead
+fn get_result(response: &Value) -> Result<&Value> {
+    if response["success"] == json!(false) {
+        return Err(anyhow!("Workers AI request failed: {}", response["errors"]));
+    }
+    response
+        .get("result")
+        .ok_or_else(|| anyhow!("Invalid response format: missing result"))
+}
+
+/// Convert a Workers AI response to internal Message format
+///
+/// The generated text is in `response`, and tool calls come without ids and with their
+/// arguments as an object rather than an encoded string.
+pub fn response_to_message(response: Value) -> Result<Message> {
+    let result = get_result(&response)?;
+    let mut message = Message::assistant();
+
+    if let Some(text) = result.get("response").and_then(|r| r.as_str()) {
+        if !text.is_empty() {
+            message = message.with_text(text);
+        }
+    }
+
+    if let Some(tool_calls) = result.get("tool_calls").and_then(|t| t.as_array()) {
+        for (i, tool_call) in tool_calls.iter().enumerate() {
+            // Some models answer with OpenAI's nesting under `function`
+            let function = tool_call.get("function").unwrap_or(tool_call);
+            let id = tool_call["id"]
+                .as_str()
+                .map(String::from)
+                .unwrap_or_else(|| format!("call_{}", i));
+            let name = function["name"].as_str().unwrap_or_default();
+            let arguments = match &function["arguments"] {
+                Value::String(arguments) => serde_json::from_str(arguments),
+                Value::Null => Ok(json!({})),
+                arguments => Ok(arguments.clone()),
+            };
+
+            let tool_call = if !is_valid_function_name(name) {
+                Err(ToolError::NotFound(format!(
+                    "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
+                    name
+                )))
+            } else {
+                arguments
+                    .map(|params| ToolCall::new(name, params))
+                    .map_err(|e| {
+                        ToolError::InvalidParameters(format!(
+                            "Could not interpret tool use parameters for id {}: {}",
+                            id, e
+                        ))
+                    })
+            };
+            message = message.with_tool_request(id, tool_call);
+        }
+    }
+
+    Ok(message)
+}
+
+/// Extract usage information from a Workers AI response
+pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
+    let usage = data["result"]
+        .get("usage")
+        .ok_or_else(|| ProviderError::UsageError("No usage data in response".to_string()))?;
+
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_i64()).map(|v| v as i32);
+    let input_tokens = count("prompt_tokens");
+    let output_tokens = count("completion_tokens");
+    let total_tokens = count("total_tokens").or(match (input_tokens, output_tokens) {
+        (Some(input), Some(output)) => Some(input.saturating_add(output)),
+        _ => None,
+    });
+
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_to_message() -> Result<()> {
+        let response = json!({
+            "result": {
+                "response": null,
+                "tool_calls": [{ "name": "weather", "arguments": { "location": "Lisbon" } }],
+                "usage": { "prompt_tokens": 210, "completion_tokens": 18, "total_tokens": 228 }
+            },
+            "success": true,
+            "errors": [],
+            "messages": []
+        });
+
+        let message = response_to_message(response.clone())?;
+        assert_eq!(message.content.len(), 1);
+        let request = message.content[0].as_tool_request().unwrap();
+        assert_eq!(request.id, "call_0");
+        let tool_call = request.tool_call.as_ref().unwrap();
+        assert_eq!(tool_call.name, "weather");
+        assert_eq!(tool_call.arguments, json!({ "location": "Lisbon" }));
+
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(210));
+        assert_eq!(usage.output_tokens, Some(18));
+        assert_eq!(usage.total_tokens, Some(228));
+
+        let failed = json!({
+            "result": null,
+            "success": false,
+            "errors": [{ "code": 5007, "message": "No such model" }]
+        });
+        assert!(response_to_message(failed).is_err());
+
+        let payload = create_request(
+            &ModelConfig::new("@cf/meta/llama-3.1-8b-instruct".to_string()),
+            "system",
+            &[Message::user().with_text("Hi")],
+            &[],
+        )?;
+        assert!(payload.get("model").is_none());
+        assert_eq!(payload["messages"][0]["role"], "system");
+
+        Ok(())
+    }
+}
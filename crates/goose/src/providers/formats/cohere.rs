@@ -0,0 +1,318 @@
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::utils::{is_valid_function_name, sanitize_function_name};
+use anyhow::{anyhow, Result};
+use mcp_core::{Content, Role, Tool, ToolCall, ToolError};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// Convert internal Message format to Cohere's v2 chat message specification
+///
+/// Tool calls go on the assistant message, and each tool result becomes its own `tool`
+/// message following it, as in the OpenAI format.
+pub fn format_messages(messages: &[Message]) -> Vec<Value> {
+    let mut messages_spec = Vec::new();
+    for message in messages {
+        let role = match message.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+
+        let mut text = Vec::new();
+        let mut tool_calls = Vec::new();
+        let mut tool_results = Vec::new();
+        for content in &message.content {
+            match content {
+                MessageContent::Text(t) if !t.text.is_empty() => text.push(t.text.clone()),
+                MessageContent::ToolRequest(request) => {
+                    if let Ok(tool_call) = &request.tool_call {
+                        tool_calls.push(json!({
+                            "id": request.id,
+                            "type": "function",
+                            "function": {
+                                "name": sanitize_function_name(&tool_call.name),
+                                "arguments": tool_call.arguments.to_string(),
+                            }
+                        }));
+                    }
+                }
+                MessageContent::ToolResponse(response) => {
+                    let content = match &response.tool_result {
+                        Ok(contents) => contents
+                            .iter()
+                            // Send only contents with no audience or with Assistant in the audience
+                            .filter(|content| {
+                                content
+                                    .audience()
+                                    .is_none_or(|audience| audience.contains(&Role::Assistant))
+                            })
+                            .filter_map(|content| match content {
+                                Content::Text(t) => Some(t.text.clone()),
+                                Content::Resource(resource) => Some(resource.get_text()),
+                                Content::Image(_) => None,
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                        Err(e) => format!("The tool call returned the following error:\n{}", e),
+                    };
+                    tool_results.push(json!({
+                        "role": "tool",
+                        "tool_call_id": response.id,
+                        "content": content,
+                    }));
+                }
+                // Cohere's chat models take no images, and confirmations stay local
+                _ => continue,
+            }
+        }
+
+        if !tool_calls.is_empty() {
+            // Text alongside tool calls is the model's plan for them
+            let mut converted = json!({ "role": role, "tool_calls": tool_calls });
+            if !text.is_empty() {
+                converted["tool_plan"] = json!(text.join("\n"));
+            }
+            messages_spec.push(converted);
+        } else if !text.is_empty() {
+            messages_spec.push(json!({ "role": role, "content": text.join("\n") }));
+        }
+        messages_spec.extend(tool_results);
+    }
+
+    messages_spec
+}
+
+/// Convert internal Tool format to Cohere's tool specification
+pub fn format_tools(tools: &[Tool]) -> Result<Vec<Value>> {
+    let mut tool_names = HashSet::new();
+    let mut result = Vec::new();
+
+    for tool in tools {
+        if !tool_names.insert(&tool.name) {
+            return Err(anyhow!("Duplicate tool name: {}", tool.name));
+        }
+
+        result.push(json!({
+            "type": "function",
+            "function": {
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.input_schema,
+            }
+        }));
+    }
+
+    Ok(result)
+}
+
+/// Convert Cohere's chat response to internal Message format
+pub fn response_to_message(response: Value) -> Result<Message> {
+    let original = response
+        .get("message")
+        .ok_or_else(|| anyhow!("Invalid response format: missing message"))?;
+    let mut message = Message::assistant();
+
+    // With tool calls, the model explains what it is about to do in the tool plan
+    if let Some(plan) = original.get("tool_plan").and_then(|p| p.as_str()) {
+        message = message.with_text(plan);
+    }
+
+    if let Some(content) = original.get("content").and_then(|c| c.as_array()) {
+        for block in content {
+            if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                message = message.with_text(text);
+            }
+        }
+    }
+
+    if let Some(tool_calls) = original.get("tool_calls").and_then(|t| t.as_array()) {
+        for tool_call in tool_calls {
+            let id = tool_call["id"].as_str().unwrap_or_default().to_string();
+            let name = tool_call["function"]["name"].as_str().unwrap_or_default();
+            let arguments = match tool_call["function"]["arguments"].as_str() {
+                Some(arguments) if !arguments.is_empty() => arguments,
+                _ => "{}",
+            };
+
+            let tool_call = if !is_valid_function_name(name) {
+                Err(ToolError::NotFound(format!(
+                    "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
+                    name
+                )))
+            } else {
+                serde_json::from_str::<Value>(arguments)
+                    .map(|params| ToolCall::new(name, params))
+                    .map_err(|e| {
+                        ToolError::InvalidParameters(format!(
+                            "Could not interpret tool use parameters for id {}: {}",
+                            id, e
+                        ))
+                    })
+            };
+            message = message.with_tool_request(id, tool_call);
+        }
+    }
+
+    Ok(message)
+}
+
+/// Extract usage information from Cohere's chat response
+///
+/// Cohere reports both the tokens the model saw and the units it billed; the billed units
+/// leave out the tokens of its own prompt template, so they are used when present.
+pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
+    let usage = data
+        .get("usage")
+        .ok_or_else(|| ProviderError::UsageError("No usage data in response".to_string()))?;
+    let counts = usage
+        .get("billed_units")
+        .or_else(|| usage.get("tokens"))
+        .ok_or_else(|| ProviderError::UsageError("No token counts in usage".to_string()))?;
+
+    let count = |key: &str| counts.get(key).and_then(|v| v.as_f64()).map(|v| v as i32);
+    let input_tokens = count("input_tokens");
+    let output_tokens = count("output_tokens");
+    let total_tokens = match (input_tokens, output_tokens) {
+        (Some(input), Some(output)) => Some(input.saturating_add(output)),
+        _ => None,
+    };
+
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+}
+
+/// Create a complete request payload for Cohere's v2 chat API
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut messages_spec = Vec::new();
+    if !system.is_empty() {
+        messages_spec.push(json!({ "role": "system", "content": system }));
+    }
+    messages_spec.extend(format_messages(messages));
+
+    let mut payload = json!({
+        "model": model_config.model_name,
+        "messages": messages_spec,
+    });
+
+    let tools_spec = format_tools(tools)?;
+    if !tools_spec.is_empty() {
+        payload["tools"] = json!(tools_spec);
+    }
+    if let Some(temperature) = model_config.temperature {
+        payload["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = model_config.max_tokens {
+        payload["max_tokens"] = json!(max_tokens);
+    }
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_round_trip() -> Result<()> {
+        let response = json!({
+            "id": "c14c80c3",
+            "finish_reason": "TOOL_CALL",
+            "message": {
+                "role": "assistant",
+                "tool_plan": "I will look up the weather.",
+                "tool_calls": [{
+                    "id": "weather_1",
+                    "type": "function",
+                    "function": {
+                        "name": "weather",
+                        "arguments": "{\"location\":\"Toronto\"}"
+                    }
+                }]
+            },
+            "usage": {
+                "billed_units": { "input_tokens": 20, "output_tokens": 10 },
+                "tokens": { "input_tokens": 520, "output_tokens": 40 }
+            }
+        });
+
+        let message = response_to_message(response.clone())?;
+        assert_eq!(message.content.len(), 2);
+        assert_eq!(
+            message.content[0].as_text(),
+            Some("I will look up the weather.")
+        );
+        let request = message.content[1].as_tool_request().unwrap();
+        let tool_call = request.tool_call.as_ref().unwrap();
+        assert_eq!(tool_call.name, "weather");
+        assert_eq!(tool_call.arguments, json!({ "location": "Toronto" }));
+
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(20));
+        assert_eq!(usage.output_tokens, Some(10));
+        assert_eq!(usage.total_tokens, Some(30));
+
+        // The call and its result go back as an assistant message followed by a tool message
+        let messages = vec![
+            Message::user().with_text("What's the weather in Toronto?"),
+            message,
+            Message::user().with_tool_response("weather_1", Ok(vec![Content::text("Sunny")])),
+        ];
+        let spec = format_messages(&messages);
+        assert_eq!(spec.len(), 3);
+        assert_eq!(spec[0]["content"], "What's the weather in Toronto?");
+        assert_eq!(spec[1]["role"], "assistant");
+        assert_eq!(spec[1]["tool_plan"], "I will look up the weather.");
+        assert_eq!(spec[1]["tool_calls"][0]["id"], "weather_1");
+        assert_eq!(
+            spec[1]["tool_calls"][0]["function"]["arguments"],
+            "{\"location\":\"Toronto\"}"
+        );
+        assert_eq!(spec[2]["role"], "tool");
+        assert_eq!(spec[2]["tool_call_id"], "weather_1");
+        assert_eq!(spec[2]["content"], "Sunny");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request() -> Result<()> {
+        let model_config = ModelConfig::new("command-r-plus".to_string());
+        let tools = vec![Tool::new(
+            "weather",
+            "Get the weather",
+            json!({ "type": "object", "properties": {} }),
+        )];
+        let response = json!({
+            "message": {
+                "role": "assistant",
+                "content": [{ "type": "text", "text": "Hello!" }]
+            },
+            "usage": { "tokens": { "input_tokens": 5, "output_tokens": 2 } }
+        });
+
+        let payload = create_request(
+            &model_config,
+            "You are a helpful assistant.",
+            &[Message::user().with_text("Hi")],
+            &tools,
+        )?;
+        assert_eq!(payload["model"], "command-r-plus");
+        assert_eq!(payload["messages"][0]["role"], "system");
+        assert_eq!(payload["messages"][1]["content"], "Hi");
+        assert_eq!(payload["tools"][0]["function"]["name"], "weather");
+        assert!(payload.get("max_tokens").is_none());
+
+        let message = response_to_message(response.clone())?;
+        assert_eq!(message.content[0].as_text(), Some("Hello!"));
+        // Without billed units, the raw token counts are used
+        assert_eq!(get_usage(&response)?.total_tokens, Some(7));
+
+        Ok(())
+    }
+}
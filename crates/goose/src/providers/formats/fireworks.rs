@@ -0,0 +1,166 @@
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai;
+use crate::providers::utils::ImageFormat;
+use anyhow::Result;
+use mcp_core::{Tool, ToolError};
+use serde_json::{json, Value};
+
+/// Prefix of the models Fireworks serves itself, as opposed to those in a user's account
+pub const MODEL_PREFIX: &str = "accounts/fireworks/models/";
+
+// Fireworks rejects larger max_tokens on requests that are not streamed
+const MAX_TOKENS_WITHOUT_STREAMING: i32 = 4096;
+
+/// The full model path for a model name, so `llama-v3p1-70b-instruct` can be used as a
+/// shorthand for `accounts/fireworks/models/llama-v3p1-70b-instruct`
+pub fn model_path(name: &str) -> String {
+    if name.starts_with("accounts/") {
+        name.to_string()
+    } else {
+        format!("{MODEL_PREFIX}{name}")
+    }
+}
+
+/// The model name without the path to the Fireworks account
+pub fn model_name(path: &str) -> &str {
+    path.strip_prefix(MODEL_PREFIX).unwrap_or(path)
+}
+
+/// Create a request payload for Fireworks' OpenAI compatible chat completions API
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut payload =
+        openai::create_request(model_config, system, messages, tools, &ImageFormat::OpenAi)?;
+    payload["model"] = json!(model_path(&model_config.model_name));
+    if let Some(max_tokens) = model_config.max_tokens {
+        payload["max_tokens"] = json!(max_tokens.min(MAX_TOKENS_WITHOUT_STREAMING));
+    }
+    Ok(payload)
+}
+
+/// Convert a Fireworks response to internal Message format
+///
+/// Fireworks ends a response with a `length` finish reason both when the output ran out of
+/// tokens and when the prompt left no room for any; the latter comes back empty and is
+/// reported as the context being exceeded, so the conversation gets truncated.
+pub fn response_to_message(response: Value) -> Result<Message, ProviderError> {
+    let finish_reason = response["choices"][0]["finish_reason"].as_str();
+    let mut message = openai::response_to_message(response.clone())?;
+    if finish_reason != Some("length") {
+        return Ok(message);
+    }
+
+    if message.content.iter().all(|content| match content {
+        MessageContent::Text(text) => text.text.is_empty(),
+        _ => false,
+    }) {
+        return Err(ProviderError::ContextLengthExceeded(
+            "The prompt left no room for the response".to_string(),
+        ));
+    }
+
+    // Arguments cut off by the token limit don't parse; say why so the model can try again
+    for content in message.content.iter_mut() {
+        if let MessageContent::ToolRequest(request) = content {
+            if let Err(ToolError::InvalidParameters(_)) = request.tool_call {
+                request.tool_call = Err(ToolError::InvalidParameters(
+                    "The tool call arguments were cut off by the output token limit. \
+                    Try again with smaller arguments, e.g. by splitting the work into several calls."
+                        .to_string(),
+                ));
+            }
+        }
+    }
+    Ok(message)
+}
+
+/// Extract usage information from a Fireworks response
+///
+/// Fireworks leaves out `completion_tokens` when nothing was generated.
+pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
+    let mut usage = openai::get_usage(data)?;
+    if usage.input_tokens.is_some() && usage.output_tokens.is_none() {
+        usage.output_tokens = Some(0);
+        usage.total_tokens = usage.total_tokens.or(usage.input_tokens);
+    }
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_path() {
+        assert_eq!(
+            model_path("llama-v3p1-70b-instruct"),
+            "accounts/fireworks/models/llama-v3p1-70b-instruct"
+        );
+        assert_eq!(
+            model_path("accounts/me/models/fine-tuned"),
+            "accounts/me/models/fine-tuned"
+        );
+        assert_eq!(
+            model_name("accounts/fireworks/models/firefunction-v2"),
+            "firefunction-v2"
+        );
+
+        let model_config =
+            ModelConfig::new("firefunction-v2".to_string()).with_max_tokens(Some(8192));
+        let payload = create_request(
+            &model_config,
+            "system",
+            &[Message::user().with_text("Hi")],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            payload["model"],
+            "accounts/fireworks/models/firefunction-v2"
+        );
+        assert_eq!(payload["max_tokens"], 4096);
+    }
+
+    #[test]
+    fn test_length_finish_reason() {
+        let response = |message: Value| {
+            json!({
+                "model": "accounts/fireworks/models/firefunction-v2",
+                "choices": [{ "message": message, "finish_reason": "length" }],
+                "usage": { "prompt_tokens": 8000, "total_tokens": 8000 }
+            })
+        };
+
+        let empty = response(json!({ "role": "assistant", "content": "" }));
+        assert!(matches!(
+            response_to_message(empty.clone()),
+            Err(ProviderError::ContextLengthExceeded(_))
+        ));
+        let usage = get_usage(&empty).unwrap();
+        assert_eq!(usage.output_tokens, Some(0));
+        assert_eq!(usage.total_tokens, Some(8000));
+
+        let truncated = response(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": { "name": "developer__text_editor", "arguments": "{\"text\": \"un" }
+            }]
+        }));
+        let message = response_to_message(truncated).unwrap();
+        let request = message.content[0].as_tool_request().unwrap();
+        assert!(matches!(
+            &request.tool_call,
+            Err(ToolError::InvalidParameters(message)) if message.contains("cut off")
+        ));
+    }
+}
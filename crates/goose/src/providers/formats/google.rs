@@ -1,6 +1,6 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{MessageDelta, Usage};
 use crate::providers::errors::ProviderError;
 use crate::providers::utils::{is_valid_function_name, sanitize_function_name};
 use anyhow::Result;
@@ -9,6 +9,8 @@ use mcp_core::role::Role;
 use mcp_core::tool::{Tool, ToolCall};
 use rand::{distributions::Alphanumeric, Rng};
 use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::time::Duration;
 
 /// Convert internal Message format to Google's API message specification
 pub fn format_messages(messages: &[Message]) -> Vec<Value> {
@@ -188,11 +190,7 @@ pub fn response_to_message(response: Value) -> Result<Message> {
     let role = Role::Assistant;
     let created = chrono::Utc::now().timestamp();
     if candidate.is_none() {
-        return Ok(Message {
-            role,
-            created,
-            content,
-        });
+        return Ok(Message::new(role, created, content));
     }
     let candidate = candidate.unwrap();
     let parts = candidate
@@ -231,11 +229,38 @@ pub fn response_to_message(response: Value) -> Result<Message> {
             }
         }
     }
-    Ok(Message {
-        role,
-        created,
-        content,
-    })
+    Ok(Message::new(role, created, content))
+}
+
+/// Convert a chunk of a streamed response to the deltas of the message
+///
+/// Gemini streams text in pieces but function calls whole, so each call is one
+/// `MessageDelta::ToolCall`, numbered from `tool_calls`, the count of calls in earlier chunks.
+pub fn response_chunk_to_deltas(chunk: Value, tool_calls: &mut usize) -> Result<Vec<MessageDelta>> {
+    let message = response_to_message(chunk)?;
+    let mut deltas = Vec::new();
+    for content in message.content {
+        match content {
+            MessageContent::Text(text) => deltas.push(MessageDelta::Text(text.text)),
+            MessageContent::ToolRequest(request) => match request.tool_call {
+                Ok(call) => {
+                    deltas.push(MessageDelta::ToolCall {
+                        index: *tool_calls,
+                        id: Some(request.id),
+                        name: Some(call.name),
+                        arguments: call.arguments.to_string(),
+                    });
+                    *tool_calls += 1;
+                }
+                Err(error) => deltas.push(MessageDelta::InvalidToolCall {
+                    id: request.id,
+                    error,
+                }),
+            },
+            _ => {}
+        }
+    }
+    Ok(deltas)
 }
 
 /// Extract usage information from Google's API response
@@ -245,15 +270,27 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
             .get("promptTokenCount")
             .and_then(|v| v.as_u64())
             .map(|v| v as i32);
+        // Thinking is billed as output but not counted in candidatesTokenCount
+        let reasoning_tokens = usage_meta_data
+            .get("thoughtsTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as i32);
         let output_tokens = usage_meta_data
             .get("candidatesTokenCount")
             .and_then(|v| v.as_u64())
-            .map(|v| v as i32);
+            .map(|v| v as i32 + reasoning_tokens.unwrap_or(0));
         let total_tokens = usage_meta_data
             .get("totalTokenCount")
             .and_then(|v| v.as_u64())
             .map(|v| v as i32);
-        Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+        // Tokens served from cached content are included in promptTokenCount
+        let cache_read_tokens = usage_meta_data
+            .get("cachedContentTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as i32);
+        Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+            .with_cache_tokens(cache_read_tokens, None)
+            .with_reasoning_tokens(reasoning_tokens))
     } else {
         tracing::debug!(
             "Failed to get usage data: {}",
@@ -297,36 +334,152 @@ pub fn create_request(
     Ok(Value::Object(payload))
 }
 
+/// Create a `cachedContents` resource holding the system instruction and tools
+///
+/// These are the parts of a request that stay the same from turn to turn, so once cached
+/// they are uploaded once and only referenced by name, see `use_cached_content`.
+pub fn create_cached_content(
+    model_config: &ModelConfig,
+    system: &str,
+    tools: &[Tool],
+    ttl: Duration,
+) -> Value {
+    let mut content = json!({
+        "model": model_resource(&model_config.model_name),
+        "systemInstruction": {"parts": [{"text": system}]},
+        "ttl": format!("{}s", ttl.as_secs()),
+    });
+    if !tools.is_empty() {
+        content["tools"] = json!([{"functionDeclarations": format_tools(tools)}]);
+    }
+    content
+}
+
+/// Point a request from `create_request` at cached content, dropping the parts it holds
+pub fn use_cached_content(payload: &mut Value, name: &str) {
+    if let Some(payload) = payload.as_object_mut() {
+        payload.remove("system_instruction");
+        payload.remove("tools");
+        payload.insert("cachedContent".to_string(), json!(name));
+    }
+}
+
+/// The resource name of a model, which the API wants prefixed with `models/`
+fn model_resource(model: &str) -> String {
+    if model.starts_with("models/") {
+        model.to_string()
+    } else {
+        format!("models/{}", model)
+    }
+}
+
+/// Create the setup message that opens a Live API session
+///
+/// The Live API takes the model, system instruction and tools once for the session, and
+/// only the turns after that.
+pub fn create_live_setup(model_config: &ModelConfig, system: &str, tools: &[Tool]) -> Value {
+    let model = model_resource(&model_config.model_name);
+    let mut generation_config = json!({ "responseModalities": ["TEXT"] });
+    if let Some(temp) = model_config.temperature {
+        generation_config["temperature"] = json!(temp);
+    }
+    if let Some(tokens) = model_config.max_tokens {
+        generation_config["maxOutputTokens"] = json!(tokens);
+    }
+    let mut setup = json!({
+        "model": model,
+        "generationConfig": generation_config,
+        "systemInstruction": {"parts": [{"text": system}]},
+    });
+    if !tools.is_empty() {
+        setup["tools"] = json!([{"functionDeclarations": format_tools(tools)}]);
+    }
+    json!({ "setup": setup })
+}
+
+/// Create a Live API message answering tool calls with the tool responses in a message
+///
+/// Live API responses are matched to their calls by id, and named after the function, which
+/// `names` has for each id it handed out.
+pub fn create_live_tool_response(message: &Message, names: &HashMap<String, String>) -> Value {
+    let responses: Vec<Value> = message
+        .content
+        .iter()
+        .filter_map(|content| content.as_tool_response())
+        .map(|response| {
+            let result = match &response.tool_result {
+                Ok(contents) => {
+                    let text: Vec<String> = contents
+                        .iter()
+                        .filter(|content| {
+                            content
+                                .audience()
+                                .is_none_or(|audience| audience.contains(&Role::Assistant))
+                        })
+                        .filter_map(|content| content.as_text().map(String::from))
+                        .collect();
+                    json!({ "content": text.join("\n") })
+                }
+                Err(e) => json!({ "error": e.to_string() }),
+            };
+            json!({
+                "id": response.id,
+                "name": names.get(&response.id).cloned().unwrap_or_default(),
+                "response": result,
+            })
+        })
+        .collect();
+    json!({ "toolResponse": { "functionResponses": responses } })
+}
+
+/// Convert the `toolCall` of a Live API message to a message requesting the tool calls
+pub fn live_tool_call_to_message(tool_call: &Value) -> Message {
+    let calls = tool_call["functionCalls"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    calls.iter().fold(Message::assistant(), |message, call| {
+        let id = call["id"].as_str().unwrap_or_default();
+        let name = call["name"].as_str().unwrap_or_default();
+        let tool_call = if is_valid_function_name(name) {
+            let arguments = call.get("args").cloned().unwrap_or_else(|| json!({}));
+            Ok(ToolCall::new(name, arguments))
+        } else {
+            Err(mcp_core::ToolError::NotFound(format!(
+                "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
+                name
+            )))
+        };
+        message.with_tool_request(id, tool_call)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
     fn set_up_text_message(text: &str, role: Role) -> Message {
-        Message {
-            role,
-            created: 0,
-            content: vec![MessageContent::text(text.to_string())],
-        }
+        Message::new(role, 0, vec![MessageContent::text(text.to_string())])
     }
 
     fn set_up_tool_request_message(id: &str, tool_call: ToolCall) -> Message {
-        Message {
-            role: Role::User,
-            created: 0,
-            content: vec![MessageContent::tool_request(id.to_string(), Ok(tool_call))],
-        }
+        Message::new(
+            Role::User,
+            0,
+            vec![MessageContent::tool_request(id.to_string(), Ok(tool_call))],
+        )
     }
 
     fn set_up_tool_response_message(id: &str, tool_response: Vec<Content>) -> Message {
-        Message {
-            role: Role::Assistant,
-            created: 0,
-            content: vec![MessageContent::tool_response(
+        Message::new(
+            Role::Assistant,
+            0,
+            vec![MessageContent::tool_response(
                 id.to_string(),
                 Ok(tool_response),
             )],
-        }
+        )
     }
 
     fn set_up_tool(name: &str, description: &str, params: Value) -> Tool {
@@ -352,6 +505,30 @@ mod tests {
         assert_eq!(usage.input_tokens, Some(1));
         assert_eq!(usage.output_tokens, Some(2));
         assert_eq!(usage.total_tokens, Some(3));
+        assert_eq!(usage.cache_read_tokens, None);
+
+        let data = json!({
+            "usageMetadata": {
+                "promptTokenCount": 100,
+                "candidatesTokenCount": 2,
+                "totalTokenCount": 102,
+                "cachedContentTokenCount": 80
+            }
+        });
+        let usage = get_usage(&data).unwrap();
+        assert_eq!(usage.cache_read_tokens, Some(80));
+
+        let data = json!({
+            "usageMetadata": {
+                "promptTokenCount": 10,
+                "candidatesTokenCount": 20,
+                "thoughtsTokenCount": 300,
+                "totalTokenCount": 330
+            }
+        });
+        let usage = get_usage(&data).unwrap();
+        assert_eq!(usage.output_tokens, Some(320));
+        assert_eq!(usage.reasoning_tokens, Some(300));
     }
 
     #[test]
@@ -536,4 +713,82 @@ mod tests {
             panic!("Expected valid tool request");
         }
     }
+
+    #[test]
+    fn test_live_tool_call_round_trip() {
+        let model_config = ModelConfig::new("gemini-2.0-flash-live-001".to_string());
+        let tools = vec![set_up_tool("weather", "Get the weather", json!({}))];
+        let setup = create_live_setup(&model_config, "Be brief.", &tools);
+        assert_eq!(setup["setup"]["model"], "models/gemini-2.0-flash-live-001");
+        assert_eq!(
+            setup["setup"]["tools"][0]["functionDeclarations"][0]["name"],
+            "weather"
+        );
+
+        let tool_call = json!({
+            "functionCalls": [{ "id": "fc_1", "name": "weather", "args": { "city": "Oslo" } }]
+        });
+        let message = live_tool_call_to_message(&tool_call);
+        let request = message.content[0].as_tool_request().unwrap();
+        assert_eq!(request.id, "fc_1");
+        assert_eq!(
+            request.tool_call.as_ref().unwrap().arguments["city"],
+            "Oslo"
+        );
+
+        let names = HashMap::from([("fc_1".to_string(), "weather".to_string())]);
+        let response = Message::user().with_tool_response("fc_1", Ok(vec![Content::text("Snow")]));
+        let response = create_live_tool_response(&response, &names);
+        assert_eq!(
+            response["toolResponse"]["functionResponses"][0],
+            json!({ "id": "fc_1", "name": "weather", "response": { "content": "Snow" } })
+        );
+    }
+
+    #[test]
+    fn test_cached_content() -> Result<()> {
+        let model_config = ModelConfig::new("gemini-1.5-pro".to_string());
+        let tools = vec![set_up_tool("weather", "Get the weather", json!({}))];
+        let content =
+            create_cached_content(&model_config, "Be brief.", &tools, Duration::from_secs(600));
+        assert_eq!(content["model"], "models/gemini-1.5-pro");
+        assert_eq!(content["ttl"], "600s");
+        assert_eq!(
+            content["systemInstruction"]["parts"][0]["text"],
+            "Be brief."
+        );
+        assert_eq!(
+            content["tools"][0]["functionDeclarations"][0]["name"],
+            "weather"
+        );
+
+        let messages = vec![Message::user().with_text("Hello")];
+        let mut payload = create_request(&model_config, "Be brief.", &messages, &tools)?;
+        use_cached_content(&mut payload, "cachedContents/abc");
+        assert_eq!(payload["cachedContent"], "cachedContents/abc");
+        assert!(payload.get("system_instruction").is_none());
+        assert!(payload.get("tools").is_none());
+        assert_eq!(payload["contents"][0]["parts"][0]["text"], "Hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_chunk_to_deltas_numbers_tool_calls() -> Result<()> {
+        let chunk =
+            |part: Value| json!({"candidates": [{"content": {"role": "model", "parts": [part]}}]});
+        let mut tool_calls = 0;
+
+        let deltas = response_chunk_to_deltas(chunk(json!({"text": "Checking"})), &mut tool_calls)?;
+        assert!(matches!(&deltas[0], MessageDelta::Text(text) if text == "Checking"));
+
+        let call = json!({"functionCall": {"name": "shell", "args": {"command": "ls"}}});
+        response_chunk_to_deltas(chunk(call.clone()), &mut tool_calls)?;
+        let deltas = response_chunk_to_deltas(chunk(call), &mut tool_calls)?;
+        assert!(matches!(
+            &deltas[0],
+            MessageDelta::ToolCall { index: 1, arguments, .. } if arguments == r#"{"command":"ls"}"#
+        ));
+        assert_eq!(tool_calls, 2);
+        Ok(())
+    }
 }
@@ -0,0 +1,129 @@
+use crate::message::Message;
+use crate::providers::formats::openai;
+use anyhow::Result;
+use serde_json::{json, Value};
+
+// Tools that text generation inference adds itself for the model to answer without a tool
+const NO_TOOL: &str = "no_tool";
+const NOTIFY_ERROR: &str = "notify_error";
+
+/// Bring a chat completion from Hugging Face's text generation inference into the shape
+/// OpenAI returns
+///
+/// Tool call arguments come back as a JSON object instead of an encoded string, and when
+/// tools are offered, a plain answer comes back as a call to a `no_tool` (or, on older
+/// versions, `notify_error`) tool that holds the text.
+pub fn normalize_response(mut response: Value) -> Value {
+    let Some(message) = response
+        .get_mut("choices")
+        .and_then(|choices| choices.get_mut(0))
+        .and_then(|choice| choice.get_mut("message"))
+    else {
+        return response;
+    };
+    let Some(tool_calls) = message
+        .get_mut("tool_calls")
+        .and_then(|calls| calls.as_array_mut())
+    else {
+        return response;
+    };
+
+    let mut answers = Vec::new();
+    tool_calls.retain_mut(|call| {
+        let function = &mut call["function"];
+        let arguments = function["arguments"].clone();
+        match function["name"].as_str() {
+            Some(NO_TOOL) => {
+                answers.push(
+                    arguments["content"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                );
+                false
+            }
+            Some(NOTIFY_ERROR) => {
+                answers.push(arguments["error"].as_str().unwrap_or_default().to_string());
+                false
+            }
+            _ => {
+                if !arguments.is_string() {
+                    function["arguments"] = json!(arguments.to_string());
+                }
+                true
+            }
+        }
+    });
+
+    if tool_calls.is_empty() {
+        if let Some(message) = message.as_object_mut() {
+            message.remove("tool_calls");
+        }
+    }
+    if !answers.is_empty() {
+        let mut text: Vec<String> = message["content"]
+            .as_str()
+            .filter(|content| !content.is_empty())
+            .map(String::from)
+            .into_iter()
+            .collect();
+        text.extend(answers);
+        message["content"] = json!(text.join("\n"));
+    }
+    response
+}
+
+/// Convert a Hugging Face chat completion to internal Message format
+pub fn response_to_message(response: Value) -> Result<Message> {
+    openai::response_to_message(normalize_response(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_to_message() -> Result<()> {
+        let response = json!({
+            "model": "meta-llama/Llama-3.3-70B-Instruct",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "0",
+                        "type": "function",
+                        "function": {
+                            "name": "developer__shell",
+                            "description": null,
+                            "arguments": { "command": "ls" }
+                        }
+                    }]
+                }
+            }]
+        });
+        let message = response_to_message(response)?;
+        let request = message.content[0].as_tool_request().unwrap();
+        let tool_call = request.tool_call.as_ref().unwrap();
+        assert_eq!(tool_call.name, "developer__shell");
+        assert_eq!(tool_call.arguments, json!({ "command": "ls" }));
+
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "tool_calls": [{
+                        "id": "0",
+                        "type": "function",
+                        "function": { "name": "no_tool", "arguments": { "content": "Hello!" } }
+                    }]
+                }
+            }]
+        });
+        let message = response_to_message(response)?;
+        assert_eq!(message.content.len(), 1);
+        assert_eq!(message.content[0].as_text(), Some("Hello!"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::formats::chat_template::{self, ChatTemplate};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// Create a request payload for KoboldCpp's `/api/v1/generate` endpoint
+///
+/// The endpoint takes a raw prompt, rendered with `chat_template::render_prompt`, and stops
+/// at the end of turn of the template, which it trims from the text.
+pub fn create_generate_request(
+    model_config: &ModelConfig,
+    template: ChatTemplate,
+    prompt: &str,
+) -> Value {
+    let mut payload = json!({
+        "prompt": prompt,
+        "stop_sequence": [template.end_of_turn()],
+        "trim_stop": true,
+    });
+    if let Some(temperature) = model_config.temperature {
+        payload["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = model_config.max_tokens {
+        payload["max_length"] = json!(max_tokens);
+    }
+    payload
+}
+
+/// Collect the server sent event stream of `/api/extra/generate/stream` into the response
+/// `/api/v1/generate` would have returned
+///
+/// Each event carries one `token` of text, and the last one the `finish_reason`.
+pub fn collect_stream(body: &str) -> Result<Value> {
+    let mut text = String::new();
+    let mut finish_reason = Value::Null;
+
+    for line in body.lines() {
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            continue;
+        };
+        if data.is_empty() {
+            continue;
+        }
+        let event: Value = serde_json::from_str(data)
+            .map_err(|e| anyhow!("Invalid event in response stream: {}", e))?;
+        if let Some(token) = event["token"].as_str() {
+            text.push_str(token);
+        }
+        if !event["finish_reason"].is_null() {
+            finish_reason = event["finish_reason"].clone();
+        }
+    }
+
+    Ok(json!({ "results": [{ "text": text, "finish_reason": finish_reason }] }))
+}
+
+/// Convert a response from KoboldCpp's generate endpoint to internal Message format
+pub fn generate_to_message(response: &Value, template: ChatTemplate) -> Result<Message> {
+    let text = response["results"][0]["text"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Invalid response format: missing results"))?;
+    // Older versions ignore trim_stop
+    let text = text.strip_suffix(template.end_of_turn()).unwrap_or(text);
+    chat_template::response_to_message(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_stream() -> Result<()> {
+        let model_config = ModelConfig::new("koboldcpp".to_string()).with_max_tokens(Some(300));
+        let payload = create_generate_request(&model_config, ChatTemplate::Chatml, "<|im_start|>");
+        assert_eq!(payload["max_length"], 300);
+        assert_eq!(payload["stop_sequence"][0], "<|im_end|>");
+
+        let body = [
+            "event: message\ndata: {\"token\": \"Hello\", \"finish_reason\": null}",
+            "event: message\ndata: {\"token\": \" there!\", \"finish_reason\": null}",
+            "event: message\ndata: {\"token\": \"\", \"finish_reason\": \"stop\"}",
+        ]
+        .join("\n\n");
+        let response = collect_stream(&body)?;
+        assert_eq!(response["results"][0]["finish_reason"], "stop");
+        let message = generate_to_message(&response, ChatTemplate::Chatml)?;
+        assert_eq!(message.as_concat_text(), "Hello there!");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// Create a request payload for llama.cpp's native `/completion` endpoint
+///
+/// The endpoint takes a raw prompt, so the conversation has to be rendered with the model's
+/// chat template first, e.g. with the server's `/apply-template` endpoint.
+pub fn create_completion_request(model_config: &ModelConfig, prompt: &str) -> Value {
+    let mut payload = json!({ "prompt": prompt });
+    if let Some(temperature) = model_config.temperature {
+        payload["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = model_config.max_tokens {
+        payload["n_predict"] = json!(max_tokens);
+    }
+    payload
+}
+
+/// Convert a response from llama.cpp's `/completion` endpoint to internal Message format
+pub fn completion_to_message(response: &Value) -> Result<Message> {
+    let content = response
+        .get("content")
+        .and_then(|c| c.as_str())
+        .ok_or_else(|| anyhow!("Invalid response format: missing content"))?;
+    Ok(Message::assistant().with_text(content.trim_start()))
+}
+
+/// Extract usage information from a response of llama.cpp's `/completion` endpoint
+pub fn get_completion_usage(response: &Value) -> Result<Usage, ProviderError> {
+    let count = |key: &str| response.get(key).and_then(|v| v.as_i64()).map(|v| v as i32);
+    let input_tokens = count("tokens_evaluated");
+    let output_tokens = count("tokens_predicted");
+    if input_tokens.is_none() && output_tokens.is_none() {
+        return Err(ProviderError::UsageError(
+            "No token counts in response".to_string(),
+        ));
+    }
+    let total_tokens = match (input_tokens, output_tokens) {
+        (Some(input), Some(output)) => Some(input.saturating_add(output)),
+        _ => None,
+    };
+
+    // With cache_prompt, the start of the prompt shared with the previous request is reused
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+        .with_cache_tokens(count("tokens_cached"), None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion_round_trip() -> Result<()> {
+        let model_config = ModelConfig::new("llama-3.2-3b-instruct".to_string())
+            .with_temperature(Some(0.2))
+            .with_max_tokens(Some(128));
+        let payload = create_completion_request(&model_config, "<|user|>Hi<|assistant|>");
+        assert_eq!(payload["prompt"], "<|user|>Hi<|assistant|>");
+        assert_eq!(payload["n_predict"], 128);
+
+        let response = json!({
+            "content": " Hello! How can I help?",
+            "model": "llama-3.2-3b-instruct",
+            "stop": true,
+            "tokens_predicted": 8,
+            "tokens_evaluated": 120,
+            "tokens_cached": 100
+        });
+        let message = completion_to_message(&response)?;
+        assert_eq!(message.content[0].as_text(), Some("Hello! How can I help?"));
+
+        let usage = get_completion_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(120));
+        assert_eq!(usage.output_tokens, Some(8));
+        assert_eq!(usage.total_tokens, Some(128));
+        assert_eq!(usage.cache_read_tokens, Some(100));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,257 @@
+use crate::message::Message;
+use crate::providers::formats::openai;
+use anyhow::Result;
+use serde_json::{json, Value};
+
+// Markers around the tool calls LM Studio leaves in the text when it can't parse them
+const TOOL_CALL_MARKERS: &[(&str, &str)] = &[
+    ("[TOOL_REQUEST]", "[END_TOOL_REQUEST]"),
+    ("<tool_call>", "</tool_call>"),
+];
+
+/// Repair the tool calls in an LM Studio chat completion so they read like OpenAI's
+///
+/// Models without native tool support in LM Studio are prompted to call tools in text, and
+/// what comes back is looser than the OpenAI format: calls can be left in the message text
+/// between markers, ids can be missing, and arguments can be an object, wrapped in a code
+/// fence or surrounded by other text, or cut off before the closing braces.
+pub fn repair_response(mut response: Value) -> Value {
+    let Some(message) = response
+        .get_mut("choices")
+        .and_then(|choices| choices.get_mut(0))
+        .and_then(|choice| choice.get_mut("message"))
+    else {
+        return response;
+    };
+
+    let has_tool_calls = message["tool_calls"]
+        .as_array()
+        .is_some_and(|calls| !calls.is_empty());
+    if !has_tool_calls {
+        if let Some(text) = message["content"].as_str() {
+            let (text, calls) = extract_tool_calls(text);
+            if !calls.is_empty() {
+                message["content"] = if text.is_empty() {
+                    Value::Null
+                } else {
+                    json!(text)
+                };
+                message["tool_calls"] = json!(calls);
+            }
+        }
+    }
+
+    if let Some(tool_calls) = message["tool_calls"].as_array_mut() {
+        for (i, call) in tool_calls.iter_mut().enumerate() {
+            if call["id"].as_str().is_none_or(str::is_empty) {
+                call["id"] = json!(format!("call_{}", i));
+            }
+            let arguments = match &call["function"]["arguments"] {
+                Value::String(arguments) => repair_arguments(arguments),
+                Value::Null => "{}".to_string(),
+                arguments => arguments.to_string(),
+            };
+            call["function"]["arguments"] = json!(arguments);
+        }
+    }
+    response
+}
+
+/// Convert an LM Studio chat completion to internal Message format
+pub fn response_to_message(response: Value) -> Result<Message> {
+    openai::response_to_message(repair_response(response))
+}
+
+/// Pull the tool calls written between markers out of the text, returning the rest of it
+fn extract_tool_calls(text: &str) -> (String, Vec<Value>) {
+    let mut rest = text.to_string();
+    let mut calls = Vec::new();
+    for (open, close) in TOOL_CALL_MARKERS {
+        while let Some(start) = rest.find(open) {
+            let body_start = start + open.len();
+            let (body, end) = match rest[body_start..].find(close) {
+                Some(len) => (
+                    &rest[body_start..body_start + len],
+                    body_start + len + close.len(),
+                ),
+                // The model stopped before closing the call
+                None => (&rest[body_start..], rest.len()),
+            };
+            let Ok(call) = serde_json::from_str::<Value>(&repair_arguments(body)) else {
+                break;
+            };
+            let Some(name) = call["name"].as_str() else {
+                break;
+            };
+            calls.push(json!({
+                "id": "",
+                "type": "function",
+                "function": { "name": name, "arguments": call["arguments"].clone() }
+            }));
+            rest.replace_range(start..end, "");
+        }
+    }
+    (rest.trim().to_string(), calls)
+}
+
+/// Best effort to turn the arguments a model wrote into a JSON object
+///
+/// Arguments that still don't parse are returned as they are, so the error reported back
+/// to the model shows what it wrote.
+fn repair_arguments(arguments: &str) -> String {
+    let trimmed = arguments.trim();
+    if trimmed.is_empty() {
+        return "{}".to_string();
+    }
+    if serde_json::from_str::<Value>(trimmed).is_ok() {
+        return trimmed.to_string();
+    }
+
+    // Drop a code fence or any text around the object
+    let Some(start) = trimmed.find('{') else {
+        return arguments.to_string();
+    };
+    let object = match trimmed.rfind('}') {
+        Some(end) if end > start => &trimmed[start..=end],
+        _ => &trimmed[start..],
+    };
+    if serde_json::from_str::<Value>(object).is_ok() {
+        return object.to_string();
+    }
+
+    // Close whatever was left open when the output stopped
+    let closed = close_brackets(&trimmed[start..]);
+    if serde_json::from_str::<Value>(&closed).is_ok() {
+        return closed;
+    }
+    arguments.to_string()
+}
+
+fn close_brackets(json: &str) -> String {
+    let mut open = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in json.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' if !in_string => open.push('}'),
+            '[' if !in_string => open.push(']'),
+            '}' | ']' if !in_string => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+
+    let mut closed = json.to_string();
+    if in_string {
+        closed.push('"');
+    }
+    closed.extend(open.iter().rev());
+    closed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completion(message: Value) -> Value {
+        json!({
+            "model": "qwen2.5-7b-instruct",
+            "choices": [{ "message": message, "finish_reason": "tool_calls" }]
+        })
+    }
+
+    #[test]
+    fn test_repair_tool_calls() -> Result<()> {
+        let response = completion(json!({
+            "role": "assistant",
+            "content": "",
+            "tool_calls": [
+                {
+                    "type": "function",
+                    "function": { "name": "developer__shell", "arguments": { "command": "ls" } }
+                },
+                {
+                    "id": "",
+                    "type": "function",
+                    "function": {
+                        "name": "developer__shell",
+                        "arguments": "```json\n{\"command\": \"pwd\"}\n```"
+                    }
+                },
+                {
+                    "id": "42",
+                    "type": "function",
+                    "function": {
+                        "name": "developer__text_editor",
+                        "arguments": "{\"command\": \"view\", \"path\": \"/tmp/a"
+                    }
+                }
+            ]
+        }));
+
+        let message = response_to_message(response)?;
+        let requests: Vec<_> = message
+            .content
+            .iter()
+            .filter_map(|content| content.as_tool_request())
+            .collect();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].id, "call_0");
+        assert_eq!(
+            requests[0].tool_call.as_ref().unwrap().arguments,
+            json!({ "command": "ls" })
+        );
+        assert_eq!(requests[1].id, "call_1");
+        assert_eq!(
+            requests[1].tool_call.as_ref().unwrap().arguments,
+            json!({ "command": "pwd" })
+        );
+        assert_eq!(requests[2].id, "42");
+        assert_eq!(
+            requests[2].tool_call.as_ref().unwrap().arguments,
+            json!({ "command": "view", "path": "/tmp/a" })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tool_calls_in_text() -> Result<()> {
+        let response = completion(json!({
+            "role": "assistant",
+            "content": "Let me check.\n[TOOL_REQUEST]{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"ls\"}}[END_TOOL_REQUEST]"
+        }));
+        let message = response_to_message(response)?;
+        assert_eq!(message.content[0].as_text(), Some("Let me check."));
+        let request = message.content[1].as_tool_request().unwrap();
+        assert_eq!(request.id, "call_0");
+        let tool_call = request.tool_call.as_ref().unwrap();
+        assert_eq!(tool_call.name, "developer__shell");
+        assert_eq!(tool_call.arguments, json!({ "command": "ls" }));
+
+        let response = completion(json!({
+            "role": "assistant",
+            "content": "<tool_call>\n{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"pwd\"}}\n</tool_call>"
+        }));
+        let message = response_to_message(response)?;
+        assert_eq!(message.content.len(), 1);
+        assert!(message.content[0].as_tool_request().is_some());
+
+        // Text that only mentions the markers is left alone
+        let response = completion(json!({
+            "role": "assistant",
+            "content": "Tool calls go between <tool_call> tags."
+        }));
+        let message = response_to_message(response)?;
+        assert_eq!(
+            message.content[0].as_text(),
+            Some("Tool calls go between <tool_call> tags.")
+        );
+
+        Ok(())
+    }
+}
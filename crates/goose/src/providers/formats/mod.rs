@@ -1,4 +1,24 @@
+pub mod ai21;
 pub mod anthropic;
 pub mod bedrock;
+pub mod chat_template;
+pub mod cloudflare;
+pub mod cohere;
+pub mod fireworks;
 pub mod google;
+pub mod huggingface;
+pub mod koboldcpp;
+pub mod llamacpp;
+pub mod lmstudio;
+pub mod moonshot;
+pub mod oci;
+pub mod ollama;
 pub mod openai;
+pub mod perplexity;
+pub mod qwen;
+pub mod replicate;
+pub mod sagemaker;
+pub mod snowflake;
+pub mod streaming;
+pub mod tgi;
+pub mod watsonx;
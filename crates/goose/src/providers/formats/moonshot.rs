@@ -0,0 +1,103 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::formats::openai;
+use crate::providers::utils::ImageFormat;
+use anyhow::Result;
+use mcp_core::{Role, Tool};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Create a request payload for the Kimi chat completions API
+///
+/// Kimi takes the OpenAI format with a few differences:
+/// - a tool result names the function it answers, not only the call id
+/// - temperature is limited to [0, 1]
+/// - a conversation ending with an assistant message is sent in partial mode, so the model
+///   continues that message instead of answering it; the response holds only the
+///   continuation
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut payload =
+        openai::create_request(model_config, system, messages, tools, &ImageFormat::OpenAi)?;
+
+    if let Some(temperature) = payload["temperature"].as_f64() {
+        payload["temperature"] = json!(temperature.clamp(0.0, 1.0));
+    }
+
+    if let Some(messages_spec) = payload["messages"].as_array_mut() {
+        let mut function_names = HashMap::new();
+        for message in messages_spec.iter_mut() {
+            if let Some(tool_calls) = message["tool_calls"].as_array() {
+                for tool_call in tool_calls {
+                    if let (Some(id), Some(name)) = (
+                        tool_call["id"].as_str(),
+                        tool_call["function"]["name"].as_str(),
+                    ) {
+                        function_names.insert(id.to_string(), name.to_string());
+                    }
+                }
+            }
+            if message["role"] == "tool" {
+                if let Some(name) = message["tool_call_id"]
+                    .as_str()
+                    .and_then(|id| function_names.get(id))
+                {
+                    message["name"] = json!(name);
+                }
+            }
+        }
+
+        let ends_with_prefix = messages
+            .last()
+            .is_some_and(|message| message.role == Role::Assistant);
+        if let Some(last) = messages_spec.last_mut() {
+            if ends_with_prefix && last["role"] == "assistant" && last.get("tool_calls").is_none() {
+                last["partial"] = json!(true);
+            }
+        }
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::{Content, ToolCall};
+
+    #[test]
+    fn test_create_request() -> Result<()> {
+        let model_config =
+            ModelConfig::new("kimi-k2-0711-preview".to_string()).with_temperature(Some(1.5));
+        let messages = vec![
+            Message::user().with_text("What's the weather in Beijing?"),
+            Message::assistant().with_tool_request(
+                "functions.get_weather:0",
+                Ok(ToolCall::new("get_weather", json!({ "city": "Beijing" }))),
+            ),
+            Message::user()
+                .with_tool_response("functions.get_weather:0", Ok(vec![Content::text("Sunny")])),
+            Message::assistant().with_text("{\"forecast\": "),
+        ];
+        let payload = create_request(&model_config, "system", &messages, &[])?;
+
+        assert_eq!(payload["temperature"], 1.0);
+        let spec = payload["messages"].as_array().unwrap();
+        assert_eq!(spec[3]["role"], "tool");
+        assert_eq!(spec[3]["name"], "get_weather");
+        assert_eq!(spec[4]["partial"], true);
+        // Partial mode only applies to the final assistant message
+        assert!(spec[2].get("partial").is_none());
+
+        let payload = create_request(&model_config, "system", &messages[..3], &[])?;
+        assert!(payload["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|message| message.get("partial").is_none()));
+        Ok(())
+    }
+}
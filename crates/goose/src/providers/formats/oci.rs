@@ -0,0 +1,244 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai;
+use crate::providers::utils::ImageFormat;
+use anyhow::{anyhow, Result};
+use mcp_core::Tool;
+use serde_json::{json, Value};
+
+/// How a model is served: models shared by the region are on demand, named by their id or
+/// OCID, and models on a dedicated AI cluster are reached through their endpoint's OCID
+pub fn serving_mode(model: &str) -> Value {
+    if model.starts_with("ocid1.generativeaiendpoint.") {
+        json!({ "servingType": "DEDICATED", "endpointId": model })
+    } else {
+        json!({ "servingType": "ON_DEMAND", "modelId": model })
+    }
+}
+
+/// Convert a content part of an OpenAI message to the OCI `GENERIC` format
+fn format_part(part: &Value) -> Value {
+    match part["type"].as_str() {
+        Some("image_url") => json!({ "type": "IMAGE", "imageUrl": part["image_url"] }),
+        _ => json!({ "type": "TEXT", "text": part["text"] }),
+    }
+}
+
+/// Convert internal Message format to the OCI `GENERIC` chat message specification
+///
+/// The generic format is the OpenAI one with upper case roles and types, camel case keys,
+/// and content always given as a list of parts, so messages are converted through it.
+pub fn format_messages(messages: &[Message]) -> Vec<Value> {
+    openai::format_messages(messages, &ImageFormat::OpenAi)
+        .into_iter()
+        .map(|message| {
+            let role = message["role"].as_str().unwrap_or("user").to_uppercase();
+            let content: Vec<Value> = match &message["content"] {
+                Value::String(text) => vec![json!({ "type": "TEXT", "text": text })],
+                Value::Array(parts) => parts.iter().map(format_part).collect(),
+                _ => Vec::new(),
+            };
+
+            let mut converted = json!({ "role": role, "content": content });
+            if let Some(tool_calls) = message["tool_calls"].as_array() {
+                let tool_calls: Vec<Value> = tool_calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "id": call["id"],
+                            "type": "FUNCTION",
+                            "name": call["function"]["name"],
+                            "arguments": call["function"]["arguments"],
+                        })
+                    })
+                    .collect();
+                converted["toolCalls"] = json!(tool_calls);
+            }
+            if let Some(id) = message["tool_call_id"].as_str() {
+                converted["toolCallId"] = json!(id);
+            }
+            converted
+        })
+        .collect()
+}
+
+/// Convert internal Tool format to the OCI function tool specification
+pub fn format_tools(tools: &[Tool]) -> Result<Vec<Value>> {
+    Ok(openai::format_tools(tools)?
+        .into_iter()
+        .map(|tool| {
+            let function = &tool["function"];
+            json!({
+                "type": "FUNCTION",
+                "name": function["name"],
+                "description": function["description"],
+                "parameters": function["parameters"],
+            })
+        })
+        .collect())
+}
+
+/// Create a complete request payload for the OCI Generative AI chat action
+///
+/// Only the `GENERIC` API format is spoken, which the Meta, xAI, OpenAI and Google models
+/// use; Cohere models need the `COHERE` format instead.
+pub fn create_request(
+    model_config: &ModelConfig,
+    compartment_id: &str,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut messages_spec = Vec::new();
+    if !system.is_empty() {
+        messages_spec.push(json!({
+            "role": "SYSTEM",
+            "content": [{ "type": "TEXT", "text": system }],
+        }));
+    }
+    messages_spec.extend(format_messages(messages));
+
+    let mut chat_request = json!({
+        "apiFormat": "GENERIC",
+        "messages": messages_spec,
+        "isStream": false,
+    });
+    let tools_spec = format_tools(tools)?;
+    if !tools_spec.is_empty() {
+        chat_request["tools"] = json!(tools_spec);
+    }
+    if let Some(temperature) = model_config.temperature {
+        chat_request["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = model_config.max_tokens {
+        chat_request["maxTokens"] = json!(max_tokens);
+    }
+
+    Ok(json!({
+        "compartmentId": compartment_id,
+        "servingMode": serving_mode(&model_config.model_name),
+        "chatRequest": chat_request,
+    }))
+}
+
+/// Convert an OCI chat response to internal Message format
+pub fn response_to_message(response: Value) -> Result<Message> {
+    let original = response["chatResponse"]["choices"][0]
+        .get("message")
+        .ok_or_else(|| anyhow!("Invalid response format: missing message"))?;
+
+    let text: Vec<&str> = original["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|part| part["text"].as_str())
+        .collect();
+    let tool_calls: Vec<Value> = original["toolCalls"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|call| {
+            json!({
+                "id": call["id"],
+                "type": "function",
+                "function": { "name": call["name"], "arguments": call["arguments"] },
+            })
+        })
+        .collect();
+
+    let mut message = json!({ "role": "assistant" });
+    if !text.is_empty() {
+        message["content"] = json!(text.join(""));
+    }
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = json!(tool_calls);
+    }
+    openai::response_to_message(json!({ "choices": [{ "message": message }] }))
+}
+
+/// Extract usage information from an OCI chat response
+pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
+    let usage = data["chatResponse"]
+        .get("usage")
+        .ok_or_else(|| ProviderError::UsageError("No usage data in response".to_string()))?;
+
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_f64()).map(|v| v as i32);
+    let input_tokens = count("promptTokens");
+    let output_tokens = count("completionTokens");
+    let total_tokens = count("totalTokens").or(match (input_tokens, output_tokens) {
+        (Some(input), Some(output)) => Some(input.saturating_add(output)),
+        _ => None,
+    });
+
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::{Content, ToolCall};
+
+    #[test]
+    fn test_generic_round_trip() -> Result<()> {
+        let response = json!({
+            "modelId": "meta.llama-3.3-70b-instruct",
+            "chatResponse": {
+                "apiFormat": "GENERIC",
+                "choices": [{
+                    "index": 0,
+                    "finishReason": "tool_calls",
+                    "message": {
+                        "role": "ASSISTANT",
+                        "toolCalls": [{
+                            "id": "chatcmpl-tool-1",
+                            "type": "FUNCTION",
+                            "name": "weather",
+                            "arguments": "{\"location\":\"Austin\"}"
+                        }]
+                    }
+                }],
+                "usage": { "promptTokens": 30, "completionTokens": 8, "totalTokens": 38 }
+            }
+        });
+
+        let message = response_to_message(response.clone())?;
+        let request = message.content[0].as_tool_request().unwrap();
+        assert_eq!(request.id, "chatcmpl-tool-1");
+        assert_eq!(
+            request.tool_call.as_ref().unwrap(),
+            &ToolCall::new("weather", json!({ "location": "Austin" }))
+        );
+        assert_eq!(get_usage(&response)?.total_tokens, Some(38));
+
+        let messages = vec![
+            Message::user().with_text("What's the weather in Austin?"),
+            message,
+            Message::user().with_tool_response("chatcmpl-tool-1", Ok(vec![Content::text("Hot")])),
+        ];
+        let model_config =
+            ModelConfig::new("ocid1.generativeaiendpoint.oc1.us-chicago-1.aaaa".to_string());
+        let payload = create_request(
+            &model_config,
+            "ocid1.compartment.oc1..bbbb",
+            "Be brief.",
+            &messages,
+            &[],
+        )?;
+        assert_eq!(payload["servingMode"]["servingType"], "DEDICATED");
+        let spec = &payload["chatRequest"]["messages"];
+        assert_eq!(spec[0]["role"], "SYSTEM");
+        assert_eq!(
+            spec[1]["content"][0]["text"],
+            "What's the weather in Austin?"
+        );
+        assert_eq!(spec[2]["role"], "ASSISTANT");
+        assert_eq!(spec[2]["toolCalls"][0]["type"], "FUNCTION");
+        assert_eq!(spec[3]["role"], "TOOL");
+        assert_eq!(spec[3]["toolCallId"], "chatcmpl-tool-1");
+        assert_eq!(spec[3]["content"][0]["text"], "Hot");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::{MessageDelta, Usage};
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai;
+use crate::providers::utils::ImageFormat;
+use anyhow::Result;
+use mcp_core::tool::Tool;
+use serde_json::{json, Value};
+
+/// Create a request payload for Ollama's native `/api/chat` endpoint, streamed
+///
+/// The endpoint takes messages much like chat completions, except that text and images are
+/// separate fields, tool call arguments are objects rather than JSON text, and sampling
+/// settings are `options`.
+pub fn create_chat_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let messages_spec: Vec<Value> = std::iter::once(json!({ "role": "system", "content": system }))
+        .chain(
+            openai::format_messages(messages, &ImageFormat::OpenAi)
+                .into_iter()
+                .map(to_native_message),
+        )
+        .collect();
+
+    let mut payload = json!({
+        "model": model_config.model_name,
+        "messages": messages_spec,
+        "stream": true,
+    });
+    let tools_spec = openai::format_tools(tools)?;
+    if !tools_spec.is_empty() {
+        payload["tools"] = json!(tools_spec);
+    }
+    let mut options = json!({});
+    if let Some(temperature) = model_config.temperature {
+        options["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = model_config.max_tokens {
+        options["num_predict"] = json!(max_tokens);
+    }
+    payload["options"] = options;
+    Ok(payload)
+}
+
+fn to_native_message(mut message: Value) -> Value {
+    if let Some(parts) = message["content"].as_array().cloned() {
+        let mut text = Vec::new();
+        let mut images = Vec::new();
+        for part in parts {
+            match part["type"].as_str() {
+                Some("text") => text.push(part["text"].as_str().unwrap_or_default().to_string()),
+                Some("image_url") => {
+                    // Images are sent as data URLs, Ollama takes their base64 data alone
+                    let url = part["image_url"]["url"].as_str().unwrap_or_default();
+                    if let Some((_, data)) = url.split_once("base64,") {
+                        images.push(json!(data));
+                    }
+                }
+                _ => {}
+            }
+        }
+        message["content"] = json!(text.join("\n"));
+        if !images.is_empty() {
+            message["images"] = json!(images);
+        }
+    }
+    if let Some(calls) = message["tool_calls"].as_array_mut() {
+        for call in calls {
+            let arguments = call["function"]["arguments"]
+                .as_str()
+                .and_then(|arguments| serde_json::from_str(arguments).ok())
+                .unwrap_or_else(|| json!({}));
+            call["function"]["arguments"] = arguments;
+        }
+    }
+    message
+}
+
+/// Convert a line of a streamed `/api/chat` response to the deltas of the message
+///
+/// Tool calls come whole, without ids, so each gets a new one and is numbered from
+/// `tool_calls`, the count of calls in earlier lines.
+pub fn chat_chunk_to_deltas(
+    chunk: &Value,
+    tool_calls: &mut usize,
+) -> Result<Vec<MessageDelta>, ProviderError> {
+    if let Some(error) = chunk["error"].as_str() {
+        return Err(ProviderError::ServerError(error.to_string()));
+    }
+
+    let message = &chunk["message"];
+    let mut deltas = Vec::new();
+    if let Some(thinking) = message["thinking"].as_str() {
+        if !thinking.is_empty() {
+            deltas.push(MessageDelta::Thinking(thinking.to_string()));
+        }
+    }
+    if let Some(text) = message["content"].as_str() {
+        if !text.is_empty() {
+            deltas.push(MessageDelta::Text(text.to_string()));
+        }
+    }
+    for call in message["tool_calls"].as_array().into_iter().flatten() {
+        deltas.push(MessageDelta::ToolCall {
+            index: *tool_calls,
+            id: Some(format!("call_{}", nanoid::nanoid!(8))),
+            name: call["function"]["name"].as_str().map(String::from),
+            arguments: call["function"]["arguments"].to_string(),
+        });
+        *tool_calls += 1;
+    }
+    Ok(deltas)
+}
+
+/// Extract usage information from the last line of a streamed `/api/chat` response
+pub fn get_chat_usage(chunk: &Value) -> Result<Usage, ProviderError> {
+    let count = |key: &str| chunk[key].as_i64().map(|v| v as i32);
+    let (input_tokens, output_tokens) = (count("prompt_eval_count"), count("eval_count"));
+    if input_tokens.is_none() && output_tokens.is_none() {
+        return Err(ProviderError::UsageError(
+            "No token counts in response".to_string(),
+        ));
+    }
+    let total_tokens = input_tokens.unwrap_or(0) + output_tokens.unwrap_or(0);
+    Ok(Usage::new(input_tokens, output_tokens, Some(total_tokens)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::ToolCall;
+
+    #[test]
+    fn test_chat_round_trip() -> Result<()> {
+        let messages = vec![
+            Message::user().with_text("List the files"),
+            Message::assistant().with_tool_request(
+                "call_1",
+                Ok(ToolCall::new("shell", json!({"command": "ls"}))),
+            ),
+        ];
+        let model_config = ModelConfig::new("qwen2.5".to_string()).with_max_tokens(Some(512));
+        let payload = create_chat_request(&model_config, "Be brief.", &messages, &[])?;
+        assert_eq!(payload["messages"][0]["role"], "system");
+        assert_eq!(
+            payload["messages"][2]["tool_calls"][0]["function"]["arguments"],
+            json!({"command": "ls"})
+        );
+        assert_eq!(payload["options"]["num_predict"], 512);
+
+        let mut tool_calls = 0;
+        let chunk = json!({"message": {"role": "assistant", "content": "Sure"}, "done": false});
+        let deltas = chat_chunk_to_deltas(&chunk, &mut tool_calls)?;
+        assert!(matches!(&deltas[0], MessageDelta::Text(text) if text == "Sure"));
+
+        let chunk = json!({
+            "message": {"role": "assistant", "content": "", "tool_calls": [{"function": {"name": "shell", "arguments": {"command": "ls"}}}]},
+            "done": true,
+            "prompt_eval_count": 40,
+            "eval_count": 12
+        });
+        let deltas = chat_chunk_to_deltas(&chunk, &mut tool_calls)?;
+        assert!(matches!(
+            &deltas[0],
+            MessageDelta::ToolCall { index: 0, arguments, .. } if arguments == r#"{"command":"ls"}"#
+        ));
+        assert_eq!(get_chat_usage(&chunk)?.total_tokens, Some(52));
+        Ok(())
+    }
+}
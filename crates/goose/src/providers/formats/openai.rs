@@ -1,6 +1,6 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{MessageDelta, Usage};
 use crate::providers::errors::ProviderError;
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file,
@@ -139,6 +139,9 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                 MessageContent::ToolConfirmationRequest(_) => {
                     // Skip tool confirmation requests
                 }
+                MessageContent::Thinking(_) => {
+                    // Reasoning models reject their earlier reasoning being sent back
+                }
                 MessageContent::Image(image) => {
                     // Handle direct image content
                     converted["content"] = json!([convert_image(image, image_format)]);
@@ -187,6 +190,13 @@ pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
     let original = response["choices"][0]["message"].clone();
     let mut content = Vec::new();
 
+    // Reasoning models such as deepseek-reasoner return their chain of thought separately
+    if let Some(reasoning) = original.get("reasoning_content").and_then(|r| r.as_str()) {
+        if !reasoning.is_empty() {
+            content.push(MessageContent::thinking(reasoning));
+        }
+    }
+
     if let Some(text) = original.get("content") {
         if let Some(text_str) = text.as_str() {
             content.push(MessageContent::text(text_str));
@@ -237,11 +247,43 @@ pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
         }
     }
 
-    Ok(Message {
-        role: Role::Assistant,
-        created: chrono::Utc::now().timestamp(),
+    Ok(Message::new(
+        Role::Assistant,
+        chrono::Utc::now().timestamp(),
         content,
-    })
+    ))
+}
+
+/// Convert a chunk of a streamed chat completion to the deltas of the message
+///
+/// Tool calls come in fragments identified by their index, with the id and name only in the
+/// first one.
+pub fn response_chunk_to_deltas(chunk: &Value) -> Vec<MessageDelta> {
+    let delta = &chunk["choices"][0]["delta"];
+    let mut deltas = Vec::new();
+
+    if let Some(reasoning) = delta["reasoning_content"].as_str() {
+        if !reasoning.is_empty() {
+            deltas.push(MessageDelta::Thinking(reasoning.to_string()));
+        }
+    }
+    if let Some(text) = delta["content"].as_str() {
+        if !text.is_empty() {
+            deltas.push(MessageDelta::Text(text.to_string()));
+        }
+    }
+    for tool_call in delta["tool_calls"].as_array().into_iter().flatten() {
+        deltas.push(MessageDelta::ToolCall {
+            index: tool_call["index"].as_u64().unwrap_or_default() as usize,
+            id: tool_call["id"].as_str().map(String::from),
+            name: tool_call["function"]["name"].as_str().map(String::from),
+            arguments: tool_call["function"]["arguments"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    deltas
 }
 
 pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
@@ -264,11 +306,27 @@ pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
         .and_then(|v| v.as_i64())
         .map(|v| v as i32)
         .or_else(|| match (input_tokens, output_tokens) {
-            (Some(input), Some(output)) => Some(input + output),
+            (Some(input), Some(output)) => Some(input.saturating_add(output)),
             _ => None,
         });
 
-    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+    // The details break down prompt_tokens and completion_tokens, which include them
+    let detail = |details: &str, name: &str| {
+        usage
+            .get(details)
+            .and_then(|d| d.get(name))
+            .and_then(|v| v.as_i64())
+            .map(|v| v as i32)
+    };
+    let cache_read_tokens = detail("prompt_tokens_details", "cached_tokens");
+    let reasoning_tokens = detail("completion_tokens_details", "reasoning_tokens");
+    let audio_input_tokens = detail("prompt_tokens_details", "audio_tokens");
+    let audio_output_tokens = detail("completion_tokens_details", "audio_tokens");
+
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+        .with_cache_tokens(cache_read_tokens, None)
+        .with_reasoning_tokens(reasoning_tokens)
+        .with_audio_tokens(audio_input_tokens, audio_output_tokens))
 }
 
 /// Validates and fixes tool schemas to ensure they have proper parameter structure.
@@ -417,6 +475,7 @@ pub fn create_request(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::DecodingConfig;
     use mcp_core::content::Content;
     use serde_json::json;
 
@@ -729,6 +788,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_response_to_message_reasoning() -> anyhow::Result<()> {
+        let response = json!({
+            "model": "deepseek-reasoner",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "reasoning_content": "The user greeted me, so I should greet back.",
+                    "content": "Hello!"
+                }
+            }]
+        });
+
+        let message = response_to_message(response)?;
+        assert_eq!(
+            message.content[0].as_thinking(),
+            Some("The user greeted me, so I should greet back.")
+        );
+        assert_eq!(message.content[1].as_text(), Some("Hello!"));
+
+        // The reasoning stays out of later requests
+        let spec = format_messages(&[message], &ImageFormat::OpenAi);
+        assert_eq!(spec.len(), 1);
+        assert_eq!(spec[0]["content"], "Hello!");
+        assert!(spec[0].get("reasoning_content").is_none());
+
+        Ok(())
+    }
+
     #[test]
     fn test_response_to_message_valid_toolrequest() -> anyhow::Result<()> {
         let response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
@@ -809,6 +897,47 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_get_usage_cached_tokens() -> anyhow::Result<()> {
+        let response = json!({
+            "usage": {
+                "prompt_tokens": 2006,
+                "completion_tokens": 300,
+                "total_tokens": 2306,
+                "prompt_tokens_details": {
+                    "cached_tokens": 1920
+                }
+            }
+        });
+
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(2006));
+        assert_eq!(usage.cache_read_tokens, Some(1920));
+        assert_eq!(usage.cache_write_tokens, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_usage_reasoning_and_audio_tokens() -> anyhow::Result<()> {
+        let response = json!({
+            "usage": {
+                "prompt_tokens": 120,
+                "completion_tokens": 900,
+                "total_tokens": 1020,
+                "prompt_tokens_details": {"cached_tokens": 0, "audio_tokens": 40},
+                "completion_tokens_details": {"reasoning_tokens": 768, "audio_tokens": 0}
+            }
+        });
+
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.reasoning_tokens, Some(768));
+        assert_eq!(usage.audio_input_tokens, Some(40));
+        assert_eq!(usage.audio_output_tokens, Some(0));
+
+        Ok(())
+    }
+
     #[test]
     fn test_create_request_gpt_4o() -> anyhow::Result<()> {
         // Test default medium reasoning effort for O3 model
@@ -818,6 +947,8 @@ mod tests {
             context_limit: Some(4096),
             temperature: None,
             max_tokens: Some(1024),
+            decoding: DecodingConfig::default(),
+            truncation_strategy: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -848,6 +979,8 @@ mod tests {
             context_limit: Some(4096),
             temperature: None,
             max_tokens: Some(1024),
+            decoding: DecodingConfig::default(),
+            truncation_strategy: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -879,6 +1012,8 @@ mod tests {
             context_limit: Some(4096),
             temperature: None,
             max_tokens: Some(1024),
+            decoding: DecodingConfig::default(),
+            truncation_strategy: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
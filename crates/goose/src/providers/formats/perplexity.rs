@@ -0,0 +1,149 @@
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::providers::formats::openai;
+use crate::providers::utils::ImageFormat;
+use anyhow::Result;
+use mcp_core::Tool;
+use serde_json::Value;
+
+/// Create a request payload for Perplexity's chat completions API
+///
+/// The Sonar models search the web instead of calling tools, so tools are left out.
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    if !tools.is_empty() {
+        tracing::debug!(
+            "Perplexity models don't call tools, leaving out {} tools",
+            tools.len()
+        );
+    }
+    openai::create_request(model_config, system, messages, &[], &ImageFormat::OpenAi)
+}
+
+/// The sources a response was based on, numbered the way the answer cites them
+///
+/// Newer responses describe each source in `search_results`, older ones only list the
+/// URLs in `citations`.
+pub fn get_sources(response: &Value) -> Vec<String> {
+    if let Some(results) = response.get("search_results").and_then(|r| r.as_array()) {
+        return results
+            .iter()
+            .filter_map(|result| {
+                let url = result.get("url")?.as_str()?;
+                Some(match result.get("title").and_then(|t| t.as_str()) {
+                    Some(title) if !title.is_empty() => format!("{} - {}", title, url),
+                    _ => url.to_string(),
+                })
+            })
+            .collect();
+    }
+    response
+        .get("citations")
+        .and_then(|c| c.as_array())
+        .map(|citations| {
+            citations
+                .iter()
+                .filter_map(|c| c.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Convert a Perplexity response to internal Message format
+///
+/// The reasoning models put their reasoning in a leading `<think>` block, which becomes
+/// thinking content, and the sources the answer cites are appended as a numbered list.
+pub fn response_to_message(response: Value) -> Result<Message> {
+    let mut message = openai::response_to_message(response.clone())?;
+
+    for content in std::mem::take(&mut message.content) {
+        let MessageContent::Text(text) = &content else {
+            message.content.push(content);
+            continue;
+        };
+        match split_thinking(&text.text) {
+            Some((thinking, answer)) => {
+                message = message.with_thinking(thinking).with_text(answer);
+            }
+            None => message.content.push(content),
+        }
+    }
+
+    let sources = get_sources(&response);
+    if !sources.is_empty() {
+        let list = sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| format!("[{}] {}", i + 1, source))
+            .collect::<Vec<_>>()
+            .join("\n");
+        message = message.with_text(format!("Sources:\n{}", list));
+    }
+    Ok(message)
+}
+
+fn split_thinking(text: &str) -> Option<(&str, &str)> {
+    let rest = text.trim_start().strip_prefix("<think>")?;
+    let (thinking, answer) = rest.split_once("</think>")?;
+    Some((thinking.trim(), answer.trim_start()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_response_with_sources() -> Result<()> {
+        let response = json!({
+            "model": "sonar-reasoning",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "<think>Both sources agree.</think>\nRust 1.0 was released in 2015 [1][2]."
+                }
+            }],
+            "citations": ["https://blog.rust-lang.org/", "https://en.wikipedia.org/wiki/Rust"],
+            "search_results": [
+                { "title": "Announcing Rust 1.0", "url": "https://blog.rust-lang.org/" },
+                { "title": "", "url": "https://en.wikipedia.org/wiki/Rust" }
+            ]
+        });
+
+        let message = response_to_message(response.clone())?;
+        assert_eq!(
+            message.content[0].as_thinking(),
+            Some("Both sources agree.")
+        );
+        assert_eq!(
+            message.content[1].as_text(),
+            Some("Rust 1.0 was released in 2015 [1][2].")
+        );
+        assert_eq!(
+            message.content[2].as_text(),
+            Some(
+                "Sources:\n[1] Announcing Rust 1.0 - https://blog.rust-lang.org/\n\
+                [2] https://en.wikipedia.org/wiki/Rust"
+            )
+        );
+
+        // Without search results, the citations are used
+        let mut response = response;
+        response.as_object_mut().unwrap().remove("search_results");
+        assert_eq!(get_sources(&response).len(), 2);
+
+        let payload = create_request(
+            &ModelConfig::new("sonar".to_string()),
+            "system",
+            &[Message::user().with_text("When was Rust 1.0 released?")],
+            &[Tool::new("search", "Search", json!({"type": "object"}))],
+        )?;
+        assert!(payload.get("tools").is_none());
+
+        Ok(())
+    }
+}
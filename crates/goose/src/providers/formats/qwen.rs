@@ -0,0 +1,273 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai;
+use crate::providers::utils::ImageFormat;
+use anyhow::{anyhow, Result};
+use mcp_core::Tool;
+use serde_json::{json, Map, Value};
+
+/// Create a request payload for DashScope's native text generation API
+///
+/// The messages and tools are the same as in the OpenAI compatible API, but go under `input`
+/// and `parameters`, and the response is asked for in the chat message format.
+pub fn create_native_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut request =
+        openai::create_request(model_config, system, messages, tools, &ImageFormat::OpenAi)?;
+    let request = request
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Request payload is not an object"))?;
+
+    let model = request.remove("model").unwrap_or_default();
+    let messages = request.remove("messages").unwrap_or_default();
+    let mut parameters = std::mem::take(request);
+    parameters.insert("result_format".to_string(), json!("message"));
+
+    Ok(json!({
+        "model": model,
+        "input": { "messages": messages },
+        "parameters": parameters,
+    }))
+}
+
+/// Convert a native DashScope response to internal Message format
+///
+/// The choices are under `output` but otherwise in the OpenAI shape, including the
+/// `reasoning_content` of thinking models.
+pub fn native_to_message(response: &Value) -> Result<Message> {
+    let choices = response["output"]
+        .get("choices")
+        .ok_or_else(|| anyhow!("Invalid response format: missing output choices"))?;
+    openai::response_to_message(json!({ "choices": choices }))
+}
+
+/// Extract usage information from a native DashScope response
+pub fn get_native_usage(data: &Value) -> Result<Usage, ProviderError> {
+    let usage = data
+        .get("usage")
+        .ok_or_else(|| ProviderError::UsageError("No usage data in response".to_string()))?;
+
+    let count = |value: &Value| value.as_i64().map(|v| v as i32);
+    let input_tokens = count(&usage["input_tokens"]);
+    let output_tokens = count(&usage["output_tokens"]);
+    let total_tokens = count(&usage["total_tokens"]).or(match (input_tokens, output_tokens) {
+        (Some(input), Some(output)) => Some(input.saturating_add(output)),
+        _ => None,
+    });
+    let cache_read_tokens = count(&usage["prompt_tokens_details"]["cached_tokens"]);
+    let reasoning_tokens = count(&usage["output_tokens_details"]["reasoning_tokens"]);
+
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+        .with_cache_tokens(cache_read_tokens, None)
+        .with_reasoning_tokens(reasoning_tokens))
+}
+
+/// Append a streamed fragment to the message collected so far
+///
+/// Text fields arrive in pieces, and tool calls in pieces matched up by their index. A call
+/// can only start right after the ones before it, so an index further ahead is an error.
+fn merge_delta(message: &mut Map<String, Value>, delta: &Value) -> Result<()> {
+    let Some(delta) = delta.as_object() else {
+        return Ok(());
+    };
+    for (key, value) in delta {
+        match (key.as_str(), value) {
+            ("tool_calls", Value::Array(tool_calls)) => {
+                let collected = message
+                    .entry("tool_calls")
+                    .or_insert_with(|| json!([]))
+                    .as_array_mut()
+                    .ok_or_else(|| anyhow!("Invalid event in response stream: tool_calls"))?;
+                for (position, tool_call) in tool_calls.iter().enumerate() {
+                    let index = tool_call["index"].as_u64().unwrap_or(position as u64);
+                    let index = usize::try_from(index)
+                        .ok()
+                        .filter(|index| *index <= collected.len())
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "Invalid event in response stream: tool call {} after {} calls",
+                                index,
+                                collected.len()
+                            )
+                        })?;
+                    if index == collected.len() {
+                        collected.push(json!({ "function": {} }));
+                    }
+                    let entry = &mut collected[index];
+                    for field in ["id", "type"] {
+                        if let Some(value) = tool_call[field].as_str().filter(|v| !v.is_empty()) {
+                            entry[field] = json!(value);
+                        }
+                    }
+                    for field in ["name", "arguments"] {
+                        if let Some(piece) = tool_call["function"][field].as_str() {
+                            let text = entry["function"][field].as_str().unwrap_or_default();
+                            entry["function"][field] = json!(format!("{}{}", text, piece));
+                        }
+                    }
+                }
+            }
+            ("content" | "reasoning_content", Value::String(piece)) => {
+                let text = message
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                let text = format!("{}{}", text, piece);
+                message.insert(key.clone(), json!(text));
+            }
+            (_, Value::Null) => {}
+            _ => {
+                message.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collect a server sent event stream into the response a non streaming request would
+/// have returned
+///
+/// DashScope only lets thinking models think when streaming. Both APIs stream incremental
+/// pieces of the message: the OpenAI compatible one as `choices[].delta`, the native one as
+/// `output.choices[].message`, which are put back together in the same shape. The usage
+/// comes with the last event.
+pub fn collect_stream(body: &str) -> Result<Value> {
+    let mut message = Map::new();
+    let mut finish_reason = Value::Null;
+    let mut usage = Value::Null;
+    let mut model = Value::Null;
+    let mut native = false;
+
+    for line in body.lines() {
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            continue;
+        };
+        if data.is_empty() || data == "[DONE]" {
+            continue;
+        }
+        let event: Value = serde_json::from_str(data)
+            .map_err(|e| anyhow!("Invalid event in response stream: {}", e))?;
+        if event.get("code").is_some() && event.get("output").is_none() {
+            return Err(anyhow!("Response stream failed: {}", event["message"]));
+        }
+
+        native |= event.get("output").is_some();
+        let choice = if native {
+            &event["output"]["choices"][0]
+        } else {
+            &event["choices"][0]
+        };
+        merge_delta(
+            &mut message,
+            &choice[if native { "message" } else { "delta" }],
+        )?;
+        if let Some(reason) = choice["finish_reason"].as_str().filter(|r| *r != "null") {
+            finish_reason = json!(reason);
+        }
+        if !event["usage"].is_null() {
+            usage = event["usage"].clone();
+        }
+        if !event["model"].is_null() {
+            model = event["model"].clone();
+        }
+    }
+
+    let choices = json!([{ "message": message, "finish_reason": finish_reason }]);
+    if native {
+        Ok(json!({ "output": { "choices": choices }, "usage": usage }))
+    } else {
+        Ok(json!({ "choices": choices, "usage": usage, "model": model }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_request_and_response() -> Result<()> {
+        let model_config = ModelConfig::new("qwen-plus".to_string()).with_max_tokens(Some(512));
+        let payload = create_native_request(
+            &model_config,
+            "system",
+            &[Message::user().with_text("Hi")],
+            &[],
+        )?;
+        assert_eq!(payload["model"], "qwen-plus");
+        assert_eq!(payload["input"]["messages"][1]["content"], "Hi");
+        assert_eq!(payload["parameters"]["max_tokens"], 512);
+        assert_eq!(payload["parameters"]["result_format"], "message");
+
+        let response = json!({
+            "output": {
+                "choices": [{
+                    "finish_reason": "stop",
+                    "message": { "role": "assistant", "content": "Hello!" }
+                }]
+            },
+            "usage": {
+                "input_tokens": 20,
+                "output_tokens": 3,
+                "total_tokens": 23,
+                "prompt_tokens_details": { "cached_tokens": 16 }
+            },
+            "request_id": "b2b3f2ea-8b3d-9c4e-a8a7-5f6e2f3a7c11"
+        });
+        let message = native_to_message(&response)?;
+        assert_eq!(message.content[0].as_text(), Some("Hello!"));
+        let usage = get_native_usage(&response)?;
+        assert_eq!(usage.total_tokens, Some(23));
+        assert_eq!(usage.cache_read_tokens, Some(16));
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_stream() -> Result<()> {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":null,\"reasoning_content\":\"The user\"},\"finish_reason\":null}],\"model\":\"qwen3-32b\"}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"reasoning_content\":\" wants the weather.\"},\"finish_reason\":null}],\"model\":\"qwen3-32b\"}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"weather\",\"arguments\":\"{\\\"city\\\":\"}}]},\"finish_reason\":null}],\"model\":\"qwen3-32b\"}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"\",\"type\":\"function\",\"function\":{\"arguments\":\" \\\"Hangzhou\\\"}\"}}]},\"finish_reason\":\"tool_calls\"}],\"model\":\"qwen3-32b\"}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":180,\"completion_tokens\":42,\"total_tokens\":222},\"model\":\"qwen3-32b\"}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let response = collect_stream(body)?;
+        assert_eq!(response["model"], "qwen3-32b");
+        assert_eq!(response["choices"][0]["finish_reason"], "tool_calls");
+        let message = openai::response_to_message(response.clone())?;
+        assert_eq!(
+            message.content[0].as_thinking(),
+            Some("The user wants the weather.")
+        );
+        let request = message.content[1].as_tool_request().unwrap();
+        assert_eq!(request.id, "call_1");
+        assert_eq!(
+            request.tool_call.as_ref().unwrap().arguments,
+            json!({ "city": "Hangzhou" })
+        );
+        assert_eq!(openai::get_usage(&response)?.total_tokens, Some(222));
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_stream_rejects_tool_call_out_of_order() {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"function\":{\"name\":\"weather\",\"arguments\":\"{}\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":4294967295,\"id\":\"call_2\",\"function\":{\"name\":\"weather\"}}]}}]}\n\n",
+        );
+        assert!(collect_stream(body).is_err());
+
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":\"weather\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\"}]}}]}\n\n",
+        );
+        assert!(collect_stream(body).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use anyhow::{anyhow, Result};
+use mcp_core::{Content, Role, Tool};
+use serde_json::{json, Value};
+
+/// Render the conversation as the plain text prompt Replicate's language models take
+///
+/// Earlier turns are labelled with their role so the model can follow the conversation;
+/// the model's prompt template wraps the whole of it as the user's turn.
+pub fn format_prompt(messages: &[Message]) -> String {
+    let mut turns = Vec::new();
+    for message in messages {
+        let role = match message.role {
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+        };
+        let text = message
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::Text(text) => Some(text.text.clone()),
+                MessageContent::ToolRequest(request) => request
+                    .tool_call
+                    .as_ref()
+                    .ok()
+                    .map(|call| format!("(called {} with {})", call.name, call.arguments)),
+                MessageContent::ToolResponse(response) => Some(match &response.tool_result {
+                    Ok(contents) => contents
+                        .iter()
+                        .filter_map(|content| match content {
+                            Content::Text(text) => Some(text.text.clone()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    Err(e) => format!("The tool call returned the following error:\n{}", e),
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !text.is_empty() {
+            turns.push((role, text));
+        }
+    }
+
+    // A single user message needs no labels
+    if let [(_, text)] = turns.as_slice() {
+        return text.clone();
+    }
+    turns
+        .iter()
+        .map(|(role, text)| format!("{}: {}", role, text))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Create the request body for a Replicate prediction
+///
+/// A model can be given as `owner/name`, to run its latest version, or as
+/// `owner/name:version` for a specific one. Replicate's language models don't call tools,
+/// so tools are left out.
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    if !tools.is_empty() {
+        tracing::debug!(
+            "Replicate models don't call tools, leaving out {} tools",
+            tools.len()
+        );
+    }
+
+    let mut input = json!({
+        "prompt": format_prompt(messages),
+        "system_prompt": system,
+    });
+    if let Some(temperature) = model_config.temperature {
+        input["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = model_config.max_tokens {
+        input["max_tokens"] = json!(max_tokens);
+    }
+
+    let mut payload = json!({ "input": input });
+    if let Some((_, version)) = model_config.model_name.split_once(':') {
+        payload["version"] = json!(version);
+    }
+    Ok(payload)
+}
+
+/// Convert a finished Replicate prediction to internal Message format
+///
+/// Language models stream their output as a list of tokens, which are joined.
+pub fn response_to_message(prediction: &Value) -> Result<Message> {
+    match prediction["status"].as_str() {
+        Some("succeeded") => {}
+        Some("canceled") => return Err(anyhow!("The prediction was canceled")),
+        status => {
+            return Err(anyhow!(
+                "The prediction did not succeed ({}): {}",
+                status.unwrap_or("unknown"),
+                prediction["error"]
+            ))
+        }
+    }
+
+    let text = match &prediction["output"] {
+        Value::Array(tokens) => tokens
+            .iter()
+            .filter_map(|token| token.as_str())
+            .collect::<String>(),
+        Value::String(text) => text.clone(),
+        output => return Err(anyhow!("Unsupported prediction output: {}", output)),
+    };
+    Ok(Message::assistant().with_text(text.trim()))
+}
+
+/// Extract usage information from a finished Replicate prediction
+pub fn get_usage(prediction: &Value) -> Result<Usage, ProviderError> {
+    let metrics = prediction
+        .get("metrics")
+        .ok_or_else(|| ProviderError::UsageError("No metrics in prediction".to_string()))?;
+
+    let count = |key: &str| metrics.get(key).and_then(|v| v.as_i64()).map(|v| v as i32);
+    let input_tokens = count("input_token_count");
+    let output_tokens = count("output_token_count");
+    let total_tokens = match (input_tokens, output_tokens) {
+        (Some(input), Some(output)) => Some(input.saturating_add(output)),
+        _ => None,
+    };
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prediction_round_trip() -> Result<()> {
+        let messages = vec![
+            Message::user().with_text("Hi"),
+            Message::assistant().with_text("Hello! How can I help?"),
+            Message::user().with_text("Tell me a joke"),
+        ];
+        let model_config = ModelConfig::new("meta/meta-llama-3-8b-instruct:5a6809ca".to_string())
+            .with_max_tokens(Some(256));
+        let payload = create_request(&model_config, "Be brief.", &messages, &[])?;
+        assert_eq!(
+            payload["input"]["prompt"],
+            "User: Hi\n\nAssistant: Hello! How can I help?\n\nUser: Tell me a joke"
+        );
+        assert_eq!(payload["input"]["system_prompt"], "Be brief.");
+        assert_eq!(payload["input"]["max_tokens"], 256);
+        assert_eq!(payload["version"], "5a6809ca");
+
+        let prediction = json!({
+            "id": "gm3qorzdhgbfurvjtvhg6dckhu",
+            "status": "succeeded",
+            "output": ["Why", " did", " the", " chicken", "..."],
+            "metrics": { "input_token_count": 40, "output_token_count": 5 }
+        });
+        let message = response_to_message(&prediction)?;
+        assert_eq!(message.content[0].as_text(), Some("Why did the chicken..."));
+        let usage = get_usage(&prediction)?;
+        assert_eq!(usage.total_tokens, Some(45));
+
+        let failed = json!({ "status": "failed", "error": "CUDA out of memory" });
+        assert!(response_to_message(&failed).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::prompt_template::load_prompt;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai;
+use crate::providers::utils::ImageFormat;
+use anyhow::{anyhow, Result};
+use mcp_core::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+fn default_response_pointer() -> String {
+    // Where text generation inference containers put the output
+    "/0/generated_text".to_string()
+}
+
+/// How goose's requests and responses map to the payloads of a SageMaker endpoint
+///
+/// Without a request template, the endpoint is sent OpenAI chat completion requests, which
+/// the Messages API of the TGI, LMI and vLLM containers accepts. For other containers,
+/// the request body is rendered from a Tera template and the output is read from the
+/// response with JSON pointers:
+///
+/// ```yaml
+/// SAGEMAKER_TEMPLATE:
+///   request: |
+///     {"inputs": {{ messages | last | get(key="content") | json_encode() }},
+///      "parameters": {"max_new_tokens": {{ max_tokens | default(value=512) }}}}
+///   response: /0/generated_text
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SageMakerTemplate {
+    /// Template of the request body, rendered with `system`, `messages` (in the OpenAI
+    /// format, without the system message), `max_tokens` and `temperature`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+    /// JSON pointer to the generated text in the response
+    #[serde(default = "default_response_pointer")]
+    pub response: String,
+    /// JSON pointer to the number of input tokens in the response, if it reports one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens: Option<String>,
+    /// JSON pointer to the number of output tokens in the response, if it reports one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens: Option<String>,
+}
+
+impl Default for SageMakerTemplate {
+    fn default() -> Self {
+        Self {
+            request: None,
+            response: default_response_pointer(),
+            input_tokens: None,
+            output_tokens: None,
+        }
+    }
+}
+
+/// Create the request body for a SageMaker endpoint
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+    template: &SageMakerTemplate,
+) -> Result<Value> {
+    let Some(request) = &template.request else {
+        return openai::create_request(model_config, system, messages, tools, &ImageFormat::OpenAi);
+    };
+
+    if !tools.is_empty() {
+        tracing::debug!(
+            "Tools can't be passed through a request template, leaving out {} tools",
+            tools.len()
+        );
+    }
+    let context = json!({
+        "system": system,
+        "messages": openai::format_messages(messages, &ImageFormat::OpenAi),
+        "max_tokens": model_config.max_tokens,
+        "temperature": model_config.temperature,
+    });
+    let body = load_prompt(request, &context)?;
+    serde_json::from_str(&body)
+        .map_err(|e| anyhow!("The request template did not render valid JSON: {}", e))
+}
+
+/// Convert a SageMaker endpoint's response to internal Message format
+pub fn response_to_message(response: Value, template: &SageMakerTemplate) -> Result<Message> {
+    if template.request.is_none() {
+        return openai::response_to_message(response);
+    }
+
+    let text = response
+        .pointer(&template.response)
+        .and_then(|text| text.as_str())
+        .ok_or_else(|| {
+            anyhow!(
+                "No generated text at {} in the response: {}",
+                template.response,
+                response
+            )
+        })?;
+    Ok(Message::assistant().with_text(text))
+}
+
+/// Extract usage information from a SageMaker endpoint's response
+pub fn get_usage(response: &Value, template: &SageMakerTemplate) -> Result<Usage, ProviderError> {
+    if template.request.is_none() {
+        return openai::get_usage(response);
+    }
+
+    let count = |pointer: &Option<String>| {
+        pointer
+            .as_deref()
+            .and_then(|pointer| response.pointer(pointer))
+            .and_then(|v| v.as_i64())
+            .map(|v| v as i32)
+    };
+    let input_tokens = count(&template.input_tokens);
+    let output_tokens = count(&template.output_tokens);
+    if input_tokens.is_none() && output_tokens.is_none() {
+        return Err(ProviderError::UsageError(
+            "The response template maps no token counts".to_string(),
+        ));
+    }
+    let total_tokens = match (input_tokens, output_tokens) {
+        (Some(input), Some(output)) => Some(input.saturating_add(output)),
+        _ => None,
+    };
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_mapping() -> Result<()> {
+        let template: SageMakerTemplate = serde_yaml::from_str(
+            r#"
+request: |
+  {"inputs": {{ messages | last | get(key="content") | json_encode() }},
+   "parameters": {"max_new_tokens": {{ max_tokens | default(value=512) }}}}
+output_tokens: /0/details/generated_tokens
+"#,
+        )?;
+        assert_eq!(template.response, "/0/generated_text");
+
+        let payload = create_request(
+            &ModelConfig::new("falcon-7b-instruct".to_string()),
+            "system",
+            &[Message::user().with_text("Say \"hi\"")],
+            &[],
+            &template,
+        )?;
+        assert_eq!(
+            payload,
+            json!({ "inputs": "Say \"hi\"", "parameters": { "max_new_tokens": 512 } })
+        );
+
+        let response = json!([{
+            "generated_text": "hi",
+            "details": { "generated_tokens": 1 }
+        }]);
+        let message = response_to_message(response.clone(), &template)?;
+        assert_eq!(message.content[0].as_text(), Some("hi"));
+        let usage = get_usage(&response, &template)?;
+        assert_eq!(usage.output_tokens, Some(1));
+        assert_eq!(usage.input_tokens, None);
+
+        assert!(response_to_message(json!({ "error": "overloaded" }), &template).is_err());
+
+        // Without a request template, requests are OpenAI chat completions
+        let payload = create_request(
+            &ModelConfig::new("llama-3-1-8b-instruct".to_string()),
+            "system",
+            &[Message::user().with_text("Hi")],
+            &[],
+            &SageMakerTemplate::default(),
+        )?;
+        assert_eq!(payload["messages"][1]["content"], "Hi");
+
+        Ok(())
+    }
+}
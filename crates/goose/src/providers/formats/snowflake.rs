@@ -0,0 +1,363 @@
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::utils::{is_valid_function_name, sanitize_function_name};
+use anyhow::{anyhow, Result};
+use mcp_core::{Content, Role, Tool, ToolCall, ToolError};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Convert internal Message format to the Cortex `inference:complete` message specification
+///
+/// Text goes in `content`, tool calls and tool results in `content_list`. A tool result has
+/// to name the tool it answers, which is found from the call with the same id.
+pub fn format_messages(messages: &[Message]) -> Vec<Value> {
+    let mut tool_names = HashMap::new();
+    let mut messages_spec = Vec::new();
+    for message in messages {
+        let role = match message.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+
+        let mut text = Vec::new();
+        let mut content_list = Vec::new();
+        for content in &message.content {
+            match content {
+                MessageContent::Text(t) if !t.text.is_empty() => text.push(t.text.clone()),
+                MessageContent::ToolRequest(request) => {
+                    if let Ok(tool_call) = &request.tool_call {
+                        let name = sanitize_function_name(&tool_call.name);
+                        tool_names.insert(request.id.clone(), name.clone());
+                        content_list.push(json!({
+                            "type": "tool_use",
+                            "tool_use": {
+                                "tool_use_id": request.id,
+                                "name": name,
+                                "input": tool_call.arguments,
+                            }
+                        }));
+                    }
+                }
+                MessageContent::ToolResponse(response) => {
+                    let result = match &response.tool_result {
+                        Ok(contents) => contents
+                            .iter()
+                            // Send only contents with no audience or with Assistant in the audience
+                            .filter(|content| {
+                                content
+                                    .audience()
+                                    .is_none_or(|audience| audience.contains(&Role::Assistant))
+                            })
+                            .filter_map(|content| match content {
+                                Content::Text(t) => Some(t.text.clone()),
+                                Content::Resource(resource) => Some(resource.get_text()),
+                                Content::Image(_) => None,
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                        Err(e) => format!("The tool call returned the following error:\n{}", e),
+                    };
+                    content_list.push(json!({
+                        "type": "tool_results",
+                        "tool_results": {
+                            "tool_use_id": response.id,
+                            "name": tool_names.get(&response.id).cloned().unwrap_or_default(),
+                            "content": [{ "type": "text", "text": result }],
+                        }
+                    }));
+                }
+                // Cortex takes no images in chat, and confirmations stay local
+                _ => continue,
+            }
+        }
+
+        if text.is_empty() && content_list.is_empty() {
+            continue;
+        }
+        let mut converted = json!({ "role": role, "content": text.join("\n") });
+        if !content_list.is_empty() {
+            converted["content_list"] = json!(content_list);
+        }
+        messages_spec.push(converted);
+    }
+
+    messages_spec
+}
+
+/// Convert internal Tool format to the Cortex tool specification
+pub fn format_tools(tools: &[Tool]) -> Result<Vec<Value>> {
+    let mut tool_names = HashSet::new();
+    let mut result = Vec::new();
+
+    for tool in tools {
+        if !tool_names.insert(&tool.name) {
+            return Err(anyhow!("Duplicate tool name: {}", tool.name));
+        }
+
+        result.push(json!({
+            "tool_spec": {
+                "type": "generic",
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": tool.input_schema,
+            }
+        }));
+    }
+
+    Ok(result)
+}
+
+/// Create a complete request payload for the Cortex `inference:complete` API
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut messages_spec = Vec::new();
+    if !system.is_empty() {
+        messages_spec.push(json!({ "role": "system", "content": system }));
+    }
+    messages_spec.extend(format_messages(messages));
+
+    let mut payload = json!({
+        "model": model_config.model_name,
+        "messages": messages_spec,
+    });
+
+    let tools_spec = format_tools(tools)?;
+    if !tools_spec.is_empty() {
+        payload["tools"] = json!(tools_spec);
+    }
+    if let Some(temperature) = model_config.temperature {
+        payload["temperature"] = json!(temperature);
+    }
+    // Cortex stops at 4096 tokens unless asked for more
+    payload["max_tokens"] = json!(model_config.max_tokens.unwrap_or(4096));
+
+    Ok(payload)
+}
+
+/// Collect the server sent event stream Cortex answers with into a single response
+///
+/// Each event carries a `delta` with either a piece of text or a piece of a tool call, whose
+/// `input` arrives as fragments of a JSON string. The pieces are put back together as
+/// `choices[0].message`, in the shape of a non streaming response, and the usage comes
+/// with the last event.
+pub fn collect_stream(body: &str) -> Result<Value> {
+    let mut text = String::new();
+    let mut tool_uses: Vec<Map<String, Value>> = Vec::new();
+    let mut current: Option<Map<String, Value>> = None;
+    let mut usage = Value::Null;
+    let mut model = Value::Null;
+
+    for line in body.lines() {
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            continue;
+        };
+        if data.is_empty() || data == "[DONE]" {
+            continue;
+        }
+        let event: Value = serde_json::from_str(data)
+            .map_err(|e| anyhow!("Invalid event in response stream: {}", e))?;
+
+        let delta = &event["choices"][0]["delta"];
+        match delta["type"].as_str() {
+            Some("tool_use") => {
+                // A new call starts with its id, later pieces only carry more input
+                let id = delta["tool_use_id"].as_str().filter(|id| !id.is_empty());
+                let starts = id.is_some_and(|id| {
+                    current
+                        .as_ref()
+                        .is_none_or(|last| last["tool_use_id"].as_str() != Some(id))
+                });
+                if starts {
+                    tool_uses.extend(current.take());
+                }
+                let tool_use = current.get_or_insert_with(|| {
+                    let mut tool_use = Map::new();
+                    tool_use.insert("tool_use_id".to_string(), json!(id.unwrap_or_default()));
+                    tool_use.insert("name".to_string(), delta["name"].clone());
+                    tool_use.insert("input".to_string(), json!(""));
+                    tool_use
+                });
+                if let Some(piece) = delta["input"].as_str() {
+                    let input = tool_use["input"].as_str().unwrap_or_default();
+                    let input = format!("{}{}", input, piece);
+                    tool_use.insert("input".to_string(), json!(input));
+                }
+            }
+            _ => {
+                if let Some(piece) = delta["content"].as_str().or(delta["text"].as_str()) {
+                    text.push_str(piece);
+                }
+            }
+        }
+        if !event["usage"].is_null() {
+            usage = event["usage"].clone();
+        }
+        if !event["model"].is_null() {
+            model = event["model"].clone();
+        }
+    }
+
+    tool_uses.extend(current);
+    let content_list: Vec<Value> = tool_uses
+        .into_iter()
+        .map(|tool_use| json!({ "type": "tool_use", "tool_use": tool_use }))
+        .collect();
+    Ok(json!({
+        "choices": [{ "message": { "content": text, "content_list": content_list } }],
+        "usage": usage,
+        "model": model,
+    }))
+}
+
+/// Convert a Cortex response, streamed or not, to internal Message format
+pub fn response_to_message(response: Value) -> Result<Message> {
+    let original = response["choices"][0]
+        .get("message")
+        .ok_or_else(|| anyhow!("Invalid response format: missing message"))?;
+    let mut message = Message::assistant();
+
+    let content_list = original["content_list"].as_array();
+    let listed_text = content_list
+        .into_iter()
+        .flatten()
+        .any(|block| block["type"] == "text");
+    if !listed_text {
+        if let Some(text) = original["content"].as_str().filter(|t| !t.is_empty()) {
+            message = message.with_text(text);
+        }
+    }
+
+    for block in content_list.into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => {
+                if let Some(text) = block["text"].as_str().filter(|t| !t.is_empty()) {
+                    message = message.with_text(text);
+                }
+            }
+            Some("tool_use") => {
+                let tool_use = &block["tool_use"];
+                let id = tool_use["tool_use_id"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                let name = tool_use["name"].as_str().unwrap_or_default();
+                // Streamed input is a JSON string, a non streamed one an object
+                let input = match &tool_use["input"] {
+                    Value::String(input) if input.trim().is_empty() => Ok(json!({})),
+                    Value::String(input) => serde_json::from_str::<Value>(input),
+                    Value::Null => Ok(json!({})),
+                    input => Ok(input.clone()),
+                };
+
+                let tool_call = if !is_valid_function_name(name) {
+                    Err(ToolError::NotFound(format!(
+                        "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
+                        name
+                    )))
+                } else {
+                    input
+                        .map(|params| ToolCall::new(name, params))
+                        .map_err(|e| {
+                            ToolError::InvalidParameters(format!(
+                                "Could not interpret tool use parameters for id {}: {}",
+                                id, e
+                            ))
+                        })
+                };
+                message = message.with_tool_request(id, tool_call);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(message)
+}
+
+/// Extract usage information from a Cortex response
+pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
+    let usage = data
+        .get("usage")
+        .filter(|usage| !usage.is_null())
+        .ok_or_else(|| ProviderError::UsageError("No usage data in response".to_string()))?;
+
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_f64()).map(|v| v as i32);
+    let input_tokens = count("prompt_tokens");
+    let output_tokens = count("completion_tokens");
+    let total_tokens = count("total_tokens").or(match (input_tokens, output_tokens) {
+        (Some(input), Some(output)) => Some(input.saturating_add(output)),
+        _ => None,
+    });
+
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_tool_round_trip() -> Result<()> {
+        let body = [
+            r#"data: {"id":"a1","model":"claude-3-5-sonnet","choices":[{"delta":{"type":"text","content":"Let me check."}}]}"#,
+            r#"data: {"id":"a1","choices":[{"delta":{"type":"tool_use","tool_use_id":"tooluse_1","name":"weather","input":""}}]}"#,
+            r#"data: {"id":"a1","choices":[{"delta":{"type":"tool_use","input":"{\"location\":"}}]}"#,
+            r#"data: {"id":"a1","choices":[{"delta":{"type":"tool_use","input":"\"Toronto\"}"}}]}"#,
+            r#"data: {"id":"a1","choices":[{"delta":{}}],"usage":{"prompt_tokens":40,"completion_tokens":12,"total_tokens":52}}"#,
+        ]
+        .join("\n\n");
+
+        let response = collect_stream(&body)?;
+        assert_eq!(response["model"], "claude-3-5-sonnet");
+        let message = response_to_message(response.clone())?;
+        assert_eq!(message.content[0].as_text(), Some("Let me check."));
+        let request = message.content[1].as_tool_request().unwrap();
+        assert_eq!(request.id, "tooluse_1");
+        let tool_call = request.tool_call.as_ref().unwrap();
+        assert_eq!(tool_call.name, "weather");
+        assert_eq!(tool_call.arguments, json!({ "location": "Toronto" }));
+        assert_eq!(get_usage(&response)?.total_tokens, Some(52));
+
+        // The result goes back naming the tool it answers
+        let messages = vec![
+            Message::user().with_text("What's the weather in Toronto?"),
+            message,
+            Message::user().with_tool_response("tooluse_1", Ok(vec![Content::text("Sunny")])),
+        ];
+        let spec = format_messages(&messages);
+        assert_eq!(spec.len(), 3);
+        assert_eq!(spec[1]["content"], "Let me check.");
+        let tool_use = &spec[1]["content_list"][0]["tool_use"];
+        assert_eq!(tool_use["input"], json!({ "location": "Toronto" }));
+        let tool_results = &spec[2]["content_list"][0]["tool_results"];
+        assert_eq!(tool_results["tool_use_id"], "tooluse_1");
+        assert_eq!(tool_results["name"], "weather");
+        assert_eq!(tool_results["content"][0]["text"], "Sunny");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_tool_uses_without_a_start() -> Result<()> {
+        // Input arriving before any tool use started goes to an unnamed one
+        let body = [
+            r#"data: {"choices":[{"delta":{"type":"tool_use","input":"{}"}}]}"#,
+            r#"data: {"choices":[{"delta":{"type":"tool_use","tool_use_id":"tooluse_2","name":"weather","input":"{}"}}]}"#,
+        ]
+        .join("\n\n");
+
+        let response = collect_stream(&body)?;
+        let content_list = response["choices"][0]["message"]["content_list"]
+            .as_array()
+            .unwrap();
+        assert_eq!(content_list.len(), 2);
+        assert_eq!(content_list[0]["tool_use"]["tool_use_id"], "");
+        assert_eq!(content_list[1]["tool_use"]["tool_use_id"], "tooluse_2");
+        Ok(())
+    }
+}
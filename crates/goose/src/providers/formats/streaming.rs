@@ -0,0 +1,229 @@
+use crate::message::{Message, MessageContent};
+use crate::providers::base::{MessageDelta, MessageStream, ProviderUsage, Usage};
+use crate::providers::errors::ProviderError;
+use crate::providers::utils::is_valid_function_name;
+use futures::StreamExt;
+use mcp_core::{ToolCall, ToolError};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    id: Option<String>,
+    name: Option<String>,
+    arguments: String,
+}
+
+#[derive(Debug)]
+enum Part {
+    Text(String),
+    Thinking(String),
+    /// The index of a tool call in `MessageAssembler::tool_calls`
+    ToolCall(usize),
+    InvalidToolCall(String, ToolError),
+}
+
+/// Assembles a message from the deltas `Provider::stream` returns
+///
+/// Text and thinking are appended as they come, while tool calls are put together from their
+/// fragments and only checked once the message is done, as their arguments are incomplete
+/// JSON until then. The parts of the message keep the order they started in.
+#[derive(Debug, Default)]
+pub struct MessageAssembler {
+    parts: Vec<Part>,
+    tool_calls: BTreeMap<usize, PartialToolCall>,
+    usage: Option<ProviderUsage>,
+}
+
+impl MessageAssembler {
+    pub fn push(&mut self, delta: MessageDelta) {
+        match delta {
+            MessageDelta::Text(text) => match self.parts.last_mut() {
+                Some(Part::Text(existing)) => existing.push_str(&text),
+                _ => self.parts.push(Part::Text(text)),
+            },
+            MessageDelta::Thinking(thinking) => match self.parts.last_mut() {
+                Some(Part::Thinking(existing)) => existing.push_str(&thinking),
+                _ => self.parts.push(Part::Thinking(thinking)),
+            },
+            MessageDelta::ToolCall {
+                index,
+                id,
+                name,
+                arguments,
+            } => {
+                let call = self.tool_calls.entry(index).or_insert_with(|| {
+                    self.parts.push(Part::ToolCall(index));
+                    PartialToolCall::default()
+                });
+                // Later fragments leave out what the first one had
+                call.id = id.or(call.id.take());
+                call.name = name.or(call.name.take());
+                call.arguments.push_str(&arguments);
+            }
+            MessageDelta::InvalidToolCall { id, error } => {
+                self.parts.push(Part::InvalidToolCall(id, error))
+            }
+            MessageDelta::Usage(usage) => self.usage = Some(usage),
+        }
+    }
+
+    /// The message so far, with the tool calls checked
+    pub fn message(&self) -> Message {
+        let mut message = Message::assistant();
+        for part in &self.parts {
+            let content = match part {
+                Part::Text(text) => MessageContent::text(text),
+                Part::Thinking(thinking) => MessageContent::thinking(thinking),
+                Part::ToolCall(index) => {
+                    let call = &self.tool_calls[index];
+                    let id = call.id.clone().unwrap_or_else(|| format!("call_{}", index));
+                    MessageContent::tool_request(id.clone(), to_tool_call(&id, call))
+                }
+                Part::InvalidToolCall(id, error) => {
+                    MessageContent::tool_request(id, Err(error.clone()))
+                }
+            };
+            message = message.with_content(content);
+        }
+        message
+    }
+
+    /// The assembled message and its usage
+    pub fn finish(self, model: &str) -> (Message, ProviderUsage) {
+        let message = self.message();
+        let usage = self
+            .usage
+            .unwrap_or_else(|| ProviderUsage::new(model.to_string(), Usage::default()));
+        (message, usage)
+    }
+}
+
+fn to_tool_call(id: &str, call: &PartialToolCall) -> Result<ToolCall, ToolError> {
+    let name = call.name.as_deref().unwrap_or_default();
+    if !is_valid_function_name(name) {
+        return Err(ToolError::NotFound(format!(
+            "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
+            name
+        )));
+    }
+    // Calls without parameters can come without arguments
+    let arguments = if call.arguments.trim().is_empty() {
+        "{}"
+    } else {
+        &call.arguments
+    };
+    match serde_json::from_str::<Value>(arguments) {
+        Ok(arguments) => Ok(ToolCall::new(name, arguments)),
+        Err(e) => Err(ToolError::InvalidParameters(format!(
+            "Could not interpret tool use parameters for id {}: {}",
+            id, e
+        ))),
+    }
+}
+
+/// Pass the deltas of a stream through, calling `on_end` with the assembled message once
+/// the stream ends, or with the error it ends on
+///
+/// Provider wrappers use this to do with a streamed message what they do with a completed
+/// one. A stream dropped before its end, such as a cancelled one, never calls `on_end`.
+pub fn inspect_stream<'a, F>(
+    mut stream: MessageStream<'a>,
+    model: String,
+    on_end: F,
+) -> MessageStream<'a>
+where
+    F: FnOnce(Result<&(Message, ProviderUsage), &ProviderError>) + Send + 'a,
+{
+    Box::pin(async_stream::stream! {
+        let mut assembler = MessageAssembler::default();
+        while let Some(delta) = stream.next().await {
+            match delta {
+                Ok(delta) => {
+                    assembler.push(delta.clone());
+                    yield Ok(delta);
+                }
+                Err(error) => {
+                    on_end(Err(&error));
+                    yield Err(error);
+                    return;
+                }
+            }
+        }
+        on_end(Ok(&assembler.finish(&model)));
+    })
+}
+
+/// Wait for the first delta of a stream, so an error the stream starts with is returned as
+/// the error of the request, before anything was streamed
+pub async fn start_stream(
+    mut stream: MessageStream<'_>,
+) -> Result<MessageStream<'_>, ProviderError> {
+    match stream.next().await {
+        Some(Err(error)) => Err(error),
+        Some(Ok(first)) => Ok(Box::pin(
+            futures::stream::once(async { Ok(first) }).chain(stream),
+        )),
+        None => Ok(stream),
+    }
+}
+
+/// Read a stream from `Provider::stream` to its end, assembling the message
+pub async fn collect_stream(
+    mut stream: MessageStream<'_>,
+    model: &str,
+) -> Result<(Message, ProviderUsage), ProviderError> {
+    let mut assembler = MessageAssembler::default();
+    while let Some(delta) = stream.next().await {
+        assembler.push(delta?);
+    }
+    Ok(assembler.finish(model))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_assembles_tool_calls_from_fragments() {
+        let mut assembler = MessageAssembler::default();
+        let fragment =
+            |index, id: Option<&str>, name: Option<&str>, arguments: &str| MessageDelta::ToolCall {
+                index,
+                id: id.map(String::from),
+                name: name.map(String::from),
+                arguments: arguments.to_string(),
+            };
+        for delta in [
+            MessageDelta::Text("Let me ".to_string()),
+            MessageDelta::Text("check.".to_string()),
+            fragment(0, Some("call_a"), Some("shell"), ""),
+            fragment(1, Some("call_b"), Some("read_file"), "{\"path\":"),
+            fragment(0, None, None, "{\"command\":"),
+            fragment(0, None, None, " \"ls\"}"),
+            fragment(1, None, None, " \"README"),
+            fragment(2, Some("call_c"), Some("bad name"), "{}"),
+        ] {
+            assembler.push(delta);
+        }
+
+        let (message, usage) = assembler.finish("mock");
+        assert_eq!(usage.model, "mock");
+        assert_eq!(message.as_concat_text(), "Let me check.");
+
+        let first = message.content[1].as_tool_request().unwrap();
+        assert_eq!(first.id, "call_a");
+        let call = first.tool_call.as_ref().unwrap();
+        assert_eq!(call.arguments, json!({"command": "ls"}));
+
+        // The arguments were cut off
+        let second = message.content[2].as_tool_request().unwrap();
+        assert!(matches!(
+            second.tool_call,
+            Err(ToolError::InvalidParameters(_))
+        ));
+        let third = message.content[3].as_tool_request().unwrap();
+        assert!(matches!(third.tool_call, Err(ToolError::NotFound(_))));
+    }
+}
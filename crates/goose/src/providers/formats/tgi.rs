@@ -0,0 +1,80 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::chat_template::{self, ChatTemplate};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// Create a request payload for TGI's native `/generate` endpoint
+///
+/// The endpoint takes a raw prompt, rendered with `chat_template::render_prompt`. The end of
+/// turn of the template is a stop sequence, so generation ends with the assistant's turn.
+pub fn create_generate_request(
+    model_config: &ModelConfig,
+    template: ChatTemplate,
+    prompt: &str,
+) -> Value {
+    let mut parameters = json!({
+        "details": true,
+        "return_full_text": false,
+        "stop": [template.end_of_turn()],
+    });
+    if let Some(temperature) = model_config.temperature {
+        parameters["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = model_config.max_tokens {
+        parameters["max_new_tokens"] = json!(max_tokens);
+    }
+    json!({ "inputs": prompt, "parameters": parameters })
+}
+
+/// Convert a response from TGI's `/generate` endpoint to internal Message format
+pub fn generate_to_message(response: &Value, template: ChatTemplate) -> Result<Message> {
+    let text = response
+        .get("generated_text")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| anyhow!("Invalid response format: missing generated_text"))?;
+    // TGI keeps the stop sequence it stopped at
+    let text = text.strip_suffix(template.end_of_turn()).unwrap_or(text);
+    chat_template::response_to_message(text)
+}
+
+/// Extract usage information from a response of TGI's `/generate` endpoint
+///
+/// Only the generated tokens are counted in the details; the prompt's tokens would take
+/// `decoder_input_details`, which returns every one of them.
+pub fn get_generate_usage(response: &Value) -> Result<Usage, ProviderError> {
+    let output_tokens = response["details"]["generated_tokens"]
+        .as_i64()
+        .map(|v| v as i32)
+        .ok_or_else(|| ProviderError::UsageError("No token counts in response".to_string()))?;
+    Ok(Usage::new(None, Some(output_tokens), None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_round_trip() -> Result<()> {
+        let model_config = ModelConfig::new("tgi".to_string()).with_max_tokens(Some(256));
+        let payload =
+            create_generate_request(&model_config, ChatTemplate::Llama3, "<|begin_of_text|>");
+        assert_eq!(payload["inputs"], "<|begin_of_text|>");
+        assert_eq!(payload["parameters"]["max_new_tokens"], 256);
+        assert_eq!(payload["parameters"]["stop"][0], "<|eot_id|>");
+
+        let response = json!({
+            "generated_text": "Checking.\n<tool_call>\n{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"ls\"}}\n</tool_call><|eot_id|>",
+            "details": { "finish_reason": "stop_sequence", "generated_tokens": 31, "seed": null }
+        });
+        let message = generate_to_message(&response, ChatTemplate::Llama3)?;
+        assert_eq!(message.as_concat_text(), "Checking.");
+        let request = message.content[1].as_tool_request().unwrap();
+        assert_eq!(request.tool_call.as_ref().unwrap().name, "developer__shell");
+        assert_eq!(get_generate_usage(&response)?.output_tokens, Some(31));
+
+        Ok(())
+    }
+}
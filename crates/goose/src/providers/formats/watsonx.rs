@@ -0,0 +1,114 @@
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::Usage;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai;
+use crate::providers::utils::ImageFormat;
+use anyhow::Result;
+use mcp_core::Tool;
+use serde_json::{json, Value};
+
+/// The watsonx.ai model id for a model name
+///
+/// watsonx ids are namespaced by provider and spell versions with hyphens, e.g.
+/// `meta-llama/llama-3-3-70b-instruct`. Full ids are used as they are, and bare names of the
+/// Granite, Llama and Mistral models hosted there are mapped to their ids, so
+/// `llama-3.3-70b-instruct` works too.
+pub fn model_id(model_name: &str) -> String {
+    if model_name.contains('/') {
+        return model_name.to_string();
+    }
+
+    let name = model_name.to_lowercase().replace('.', "-");
+    let namespace = if name.starts_with("granite") {
+        "ibm"
+    } else if name.starts_with("llama") {
+        "meta-llama"
+    } else if name.starts_with("mistral") || name.starts_with("mixtral") {
+        "mistralai"
+    } else {
+        return model_name.to_string();
+    };
+    format!("{}/{}", namespace, name)
+}
+
+/// Create a request payload for watsonx.ai's `text/chat` API
+///
+/// The messages and tools follow the OpenAI schema, but the model is given as `model_id`
+/// and every request is scoped to a project.
+pub fn create_request(
+    model_config: &ModelConfig,
+    project_id: &str,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut payload =
+        openai::create_request(model_config, system, messages, tools, &ImageFormat::OpenAi)?;
+    if let Some(payload) = payload.as_object_mut() {
+        payload.remove("model");
+        // watsonx only knows max_tokens, whatever the model family
+        if let Some(max_tokens) = payload.remove("max_completion_tokens") {
+            payload.insert("max_tokens".to_string(), max_tokens);
+        }
+        payload.insert(
+            "model_id".to_string(),
+            json!(model_id(&model_config.model_name)),
+        );
+        payload.insert("project_id".to_string(), json!(project_id));
+    }
+    Ok(payload)
+}
+
+/// Convert a watsonx.ai chat response to internal Message format
+pub fn response_to_message(response: Value) -> Result<Message> {
+    openai::response_to_message(response)
+}
+
+/// Extract usage information from a watsonx.ai chat response
+pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
+    openai::get_usage(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_id() {
+        assert_eq!(
+            model_id("ibm/granite-3-8b-instruct"),
+            "ibm/granite-3-8b-instruct"
+        );
+        assert_eq!(
+            model_id("granite-3.3-8b-instruct"),
+            "ibm/granite-3-3-8b-instruct"
+        );
+        assert_eq!(
+            model_id("llama-3.3-70b-instruct"),
+            "meta-llama/llama-3-3-70b-instruct"
+        );
+        assert_eq!(model_id("mistral-large"), "mistralai/mistral-large");
+        assert_eq!(model_id("my-tuned-model"), "my-tuned-model");
+    }
+
+    #[test]
+    fn test_create_request() -> Result<()> {
+        let model_config =
+            ModelConfig::new("granite-3.3-8b-instruct".to_string()).with_max_tokens(Some(512));
+        let payload = create_request(
+            &model_config,
+            "b7e4b1c6",
+            "system",
+            &[Message::user().with_text("Hi")],
+            &[],
+        )?;
+
+        assert!(payload.get("model").is_none());
+        assert_eq!(payload["model_id"], "ibm/granite-3-3-8b-instruct");
+        assert_eq!(payload["project_id"], "b7e4b1c6");
+        assert_eq!(payload["max_tokens"], 512);
+        assert_eq!(payload["messages"][1]["content"], "Hi");
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex as TokioMutex;
+
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+// Tokens are refreshed a little before they expire, so none expires mid request
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+fn default_token_uri() -> String {
+    DEFAULT_TOKEN_URI.to_string()
+}
+
+/// Credentials for Google Cloud APIs, as found by application default credentials
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GcpCredentials {
+    /// A service account key file
+    ServiceAccount {
+        client_email: String,
+        private_key: String,
+        #[serde(default = "default_token_uri")]
+        token_uri: String,
+    },
+    /// The user signed in with `gcloud auth application-default login`
+    AuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+        #[serde(default = "default_token_uri")]
+        token_uri: String,
+    },
+    /// The service account attached to the machine goose runs on, e.g. on Compute Engine
+    #[serde(skip)]
+    MetadataServer,
+}
+
+impl GcpCredentials {
+    /// Find credentials the way Google's client libraries do: the key file named by
+    /// `GOOGLE_APPLICATION_CREDENTIALS` if set, then the one written by
+    /// `gcloud auth application-default login`, then the metadata server.
+    pub fn application_default(credentials_path: Option<&str>) -> Result<Self> {
+        if let Some(path) = credentials_path {
+            return Self::from_file(Path::new(path));
+        }
+        if let Some(path) = gcloud_credentials_path().filter(|path| path.exists()) {
+            return Self::from_file(&path);
+        }
+        Ok(Self::MetadataServer)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read credentials from {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Unsupported credentials in {}", path.display()))
+    }
+}
+
+fn gcloud_credentials_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var("CLOUDSDK_CONFIG") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) if cfg!(windows) => PathBuf::from(std::env::var("APPDATA").ok()?).join("gcloud"),
+        Err(_) => etcetera::home_dir().ok()?.join(".config").join("gcloud"),
+    };
+    Some(config_dir.join("application_default_credentials.json"))
+}
+
+#[derive(Debug, Clone)]
+struct AccessToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct JwtClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+/// Access tokens for Google Cloud APIs, cached until shortly before they expire
+pub struct GcpAuth {
+    client: Client,
+    credentials: GcpCredentials,
+    token: TokioMutex<Option<AccessToken>>,
+}
+
+impl GcpAuth {
+    pub fn new(credentials: GcpCredentials) -> Self {
+        Self {
+            client: Client::new(),
+            credentials,
+            token: TokioMutex::new(None),
+        }
+    }
+
+    /// A valid access token, fetching a new one when the cached one is about to expire
+    pub async fn token(&self) -> Result<String> {
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if token.expires_at - Duration::seconds(EXPIRY_MARGIN_SECS) > Utc::now() {
+                return Ok(token.token.clone());
+            }
+        }
+
+        let token = self.fetch_token().await?;
+        let access_token = token.token.clone();
+        *cached = Some(token);
+        Ok(access_token)
+    }
+
+    async fn fetch_token(&self) -> Result<AccessToken> {
+        let response = match &self.credentials {
+            GcpCredentials::ServiceAccount {
+                client_email,
+                private_key,
+                token_uri,
+            } => {
+                let now = Utc::now().timestamp();
+                let claims = JwtClaims {
+                    iss: client_email,
+                    scope: CLOUD_PLATFORM_SCOPE,
+                    aud: token_uri,
+                    iat: now,
+                    exp: now + 3600,
+                };
+                let key = EncodingKey::from_rsa_pem(private_key.as_bytes())
+                    .context("Invalid private key in the service account credentials")?;
+                let assertion =
+                    jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)?;
+                self.client
+                    .post(token_uri)
+                    .form(&[
+                        ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                        ("assertion", &assertion),
+                    ])
+                    .send()
+                    .await?
+            }
+            GcpCredentials::AuthorizedUser {
+                client_id,
+                client_secret,
+                refresh_token,
+                token_uri,
+            } => {
+                self.client
+                    .post(token_uri)
+                    .form(&[
+                        ("grant_type", "refresh_token"),
+                        ("client_id", client_id),
+                        ("client_secret", client_secret),
+                        ("refresh_token", refresh_token),
+                    ])
+                    .send()
+                    .await?
+            }
+            GcpCredentials::MetadataServer => {
+                self.client
+                    .get(METADATA_TOKEN_URL)
+                    .header("Metadata-Flavor", "Google")
+                    .send()
+                    .await
+                    .context("No Google Cloud credentials found, set GOOGLE_APPLICATION_CREDENTIALS or run `gcloud auth application-default login`")?
+            }
+        };
+
+        let status = response.status();
+        let body: Value = response.json().await?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Failed to get a Google Cloud access token ({}): {}",
+                status,
+                body
+            ));
+        }
+
+        let token = body["access_token"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No access token in response: {}", body))?
+            .to_string();
+        let expires_in = body["expires_in"].as_i64().unwrap_or(3600);
+        Ok(AccessToken {
+            token,
+            expires_at: Utc::now() + Duration::seconds(expires_in),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_credentials_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("credentials.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "type": "authorized_user",
+                "client_id": "id.apps.googleusercontent.com",
+                "client_secret": "secret",
+                "refresh_token": "refresh"
+            }"#,
+        )?;
+
+        let credentials = GcpCredentials::application_default(path.to_str())?;
+        assert!(matches!(
+            credentials,
+            GcpCredentials::AuthorizedUser { ref token_uri, .. } if token_uri == DEFAULT_TOKEN_URI
+        ));
+
+        std::fs::write(&path, r#"{"type": "external_account"}"#)?;
+        assert!(GcpCredentials::from_file(&path).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_token_is_cached() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("grant_type=refresh_token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "ya29.token",
+                "expires_in": 3599,
+                "token_type": "Bearer"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let auth = GcpAuth::new(GcpCredentials::AuthorizedUser {
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+            refresh_token: "refresh".to_string(),
+            token_uri: format!("{}/token", server.uri()),
+        });
+        assert_eq!(auth.token().await?, "ya29.token");
+        assert_eq!(auth.token().await?, "ya29.token");
+        Ok(())
+    }
+}
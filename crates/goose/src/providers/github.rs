@@ -0,0 +1,272 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const GITHUB_MODELS_HOST: &str = "https://models.github.ai";
+pub const GITHUB_MODELS_DEFAULT_MODEL: &str = "openai/gpt-4.1";
+// The catalog changes often, see `GitHubModelsProvider::fetch_metadata`
+pub const GITHUB_MODELS_KNOWN_MODELS: &[&str] = &[
+    "openai/gpt-4.1",
+    "openai/gpt-4.1-mini",
+    "openai/gpt-4o",
+    "openai/gpt-4o-mini",
+    "meta/llama-4-maverick-17b-128e-instruct-fp8",
+    "mistral-ai/mistral-medium-2505",
+    "deepseek/deepseek-v3-0324",
+];
+
+pub const GITHUB_MODELS_DOC_URL: &str = "https://github.com/marketplace?type=models";
+
+const GITHUB_API_VERSION: &str = "2022-11-28";
+
+/// Models hosted by GitHub, authenticated with a personal access token with the
+/// `models:read` permission
+///
+/// The free tier limits requests per minute and per day for each model, and the size of
+/// each request. A day's limit is reported as an exceeded quota rather than a rate limit,
+/// since retrying won't help until the next day.
+#[derive(serde::Serialize)]
+pub struct GitHubModelsProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    token: String,
+    /// Bill an organization instead of the owner of the token
+    organization: Option<String>,
+    model: ModelConfig,
+}
+
+impl Default for GitHubModelsProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(GitHubModelsProvider::metadata().default_model);
+        GitHubModelsProvider::from_env(model).expect("Failed to initialize GitHub Models provider")
+    }
+}
+
+impl GitHubModelsProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let token: String = config.get_secret("GITHUB_TOKEN")?;
+        let host: String = config
+            .get("GITHUB_MODELS_HOST")
+            .unwrap_or_else(|_| GITHUB_MODELS_HOST.to_string());
+        let organization: Option<String> = config.get("GITHUB_MODELS_ORG").ok();
+
+        let client = http_client("GITHUB_MODELS")?;
+
+        Ok(Self {
+            client,
+            host,
+            token,
+            organization,
+            model,
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<Url, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        base_url.join(path).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .bearer_auth(&self.token)
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", GITHUB_API_VERSION)
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let path = match &self.organization {
+            Some(org) => format!("orgs/{}/inference/chat/completions", org),
+            None => "inference/chat/completions".to_string(),
+        };
+        let url = self.url(&path)?;
+        let request = self.authorize(self.client.post(url)).json(&payload);
+        let response = retry::send(request).await?;
+
+        handle_response(response).await
+    }
+
+    /// The models in the GitHub Models catalog that can call tools
+    pub async fn fetch_models(&self) -> Result<Vec<String>, ProviderError> {
+        let url = self.url("catalog/models")?;
+        let response = self.authorize(self.client.get(url)).send().await?;
+        let response = handle_response(response).await?;
+
+        let models = response
+            .as_array()
+            .ok_or_else(|| ProviderError::RequestFailed("Catalog is not a list".to_string()))?
+            .iter()
+            .filter(|model| {
+                model["capabilities"]
+                    .as_array()
+                    .is_some_and(|capabilities| capabilities.iter().any(|c| c == "tool-calling"))
+            })
+            .filter_map(|model| model["id"].as_str())
+            .map(String::from)
+            .collect();
+        Ok(models)
+    }
+
+    /// The provider metadata with the catalog's models as the known models; the static
+    /// metadata when the catalog can't be read
+    pub async fn fetch_metadata() -> ProviderMetadata {
+        let mut metadata = Self::metadata();
+        let models = match Self::from_env(ModelConfig::new(metadata.default_model.clone())) {
+            Ok(provider) => provider.fetch_models().await,
+            Err(e) => Err(ProviderError::RequestFailed(e.to_string())),
+        };
+        match models {
+            Ok(models) if !models.is_empty() => metadata.known_models = models,
+            Ok(_) => tracing::debug!("The GitHub Models catalog has no tool calling models"),
+            Err(e) => tracing::debug!("Failed to read the GitHub Models catalog: {}", e),
+        }
+        metadata
+    }
+}
+
+/// Map GitHub's limits onto provider errors: a request over the tier's token limit is too
+/// long for the context, and a daily limit is a quota rather than a rate limit
+async fn handle_response(response: Response) -> Result<Value, ProviderError> {
+    let status = response.status();
+    if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::PAYLOAD_TOO_LARGE {
+        return handle_response_openai_compat(response).await;
+    }
+
+    let wait = retry::retry_after(response.headers()).map(|wait| wait.as_secs());
+    let limit_type = response
+        .headers()
+        .get("x-ratelimit-type")
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let payload: Value = response.json().await.unwrap_or_default();
+    let message = payload["error"]["message"]
+        .as_str()
+        .unwrap_or("Unknown error")
+        .to_string();
+    Err(limit_error(status, &message, limit_type.as_deref(), wait))
+}
+
+fn limit_error(
+    status: StatusCode,
+    message: &str,
+    limit_type: Option<&str>,
+    retry_after: Option<u64>,
+) -> ProviderError {
+    if status == StatusCode::PAYLOAD_TOO_LARGE {
+        return ProviderError::ContextLengthExceeded(message.to_string());
+    }
+    let message = match retry_after {
+        Some(seconds) => format!("{} (retry after {}s)", message, seconds),
+        None => message.to_string(),
+    };
+    let daily = limit_type.is_some_and(|t| t.contains("ByDay")) || message.contains("86400s");
+    if daily {
+        ProviderError::QuotaExceeded(message)
+    } else {
+        ProviderError::RateLimitExceeded(message)
+    }
+}
+
+#[async_trait]
+impl Provider for GitHubModelsProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "github_models",
+            "GitHub Models",
+            "Models from OpenAI, Meta, Mistral and others hosted by GitHub",
+            GITHUB_MODELS_DEFAULT_MODEL,
+            GITHUB_MODELS_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            GITHUB_MODELS_DOC_URL,
+            vec![
+                ConfigKey::new("GITHUB_TOKEN", true, true, None),
+                ConfigKey::new("GITHUB_MODELS_ORG", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        let response = vcr::post("github_models", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_errors() {
+        let error = limit_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Rate limit of 150 per 86400s exceeded for UserByModelByDay.",
+            Some("UserByModelByDay"),
+            Some(3600),
+        );
+        assert!(
+            matches!(error, ProviderError::QuotaExceeded(m) if m.ends_with("(retry after 3600s)"))
+        );
+
+        let error = limit_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Rate limit of 15 per 60s exceeded for UserByModelByMinute.",
+            Some("UserByModelByMinute"),
+            None,
+        );
+        assert!(matches!(error, ProviderError::RateLimitExceeded(_)));
+
+        let error = limit_error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Request body too large for gpt-4o model. Max size: 8000 tokens.",
+            None,
+            None,
+        );
+        assert!(matches!(error, ProviderError::ContextLengthExceeded(_)));
+    }
+}
@@ -1,17 +1,30 @@
 use super::errors::ProviderError;
+use super::google_live::GoogleLiveSession;
+use super::keys::KeyPool;
+use super::{retry, vcr};
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
-use crate::providers::formats::google::{create_request, get_usage, response_to_message};
+use crate::providers::base::{
+    message_deltas, ConfigKey, MessageDelta, MessageStream, Provider, ProviderMetadata,
+    ProviderUsage, Usage,
+};
+use crate::providers::formats::google::{
+    create_cached_content, create_live_setup, create_request, get_usage, response_chunk_to_deltas,
+    response_to_message, use_cached_content,
+};
 use crate::providers::utils::{
-    emit_debug_trace, handle_response_google_compat, unescape_json_values,
+    emit_debug_trace, handle_response_google_compat, http_client, sse_data, unescape_json_values,
 };
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::StreamExt;
 use mcp_core::tool::Tool;
-use reqwest::{Client, StatusCode};
+use reqwest::{Client, Response, StatusCode};
 use serde_json::Value;
-use std::time::Duration;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use url::Url;
 
 pub const GOOGLE_API_HOST: &str = "https://generativelanguage.googleapis.com";
@@ -28,14 +41,70 @@ pub const GOOGLE_KNOWN_MODELS: &[&str] = &[
 ];
 
 pub const GOOGLE_DOC_URL: &str = "https://ai.google/get-started/our-models/";
+pub const GOOGLE_CONTEXT_CACHE_TTL_SECS: u64 = 3600;
+
+/// Keeps the system instruction and tools of big requests as `cachedContents`
+///
+/// Only a system instruction and tools of at least `min_tokens` are cached, since the API
+/// won't cache less than a few thousand tokens and small ones aren't worth it. Each cache is
+/// used until shortly before its TTL runs out, then created again.
+#[derive(Debug)]
+struct ContextCache {
+    min_tokens: usize,
+    ttl: Duration,
+    /// Cached content names and when to stop using them, by hash of what they hold
+    entries: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl ContextCache {
+    fn from_config(config: &crate::config::Config) -> Option<Self> {
+        let min_tokens: usize = config.get("GOOGLE_CONTEXT_CACHE_MIN_TOKENS").ok()?;
+        let ttl_secs: u64 = config
+            .get("GOOGLE_CONTEXT_CACHE_TTL_SECS")
+            .unwrap_or(GOOGLE_CONTEXT_CACHE_TTL_SECS);
+        Some(Self {
+            min_tokens,
+            ttl: Duration::from_secs(ttl_secs),
+            entries: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn key(model: &ModelConfig, system: &str, tools: &[Tool]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(model.model_name.as_bytes());
+        hasher.update(system.as_bytes());
+        hasher.update(serde_json::to_string(tools).unwrap_or_default().as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((name, until)) if *until > Instant::now() => Some(name.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: String, name: String) {
+        // Stop using it a little early, so a request never refers to an expired cache
+        let until = Instant::now() + self.ttl.mul_f64(0.9);
+        self.entries.lock().unwrap().insert(key, (name, until));
+    }
+}
 
 #[derive(Debug, serde::Serialize)]
 pub struct GoogleProvider {
     #[serde(skip)]
     client: Client,
     host: String,
-    api_key: String,
+    api_keys: KeyPool,
     model: ModelConfig,
+    #[serde(skip)]
+    context_cache: Option<ContextCache>,
 }
 
 impl Default for GoogleProvider {
@@ -48,74 +117,134 @@ impl Default for GoogleProvider {
 impl GoogleProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
         let config = crate::config::Config::global();
-        let api_key: String = config.get_secret("GOOGLE_API_KEY")?;
+        let api_keys = KeyPool::from_secret("GOOGLE_API_KEY")?;
         let host: String = config
             .get("GOOGLE_HOST")
             .unwrap_or_else(|_| GOOGLE_API_HOST.to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = http_client("GOOGLE")?;
+        let context_cache = ContextCache::from_config(config);
 
         Ok(Self {
             client,
             host,
-            api_key,
+            api_keys,
             model,
+            context_cache,
+        })
+    }
+
+    /// Open a session with the Live API, for replies streamed as they are generated
+    ///
+    /// The model has to be one the Live API serves, such as `gemini-2.0-flash-live-001`.
+    pub async fn connect_live(
+        &self,
+        system: &str,
+        tools: &[Tool],
+    ) -> Result<GoogleLiveSession, ProviderError> {
+        let setup = create_live_setup(&self.model, system, tools);
+        let api_key = self.api_keys.key();
+        GoogleLiveSession::connect(&self.host, &api_key, &self.model.model_name, setup).await
+    }
+
+    /// Create the request payload, referring to cached content for a big system and tools
+    async fn request(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<Value, ProviderError> {
+        let mut payload = create_request(&self.model, system, messages, tools)?;
+        let Some(cache) = &self.context_cache else {
+            return Ok(payload);
+        };
+        // Recordings have to match from run to run, which cache names don't
+        if vcr::is_active()
+            || self
+                .model
+                .token_counter()
+                .count_chat_tokens(system, &[], tools)
+                < cache.min_tokens
+        {
+            return Ok(payload);
+        }
+
+        let key = ContextCache::key(&self.model, system, tools);
+        let name = match cache.get(&key) {
+            Some(name) => name,
+            None => match self.create_cached_content(system, tools, cache.ttl).await {
+                Ok(name) => {
+                    cache.insert(key, name.clone());
+                    name
+                }
+                // The request still works without the cache, it just costs more
+                Err(e) => {
+                    tracing::warn!("Failed to create cached content: {}", e);
+                    return Ok(payload);
+                }
+            },
+        };
+        use_cached_content(&mut payload, &name);
+        Ok(payload)
+    }
+
+    /// Upload the system instruction and tools as cached content, returning its name
+    async fn create_cached_content(
+        &self,
+        system: &str,
+        tools: &[Tool],
+        ttl: Duration,
+    ) -> Result<String, ProviderError> {
+        let content = create_cached_content(&self.model, system, tools, ttl);
+        let url = Url::parse(&self.host)
+            .and_then(|base| base.join("v1beta/cachedContents"))
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let request = |key: &str| {
+            let mut url = url.clone();
+            url.query_pairs_mut().append_pair("key", key);
+            self.client.post(url).json(&content)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+        let response = handle_response_google_compat(response).await?;
+        response["name"].as_str().map(String::from).ok_or_else(|| {
+            ProviderError::RequestFailed(format!("No name for cached content: {}", response))
         })
     }
 
     async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send("generateContent", &[], &payload).await?;
+        handle_response_google_compat(response).await
+    }
+
+    /// Send a request to `method` of the model
+    async fn send(
+        &self,
+        method: &str,
+        query: &[(&str, &str)],
+        payload: &Value,
+    ) -> Result<Response, ProviderError> {
         let base_url = Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
 
-        let url = base_url
+        let mut url = base_url
             .join(&format!(
-                "v1beta/models/{}:generateContent?key={}",
-                self.model.model_name, self.api_key
+                "v1beta/models/{}:{}",
+                self.model.model_name, method
             ))
             .map_err(|e| {
                 ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
             })?;
-        let max_retries = 10;
-        let mut retries = 0;
-        let base_delay = Duration::from_secs(4);
-
-        loop {
-            let response = self
-                .client
-                .post(url.clone()) // Clone the URL for each retry
+        url.query_pairs_mut().extend_pairs(query);
+
+        let request = |key: &str| {
+            let mut url = url.clone();
+            url.query_pairs_mut().append_pair("key", key);
+            self.client
+                .post(url)
                 .header("CONTENT_TYPE", "application/json")
-                .json(&payload)
-                .send()
-                .await;
-
-            match response {
-                Ok(res) => {
-                    if res.status() == StatusCode::TOO_MANY_REQUESTS {
-                        retries += 1;
-                        if retries > max_retries {
-                            return Err(ProviderError::RateLimitExceeded(
-                                "Max retries exceeded".to_string(),
-                            ));
-                        }
-
-                        let delay = 2u64.pow(retries);
-                        let total_delay = Duration::from_secs(delay) + base_delay;
-
-                        println!("Rate limit hit. Retrying in {:?}", total_delay);
-                        tokio::time::sleep(total_delay).await;
-                        continue;
-                    } else {
-                        // Successful response or other non-rate-limit error
-                        return handle_response_google_compat(res).await;
-                    }
-                }
-                Err(err) => {
-                    return Err(ProviderError::RequestFailed(format!("Request failed: {}", err)));
-                }
-            }
-        }
+                .json(payload)
+        };
+        retry::send_keyed(&self.api_keys, request).await
     }
 }
 
@@ -132,6 +261,8 @@ impl Provider for GoogleProvider {
             vec![
                 ConfigKey::new("GOOGLE_API_KEY", true, true, None),
                 ConfigKey::new("GOOGLE_HOST", false, false, Some(GOOGLE_API_HOST)),
+                ConfigKey::new("GOOGLE_CONTEXT_CACHE_MIN_TOKENS", false, false, None),
+                ConfigKey::new("GOOGLE_CONTEXT_CACHE_TTL_SECS", false, false, Some("3600")),
             ],
         )
     }
@@ -150,10 +281,10 @@ impl Provider for GoogleProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools)?;
+        let payload = self.request(system, messages, tools).await?;
 
         // Make request
-        let response = self.post(payload.clone()).await?;
+        let response = vcr::post("google", &payload, || self.post(payload.clone())).await?;
 
         // Parse response
         let message = response_to_message(unescape_json_values(&response))?;
@@ -166,4 +297,52 @@ impl Provider for GoogleProvider {
         let provider_usage = ProviderUsage::new(model, usage);
         Ok((message, provider_usage))
     }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        if vcr::is_active() {
+            let (message, usage) = self.complete(system, messages, tools).await?;
+            let deltas = message_deltas(message, usage).into_iter().map(Ok);
+            return Ok(Box::pin(futures::stream::iter(deltas)));
+        }
+
+        let payload = self.request(system, messages, tools).await?;
+        let response = self
+            .send("streamGenerateContent", &[("alt", "sse")], &payload)
+            .await?;
+        if response.status() != StatusCode::OK {
+            // Errors come back as a whole, like those of requests that aren't streamed
+            return Err(match handle_response_google_compat(response).await {
+                Err(e) => e,
+                Ok(body) => ProviderError::RequestFailed(format!("Unexpected response: {}", body)),
+            });
+        }
+
+        let mut events = sse_data(response);
+        Ok(Box::pin(async_stream::try_stream! {
+            let mut model = self.model.model_name.clone();
+            let mut usage = Usage::default();
+            let mut tool_calls = 0;
+            while let Some(data) = events.next().await {
+                let chunk: Value = serde_json::from_str(&data?).map_err(|e| {
+                    ProviderError::RequestFailed(format!("Invalid chunk in response stream: {e}"))
+                })?;
+                if let Some(version) = chunk["modelVersion"].as_str() {
+                    model = version.to_string();
+                }
+                // Each chunk has the usage so far
+                if chunk.get("usageMetadata").is_some() {
+                    usage = get_usage(&chunk)?;
+                }
+                for delta in response_chunk_to_deltas(unescape_json_values(&chunk), &mut tool_calls)? {
+                    yield delta;
+                }
+            }
+            yield MessageDelta::Usage(ProviderUsage::new(model, usage));
+        }))
+    }
 }
@@ -0,0 +1,214 @@
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use url::Url;
+
+use super::base::{ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::google::{
+    create_live_tool_response, format_messages, live_tool_call_to_message,
+};
+use crate::message::Message;
+
+const LIVE_PATH: &str =
+    "ws/google.ai.generativelanguage.v1beta.GenerativeService.BidiGenerateContent";
+
+/// What a Live API session reports as the model responds
+#[derive(Debug, Clone)]
+pub enum LiveEvent {
+    /// Part of the model's reply, as soon as it is generated
+    Text(String),
+    /// Tool calls the model waits on, answered with `GoogleLiveSession::send_tool_responses`
+    ToolRequests(Message),
+    /// The model stopped its reply because the user spoke over it
+    Interrupted,
+    /// The model is done with its turn
+    TurnComplete,
+    /// The tokens used so far in the session
+    Usage(ProviderUsage),
+}
+
+/// A bidirectional session with Gemini's Live API, opened with `GoogleProvider::connect_live`
+///
+/// Unlike `complete`, the server keeps the conversation: each turn is sent once, and the
+/// reply comes back as `LiveEvent`s while it is generated. Text sent with `send_text` while
+/// the model is replying interrupts it, so frontends can let users barge in.
+pub struct GoogleLiveSession {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    model: String,
+    /// The function names of the tool calls waiting on responses, by call id
+    pending_calls: HashMap<String, String>,
+}
+
+impl GoogleLiveSession {
+    /// Connect to the Live API on `host` and set the session up
+    pub(super) async fn connect(
+        host: &str,
+        api_key: &str,
+        model: &str,
+        setup: Value,
+    ) -> Result<Self, ProviderError> {
+        let mut url = Url::parse(host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let scheme = if url.scheme() == "http" { "ws" } else { "wss" };
+        url.set_scheme(scheme)
+            .map_err(|_| ProviderError::RequestFailed(format!("Invalid base URL: {host}")))?;
+        let mut url = url.join(LIVE_PATH).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+        url.query_pairs_mut().append_pair("key", api_key);
+
+        let (socket, _) = connect_async(url.as_str()).await.map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to connect to the Live API: {e}"))
+        })?;
+        let mut session = Self {
+            socket,
+            model: model.to_string(),
+            pending_calls: HashMap::new(),
+        };
+
+        session.send(setup).await?;
+        match session.receive().await? {
+            Some(message) if message.get("setupComplete").is_some() => Ok(session),
+            Some(message) => Err(ProviderError::RequestFailed(format!(
+                "Unexpected response to the session setup: {}",
+                message
+            ))),
+            None => Err(ProviderError::RequestFailed(
+                "The Live API closed the session during setup".to_string(),
+            )),
+        }
+    }
+
+    /// Send messages as complete turns, for the model to reply to
+    pub async fn send_turns(&mut self, messages: &[Message]) -> Result<(), ProviderError> {
+        let turns = format_messages(messages);
+        self.send(json!({ "clientContent": { "turns": turns, "turnComplete": true } }))
+            .await
+    }
+
+    /// Send text as it is typed or transcribed, interrupting a reply in progress
+    pub async fn send_text(&mut self, text: &str) -> Result<(), ProviderError> {
+        self.send(json!({ "realtimeInput": { "text": text } }))
+            .await
+    }
+
+    /// Answer the tool calls of a `LiveEvent::ToolRequests` with the tool responses in `message`
+    pub async fn send_tool_responses(&mut self, message: &Message) -> Result<(), ProviderError> {
+        let response = create_live_tool_response(message, &self.pending_calls);
+        for content in &message.content {
+            if let Some(response) = content.as_tool_response() {
+                self.pending_calls.remove(&response.id);
+            }
+        }
+        self.send(response).await
+    }
+
+    /// Wait for the next events from the server; `None` once the session is closed
+    pub async fn next_events(&mut self) -> Result<Option<Vec<LiveEvent>>, ProviderError> {
+        let Some(message) = self.receive().await? else {
+            return Ok(None);
+        };
+        let mut events = Vec::new();
+
+        if let Some(content) = message.get("serverContent") {
+            let parts = content["modelTurn"]["parts"].as_array();
+            for part in parts.into_iter().flatten() {
+                if let Some(text) = part["text"].as_str() {
+                    events.push(LiveEvent::Text(text.to_string()));
+                }
+            }
+            if content["interrupted"].as_bool() == Some(true) {
+                events.push(LiveEvent::Interrupted);
+            }
+            if content["turnComplete"].as_bool() == Some(true) {
+                events.push(LiveEvent::TurnComplete);
+            }
+        }
+        if let Some(tool_call) = message.get("toolCall") {
+            let request = live_tool_call_to_message(tool_call);
+            for content in &request.content {
+                if let Some(request) = content.as_tool_request() {
+                    if let Ok(call) = &request.tool_call {
+                        self.pending_calls
+                            .insert(request.id.clone(), call.name.clone());
+                    }
+                }
+            }
+            events.push(LiveEvent::ToolRequests(request));
+        }
+        if let Some(cancellation) = message.get("toolCallCancellation") {
+            // Calls the model gave up on, after being interrupted
+            for id in cancellation["ids"].as_array().into_iter().flatten() {
+                if let Some(id) = id.as_str() {
+                    self.pending_calls.remove(id);
+                }
+            }
+        }
+        if let Some(metadata) = message.get("usageMetadata") {
+            let count = |key: &str| metadata[key].as_u64().map(|v| v as i32);
+            let usage = Usage::new(
+                count("promptTokenCount"),
+                count("responseTokenCount"),
+                count("totalTokenCount"),
+            );
+            events.push(LiveEvent::Usage(ProviderUsage::new(
+                self.model.clone(),
+                usage,
+            )));
+        }
+        if let Some(go_away) = message.get("goAway") {
+            tracing::debug!(
+                "The Live API will close the session in {}",
+                go_away["timeLeft"]
+            );
+        }
+        Ok(Some(events))
+    }
+
+    /// Close the session
+    pub async fn close(mut self) -> Result<(), ProviderError> {
+        self.socket
+            .close(None)
+            .await
+            .map_err(|e| ProviderError::RequestFailed(format!("Failed to close the session: {e}")))
+    }
+
+    async fn send(&mut self, message: Value) -> Result<(), ProviderError> {
+        self.socket
+            .send(WsMessage::Text(message.to_string()))
+            .await
+            .map_err(|e| {
+                ProviderError::RequestFailed(format!("Failed to send to the Live API: {e}"))
+            })
+    }
+
+    /// The next JSON message from the server, which comes in text or binary frames
+    async fn receive(&mut self) -> Result<Option<Value>, ProviderError> {
+        while let Some(frame) = self.socket.next().await {
+            let frame = frame.map_err(|e| {
+                ProviderError::RequestFailed(format!("Failed to receive from the Live API: {e}"))
+            })?;
+            let data = match frame {
+                WsMessage::Text(text) => text.into_bytes(),
+                WsMessage::Binary(data) => data,
+                WsMessage::Close(frame) => {
+                    let reason = frame.map(|f| f.reason.to_string()).unwrap_or_default();
+                    // A close with a reason is how the Live API reports errors
+                    if reason.is_empty() {
+                        return Ok(None);
+                    }
+                    return Err(ProviderError::ServerError(reason));
+                }
+                _ => continue,
+            };
+            return serde_json::from_slice(&data).map(Some).map_err(|e| {
+                ProviderError::RequestFailed(format!("Invalid message from the Live API: {e}"))
+            });
+        }
+        Ok(None)
+    }
+}
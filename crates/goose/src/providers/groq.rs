@@ -1,15 +1,16 @@
 use super::errors::ProviderError;
+use super::keys::KeyPool;
+use super::{retry, vcr};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
-use crate::providers::utils::get_model;
+use crate::providers::utils::{get_model, http_client};
 use anyhow::Result;
 use async_trait::async_trait;
 use mcp_core::Tool;
 use reqwest::{Client, StatusCode};
 use serde_json::Value;
-use std::time::Duration;
 use url::Url;
 
 pub const GROQ_API_HOST: &str = "https://api.groq.com";
@@ -23,7 +24,7 @@ pub struct GroqProvider {
     #[serde(skip)]
     client: Client,
     host: String,
-    api_key: String,
+    api_keys: KeyPool,
     model: ModelConfig,
 }
 
@@ -37,19 +38,17 @@ impl Default for GroqProvider {
 impl GroqProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
         let config = crate::config::Config::global();
-        let api_key: String = config.get_secret("GROQ_API_KEY")?;
+        let api_keys = KeyPool::from_secret("GROQ_API_KEY")?;
         let host: String = config
             .get("GROQ_HOST")
             .unwrap_or_else(|_| GROQ_API_HOST.to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = http_client("GROQ")?;
 
         Ok(Self {
             client,
             host,
-            api_key,
+            api_keys,
             model,
         })
     }
@@ -61,13 +60,13 @@ impl GroqProvider {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
         })?;
 
-        let response = self
-            .client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&payload)
-            .send()
-            .await?;
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Authorization", format!("Bearer {}", key))
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
 
         let status = response.status();
         let payload: Option<Value> = response.json().await.ok();
@@ -136,7 +135,7 @@ impl Provider for GroqProvider {
             &super::utils::ImageFormat::OpenAi,
         )?;
 
-        let response = self.post(payload.clone()).await?;
+        let response = vcr::post("groq", &payload, || self.post(payload.clone())).await?;
 
         let message = response_to_message(response.clone())?;
         let usage = match get_usage(&response) {
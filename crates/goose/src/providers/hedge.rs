@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::start_stream;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the `HedgeConfig`
+pub const HEDGE_CONFIG_KEY: &str = "GOOSE_HEDGE";
+
+/// Where a hedged request goes and how long the primary gets to answer first
+///
+/// ```yaml
+/// GOOSE_HEDGE:
+///   provider: groq
+///   model: llama-3.3-70b-versatile
+///   delay_ms: 1500
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HedgeConfig {
+    /// The provider to send the duplicate request to, the primary provider when not set
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// The model to use, the primary's model for the same provider and the provider's
+    /// default for another one when not set
+    #[serde(default)]
+    pub model: Option<String>,
+    /// How long the primary has to answer before the duplicate is sent
+    #[serde(default = "default_delay_ms")]
+    pub delay_ms: u64,
+}
+
+fn default_delay_ms() -> u64 {
+    2000
+}
+
+impl HedgeConfig {
+    /// Read `GOOSE_HEDGE`, returning None when it is not set
+    pub fn from_config() -> Option<Self> {
+        Config::global().get(HEDGE_CONFIG_KEY).ok()
+    }
+
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+}
+
+/// A provider wrapper that races a slow request against a duplicate sent to another provider
+///
+/// A request goes to the primary, and if it hasn't answered after the delay the same
+/// request is also sent to the secondary. Whichever answers first wins and the other request
+/// is dropped, which closes its connection. When one of them fails the other is still
+/// waited for, so hedging never turns an answer into an error.
+pub struct HedgedProvider {
+    primary: Box<dyn Provider>,
+    secondary: Box<dyn Provider>,
+    delay: Duration,
+}
+
+impl HedgedProvider {
+    pub fn new(primary: Box<dyn Provider>, secondary: Box<dyn Provider>, delay: Duration) -> Self {
+        Self {
+            primary,
+            secondary,
+            delay,
+        }
+    }
+
+    /// Race the primary request against the secondary one, sent once the delay has passed
+    async fn hedge<T>(
+        &self,
+        primary: impl Future<Output = Result<T, ProviderError>>,
+        secondary: impl Future<Output = Result<T, ProviderError>>,
+    ) -> Result<T, ProviderError> {
+        tokio::pin!(primary);
+        if let Ok(result) = tokio::time::timeout(self.delay, &mut primary).await {
+            return result;
+        }
+
+        tracing::debug!(
+            "No answer after {}ms, hedging with {}",
+            self.delay.as_millis(),
+            self.secondary.get_model_config().model_name
+        );
+        tokio::pin!(secondary);
+        tokio::select! {
+            result = &mut primary => match result {
+                Ok(reply) => Ok(reply),
+                Err(_) => secondary.await,
+            },
+            result = &mut secondary => match result {
+                Ok(reply) => Ok(reply),
+                Err(_) => primary.await,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for HedgedProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.primary.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.hedge(
+            self.primary.complete(system, messages, tools),
+            self.secondary.complete(system, messages, tools),
+        )
+        .await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        // A stream answers with its first delta, and the loser is dropped from then on
+        self.hedge(
+            async { start_stream(self.primary.stream(system, messages, tools).await?).await },
+            async { start_stream(self.secondary.stream(system, messages, tools).await?).await },
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    #[tokio::test]
+    async fn test_fastest_answer_wins() {
+        let slow = MockProvider::default()
+            .with_latency(Duration::from_millis(500))
+            .with_text("primary");
+        let fast = MockProvider::default()
+            .with_latency(Duration::from_millis(10))
+            .with_text("secondary");
+        let provider = HedgedProvider::new(
+            Box::new(slow.clone()),
+            Box::new(fast.clone()),
+            Duration::from_millis(50),
+        );
+
+        let (message, _) = provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "secondary");
+        assert_eq!(slow.requests().len(), 1);
+        // The primary was dropped before it could answer
+        assert_eq!(slow.remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_hedge_for_a_quick_answer() {
+        let primary = MockProvider::default().with_text("primary");
+        let secondary = MockProvider::default().with_text("secondary");
+        let provider = HedgedProvider::new(
+            Box::new(primary),
+            Box::new(secondary.clone()),
+            Duration::from_millis(200),
+        );
+
+        let (message, _) = provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "primary");
+        assert!(secondary.requests().is_empty());
+    }
+}
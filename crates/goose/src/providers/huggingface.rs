@@ -0,0 +1,188 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::huggingface::response_to_message;
+use super::formats::openai::{create_request, get_usage};
+use super::keys::KeyPool;
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const HUGGINGFACE_API_HOST: &str = "https://router.huggingface.co/hf-inference";
+pub const HUGGINGFACE_DEFAULT_MODEL: &str = "Qwen/Qwen2.5-72B-Instruct";
+// Repo ids of models the serverless API serves with tool calling
+pub const HUGGINGFACE_KNOWN_MODELS: &[&str] = &[
+    "Qwen/Qwen2.5-72B-Instruct",
+    "Qwen/Qwen2.5-Coder-32B-Instruct",
+    "meta-llama/Llama-3.3-70B-Instruct",
+    "meta-llama/Llama-3.1-8B-Instruct",
+    "mistralai/Mistral-Nemo-Instruct-2407",
+];
+
+pub const HUGGINGFACE_DOC_URL: &str =
+    "https://huggingface.co/docs/api-inference/tasks/chat-completion";
+
+/// Runs models on the serverless Inference API, or on a dedicated Inference Endpoint
+/// when `HF_ENDPOINT` is set
+#[derive(serde::Serialize)]
+pub struct HuggingFaceProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    endpoint: Option<String>,
+    api_keys: KeyPool,
+    model: ModelConfig,
+}
+
+impl Default for HuggingFaceProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(HuggingFaceProvider::metadata().default_model);
+        HuggingFaceProvider::from_env(model).expect("Failed to initialize Hugging Face provider")
+    }
+}
+
+impl HuggingFaceProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_keys = KeyPool::from_secret("HF_TOKEN")?;
+        let host: String = config
+            .get("HF_HOST")
+            .unwrap_or_else(|_| HUGGINGFACE_API_HOST.to_string());
+        let endpoint: Option<String> = config.get("HF_ENDPOINT").ok();
+
+        let client = http_client("HF")?;
+
+        Ok(Self {
+            client,
+            host,
+            endpoint,
+            api_keys,
+            model,
+        })
+    }
+
+    // An Inference Endpoint serves a single model, the serverless API picks it by repo id
+    fn url(&self) -> Result<Url, ProviderError> {
+        let (base, path) = match &self.endpoint {
+            Some(endpoint) => (endpoint.clone(), "v1/chat/completions".to_string()),
+            None => (
+                self.host.clone(),
+                format!("models/{}/v1/chat/completions", self.model.model_name),
+            ),
+        };
+        // Without a trailing slash, join would replace the last segment of the base
+        let base = format!("{}/", base.trim_end_matches('/'));
+        let base_url = Url::parse(&base)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        base_url.join(&path).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let url = self.url()?;
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Authorization", format!("Bearer {}", key))
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for HuggingFaceProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "huggingface",
+            "Hugging Face",
+            "Open source models hosted by Together AI",
+            HUGGINGFACE_DEFAULT_MODEL,
+            HUGGINGFACE_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            HUGGINGFACE_DOC_URL,
+            vec![
+                ConfigKey::new("HF_TOKEN", true, true, None),
+                ConfigKey::new("HF_HOST", false, false, Some(HUGGINGFACE_API_HOST)),
+                ConfigKey::new("HF_ENDPOINT", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        let response = vcr::post("huggingface", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        // Inference Endpoints answer with a placeholder model name
+        let model = match &self.endpoint {
+            Some(_) => self.model.model_name.clone(),
+            None => get_model(&response),
+        };
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        let mut provider = HuggingFaceProvider {
+            client: Client::new(),
+            host: HUGGINGFACE_API_HOST.to_string(),
+            endpoint: None,
+            api_keys: KeyPool::single("hf_test"),
+            model: ModelConfig::new("Qwen/Qwen2.5-72B-Instruct".to_string()),
+        };
+        assert_eq!(
+            provider.url().unwrap().as_str(),
+            "https://router.huggingface.co/hf-inference/models/Qwen/Qwen2.5-72B-Instruct/v1/chat/completions"
+        );
+
+        provider.endpoint =
+            Some("https://abc123.us-east-1.aws.endpoints.huggingface.cloud".to_string());
+        assert_eq!(
+            provider.url().unwrap().as_str(),
+            "https://abc123.us-east-1.aws.endpoints.huggingface.cloud/v1/chat/completions"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize, Serializer};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::retry::retry_after;
+use crate::config::Config;
+
+/// Config key holding the `KeyRotation`
+pub const KEY_ROTATION_CONFIG_KEY: &str = "GOOSE_KEY_ROTATION";
+
+// How long a rate limited key rests when the provider doesn't say
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// How a provider with several API keys picks the key for each request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRotation {
+    /// Each request uses the next key
+    RoundRobin,
+    /// Requests use the same key until it is rate limited
+    #[default]
+    OnRateLimit,
+}
+
+#[derive(Debug, Clone)]
+struct PoolState {
+    next: usize,
+    /// When each key can be used again after it was rate limited
+    cooldowns: Vec<Option<Instant>>,
+}
+
+/// The API keys of a provider, rotated between requests
+///
+/// Several keys are configured by separating them with commas in the provider's secret, as in
+/// `OPENAI_API_KEY=sk-first,sk-second`, and `GOOSE_KEY_ROTATION` picks how they are rotated.
+/// A key that gets rate limited rests for as long as the provider asks, and is skipped until
+/// then unless every key is resting.
+pub struct KeyPool {
+    keys: Vec<String>,
+    rotation: KeyRotation,
+    state: Mutex<PoolState>,
+}
+
+impl KeyPool {
+    pub fn new(keys: Vec<String>, rotation: KeyRotation) -> Self {
+        assert!(!keys.is_empty(), "A key pool needs a key");
+        let state = PoolState {
+            next: 0,
+            cooldowns: vec![None; keys.len()],
+        };
+        Self {
+            keys,
+            rotation,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// A pool with a single key, which is never rotated
+    pub fn single<S: Into<String>>(key: S) -> Self {
+        Self::new(vec![key.into()], KeyRotation::default())
+    }
+
+    /// Read the keys in the secret `name`, with the rotation from `GOOSE_KEY_ROTATION`
+    pub fn from_secret(name: &str) -> Result<Self> {
+        let config = Config::global();
+        let secret: String = config.get_secret(name)?;
+        let keys: Vec<String> = secret
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+            .collect();
+        if keys.is_empty() {
+            anyhow::bail!("{} has no API keys", name);
+        }
+        let rotation = config.get(KEY_ROTATION_CONFIG_KEY).unwrap_or_default();
+        Ok(Self::new(keys, rotation))
+    }
+
+    /// The key for the next request
+    pub fn key(&self) -> String {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let count = self.keys.len();
+        let available = (0..count)
+            .map(|offset| (state.next + offset) % count)
+            .find(|&index| state.cooldowns[index].map_or(true, |until| until <= now));
+        // When every key is resting, use the one that is ready first
+        let index = available.unwrap_or_else(|| {
+            (0..count)
+                .min_by_key(|&index| state.cooldowns[index])
+                .unwrap_or(0)
+        });
+        state.next = match self.rotation {
+            KeyRotation::RoundRobin => (index + 1) % count,
+            KeyRotation::OnRateLimit => index,
+        };
+        self.keys[index].clone()
+    }
+
+    /// Record the response to a request made with `key`, resting the key if it is rate limited
+    pub fn report(&self, key: &str, response: &Response) {
+        if response.status() != StatusCode::TOO_MANY_REQUESTS {
+            return;
+        }
+        let Some(index) = self.keys.iter().position(|k| k == key) else {
+            return;
+        };
+        let cooldown = retry_after(response.headers()).unwrap_or(DEFAULT_COOLDOWN);
+        let mut state = self.state.lock().unwrap();
+        state.cooldowns[index] = Some(Instant::now() + cooldown);
+        if state.next == index {
+            state.next = (index + 1) % self.keys.len();
+        }
+    }
+
+    /// Whether a key is ready to use right away
+    pub fn has_available(&self) -> bool {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+        state
+            .cooldowns
+            .iter()
+            .any(|cooldown| cooldown.map_or(true, |until| until <= now))
+    }
+}
+
+// Clones start out with the keys resting as they are now, then keep their own state
+impl Clone for KeyPool {
+    fn clone(&self) -> Self {
+        Self {
+            keys: self.keys.clone(),
+            rotation: self.rotation,
+            state: Mutex::new(self.state.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl std::fmt::Debug for KeyPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPool")
+            .field("keys", &self.keys.len())
+            .field("rotation", &self.rotation)
+            .finish()
+    }
+}
+
+// Providers are serialized for traces, which shouldn't hold the keys
+impl Serialize for KeyPool {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.keys.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limited() -> Response {
+        axum::http::Response::builder()
+            .status(429)
+            .header("retry-after", "30")
+            .body("")
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn test_rotation() {
+        let keys = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let pool = KeyPool::new(keys.clone(), KeyRotation::RoundRobin);
+        let picked: Vec<String> = (0..4).map(|_| pool.key()).collect();
+        assert_eq!(picked, vec!["a", "b", "c", "a"]);
+
+        let pool = KeyPool::new(keys, KeyRotation::OnRateLimit);
+        assert_eq!(pool.key(), "a");
+        assert_eq!(pool.key(), "a");
+        pool.report("a", &rate_limited());
+        assert_eq!(pool.key(), "b");
+        pool.report("b", &rate_limited());
+        pool.report("c", &rate_limited());
+        assert!(!pool.has_available());
+        // Every key is resting, so the one that rested longest is used
+        assert_eq!(pool.key(), "a");
+    }
+}
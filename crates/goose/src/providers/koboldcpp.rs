@@ -0,0 +1,253 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::chat_template::{render_prompt, ChatTemplate};
+use super::formats::koboldcpp::{collect_stream, create_generate_request, generate_to_message};
+use super::utils::{emit_debug_trace, handle_response_openai_compat, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const KOBOLDCPP_HOST: &str = "http://localhost:5001";
+// KoboldCpp serves the one model it was started with, we only provide the default
+pub const KOBOLDCPP_DEFAULT_MODEL: &str = "koboldcpp";
+pub const KOBOLDCPP_KNOWN_MODELS: &[&str] = &[KOBOLDCPP_DEFAULT_MODEL];
+
+pub const KOBOLDCPP_DOC_URL: &str = "https://github.com/LostRuins/koboldcpp/wiki";
+
+/// Config key holding the `KoboldCppOptions`
+///
+/// ```yaml
+/// KOBOLDCPP_OPTIONS:
+///   template: llama3
+///   max_context_length: 8192
+///   stream: true
+///   sampler:
+///     min_p: 0.05
+///     rep_pen: 1.1
+///     sampler_order: [6, 0, 1, 3, 4, 2, 5]
+/// ```
+pub const KOBOLDCPP_OPTIONS_CONFIG_KEY: &str = "KOBOLDCPP_OPTIONS";
+
+/// KoboldCpp's sampler settings, sent as they are named in its API
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KoboldCppSampler {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typical: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tfs: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rep_pen: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rep_pen_range: Option<u32>,
+    /// The order samplers are applied in, by KoboldCpp's numbering of them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampler_order: Option<Vec<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirostat: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirostat_tau: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirostat_eta: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampler_seed: Option<i64>,
+    /// A GBNF grammar the output has to match; left out of requests with tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grammar: Option<String>,
+}
+
+/// Settings specific to KoboldCpp
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KoboldCppOptions {
+    /// The chat template the model was trained with, for rendering prompts
+    #[serde(default)]
+    pub template: ChatTemplate,
+    /// The context size KoboldCpp was started with (`--contextsize`), sent with each request
+    /// and used as the context limit so the conversation is truncated before it overflows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_context_length: Option<usize>,
+    /// Generate through the streaming endpoint, collecting the tokens as they come, so long
+    /// generations aren't cut off by proxies that time out idle connections
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub sampler: KoboldCppSampler,
+}
+
+/// KoboldCpp, through its KoboldAI API, with the prompt rendered by goose
+#[derive(serde::Serialize)]
+pub struct KoboldCppProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    #[serde(skip)]
+    password: Option<String>,
+    model: ModelConfig,
+    options: KoboldCppOptions,
+}
+
+impl Default for KoboldCppProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(KoboldCppProvider::metadata().default_model);
+        KoboldCppProvider::from_env(model).expect("Failed to initialize KoboldCpp provider")
+    }
+}
+
+impl KoboldCppProvider {
+    pub fn from_env(mut model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        // Only needed when KoboldCpp was started with --password
+        let password: Option<String> = config.get_secret("KOBOLDCPP_PASSWORD").ok();
+        let host: String = config
+            .get("KOBOLDCPP_HOST")
+            .unwrap_or_else(|_| KOBOLDCPP_HOST.to_string());
+        let options: KoboldCppOptions =
+            config.get(KOBOLDCPP_OPTIONS_CONFIG_KEY).unwrap_or_default();
+        if options.max_context_length.is_some() {
+            model.context_limit = options.max_context_length;
+        }
+
+        let client = http_client("KOBOLDCPP")?;
+
+        Ok(Self {
+            client,
+            host,
+            password,
+            model,
+            options,
+        })
+    }
+
+    async fn post(&self, payload: &Value) -> Result<Value, ProviderError> {
+        let path = if self.options.stream {
+            "api/extra/generate/stream"
+        } else {
+            "api/v1/generate"
+        };
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join(path).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let mut request = self.client.post(url);
+        if let Some(password) = &self.password {
+            request = request.bearer_auth(password);
+        }
+        let response = retry::send(request.json(payload)).await?;
+
+        if self.options.stream && response.status() == StatusCode::OK {
+            let body = response.text().await?;
+            return collect_stream(&body).map_err(|e| ProviderError::RequestFailed(e.to_string()));
+        }
+        handle_response_openai_compat(response).await
+    }
+}
+
+/// Add the sampler settings and context size to a generate payload
+fn add_options(payload: &mut Value, options: &KoboldCppOptions, has_tools: bool) {
+    if let Some(max_context_length) = options.max_context_length {
+        payload["max_context_length"] = json!(max_context_length);
+    }
+    let Ok(Value::Object(sampler)) = serde_json::to_value(&options.sampler) else {
+        return;
+    };
+    for (key, value) in sampler {
+        if key == "grammar" && has_tools {
+            tracing::debug!("Leaving out the grammar, the model has to be free to call tools");
+            continue;
+        }
+        payload[key] = value;
+    }
+}
+
+#[async_trait]
+impl Provider for KoboldCppProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "koboldcpp",
+            "KoboldCpp",
+            "Local GGUF models served by KoboldCpp",
+            KOBOLDCPP_DEFAULT_MODEL,
+            KOBOLDCPP_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            KOBOLDCPP_DOC_URL,
+            vec![
+                ConfigKey::new("KOBOLDCPP_HOST", true, false, Some(KOBOLDCPP_HOST)),
+                ConfigKey::new("KOBOLDCPP_PASSWORD", false, true, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let template = self.options.template;
+        let prompt = render_prompt(template, system, messages, tools)?;
+        let mut payload = create_generate_request(&self.model, template, &prompt);
+        add_options(&mut payload, &self.options, !tools.is_empty());
+
+        let response = vcr::post("koboldcpp", &payload, || self.post(&payload)).await?;
+
+        let message = generate_to_message(&response, template)?;
+        // The KoboldAI API reports no token counts
+        let usage = Usage::default();
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((
+            message,
+            ProviderUsage::new(self.model.model_name.clone(), usage),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_options() {
+        let options: KoboldCppOptions = serde_yaml::from_str(
+            "max_context_length: 8192\nstream: true\nsampler: {min_p: 0.05, rep_pen: 1.1, sampler_order: [6, 0, 1], grammar: 'root ::= \"yes\"'}",
+        )
+        .unwrap();
+        assert!(options.stream);
+
+        let mut payload = json!({ "prompt": "Is the sky blue?" });
+        add_options(&mut payload, &options, false);
+        assert_eq!(payload["max_context_length"], 8192);
+        assert_eq!(payload["rep_pen"], json!(1.1f32));
+        assert_eq!(payload["sampler_order"], json!([6, 0, 1]));
+        assert_eq!(payload["grammar"], "root ::= \"yes\"");
+        assert!(payload.get("top_k").is_none());
+
+        let mut payload = json!({ "prompt": "List the files" });
+        add_options(&mut payload, &options, true);
+        assert!(payload.get("grammar").is_none());
+    }
+}
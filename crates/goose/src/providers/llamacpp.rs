@@ -0,0 +1,285 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::llamacpp::{
+    completion_to_message, create_completion_request, get_completion_usage,
+};
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const LLAMACPP_HOST: &str = "http://localhost:8080";
+pub const LLAMACPP_DEFAULT_MODEL: &str = "llama-3.2-3b-instruct";
+// llama-server serves the one model it was started with, we only provide the default
+pub const LLAMACPP_KNOWN_MODELS: &[&str] = &[LLAMACPP_DEFAULT_MODEL];
+
+pub const LLAMACPP_DOC_URL: &str =
+    "https://github.com/ggml-org/llama.cpp/blob/master/tools/server/README.md";
+
+/// Config key holding the `LlamaCppOptions`
+///
+/// ```yaml
+/// LLAMACPP_OPTIONS:
+///   n_ctx: 8192
+///   cache_prompt: true
+///   grammar: 'root ::= "yes" | "no"'
+/// ```
+pub const LLAMACPP_OPTIONS_CONFIG_KEY: &str = "LLAMACPP_OPTIONS";
+
+/// Which of llama-server's endpoints requests go to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlamaCppEndpoint {
+    /// `/v1/chat/completions`, which applies the chat template and parses tool calls
+    #[default]
+    Chat,
+    /// `/completion` with the prompt rendered by `/apply-template`, for plain text
+    /// generation; requests with tools still go to the chat endpoint
+    Completion,
+}
+
+/// Settings specific to llama.cpp
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LlamaCppOptions {
+    /// The context size the server was started with (`--ctx-size`), used as the context
+    /// limit so the conversation is truncated before the server refuses it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n_ctx: Option<usize>,
+    /// A GBNF grammar the output has to match; left out of requests with tools, which
+    /// llama.cpp constrains with a grammar of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grammar: Option<String>,
+    /// Whether the server should reuse the cached prompt shared with the previous request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_prompt: Option<bool>,
+    #[serde(default)]
+    pub endpoint: LlamaCppEndpoint,
+}
+
+/// A llama.cpp server (`llama-server`), through its native API rather than only the
+/// generic OpenAI compatible one
+#[derive(serde::Serialize)]
+pub struct LlamaCppProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_key: Option<String>,
+    model: ModelConfig,
+    options: LlamaCppOptions,
+}
+
+impl Default for LlamaCppProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(LlamaCppProvider::metadata().default_model);
+        LlamaCppProvider::from_env(model).expect("Failed to initialize llama.cpp provider")
+    }
+}
+
+impl LlamaCppProvider {
+    pub fn from_env(mut model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        // Only needed when the server was started with --api-key
+        let api_key: Option<String> = config.get_secret("LLAMACPP_API_KEY").ok();
+        let host: String = config
+            .get("LLAMACPP_HOST")
+            .unwrap_or_else(|_| LLAMACPP_HOST.to_string());
+        let options: LlamaCppOptions = config.get(LLAMACPP_OPTIONS_CONFIG_KEY).unwrap_or_default();
+        if options.n_ctx.is_some() {
+            model.context_limit = options.n_ctx;
+        }
+
+        let client = http_client("LLAMACPP")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model,
+            options,
+        })
+    }
+
+    async fn post(&self, path: &str, payload: &Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join(path).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let mut request = self.client.post(url);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+        let response = retry::send(request.json(payload)).await?;
+
+        handle_response(response).await
+    }
+
+    /// Render the conversation with the model's chat template and complete it as plain text
+    async fn post_completion(&self, chat_payload: &Value) -> Result<Value, ProviderError> {
+        let template = self
+            .post(
+                "apply-template",
+                &json!({ "messages": chat_payload["messages"] }),
+            )
+            .await?;
+        let prompt = template["prompt"].as_str().ok_or_else(|| {
+            ProviderError::RequestFailed("Applying the chat template returned no prompt".into())
+        })?;
+
+        let mut payload = create_completion_request(&self.model, prompt);
+        add_options(&mut payload, &self.options, false);
+        self.post("completion", &payload).await
+    }
+}
+
+/// llama.cpp reports errors in the OpenAI shape but with a numeric code, so a prompt that
+/// doesn't fit is recognized by the error type instead
+async fn handle_response(response: Response) -> Result<Value, ProviderError> {
+    if response.status() != StatusCode::BAD_REQUEST {
+        return handle_response_openai_compat(response).await;
+    }
+
+    let payload: Value = response
+        .json()
+        .await
+        .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
+    let error = &payload["error"];
+    let message = error["message"]
+        .as_str()
+        .unwrap_or("Unknown error")
+        .to_string();
+    if error["type"] == "exceed_context_size_error" {
+        return Err(ProviderError::ContextLengthExceeded(message));
+    }
+    Err(ProviderError::RequestFailed(format!(
+        "{} (status 400)",
+        message
+    )))
+}
+
+/// Add the llama.cpp options to a request payload; both endpoints take the same ones
+fn add_options(payload: &mut Value, options: &LlamaCppOptions, has_tools: bool) {
+    if let Some(grammar) = &options.grammar {
+        if has_tools {
+            tracing::debug!("Leaving out the grammar, llama.cpp constrains tool calls itself");
+        } else {
+            payload["grammar"] = json!(grammar);
+        }
+    }
+    if let Some(cache_prompt) = options.cache_prompt {
+        payload["cache_prompt"] = json!(cache_prompt);
+    }
+}
+
+#[async_trait]
+impl Provider for LlamaCppProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "llamacpp",
+            "llama.cpp",
+            "Local models served by llama.cpp's llama-server",
+            LLAMACPP_DEFAULT_MODEL,
+            LLAMACPP_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            LLAMACPP_DOC_URL,
+            vec![
+                ConfigKey::new("LLAMACPP_HOST", true, false, Some(LLAMACPP_HOST)),
+                ConfigKey::new("LLAMACPP_API_KEY", false, true, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        add_options(&mut payload, &self.options, !tools.is_empty());
+
+        if self.options.endpoint == LlamaCppEndpoint::Completion && tools.is_empty() {
+            let response =
+                vcr::post("llamacpp", &payload, || self.post_completion(&payload)).await?;
+
+            let message = completion_to_message(&response)?;
+            let usage = get_completion_usage(&response).unwrap_or_else(|e| {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            });
+            let model = response["model"]
+                .as_str()
+                .unwrap_or(&self.model.model_name)
+                .to_string();
+            emit_debug_trace(self, &payload, &response, &usage);
+            return Ok((message, ProviderUsage::new(model, usage)));
+        }
+
+        let response = vcr::post("llamacpp", &payload, || {
+            self.post("v1/chat/completions", &payload)
+        })
+        .await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_options() {
+        let options: LlamaCppOptions = serde_yaml::from_str(
+            "n_ctx: 8192\ncache_prompt: true\ngrammar: 'root ::= \"yes\" | \"no\"'\nendpoint: completion",
+        )
+        .unwrap();
+        assert_eq!(options.endpoint, LlamaCppEndpoint::Completion);
+
+        let mut payload = json!({ "prompt": "Is the sky blue?" });
+        add_options(&mut payload, &options, false);
+        assert_eq!(payload["grammar"], "root ::= \"yes\" | \"no\"");
+        assert_eq!(payload["cache_prompt"], true);
+        // The context size is the server's, not a request setting
+        assert!(payload.get("n_ctx").is_none());
+
+        let mut payload = json!({ "messages": [] });
+        add_options(&mut payload, &options, true);
+        assert!(payload.get("grammar").is_none());
+        assert_eq!(payload["cache_prompt"], true);
+    }
+}
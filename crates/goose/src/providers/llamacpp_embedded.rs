@@ -0,0 +1,389 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+use llama_cpp_2::sampling::LlamaSampler;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use super::base::{
+    message_stream, ConfigKey, MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
+use super::errors::ProviderError;
+use super::formats::chat_template::{
+    conversation_turns, render_prompt, response_to_message, ChatTemplate,
+};
+use super::utils::emit_debug_trace;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the path of the GGUF file to load
+pub const LLAMACPP_MODEL_PATH_CONFIG_KEY: &str = "LLAMACPP_MODEL_PATH";
+
+/// Config key holding the `EmbeddedLlamaCppOptions`
+///
+/// ```yaml
+/// LLAMACPP_EMBEDDED_OPTIONS:
+///   n_ctx: 8192
+///   n_gpu_layers: 99
+///   template: chatml
+/// ```
+pub const LLAMACPP_EMBEDDED_OPTIONS_CONFIG_KEY: &str = "LLAMACPP_EMBEDDED_OPTIONS";
+
+pub const LLAMACPP_EMBEDDED_DEFAULT_MODEL: &str = "local";
+pub const LLAMACPP_EMBEDDED_DOC_URL: &str = "https://github.com/utilityai/llama-cpp-rs";
+
+const DEFAULT_N_CTX: u32 = 4096;
+// Tokens generated when the model config doesn't set max_tokens
+const DEFAULT_MAX_TOKENS: usize = 2048;
+const DEFAULT_SEED: u32 = 299792458;
+
+// llama.cpp can only be initialized once per process
+static BACKEND: OnceCell<LlamaBackend> = OnceCell::new();
+
+fn backend() -> Result<&'static LlamaBackend> {
+    BACKEND.get_or_try_init(|| {
+        let mut backend = LlamaBackend::init()?;
+        backend.void_logs();
+        Ok(backend)
+    })
+}
+
+/// Settings for the embedded llama.cpp provider
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddedLlamaCppOptions {
+    /// The context size, 4096 tokens by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n_ctx: Option<u32>,
+    /// How many layers to offload to the GPU, when llama.cpp was built with GPU support
+    #[serde(default)]
+    pub n_gpu_layers: u32,
+    /// Threads used for generation, llama.cpp's default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<i32>,
+    /// The prompt format, instead of the chat template stored in the GGUF file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<ChatTemplate>,
+}
+
+/// A GGUF model run in process with llama.cpp, without a server or daemon
+///
+/// The model is loaded from `LLAMACPP_MODEL_PATH` the first time it is used and kept
+/// for the life of the provider; nothing is ever downloaded. Only available when goose is
+/// built with the `llama-cpp` feature.
+#[derive(serde::Serialize)]
+pub struct EmbeddedLlamaCppProvider {
+    model: ModelConfig,
+    path: PathBuf,
+    options: EmbeddedLlamaCppOptions,
+    #[serde(skip)]
+    loaded: tokio::sync::OnceCell<Arc<LlamaModel>>,
+}
+
+impl Default for EmbeddedLlamaCppProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(EmbeddedLlamaCppProvider::metadata().default_model);
+        EmbeddedLlamaCppProvider::from_env(model)
+            .expect("Failed to initialize embedded llama.cpp provider")
+    }
+}
+
+impl EmbeddedLlamaCppProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let path: String = config.get(LLAMACPP_MODEL_PATH_CONFIG_KEY)?;
+        let options: EmbeddedLlamaCppOptions = config
+            .get(LLAMACPP_EMBEDDED_OPTIONS_CONFIG_KEY)
+            .unwrap_or_default();
+        Ok(Self::new(model, path.into(), options))
+    }
+
+    pub fn new(mut model: ModelConfig, path: PathBuf, options: EmbeddedLlamaCppOptions) -> Self {
+        model.context_limit = Some(options.n_ctx.unwrap_or(DEFAULT_N_CTX) as usize);
+        Self {
+            model,
+            path,
+            options,
+            loaded: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    async fn load(&self) -> Result<Arc<LlamaModel>, ProviderError> {
+        self.loaded
+            .get_or_try_init(|| async {
+                let path = self.path.clone();
+                let params =
+                    LlamaModelParams::default().with_n_gpu_layers(self.options.n_gpu_layers);
+                tokio::task::spawn_blocking(move || {
+                    if !path.exists() {
+                        return Err(anyhow!("No model at {}", path.display()));
+                    }
+                    let model = LlamaModel::load_from_file(backend()?, &path, &params)
+                        .map_err(|e| anyhow!("Failed to load {}: {}", path.display(), e))?;
+                    Ok(Arc::new(model))
+                })
+                .await
+                .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+                .map_err(|e| ProviderError::ExecutionError(e.to_string()))
+            })
+            .await
+            .cloned()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools, cancel),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn run(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel: &CancellationToken,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let model = self.load().await?;
+        let prompt = prompt(&model, self.options.template, system, messages, tools)?;
+
+        // Generation stops when the request is cancelled, or when this future is dropped
+        let stop = cancel.child_token();
+        let _stop_on_drop = stop.clone().drop_guard();
+        let options = self.options.clone();
+        let config = self.model.clone();
+        let request = prompt.clone();
+        let (text, input_tokens, output_tokens) = tokio::task::spawn_blocking(move || {
+            generate(&model, &options, &config, &request, &stop)
+        })
+        .await
+        .map_err(|e| ProviderError::ExecutionError(e.to_string()))??;
+
+        let message = response_to_message(&text)?;
+        let usage = Usage::new(
+            Some(input_tokens as i32),
+            Some(output_tokens as i32),
+            Some((input_tokens + output_tokens) as i32),
+        );
+        emit_debug_trace(
+            self,
+            &json!({ "prompt": prompt }),
+            &json!({ "text": text }),
+            &usage,
+        );
+        Ok((
+            message,
+            ProviderUsage::new(self.model.model_name.clone(), usage),
+        ))
+    }
+}
+
+/// Render the conversation with the configured template, or the one in the GGUF file
+fn prompt(
+    model: &LlamaModel,
+    template: Option<ChatTemplate>,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<String> {
+    if let Some(template) = template {
+        return render_prompt(template, system, messages, tools);
+    }
+
+    let chat = conversation_turns(system, messages, tools)?
+        .into_iter()
+        .map(|(role, content)| {
+            // Not every embedded template knows a tool role, but all of them know the user
+            let role = if role == "tool" {
+                "user".to_string()
+            } else {
+                role
+            };
+            LlamaChatMessage::new(role, content)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let template = model.chat_template(None).map_err(|e| {
+        anyhow!(
+            "The model has no chat template, set one in the options: {}",
+            e
+        )
+    })?;
+    Ok(model.apply_chat_template(&template, &chat, true)?)
+}
+
+fn execution_error<E: std::fmt::Display>(error: E) -> ProviderError {
+    ProviderError::ExecutionError(error.to_string())
+}
+
+/// Generate a reply to the prompt, returning it with the prompt and reply token counts
+///
+/// Generation stops between tokens once `stop` is cancelled.
+fn generate(
+    model: &LlamaModel,
+    options: &EmbeddedLlamaCppOptions,
+    config: &ModelConfig,
+    prompt: &str,
+    stop: &CancellationToken,
+) -> Result<(String, usize, usize), ProviderError> {
+    let n_ctx = options.n_ctx.unwrap_or(DEFAULT_N_CTX);
+    let mut params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(n_ctx));
+    if let Some(threads) = options.threads {
+        params = params.with_n_threads(threads);
+    }
+    let backend = backend().map_err(execution_error)?;
+    let mut context = model
+        .new_context(backend, params)
+        .map_err(execution_error)?;
+
+    // The rendered template already starts with any begin of text token
+    let tokens = model
+        .str_to_token(prompt, AddBos::Never)
+        .map_err(execution_error)?;
+    let max_tokens = config
+        .max_tokens
+        .map(|tokens| tokens.max(1) as usize)
+        .unwrap_or(DEFAULT_MAX_TOKENS);
+    if tokens.len() + max_tokens > n_ctx as usize {
+        return Err(ProviderError::ContextLengthExceeded(format!(
+            "The prompt has {} tokens and up to {} more are generated, but the context is {}",
+            tokens.len(),
+            max_tokens,
+            n_ctx
+        )));
+    }
+
+    let mut batch = LlamaBatch::new(tokens.len().max(1), 1);
+    let last = tokens.len() as i32 - 1;
+    for (position, token) in (0_i32..).zip(tokens.iter()) {
+        batch
+            .add(*token, position, &[0], position == last)
+            .map_err(execution_error)?;
+    }
+    context.decode(&mut batch).map_err(execution_error)?;
+
+    let mut sampler = match config.temperature.filter(|t| *t > 0.0) {
+        Some(temperature) => LlamaSampler::chain_simple([
+            LlamaSampler::temp(temperature),
+            LlamaSampler::dist(DEFAULT_SEED),
+        ]),
+        None => LlamaSampler::greedy(),
+    };
+
+    let mut output = Vec::new();
+    let mut generated = 0;
+    let mut position = batch.n_tokens();
+    while generated < max_tokens {
+        if stop.is_cancelled() {
+            return Err(ProviderError::Cancelled);
+        }
+        let token = sampler.sample(&context, batch.n_tokens() - 1);
+        sampler.accept(token);
+        if model.is_eog_token(token) {
+            break;
+        }
+        generated += 1;
+        output.extend(
+            model
+                .token_to_bytes(token, Special::Plaintext)
+                .map_err(execution_error)?,
+        );
+
+        batch.clear();
+        batch
+            .add(token, position, &[0], true)
+            .map_err(execution_error)?;
+        position += 1;
+        context.decode(&mut batch).map_err(execution_error)?;
+    }
+
+    // Tokens can split a multi-byte character, so the text is only decoded at the end
+    let text = String::from_utf8_lossy(&output).into_owned();
+    Ok((text, tokens.len(), generated))
+}
+
+#[async_trait]
+impl Provider for EmbeddedLlamaCppProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "llamacpp_embedded",
+            "llama.cpp (embedded)",
+            "A local GGUF model run inside goose with llama.cpp, without a server",
+            LLAMACPP_EMBEDDED_DEFAULT_MODEL,
+            vec![LLAMACPP_EMBEDDED_DEFAULT_MODEL.to_string()],
+            LLAMACPP_EMBEDDED_DOC_URL,
+            vec![ConfigKey::new(
+                LLAMACPP_MODEL_PATH_CONFIG_KEY,
+                true,
+                false,
+                None,
+            )],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.run(system, messages, tools, &CancellationToken::new())
+            .await
+    }
+
+    async fn complete_cancellable(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel: &CancellationToken,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.run(system, messages, tools, cancel).await
+    }
+
+    async fn stream_cancellable(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel: &CancellationToken,
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let (message, usage) = self.run(system, messages, tools, cancel).await?;
+        Ok(message_stream(message, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let options: EmbeddedLlamaCppOptions =
+            serde_yaml::from_str("n_ctx: 8192\nn_gpu_layers: 99\ntemplate: llama3").unwrap();
+        assert_eq!(options.template, Some(ChatTemplate::Llama3));
+
+        let provider = EmbeddedLlamaCppProvider::new(
+            ModelConfig::new("local".to_string()),
+            PathBuf::from("/models/model.gguf"),
+            options,
+        );
+        assert_eq!(provider.model.context_limit, Some(8192));
+
+        let provider = EmbeddedLlamaCppProvider::new(
+            ModelConfig::new("local".to_string()),
+            PathBuf::from("/models/model.gguf"),
+            EmbeddedLlamaCppOptions::default(),
+        );
+        assert_eq!(provider.model.context_limit, Some(DEFAULT_N_CTX as usize));
+    }
+}
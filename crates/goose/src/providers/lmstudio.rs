@@ -0,0 +1,170 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::lmstudio::response_to_message;
+use super::formats::openai::{create_request, get_usage};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const LMSTUDIO_HOST: &str = "http://localhost:1234";
+pub const LMSTUDIO_DEFAULT_MODEL: &str = "qwen2.5-7b-instruct";
+// LM Studio serves whichever models are loaded, see `LmStudioProvider::fetch_metadata`
+pub const LMSTUDIO_KNOWN_MODELS: &[&str] = &[
+    LMSTUDIO_DEFAULT_MODEL,
+    "llama-3.2-3b-instruct",
+    "mistral-nemo-instruct-2407",
+];
+
+pub const LMSTUDIO_DOC_URL: &str = "https://lmstudio.ai/docs/app/api/tools";
+
+/// The local server of the LM Studio desktop app, through its OpenAI compatible API
+#[derive(serde::Serialize)]
+pub struct LmStudioProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    model: ModelConfig,
+}
+
+impl Default for LmStudioProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(LmStudioProvider::metadata().default_model);
+        LmStudioProvider::from_env(model).expect("Failed to initialize LM Studio provider")
+    }
+}
+
+impl LmStudioProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let host: String = config
+            .get("LMSTUDIO_HOST")
+            .unwrap_or_else(|_| LMSTUDIO_HOST.to_string());
+
+        let client = http_client("LMSTUDIO")?;
+
+        Ok(Self {
+            client,
+            host,
+            model,
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<Url, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        base_url.join(path).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let url = self.url("v1/chat/completions")?;
+        let response = retry::send(self.client.post(url).json(&payload)).await?;
+
+        handle_response_openai_compat(response).await
+    }
+
+    /// The models LM Studio has loaded, as listed by its `/v1/models` endpoint
+    pub async fn fetch_models(&self) -> Result<Vec<String>, ProviderError> {
+        let url = self.url("v1/models")?;
+        let response = self.client.get(url).send().await?;
+        let response = handle_response_openai_compat(response).await?;
+
+        let models = response["data"]
+            .as_array()
+            .ok_or_else(|| {
+                ProviderError::RequestFailed("Model list is missing the data field".to_string())
+            })?
+            .iter()
+            .filter_map(|model| model["id"].as_str())
+            // Embedding models are listed too but can't chat
+            .filter(|id| !id.contains("embed"))
+            .map(String::from)
+            .collect();
+        Ok(models)
+    }
+
+    /// The provider metadata with the loaded models as the known models, the first one being
+    /// the default; when LM Studio can't be reached or has nothing loaded, the static metadata
+    pub async fn fetch_metadata() -> ProviderMetadata {
+        let mut metadata = Self::metadata();
+        let models = match Self::from_env(ModelConfig::new(metadata.default_model.clone())) {
+            Ok(provider) => provider.fetch_models().await,
+            Err(e) => Err(ProviderError::RequestFailed(e.to_string())),
+        };
+        match models {
+            Ok(models) if !models.is_empty() => {
+                metadata.default_model = models[0].clone();
+                metadata.known_models = models;
+            }
+            Ok(_) => tracing::debug!("LM Studio has no models loaded"),
+            Err(e) => tracing::debug!("Failed to list the LM Studio models: {}", e),
+        }
+        metadata
+    }
+}
+
+#[async_trait]
+impl Provider for LmStudioProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "lmstudio",
+            "LM Studio",
+            "Local models loaded in LM Studio",
+            LMSTUDIO_DEFAULT_MODEL,
+            LMSTUDIO_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            LMSTUDIO_DOC_URL,
+            vec![ConfigKey::new(
+                "LMSTUDIO_HOST",
+                true,
+                false,
+                Some(LMSTUDIO_HOST),
+            )],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        let response = vcr::post("lmstudio", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
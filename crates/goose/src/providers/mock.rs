@@ -0,0 +1,287 @@
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::base::{
+    message_deltas, MessageDelta, MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
+use super::errors::ProviderError;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::{Tool, ToolCall};
+
+// Mocks registered with the factory by name
+static REGISTRY: Lazy<Mutex<HashMap<String, MockProvider>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A request received by a `MockProvider`
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub system: String,
+    pub messages: Vec<Message>,
+    pub tools: Vec<Tool>,
+}
+
+enum MockResponse {
+    Message(Message),
+    Error(ProviderError),
+}
+
+/// A provider that replies with a programmed sequence of messages and errors
+///
+/// Responses are returned in the order they were added, one per call to `complete`;
+/// once they run out every call fails. Clones share the same queue and request log, so a
+/// test can keep a handle to inspect what the agent sent.
+#[derive(Clone)]
+pub struct MockProvider {
+    model: ModelConfig,
+    usage: Usage,
+    latency: Duration,
+    responses: Arc<Mutex<VecDeque<MockResponse>>>,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new(ModelConfig::new("mock".to_string()))
+    }
+}
+
+impl MockProvider {
+    pub fn new(model: ModelConfig) -> Self {
+        Self {
+            model,
+            usage: Usage::default(),
+            latency: Duration::ZERO,
+            responses: Arc::new(Mutex::new(VecDeque::new())),
+            requests: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Queue an assistant reply with the given text
+    pub fn with_text<S: Into<String>>(self, text: S) -> Self {
+        self.with_message(Message::assistant().with_text(text))
+    }
+
+    /// Queue an assistant reply requesting a single tool call
+    pub fn with_tool_call(self, name: &str, arguments: Value) -> Self {
+        let id = format!("mock_call_{}", self.responses.lock().unwrap().len());
+        self.with_message(
+            Message::assistant().with_tool_request(id, Ok(ToolCall::new(name, arguments))),
+        )
+    }
+
+    /// Queue an arbitrary reply
+    pub fn with_message(self, message: Message) -> Self {
+        self.push(MockResponse::Message(message));
+        self
+    }
+
+    /// Queue an error
+    pub fn with_error(self, error: ProviderError) -> Self {
+        self.push(MockResponse::Error(error));
+        self
+    }
+
+    /// Report this usage with every reply
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Wait this long before each reply, as a slow provider would
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Every request received so far
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The number of responses that have not been returned yet
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    /// Make this mock available from `providers::create` under the given name
+    ///
+    /// Names of built-in providers keep creating those, so `mock` stays the scripted
+    /// provider; pick a name of the test's own. The created provider shares this mock's responses and request log, with the model
+    /// config passed to `create`.
+    pub fn register(&self, name: &str) {
+        REGISTRY
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), self.clone());
+    }
+
+    /// Remove a mock registered under the given name
+    pub fn unregister(name: &str) {
+        REGISTRY.lock().unwrap().remove(name);
+    }
+
+    pub(crate) fn registered(name: &str, model: ModelConfig) -> Option<Self> {
+        let registry = REGISTRY.lock().unwrap();
+        registry.get(name).map(|mock| Self {
+            model,
+            ..mock.clone()
+        })
+    }
+
+    fn push(&self, response: MockResponse) {
+        self.responses.lock().unwrap().push_back(response);
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "test_mock",
+            "Test Mock",
+            "Replies with a programmed sequence of responses, for testing",
+            "mock",
+            vec![],
+            "",
+            vec![],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.requests.lock().unwrap().push(MockRequest {
+            system: system.to_string(),
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+        });
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+
+        match self.responses.lock().unwrap().pop_front() {
+            Some(MockResponse::Message(message)) => Ok((
+                message,
+                ProviderUsage::new(self.model.model_name.clone(), self.usage.clone()),
+            )),
+            Some(MockResponse::Error(error)) => Err(error),
+            None => Err(ProviderError::ExecutionError(
+                "MockProvider has no more responses".to_string(),
+            )),
+        }
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let (message, usage) = self.complete(system, messages, tools).await?;
+        // Text arrives a word at a time, the way providers stream it
+        let deltas = message_deltas(message, usage)
+            .into_iter()
+            .flat_map(|delta| match delta {
+                MessageDelta::Text(text) => text
+                    .split_inclusive(' ')
+                    .map(|word| MessageDelta::Text(word.to_string()))
+                    .collect(),
+                delta => vec![delta],
+            })
+            .map(Ok);
+        Ok(Box::pin(futures::stream::iter(deltas)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_sequence() {
+        let provider = MockProvider::default()
+            .with_tool_call("developer__shell", json!({"command": "ls"}))
+            .with_error(ProviderError::RateLimitExceeded("slow down".to_string()))
+            .with_text("Done!");
+
+        let (message, _) = provider.complete("system", &[], &[]).await.unwrap();
+        let request = message.content[0].as_tool_request().unwrap();
+        assert_eq!(request.tool_call.as_ref().unwrap().name, "developer__shell");
+
+        let result = provider.complete("system", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::RateLimitExceeded(_))));
+
+        let (message, _) = provider
+            .complete("system", &[Message::user().with_text("hi")], &[])
+            .await
+            .unwrap();
+        assert_eq!(message.as_concat_text(), "Done!");
+        assert_eq!(provider.remaining(), 0);
+
+        assert!(provider.complete("system", &[], &[]).await.is_err());
+
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[2].messages[0].as_concat_text(), "hi");
+    }
+
+    #[tokio::test]
+    async fn test_registered_in_factory() {
+        let mock = MockProvider::default().with_text("from the factory");
+        mock.register("mock_factory_test");
+
+        let provider =
+            crate::providers::create("mock_factory_test", ModelConfig::new("gpt-4o".to_string()))
+                .unwrap();
+        let (message, usage) = provider.complete("", &[], &[]).await.unwrap();
+        MockProvider::unregister("mock_factory_test");
+
+        assert_eq!(message.as_concat_text(), "from the factory");
+        assert_eq!(usage.model, "gpt-4o");
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_streams_through_factory() {
+        let mock = MockProvider::default()
+            .with_usage(Usage::new(Some(10), Some(5), Some(15)))
+            .with_text("streamed from the factory");
+        mock.register("mock_stream_test");
+
+        let provider =
+            crate::providers::create("mock_stream_test", ModelConfig::new("gpt-4o".to_string()))
+                .unwrap();
+        let stream = provider.stream("", &[], &[]).await.unwrap();
+        let deltas: Vec<MessageDelta> = stream.map(|delta| delta.unwrap()).collect().await;
+        MockProvider::unregister("mock_stream_test");
+
+        // The text arrives in pieces, through every wrapper the factory adds
+        let texts: Vec<&str> = deltas
+            .iter()
+            .filter_map(|delta| match delta {
+                MessageDelta::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, vec!["streamed ", "from ", "the ", "factory"]);
+        assert!(matches!(
+            deltas.last(),
+            Some(MessageDelta::Usage(usage)) if usage.usage.total_tokens == Some(15)
+        ));
+        assert_eq!(mock.requests().len(), 1);
+    }
+}
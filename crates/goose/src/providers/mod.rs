@@ -1,17 +1,74 @@
+pub mod ai21;
 pub mod anthropic;
+pub mod anthropic_vertex;
 pub mod azure;
+pub mod azure_ai;
 pub mod base;
 pub mod bedrock;
+pub mod budget;
+pub mod cache;
+#[cfg(feature = "candle")]
+pub mod candle;
+pub mod circuit;
+pub mod cloudflare;
+pub mod cohere;
+pub mod compaction;
+pub mod concurrency;
+pub mod custom;
 pub mod databricks;
+pub mod deepseek;
 pub mod errors;
 mod factory;
+pub mod fallback;
+pub mod faults;
+pub mod fireworks;
 pub mod formats;
+pub mod gcpauth;
+pub mod github;
 pub mod google;
+pub mod google_live;
 pub mod groq;
+pub mod guardrail;
+pub mod hedge;
+pub mod huggingface;
+pub mod keys;
+pub mod koboldcpp;
+pub mod llamacpp;
+#[cfg(feature = "llama-cpp")]
+pub mod llamacpp_embedded;
+pub mod lmstudio;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod mock;
+pub mod moonshot;
+pub mod nvidia;
 pub mod oauth;
+pub mod oci;
 pub mod ollama;
 pub mod openai;
+pub mod openai_assistants;
 pub mod openrouter;
+pub mod perplexity;
+pub mod pricing;
+pub mod quota;
+pub mod qwen;
+pub mod ratelimit;
+pub mod replicate;
+pub mod retry;
+pub mod router;
+pub mod sagemaker;
+pub mod scripted;
+pub mod snowflake;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
+pub mod tgi;
+pub mod together;
+pub mod tracking;
 pub mod utils;
+pub mod vcr;
+pub mod vertexai;
+pub mod vllm;
+pub mod watsonx;
+pub mod xai;
+pub mod zhipu;
 
-pub use factory::{create, providers};
+pub use factory::{create, fetch_metadata, providers};
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::moonshot::create_request;
+use super::formats::openai::{get_usage, response_to_message};
+use super::keys::KeyPool;
+use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+// The international platform; accounts in mainland China use https://api.moonshot.cn
+pub const MOONSHOT_API_HOST: &str = "https://api.moonshot.ai";
+pub const MOONSHOT_DEFAULT_MODEL: &str = "kimi-k2-0711-preview";
+pub const MOONSHOT_KNOWN_MODELS: &[&str] = &[
+    "kimi-k2-0711-preview",
+    "kimi-latest",
+    "moonshot-v1-8k",
+    "moonshot-v1-32k",
+    "moonshot-v1-128k",
+    "moonshot-v1-auto",
+];
+
+pub const MOONSHOT_DOC_URL: &str = "https://platform.moonshot.ai/docs/api/chat";
+
+#[derive(serde::Serialize)]
+pub struct MoonshotProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_keys: KeyPool,
+    model: ModelConfig,
+}
+
+impl Default for MoonshotProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(MoonshotProvider::metadata().default_model);
+        MoonshotProvider::from_env(model).expect("Failed to initialize Moonshot provider")
+    }
+}
+
+impl MoonshotProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_keys = KeyPool::from_secret("MOONSHOT_API_KEY")?;
+        let host: String = config
+            .get("MOONSHOT_HOST")
+            .unwrap_or_else(|_| MOONSHOT_API_HOST.to_string());
+
+        let client = http_client("MOONSHOT")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_keys,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("v1/chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Authorization", format!("Bearer {}", key))
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for MoonshotProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "moonshot",
+            "Moonshot AI",
+            "Kimi models from Moonshot AI",
+            MOONSHOT_DEFAULT_MODEL,
+            MOONSHOT_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            MOONSHOT_DOC_URL,
+            vec![
+                ConfigKey::new("MOONSHOT_API_KEY", true, true, None),
+                ConfigKey::new("MOONSHOT_HOST", false, false, Some(MOONSHOT_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = vcr::post("moonshot", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
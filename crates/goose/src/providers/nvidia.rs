@@ -0,0 +1,143 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const NVIDIA_API_HOST: &str = "https://integrate.api.nvidia.com";
+pub const NVIDIA_DEFAULT_MODEL: &str = "meta/llama-3.3-70b-instruct";
+// Models in the build.nvidia.com catalog that support tool calling
+pub const NVIDIA_KNOWN_MODELS: &[&str] = &[
+    "meta/llama-3.3-70b-instruct",
+    "meta/llama-3.1-405b-instruct",
+    "meta/llama-3.1-70b-instruct",
+    "meta/llama-3.1-8b-instruct",
+    "nvidia/llama-3.3-nemotron-super-49b-v1",
+    "nvidia/llama-3.1-nemotron-ultra-253b-v1",
+    "nvidia/llama-3.1-nemotron-nano-8b-v1",
+    "mistralai/mistral-large-2-instruct",
+    "mistralai/mixtral-8x22b-instruct-v0.1",
+    "qwen/qwen2.5-coder-32b-instruct",
+];
+
+pub const NVIDIA_DOC_URL: &str = "https://build.nvidia.com/models";
+
+/// NVIDIA NIM, either the hosted endpoints behind build.nvidia.com or a self hosted NIM
+/// container, through their OpenAI compatible API
+#[derive(serde::Serialize)]
+pub struct NvidiaProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_key: Option<String>,
+    model: ModelConfig,
+}
+
+impl Default for NvidiaProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(NvidiaProvider::metadata().default_model);
+        NvidiaProvider::from_env(model).expect("Failed to initialize NVIDIA provider")
+    }
+}
+
+impl NvidiaProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let host: String = config
+            .get("NVIDIA_HOST")
+            .unwrap_or_else(|_| NVIDIA_API_HOST.to_string());
+        // Self hosted containers don't check for a key unless put behind a gateway that does
+        let api_key: Option<String> = config.get_secret("NVIDIA_API_KEY").ok();
+        if api_key.is_none() && host == NVIDIA_API_HOST {
+            return Err(anyhow!(
+                "NVIDIA_API_KEY is required for NVIDIA's hosted endpoints, set NVIDIA_HOST to use a self hosted NIM"
+            ));
+        }
+
+        let client = http_client("NVIDIA")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("v1/chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let mut request = self.client.post(url);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+        let response = retry::send(request.json(&payload)).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for NvidiaProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "nvidia",
+            "NVIDIA NIM",
+            "Models from NVIDIA's API catalog or a self hosted NIM",
+            NVIDIA_DEFAULT_MODEL,
+            NVIDIA_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            NVIDIA_DOC_URL,
+            vec![
+                ConfigKey::new("NVIDIA_API_KEY", false, true, None),
+                ConfigKey::new("NVIDIA_HOST", false, false, Some(NVIDIA_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        let response = vcr::post("nvidia", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
@@ -1,9 +1,11 @@
 use anyhow::Result;
+use async_trait::async_trait;
 use axum::{extract::Query, response::Html, routing::get, Router};
 use base64::Engine;
 use chrono::{DateTime, Utc};
 use etcetera::{choose_app_strategy, AppStrategy};
 use lazy_static::lazy_static;
+use mcp_client::transport::{AuthProvider, Error as TransportError};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sha2::Digest;
@@ -11,20 +13,37 @@ use std::{collections::HashMap, fs, net::SocketAddr, path::PathBuf, sync::Arc};
 use tokio::sync::{oneshot, Mutex as TokioMutex};
 use url::Url;
 
+use crate::config::Config;
+
+// Where the browser is sent back to after signing in to an MCP server
+const MCP_REDIRECT_URL: &str = "http://localhost:8021";
+
 lazy_static! {
     static ref OAUTH_MUTEX: TokioMutex<()> = TokioMutex::new(());
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct OidcEndpoints {
     authorization_endpoint: String,
     token_endpoint: String,
+    /// For dynamic client registration (RFC 7591), when the server supports it
+    #[serde(default)]
+    registration_endpoint: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct TokenData {
     access_token: String,
     expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+}
+
+impl TokenData {
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
 }
 
 struct TokenCache {
@@ -57,11 +76,7 @@ impl TokenCache {
     fn load_token(&self) -> Option<TokenData> {
         if let Ok(contents) = fs::read_to_string(&self.cache_path) {
             if let Ok(token_data) = serde_json::from_str::<TokenData>(&contents) {
-                if let Some(expires_at) = token_data.expires_at {
-                    if expires_at > Utc::now() {
-                        return Some(token_data);
-                    }
-                } else {
+                if !token_data.is_expired() {
                     return Some(token_data);
                 }
             }
@@ -95,8 +110,40 @@ async fn get_workspace_endpoints(host: &str) -> Result<OidcEndpoints> {
         ));
     }
 
-    let oidc_config: Value = resp.json().await?;
+    parse_endpoints(resp.json().await?)
+}
+
+/// Find the authorization server of an MCP server from its metadata, which lives on the
+/// origin of the server URL. Servers without metadata use the default paths of the MCP spec.
+async fn get_mcp_endpoints(server_url: &str) -> Result<OidcEndpoints> {
+    let origin = Url::parse(server_url)?.join("/")?;
+    let metadata_url = origin.join(".well-known/oauth-authorization-server")?;
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .get(metadata_url.clone())
+        .header("MCP-Protocol-Version", "2025-03-26")
+        .send()
+        .await?;
+
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(OidcEndpoints {
+            authorization_endpoint: origin.join("authorize")?.to_string(),
+            token_endpoint: origin.join("token")?.to_string(),
+            registration_endpoint: Some(origin.join("register")?.to_string()),
+        });
+    }
+    if !resp.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "Failed to get authorization server metadata from {}",
+            metadata_url
+        ));
+    }
 
+    parse_endpoints(resp.json().await?)
+}
+
+fn parse_endpoints(oidc_config: Value) -> Result<OidcEndpoints> {
     let authorization_endpoint = oidc_config
         .get("authorization_endpoint")
         .and_then(|v| v.as_str())
@@ -109,12 +156,108 @@ async fn get_workspace_endpoints(host: &str) -> Result<OidcEndpoints> {
         .ok_or_else(|| anyhow::anyhow!("token_endpoint not found in OIDC configuration"))?
         .to_string();
 
+    let registration_endpoint = oidc_config
+        .get("registration_endpoint")
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
     Ok(OidcEndpoints {
         authorization_endpoint,
         token_endpoint,
+        registration_endpoint,
     })
 }
 
+/// Register goose as a public client of the authorization server, returning its client id
+async fn register_client(registration_endpoint: &str, redirect_url: &str) -> Result<String> {
+    let registration = serde_json::json!({
+        "client_name": "goose",
+        "redirect_uris": [redirect_url],
+        "grant_types": ["authorization_code", "refresh_token"],
+        "response_types": ["code"],
+        "token_endpoint_auth_method": "none",
+    });
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(registration_endpoint)
+        .json(&registration)
+        .send()
+        .await?;
+
+    if !resp.status().is_success() {
+        let err_text = resp.text().await?;
+        return Err(anyhow::anyhow!("Failed to register client: {}", err_text));
+    }
+
+    let registered: Value = resp.json().await?;
+    registered
+        .get("client_id")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("client_id not found in registration response"))
+}
+
+/// Request a token from the token endpoint with the given grant
+async fn request_token(token_endpoint: &str, params: &[(&str, &str)]) -> Result<TokenData> {
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(token_endpoint)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .form(params)
+        .send()
+        .await?;
+
+    if !resp.status().is_success() {
+        let err_text = resp.text().await?;
+        return Err(anyhow::anyhow!("Failed to get token: {}", err_text));
+    }
+
+    let token_response: Value = resp.json().await?;
+    let access_token = token_response
+        .get("access_token")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("access_token not found in token response"))?
+        .to_string();
+
+    let expires_in = token_response
+        .get("expires_in")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(3600);
+
+    let expires_at = Utc::now() + chrono::Duration::seconds(expires_in as i64);
+
+    let refresh_token = token_response
+        .get("refresh_token")
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
+    Ok(TokenData {
+        access_token,
+        expires_at: Some(expires_at),
+        refresh_token,
+    })
+}
+
+/// Trade a refresh token for a new access token, keeping the refresh token unless the server
+/// rotates it
+async fn refresh_token(
+    endpoints: &OidcEndpoints,
+    client_id: &str,
+    refresh_token: &str,
+) -> Result<TokenData> {
+    let params = [
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("client_id", client_id),
+    ];
+    let mut token = request_token(&endpoints.token_endpoint, &params).await?;
+    token
+        .refresh_token
+        .get_or_insert_with(|| refresh_token.to_string());
+    Ok(token)
+}
+
 struct OAuthFlow {
     endpoints: OidcEndpoints,
     client_id: String,
@@ -147,15 +290,19 @@ impl OAuthFlow {
             base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(digest)
         };
 
-        let params = [
+        let scope = self.scopes.join(" ");
+        let mut params = vec![
             ("response_type", "code"),
             ("client_id", &self.client_id),
             ("redirect_uri", &self.redirect_url),
-            ("scope", &self.scopes.join(" ")),
             ("state", &self.state),
             ("code_challenge", &challenge),
             ("code_challenge_method", "S256"),
         ];
+        // Servers that registered the client on the fly may not know of any scopes
+        if !scope.is_empty() {
+            params.push(("scope", &scope));
+        }
 
         format!(
             "{}?{}",
@@ -173,40 +320,9 @@ impl OAuthFlow {
             ("client_id", &self.client_id),
         ];
 
-        let client = reqwest::Client::new();
-        let resp = client
-            .post(&self.endpoints.token_endpoint)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .form(&params)
-            .send()
-            .await?;
-
-        if !resp.status().is_success() {
-            let err_text = resp.text().await?;
-            return Err(anyhow::anyhow!(
-                "Failed to exchange code for token: {}",
-                err_text
-            ));
-        }
-
-        let token_response: Value = resp.json().await?;
-        let access_token = token_response
-            .get("access_token")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("access_token not found in token response"))?
-            .to_string();
-
-        let expires_in = token_response
-            .get("expires_in")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(3600);
-
-        let expires_at = Utc::now() + chrono::Duration::seconds(expires_in as i64);
-
-        Ok(TokenData {
-            access_token,
-            expires_at: Some(expires_at),
-        })
+        request_token(&self.endpoints.token_endpoint, &params)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to exchange code for token: {}", e))
     }
 
     async fn execute(&self) -> Result<TokenData> {
@@ -318,6 +434,135 @@ pub(crate) async fn get_oauth_token_async(
     Ok(token.access_token)
 }
 
+/// What goose keeps in the secret storage for an MCP server it signed in to
+#[derive(Serialize, Deserialize)]
+struct McpCredentials {
+    endpoints: OidcEndpoints,
+    client_id: String,
+    token: TokenData,
+}
+
+/// Signs in to a remote MCP server following the MCP authorization spec
+///
+/// Nothing happens until the server first answers 401. Goose then finds the authorization
+/// server from the server's metadata, registers itself as a client and signs in through the
+/// browser with PKCE. The tokens are kept in the secret storage, per server origin, and
+/// refreshed when they expire.
+pub struct McpOAuth {
+    server_url: String,
+    secret_key: String,
+    credentials: TokioMutex<Option<McpCredentials>>,
+}
+
+impl McpOAuth {
+    pub fn new(server_url: &str) -> Self {
+        let origin = Url::parse(server_url)
+            .map(|url| url.origin().ascii_serialization())
+            .unwrap_or_else(|_| server_url.to_string());
+        let hash = format!("{:x}", sha2::Sha256::digest(origin.as_bytes()));
+        let secret_key = format!("mcp_oauth_{}", &hash[..16]);
+        let credentials = Config::global().get_secret(&secret_key).ok();
+
+        Self {
+            server_url: server_url.to_string(),
+            secret_key,
+            credentials: TokioMutex::new(credentials),
+        }
+    }
+
+    /// The stored access token, refreshed if it expired; None until signed in
+    async fn access_token(&self) -> Option<String> {
+        let mut credentials = self.credentials.lock().await;
+        let stored = credentials.as_mut()?;
+        if stored.token.is_expired() {
+            if let Err(e) = self.refresh(stored).await {
+                tracing::warn!("Failed to refresh token for {}: {}", self.server_url, e);
+                return None;
+            }
+        }
+        Some(stored.token.access_token.clone())
+    }
+
+    async fn refresh(&self, stored: &mut McpCredentials) -> Result<()> {
+        let refresh = stored
+            .token
+            .refresh_token
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("No refresh token"))?;
+        stored.token = refresh_token(&stored.endpoints, &stored.client_id, &refresh).await?;
+        self.save(stored)
+    }
+
+    fn save(&self, stored: &McpCredentials) -> Result<()> {
+        Config::global().set_secret(&self.secret_key, serde_json::to_value(stored)?)?;
+        Ok(())
+    }
+
+    /// Get a new access token after the server rejected the current one
+    async fn sign_in(&self) -> Result<String> {
+        // Only one flow at a time, since they share the redirect port
+        let _guard = OAUTH_MUTEX.lock().await;
+        let mut credentials = self.credentials.lock().await;
+
+        // The token may have been revoked early, but the refresh token could still be good
+        if let Some(stored) = credentials.as_mut() {
+            if stored.token.refresh_token.is_some() && self.refresh(stored).await.is_ok() {
+                return Ok(stored.token.access_token.clone());
+            }
+        }
+
+        let endpoints = get_mcp_endpoints(&self.server_url).await?;
+        let client_id = match credentials.take() {
+            Some(stored) => stored.client_id,
+            None => match &endpoints.registration_endpoint {
+                Some(registration_endpoint) => {
+                    register_client(registration_endpoint, MCP_REDIRECT_URL).await?
+                }
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "{} requires authorization, but does not support client registration",
+                        self.server_url
+                    ))
+                }
+            },
+        };
+
+        let flow = OAuthFlow::new(
+            endpoints.clone(),
+            client_id.clone(),
+            MCP_REDIRECT_URL.to_string(),
+            vec![],
+        );
+        let token = flow.execute().await?;
+        let access_token = token.access_token.clone();
+
+        let stored = McpCredentials {
+            endpoints,
+            client_id,
+            token,
+        };
+        self.save(&stored)?;
+        *credentials = Some(stored);
+        Ok(access_token)
+    }
+}
+
+#[async_trait]
+impl AuthProvider for McpOAuth {
+    async fn authorization(&self) -> Option<String> {
+        self.access_token()
+            .await
+            .map(|token| format!("Bearer {}", token))
+    }
+
+    async fn reauthorize(&self) -> Result<String, TransportError> {
+        self.sign_in()
+            .await
+            .map(|token| format!("Bearer {}", token))
+            .map_err(|e| TransportError::Authorization(e.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,6 +597,80 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_mcp_endpoints() -> Result<()> {
+        let mock_server = MockServer::start().await;
+
+        // Without metadata, the default paths on the origin are used
+        let endpoints = get_mcp_endpoints(&format!("{}/mcp", mock_server.uri())).await?;
+        assert_eq!(
+            endpoints.token_endpoint,
+            format!("{}/token", mock_server.uri())
+        );
+        assert_eq!(
+            endpoints.registration_endpoint,
+            Some(format!("{}/register", mock_server.uri()))
+        );
+
+        Mock::given(method("GET"))
+            .and(path("/.well-known/oauth-authorization-server"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "authorization_endpoint": "https://auth.example.com/authorize",
+                "token_endpoint": "https://auth.example.com/token",
+                "registration_endpoint": "https://auth.example.com/register"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let endpoints = get_mcp_endpoints(&format!("{}/mcp", mock_server.uri())).await?;
+        assert_eq!(endpoints.token_endpoint, "https://auth.example.com/token");
+        assert_eq!(
+            endpoints.registration_endpoint.as_deref(),
+            Some("https://auth.example.com/register")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_and_refresh() -> Result<()> {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/register"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .set_body_json(serde_json::json!({ "client_id": "goose-123" })),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "new-token",
+                "expires_in": 60
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client_id =
+            register_client(&format!("{}/register", mock_server.uri()), MCP_REDIRECT_URL).await?;
+        assert_eq!(client_id, "goose-123");
+
+        let endpoints = OidcEndpoints {
+            authorization_endpoint: format!("{}/authorize", mock_server.uri()),
+            token_endpoint: format!("{}/token", mock_server.uri()),
+            registration_endpoint: None,
+        };
+        let token = refresh_token(&endpoints, &client_id, "refresh-me").await?;
+        assert_eq!(token.access_token, "new-token");
+        // The server did not rotate the refresh token, so the old one still applies
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh-me"));
+        assert!(!token.is_expired());
+
+        Ok(())
+    }
+
     #[test]
     fn test_token_cache() -> Result<()> {
         let cache = TokenCache::new(
@@ -363,6 +682,7 @@ mod tests {
         let token_data = TokenData {
             access_token: "test-token".to_string(),
             expires_at: Some(Utc::now() + chrono::Duration::hours(1)),
+            refresh_token: None,
         };
 
         cache.save_token(&token_data)?;
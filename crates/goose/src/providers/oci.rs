@@ -0,0 +1,315 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::Engine;
+use jsonwebtoken::{Algorithm, EncodingKey};
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::oci::{create_request, get_usage, response_to_message};
+use super::utils::{emit_debug_trace, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const OCI_DEFAULT_REGION: &str = "us-chicago-1";
+pub const OCI_DEFAULT_MODEL: &str = "meta.llama-3.3-70b-instruct";
+// Models in the GENERIC API format, see `formats::oci::create_request`
+pub const OCI_KNOWN_MODELS: &[&str] = &[
+    "meta.llama-3.3-70b-instruct",
+    "meta.llama-4-maverick-17b-128e-instruct-fp8",
+    "meta.llama-4-scout-17b-16e-instruct",
+    "xai.grok-3",
+    "xai.grok-3-mini",
+    "openai.gpt-oss-120b",
+];
+
+pub const OCI_DOC_URL: &str =
+    "https://docs.oracle.com/en-us/iaas/Content/generative-ai/pretrained-models.htm";
+
+// The dated version of the Generative AI inference API
+const OCI_API_VERSION: &str = "20231130";
+
+// The headers a request with a body is signed over, in order
+const SIGNED_HEADERS: &[&str] = &[
+    "date",
+    "(request-target)",
+    "host",
+    "content-length",
+    "content-type",
+    "x-content-sha256",
+];
+
+/// Models on Oracle Cloud Infrastructure Generative AI, billed to a compartment
+///
+/// Requests are signed with an API signing key of an OCI user, with the same tenancy, user,
+/// fingerprint and key as the `~/.oci/config` of the OCI CLI. The model is a model id or
+/// OCID for on demand models, or the OCID of a dedicated AI cluster endpoint.
+#[derive(serde::Serialize)]
+pub struct OciProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    compartment_id: String,
+    /// `{tenancy}/{user}/{fingerprint}`, which names the key requests are signed with
+    key_id: String,
+    #[serde(skip)]
+    private_key: String,
+    model: ModelConfig,
+}
+
+impl Default for OciProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(OciProvider::metadata().default_model);
+        OciProvider::from_env(model).expect("Failed to initialize OCI Generative AI provider")
+    }
+}
+
+impl OciProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let tenancy: String = config.get("OCI_TENANCY_OCID")?;
+        let user: String = config.get("OCI_USER_OCID")?;
+        let fingerprint: String = config.get("OCI_FINGERPRINT")?;
+        let private_key: String = config.get_secret("OCI_PRIVATE_KEY")?;
+        // Models are billed to the tenancy's root compartment unless another is given
+        let compartment_id: String = config
+            .get("OCI_COMPARTMENT_OCID")
+            .unwrap_or_else(|_| tenancy.clone());
+        let region: String = config
+            .get("OCI_REGION")
+            .unwrap_or_else(|_| OCI_DEFAULT_REGION.to_string());
+        let host: String = config.get("OCI_GENAI_HOST").unwrap_or_else(|_| {
+            format!(
+                "https://inference.generativeai.{}.oci.oraclecloud.com",
+                region
+            )
+        });
+
+        let client = http_client("OCI")?;
+
+        Ok(Self {
+            client,
+            host,
+            compartment_id,
+            key_id: format!("{}/{}/{}", tenancy, user, fingerprint),
+            private_key,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url
+            .join(&format!("{}/actions/chat", OCI_API_VERSION))
+            .map_err(|e| {
+                ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+            })?;
+
+        let body = serde_json::to_vec(&payload)
+            .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
+        let date = chrono::Utc::now()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        let headers = signed_headers(&url, &date, &body);
+        let signature = sign(&signing_string("post", &url, &headers), &self.private_key)
+            .map_err(|e| ProviderError::Authentication(e.to_string()))?;
+        let authorization = format!(
+            "Signature version=\"1\",keyId=\"{}\",algorithm=\"rsa-sha256\",headers=\"{}\",signature=\"{}\"",
+            self.key_id,
+            SIGNED_HEADERS.join(" "),
+            signature
+        );
+
+        let mut request = self.client.post(url).header("authorization", authorization);
+        // The host and content length are set by the client as they were signed
+        for (name, value) in &headers {
+            if name != "host" && name != "content-length" {
+                request = request.header(name.as_str(), value);
+            }
+        }
+        let response = retry::send(request.body(body)).await?;
+
+        let status = response.status();
+        let payload: Option<Value> = response.json().await.ok();
+        let message = payload
+            .as_ref()
+            .and_then(|p| p["message"].as_str())
+            .unwrap_or("Unknown error")
+            .to_string();
+
+        // https://docs.oracle.com/en-us/iaas/Content/API/References/apierrors.htm
+        match status {
+            StatusCode::OK => payload.ok_or_else(|| {
+                ProviderError::RequestFailed("Response body is not valid JSON".to_string())
+            }),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure the signing key, fingerprint and OCIDs are valid and a policy lets the user use generative-ai-family in the compartment. \
+                    Status: {}. Message: {}", status, message)))
+            }
+            StatusCode::BAD_REQUEST => {
+                if message.contains("context length") || message.contains("too long") {
+                    return Err(ProviderError::ContextLengthExceeded(message));
+                }
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}. Message: {}",
+                    status, message
+                )))
+            }
+            StatusCode::TOO_MANY_REQUESTS => Err(ProviderError::RateLimitExceeded(message)),
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                Err(ProviderError::ServerError(message))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}",
+                    format!(
+                        "Provider request failed with status: {}. Payload: {:?}",
+                        status, payload
+                    )
+                );
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}. Message: {}",
+                    status, message
+                )))
+            }
+        }
+    }
+}
+
+/// The values of the headers in `SIGNED_HEADERS` other than `(request-target)`
+fn signed_headers(url: &Url, date: &str, body: &[u8]) -> Vec<(String, String)> {
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let digest = base64::engine::general_purpose::STANDARD.encode(Sha256::digest(body));
+    vec![
+        ("date".to_string(), date.to_string()),
+        ("host".to_string(), host),
+        ("content-length".to_string(), body.len().to_string()),
+        ("content-type".to_string(), "application/json".to_string()),
+        ("x-content-sha256".to_string(), digest),
+    ]
+}
+
+/// The string an OCI request signature covers: each of `SIGNED_HEADERS` as `name: value`
+/// on its own line
+///
+/// https://docs.oracle.com/en-us/iaas/Content/API/Concepts/signingrequests.htm
+fn signing_string(method: &str, url: &Url, headers: &[(String, String)]) -> String {
+    SIGNED_HEADERS
+        .iter()
+        .map(|&name| {
+            let value = if name == "(request-target)" {
+                match url.query() {
+                    Some(query) => format!("{} {}?{}", method, url.path(), query),
+                    None => format!("{} {}", method, url.path()),
+                }
+            } else {
+                headers
+                    .iter()
+                    .find(|(header, _)| header == name)
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_default()
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Sign with RSA PKCS#1 v1.5 over SHA-256, the same scheme as a JWT's RS256, returning the
+/// signature in standard base64 rather than a JWT's URL safe base64
+fn sign(signing_string: &str, private_key: &str) -> Result<String> {
+    let key = EncodingKey::from_rsa_pem(private_key.as_bytes())
+        .map_err(|e| anyhow!("Invalid OCI_PRIVATE_KEY: {}", e))?;
+    let signature = jsonwebtoken::crypto::sign(signing_string.as_bytes(), &key, Algorithm::RS256)?;
+    let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(signature)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(signature))
+}
+
+#[async_trait]
+impl Provider for OciProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "oci",
+            "OCI Generative AI",
+            "Llama, Grok and other models on Oracle Cloud Infrastructure",
+            OCI_DEFAULT_MODEL,
+            OCI_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            OCI_DOC_URL,
+            vec![
+                ConfigKey::new("OCI_TENANCY_OCID", true, false, None),
+                ConfigKey::new("OCI_USER_OCID", true, false, None),
+                ConfigKey::new("OCI_FINGERPRINT", true, false, None),
+                ConfigKey::new("OCI_PRIVATE_KEY", true, true, None),
+                ConfigKey::new("OCI_COMPARTMENT_OCID", false, false, None),
+                ConfigKey::new("OCI_REGION", false, false, Some(OCI_DEFAULT_REGION)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, &self.compartment_id, system, messages, tools)?;
+
+        let response = vcr::post("oci", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = response["modelId"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| self.model.model_name.clone());
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_string() {
+        let url = Url::parse(
+            "https://inference.generativeai.us-chicago-1.oci.oraclecloud.com/20231130/actions/chat",
+        )
+        .unwrap();
+        let headers = signed_headers(&url, "Thu, 05 Jan 2014 21:31:40 GMT", b"{}");
+        assert_eq!(
+            signing_string("post", &url, &headers),
+            "date: Thu, 05 Jan 2014 21:31:40 GMT\n\
+             (request-target): post /20231130/actions/chat\n\
+             host: inference.generativeai.us-chicago-1.oci.oraclecloud.com\n\
+             content-length: 2\n\
+             content-type: application/json\n\
+             x-content-sha256: RBNvo1WzZ4oRRq0W9+hknpT7T8If536DEMBg9hyq/4o="
+        );
+    }
+}
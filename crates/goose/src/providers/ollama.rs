@@ -1,16 +1,23 @@
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    message_deltas, ConfigKey, MessageDelta, MessageStream, Provider, ProviderMetadata,
+    ProviderUsage, Usage,
+};
 use super::errors::ProviderError;
-use super::utils::{get_model, handle_response_openai_compat};
+use super::utils::{get_model, handle_response_openai_compat, http_client, json_lines};
+use super::{retry, vcr};
 use crate::message::Message;
 use crate::model::ModelConfig;
+use crate::providers::formats::ollama::{
+    chat_chunk_to_deltas, create_chat_request, get_chat_usage,
+};
 use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::StreamExt;
 use indoc::formatdoc;
 use mcp_core::tool::Tool;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde_json::Value;
-use std::time::Duration;
 use url::Url;
 
 pub const OLLAMA_HOST: &str = "localhost";
@@ -42,9 +49,7 @@ impl OllamaProvider {
             .get("OLLAMA_HOST")
             .unwrap_or_else(|_| OLLAMA_HOST.to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = http_client("OLLAMA")?;
 
         Ok(Self {
             client,
@@ -53,7 +58,8 @@ impl OllamaProvider {
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    /// The URL of `path` on the Ollama server
+    fn url(&self, path: &str) -> Result<Url, ProviderError> {
         // TODO: remove this later when the UI handles provider config refresh
         // OLLAMA_HOST is sometimes just the 'host' or 'host:port' without a scheme
         let base = if self.host.starts_with("http://") || self.host.starts_with("https://") {
@@ -73,16 +79,91 @@ impl OllamaProvider {
             })?;
         }
 
-        let url = base_url.join("v1/chat/completions").map_err(|e| {
+        base_url.join(path).map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
-        })?;
+        })
+    }
 
-        let response = self.client.post(url).json(&payload).send().await?;
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let url = self.url("v1/chat/completions")?;
+        let response = retry::send(self.client.post(url).json(&payload)).await?;
 
         handle_response_openai_compat(response).await
     }
 }
 
+/// The system prompt with the developer extension's instructions replaced by a shorter
+/// version that local models follow better
+fn developer_system_prompt(system: &str) -> String {
+    if let Some(dev_section) = system.split("## developer").nth(1) {
+        if let (Some(start_idx), Some(end_idx)) = (
+            dev_section.find("### Instructions"),
+            dev_section.find("operating system:"),
+        ) {
+            let new_instructions = formatdoc! {r#"
+    The Developer extension enables you to edit code files, execute shell commands, and capture screen/window content. These tools allow for various development and debugging workflows.
+    Available Tools:
+    1. Shell Execution (`shell`)
+    Executes commands in the shell and returns the combined output and error messages.
+    Use cases:
+    - Running scripts: `python script.py`
+    - Installing dependencies: `pip install -r requirements.txt`
+    - Checking system information: `uname -a`, `df -h`
+    - Searching for files or text: **Use `rg` (ripgrep) instead of `find` or `ls -r`**
+      - Find a file: `rg --files | rg example.py`
+      - Search within files: `rg 'class Example'`
+    Best Practices:
+    - **Avoid commands with large output** (pipe them to a file if necessary).
+    - **Run background processes** if they take a long time (e.g., `uvicorn main:app &`).
+    - **git commands can be run on the shell, however if the git extension is installed, you should use the git tool instead.
+    - **If the shell command is a rm, mv, or cp, you should verify with the user before running the command.
+    2. Text Editor (`text_editor`)
+    Performs file-based operations such as viewing, writing, replacing text, and undoing edits.
+    Commands:
+    - view: Read the content of a file.
+    - write: Create or overwrite a file. Caution: Overwrites the entire file!
+    - str_replace: Replace a specific string in a file.
+    - undo_edit: Revert the last edit.
+    Example Usage:
+    text_editor(command="view", file_path="/absolute/path/to/file.py")
+    text_editor(command="write", file_path="/absolute/path/to/file.py", file_text="print('hello world')")
+    text_editor(command="str_replace", file_path="/absolute/path/to/file.py", old_str="hello world", new_str="goodbye world")
+    text_editor(command="undo_edit", file_path="/absolute/path/to/file.py")
+    Protocol for Text Editor:
+    For edit and replace commands, please verify what you are editing with the user before running the command.
+    - User: "Please edit the file /absolute/path/to/file.py"
+    - Assistant: "Ok sounds good, I'll be editing the file /absolute/path/to/file.py and creating modifications xyz to the file. Let me know whether you'd like to proceed."
+    - User: "Yes, please proceed."
+    - Assistant: "I've created the modifications xyz to the file /absolute/path/to/file.py"
+    3. List Windows (`list_windows`)
+    Lists all visible windows with their titles.
+    Use this to find window titles for screen capture.
+    4. Screen Capture (`screen_capture`)
+    Takes a screenshot of a display or specific window.
+    Options:
+    - Capture display: `screen_capture(display=0)`  # Main display
+    - Capture window: `screen_capture(window_title="Window Title")`
+    Info: at the start of the session, the user's directory is:
+    "#};
+
+            let before_dev = system.split("## developer").next().unwrap_or("");
+            let after_marker = &dev_section[end_idx..];
+
+            format!(
+                "{}## developer{}### Instructions\n{}{}",
+                before_dev,
+                &dev_section[..start_idx],
+                new_instructions,
+                after_marker
+            )
+        } else {
+            system.to_string()
+        }
+    } else {
+        system.to_string()
+    }
+}
+
 #[async_trait]
 impl Provider for OllamaProvider {
     fn metadata() -> ProviderMetadata {
@@ -116,74 +197,7 @@ impl Provider for OllamaProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        // Transform the system message to replace developer instructions
-        let modified_system = if let Some(dev_section) = system.split("## developer").nth(1) {
-            if let (Some(start_idx), Some(end_idx)) = (
-                dev_section.find("### Instructions"),
-                dev_section.find("operating system:"),
-            ) {
-                let new_instructions = formatdoc! {r#"
-        The Developer extension enables you to edit code files, execute shell commands, and capture screen/window content. These tools allow for various development and debugging workflows.
-        Available Tools:
-        1. Shell Execution (`shell`)
-        Executes commands in the shell and returns the combined output and error messages.
-        Use cases:
-        - Running scripts: `python script.py`
-        - Installing dependencies: `pip install -r requirements.txt`
-        - Checking system information: `uname -a`, `df -h`
-        - Searching for files or text: **Use `rg` (ripgrep) instead of `find` or `ls -r`**
-          - Find a file: `rg --files | rg example.py`
-          - Search within files: `rg 'class Example'`
-        Best Practices:
-        - **Avoid commands with large output** (pipe them to a file if necessary).
-        - **Run background processes** if they take a long time (e.g., `uvicorn main:app &`).
-        - **git commands can be run on the shell, however if the git extension is installed, you should use the git tool instead.
-        - **If the shell command is a rm, mv, or cp, you should verify with the user before running the command.
-        2. Text Editor (`text_editor`)
-        Performs file-based operations such as viewing, writing, replacing text, and undoing edits.
-        Commands:
-        - view: Read the content of a file.
-        - write: Create or overwrite a file. Caution: Overwrites the entire file!
-        - str_replace: Replace a specific string in a file.
-        - undo_edit: Revert the last edit.
-        Example Usage:
-        text_editor(command="view", file_path="/absolute/path/to/file.py")
-        text_editor(command="write", file_path="/absolute/path/to/file.py", file_text="print('hello world')")
-        text_editor(command="str_replace", file_path="/absolute/path/to/file.py", old_str="hello world", new_str="goodbye world")
-        text_editor(command="undo_edit", file_path="/absolute/path/to/file.py")
-        Protocol for Text Editor:
-        For edit and replace commands, please verify what you are editing with the user before running the command.
-        - User: "Please edit the file /absolute/path/to/file.py"
-        - Assistant: "Ok sounds good, I'll be editing the file /absolute/path/to/file.py and creating modifications xyz to the file. Let me know whether you'd like to proceed."
-        - User: "Yes, please proceed."
-        - Assistant: "I've created the modifications xyz to the file /absolute/path/to/file.py"
-        3. List Windows (`list_windows`)
-        Lists all visible windows with their titles.
-        Use this to find window titles for screen capture.
-        4. Screen Capture (`screen_capture`)
-        Takes a screenshot of a display or specific window.
-        Options:
-        - Capture display: `screen_capture(display=0)`  # Main display
-        - Capture window: `screen_capture(window_title="Window Title")`
-        Info: at the start of the session, the user's directory is:
-        "#};
-
-                let before_dev = system.split("## developer").next().unwrap_or("");
-                let after_marker = &dev_section[end_idx..];
-
-                format!(
-                    "{}## developer{}### Instructions\n{}{}",
-                    before_dev,
-                    &dev_section[..start_idx],
-                    new_instructions,
-                    after_marker
-                )
-            } else {
-                system.to_string()
-            }
-        } else {
-            system.to_string()
-        };
+        let modified_system = developer_system_prompt(system);
 
         let payload = create_request(
             &self.model,
@@ -192,7 +206,7 @@ impl Provider for OllamaProvider {
             tools,
             &super::utils::ImageFormat::OpenAi,
         )?;
-        let response = self.post(payload.clone()).await?;
+        let response = vcr::post("ollama", &payload, || self.post(payload.clone())).await?;
 
         // Parse response
         let message = response_to_message(response.clone())?;
@@ -208,4 +222,57 @@ impl Provider for OllamaProvider {
         super::utils::emit_debug_trace(self, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        if vcr::is_active() {
+            let (message, usage) = self.complete(system, messages, tools).await?;
+            let deltas = message_deltas(message, usage).into_iter().map(Ok);
+            return Ok(Box::pin(futures::stream::iter(deltas)));
+        }
+
+        // Streams from the native API, which reports token counts where the OpenAI
+        // compatible one doesn't
+        let system = developer_system_prompt(system);
+        let payload = create_chat_request(&self.model, &system, messages, tools)?;
+        let request = self.client.post(self.url("api/chat")?).json(&payload);
+        let response = retry::send(request).await?;
+        let status = response.status();
+        if status != StatusCode::OK {
+            let body: Value = response.json().await.unwrap_or_default();
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(ProviderError::RequestFailed(format!(
+                "{} (status {})",
+                error,
+                status.as_u16()
+            )));
+        }
+
+        let mut lines = json_lines(response);
+        Ok(Box::pin(async_stream::try_stream! {
+            let mut model = self.model.model_name.clone();
+            let mut usage = Usage::default();
+            let mut tool_calls = 0;
+            while let Some(chunk) = lines.next().await {
+                let chunk = chunk?;
+                for delta in chat_chunk_to_deltas(&chunk, &mut tool_calls)? {
+                    yield delta;
+                }
+                if chunk["done"].as_bool() == Some(true) {
+                    if let Some(name) = chunk["model"].as_str() {
+                        model = name.to_string();
+                    }
+                    usage = get_chat_usage(&chunk).unwrap_or_else(|e| {
+                        tracing::debug!("Failed to get usage data: {}", e);
+                        Usage::default()
+                    });
+                }
+            }
+            yield MessageDelta::Usage(ProviderUsage::new(model, usage));
+        }))
+    }
 }
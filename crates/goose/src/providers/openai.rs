@@ -1,16 +1,31 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use reqwest::Client;
-use serde_json::Value;
-use std::time::Duration;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde_json::{json, Value};
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    message_deltas, ConfigKey, MessageDelta, MessageStream, Provider, ProviderMetadata,
+    ProviderUsage, Usage,
+};
 use super::errors::ProviderError;
-use super::formats::openai::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
+use super::formats::openai::{
+    create_request, get_usage, response_chunk_to_deltas, response_to_message,
+};
+use super::formats::streaming::inspect_stream;
+use super::keys::KeyPool;
+use super::openai_assistants::{
+    delete_assistant, AssistantsSession, OpenAiApiMode, OPENAI_API_MODE_CONFIG_KEY,
+    OPENAI_ASSISTANTS_CONFIG_KEY,
+};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, sse_data, ImageFormat,
+};
+use super::{retry, vcr};
 use crate::message::Message;
 use crate::model::ModelConfig;
+use futures::StreamExt;
 use mcp_core::tool::Tool;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub const OPEN_AI_DEFAULT_MODEL: &str = "gpt-4o";
 pub const OPEN_AI_KNOWN_MODELS: &[&str] = &[
@@ -29,10 +44,16 @@ pub struct OpenAiProvider {
     client: Client,
     host: String,
     base_path: String,
-    api_key: String,
+    api_keys: KeyPool,
     organization: Option<String>,
     project: Option<String>,
     model: ModelConfig,
+    /// Set when configured to run through the Assistants API instead of chat completions
+    #[serde(skip)]
+    assistants: Option<AssistantsSession>,
+    /// Whether streamed requests ask for the usage, cleared once the server rejects it
+    #[serde(skip)]
+    stream_usage: AtomicBool,
 }
 
 impl Default for OpenAiProvider {
@@ -45,7 +66,7 @@ impl Default for OpenAiProvider {
 impl OpenAiProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
         let config = crate::config::Config::global();
-        let api_key: String = config.get_secret("OPENAI_API_KEY")?;
+        let api_keys = KeyPool::from_secret("OPENAI_API_KEY")?;
         let host: String = config
             .get("OPENAI_HOST")
             .unwrap_or_else(|_| "https://api.openai.com".to_string());
@@ -54,49 +75,113 @@ impl OpenAiProvider {
             .unwrap_or_else(|_| "v1/chat/completions".to_string());
         let organization: Option<String> = config.get("OPENAI_ORGANIZATION").ok();
         let project: Option<String> = config.get("OPENAI_PROJECT").ok();
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let mode: OpenAiApiMode = config.get(OPENAI_API_MODE_CONFIG_KEY).unwrap_or_default();
+        let assistants = match mode {
+            OpenAiApiMode::Chat => None,
+            OpenAiApiMode::Assistants => Some(AssistantsSession::new(
+                config.get(OPENAI_ASSISTANTS_CONFIG_KEY).unwrap_or_default(),
+            )),
+        };
+        let client = http_client("OPENAI")?;
 
         Ok(Self {
             client,
             host,
             base_path,
-            api_key,
+            api_keys,
             organization,
             project,
             model,
+            assistants,
+            stream_usage: AtomicBool::new(true),
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    /// Send a request to `path` on the host with the account's headers, and a key from the pool
+    ///
+    /// `build` adds the rest of the request, for each key it is sent with.
+    pub(super) async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response, ProviderError> {
         let base_url = url::Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
-        let url = base_url.join(&self.base_path).map_err(|e| {
+        let url = base_url.join(path).map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
         })?;
 
-        let mut request = self
-            .client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.api_key));
+        let request = |key: &str| {
+            let mut request = self
+                .client
+                .request(method.clone(), url.clone())
+                .header("Authorization", format!("Bearer {}", key));
 
-        // Add organization header if present
-        if let Some(org) = &self.organization {
-            request = request.header("OpenAI-Organization", org);
-        }
+            // Add organization header if present
+            if let Some(org) = &self.organization {
+                request = request.header("OpenAI-Organization", org);
+            }
+
+            // Add project header if present
+            if let Some(project) = &self.project {
+                request = request.header("OpenAI-Project", project);
+            }
 
-        // Add project header if present
-        if let Some(project) = &self.project {
-            request = request.header("OpenAI-Project", project);
+            build(request)
+        };
+        retry::send_keyed(&self.api_keys, request).await
+    }
+
+    // A provider with the same account and no Assistants API session
+    fn detached(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            host: self.host.clone(),
+            base_path: self.base_path.clone(),
+            api_keys: self.api_keys.clone(),
+            organization: self.organization.clone(),
+            project: self.project.clone(),
+            model: self.model.clone(),
+            assistants: None,
+            stream_usage: AtomicBool::new(self.stream_usage.load(Ordering::Relaxed)),
         }
+    }
 
-        let response = request.json(&payload).send().await?;
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let response = self
+            .send(Method::POST, &self.base_path, |request| {
+                request.json(&payload)
+            })
+            .await?;
 
         handle_response_openai_compat(response).await
     }
 }
 
+impl Drop for OpenAiProvider {
+    fn drop(&mut self) {
+        // An assistant created for the session would otherwise stay in the account for good
+        let Some(assistant_id) = self
+            .assistants
+            .as_mut()
+            .and_then(AssistantsSession::take_created_assistant)
+        else {
+            return;
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let provider = self.detached();
+                runtime.spawn(async move { delete_assistant(&provider, &assistant_id).await });
+            }
+            Err(_) => tracing::warn!(
+                "Assistant {} was left in the account, there is no runtime to delete it",
+                assistant_id
+            ),
+        }
+    }
+}
+
 #[async_trait]
 impl Provider for OpenAiProvider {
     fn metadata() -> ProviderMetadata {
@@ -116,6 +201,7 @@ impl Provider for OpenAiProvider {
                 ConfigKey::new("OPENAI_BASE_PATH", true, false, Some("v1/chat/completions")),
                 ConfigKey::new("OPENAI_ORGANIZATION", false, false, None),
                 ConfigKey::new("OPENAI_PROJECT", false, false, None),
+                ConfigKey::new(OPENAI_API_MODE_CONFIG_KEY, false, false, Some("chat")),
             ],
         )
     }
@@ -134,10 +220,14 @@ impl Provider for OpenAiProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
+        if let Some(assistants) = &self.assistants {
+            return assistants.complete(self, system, messages, tools).await;
+        }
+
         let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
 
         // Make request
-        let response = self.post(payload.clone()).await?;
+        let response = vcr::post("openai", &payload, || self.post(payload.clone())).await?;
 
         // Parse response
         let message = response_to_message(response.clone())?;
@@ -153,4 +243,350 @@ impl Provider for OpenAiProvider {
         emit_debug_trace(self, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        if self.assistants.is_some() || vcr::is_active() {
+            let (message, usage) = self.complete(system, messages, tools).await?;
+            let deltas = message_deltas(message, usage).into_iter().map(Ok);
+            return Ok(Box::pin(futures::stream::iter(deltas)));
+        }
+
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        payload["stream"] = json!(true);
+        // The usage comes in a last chunk of its own
+        let stream_usage = self.stream_usage.load(Ordering::Relaxed);
+        if stream_usage {
+            payload["stream_options"] = json!({ "include_usage": true });
+        }
+
+        let mut response = self
+            .send(Method::POST, &self.base_path, |request| {
+                request.json(&payload)
+            })
+            .await?;
+        if stream_usage && response.status() == StatusCode::BAD_REQUEST {
+            // Some OpenAI compatible servers reject stream_options, and stream without usage
+            if let Some(payload) = payload.as_object_mut() {
+                payload.remove("stream_options");
+            }
+            response = self
+                .send(Method::POST, &self.base_path, |request| {
+                    request.json(&payload)
+                })
+                .await?;
+            if response.status() == StatusCode::OK {
+                tracing::debug!("The server rejected stream_options, streaming without usage");
+                self.stream_usage.store(false, Ordering::Relaxed);
+            }
+        }
+        if response.status() != StatusCode::OK {
+            // Errors come back as a whole, like those of requests that aren't streamed
+            return Err(match handle_response_openai_compat(response).await {
+                Err(e) => e,
+                Ok(body) => ProviderError::RequestFailed(format!("Unexpected response: {}", body)),
+            });
+        }
+
+        let mut events = sse_data(response);
+        let stream = Box::pin(async_stream::try_stream! {
+            let mut model = self.model.model_name.clone();
+            let mut usage = Usage::default();
+            while let Some(data) = events.next().await {
+                let data = data?;
+                if data == "[DONE]" {
+                    break;
+                }
+                let chunk: Value = serde_json::from_str(&data).map_err(|e| {
+                    ProviderError::RequestFailed(format!("Invalid chunk in response stream: {e}"))
+                })?;
+                if let Some(name) = chunk["model"].as_str() {
+                    model = name.to_string();
+                }
+                if !chunk["usage"].is_null() {
+                    usage = get_usage(&chunk).unwrap_or_else(|e| {
+                        tracing::debug!("Failed to get usage data: {}", e);
+                        Usage::default()
+                    });
+                }
+                for delta in response_chunk_to_deltas(&chunk) {
+                    yield delta;
+                }
+            }
+            yield MessageDelta::Usage(ProviderUsage::new(model, usage));
+        });
+        Ok(inspect_stream(
+            stream,
+            self.model.model_name.clone(),
+            move |result| {
+                if let Ok((message, usage)) = result {
+                    let response = serde_json::to_value(message).unwrap_or_default();
+                    emit_debug_trace(self, &payload, &response, &usage.usage);
+                }
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::openai_assistants::AssistantsOptions;
+    use mcp_core::Content;
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio_util::sync::CancellationToken;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mock(server: &MockServer, verb: &str, route: &str, body: Value) {
+        Mock::given(method(verb))
+            .and(path(route))
+            .and(header("openai-beta", "assistants=v2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    fn provider(host: String, assistants: Option<AssistantsSession>) -> OpenAiProvider {
+        OpenAiProvider {
+            client: Client::new(),
+            host,
+            base_path: "v1/chat/completions".to_string(),
+            api_keys: KeyPool::single("sk-test"),
+            organization: None,
+            project: None,
+            model: ModelConfig::new(OPEN_AI_DEFAULT_MODEL.to_string()),
+            assistants,
+            stream_usage: AtomicBool::new(true),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream() -> Result<()> {
+        let server = MockServer::start().await;
+        let body = [
+            r#"{"model":"gpt-4o-2024-08-06","choices":[{"index":0,"delta":{"role":"assistant","content":"Let me "}}],"usage":null}"#,
+            r#"{"model":"gpt-4o-2024-08-06","choices":[{"index":0,"delta":{"content":"check."}}],"usage":null}"#,
+            r#"{"model":"gpt-4o-2024-08-06","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"weather","arguments":""}}]}}],"usage":null}"#,
+            r#"{"model":"gpt-4o-2024-08-06","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"city\":\"Oslo\"}"}}]}}],"usage":null}"#,
+            r#"{"model":"gpt-4o-2024-08-06","choices":[],"usage":{"prompt_tokens":20,"completion_tokens":9,"total_tokens":29}}"#,
+            "[DONE]",
+        ]
+        .map(|data| format!("data: {}\n\n", data))
+        .concat();
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let deltas: Vec<MessageDelta> = provider(server.uri(), None)
+            .stream("system", &[Message::user().with_text("Weather?")], &[])
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+        assert!(matches!(&deltas[0], MessageDelta::Text(text) if text == "Let me "));
+        assert!(matches!(
+            &deltas[2],
+            MessageDelta::ToolCall { index: 0, id: Some(id), name: Some(name), .. }
+                if id == "call_1" && name == "weather"
+        ));
+        assert!(matches!(
+            &deltas[3],
+            MessageDelta::ToolCall { id: None, arguments, .. } if arguments == r#"{"city":"Oslo"}"#
+        ));
+        let MessageDelta::Usage(usage) = &deltas[4] else {
+            panic!("Expected the usage last, got {:?}", deltas[4]);
+        };
+        assert_eq!(usage.model, "gpt-4o-2024-08-06");
+        assert_eq!(usage.usage.total_tokens, Some(29));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_stream_options() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(body_partial_json(
+                json!({ "stream_options": { "include_usage": true } }),
+            ))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": { "message": "Unrecognized request argument supplied: stream_options" }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let body = [
+            r#"{"model":"local","choices":[{"index":0,"delta":{"content":"Hi"}}]}"#,
+            "[DONE]",
+        ]
+        .map(|data| format!("data: {}\n\n", data))
+        .concat();
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        // Only the first request asks for the usage
+        let provider = provider(server.uri(), None);
+        for _ in 0..2 {
+            let deltas: Vec<MessageDelta> = provider
+                .stream("system", &[Message::user().with_text("Hi")], &[])
+                .await?
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<Result<_, _>>()?;
+            assert!(matches!(&deltas[0], MessageDelta::Text(text) if text == "Hi"));
+            assert!(
+                matches!(&deltas[1], MessageDelta::Usage(usage) if usage.usage.total_tokens.is_none())
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_aborts_request_in_flight() -> Result<()> {
+        // A server that reads the request and never answers, reporting when the connection closes
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let host = format!("http://{}", listener.local_addr()?);
+        let (closed_tx, closed_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 4096];
+            while socket.read(&mut buf).await.is_ok_and(|n| n > 0) {}
+            let _ = closed_tx.send(());
+        });
+
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+        let messages = [Message::user().with_text("Hello")];
+        let result = provider(host, None)
+            .complete_cancellable("system", &messages, &[], &cancel)
+            .await;
+        assert!(matches!(result, Err(ProviderError::Cancelled)));
+
+        // The request was dropped, which closed its connection
+        tokio::time::timeout(Duration::from_secs(5), closed_rx).await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_assistants_tool_call_round_trip() -> Result<()> {
+        let server = MockServer::start().await;
+        mock(&server, "POST", "/v1/assistants", json!({ "id": "asst_1" })).await;
+        mock(&server, "POST", "/v1/threads", json!({ "id": "thread_1" })).await;
+        mock(
+            &server,
+            "POST",
+            "/v1/threads/thread_1/messages",
+            json!({ "id": "msg_1" }),
+        )
+        .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/threads/thread_1/runs"))
+            .and(body_partial_json(
+                json!({ "assistant_id": "asst_1", "instructions": "Be brief." }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "run_1",
+                "status": "requires_action",
+                "required_action": {
+                    "type": "submit_tool_outputs",
+                    "submit_tool_outputs": {
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": { "name": "weather", "arguments": "{\"city\":\"Oslo\"}" }
+                        }]
+                    }
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/threads/thread_1/runs/run_1/submit_tool_outputs"))
+            .and(body_partial_json(json!({
+                "tool_outputs": [{ "tool_call_id": "call_1", "output": "Snow" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "run_1",
+                "status": "completed",
+                "model": "gpt-4o",
+                "usage": { "prompt_tokens": 50, "completion_tokens": 5, "total_tokens": 55 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mock(
+            &server,
+            "GET",
+            "/v1/threads/thread_1/messages",
+            json!({
+                "data": [{
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": { "value": "It is snowing." } }]
+                }]
+            }),
+        )
+        .await;
+
+        let provider = provider(
+            server.uri(),
+            Some(AssistantsSession::new(AssistantsOptions::default())),
+        );
+        let tool = Tool::new("weather", "Get the weather", json!({ "type": "object" }));
+
+        let mut messages = vec![Message::user().with_text("Weather in Oslo?")];
+        let (message, _) = provider
+            .complete("Be brief.", &messages, &[tool.clone()])
+            .await?;
+        let request = message.content[0].as_tool_request().unwrap();
+        assert_eq!(request.id, "call_1");
+
+        messages.push(message);
+        messages
+            .push(Message::user().with_tool_response("call_1", Ok(vec![Content::text("Snow")])));
+        let (message, usage) = provider.complete("Be brief.", &messages, &[tool]).await?;
+        assert_eq!(message.as_concat_text(), "It is snowing.");
+        assert_eq!(usage.usage.total_tokens, Some(55));
+
+        // The assistant created for the session goes with the provider
+        mock(
+            &server,
+            "DELETE",
+            "/v1/assistants/asst_1",
+            json!({ "deleted": true }),
+        )
+        .await;
+        drop(provider);
+        let deleted = async {
+            loop {
+                let requests = server.received_requests().await.unwrap_or_default();
+                if requests.iter().any(|r| r.method.as_str() == "DELETE") {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), deleted).await?;
+        Ok(())
+    }
 }
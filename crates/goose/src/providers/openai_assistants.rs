@@ -0,0 +1,429 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex as TokioMutex;
+
+use super::base::{Provider, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{format_tools, get_usage};
+use super::openai::OpenAiProvider;
+use super::utils::{handle_response_openai_compat, Timeouts};
+use super::vcr;
+use crate::message::{Message, MessageContent};
+use mcp_core::{Content, Role, Tool, ToolCall};
+
+/// Config key selecting the `OpenAiApiMode`
+pub const OPENAI_API_MODE_CONFIG_KEY: &str = "OPENAI_API_MODE";
+
+/// Config key holding the `AssistantsOptions`
+///
+/// ```yaml
+/// OPENAI_API_MODE: assistants
+/// OPENAI_ASSISTANTS:
+///   assistant_id: asst_abc123
+///   code_interpreter: true
+/// ```
+pub const OPENAI_ASSISTANTS_CONFIG_KEY: &str = "OPENAI_ASSISTANTS";
+
+const DEFAULT_POLL_INTERVAL_MS: u64 = 500;
+
+/// Which OpenAI API the provider drives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenAiApiMode {
+    #[default]
+    Chat,
+    /// Threads and runs of the Assistants API, see `AssistantsSession`
+    Assistants,
+}
+
+/// Settings for the Assistants API mode
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssistantsOptions {
+    /// An existing assistant to run, whose instructions the system prompt is added to; one
+    /// is created for the session otherwise, and deleted when the provider is dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assistant_id: Option<String>,
+    /// Give the assistant OpenAI's code interpreter, which runs code on OpenAI's servers
+    #[serde(default)]
+    pub code_interpreter: bool,
+    /// How often a run in progress is checked on, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct ThreadState {
+    /// The assistant created for the session, when none is configured
+    assistant_id: Option<String>,
+    thread_id: Option<String>,
+    /// How many messages of the conversation the thread holds, and their hash, to tell
+    /// whether the next conversation continues it
+    synced: Option<(usize, String)>,
+    /// A run waiting for the outputs of the tool calls it made
+    pending_run: Option<String>,
+}
+
+/// Drives the Assistants API for `OpenAiProvider`, keeping the conversation in a thread on
+/// OpenAI's side
+///
+/// `complete` gets the whole conversation each time, while a thread holds what was sent
+/// before, so only the messages added since are sent. A conversation that doesn't continue
+/// the thread, because it was truncated or summarized, starts a new one. Tool calls leave
+/// their run waiting, and the results that come back in the next call are submitted to it.
+#[derive(Debug)]
+pub struct AssistantsSession {
+    options: AssistantsOptions,
+    state: TokioMutex<ThreadState>,
+}
+
+impl AssistantsSession {
+    pub fn new(options: AssistantsOptions) -> Self {
+        Self {
+            options,
+            state: TokioMutex::new(ThreadState::default()),
+        }
+    }
+
+    /// The assistant created for the session, which nothing uses once this is taken
+    pub(super) fn take_created_assistant(&mut self) -> Option<String> {
+        self.state.get_mut().assistant_id.take()
+    }
+
+    /// Call the Assistants API, through the installed `Vcr` if any
+    async fn api(
+        &self,
+        provider: &OpenAiProvider,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Value, ProviderError> {
+        let request = json!({ "method": method.as_str(), "path": path, "body": body });
+        vcr::post("openai_assistants", &request, || {
+            send(provider, method.clone(), path, body.as_ref())
+        })
+        .await
+    }
+
+    pub async fn complete(
+        &self,
+        provider: &OpenAiProvider,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let model = provider.get_model_config();
+        let mut state = self.state.lock().await;
+
+        let configured = self.options.assistant_id.clone();
+        let assistant_id = match configured.clone().or(state.assistant_id.clone()) {
+            Some(id) => id,
+            None => {
+                let body = json!({ "model": model.model_name, "name": "goose" });
+                let assistant = self
+                    .api(provider, Method::POST, "assistants", Some(body))
+                    .await?;
+                let id = object_id(&assistant)?;
+                state.assistant_id = Some(id.clone());
+                id
+            }
+        };
+
+        let continues = match (&state.thread_id, &state.synced) {
+            (Some(_), Some((count, hash))) => {
+                messages.len() >= *count && conversation_hash(&messages[..*count]) == *hash
+            }
+            _ => false,
+        };
+        if !continues {
+            let thread = self
+                .api(provider, Method::POST, "threads", Some(json!({})))
+                .await?;
+            state.thread_id = Some(object_id(&thread)?);
+            state.synced = None;
+            state.pending_run = None;
+        }
+        let thread_id = state.thread_id.clone().expect("a thread was started");
+        let synced = state.synced.as_ref().map_or(0, |(count, _)| *count);
+        let added = &messages[synced..];
+
+        let tool_outputs = tool_outputs(added);
+        let run = match state.pending_run.take() {
+            Some(run_id) if !tool_outputs.is_empty() => {
+                let path = format!("threads/{}/runs/{}/submit_tool_outputs", thread_id, run_id);
+                let body = json!({ "tool_outputs": tool_outputs });
+                self.api(provider, Method::POST, &path, Some(body)).await?
+            }
+            pending => {
+                // A run still waiting for tool outputs holds the thread, so it is given up
+                if let Some(run_id) = pending {
+                    let path = format!("threads/{}/runs/{}/cancel", thread_id, run_id);
+                    if let Err(e) = self.api(provider, Method::POST, &path, None).await {
+                        tracing::debug!("Failed to cancel run {}: {}", run_id, e);
+                    }
+                }
+                for message in added {
+                    let text = thread_text(message);
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let role = match message.role {
+                        Role::User => "user",
+                        Role::Assistant => "assistant",
+                    };
+                    let path = format!("threads/{}/messages", thread_id);
+                    let body = json!({ "role": role, "content": text });
+                    self.api(provider, Method::POST, &path, Some(body)).await?;
+                }
+
+                let mut tools_spec = format_tools(tools)?;
+                if self.options.code_interpreter {
+                    tools_spec.push(json!({ "type": "code_interpreter" }));
+                }
+                let mut body = json!({
+                    "assistant_id": assistant_id,
+                    "model": model.model_name,
+                    "tools": tools_spec,
+                });
+                // A configured assistant keeps its own instructions
+                let instructions = if configured.is_some() {
+                    "additional_instructions"
+                } else {
+                    "instructions"
+                };
+                body[instructions] = json!(system);
+                if let Some(temperature) = model.temperature {
+                    body["temperature"] = json!(temperature);
+                }
+                if let Some(max_tokens) = model.max_tokens {
+                    body["max_completion_tokens"] = json!(max_tokens);
+                }
+                let path = format!("threads/{}/runs", thread_id);
+                self.api(provider, Method::POST, &path, Some(body)).await?
+            }
+        };
+
+        let run = self.wait(provider, &thread_id, run).await?;
+        let run_id = object_id(&run)?;
+        let message = match run["status"].as_str() {
+            Some("requires_action") => {
+                state.pending_run = Some(run_id);
+                tool_calls_to_message(&run)
+            }
+            Some("completed") => {
+                let path = format!("threads/{}/messages?run_id={}&order=asc", thread_id, run_id);
+                let listed = self.api(provider, Method::GET, &path, None).await?;
+                thread_messages_to_message(&listed)
+            }
+            _ => return Err(run_error(&run)),
+        };
+
+        let mut conversation = messages.to_vec();
+        conversation.push(message.clone());
+        state.synced = Some((conversation.len(), conversation_hash(&conversation)));
+
+        let usage = get_usage(&run).unwrap_or_else(|e| {
+            tracing::debug!("Failed to get usage data: {}", e);
+            Usage::default()
+        });
+        let model_name = run["model"]
+            .as_str()
+            .unwrap_or(&model.model_name)
+            .to_string();
+        Ok((message, ProviderUsage::new(model_name, usage)))
+    }
+
+    /// Check on a run until it finishes or waits for tool outputs
+    async fn wait(
+        &self,
+        provider: &OpenAiProvider,
+        thread_id: &str,
+        mut run: Value,
+    ) -> Result<Value, ProviderError> {
+        let interval = Duration::from_millis(
+            self.options
+                .poll_interval_ms
+                .unwrap_or(DEFAULT_POLL_INTERVAL_MS),
+        );
+        // Runs are given as long to finish as a chat completion request
+        let timeout = Timeouts::from_config("OPENAI").total;
+        let started = Instant::now();
+        while matches!(
+            run["status"].as_str(),
+            Some("queued" | "in_progress" | "cancelling")
+        ) {
+            if started.elapsed() > timeout {
+                return Err(ProviderError::ServerError(format!(
+                    "Run {} did not finish in {}s",
+                    run["id"],
+                    timeout.as_secs()
+                )));
+            }
+            tokio::time::sleep(interval).await;
+            let path = format!("threads/{}/runs/{}", thread_id, object_id(&run)?);
+            run = self.api(provider, Method::GET, &path, None).await?;
+        }
+        Ok(run)
+    }
+}
+
+/// Delete an assistant created for a session, logging rather than failing
+pub(super) async fn delete_assistant(provider: &OpenAiProvider, assistant_id: &str) {
+    let path = format!("assistants/{}", assistant_id);
+    match send(provider, Method::DELETE, &path, None).await {
+        Ok(_) => tracing::debug!("Deleted assistant {}", assistant_id),
+        Err(e) => tracing::warn!("Failed to delete assistant {}: {}", assistant_id, e),
+    }
+}
+
+async fn send(
+    provider: &OpenAiProvider,
+    method: Method,
+    path: &str,
+    body: Option<&Value>,
+) -> Result<Value, ProviderError> {
+    let response = provider
+        .send(method, &format!("v1/{}", path), |request| {
+            let request = request.header("OpenAI-Beta", "assistants=v2");
+            match body {
+                Some(body) => request.json(body),
+                None => request,
+            }
+        })
+        .await?;
+    handle_response_openai_compat(response).await
+}
+
+fn object_id(object: &Value) -> Result<String, ProviderError> {
+    object["id"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| ProviderError::RequestFailed(format!("No id in response: {}", object)))
+}
+
+/// The hash of a conversation, without the message timestamps
+fn conversation_hash(messages: &[Message]) -> String {
+    let messages: Vec<Value> = messages
+        .iter()
+        .map(|message| json!({ "role": message.role, "content": message.content }))
+        .collect();
+    let mut hasher = Sha256::new();
+    hasher.update(Value::Array(messages).to_string().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// The text of a tool result, as it is sent back to the model
+fn tool_result_text(result: &Result<Vec<Content>, mcp_core::ToolError>) -> String {
+    match result {
+        Ok(contents) => contents
+            .iter()
+            // Send only contents with no audience or with Assistant in the audience
+            .filter(|content| {
+                content
+                    .audience()
+                    .is_none_or(|audience| audience.contains(&Role::Assistant))
+            })
+            .filter_map(|content| match content {
+                Content::Text(t) => Some(t.text.clone()),
+                Content::Resource(resource) => Some(resource.get_text()),
+                Content::Image(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(e) => format!("The tool call returned the following error:\n{}", e),
+    }
+}
+
+/// The outputs of the tool calls answered in these messages
+fn tool_outputs(messages: &[Message]) -> Vec<Value> {
+    messages
+        .iter()
+        .flat_map(|message| &message.content)
+        .filter_map(|content| content.as_tool_response())
+        .map(|response| {
+            json!({
+                "tool_call_id": response.id,
+                "output": tool_result_text(&response.tool_result),
+            })
+        })
+        .collect()
+}
+
+/// A message as text for a thread, which only takes text from outside a run; tool calls and
+/// results from an earlier conversation are written out
+fn thread_text(message: &Message) -> String {
+    let mut parts = Vec::new();
+    for content in &message.content {
+        match content {
+            MessageContent::Text(t) if !t.text.is_empty() => parts.push(t.text.clone()),
+            MessageContent::ToolRequest(request) => {
+                if let Ok(call) = &request.tool_call {
+                    parts.push(format!("Called {} with {}", call.name, call.arguments));
+                }
+            }
+            MessageContent::ToolResponse(response) => {
+                let text = tool_result_text(&response.tool_result);
+                parts.push(format!("Tool result:\n{}", text));
+            }
+            _ => {}
+        }
+    }
+    parts.join("\n\n")
+}
+
+/// The tool calls a run is waiting on, as a message requesting them
+fn tool_calls_to_message(run: &Value) -> Message {
+    let calls = run["required_action"]["submit_tool_outputs"]["tool_calls"].as_array();
+    calls
+        .into_iter()
+        .flatten()
+        .fold(Message::assistant(), |message, call| {
+            let id = call["id"].as_str().unwrap_or_default();
+            let name = call["function"]["name"].as_str().unwrap_or_default();
+            let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+            let tool_call = serde_json::from_str::<Value>(arguments)
+                .map(|arguments| ToolCall::new(name, arguments))
+                .map_err(|e| {
+                    mcp_core::ToolError::InvalidParameters(format!(
+                        "Could not interpret tool use parameters for id {}: {}",
+                        id, e
+                    ))
+                });
+            message.with_tool_request(id, tool_call)
+        })
+}
+
+/// The text of the messages a run added to its thread, as one message
+fn thread_messages_to_message(listed: &Value) -> Message {
+    let texts = listed["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|message| message["role"] == "assistant")
+        .flat_map(|message| message["content"].as_array().into_iter().flatten())
+        .filter_map(|content| content["text"]["value"].as_str());
+    texts.fold(Message::assistant(), |message, text| {
+        message.with_text(text)
+    })
+}
+
+/// The error a run that didn't complete ended with
+fn run_error(run: &Value) -> ProviderError {
+    let status = run["status"].as_str().unwrap_or("unknown");
+    let message = run["last_error"]["message"]
+        .as_str()
+        .or(run["incomplete_details"]["reason"].as_str())
+        .unwrap_or("Unknown error")
+        .to_string();
+    match (status, run["last_error"]["code"].as_str()) {
+        (_, Some("rate_limit_exceeded")) => ProviderError::RateLimitExceeded(message),
+        (_, Some("server_error")) => ProviderError::ServerError(message),
+        ("incomplete", _) if message == "max_prompt_tokens" => {
+            ProviderError::ContextLengthExceeded(message)
+        }
+        _ => ProviderError::RequestFailed(format!("Run {}: {}", status, message)),
+    }
+}
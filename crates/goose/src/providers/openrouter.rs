@@ -2,14 +2,15 @@ use anyhow::{Error, Result};
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::{json, Value};
-use std::time::Duration;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
+use super::keys::KeyPool;
 use super::utils::{
     emit_debug_trace, get_model, handle_response_google_compat, handle_response_openai_compat,
-    is_google_model,
+    http_client, is_google_model,
 };
+use super::{retry, vcr};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
@@ -28,7 +29,7 @@ pub struct OpenRouterProvider {
     #[serde(skip)]
     client: Client,
     host: String,
-    api_key: String,
+    api_keys: KeyPool,
     model: ModelConfig,
 }
 
@@ -42,19 +43,17 @@ impl Default for OpenRouterProvider {
 impl OpenRouterProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
         let config = crate::config::Config::global();
-        let api_key: String = config.get_secret("OPENROUTER_API_KEY")?;
+        let api_keys = KeyPool::from_secret("OPENROUTER_API_KEY")?;
         let host: String = config
             .get("OPENROUTER_HOST")
             .unwrap_or_else(|_| "https://openrouter.ai".to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = http_client("OPENROUTER")?;
 
         Ok(Self {
             client,
             host,
-            api_key,
+            api_keys,
             model,
         })
     }
@@ -66,16 +65,16 @@ impl OpenRouterProvider {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
         })?;
 
-        let response = self
-            .client
-            .post(url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("HTTP-Referer", "https://github.com/block/goose")
-            .header("X-Title", "Goose")
-            .json(&payload)
-            .send()
-            .await?;
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", key))
+                .header("HTTP-Referer", "https://github.com/block/goose")
+                .header("X-Title", "Goose")
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
 
         if is_google_model(&payload) {
             handle_response_google_compat(response).await
@@ -225,7 +224,7 @@ impl Provider for OpenRouterProvider {
         let payload = create_request_based_on_model(&self.model, system, messages, tools)?;
 
         // Make request
-        let response = self.post(payload.clone()).await?;
+        let response = vcr::post("openrouter", &payload, || self.post(payload.clone())).await?;
 
         // Parse response
         let message = response_to_message(response.clone())?;
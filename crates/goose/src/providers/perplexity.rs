@@ -0,0 +1,134 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::get_usage;
+use super::formats::perplexity::{create_request, response_to_message};
+use super::keys::KeyPool;
+use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const PERPLEXITY_API_HOST: &str = "https://api.perplexity.ai";
+pub const PERPLEXITY_DEFAULT_MODEL: &str = "sonar-pro";
+pub const PERPLEXITY_KNOWN_MODELS: &[&str] = &[
+    "sonar",
+    "sonar-pro",
+    "sonar-reasoning",
+    "sonar-reasoning-pro",
+    "sonar-deep-research",
+];
+
+pub const PERPLEXITY_DOC_URL: &str = "https://docs.perplexity.ai/guides/model-cards";
+
+#[derive(serde::Serialize)]
+pub struct PerplexityProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_keys: KeyPool,
+    model: ModelConfig,
+}
+
+impl Default for PerplexityProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(PerplexityProvider::metadata().default_model);
+        PerplexityProvider::from_env(model).expect("Failed to initialize Perplexity provider")
+    }
+}
+
+impl PerplexityProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_keys = KeyPool::from_secret("PERPLEXITY_API_KEY")?;
+        let host: String = config
+            .get("PERPLEXITY_HOST")
+            .unwrap_or_else(|_| PERPLEXITY_API_HOST.to_string());
+
+        let client = http_client("PERPLEXITY")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_keys,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Authorization", format!("Bearer {}", key))
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for PerplexityProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "perplexity",
+            "Perplexity",
+            "Open source models hosted by Together AI",
+            PERPLEXITY_DEFAULT_MODEL,
+            PERPLEXITY_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            PERPLEXITY_DOC_URL,
+            vec![
+                ConfigKey::new("PERPLEXITY_API_KEY", true, true, None),
+                ConfigKey::new("PERPLEXITY_HOST", false, false, Some(PERPLEXITY_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = vcr::post("perplexity", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::AddAssign;
+
+use super::base::Usage;
+use crate::config::Config;
+
+/// Config key holding a map of model name to `ModelPricing`, used to override the
+/// built-in list prices (e.g. for negotiated rates).
+pub const PRICING_CONFIG_KEY: &str = "GOOSE_PRICING";
+
+const TOKENS_PER_UNIT: f64 = 1_000_000.0;
+
+/// Prices for a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    /// Price for input tokens served from the provider's prompt cache
+    #[serde(default)]
+    pub cache_read: Option<f64>,
+    /// Price for input tokens written to the provider's prompt cache
+    #[serde(default)]
+    pub cache_write: Option<f64>,
+    /// Price for input tokens of audio
+    #[serde(default)]
+    pub audio_input: Option<f64>,
+    /// Price for output tokens of audio
+    #[serde(default)]
+    pub audio_output: Option<f64>,
+}
+
+impl ModelPricing {
+    pub const fn new(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_read: None,
+            cache_write: None,
+            audio_input: None,
+            audio_output: None,
+        }
+    }
+
+    pub const fn with_cache(mut self, cache_read: f64, cache_write: Option<f64>) -> Self {
+        self.cache_read = Some(cache_read);
+        self.cache_write = cache_write;
+        self
+    }
+
+    pub const fn with_audio(mut self, audio_input: f64, audio_output: f64) -> Self {
+        self.audio_input = Some(audio_input);
+        self.audio_output = Some(audio_output);
+        self
+    }
+
+    /// Compute the cost of a single request, or None if the usage has no token counts
+    pub fn cost(&self, usage: &Usage) -> Option<Cost> {
+        if usage.input_tokens.is_none() && usage.output_tokens.is_none() {
+            return None;
+        }
+
+        // Cached and audio tokens are part of the input count but billed at their own rates,
+        // falling back to the regular input price when the model has no such pricing
+        let cache_read = usage.cache_read_tokens.unwrap_or(0);
+        let cache_write = usage.cache_write_tokens.unwrap_or(0);
+        let audio_input = usage.audio_input_tokens.unwrap_or(0);
+        let uncached =
+            (usage.input_tokens.unwrap_or(0) - cache_read - cache_write - audio_input).max(0);
+        // Reasoning tokens are billed as output, which they are already counted in
+        let audio_output = usage.audio_output_tokens.unwrap_or(0);
+        let text_output = (usage.output_tokens.unwrap_or(0) - audio_output).max(0);
+
+        let input = (uncached as f64 * self.input
+            + audio_input as f64 * self.audio_input.unwrap_or(self.input))
+            / TOKENS_PER_UNIT;
+        let output = (text_output as f64 * self.output
+            + audio_output as f64 * self.audio_output.unwrap_or(self.output))
+            / TOKENS_PER_UNIT;
+        let cache = (cache_read as f64 * self.cache_read.unwrap_or(self.input)
+            + cache_write as f64 * self.cache_write.unwrap_or(self.input))
+            / TOKENS_PER_UNIT;
+
+        Some(Cost {
+            input,
+            output,
+            cache,
+            total: input + output + cache,
+        })
+    }
+}
+
+/// The cost of one or more requests in USD
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cost {
+    pub input: f64,
+    pub output: f64,
+    pub cache: f64,
+    pub total: f64,
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, other: Self) {
+        self.input += other.input;
+        self.output += other.output;
+        self.cache += other.cache;
+        self.total += other.total;
+    }
+}
+
+/// List prices for well known models, matched by substring against the model name so that
+/// provider specific names (e.g. bedrock or databricks ids) resolve to the same entry.
+/// The longest matching entry wins, so more specific names should be listed alongside
+/// their families (e.g. "gpt-4o-mini" and "gpt-4o").
+const KNOWN_PRICING: &[(&str, ModelPricing)] = &[
+    // OpenAI, https://openai.com/api/pricing/
+    (
+        "gpt-4o",
+        ModelPricing::new(2.50, 10.00).with_cache(1.25, None),
+    ),
+    (
+        "gpt-4o-audio-preview",
+        ModelPricing::new(2.50, 10.00).with_audio(40.00, 80.00),
+    ),
+    (
+        "gpt-4o-mini",
+        ModelPricing::new(0.15, 0.60).with_cache(0.075, None),
+    ),
+    ("gpt-4-turbo", ModelPricing::new(10.00, 30.00)),
+    ("o1", ModelPricing::new(15.00, 60.00).with_cache(7.50, None)),
+    (
+        "o1-mini",
+        ModelPricing::new(1.10, 4.40).with_cache(0.55, None),
+    ),
+    (
+        "o3-mini",
+        ModelPricing::new(1.10, 4.40).with_cache(0.55, None),
+    ),
+    // Anthropic, https://www.anthropic.com/pricing#anthropic-api
+    (
+        "claude-3-7-sonnet",
+        ModelPricing::new(3.00, 15.00).with_cache(0.30, Some(3.75)),
+    ),
+    (
+        "claude-3-5-sonnet",
+        ModelPricing::new(3.00, 15.00).with_cache(0.30, Some(3.75)),
+    ),
+    (
+        "claude-3-5-haiku",
+        ModelPricing::new(0.80, 4.00).with_cache(0.08, Some(1.00)),
+    ),
+    (
+        "claude-3-opus",
+        ModelPricing::new(15.00, 75.00).with_cache(1.50, Some(18.75)),
+    ),
+    (
+        "claude-3-haiku",
+        ModelPricing::new(0.25, 1.25).with_cache(0.03, Some(0.30)),
+    ),
+    // Google, https://ai.google.dev/pricing
+    (
+        "gemini-2.0-flash",
+        ModelPricing::new(0.10, 0.40).with_cache(0.025, None),
+    ),
+    ("gemini-1.5-pro", ModelPricing::new(1.25, 5.00)),
+    ("gemini-1.5-flash", ModelPricing::new(0.075, 0.30)),
+    // DeepSeek, https://api-docs.deepseek.com/quick_start/pricing
+    (
+        "deepseek-chat",
+        ModelPricing::new(0.27, 1.10).with_cache(0.07, None),
+    ),
+    (
+        "deepseek-reasoner",
+        ModelPricing::new(0.55, 2.19).with_cache(0.14, None),
+    ),
+    // xAI, https://docs.x.ai/docs/models
+    ("grok-2", ModelPricing::new(2.00, 10.00)),
+    ("grok-beta", ModelPricing::new(5.00, 15.00)),
+    // Perplexity, https://docs.perplexity.ai/guides/pricing, leaving out the search fees
+    ("sonar", ModelPricing::new(1.00, 1.00)),
+    ("sonar-pro", ModelPricing::new(3.00, 15.00)),
+    ("sonar-reasoning", ModelPricing::new(1.00, 5.00)),
+    ("sonar-reasoning-pro", ModelPricing::new(2.00, 8.00)),
+    ("sonar-deep-research", ModelPricing::new(2.00, 8.00)),
+    // AI21, https://www.ai21.com/pricing
+    ("jamba-large", ModelPricing::new(2.00, 8.00)),
+    ("jamba-mini", ModelPricing::new(0.20, 0.40)),
+    // Cohere, https://cohere.com/pricing
+    ("command-a", ModelPricing::new(2.50, 10.00)),
+    ("command-r-plus", ModelPricing::new(2.50, 10.00)),
+    ("command-r", ModelPricing::new(0.15, 0.60)),
+    ("command-r7b", ModelPricing::new(0.0375, 0.15)),
+    // Moonshot, https://platform.moonshot.ai/docs/pricing/chat
+    ("kimi-k2", ModelPricing::new(0.60, 2.50)),
+    ("moonshot-v1-8k", ModelPricing::new(0.20, 2.00)),
+    ("moonshot-v1-32k", ModelPricing::new(1.00, 3.00)),
+    ("moonshot-v1-128k", ModelPricing::new(2.00, 5.00)),
+    // Qwen on DashScope (international), https://www.alibabacloud.com/help/en/model-studio/models
+    ("qwen-max", ModelPricing::new(1.60, 6.40)),
+    ("qwen-plus", ModelPricing::new(0.40, 1.20)),
+    ("qwen-turbo", ModelPricing::new(0.05, 0.20)),
+];
+
+fn find_pricing<'a>(
+    model: &str,
+    table: impl Iterator<Item = (&'a str, ModelPricing)>,
+) -> Option<ModelPricing> {
+    table
+        .filter(|(name, _)| model.contains(name))
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, pricing)| pricing)
+}
+
+/// Look up the pricing for a model
+///
+/// Entries under `GOOSE_PRICING` in the config are matched the same way as the built-in
+/// table and take precedence over a built-in entry with the same name.
+pub fn get_pricing(model: &str) -> Option<ModelPricing> {
+    let overrides: HashMap<String, ModelPricing> =
+        Config::global().get(PRICING_CONFIG_KEY).unwrap_or_default();
+
+    // max_by_key returns the last of equal maxima, so overrides go last
+    find_pricing(
+        model,
+        KNOWN_PRICING
+            .iter()
+            .copied()
+            .chain(overrides.iter().map(|(k, v)| (k.as_str(), *v))),
+    )
+}
+
+/// Compute the cost of a request for the given model, if its pricing is known
+pub fn calculate_cost(model: &str, usage: &Usage) -> Option<Cost> {
+    get_pricing(model).and_then(|pricing| pricing.cost(usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    fn test_longest_match_wins() {
+        let pricing = find_pricing("gpt-4o-mini-2024-07-18", KNOWN_PRICING.iter().copied());
+        assert_eq!(pricing.unwrap().input, 0.15);
+
+        let pricing = find_pricing(
+            "us.anthropic.claude-3-5-sonnet-20241022-v2:0",
+            KNOWN_PRICING.iter().copied(),
+        );
+        assert_eq!(pricing.unwrap().output, 15.00);
+
+        assert!(find_pricing("some-local-model", KNOWN_PRICING.iter().copied()).is_none());
+    }
+
+    #[test]
+    fn test_cost() {
+        let pricing = ModelPricing::new(2.0, 10.0);
+        let cost = pricing
+            .cost(&Usage::new(Some(1_000_000), Some(500_000), Some(1_500_000)))
+            .unwrap();
+        assert_eq!(cost.input, 2.0);
+        assert_eq!(cost.output, 5.0);
+        assert_eq!(cost.total, 7.0);
+
+        assert!(pricing.cost(&Usage::default()).is_none());
+    }
+
+    #[test]
+    fn test_cost_with_cache() {
+        let pricing = ModelPricing::new(3.0, 15.0).with_cache(0.30, Some(3.75));
+        let usage = Usage::new(Some(1_000_000), Some(0), Some(1_000_000))
+            .with_cache_tokens(Some(600_000), Some(200_000));
+        let cost = pricing.cost(&usage).unwrap();
+        assert!((cost.input - 0.6).abs() < 1e-9);
+        assert!((cost.cache - (0.18 + 0.75)).abs() < 1e-9);
+        assert!((cost.total - 1.53).abs() < 1e-9);
+
+        // Without cache pricing, cached tokens cost the same as regular input
+        let pricing = ModelPricing::new(3.0, 15.0);
+        let cost = pricing.cost(&usage).unwrap();
+        assert!((cost.total - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cost_with_audio() {
+        let pricing = ModelPricing::new(2.5, 10.0).with_audio(40.0, 80.0);
+        let usage = Usage::new(Some(1_000_000), Some(1_000_000), Some(2_000_000))
+            .with_audio_tokens(Some(250_000), Some(500_000));
+        let cost = pricing.cost(&usage).unwrap();
+        assert!((cost.input - (1.875 + 10.0)).abs() < 1e-9);
+        assert!((cost.output - (5.0 + 40.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    #[serial]
+    fn test_config_override() {
+        std::env::set_var(
+            PRICING_CONFIG_KEY,
+            r#"{"gpt-4o": {"input": 1.0, "output": 2.0}}"#,
+        );
+        let pricing = get_pricing("gpt-4o-2024-08-06").unwrap();
+        let mini_pricing = get_pricing("gpt-4o-mini").unwrap();
+        std::env::remove_var(PRICING_CONFIG_KEY);
+
+        assert_eq!(pricing, ModelPricing::new(1.0, 2.0));
+        // A more specific built-in entry still wins over a shorter override
+        assert_eq!(mini_pricing.input, 0.15);
+    }
+}
@@ -0,0 +1,379 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Days, Local, Months, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::inspect_stream;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::usage::{UsageFilter, UsageSummary, UsageTracker};
+use mcp_core::tool::Tool;
+
+/// Config key holding the list of `Quota`s
+pub const QUOTA_CONFIG_KEY: &str = "GOOSE_QUOTAS";
+
+/// A calendar period that a quota applies to, in local time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaWindow {
+    Daily,
+    /// Weeks start on Monday
+    Weekly,
+    Monthly,
+}
+
+impl QuotaWindow {
+    /// The start of the window containing `now`
+    pub fn start<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> DateTime<Tz> {
+        let today = now.date_naive();
+        let date = match self {
+            QuotaWindow::Daily => today,
+            QuotaWindow::Weekly => today - Days::new(today.weekday().num_days_from_monday() as u64),
+            QuotaWindow::Monthly => today.with_day(1).unwrap_or(today),
+        };
+        midnight(&now.timezone(), date)
+    }
+
+    /// When the window containing `now` ends and the quota resets
+    pub fn reset<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> DateTime<Tz> {
+        let start = self.start(now).date_naive();
+        let date = match self {
+            QuotaWindow::Daily => start + Days::new(1),
+            QuotaWindow::Weekly => start + Days::new(7),
+            QuotaWindow::Monthly => start + Months::new(1),
+        };
+        midnight(&now.timezone(), date)
+    }
+}
+
+impl std::fmt::Display for QuotaWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaWindow::Daily => write!(f, "daily"),
+            QuotaWindow::Weekly => write!(f, "weekly"),
+            QuotaWindow::Monthly => write!(f, "monthly"),
+        }
+    }
+}
+
+fn midnight<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> DateTime<Tz> {
+    let naive = date.and_time(chrono::NaiveTime::MIN);
+    tz.from_local_datetime(&naive)
+        .earliest()
+        // Midnight can be skipped by a DST change, in which case the day starts an hour later
+        .unwrap_or_else(|| tz.from_utc_datetime(&naive))
+}
+
+/// A token or cost ceiling for a provider over a calendar window
+///
+/// Usage is read from the persisted usage history, so quotas carry over across restarts and
+/// reset on their own once a new window starts.
+///
+/// ```yaml
+/// GOOSE_QUOTAS:
+///   - provider: openai
+///     window: daily
+///     max_tokens: 2000000
+///   - window: monthly
+///     max_cost: 50.0
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quota {
+    /// The provider this quota applies to, or every provider if unset
+    #[serde(default)]
+    pub provider: Option<String>,
+    pub window: QuotaWindow,
+    #[serde(default)]
+    pub max_tokens: Option<i64>,
+    /// Maximum spend in USD
+    #[serde(default)]
+    pub max_cost: Option<f64>,
+}
+
+impl Quota {
+    /// Read all quotas from `GOOSE_QUOTAS`
+    pub fn from_config() -> Vec<Self> {
+        Config::global().get(QUOTA_CONFIG_KEY).unwrap_or_default()
+    }
+
+    fn applies_to(&self, provider: &str) -> bool {
+        self.provider.as_ref().is_none_or(|p| p == provider)
+    }
+
+    fn consumed(&self, used: &UsageSummary) -> bool {
+        self.max_tokens.is_some_and(|max| used.total_tokens >= max)
+            || self.max_cost.is_some_and(|max| used.cost >= max)
+    }
+}
+
+/// The state of a quota within its current window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaStatus {
+    pub quota: Quota,
+    pub used: UsageSummary,
+    pub resets_at: DateTime<Local>,
+}
+
+impl QuotaStatus {
+    pub fn is_consumed(&self) -> bool {
+        self.quota.consumed(&self.used)
+    }
+}
+
+impl std::fmt::Display for QuotaStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scope = self.quota.provider.as_deref().unwrap_or("all providers");
+        write!(
+            f,
+            "{} quota for {} used up (${:.4} and {} tokens), resets at {}",
+            self.quota.window,
+            scope,
+            self.used.cost,
+            self.used.total_tokens,
+            self.resets_at.format("%Y-%m-%d %H:%M")
+        )
+    }
+}
+
+/// A provider wrapper that stops requests once any applicable quota is consumed
+pub struct QuotaProvider {
+    inner: Box<dyn Provider>,
+    provider_name: String,
+    quotas: Vec<Quota>,
+    tracker: Option<&'static UsageTracker>,
+}
+
+impl QuotaProvider {
+    pub fn new(inner: Box<dyn Provider>, provider_name: &str, quotas: Vec<Quota>) -> Self {
+        let quotas = quotas
+            .into_iter()
+            .filter(|q| q.applies_to(provider_name))
+            .collect();
+        Self {
+            inner,
+            provider_name: provider_name.to_string(),
+            quotas,
+            tracker: None,
+        }
+    }
+
+    /// Read usage from the given tracker instead of the global one
+    pub fn with_tracker(mut self, tracker: &'static UsageTracker) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+
+    fn tracker(&self) -> &'static UsageTracker {
+        self.tracker.unwrap_or_else(UsageTracker::global)
+    }
+
+    /// The current state of every quota that applies to this provider
+    pub fn statuses(&self) -> Vec<QuotaStatus> {
+        let now = Local::now();
+        self.quotas
+            .iter()
+            .map(|quota| {
+                let filter = UsageFilter {
+                    provider: quota.provider.clone(),
+                    since: Some(quota.window.start(&now).with_timezone(&Utc)),
+                    ..Default::default()
+                };
+                QuotaStatus {
+                    quota: quota.clone(),
+                    used: self.used(&filter),
+                    resets_at: quota.window.reset(&now),
+                }
+            })
+            .collect()
+    }
+
+    fn used(&self, filter: &UsageFilter) -> UsageSummary {
+        // Prefer the persisted history so usage from earlier runs counts too
+        let tracker = self.tracker();
+        match tracker.store().map(|store| store.totals(filter)) {
+            Some(Ok(summary)) => summary,
+            Some(Err(e)) => {
+                tracing::warn!("Failed to read usage history for quotas: {}", e);
+                tracker.totals(filter)
+            }
+            None => tracker.totals(filter),
+        }
+    }
+
+    fn check(&self) -> Result<(), ProviderError> {
+        match self.statuses().into_iter().find(|s| s.is_consumed()) {
+            Some(status) => Err(ProviderError::QuotaExceeded(status.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    fn warn_consumed(&self) {
+        // The inner provider has already recorded this request with the tracker
+        for status in self.statuses().into_iter().filter(|s| s.is_consumed()) {
+            tracing::warn!(provider = %self.provider_name, "{}", status);
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for QuotaProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.check()?;
+        let result = self.inner.complete(system, messages, tools).await?;
+        self.warn_consumed();
+        Ok(result)
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        self.check()?;
+        let stream = self.inner.stream(system, messages, tools).await?;
+        let model = self.get_model_config().model_name;
+        Ok(inspect_stream(stream, model, |result| {
+            if result.is_ok() {
+                self.warn_consumed();
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use crate::usage::UsageStore;
+    use chrono::FixedOffset;
+    use std::time::Duration;
+
+    struct MockProvider;
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("mock".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text("Mock response"),
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    fn tracker() -> &'static UsageTracker {
+        Box::leak(Box::new(
+            UsageTracker::default().with_store(UsageStore::open_in_memory().unwrap()),
+        ))
+    }
+
+    fn record(tracker: &UsageTracker, provider: &str, tokens: i32) {
+        let usage = ProviderUsage::new(
+            "mock".to_string(),
+            Usage::new(Some(tokens), Some(0), Some(tokens)),
+        );
+        tracker.record(provider, &usage, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_window_boundaries() {
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        // Wednesday
+        let now = tz.with_ymd_and_hms(2025, 1, 29, 15, 30, 0).unwrap();
+
+        assert_eq!(
+            QuotaWindow::Daily.start(&now),
+            tz.with_ymd_and_hms(2025, 1, 29, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            QuotaWindow::Daily.reset(&now),
+            tz.with_ymd_and_hms(2025, 1, 30, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            QuotaWindow::Weekly.start(&now),
+            tz.with_ymd_and_hms(2025, 1, 27, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            QuotaWindow::Weekly.reset(&now),
+            tz.with_ymd_and_hms(2025, 2, 3, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            QuotaWindow::Monthly.start(&now),
+            tz.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            QuotaWindow::Monthly.reset(&now),
+            tz.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consumed_quota_stops_requests() {
+        let tracker = tracker();
+        let quotas = vec![Quota {
+            provider: Some("openai".to_string()),
+            window: QuotaWindow::Daily,
+            max_tokens: Some(100),
+            max_cost: None,
+        }];
+        let provider =
+            QuotaProvider::new(Box::new(MockProvider), "openai", quotas).with_tracker(tracker);
+
+        // Usage from other providers does not count
+        record(tracker, "anthropic", 500);
+        provider.complete("", &[], &[]).await.unwrap();
+
+        record(tracker, "openai", 100);
+        let result = provider.complete("", &[], &[]).await;
+        match result {
+            Err(ProviderError::QuotaExceeded(message)) => {
+                assert!(message.starts_with("daily quota for openai used up"));
+            }
+            _ => panic!("Expected QuotaExceeded"),
+        }
+    }
+
+    #[test]
+    fn test_quotas_filtered_by_provider() {
+        let quotas: Vec<Quota> = serde_json::from_value(serde_json::json!([
+            {"provider": "openai", "window": "daily", "max_tokens": 10},
+            {"window": "monthly", "max_cost": 5.0},
+        ]))
+        .unwrap();
+
+        let provider =
+            QuotaProvider::new(Box::new(MockProvider), "anthropic", quotas).with_tracker(tracker());
+        let statuses = provider.statuses();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].quota.window, QuotaWindow::Monthly);
+        assert!(!statuses[0].is_consumed());
+    }
+}
@@ -0,0 +1,290 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::formats::qwen::{
+    collect_stream, create_native_request, get_native_usage, native_to_message,
+};
+use super::utils::{emit_debug_trace, http_client, ImageFormat};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+// The international endpoint; accounts in mainland China use https://dashscope.aliyuncs.com
+pub const QWEN_API_HOST: &str = "https://dashscope-intl.aliyuncs.com";
+pub const QWEN_DEFAULT_MODEL: &str = "qwen-plus";
+pub const QWEN_KNOWN_MODELS: &[&str] = &[
+    "qwen-max",
+    "qwen-plus",
+    "qwen-turbo",
+    "qwen3-coder-plus",
+    "qwen3-235b-a22b",
+    "qwen3-32b",
+    "qwq-plus",
+];
+
+pub const QWEN_DOC_URL: &str = "https://www.alibabacloud.com/help/en/model-studio/models";
+
+/// Config key holding the `QwenOptions`
+///
+/// ```yaml
+/// QWEN_OPTIONS:
+///   enable_thinking: true
+///   thinking_budget: 4096
+///   endpoint: native
+/// ```
+pub const QWEN_OPTIONS_CONFIG_KEY: &str = "QWEN_OPTIONS";
+
+/// Which of DashScope's APIs requests go to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QwenEndpoint {
+    /// The OpenAI compatible `compatible-mode/v1/chat/completions`
+    #[default]
+    Compatible,
+    /// DashScope's own text generation API
+    Native,
+}
+
+/// Settings specific to Qwen on DashScope
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QwenOptions {
+    /// Whether hybrid thinking models like Qwen3 think before answering; DashScope only
+    /// allows this for streamed requests, so the response is streamed and collected
+    #[serde(default)]
+    pub enable_thinking: bool,
+    /// The most tokens a thinking model spends on thinking
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
+    #[serde(default)]
+    pub endpoint: QwenEndpoint,
+}
+
+/// Qwen models on Alibaba Cloud Model Studio (DashScope)
+#[derive(serde::Serialize)]
+pub struct QwenProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_key: String,
+    model: ModelConfig,
+    options: QwenOptions,
+}
+
+impl Default for QwenProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(QwenProvider::metadata().default_model);
+        QwenProvider::from_env(model).expect("Failed to initialize Qwen provider")
+    }
+}
+
+impl QwenProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("DASHSCOPE_API_KEY")?;
+        let host: String = config
+            .get("DASHSCOPE_HOST")
+            .unwrap_or_else(|_| QWEN_API_HOST.to_string());
+        let options: QwenOptions = config.get(QWEN_OPTIONS_CONFIG_KEY).unwrap_or_default();
+
+        let client = http_client("DASHSCOPE")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model,
+            options,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let path = match self.options.endpoint {
+            QwenEndpoint::Compatible => "compatible-mode/v1/chat/completions",
+            QwenEndpoint::Native => "api/v1/services/aigc/text-generation/generation",
+        };
+        let url = base_url.join(path).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let mut request = self.client.post(url).bearer_auth(&self.api_key);
+        if self.options.enable_thinking && self.options.endpoint == QwenEndpoint::Native {
+            request = request.header("X-DashScope-SSE", "enable");
+        }
+        let response = retry::send(request.json(&payload)).await?;
+
+        let status = response.status();
+        if status.is_success() {
+            if !self.options.enable_thinking {
+                return response
+                    .json()
+                    .await
+                    .map_err(|e| ProviderError::RequestFailed(e.to_string()));
+            }
+            let body = response.text().await?;
+            return collect_stream(&body).map_err(|e| ProviderError::RequestFailed(e.to_string()));
+        }
+
+        // The compatible API reports errors in the OpenAI shape, the native one at the top level
+        let payload: Option<Value> = response.json().await.ok();
+        let error = payload
+            .as_ref()
+            .map(|p| p.get("error").unwrap_or(p))
+            .cloned()
+            .unwrap_or_default();
+        let message = error["message"]
+            .as_str()
+            .unwrap_or("Unknown error")
+            .to_string();
+
+        // https://www.alibabacloud.com/help/en/model-studio/error-code
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API key is valid and for the region of DASHSCOPE_HOST. \
+                    Status: {}. Message: {}", status, message)))
+            }
+            StatusCode::BAD_REQUEST => {
+                // e.g. "Range of input length should be [1, 129024]"
+                if message.contains("input length") {
+                    return Err(ProviderError::ContextLengthExceeded(message));
+                }
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, message)))
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                Err(ProviderError::RateLimitExceeded(message))
+            }
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                Err(ProviderError::ServerError(message))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                );
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, message)))
+            }
+        }
+    }
+}
+
+/// Add the thinking settings to a request payload, where each API expects them
+fn add_options(payload: &mut Value, options: &QwenOptions) {
+    let parameters = match options.endpoint {
+        QwenEndpoint::Compatible => {
+            if options.enable_thinking {
+                payload["stream"] = json!(true);
+                payload["stream_options"] = json!({ "include_usage": true });
+            }
+            payload
+        }
+        QwenEndpoint::Native => {
+            if options.enable_thinking {
+                payload["parameters"]["incremental_output"] = json!(true);
+            }
+            &mut payload["parameters"]
+        }
+    };
+    parameters["enable_thinking"] = json!(options.enable_thinking);
+    if let (true, Some(budget)) = (options.enable_thinking, options.thinking_budget) {
+        parameters["thinking_budget"] = json!(budget);
+    }
+}
+
+#[async_trait]
+impl Provider for QwenProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "qwen",
+            "Qwen",
+            "Qwen models from Alibaba Cloud through DashScope",
+            QWEN_DEFAULT_MODEL,
+            QWEN_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            QWEN_DOC_URL,
+            vec![
+                ConfigKey::new("DASHSCOPE_API_KEY", true, true, None),
+                ConfigKey::new("DASHSCOPE_HOST", false, false, Some(QWEN_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let native = self.options.endpoint == QwenEndpoint::Native;
+        let mut payload = if native {
+            create_native_request(&self.model, system, messages, tools)?
+        } else {
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?
+        };
+        add_options(&mut payload, &self.options);
+
+        let response = vcr::post("qwen", &payload, || self.post(payload.clone())).await?;
+
+        let (message, usage) = if native {
+            (native_to_message(&response)?, get_native_usage(&response))
+        } else {
+            (response_to_message(response.clone())?, get_usage(&response))
+        };
+        let usage = match usage {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        // Only the compatible API echoes the model back
+        let model = response["model"]
+            .as_str()
+            .unwrap_or(&self.model.model_name)
+            .to_string();
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_options() {
+        let options: QwenOptions =
+            serde_yaml::from_str("enable_thinking: true\nthinking_budget: 2048").unwrap();
+        let mut payload = json!({ "model": "qwen3-32b", "messages": [] });
+        add_options(&mut payload, &options);
+        assert_eq!(payload["enable_thinking"], true);
+        assert_eq!(payload["thinking_budget"], 2048);
+        assert_eq!(payload["stream"], true);
+
+        let options = QwenOptions {
+            endpoint: QwenEndpoint::Native,
+            ..Default::default()
+        };
+        let mut payload = json!({ "model": "qwen3-32b", "input": {}, "parameters": {} });
+        add_options(&mut payload, &options);
+        // Thinking is turned off explicitly, as Qwen3 models think by default
+        assert_eq!(payload["parameters"]["enable_thinking"], false);
+        assert!(payload["parameters"].get("incremental_output").is_none());
+        assert!(payload.get("stream").is_none());
+    }
+}
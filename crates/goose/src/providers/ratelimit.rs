@@ -0,0 +1,251 @@
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::inspect_stream;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::token_counter::TokenCounter;
+use mcp_core::tool::Tool;
+
+/// Config key holding the list of `RateLimit`s
+pub const RATE_LIMIT_CONFIG_KEY: &str = "GOOSE_RATE_LIMITS";
+
+// The buckets of every rate limited provider in this process, shared by all of its models
+// since account limits are
+static BUCKETS: Lazy<Mutex<HashMap<String, Buckets>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The pace to keep with a provider, matching the limits of the account
+///
+/// ```yaml
+/// GOOSE_RATE_LIMITS:
+///   - provider: openai
+///     requests_per_minute: 500
+///     tokens_per_minute: 30000
+///   - requests_per_minute: 60
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// The provider this limit applies to, or each provider without a limit of its own if unset
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Tokens sent and received, with the tokens of a request estimated from its messages
+    #[serde(default)]
+    pub tokens_per_minute: Option<u32>,
+}
+
+impl RateLimit {
+    /// Read the limit for a provider from `GOOSE_RATE_LIMITS`, returning None when it has none
+    pub fn for_provider(provider: &str) -> Option<Self> {
+        let limits: Vec<Self> = Config::global()
+            .get(RATE_LIMIT_CONFIG_KEY)
+            .unwrap_or_default();
+        let specific = limits
+            .iter()
+            .find(|limit| limit.provider.as_deref() == Some(provider));
+        specific
+            .or_else(|| limits.iter().find(|limit| limit.provider.is_none()))
+            .cloned()
+    }
+}
+
+/// A token bucket refilling to its capacity over a minute
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    /// Below zero when requests have been let through ahead of the refill
+    available: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32, now: Instant) -> Self {
+        Self {
+            capacity: per_minute as f64,
+            available: per_minute as f64,
+            updated: now,
+        }
+    }
+
+    fn per_second(&self) -> f64 {
+        self.capacity / 60.0
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * self.per_second()).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Take `amount`, returning how long to wait until the bucket covers it
+    fn take(&mut self, amount: f64, now: Instant) -> Duration {
+        self.refill(now);
+        // More than a minute's worth could never fit, so it only has to wait for a full bucket
+        self.available -= amount.min(self.capacity);
+        if self.available >= 0.0 || self.per_second() <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.available / self.per_second())
+        }
+    }
+
+    /// Correct an earlier `take` by `amount`, giving back when it is negative
+    fn adjust(&mut self, amount: f64, now: Instant) {
+        self.refill(now);
+        self.available = (self.available - amount).min(self.capacity);
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+}
+
+/// A provider wrapper that paces requests to stay under the provider's rate limits
+///
+/// Requests and tokens are drawn from buckets that refill at the configured rate per minute,
+/// and a request that would overdraw them waits until they have refilled, rather than
+/// being sent and rejected with a 429. The tokens of a request are counted with the model's
+/// tokenizer up front and corrected with the usage the provider reports.
+pub struct RateLimitedProvider {
+    inner: Box<dyn Provider>,
+    provider: String,
+    limit: RateLimit,
+    counter: Arc<TokenCounter>,
+}
+
+impl RateLimitedProvider {
+    pub fn new(inner: Box<dyn Provider>, provider: &str, limit: RateLimit) -> Self {
+        let counter = inner.get_model_config().token_counter();
+        Self {
+            inner,
+            provider: provider.to_string(),
+            limit,
+            counter,
+        }
+    }
+
+    /// Draw a request from the buckets, returning how long to wait before sending it
+    fn reserve(&self, tokens: f64) -> Duration {
+        let now = Instant::now();
+        let mut buckets = BUCKETS.lock().unwrap();
+        let buckets = buckets
+            .entry(self.provider.clone())
+            .or_insert_with(|| Buckets {
+                requests: self.limit.requests_per_minute.map(|n| Bucket::new(n, now)),
+                tokens: self.limit.tokens_per_minute.map(|n| Bucket::new(n, now)),
+            });
+        let requests = buckets.requests.as_mut().map(|b| b.take(1.0, now));
+        let tokens = buckets.tokens.as_mut().map(|b| b.take(tokens, now));
+        requests.max(tokens).unwrap_or_default()
+    }
+
+    fn settle(&self, tokens: f64) {
+        let mut buckets = BUCKETS.lock().unwrap();
+        if let Some(bucket) = buckets
+            .get_mut(&self.provider)
+            .and_then(|buckets| buckets.tokens.as_mut())
+        {
+            bucket.adjust(tokens, Instant::now());
+        }
+    }
+
+    /// Reserve the estimated tokens of a request, sleeping until the limits allow it
+    async fn wait_for_capacity(&self, system: &str, messages: &[Message], tools: &[Tool]) -> f64 {
+        let estimate = self.counter.count_chat_tokens(system, messages, tools) as f64;
+        let wait = self.reserve(estimate);
+        if !wait.is_zero() {
+            tracing::debug!(
+                "Waiting {}ms to stay under the rate limits of {}",
+                wait.as_millis(),
+                self.provider
+            );
+            tokio::time::sleep(wait).await;
+        }
+        estimate
+    }
+
+    /// Correct the reservation once the provider has reported what the request used
+    fn settle_usage(&self, estimate: f64, usage: &ProviderUsage) {
+        if let Some(total) = usage.usage.total_tokens {
+            self.settle(total as f64 - estimate);
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for RateLimitedProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let estimate = self.wait_for_capacity(system, messages, tools).await;
+        let result = self.inner.complete(system, messages, tools).await;
+        if let Ok((_, usage)) = &result {
+            self.settle_usage(estimate, usage);
+        }
+        result
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let estimate = self.wait_for_capacity(system, messages, tools).await;
+        let stream = self.inner.stream(system, messages, tools).await?;
+        let model = self.get_model_config().model_name;
+        Ok(inspect_stream(stream, model, move |result| {
+            if let Ok((_, usage)) = result {
+                self.settle_usage(estimate, usage);
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_paces_after_the_burst() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(60, start);
+        for _ in 0..60 {
+            assert_eq!(bucket.take(1.0, start), Duration::ZERO);
+        }
+        // Empty, and refilling at one per second
+        assert_eq!(bucket.take(1.0, start), Duration::from_secs(1));
+        assert_eq!(bucket.take(1.0, start), Duration::from_secs(2));
+        assert_eq!(
+            bucket.take(1.0, start + Duration::from_secs(3)),
+            Duration::ZERO
+        );
+
+        // Usage below the estimate is given back
+        let mut bucket = Bucket::new(1000, start);
+        assert_eq!(bucket.take(1500.0, start), Duration::ZERO);
+        bucket.adjust(-400.0, start);
+        assert_eq!(bucket.take(400.0, start), Duration::ZERO);
+    }
+}
@@ -0,0 +1,326 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde_json::Value;
+use std::time::Duration;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::replicate::{create_request, get_usage, response_to_message};
+use super::utils::{emit_debug_trace, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const REPLICATE_API_HOST: &str = "https://api.replicate.com";
+pub const REPLICATE_DEFAULT_MODEL: &str = "meta/meta-llama-3-70b-instruct";
+pub const REPLICATE_KNOWN_MODELS: &[&str] = &[
+    "meta/meta-llama-3-70b-instruct",
+    "meta/meta-llama-3-8b-instruct",
+    "mistralai/mixtral-8x7b-instruct-v0.1",
+    "ibm-granite/granite-3.3-8b-instruct",
+];
+
+pub const REPLICATE_DOC_URL: &str = "https://replicate.com/collections/language-models";
+
+// Polling starts quickly for short predictions and backs off for long ones
+const POLL_INITIAL_DELAY: Duration = Duration::from_millis(250);
+const POLL_MAX_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(serde::Serialize)]
+pub struct ReplicateProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_token: String,
+    model: ModelConfig,
+}
+
+impl Default for ReplicateProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(ReplicateProvider::metadata().default_model);
+        ReplicateProvider::from_env(model).expect("Failed to initialize Replicate provider")
+    }
+}
+
+/// Cancels a prediction that is still running when dropped, e.g. because the goose request
+/// waiting for it was aborted, so it doesn't keep running and billing
+struct CancelOnDrop {
+    client: Client,
+    api_token: String,
+    url: Option<String>,
+}
+
+impl CancelOnDrop {
+    fn disarm(&mut self) {
+        self.url = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(url) = self.url.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let request = self.client.post(&url).bearer_auth(&self.api_token);
+        runtime.spawn(async move {
+            match request.send().await {
+                Ok(_) => tracing::debug!("Canceled prediction {}", url),
+                Err(e) => tracing::warn!("Failed to cancel prediction {}: {}", url, e),
+            }
+        });
+    }
+}
+
+fn is_finished(prediction: &Value) -> bool {
+    matches!(
+        prediction["status"].as_str(),
+        Some("succeeded" | "failed" | "canceled")
+    )
+}
+
+impl ReplicateProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_token: String = config.get_secret("REPLICATE_API_TOKEN")?;
+        let host: String = config
+            .get("REPLICATE_HOST")
+            .unwrap_or_else(|_| REPLICATE_API_HOST.to_string());
+
+        let client = http_client("REPLICATE")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_token,
+            model,
+        })
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Value, ProviderError> {
+        let response = retry::send(request.bearer_auth(&self.api_token)).await?;
+
+        let status = response.status();
+        let payload: Option<Value> = response.json().await.ok();
+        if status.is_success() {
+            return payload.ok_or_else(|| {
+                ProviderError::RequestFailed("Response body is not valid JSON".to_string())
+            });
+        }
+
+        // https://replicate.com/docs/topics/predictions/errors
+        let detail = payload
+            .as_ref()
+            .and_then(|p| p["detail"].as_str())
+            .unwrap_or("Unknown error")
+            .to_string();
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderError::Authentication(format!(
+                    "Authentication failed. Please ensure your API token is valid. Status: {}. Message: {}",
+                    status, detail
+                )))
+            }
+            StatusCode::TOO_MANY_REQUESTS => Err(ProviderError::RateLimitExceeded(detail)),
+            status if status.is_server_error() => Err(ProviderError::ServerError(detail)),
+            _ => Err(ProviderError::RequestFailed(format!(
+                "Request failed with status: {}. Message: {}",
+                status, detail
+            ))),
+        }
+    }
+
+    /// Create a prediction and poll it until it finishes, returning the finished prediction
+    async fn predict(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        // A pinned version is run through the generic endpoint, which takes it in the body
+        let path = match self.model.model_name.split_once(':') {
+            Some(_) => "v1/predictions".to_string(),
+            None => format!("v1/models/{}/predictions", self.model.model_name),
+        };
+        let url = base_url.join(&path).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let mut prediction = self.send(self.client.post(url).json(&payload)).await?;
+        let mut cancel = CancelOnDrop {
+            client: self.client.clone(),
+            api_token: self.api_token.clone(),
+            url: prediction["urls"]["cancel"].as_str().map(String::from),
+        };
+
+        let mut delay = POLL_INITIAL_DELAY;
+        while !is_finished(&prediction) {
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(POLL_MAX_DELAY);
+
+            let url = prediction["urls"]["get"].as_str().ok_or_else(|| {
+                ProviderError::RequestFailed("Prediction has no URL to poll".to_string())
+            })?;
+            prediction = self.send(self.client.get(url)).await?;
+        }
+        cancel.disarm();
+        Ok(prediction)
+    }
+}
+
+#[async_trait]
+impl Provider for ReplicateProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "replicate",
+            "Replicate",
+            "Open source language models run on Replicate",
+            REPLICATE_DEFAULT_MODEL,
+            REPLICATE_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            REPLICATE_DOC_URL,
+            vec![
+                ConfigKey::new("REPLICATE_API_TOKEN", true, true, None),
+                ConfigKey::new("REPLICATE_HOST", false, false, Some(REPLICATE_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = vcr::post("replicate", &payload, || self.predict(payload.clone())).await?;
+
+        let message = response_to_message(&response)?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = response["model"]
+            .as_str()
+            .unwrap_or(&self.model.model_name)
+            .to_string();
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(host: String) -> ReplicateProvider {
+        ReplicateProvider {
+            client: Client::new(),
+            host,
+            api_token: "r8_test".to_string(),
+            model: ModelConfig::new(REPLICATE_DEFAULT_MODEL.to_string()),
+        }
+    }
+
+    fn prediction(server: &MockServer, status: &str) -> Value {
+        json!({
+            "id": "p1",
+            "model": REPLICATE_DEFAULT_MODEL,
+            "status": status,
+            "output": if status == "succeeded" { json!(["Hello", "!"]) } else { Value::Null },
+            "urls": {
+                "get": format!("{}/v1/predictions/p1", server.uri()),
+                "cancel": format!("{}/v1/predictions/p1/cancel", server.uri())
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_polls_until_finished() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(
+                "/v1/models/meta/meta-llama-3-70b-instruct/predictions",
+            ))
+            .respond_with(ResponseTemplate::new(201).set_body_json(prediction(&server, "starting")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(prediction(&server, "succeeded")),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions/p1/cancel"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let (message, usage) = provider(server.uri())
+            .complete("system", &[Message::user().with_text("Hi")], &[])
+            .await?;
+        assert_eq!(message.content[0].as_text(), Some("Hello!"));
+        assert_eq!(usage.model, REPLICATE_DEFAULT_MODEL);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_aborted_request_cancels_prediction() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(
+                "/v1/models/meta/meta-llama-3-70b-instruct/predictions",
+            ))
+            .respond_with(ResponseTemplate::new(201).set_body_json(prediction(&server, "starting")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(prediction(&server, "processing")),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions/p1/cancel"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction(&server, "canceled")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = provider(server.uri());
+        let messages = [Message::user().with_text("Hi")];
+        let request = provider.complete("system", &messages, &[]);
+        assert!(tokio::time::timeout(Duration::from_millis(500), request)
+            .await
+            .is_err());
+
+        // The cancellation is sent in the background
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        server.verify().await;
+        Ok(())
+    }
+}
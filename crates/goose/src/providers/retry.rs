@@ -0,0 +1,384 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::errors::ProviderError;
+use super::keys::KeyPool;
+use crate::config::Config;
+use crate::events::{self, Event};
+
+/// Config key holding the `RetryConfig`
+pub const RETRY_CONFIG_KEY: &str = "GOOSE_RETRY";
+
+/// When and how often HTTP providers send a request again after it fails
+///
+/// ```yaml
+/// GOOSE_RETRY:
+///   max_attempts: 5
+///   base_delay_ms: 1000
+///   max_delay_ms: 60000
+///   retryable_status: [429, 500, 502, 503, 504]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// How many times a request is sent at most, counting the first; 1 turns retries off
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubled for each retry after it
+    pub base_delay_ms: u64,
+    /// The longest delay between two attempts; a server asking to wait longer isn't retried
+    pub max_delay_ms: u64,
+    /// The response statuses worth sending the request again for
+    pub retryable_status: Vec<u16>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay_ms: 1000,
+            max_delay_ms: 60_000,
+            retryable_status: vec![429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Read `GOOSE_RETRY`, falling back to the defaults when it is not set
+    pub fn from_config() -> Self {
+        Config::global().get(RETRY_CONFIG_KEY).unwrap_or_default()
+    }
+
+    /// The delay before the `retry`th retry, counting from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u64.saturating_pow(retry.saturating_sub(1));
+        let delay_ms = self.base_delay_ms.saturating_mul(factor);
+        Duration::from_millis(delay_ms.min(self.max_delay_ms))
+    }
+
+    fn is_retryable(&self, response: &Response) -> bool {
+        self.retryable_status.contains(&response.status().as_u16())
+    }
+
+    /// How long to wait before the `retry`th retry of a failed response, None to give up
+    fn wait(&self, retry: u32, response: &Response) -> Option<Duration> {
+        match retry_after(response.headers()) {
+            Some(wait) if wait > Duration::from_millis(self.max_delay_ms) => None,
+            Some(wait) => Some(wait),
+            None => Some(self.delay(retry)),
+        }
+    }
+}
+
+/// Send a request with the retries configured under `GOOSE_RETRY`
+///
+/// Every HTTP provider sends its requests through here, so they all back off the same way.
+pub async fn send(request: RequestBuilder) -> Result<Response, ProviderError> {
+    send_with(&RetryConfig::from_config(), request).await
+}
+
+/// Send a request, sending it again while the response has a retryable status
+///
+/// The wait between attempts is the one the server asks for with `retry_after`, or else
+/// backs off exponentially. Once the attempts run out, or the server asks to wait longer than
+/// `max_delay_ms`, the last response is returned as it is, for the provider to turn into an
+/// error the way it does for any other failed request. Requests with a body that can't be
+/// copied, such as a stream, are only sent once.
+pub async fn send_with(
+    config: &RetryConfig,
+    request: RequestBuilder,
+) -> Result<Response, ProviderError> {
+    let mut attempt = 1;
+    loop {
+        let retry = match request.try_clone() {
+            Some(retry) if attempt < config.max_attempts => retry,
+            _ => return Ok(request.send().await?),
+        };
+
+        let response = retry.send().await?;
+        if !config.is_retryable(&response) {
+            return Ok(response);
+        }
+        let Some(delay) = config.wait(attempt, &response) else {
+            return Ok(response);
+        };
+        back_off(attempt, delay, &response).await;
+        attempt += 1;
+    }
+}
+
+/// Send a request like `send`, with a key from the pool for each attempt
+///
+/// `request` builds the request for a key. A rate limited key rests, and while another key is
+/// ready the request is sent again with it right away.
+pub async fn send_keyed(
+    keys: &KeyPool,
+    request: impl Fn(&str) -> RequestBuilder,
+) -> Result<Response, ProviderError> {
+    let config = RetryConfig::from_config();
+    let mut attempt = 1;
+    loop {
+        let key = keys.key();
+        let response = request(&key).send().await?;
+        keys.report(&key, &response);
+        if attempt >= config.max_attempts || !config.is_retryable(&response) {
+            return Ok(response);
+        }
+
+        let delay = if response.status() == StatusCode::TOO_MANY_REQUESTS && keys.has_available() {
+            Duration::ZERO
+        } else {
+            match config.wait(attempt, &response) {
+                Some(delay) => delay,
+                None => return Ok(response),
+            }
+        };
+        back_off(attempt, delay, &response).await;
+        attempt += 1;
+    }
+}
+
+async fn back_off(attempt: u32, delay: Duration, response: &Response) {
+    let reason = format!("server responded with {}", response.status());
+    tracing::warn!("Request failed, {}. Retrying in {:?}", reason, delay);
+    events::emit(Event::Retry {
+        attempt,
+        delay_ms: delay.as_millis() as u64,
+        reason,
+    });
+    tokio::time::sleep(delay).await;
+}
+
+/// How long the server asks to wait before the request is sent again
+///
+/// This is `retry-after`, in seconds or as a date, or OpenAI's `retry-after-ms`. Without them,
+/// it is the longest wait for an exhausted rate limit to reset: the `x-ratelimit-reset-*`
+/// durations of OpenAI and Groq, or the `anthropic-ratelimit-*-reset` times of Anthropic.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(ms) = header(headers, "retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return Duration::try_from_secs_f64(ms / 1000.0).ok();
+    }
+    if let Some(value) = header(headers, "retry-after") {
+        return match value.parse::<f64>() {
+            Ok(seconds) => Duration::try_from_secs_f64(seconds).ok(),
+            Err(_) => DateTime::parse_from_rfc2822(value)
+                .ok()
+                .map(|time| until(time.with_timezone(&Utc))),
+        };
+    }
+    headers
+        .keys()
+        .filter_map(|name| rate_limit_reset(headers, name.as_str()))
+        .max()
+}
+
+/// The wait for the rate limit whose reset time is in the header `name`, if it is exhausted
+fn rate_limit_reset(headers: &HeaderMap, name: &str) -> Option<Duration> {
+    if let Some(limit) = name.strip_prefix("x-ratelimit-reset-") {
+        if exhausted(headers, &format!("x-ratelimit-remaining-{}", limit)) {
+            return parse_duration(header(headers, name)?);
+        }
+    } else if let Some(limit) = name
+        .strip_prefix("anthropic-ratelimit-")
+        .and_then(|name| name.strip_suffix("-reset"))
+    {
+        if exhausted(headers, &format!("anthropic-ratelimit-{}-remaining", limit)) {
+            let time = DateTime::parse_from_rfc3339(header(headers, name)?).ok()?;
+            return Some(until(time.with_timezone(&Utc)));
+        }
+    }
+    None
+}
+
+/// Whether nothing remains of a rate limit, assuming so when the header is missing
+fn exhausted(headers: &HeaderMap, remaining: &str) -> bool {
+    header(headers, remaining)
+        .and_then(|value| value.parse::<f64>().ok())
+        .map_or(true, |remaining| remaining <= 0.0)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok().map(str::trim)
+}
+
+fn until(time: DateTime<Utc>) -> Duration {
+    (time - Utc::now()).to_std().unwrap_or_default()
+}
+
+/// Parse a duration such as `20ms`, `1.5s` or `6m0s`, with a bare number taken as seconds
+fn parse_duration(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let mut seconds = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let unit_start = rest.find(|c: char| c.is_ascii_alphabetic())?;
+        let (number, tail) = rest.split_at(unit_start);
+        let unit_end = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_end);
+        let scale = match unit {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        seconds += number.parse::<f64>().ok()? * scale;
+        rest = tail;
+    }
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::keys::KeyRotation;
+    use wiremock::matchers::{header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config(max_attempts: u32) -> RetryConfig {
+        RetryConfig {
+            max_attempts,
+            base_delay_ms: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_delay_backs_off_up_to_the_max() {
+        let config = RetryConfig {
+            base_delay_ms: 500,
+            max_delay_ms: 3000,
+            ..Default::default()
+        };
+        let delays: Vec<u128> = (1..=5)
+            .map(|retry| config.delay(retry).as_millis())
+            .collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 3000, 3000]);
+    }
+
+    #[test]
+    fn test_retry_after_headers() {
+        let headers = |pairs: &[(&'static str, String)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, value.parse().unwrap());
+            }
+            headers
+        };
+
+        let standard = headers(&[("retry-after", "7".to_string())]);
+        assert_eq!(retry_after(&standard), Some(Duration::from_secs(7)));
+        let openai = headers(&[
+            ("retry-after-ms", "250".to_string()),
+            ("retry-after", "1".to_string()),
+        ]);
+        assert_eq!(retry_after(&openai), Some(Duration::from_millis(250)));
+
+        // Only limits with nothing remaining count
+        let reset = headers(&[
+            ("x-ratelimit-remaining-requests", "20".to_string()),
+            ("x-ratelimit-reset-requests", "6m0s".to_string()),
+            ("x-ratelimit-remaining-tokens", "0".to_string()),
+            ("x-ratelimit-reset-tokens", "1.5s".to_string()),
+        ]);
+        assert_eq!(retry_after(&reset), Some(Duration::from_millis(1500)));
+
+        let reset_at = (Utc::now() + chrono::Duration::seconds(30)).to_rfc3339();
+        let anthropic = headers(&[
+            ("anthropic-ratelimit-tokens-remaining", "0".to_string()),
+            ("anthropic-ratelimit-tokens-reset", reset_at),
+        ]);
+        let wait = retry_after(&anthropic).unwrap();
+        assert!(wait > Duration::from_secs(28) && wait <= Duration::from_secs(30));
+
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_key_is_swapped() {
+        let server = MockServer::start().await;
+        Mock::given(header("authorization", "Bearer first"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "120"))
+            .mount(&server)
+            .await;
+        Mock::given(header("authorization", "Bearer second"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let client = reqwest::Client::new();
+        let keys = KeyPool::new(
+            vec!["first".to_string(), "second".to_string()],
+            KeyRotation::OnRateLimit,
+        );
+
+        let response = send_keyed(&keys, |key| client.post(server.uri()).bearer_auth(key))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+        assert_eq!(keys.key(), "second");
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success_or_attempts_run_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let client = reqwest::Client::new();
+
+        let response = send_with(&config(3), client.post(server.uri()).body("{}"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+
+        // A status that isn't retryable comes back after a single attempt
+        server.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&server)
+            .await;
+        let response = send_with(&config(3), client.post(server.uri()).body("{}"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        // The last failed response is returned once the attempts run out
+        server.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429))
+            .mount(&server)
+            .await;
+        let response = send_with(&config(2), client.post(server.uri()).body("{}"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 429);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        // Nor is it sent again when the server asks to wait longer than the longest delay
+        server.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "86400"))
+            .mount(&server)
+            .await;
+        let response = send_with(&config(3), client.post(server.uri()).body("{}"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 429);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+}
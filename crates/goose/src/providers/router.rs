@@ -0,0 +1,297 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::base::{ConfigKey, MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the `RouterConfig`
+pub const ROUTER_CONFIG_KEY: &str = "GOOSE_ROUTER";
+
+// Conversations remembered for sticky sessions, forgotten all at once past this
+const MAX_SESSIONS: usize = 10_000;
+
+// How much each new latency counts in a backend's average
+const LATENCY_SMOOTHING: f64 = 0.3;
+
+/// How the router picks the backend of a new conversation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouterStrategy {
+    /// At random, in proportion to the weights
+    #[default]
+    Weighted,
+    /// The backend that has answered fastest lately, trying each one first
+    Latency,
+}
+
+/// A provider the router sends completions to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouterBackend {
+    pub provider: String,
+    /// The model to use, the provider's default when not set
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// The backends of the `router` provider and how it balances between them
+///
+/// ```yaml
+/// GOOSE_ROUTER:
+///   strategy: weighted
+///   backends:
+///     - provider: vllm
+///       weight: 3
+///     - provider: tgi
+///       weight: 1
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouterConfig {
+    #[serde(default)]
+    pub strategy: RouterStrategy,
+    pub backends: Vec<RouterBackend>,
+}
+
+#[derive(Debug, Default)]
+struct RouterState {
+    /// The backend each conversation went to, by the hash of its first message
+    sessions: HashMap<u64, usize>,
+    /// The average latency of each backend, None until it has answered
+    latencies: Vec<Option<Duration>>,
+}
+
+/// A virtual provider that balances completions across several backends
+///
+/// Each conversation sticks to the backend its first completion went to, so that servers
+/// which cache prompts keep serving the same conversation. New conversations are spread by
+/// the configured `RouterStrategy`.
+pub struct RouterProvider {
+    model: ModelConfig,
+    strategy: RouterStrategy,
+    backends: Vec<(RouterBackend, Box<dyn Provider>)>,
+    state: Mutex<RouterState>,
+}
+
+impl RouterProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config: RouterConfig = Config::global().get(ROUTER_CONFIG_KEY)?;
+        let mut backends = Vec::new();
+        for backend in config.backends {
+            if backend.provider == "router" {
+                anyhow::bail!("The router can't be one of its own backends");
+            }
+            let model = match &backend.model {
+                Some(model) => model.clone(),
+                None => super::factory::default_model(&backend.provider)?,
+            };
+            let provider =
+                super::factory::create_provider(&backend.provider, ModelConfig::new(model))?;
+            backends.push((backend, provider as Box<dyn Provider>));
+        }
+        Self::new(model, config.strategy, backends)
+    }
+
+    pub fn new(
+        model: ModelConfig,
+        strategy: RouterStrategy,
+        backends: Vec<(RouterBackend, Box<dyn Provider>)>,
+    ) -> Result<Self> {
+        if backends.is_empty() {
+            anyhow::bail!(
+                "The router needs at least one backend in {}",
+                ROUTER_CONFIG_KEY
+            );
+        }
+        if strategy == RouterStrategy::Weighted && backends.iter().all(|(b, _)| b.weight == 0) {
+            anyhow::bail!("The router needs a backend with a weight above 0");
+        }
+        let state = RouterState {
+            sessions: HashMap::new(),
+            latencies: vec![None; backends.len()],
+        };
+        Ok(Self {
+            model,
+            strategy,
+            backends,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// The backend for a conversation, picking one if it is new
+    fn route(&self, messages: &[Message]) -> usize {
+        let session = messages.first().map(|first| {
+            let mut hasher = DefaultHasher::new();
+            serde_json::to_string(first)
+                .unwrap_or_default()
+                .hash(&mut hasher);
+            hasher.finish()
+        });
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(index) = session.and_then(|session| state.sessions.get(&session)) {
+            return *index;
+        }
+        let index = match self.strategy {
+            RouterStrategy::Weighted => self.pick_weighted(),
+            RouterStrategy::Latency => pick_fastest(&state.latencies),
+        };
+        if let Some(session) = session {
+            if state.sessions.len() >= MAX_SESSIONS {
+                state.sessions.clear();
+            }
+            state.sessions.insert(session, index);
+        }
+        index
+    }
+
+    fn pick_weighted(&self) -> usize {
+        let total: u32 = self.backends.iter().map(|(b, _)| b.weight).sum();
+        let mut pick = rand::thread_rng().gen_range(0..total);
+        for (index, (backend, _)) in self.backends.iter().enumerate() {
+            if pick < backend.weight {
+                return index;
+            }
+            pick -= backend.weight;
+        }
+        0
+    }
+
+    fn record_latency(&self, index: usize, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let average = match state.latencies[index] {
+            Some(average) => {
+                average.mul_f64(1.0 - LATENCY_SMOOTHING) + latency.mul_f64(LATENCY_SMOOTHING)
+            }
+            None => latency,
+        };
+        state.latencies[index] = Some(average);
+    }
+}
+
+/// The backend with the lowest average latency, or the first that hasn't answered yet
+fn pick_fastest(latencies: &[Option<Duration>]) -> usize {
+    latencies
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, latency)| latency.map_or((0, Duration::ZERO), |latency| (1, latency)))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+#[async_trait]
+impl Provider for RouterProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "router",
+            "Router",
+            "Balances completions across several providers, by weight or latency",
+            "",
+            vec![],
+            "",
+            vec![ConfigKey::new(ROUTER_CONFIG_KEY, true, false, None)],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let index = self.route(messages);
+        let started = Instant::now();
+        let result = self.backends[index]
+            .1
+            .complete(system, messages, tools)
+            .await;
+        if result.is_ok() {
+            self.record_latency(index, started.elapsed());
+        }
+        result
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let index = self.route(messages);
+        let started = Instant::now();
+        let stream = self.backends[index]
+            .1
+            .stream(system, messages, tools)
+            .await?;
+        // The time to the first response, as the stream is read by the caller
+        self.record_latency(index, started.elapsed());
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    fn backend(provider: &str, weight: u32) -> RouterBackend {
+        RouterBackend {
+            provider: provider.to_string(),
+            model: None,
+            weight,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conversations_stick_to_a_backend() -> Result<()> {
+        let first = (0..6).fold(MockProvider::default(), |mock, _| mock.with_text("first"));
+        let second = (0..6).fold(MockProvider::default(), |mock, _| mock.with_text("second"));
+        let router = RouterProvider::new(
+            ModelConfig::new("router".to_string()),
+            RouterStrategy::Weighted,
+            vec![
+                (
+                    backend("a", 1),
+                    Box::new(first.clone()) as Box<dyn Provider>,
+                ),
+                (backend("b", 1), Box::new(second.clone())),
+            ],
+        )?;
+
+        let conversation = vec![Message::user().with_text("Hello")];
+        let (reply, _) = router.complete("", &conversation, &[]).await?;
+        for _ in 0..5 {
+            let (next, _) = router.complete("", &conversation, &[]).await?;
+            assert_eq!(next.as_concat_text(), reply.as_concat_text());
+        }
+        assert_eq!(first.requests().len() + second.requests().len(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pick_fastest() {
+        let ms = |ms| Some(Duration::from_millis(ms));
+        assert_eq!(pick_fastest(&[ms(300), ms(120), ms(200)]), 1);
+        // Backends that haven't answered yet are tried first
+        assert_eq!(pick_fastest(&[ms(300), None, ms(100)]), 1);
+    }
+}
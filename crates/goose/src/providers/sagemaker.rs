@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use aws_config::SdkConfig;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sigv4::http_request::{sign, SignableBody, SignableRequest, SigningSettings};
+use aws_sigv4::sign::v4;
+use reqwest::Client;
+use serde_json::Value;
+use std::time::SystemTime;
+use tokio::sync::OnceCell;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::sagemaker::{
+    create_request, get_usage, response_to_message, SageMakerTemplate,
+};
+use super::utils::{emit_debug_trace, handle_response_openai_compat, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const SAGEMAKER_DEFAULT_MODEL: &str = "meta-llama-3-1-8b-instruct";
+// The endpoint serves whichever model was deployed to it, we only provide the default
+pub const SAGEMAKER_KNOWN_MODELS: &[&str] = &[SAGEMAKER_DEFAULT_MODEL];
+
+pub const SAGEMAKER_DOC_URL: &str =
+    "https://docs.aws.amazon.com/sagemaker/latest/dg/realtime-endpoints.html";
+
+/// Config key holding the `SageMakerTemplate`
+pub const SAGEMAKER_TEMPLATE_CONFIG_KEY: &str = "SAGEMAKER_TEMPLATE";
+
+/// A SageMaker real-time inference endpoint, invoked with requests signed by the AWS
+/// credentials in the environment
+#[derive(serde::Serialize)]
+pub struct SageMakerProvider {
+    #[serde(skip)]
+    client: Client,
+    #[serde(skip)]
+    sdk_config: OnceCell<SdkConfig>,
+    endpoint_name: String,
+    region: Option<String>,
+    template: SageMakerTemplate,
+    model: ModelConfig,
+}
+
+impl Default for SageMakerProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(SageMakerProvider::metadata().default_model);
+        SageMakerProvider::from_env(model).expect("Failed to initialize SageMaker provider")
+    }
+}
+
+impl SageMakerProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let endpoint_name: String = config.get("SAGEMAKER_ENDPOINT_NAME")?;
+        // Otherwise the region comes from the AWS config, like the credentials
+        let region: Option<String> = config.get("AWS_REGION").ok();
+        let template: SageMakerTemplate = config
+            .get(SAGEMAKER_TEMPLATE_CONFIG_KEY)
+            .unwrap_or_default();
+
+        let client = http_client("SAGEMAKER")?;
+
+        Ok(Self {
+            client,
+            sdk_config: OnceCell::new(),
+            endpoint_name,
+            region,
+            template,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        self.invoke(payload)
+            .await
+            .map_err(|e| match e.downcast::<ProviderError>() {
+                Ok(e) => e,
+                Err(e) => ProviderError::RequestFailed(e.to_string()),
+            })
+    }
+
+    async fn invoke(&self, payload: Value) -> Result<Value> {
+        let sdk_config = self.sdk_config.get_or_init(aws_config::load_from_env).await;
+        let region = self
+            .region
+            .clone()
+            .or_else(|| sdk_config.region().map(|r| r.to_string()))
+            .ok_or_else(|| anyhow!("No AWS region configured, set AWS_REGION"))?;
+        let credentials = sdk_config
+            .credentials_provider()
+            .ok_or_else(|| ProviderError::Authentication("No AWS credentials found".into()))?
+            .provide_credentials()
+            .await
+            .map_err(|e| ProviderError::Authentication(e.to_string()))?;
+
+        let url = format!(
+            "https://runtime.sagemaker.{}.amazonaws.com/endpoints/{}/invocations",
+            region, self.endpoint_name
+        );
+        let body = serde_json::to_vec(&payload)?;
+        let headers = [
+            ("content-type", "application/json"),
+            ("accept", "application/json"),
+        ];
+
+        let identity = credentials.into();
+        let params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(&region)
+            .name("sagemaker")
+            .time(SystemTime::now())
+            .settings(SigningSettings::default())
+            .build()?
+            .into();
+        let signable = SignableRequest::new(
+            "POST",
+            &url,
+            headers.iter().copied(),
+            SignableBody::Bytes(&body),
+        )?;
+        let (instructions, _) = sign(signable, &params)?.into_parts();
+
+        let mut request = self.client.post(&url).body(body.clone());
+        for (name, value) in headers.iter().copied().chain(instructions.headers()) {
+            request = request.header(name, value);
+        }
+        let response = retry::send(request).await?;
+
+        Ok(handle_response_openai_compat(response).await?)
+    }
+}
+
+#[async_trait]
+impl Provider for SageMakerProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "sagemaker",
+            "Amazon SageMaker",
+            "Models deployed to a SageMaker real-time inference endpoint, using the AWS credentials in your environment",
+            SAGEMAKER_DEFAULT_MODEL,
+            SAGEMAKER_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            SAGEMAKER_DOC_URL,
+            vec![
+                ConfigKey::new("SAGEMAKER_ENDPOINT_NAME", true, false, None),
+                ConfigKey::new("AWS_REGION", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &self.template)?;
+
+        let response = vcr::post("sagemaker", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone(), &self.template)?;
+        let usage = match get_usage(&response, &self.template) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        // Containers name the model inconsistently, if at all
+        let model = self.model.model_name.clone();
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
@@ -0,0 +1,359 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::vcr::RecordedError;
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use mcp_core::tool::{Tool, ToolCall};
+
+/// Config key holding the path of the scenario file played by the `scripted` provider
+pub const SCENARIO_CONFIG_KEY: &str = "GOOSE_SCRIPTED_SCENARIO";
+
+pub const SCRIPTED_DEFAULT_MODEL: &str = "scripted";
+
+/// A tool call emitted by a scripted step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptedToolCall {
+    pub name: String,
+    #[serde(default = "no_arguments")]
+    pub arguments: Value,
+}
+
+fn no_arguments() -> Value {
+    Value::Object(Default::default())
+}
+
+/// One reply of a scenario
+///
+/// A step either fails with `error` or replies with its `text` followed by its
+/// `tool_calls`. The `delay_ms` is waited out before either.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioStep {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ScriptedToolCall>,
+    #[serde(default)]
+    pub error: Option<RecordedError>,
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ScriptedUsage>,
+}
+
+/// Token counts reported for a scripted step
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScriptedUsage {
+    #[serde(default)]
+    pub input_tokens: Option<i32>,
+    #[serde(default)]
+    pub output_tokens: Option<i32>,
+}
+
+/// A reply given whenever the conversation matches, rather than in order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CannedResponse {
+    /// A regex searched for in the text of the latest user message, including tool
+    /// results; a response without one matches anything
+    #[serde(default, rename = "match", skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(flatten)]
+    pub step: ScenarioStep,
+}
+
+/// Replies loaded from YAML or JSON: ordered steps, then canned responses
+///
+/// ```yaml
+/// steps:
+///   - text: Let me look at the files first.
+///     tool_calls:
+///       - name: developer__shell
+///         arguments: {command: ls}
+///   - error: {kind: rate_limit_exceeded, message: slow down}
+///     delay_ms: 500
+///   - text: There are two files in this directory.
+/// responses:
+///   - match: (?i)weather
+///     text: It's sunny.
+///   - text: I can only talk about the weather.
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub steps: Vec<ScenarioStep>,
+    #[serde(default)]
+    pub responses: Vec<CannedResponse>,
+}
+
+impl Scenario {
+    /// Parse a scenario from YAML, which also accepts JSON
+    pub fn parse(contents: &str) -> Result<Self> {
+        let scenario: Self = serde_yaml::from_str(contents)?;
+        for response in &scenario.responses {
+            if let Some(pattern) = &response.pattern {
+                Regex::new(pattern)
+                    .map_err(|e| anyhow::anyhow!("Invalid match {}: {}", pattern, e))?;
+            }
+        }
+        Ok(scenario)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read scenario {}: {}", path.display(), e))?;
+        Self::parse(&contents)
+    }
+}
+
+/// A provider that plays back a scenario, one step per call to `complete`
+///
+/// This makes multi-turn agent behavior reproducible without a model: the agent sees the
+/// scripted tool calls, runs them, and gets the next step back. Every request takes a step,
+/// including the agent's own side requests such as the read-only tool check. Once the steps
+/// run out, requests get the first canned response that matches, and fail if none does.
+/// Also available as the `mock` provider.
+pub struct ScriptedProvider {
+    model: ModelConfig,
+    scenario: Scenario,
+    canned: Vec<(Option<Regex>, ScenarioStep)>,
+    next: AtomicUsize,
+}
+
+impl ScriptedProvider {
+    pub fn new(scenario: Scenario, model: ModelConfig) -> Self {
+        let canned = scenario
+            .responses
+            .iter()
+            .filter_map(|response| match &response.pattern {
+                None => Some((None, response.step.clone())),
+                Some(pattern) => match Regex::new(pattern) {
+                    Ok(regex) => Some((Some(regex), response.step.clone())),
+                    Err(e) => {
+                        tracing::warn!("Skipping the canned response for {}: {}", pattern, e);
+                        None
+                    }
+                },
+            })
+            .collect();
+        Self {
+            model,
+            scenario,
+            canned,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = Config::global();
+        let path: String = config.get(SCENARIO_CONFIG_KEY)?;
+        Ok(Self::new(Scenario::load(path)?, model))
+    }
+
+    /// The first canned response matching the latest user message
+    fn canned_response(&self, messages: &[Message]) -> Option<&ScenarioStep> {
+        let text = messages
+            .iter()
+            .rev()
+            .find(|message| message.role == mcp_core::Role::User)
+            .map(user_text)
+            .unwrap_or_default();
+        self.canned
+            .iter()
+            .find(|(pattern, _)| pattern.as_ref().is_none_or(|p| p.is_match(&text)))
+            .map(|(_, step)| step)
+    }
+
+    /// The number of steps that have not been played yet
+    pub fn remaining(&self) -> usize {
+        self.scenario
+            .steps
+            .len()
+            .saturating_sub(self.next.load(Ordering::SeqCst))
+    }
+}
+
+/// The text of a user message and of the tool results it carries
+fn user_text(message: &Message) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            MessageContent::Text(text) => Some(text.text.clone()),
+            MessageContent::ToolResponse(_) => content.as_tool_response_text(),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[async_trait]
+impl Provider for ScriptedProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "scripted",
+            "Scripted",
+            "Plays back a scenario file of responses, for tests and demos",
+            SCRIPTED_DEFAULT_MODEL,
+            vec![SCRIPTED_DEFAULT_MODEL.to_string()],
+            "",
+            vec![ConfigKey::new(SCENARIO_CONFIG_KEY, true, false, None)],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        _system: &str,
+        messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let index = self.next.fetch_add(1, Ordering::SeqCst);
+        let step = match self.scenario.steps.get(index) {
+            Some(step) => step,
+            None => self.canned_response(messages).ok_or_else(|| {
+                ProviderError::ExecutionError(format!(
+                    "Scenario has no more steps after {} and no response matches",
+                    self.scenario.steps.len()
+                ))
+            })?,
+        };
+
+        if step.delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(step.delay_ms)).await;
+        }
+
+        if let Some(error) = &step.error {
+            return Err(error.clone().into());
+        }
+
+        let mut message = Message::assistant();
+        if let Some(text) = &step.text {
+            message = message.with_text(text);
+        }
+        for (i, call) in step.tool_calls.iter().enumerate() {
+            message = message.with_tool_request(
+                format!("scripted_{}_{}", index, i),
+                Ok(ToolCall::new(&call.name, call.arguments.clone())),
+            );
+        }
+
+        let usage = step
+            .usage
+            .map(|u| {
+                let total = match (u.input_tokens, u.output_tokens) {
+                    (Some(input), Some(output)) => Some(input + output),
+                    _ => None,
+                };
+                Usage::new(u.input_tokens, u.output_tokens, total)
+            })
+            .unwrap_or_default();
+
+        Ok((
+            message,
+            ProviderUsage::new(self.model.model_name.clone(), usage),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const SCENARIO: &str = r#"
+steps:
+  - text: Let me look.
+    tool_calls:
+      - name: developer__shell
+        arguments: {command: ls}
+    usage: {input_tokens: 10, output_tokens: 4}
+  - error: {kind: rate_limit_exceeded, message: slow down}
+    delay_ms: 50
+  - text: Done.
+"#;
+
+    #[tokio::test]
+    async fn test_plays_steps_in_order() {
+        let scenario = Scenario::parse(SCENARIO).unwrap();
+        let provider = ScriptedProvider::new(scenario, ModelConfig::new("scripted".to_string()));
+
+        let (message, usage) = provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "Let me look.");
+        let request = message.content[1].as_tool_request().unwrap();
+        assert_eq!(request.id, "scripted_0_0");
+        let call = request.tool_call.as_ref().unwrap();
+        assert_eq!(call.name, "developer__shell");
+        assert_eq!(call.arguments, serde_json::json!({"command": "ls"}));
+        assert_eq!(usage.usage.total_tokens, Some(14));
+
+        let start = Instant::now();
+        let result = provider.complete("", &[], &[]).await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(matches!(result, Err(ProviderError::RateLimitExceeded(m)) if m == "slow down"));
+
+        let (message, _) = provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "Done.");
+        assert_eq!(provider.remaining(), 0);
+
+        assert!(provider.complete("", &[], &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_canned_responses_after_steps() {
+        let scenario = Scenario::parse(
+            r#"
+steps:
+  - text: First.
+responses:
+  - match: (?i)weather
+    text: It's sunny.
+  - match: ^ls
+    tool_calls: [{name: developer__shell, arguments: {command: ls}}]
+"#,
+        )
+        .unwrap();
+        let provider = ScriptedProvider::new(scenario, ModelConfig::new("mock".to_string()));
+        let ask = |text: &str| vec![Message::user().with_text(text)];
+
+        let (message, _) = provider.complete("", &ask("weather?"), &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "First.");
+
+        let (message, _) = provider
+            .complete("", &ask("What's the Weather?"), &[])
+            .await
+            .unwrap();
+        assert_eq!(message.as_concat_text(), "It's sunny.");
+        let (message, _) = provider.complete("", &ask("ls -la"), &[]).await.unwrap();
+        assert!(message.is_tool_call());
+
+        assert!(provider.complete("", &ask("hello"), &[]).await.is_err());
+        assert!(Scenario::parse("responses: [{match: '(', text: x}]").is_err());
+    }
+
+    #[test]
+    fn test_parses_json() {
+        let scenario = Scenario::parse(
+            r#"{"steps": [{"tool_calls": [{"name": "memory__remember"}]}, {"text": "ok"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(scenario.steps.len(), 2);
+        assert_eq!(
+            scenario.steps[0].tool_calls[0].arguments,
+            serde_json::json!({})
+        );
+        assert_eq!(scenario.steps[1].text.as_deref(), Some("ok"));
+    }
+}
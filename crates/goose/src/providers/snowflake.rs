@@ -0,0 +1,365 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use tokio::sync::Mutex as TokioMutex;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::snowflake::{collect_stream, create_request, get_usage, response_to_message};
+use super::utils::{emit_debug_trace, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const SNOWFLAKE_DEFAULT_MODEL: &str = "claude-3-5-sonnet";
+// Of the Cortex models, only the Claude and OpenAI ones can call tools
+pub const SNOWFLAKE_KNOWN_MODELS: &[&str] = &[
+    "claude-3-5-sonnet",
+    "claude-3-7-sonnet",
+    "claude-4-sonnet",
+    "openai-gpt-4.1",
+    "llama3.3-70b",
+    "snowflake-llama-3.3-70b",
+    "mistral-large2",
+    "deepseek-r1",
+];
+
+pub const SNOWFLAKE_DOC_URL: &str =
+    "https://docs.snowflake.com/en/user-guide/snowflake-cortex/cortex-llm-rest-api";
+
+// Key pair tokens may be valid for at most an hour
+const JWT_TTL_SECS: i64 = 59 * 60;
+
+// Tokens are signed again a little before they expire, so none expires mid request
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// How requests to Snowflake are authenticated
+enum SnowflakeAuth {
+    /// A JWT signed with the private key of a key pair registered for the user
+    KeyPair {
+        user: String,
+        private_key: String,
+        /// The `SHA256:...` fingerprint of the public key, as `DESC USER` shows it
+        fingerprint: String,
+    },
+    /// An OAuth access token from the account's security integration
+    OAuth(String),
+}
+
+#[derive(Debug, Clone)]
+struct SignedToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Models served through the Snowflake Cortex REST API
+///
+/// The account is the Snowflake account identifier, such as `myorg-myaccount`, which also
+/// names the host unless `SNOWFLAKE_HOST` is set.
+#[derive(serde::Serialize)]
+pub struct SnowflakeProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    account: String,
+    #[serde(skip)]
+    auth: SnowflakeAuth,
+    #[serde(skip)]
+    token: TokioMutex<Option<SignedToken>>,
+    model: ModelConfig,
+}
+
+impl Default for SnowflakeProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(SnowflakeProvider::metadata().default_model);
+        SnowflakeProvider::from_env(model).expect("Failed to initialize Snowflake provider")
+    }
+}
+
+impl SnowflakeProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let account: String = config.get("SNOWFLAKE_ACCOUNT")?;
+        let host: String = config
+            .get("SNOWFLAKE_HOST")
+            .unwrap_or_else(|_| format!("https://{}.snowflakecomputing.com", account));
+
+        // A configured key pair takes precedence over an OAuth token
+        let auth = match config.get_secret::<String>("SNOWFLAKE_PRIVATE_KEY") {
+            Ok(private_key) => SnowflakeAuth::KeyPair {
+                user: config.get("SNOWFLAKE_USER")?,
+                private_key,
+                fingerprint: config.get("SNOWFLAKE_PUBLIC_KEY_FP")?,
+            },
+            Err(_) => SnowflakeAuth::OAuth(config.get_secret("SNOWFLAKE_TOKEN").map_err(|_| {
+                anyhow!("Snowflake needs SNOWFLAKE_PRIVATE_KEY for key pair authentication or SNOWFLAKE_TOKEN for OAuth")
+            })?),
+        };
+
+        let client = http_client("SNOWFLAKE")?;
+
+        Ok(Self {
+            client,
+            host,
+            account,
+            auth,
+            token: TokioMutex::new(None),
+            model,
+        })
+    }
+
+    /// The token and its type for the `X-Snowflake-Authorization-Token-Type` header; key
+    /// pair tokens are cached until shortly before they expire
+    async fn authorization(&self) -> Result<(String, &'static str)> {
+        let (user, private_key, fingerprint) = match &self.auth {
+            SnowflakeAuth::OAuth(token) => return Ok((token.clone(), "OAUTH")),
+            SnowflakeAuth::KeyPair {
+                user,
+                private_key,
+                fingerprint,
+            } => (user, private_key, fingerprint),
+        };
+
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if token.expires_at - ChronoDuration::seconds(EXPIRY_MARGIN_SECS) > Utc::now() {
+                return Ok((token.token.clone(), "KEYPAIR_JWT"));
+            }
+        }
+
+        let now = Utc::now();
+        let claims = key_pair_claims(&self.account, user, fingerprint, now.timestamp());
+        let key = EncodingKey::from_rsa_pem(private_key.as_bytes())
+            .map_err(|e| anyhow!("Invalid SNOWFLAKE_PRIVATE_KEY: {}", e))?;
+        let token = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)?;
+        *cached = Some(SignedToken {
+            token: token.clone(),
+            expires_at: now + ChronoDuration::seconds(JWT_TTL_SECS),
+        });
+        Ok((token, "KEYPAIR_JWT"))
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url
+            .join("api/v2/cortex/inference:complete")
+            .map_err(|e| {
+                ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+            })?;
+
+        let (token, token_type) = self
+            .authorization()
+            .await
+            .map_err(|e| ProviderError::Authentication(e.to_string()))?;
+        let request = self
+            .client
+            .post(url)
+            .bearer_auth(token)
+            .header("X-Snowflake-Authorization-Token-Type", token_type)
+            .header("Accept", "application/json, text/event-stream")
+            .json(&payload);
+        let response = retry::send(request).await?;
+
+        let status = response.status();
+        if status == StatusCode::OK {
+            let streamed = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("text/event-stream"));
+            let body = response.text().await?;
+            if streamed {
+                return collect_stream(&body)
+                    .map_err(|e| ProviderError::RequestFailed(e.to_string()));
+            }
+            return serde_json::from_str(&body).map_err(|_| {
+                ProviderError::RequestFailed("Response body is not valid JSON".to_string())
+            });
+        }
+
+        let payload: Option<Value> = response.json().await.ok();
+        let message = payload
+            .as_ref()
+            .and_then(|p| p["message"].as_str())
+            .unwrap_or("Unknown error")
+            .to_string();
+
+        // https://docs.snowflake.com/en/user-guide/snowflake-cortex/cortex-llm-rest-api#error-codes
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                // Drop the token in case the key was rotated, so the next request signs a new one
+                self.token.lock().await.take();
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure the key pair or OAuth token is valid and the role has the CORTEX_USER database role. \
+                    Status: {}. Message: {}", status, message)))
+            }
+            StatusCode::BAD_REQUEST => {
+                if message.contains("max tokens") || message.contains("context window") {
+                    return Err(ProviderError::ContextLengthExceeded(message));
+                }
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}. Message: {}",
+                    status, message
+                )))
+            }
+            StatusCode::TOO_MANY_REQUESTS => Err(ProviderError::RateLimitExceeded(message)),
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                Err(ProviderError::ServerError(message))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}",
+                    format!(
+                        "Provider request failed with status: {}. Payload: {:?}",
+                        status, payload
+                    )
+                );
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}. Message: {}",
+                    status, message
+                )))
+            }
+        }
+    }
+}
+
+/// The claims of a key pair JWT, issued by the key's fingerprint for the user
+///
+/// Snowflake wants the account and user in upper case, and an account locator without the
+/// region and cloud that may follow it, as in `xy12345.us-east-2.aws`.
+fn key_pair_claims(account: &str, user: &str, fingerprint: &str, now: i64) -> Value {
+    let account = account.split('.').next().unwrap_or(account).to_uppercase();
+    let user = user.to_uppercase();
+    let fingerprint = if fingerprint.starts_with("SHA256:") {
+        fingerprint.to_string()
+    } else {
+        format!("SHA256:{}", fingerprint)
+    };
+    json!({
+        "iss": format!("{}.{}.{}", account, user, fingerprint),
+        "sub": format!("{}.{}", account, user),
+        "iat": now,
+        "exp": now + JWT_TTL_SECS,
+    })
+}
+
+#[async_trait]
+impl Provider for SnowflakeProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "snowflake",
+            "Snowflake Cortex",
+            "Claude, Llama and other models served from your Snowflake account",
+            SNOWFLAKE_DEFAULT_MODEL,
+            SNOWFLAKE_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            SNOWFLAKE_DOC_URL,
+            vec![
+                ConfigKey::new("SNOWFLAKE_ACCOUNT", true, false, None),
+                ConfigKey::new("SNOWFLAKE_USER", false, false, None),
+                ConfigKey::new("SNOWFLAKE_PRIVATE_KEY", false, true, None),
+                ConfigKey::new("SNOWFLAKE_PUBLIC_KEY_FP", false, false, None),
+                ConfigKey::new("SNOWFLAKE_TOKEN", false, true, None),
+                ConfigKey::new("SNOWFLAKE_HOST", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = vcr::post("snowflake", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = response["model"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| self.model.model_name.clone());
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_key_pair_claims() {
+        let claims = key_pair_claims(
+            "xy12345.us-east-2.aws",
+            "goose_svc",
+            "SHA256:fQbUoVmK4Tm8mQyJbXyhKhvyQmJt0ZcKS2g8vXwI1nI=",
+            1_700_000_000,
+        );
+        assert_eq!(
+            claims["iss"],
+            "XY12345.GOOSE_SVC.SHA256:fQbUoVmK4Tm8mQyJbXyhKhvyQmJt0ZcKS2g8vXwI1nI="
+        );
+        assert_eq!(claims["sub"], "XY12345.GOOSE_SVC");
+        assert_eq!(claims["exp"], 1_700_000_000 + JWT_TTL_SECS);
+    }
+
+    #[tokio::test]
+    async fn test_oauth_streamed_response() -> Result<()> {
+        let server = MockServer::start().await;
+        let body = concat!(
+            "data: {\"id\":\"a1\",\"model\":\"claude-3-5-sonnet\",\"choices\":[{\"delta\":{\"type\":\"text\",\"content\":\"Hel\"}}]}\n\n",
+            "data: {\"id\":\"a1\",\"choices\":[{\"delta\":{\"type\":\"text\",\"content\":\"lo!\"}}],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":2,\"total_tokens\":11}}\n\n",
+        );
+        Mock::given(method("POST"))
+            .and(path("/api/v2/cortex/inference:complete"))
+            .and(header("authorization", "Bearer oauth-token"))
+            .and(header("x-snowflake-authorization-token-type", "OAUTH"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = SnowflakeProvider {
+            client: Client::new(),
+            host: server.uri(),
+            account: "myorg-myaccount".to_string(),
+            auth: SnowflakeAuth::OAuth("oauth-token".to_string()),
+            token: TokioMutex::new(None),
+            model: ModelConfig::new(SNOWFLAKE_DEFAULT_MODEL.to_string()),
+        };
+        let (message, usage) = provider
+            .complete("system", &[Message::user().with_text("Hi")], &[])
+            .await?;
+        assert_eq!(message.as_concat_text(), "Hello!");
+        assert_eq!(usage.model, "claude-3-5-sonnet");
+        assert_eq!(usage.usage.total_tokens, Some(11));
+        Ok(())
+    }
+}
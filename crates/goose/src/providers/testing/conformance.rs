@@ -0,0 +1,391 @@
+//! A conformance suite that any `Provider` can be run against
+//!
+//! ```ignore
+//! let provider = MyProvider::from_env(ModelConfig::new("my-model".to_string()))?;
+//! Conformance::new().skip(ConformanceCase::Images).run(&provider).await.assert_passed();
+//! ```
+
+use futures::FutureExt;
+use serde_json::json;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+
+use crate::message::Message;
+use crate::providers::base::{Provider, ProviderUsage};
+use crate::providers::errors::ProviderError;
+use mcp_core::role::Role;
+use mcp_core::tool::Tool;
+
+const SYSTEM_PROMPT: &str = "You are a helpful assistant taking part in an automated test. \
+    Follow the user's instructions exactly.";
+
+// A 1x1 red PNG
+const PIXEL_PNG: &str =
+    "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8BQDwAEhQGAhKmMIQAAAABJRU5ErkJggg==";
+
+const UNICODE_PHRASE: &str = "Grüße, 世界 🦆";
+
+/// A scenario in the conformance suite
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConformanceCase {
+    /// A request without any messages fails cleanly or gets a reply, but never panics
+    EmptyHistory,
+    /// A long alternating conversation gets a reply
+    LongHistory,
+    /// A request with a tool the model is asked to use gets a well formed tool call back
+    Tools,
+    /// A message with an image is accepted
+    Images,
+    /// Non-ASCII text survives the round trip
+    Unicode,
+    /// A request dropped mid-flight leaves the provider usable
+    Cancellation,
+}
+
+impl ConformanceCase {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::EmptyHistory,
+            Self::LongHistory,
+            Self::Tools,
+            Self::Images,
+            Self::Unicode,
+            Self::Cancellation,
+        ]
+    }
+}
+
+impl std::fmt::Display for ConformanceCase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::EmptyHistory => "empty history",
+            Self::LongHistory => "long history",
+            Self::Tools => "tools",
+            Self::Images => "images",
+            Self::Unicode => "unicode",
+            Self::Cancellation => "cancellation",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+}
+
+/// How a provider did on a single case
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    pub case: ConformanceCase,
+    pub outcome: Outcome,
+    pub duration: Duration,
+}
+
+/// The results of running the suite against a provider
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    pub results: Vec<CaseResult>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|r| r.outcome == Outcome::Passed)
+    }
+
+    pub fn failures(&self) -> Vec<&CaseResult> {
+        self.results
+            .iter()
+            .filter(|r| r.outcome != Outcome::Passed)
+            .collect()
+    }
+
+    /// Panic with every failure if any case failed, for use in tests
+    pub fn assert_passed(&self) {
+        let failures: Vec<String> = self
+            .failures()
+            .iter()
+            .map(|r| match &r.outcome {
+                Outcome::Failed(reason) => format!("  {}: {}", r.case, reason),
+                Outcome::Passed => unreachable!(),
+            })
+            .collect();
+        assert!(
+            failures.is_empty(),
+            "Provider failed {} conformance case(s):\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+}
+
+/// Runs a selection of `ConformanceCase`s against a provider
+///
+/// Cases run one after another against the same provider, each bounded by a timeout. Cases
+/// that a provider cannot support (e.g. images on a text-only model) can be skipped.
+pub struct Conformance {
+    cases: Vec<ConformanceCase>,
+    timeout: Duration,
+    cancel_after: Duration,
+}
+
+impl Default for Conformance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Conformance {
+    pub fn new() -> Self {
+        Self {
+            cases: ConformanceCase::all(),
+            timeout: Duration::from_secs(120),
+            cancel_after: Duration::from_millis(50),
+        }
+    }
+
+    /// Leave a case out of the run
+    pub fn skip(mut self, case: ConformanceCase) -> Self {
+        self.cases.retain(|c| *c != case);
+        self
+    }
+
+    /// Run only these cases
+    pub fn only(mut self, cases: &[ConformanceCase]) -> Self {
+        self.cases = cases.to_vec();
+        self
+    }
+
+    /// How long a single request may take before its case fails
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub async fn run(&self, provider: &dyn Provider) -> ConformanceReport {
+        let mut report = ConformanceReport::default();
+        for case in &self.cases {
+            let start = Instant::now();
+            let outcome = match AssertUnwindSafe(self.run_case(*case, provider))
+                .catch_unwind()
+                .await
+            {
+                Ok(Ok(())) => Outcome::Passed,
+                Ok(Err(reason)) => Outcome::Failed(reason),
+                Err(_) => Outcome::Failed("provider panicked".to_string()),
+            };
+            report.results.push(CaseResult {
+                case: *case,
+                outcome,
+                duration: start.elapsed(),
+            });
+        }
+        report
+    }
+
+    async fn run_case(&self, case: ConformanceCase, provider: &dyn Provider) -> Result<(), String> {
+        match case {
+            ConformanceCase::EmptyHistory => {
+                // Rejecting the request is fine, as long as it is reported as an error
+                match self.complete(provider, &[], &[]).await {
+                    Ok(_) | Err(CaseError::Provider(_)) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            ConformanceCase::LongHistory => {
+                let mut messages = Vec::new();
+                for i in 0..25 {
+                    messages.push(Message::user().with_text(format!("Remember the number {}.", i)));
+                    messages.push(Message::assistant().with_text(format!("Noted: {}.", i)));
+                }
+                messages.push(Message::user().with_text("Reply with the word done."));
+                let (message, _) = self.complete(provider, &messages, &[]).await?;
+                expect_content(&message)
+            }
+            ConformanceCase::Tools => {
+                let messages = [Message::user().with_text(
+                    "Use the get_weather tool to look up the weather in Paris. Do not answer \
+                    without calling it.",
+                )];
+                let (message, _) = self
+                    .complete(provider, &messages, &[weather_tool()])
+                    .await?;
+                let request = message
+                    .content
+                    .iter()
+                    .find_map(|c| c.as_tool_request())
+                    .ok_or("expected a tool request in the reply")?;
+                let call = request
+                    .tool_call
+                    .as_ref()
+                    .map_err(|e| format!("the tool call could not be parsed: {}", e))?;
+                if request.id.is_empty() {
+                    return Err("the tool request has no id".to_string());
+                }
+                if call.name != "get_weather" {
+                    return Err(format!("expected a call to get_weather, got {}", call.name));
+                }
+                if !call.arguments.is_object() {
+                    return Err(format!("expected object arguments, got {}", call.arguments));
+                }
+                Ok(())
+            }
+            ConformanceCase::Images => {
+                let messages = [Message::user()
+                    .with_text("Describe this image in a few words.")
+                    .with_image(PIXEL_PNG, "image/png")];
+                let (message, _) = self.complete(provider, &messages, &[]).await?;
+                expect_content(&message)
+            }
+            ConformanceCase::Unicode => {
+                let messages = [Message::user().with_text(format!(
+                    "Reply with exactly this text and nothing else: {}",
+                    UNICODE_PHRASE
+                ))];
+                let (message, _) = self.complete(provider, &messages, &[]).await?;
+                let text = message.as_concat_text();
+                if text.contains("世界") {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "expected the reply to contain 世界, got {:?}",
+                        text
+                    ))
+                }
+            }
+            ConformanceCase::Cancellation => {
+                let messages = [Message::user().with_text("Count from one to fifty in words.")];
+                // Whether or not the first request finishes in time, it is dropped here
+                let _ = tokio::time::timeout(
+                    self.cancel_after,
+                    provider.complete(SYSTEM_PROMPT, &messages, &[]),
+                )
+                .await;
+
+                let messages = [Message::user().with_text("Reply with the word ok.")];
+                let (message, _) = self.complete(provider, &messages, &[]).await?;
+                expect_content(&message)
+            }
+        }
+    }
+
+    async fn complete(
+        &self,
+        provider: &dyn Provider,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), CaseError> {
+        let (message, usage) = tokio::time::timeout(
+            self.timeout,
+            provider.complete(SYSTEM_PROMPT, messages, tools),
+        )
+        .await
+        .map_err(|_| CaseError::Timeout(self.timeout))??;
+
+        if message.role != Role::Assistant {
+            return Err(CaseError::Invalid(format!(
+                "expected an assistant reply, got {:?}",
+                message.role
+            )));
+        }
+        let counts = [
+            usage.usage.input_tokens,
+            usage.usage.output_tokens,
+            usage.usage.total_tokens,
+        ];
+        if counts.iter().flatten().any(|n| *n < 0) {
+            return Err(CaseError::Invalid(format!(
+                "negative token counts in {:?}",
+                usage.usage
+            )));
+        }
+        Ok((message, usage))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum CaseError {
+    #[error("{0}")]
+    Provider(#[from] ProviderError),
+    #[error("no reply within {0:?}")]
+    Timeout(Duration),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl From<CaseError> for String {
+    fn from(error: CaseError) -> Self {
+        error.to_string()
+    }
+}
+
+fn expect_content(message: &Message) -> Result<(), String> {
+    if message.content.is_empty() {
+        Err("the reply is empty".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn weather_tool() -> Tool {
+    Tool::new(
+        "get_weather",
+        "Get the current weather for a location",
+        json!({
+            "type": "object",
+            "required": ["location"],
+            "properties": {
+                "location": {"type": "string", "description": "The city, e.g. Paris"}
+            }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelConfig;
+    use crate::providers::mock::MockProvider;
+
+    #[tokio::test]
+    async fn test_mock_provider_conforms() {
+        let provider = MockProvider::default()
+            .with_error(ProviderError::RequestFailed("no messages".to_string()))
+            .with_text("done")
+            .with_tool_call("get_weather", json!({"location": "Paris"}))
+            .with_text("A red pixel.")
+            .with_text(UNICODE_PHRASE)
+            // The mock answers at once, so the cancelled request takes a reply too
+            .with_text("one, two, three")
+            .with_text("ok");
+
+        let report = Conformance::new().run(&provider).await;
+        report.assert_passed();
+        assert_eq!(report.results.len(), 6);
+        assert_eq!(provider.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reports_failures() {
+        let provider = MockProvider::new(ModelConfig::new("mock".to_string()))
+            .with_text("I'd rather not use tools")
+            .with_message(Message::user().with_text("wrong role"));
+
+        let report = Conformance::new()
+            .only(&[ConformanceCase::Tools, ConformanceCase::Unicode])
+            .run(&provider)
+            .await;
+
+        assert!(!report.passed());
+        let failures = report.failures();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(
+            failures[0].outcome,
+            Outcome::Failed("expected a tool request in the reply".to_string())
+        );
+        assert!(
+            matches!(&failures[1].outcome, Outcome::Failed(r) if r.contains("assistant reply"))
+        );
+    }
+}
@@ -0,0 +1,3 @@
+//! Helpers for testing `Provider` implementations, including ones defined outside this crate
+
+pub mod conformance;
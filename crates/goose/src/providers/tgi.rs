@@ -0,0 +1,355 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::chat_template::{render_prompt, ChatTemplate};
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::formats::tgi::{create_generate_request, generate_to_message, get_generate_usage};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const TGI_HOST: &str = "http://localhost:8080";
+// The messages API answers to any model name, TGI serves the one it was launched with
+pub const TGI_DEFAULT_MODEL: &str = "tgi";
+pub const TGI_KNOWN_MODELS: &[&str] = &[TGI_DEFAULT_MODEL];
+
+pub const TGI_DOC_URL: &str = "https://huggingface.co/docs/text-generation-inference";
+
+/// Config key holding the `TgiOptions`
+///
+/// ```yaml
+/// TGI_OPTIONS:
+///   endpoint: generate
+///   template: llama3
+///   top_k: 40
+///   repetition_penalty: 1.1
+///   grammar:
+///     type: regex
+///     value: '(yes|no)'
+/// ```
+pub const TGI_OPTIONS_CONFIG_KEY: &str = "TGI_OPTIONS";
+
+/// Which of TGI's endpoints requests go to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TgiEndpoint {
+    /// `/v1/chat/completions`, the messages API, which applies the model's chat template
+    #[default]
+    Chat,
+    /// `/generate` with the prompt rendered by goose, which takes every sampling parameter
+    /// and regex grammars
+    Generate,
+}
+
+/// A grammar the output has to match, as TGI takes it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum TgiGrammar {
+    /// A JSON schema
+    Json(Value),
+    /// A regular expression, which only `/generate` takes
+    Regex(String),
+}
+
+/// Settings specific to TGI
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TgiOptions {
+    #[serde(default)]
+    pub endpoint: TgiEndpoint,
+    /// The chat template the model was trained with, for rendering prompts for `/generate`
+    #[serde(default)]
+    pub template: ChatTemplate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typical_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repetition_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Left out of requests with tools, whose calls the grammar would get in the way of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grammar: Option<TgiGrammar>,
+}
+
+/// A Text Generation Inference server, such as a Hugging Face Inference Endpoint or a self
+/// hosted deployment, through its own API rather than only the OpenAI compatible one
+#[derive(serde::Serialize)]
+pub struct TgiProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    #[serde(skip)]
+    api_key: Option<String>,
+    model: ModelConfig,
+    options: TgiOptions,
+}
+
+impl Default for TgiProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(TgiProvider::metadata().default_model);
+        TgiProvider::from_env(model).expect("Failed to initialize TGI provider")
+    }
+}
+
+impl TgiProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        // Needed for Inference Endpoints and servers behind an authenticating proxy
+        let api_key: Option<String> = config.get_secret("TGI_API_KEY").ok();
+        let host: String = config
+            .get("TGI_HOST")
+            .unwrap_or_else(|_| TGI_HOST.to_string());
+        let options: TgiOptions = config.get(TGI_OPTIONS_CONFIG_KEY).unwrap_or_default();
+
+        let client = http_client("TGI")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model,
+            options,
+        })
+    }
+
+    async fn post(&self, path: &str, payload: &Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join(path).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let mut request = self.client.post(url);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = retry::send(request.json(payload)).await?;
+
+        handle_response(response).await
+    }
+}
+
+/// TGI reports errors as `{"error": ..., "error_type": ...}`; a prompt that doesn't fit is a
+/// validation error, and an overloaded server answers 429
+async fn handle_response(response: Response) -> Result<Value, ProviderError> {
+    let status = response.status();
+    if status.is_success() {
+        return handle_response_openai_compat(response).await;
+    }
+
+    let payload: Value = response.json().await.unwrap_or_default();
+    let message = payload["error"]
+        .as_str()
+        .or(payload["error"]["message"].as_str())
+        .unwrap_or("Unknown error")
+        .to_string();
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err(ProviderError::Authentication(format!(
+                "Authentication failed. Please ensure TGI_API_KEY is valid for the server. \
+                Status: {}. Message: {}",
+                status, message
+            )))
+        }
+        StatusCode::UNPROCESSABLE_ENTITY | StatusCode::BAD_REQUEST
+            if message.contains("must be <=") =>
+        {
+            Err(ProviderError::ContextLengthExceeded(message))
+        }
+        StatusCode::TOO_MANY_REQUESTS => Err(ProviderError::RateLimitExceeded(message)),
+        StatusCode::FAILED_DEPENDENCY
+        | StatusCode::INTERNAL_SERVER_ERROR
+        | StatusCode::SERVICE_UNAVAILABLE => Err(ProviderError::ServerError(message)),
+        _ => Err(ProviderError::RequestFailed(format!(
+            "{} (status {})",
+            message,
+            status.as_u16()
+        ))),
+    }
+}
+
+/// Add the TGI options to a request payload
+///
+/// `/generate` takes them in its `parameters`; the messages API takes only the sampling
+/// parameters OpenAI has, and a JSON grammar as the response format.
+fn add_options(payload: &mut Value, options: &TgiOptions, has_tools: bool) {
+    let generate = payload.get("parameters").is_some();
+    let grammar = options.grammar.as_ref().filter(|_| {
+        if has_tools {
+            tracing::debug!("Leaving out the grammar, the model has to be free to call tools");
+        }
+        !has_tools
+    });
+
+    if generate {
+        let parameters = &mut payload["parameters"];
+        for (key, value) in [
+            ("top_k", json!(options.top_k)),
+            ("top_p", json!(options.top_p)),
+            ("typical_p", json!(options.typical_p)),
+            ("repetition_penalty", json!(options.repetition_penalty)),
+            ("frequency_penalty", json!(options.frequency_penalty)),
+            ("seed", json!(options.seed)),
+        ] {
+            if !value.is_null() {
+                parameters[key] = value;
+            }
+        }
+        if let Some(grammar) = grammar {
+            parameters["grammar"] = json!(grammar);
+        }
+        return;
+    }
+
+    for (key, value) in [
+        ("top_p", json!(options.top_p)),
+        ("frequency_penalty", json!(options.frequency_penalty)),
+        ("seed", json!(options.seed)),
+    ] {
+        if !value.is_null() {
+            payload[key] = value;
+        }
+    }
+    match grammar {
+        Some(TgiGrammar::Json(schema)) => {
+            payload["response_format"] = json!({ "type": "json", "value": schema });
+        }
+        Some(TgiGrammar::Regex(_)) => {
+            tracing::warn!("Leaving out the regex grammar, only the generate endpoint takes one");
+        }
+        None => {}
+    }
+}
+
+#[async_trait]
+impl Provider for TgiProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "tgi",
+            "Text Generation Inference",
+            "Models served by Hugging Face Text Generation Inference",
+            TGI_DEFAULT_MODEL,
+            TGI_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            TGI_DOC_URL,
+            vec![
+                ConfigKey::new("TGI_HOST", true, false, Some(TGI_HOST)),
+                ConfigKey::new("TGI_API_KEY", false, true, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        if self.options.endpoint == TgiEndpoint::Generate {
+            let template = self.options.template;
+            let prompt = render_prompt(template, system, messages, tools)?;
+            let mut payload = create_generate_request(&self.model, template, &prompt);
+            add_options(&mut payload, &self.options, !tools.is_empty());
+
+            let response = vcr::post("tgi", &payload, || self.post("generate", &payload)).await?;
+
+            let message = generate_to_message(&response, template)?;
+            let usage = get_generate_usage(&response).unwrap_or_else(|e| {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            });
+            emit_debug_trace(self, &payload, &response, &usage);
+            return Ok((
+                message,
+                ProviderUsage::new(self.model.model_name.clone(), usage),
+            ));
+        }
+
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        add_options(&mut payload, &self.options, !tools.is_empty());
+
+        let response = vcr::post("tgi", &payload, || {
+            self.post("v1/chat/completions", &payload)
+        })
+        .await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_options() {
+        let options: TgiOptions = serde_yaml::from_str(
+            "endpoint: generate\ntop_k: 40\nrepetition_penalty: 1.1\nseed: 7\ngrammar: {type: regex, value: '(yes|no)'}",
+        )
+        .unwrap();
+        assert_eq!(options.endpoint, TgiEndpoint::Generate);
+
+        let mut payload = json!({ "inputs": "Is the sky blue?", "parameters": {} });
+        add_options(&mut payload, &options, false);
+        let parameters = &payload["parameters"];
+        assert_eq!(parameters["top_k"], 40);
+        assert_eq!(parameters["seed"], 7);
+        assert_eq!(
+            parameters["grammar"],
+            json!({ "type": "regex", "value": "(yes|no)" })
+        );
+        assert!(parameters.get("top_p").is_none());
+
+        // The messages API has no top_k, and takes only JSON grammars
+        let mut payload = json!({ "messages": [] });
+        add_options(&mut payload, &options, false);
+        assert!(payload.get("top_k").is_none());
+        assert_eq!(payload["seed"], 7);
+        assert!(payload.get("response_format").is_none());
+
+        let options = TgiOptions {
+            grammar: Some(TgiGrammar::Json(json!({ "type": "object" }))),
+            ..Default::default()
+        };
+        let mut payload = json!({ "messages": [] });
+        add_options(&mut payload, &options, false);
+        assert_eq!(payload["response_format"]["type"], "json");
+        let mut payload = json!({ "messages": [] });
+        add_options(&mut payload, &options, true);
+        assert!(payload.get("response_format").is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use async_trait::async_trait;
+use std::time::Instant;
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::inspect_stream;
+use crate::events::{self, Event};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::usage::UsageTracker;
+use mcp_core::tool::Tool;
+
+/// A provider wrapper that reports every successful request to `UsageTracker::global()`, and
+/// the start and finish of every request to the event bus
+pub struct TrackedProvider {
+    inner: Box<dyn Provider>,
+    provider_name: String,
+}
+
+impl TrackedProvider {
+    pub fn new(inner: Box<dyn Provider>, provider_name: &str) -> Self {
+        Self {
+            inner,
+            provider_name: provider_name.to_string(),
+        }
+    }
+
+    /// Report the start of a request, returning its id
+    fn start(&self, model: &str, messages: &[Message], tools: &[Tool]) -> u64 {
+        let id = events::next_id();
+        events::emit(Event::CompletionStarted {
+            id,
+            provider: self.provider_name.clone(),
+            model: model.to_string(),
+            messages: messages.len(),
+            tools: tools.len(),
+        });
+        id
+    }
+
+    /// Report the end of a request, recording its usage when it succeeded
+    fn finish(
+        &self,
+        id: u64,
+        model: String,
+        start: Instant,
+        result: Result<&(Message, ProviderUsage), &ProviderError>,
+    ) {
+        events::emit(Event::CompletionFinished {
+            id,
+            provider: self.provider_name.clone(),
+            model,
+            duration_ms: start.elapsed().as_millis() as u64,
+            usage: result.ok().map(|(_, usage)| usage.usage.clone()),
+            error: result.err().map(|e| e.to_string()),
+        });
+        if let Ok((_, usage)) = result {
+            UsageTracker::global().record(&self.provider_name, usage, start.elapsed());
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for TrackedProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let model = self.get_model_config().model_name;
+        let id = self.start(&model, messages, tools);
+        let start = Instant::now();
+        let result = self.inner.complete(system, messages, tools).await;
+        self.finish(id, model, start, result.as_ref());
+        result
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let model = self.get_model_config().model_name;
+        let id = self.start(&model, messages, tools);
+        let start = Instant::now();
+        match self.inner.stream(system, messages, tools).await {
+            Ok(stream) => Ok(inspect_stream(stream, model.clone(), move |result| {
+                self.finish(id, model, start, result)
+            })),
+            Err(error) => {
+                self.finish(id, model, start, Err(&error));
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventBus;
+    use crate::providers::mock::MockProvider;
+
+    #[tokio::test]
+    async fn test_emits_completion_events() {
+        let mut events = EventBus::global().subscribe();
+        let mock = MockProvider::default()
+            .with_text("hi")
+            .with_error(ProviderError::ServerError("down".to_string()));
+        let provider = TrackedProvider::new(Box::new(mock), "tracked-events");
+        let messages = [Message::user().with_text("hello")];
+        provider.complete("system", &messages, &[]).await.unwrap();
+        assert!(provider.complete("system", &messages, &[]).await.is_err());
+
+        // Other tests emit on the global bus too
+        let mut ours = Vec::new();
+        while ours.len() < 4 {
+            match events.recv().await.unwrap() {
+                Event::CompletionStarted { id, provider, .. }
+                | Event::CompletionFinished { id, provider, .. }
+                    if provider == "tracked-events" =>
+                {
+                    ours.push(id)
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(ours[0], ours[1]);
+        assert_ne!(ours[1], ours[2]);
+        assert_eq!(ours[2], ours[3]);
+    }
+}
@@ -2,13 +2,17 @@ use super::base::Usage;
 use super::errors::GoogleErrorCode;
 use anyhow::Result;
 use base64::Engine;
+use futures::stream::BoxStream;
+use futures::StreamExt;
 use regex::Regex;
-use reqwest::{Response, StatusCode};
+use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, json, Map, Value};
 use std::io::Read;
 use std::path::Path;
+use std::time::Duration;
 
+use crate::config::Config;
 use crate::providers::errors::{OpenAIError, ProviderError};
 use mcp_core::content::ImageContent;
 
@@ -88,6 +92,72 @@ pub async fn handle_response_openai_compat(response: Response) -> Result<Value,
     }
 }
 
+/// The data of each server sent event in a streamed response, in order
+///
+/// The `data:` lines of an event are joined with newlines; other fields and comments are
+/// skipped. Events end at a blank line, or with the response.
+pub fn sse_data(response: Response) -> BoxStream<'static, Result<String, ProviderError>> {
+    Box::pin(async_stream::try_stream! {
+        let mut bytes = response.bytes_stream();
+        // Chunks can end anywhere, even within a character
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut data: Vec<String> = Vec::new();
+        while let Some(chunk) = bytes.next().await {
+            buffer.extend_from_slice(&chunk?);
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end_matches(['\r', '\n']);
+                if line.is_empty() {
+                    if !data.is_empty() {
+                        yield data.join("\n");
+                        data.clear();
+                    }
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+                }
+            }
+        }
+        let line = String::from_utf8_lossy(&buffer);
+        if let Some(value) = line.trim_end_matches('\r').strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+        if !data.is_empty() {
+            yield data.join("\n");
+        }
+    })
+}
+
+/// Each line of a streamed response of newline-delimited JSON, parsed, in order
+pub fn json_lines(response: Response) -> BoxStream<'static, Result<Value, ProviderError>> {
+    Box::pin(async_stream::try_stream! {
+        let mut bytes = response.bytes_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(chunk) = bytes.next().await {
+            buffer.extend_from_slice(&chunk?);
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                if let Some(value) = parse_json_line(&line)? {
+                    yield value;
+                }
+            }
+        }
+        // The last line may end with the response rather than a newline
+        if let Some(value) = parse_json_line(&buffer)? {
+            yield value;
+        }
+    })
+}
+
+fn parse_json_line(line: &[u8]) -> Result<Option<Value>, ProviderError> {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    serde_json::from_slice(line)
+        .map(Some)
+        .map_err(|e| ProviderError::RequestFailed(format!("Invalid line in response stream: {e}")))
+}
+
 /// Check if the model is a Google model based on the "model" field in the payload.
 ///
 /// ### Arguments
@@ -338,6 +408,53 @@ pub fn emit_debug_trace<T: serde::Serialize>(
     );
 }
 
+// How long a request can take when no timeout is configured
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The timeouts of the HTTP client a provider sends its requests with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Limit on establishing a connection
+    pub connect: Option<Duration>,
+    /// Limit on each read of the response, such as waiting for the next chunk of a stream
+    pub read: Option<Duration>,
+    /// Limit on the whole request, from connecting to the end of the response
+    pub total: Duration,
+}
+
+impl Timeouts {
+    /// Read `{prefix}_TIMEOUT_SECS`, `{prefix}_CONNECT_TIMEOUT_SECS` and
+    /// `{prefix}_READ_TIMEOUT_SECS`, falling back to the same keys starting with `GOOSE`
+    /// and then to a total of 600 seconds
+    pub fn from_config(prefix: &str) -> Self {
+        let config = Config::global();
+        let secs = |name: &str| {
+            [prefix, "GOOSE"]
+                .iter()
+                .find_map(|p| config.get::<u64>(&format!("{}_{}", p, name)).ok())
+                .map(Duration::from_secs)
+        };
+        Self {
+            connect: secs("CONNECT_TIMEOUT_SECS"),
+            read: secs("READ_TIMEOUT_SECS"),
+            total: secs("TIMEOUT_SECS").unwrap_or(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+/// An HTTP client with the timeouts configured for the provider whose keys start with `prefix`
+pub fn http_client(prefix: &str) -> Result<Client> {
+    let timeouts = Timeouts::from_config(prefix);
+    let mut builder = Client::builder().timeout(timeouts.total);
+    if let Some(connect) = timeouts.connect {
+        builder = builder.connect_timeout(connect);
+    }
+    if let Some(read) = timeouts.read {
+        builder = builder.read_timeout(read);
+    }
+    Ok(builder.build()?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -548,4 +665,15 @@ mod tests {
             assert_eq!(result, expected_status);
         }
     }
+
+    #[test]
+    fn test_timeouts_from_config() {
+        std::env::set_var("TIMEOUT_TEST_TIMEOUT_SECS", "30");
+        std::env::set_var("TIMEOUT_TEST_CONNECT_TIMEOUT_SECS", "5");
+        let timeouts = Timeouts::from_config("TIMEOUT_TEST");
+        assert_eq!(timeouts.total, Duration::from_secs(30));
+        assert_eq!(timeouts.connect, Some(Duration::from_secs(5)));
+        std::env::remove_var("TIMEOUT_TEST_TIMEOUT_SECS");
+        std::env::remove_var("TIMEOUT_TEST_CONNECT_TIMEOUT_SECS");
+    }
 }
@@ -0,0 +1,639 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::base::{message_stream, MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::streaming::inspect_stream;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key selecting `record` or `replay`; recording is off when unset
+pub const VCR_MODE_CONFIG_KEY: &str = "GOOSE_VCR_MODE";
+/// Config key holding the path of the cassette file
+pub const VCR_CASSETTE_CONFIG_KEY: &str = "GOOSE_VCR_CASSETTE";
+
+const REDACTED: &str = "[REDACTED]";
+
+// Object keys whose values are always scrubbed, compared case insensitively
+const SECRET_KEYS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "x-api-key",
+    "authorization",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "client_secret",
+    "password",
+    "secret",
+];
+
+// Environment variables with these suffixes hold credentials whose values are scrubbed
+// wherever they appear
+const SECRET_ENV_SUFFIXES: &[&str] = &["_API_KEY", "_TOKEN", "_SECRET", "_PASSWORD"];
+
+static SECRET_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
+    [
+        r"sk-[A-Za-z0-9_\-]{20,}",
+        r"sk-ant-[A-Za-z0-9_\-]{20,}",
+        r"AIza[0-9A-Za-z_\-]{35}",
+        r"gsk_[A-Za-z0-9]{20,}",
+        r"dapi[0-9a-f]{32}",
+        r"Bearer [A-Za-z0-9_\-\.=]+",
+    ]
+    .iter()
+    .map(|p| Regex::new(p).expect("valid secret pattern"))
+    .collect()
+});
+
+// The recorder installed for the HTTP traffic of every provider, see `Vcr::install`
+static ACTIVE: Mutex<Option<Arc<Vcr>>> = Mutex::new(None);
+
+// The recorder the providers goose creates are wrapped in, shared so they write one cassette
+static CONFIGURED: Lazy<Option<Arc<Vcr>>> = Lazy::new(|| {
+    Vcr::from_config()
+        .map_err(|e| tracing::warn!("Failed to set up provider recording: {}", e))
+        .ok()
+        .flatten()
+        .map(Arc::new)
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VcrMode {
+    /// Send requests for real and append them to the cassette
+    Record,
+    /// Answer requests from the cassette without touching the network
+    Replay,
+}
+
+/// A provider error as stored in a cassette
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum RecordedError {
+    Authentication(String),
+    ContextLengthExceeded(String),
+    RateLimitExceeded(String),
+    ServerError(String),
+    RequestFailed(String),
+    ExecutionError(String),
+}
+
+impl From<&ProviderError> for RecordedError {
+    fn from(error: &ProviderError) -> Self {
+        match error {
+            ProviderError::Authentication(m) => Self::Authentication(m.clone()),
+            ProviderError::ContextLengthExceeded(m) => Self::ContextLengthExceeded(m.clone()),
+            ProviderError::RateLimitExceeded(m) => Self::RateLimitExceeded(m.clone()),
+            ProviderError::ServerError(m) => Self::ServerError(m.clone()),
+            ProviderError::RequestFailed(m) => Self::RequestFailed(m.clone()),
+            other => Self::ExecutionError(other.to_string()),
+        }
+    }
+}
+
+impl From<RecordedError> for ProviderError {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::Authentication(m) => Self::Authentication(m),
+            RecordedError::ContextLengthExceeded(m) => Self::ContextLengthExceeded(m),
+            RecordedError::RateLimitExceeded(m) => Self::RateLimitExceeded(m),
+            RecordedError::ServerError(m) => Self::ServerError(m),
+            RecordedError::RequestFailed(m) => Self::RequestFailed(m),
+            RecordedError::ExecutionError(m) => Self::ExecutionError(m),
+        }
+    }
+}
+
+/// One request to a provider's API and what it answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub provider: String,
+    pub request: Value,
+    pub response: Result<Value, RecordedError>,
+}
+
+/// The recorded traffic of a test, stored as JSON
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Records provider requests to a cassette, or replays them deterministically
+///
+/// A `Vcr` records at one of two levels. Installed with `Vcr::install`, it records the HTTP
+/// traffic providers send through `vcr::post`, so tests catch changes in the API formats.
+/// Set up through `GOOSE_VCR_MODE` and `GOOSE_VCR_CASSETTE`, it records the calls to the
+/// providers goose creates with a `VcrProvider`. Requests and responses are scrubbed of
+/// credentials before they are written. On replay a request is answered by the first
+/// recording not yet replayed with the same payload hash, so a change in the request fails
+/// instead of silently using a stale response.
+pub struct Vcr {
+    mode: VcrMode,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+    /// The interactions already replayed
+    replayed: Mutex<HashSet<usize>>,
+}
+
+impl Vcr {
+    /// Record to the cassette at `path`, replacing anything recorded there before
+    pub fn record<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            mode: VcrMode::Record,
+            path: path.into(),
+            cassette: Mutex::new(Cassette::default()),
+            replayed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Replay the cassette at `path`
+    pub fn replay<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        let cassette = Cassette::load(&path)?;
+        Ok(Self {
+            mode: VcrMode::Replay,
+            path,
+            cassette: Mutex::new(cassette),
+            replayed: Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub fn from_config() -> Result<Option<Self>> {
+        let config = Config::global();
+        let Ok(mode) = config.get::<VcrMode>(VCR_MODE_CONFIG_KEY) else {
+            return Ok(None);
+        };
+        let path: PathBuf = config.get::<String>(VCR_CASSETTE_CONFIG_KEY)?.into();
+        match mode {
+            VcrMode::Record => Ok(Some(Self::record(path))),
+            VcrMode::Replay => Ok(Some(Self::replay(path)?)),
+        }
+    }
+
+    /// Route provider traffic through the given recorder, or back to the network with None
+    pub fn install(vcr: Option<Vcr>) {
+        *ACTIVE.lock().unwrap() = vcr.map(Arc::new);
+    }
+
+    pub fn mode(&self) -> VcrMode {
+        self.mode
+    }
+
+    /// The interactions recorded or loaded so far
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    fn record_interaction(&self, interaction: Interaction) -> Result<()> {
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(interaction);
+        cassette.save(&self.path)
+    }
+
+    fn next_response(&self, provider: &str, request: &Value) -> Result<Value, ProviderError> {
+        let cassette = self.cassette.lock().unwrap();
+        let mut replayed = self.replayed.lock().unwrap();
+        let hash = request_hash(request);
+        let (index, interaction) = cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .find(|(i, interaction)| {
+                !replayed.contains(i)
+                    && interaction.provider == provider
+                    && request_hash(&interaction.request) == hash
+            })
+            .ok_or_else(|| {
+                ProviderError::ExecutionError(format!(
+                    "Cassette {} has no recording left that matches request {} to {}\nrequest: {}",
+                    self.path.display(),
+                    hash,
+                    provider,
+                    request
+                ))
+            })?;
+
+        replayed.insert(index);
+        interaction.response.clone().map_err(ProviderError::from)
+    }
+}
+
+/// The recorder set up through `GOOSE_VCR_MODE` and `GOOSE_VCR_CASSETTE`, if any
+pub fn configured() -> Option<Arc<Vcr>> {
+    CONFIGURED.clone()
+}
+
+/// Whether provider traffic goes through a `Vcr`
+///
+/// Streamed responses aren't recorded, so providers complete requests instead of streaming
+/// them while one is installed.
+pub fn is_active() -> bool {
+    ACTIVE.lock().unwrap().is_some()
+}
+
+/// Send a provider request through the installed `Vcr`, if any
+///
+/// `send` performs the real request and is only called when not replaying.
+pub async fn post<F, Fut>(provider: &str, payload: &Value, send: F) -> Result<Value, ProviderError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Value, ProviderError>>,
+{
+    let Some(vcr) = ACTIVE.lock().unwrap().clone() else {
+        return send().await;
+    };
+
+    let request = scrub(payload);
+    match vcr.mode {
+        VcrMode::Replay => vcr.next_response(provider, &request),
+        VcrMode::Record => {
+            let response = send().await;
+            let interaction = Interaction {
+                provider: provider.to_string(),
+                request,
+                response: match &response {
+                    Ok(body) => Ok(scrub(body)),
+                    Err(e) => Err(RecordedError::from(e)),
+                },
+            };
+            if let Err(e) = vcr.record_interaction(interaction) {
+                tracing::warn!("Failed to write cassette {}: {}", vcr.path.display(), e);
+            }
+            response
+        }
+    }
+}
+
+/// The hash a request is recorded and replayed by
+pub fn request_hash(request: &Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(request.to_string().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// The request a call to `complete` makes, without the message timestamps
+pub(super) fn request_payload(
+    provider: &str,
+    model: &str,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Value {
+    let messages: Vec<Value> = messages
+        .iter()
+        .map(|message| json!({ "role": message.role, "content": message.content }))
+        .collect();
+    json!({
+        "provider": provider,
+        "model": model,
+        "system": system,
+        "messages": messages,
+        "tools": tools,
+    })
+}
+
+/// What a provider answered, as a `VcrProvider` records it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedReply {
+    pub message: Message,
+    pub usage: ProviderUsage,
+}
+
+/// A provider wrapper that records each call to a cassette, or answers it from one
+///
+/// Unlike `post`, which records the HTTP traffic of a provider, this works on `complete`
+/// itself, with the provider, model, system prompt, messages and tools as the request, so it
+/// works for any provider. Replaying needs no inner provider, and so no credentials or
+/// network.
+pub struct VcrProvider {
+    inner: Option<Box<dyn Provider>>,
+    name: String,
+    model: ModelConfig,
+    vcr: Arc<Vcr>,
+}
+
+impl VcrProvider {
+    /// Record every call to `inner`, or answer it from the cassette when `vcr` replays
+    pub fn new<S: Into<String>>(inner: Box<dyn Provider>, name: S, vcr: Arc<Vcr>) -> Self {
+        Self {
+            model: inner.get_model_config(),
+            inner: Some(inner),
+            name: name.into(),
+            vcr,
+        }
+    }
+
+    /// Answer every call from the cassette `vcr` replays, as the named provider and model
+    pub fn replay<S: Into<String>>(name: S, model: ModelConfig, vcr: Arc<Vcr>) -> Self {
+        Self {
+            inner: None,
+            name: name.into(),
+            model,
+            vcr,
+        }
+    }
+
+    fn request(&self, system: &str, messages: &[Message], tools: &[Tool]) -> Value {
+        scrub(&request_payload(
+            &self.name,
+            &self.model.model_name,
+            system,
+            messages,
+            tools,
+        ))
+    }
+
+    /// The inner provider when recording, None when calls are answered from the cassette
+    fn recording(&self) -> Option<&dyn Provider> {
+        match (&self.inner, self.vcr.mode) {
+            (Some(inner), VcrMode::Record) => Some(inner.as_ref()),
+            _ => None,
+        }
+    }
+
+    fn replay_reply(&self, request: &Value) -> Result<(Message, ProviderUsage), ProviderError> {
+        let response = self.vcr.next_response(&self.name, request)?;
+        let reply: RecordedReply = serde_json::from_value(response)
+            .map_err(|e| ProviderError::ExecutionError(format!("Invalid recorded reply: {}", e)))?;
+        Ok((reply.message, reply.usage))
+    }
+
+    fn record_reply(
+        &self,
+        request: Value,
+        result: Result<&(Message, ProviderUsage), &ProviderError>,
+    ) {
+        let interaction = Interaction {
+            provider: self.name.clone(),
+            request,
+            response: match result {
+                Ok((message, usage)) => Ok(scrub(&json!(RecordedReply {
+                    message: message.clone(),
+                    usage: usage.clone(),
+                }))),
+                Err(e) => Err(RecordedError::from(e)),
+            },
+        };
+        if let Err(e) = self.vcr.record_interaction(interaction) {
+            tracing::warn!(
+                "Failed to write cassette {}: {}",
+                self.vcr.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for VcrProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let request = self.request(system, messages, tools);
+        let Some(inner) = self.recording() else {
+            return self.replay_reply(&request);
+        };
+
+        let result = inner.complete(system, messages, tools).await;
+        self.record_reply(request, result.as_ref());
+        result
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream<'_>, ProviderError> {
+        let request = self.request(system, messages, tools);
+        let Some(inner) = self.recording() else {
+            let (message, usage) = self.replay_reply(&request)?;
+            return Ok(message_stream(message, usage));
+        };
+
+        // The reply is recorded whole, once the stream has ended
+        match inner.stream(system, messages, tools).await {
+            Ok(stream) => Ok(inspect_stream(
+                stream,
+                self.model.model_name.clone(),
+                move |result| self.record_reply(request, result),
+            )),
+            Err(error) => {
+                self.record_reply(request, Err(&error));
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Replace credentials in a JSON value with a placeholder
+pub fn scrub(value: &Value) -> Value {
+    let env_secrets: Vec<String> = std::env::vars()
+        .filter(|(name, value)| {
+            value.len() >= 8 && SECRET_ENV_SUFFIXES.iter().any(|s| name.ends_with(s))
+        })
+        .map(|(_, value)| value)
+        .collect();
+    scrub_value(value, &env_secrets)
+}
+
+fn scrub_value(value: &Value, env_secrets: &[String]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let is_secret = SECRET_KEYS.iter().any(|s| k.eq_ignore_ascii_case(s));
+                    let v = if is_secret && !v.is_null() {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        scrub_value(v, env_secrets)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => {
+            Value::Array(values.iter().map(|v| scrub_value(v, env_secrets)).collect())
+        }
+        Value::String(s) => {
+            let mut s = s.clone();
+            for secret in env_secrets {
+                s = s.replace(secret.as_str(), REDACTED);
+            }
+            for pattern in SECRET_PATTERNS.iter() {
+                s = pattern.replace_all(&s, REDACTED).into_owned();
+            }
+            Value::String(s)
+        }
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::scripted::{Scenario, ScriptedProvider};
+    use serial_test::serial;
+
+    #[test]
+    fn test_scrub() {
+        let value = json!({
+            "model": "gpt-4o",
+            "max_tokens": 100,
+            "api_key": "abc",
+            "messages": [{"content": "my key is sk-abcdefghijklmnopqrstuvwxyz123456"}],
+            "headers": {"Authorization": "Bearer secret"},
+        });
+
+        let scrubbed = scrub(&value);
+        assert_eq!(scrubbed["max_tokens"], 100);
+        assert_eq!(scrubbed["api_key"], REDACTED);
+        assert_eq!(scrubbed["headers"]["Authorization"], REDACTED);
+        assert_eq!(
+            scrubbed["messages"][0]["content"],
+            format!("my key is {}", REDACTED)
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_record_then_replay() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cassette.json");
+        let request = json!({"model": "gpt-4o", "messages": []});
+
+        Vcr::install(Some(Vcr::record(&path)));
+        let response = post("openai", &request, || async {
+            Ok(json!({"choices": [{"message": {"content": "hi"}}]}))
+        })
+        .await?;
+        let error = post("openai", &request, || async {
+            Err(ProviderError::RateLimitExceeded("slow down".to_string()))
+        })
+        .await;
+        assert!(error.is_err());
+
+        Vcr::install(Some(Vcr::replay(&path)?));
+        let replayed = post("openai", &request, || async {
+            panic!("replay must not send requests")
+        })
+        .await?;
+        assert_eq!(replayed, response);
+        let error = post("openai", &request, || async { unreachable!() }).await;
+        assert!(matches!(error, Err(ProviderError::RateLimitExceeded(_))));
+
+        // Nothing left to replay
+        assert!(post("openai", &request, || async { unreachable!() })
+            .await
+            .is_err());
+        Vcr::install(None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_replay_rejects_changed_request() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cassette.json");
+        Cassette {
+            interactions: vec![Interaction {
+                provider: "openai".to_string(),
+                request: json!({"model": "gpt-4o"}),
+                response: Ok(json!({})),
+            }],
+        }
+        .save(&path)?;
+
+        Vcr::install(Some(Vcr::replay(&path)?));
+        let result = post("openai", &json!({"model": "gpt-4o-mini"}), || async {
+            unreachable!()
+        })
+        .await;
+        Vcr::install(None);
+
+        match result {
+            Err(ProviderError::ExecutionError(message)) => {
+                assert!(message.contains("no recording left that matches"));
+            }
+            _ => panic!("Expected a mismatch error"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_provider_replays_by_hash() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cassette.json");
+        let scenario = Scenario::parse(
+            "steps:\n  - text: first\n  - error: {kind: server_error, message: down}",
+        )?;
+        let model = ModelConfig::new("scripted".to_string());
+        let recorder = VcrProvider::new(
+            Box::new(ScriptedProvider::new(scenario, model.clone())),
+            "scripted",
+            Arc::new(Vcr::record(&path)),
+        );
+
+        let hello = [Message::user().with_text("hello")];
+        let bye = [Message::user().with_text("bye")];
+        let (message, _) = recorder.complete("system", &hello, &[]).await?;
+        assert_eq!(message.as_concat_text(), "first");
+        assert!(recorder.complete("system", &bye, &[]).await.is_err());
+
+        // Replayed out of order, with messages created at other times
+        let player = VcrProvider::replay("scripted", model, Arc::new(Vcr::replay(&path)?));
+        let bye = [Message {
+            created: 0,
+            ..Message::user().with_text("bye")
+        }];
+        let result = player.complete("system", &bye, &[]).await;
+        assert!(matches!(result, Err(ProviderError::ServerError(m)) if m == "down"));
+        let (message, usage) = player.complete("system", &hello, &[]).await?;
+        assert_eq!(message.as_concat_text(), "first");
+        assert_eq!(usage.model, "scripted");
+
+        let result = player.complete("other system", &hello, &[]).await;
+        assert!(
+            matches!(result, Err(ProviderError::ExecutionError(m)) if m.contains("no recording"))
+        );
+        Ok(())
+    }
+}
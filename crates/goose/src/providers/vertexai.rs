@@ -0,0 +1,159 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::formats::google::{create_request, get_usage, response_to_message};
+use super::gcpauth::{GcpAuth, GcpCredentials};
+use super::utils::{
+    emit_debug_trace, handle_response_google_compat, http_client, unescape_json_values,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const VERTEXAI_DEFAULT_LOCATION: &str = "us-central1";
+pub const VERTEXAI_DEFAULT_MODEL: &str = "gemini-2.0-flash-001";
+pub const VERTEXAI_KNOWN_MODELS: &[&str] = &[
+    "gemini-2.0-flash-001",
+    "gemini-2.0-flash-lite-001",
+    "gemini-1.5-pro-002",
+    "gemini-1.5-flash-002",
+];
+
+pub const VERTEXAI_DOC_URL: &str =
+    "https://cloud.google.com/vertex-ai/generative-ai/docs/learn/models";
+
+/// Gemini models on Google Cloud's Vertex AI, authenticated with application default
+/// credentials instead of an AI Studio API key
+#[derive(serde::Serialize)]
+pub struct VertexAiProvider {
+    #[serde(skip)]
+    client: Client,
+    #[serde(skip)]
+    auth: GcpAuth,
+    project_id: String,
+    location: String,
+    model: ModelConfig,
+}
+
+impl Default for VertexAiProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(VertexAiProvider::metadata().default_model);
+        VertexAiProvider::from_env(model).expect("Failed to initialize Vertex AI provider")
+    }
+}
+
+impl VertexAiProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let project_id: String = config.get("GCP_PROJECT_ID")?;
+        let location: String = config
+            .get("GCP_LOCATION")
+            .unwrap_or_else(|_| VERTEXAI_DEFAULT_LOCATION.to_string());
+        let credentials_path: Option<String> = config.get("GOOGLE_APPLICATION_CREDENTIALS").ok();
+        let credentials = GcpCredentials::application_default(credentials_path.as_deref())?;
+
+        let client = http_client("VERTEX_AI")?;
+
+        Ok(Self {
+            client,
+            auth: GcpAuth::new(credentials),
+            project_id,
+            location,
+            model,
+        })
+    }
+
+    fn url(&self) -> Result<Url, ProviderError> {
+        let url = format!(
+            "{}/v1/projects/{}/locations/{}/publishers/google/models/{}:generateContent",
+            endpoint_host(&self.location),
+            self.project_id,
+            self.location,
+            self.model.model_name
+        );
+        Url::parse(&url).map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let token = self
+            .auth
+            .token()
+            .await
+            .map_err(|e| ProviderError::Authentication(e.to_string()))?;
+
+        let request = self
+            .client
+            .post(self.url()?)
+            .bearer_auth(token)
+            .json(&payload);
+        let response = retry::send(request).await?;
+
+        handle_response_google_compat(response).await
+    }
+}
+
+/// The Vertex AI endpoint serving a location; the global endpoint has no region in its host
+pub(super) fn endpoint_host(location: &str) -> String {
+    match location {
+        "global" => "https://aiplatform.googleapis.com".to_string(),
+        location => format!("https://{}-aiplatform.googleapis.com", location),
+    }
+}
+
+#[async_trait]
+impl Provider for VertexAiProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "vertex_ai",
+            "Google Vertex AI",
+            "Gemini models on Google Cloud, with your Google Cloud credentials",
+            VERTEXAI_DEFAULT_MODEL,
+            VERTEXAI_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            VERTEXAI_DOC_URL,
+            vec![
+                ConfigKey::new("GCP_PROJECT_ID", true, false, None),
+                ConfigKey::new("GCP_LOCATION", true, false, Some(VERTEXAI_DEFAULT_LOCATION)),
+                ConfigKey::new("GOOGLE_APPLICATION_CREDENTIALS", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = vcr::post("vertex_ai", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(unescape_json_values(&response))?;
+        let usage = get_usage(&response)?;
+        let model = match response.get("modelVersion") {
+            Some(model_version) => model_version.as_str().unwrap_or_default().to_string(),
+            None => self.model.model_name.clone(),
+        };
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::{DecodingConfig, ModelConfig};
+use mcp_core::tool::Tool;
+
+pub const VLLM_API_HOST: &str = "http://localhost:8000";
+pub const VLLM_DEFAULT_MODEL: &str = "Qwen/Qwen2.5-7B-Instruct";
+// vLLM serves whichever model it was started with, we only provide the default
+pub const VLLM_KNOWN_MODELS: &[&str] = &[VLLM_DEFAULT_MODEL];
+
+pub const VLLM_DOC_URL: &str = "https://docs.vllm.ai/en/latest/features/tool_calling.html";
+
+/// Config key holding the `DecodingConfig` used when the model config doesn't set one
+///
+/// ```yaml
+/// VLLM_DECODING:
+///   min_p: 0.05
+///   guided_regex: "^(yes|no)$"
+/// ```
+pub const VLLM_DECODING_CONFIG_KEY: &str = "VLLM_DECODING";
+
+/// A self hosted vLLM server, through its OpenAI compatible API plus vLLM's own sampling
+/// and guided decoding parameters
+#[derive(serde::Serialize)]
+pub struct VllmProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_key: Option<String>,
+    model: ModelConfig,
+}
+
+impl Default for VllmProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(VllmProvider::metadata().default_model);
+        VllmProvider::from_env(model).expect("Failed to initialize vLLM provider")
+    }
+}
+
+impl VllmProvider {
+    pub fn from_env(mut model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        // Only needed when the server was started with --api-key
+        let api_key: Option<String> = config.get_secret("VLLM_API_KEY").ok();
+        let host: String = config
+            .get("VLLM_HOST")
+            .unwrap_or_else(|_| VLLM_API_HOST.to_string());
+        if model.decoding.is_empty() {
+            model.decoding = config.get(VLLM_DECODING_CONFIG_KEY).unwrap_or_default();
+        }
+
+        let client = http_client("VLLM")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("v1/chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let mut request = self.client.post(url);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+        let response = retry::send(request.json(&payload)).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+/// Add the decoding settings to a chat completions payload, as vLLM's extra parameters
+fn add_decoding(payload: &mut Value, decoding: &DecodingConfig) {
+    if let (Some(payload), Value::Object(decoding)) = (payload.as_object_mut(), json!(decoding)) {
+        payload.extend(decoding);
+    }
+}
+
+#[async_trait]
+impl Provider for VllmProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "vllm",
+            "vLLM",
+            "Models served by a self hosted vLLM server",
+            VLLM_DEFAULT_MODEL,
+            VLLM_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            VLLM_DOC_URL,
+            vec![
+                ConfigKey::new("VLLM_HOST", true, false, Some(VLLM_API_HOST)),
+                ConfigKey::new("VLLM_API_KEY", false, true, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        add_decoding(&mut payload, &self.model.decoding);
+
+        let response = vcr::post("vllm", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_decoding() {
+        let model =
+            ModelConfig::new(VLLM_DEFAULT_MODEL.to_string()).with_decoding(DecodingConfig {
+                guided_json: Some(json!({ "type": "object" })),
+                min_p: Some(0.1),
+                ..Default::default()
+            });
+        let mut payload = create_request(
+            &model,
+            "system",
+            &[Message::user().with_text("Hi")],
+            &[],
+            &ImageFormat::OpenAi,
+        )
+        .unwrap();
+        add_decoding(&mut payload, &model.decoding);
+
+        assert_eq!(payload["guided_json"], json!({ "type": "object" }));
+        assert!((payload["min_p"].as_f64().unwrap() - 0.1).abs() < 1e-6);
+        // Unset settings are left to the server's defaults
+        assert!(payload.get("guided_regex").is_none());
+        assert!(payload.get("best_of").is_none());
+        assert_eq!(payload["model"], VLLM_DEFAULT_MODEL);
+    }
+}
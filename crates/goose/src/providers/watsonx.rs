@@ -0,0 +1,324 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use tokio::sync::Mutex as TokioMutex;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::watsonx::{create_request, get_usage, model_id, response_to_message};
+use super::utils::{emit_debug_trace, http_client};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const WATSONX_API_HOST: &str = "https://us-south.ml.cloud.ibm.com";
+pub const WATSONX_IAM_HOST: &str = "https://iam.cloud.ibm.com";
+pub const WATSONX_DEFAULT_MODEL: &str = "ibm/granite-3-3-8b-instruct";
+pub const WATSONX_KNOWN_MODELS: &[&str] = &[
+    "ibm/granite-3-3-8b-instruct",
+    "ibm/granite-3-2-8b-instruct",
+    "ibm/granite-3-8b-instruct",
+    "meta-llama/llama-3-3-70b-instruct",
+    "meta-llama/llama-3-405b-instruct",
+    "mistralai/mistral-large",
+    "mistralai/mistral-small-3-1-24b-instruct-2503",
+];
+
+pub const WATSONX_DOC_URL: &str = "https://www.ibm.com/products/watsonx-ai/foundation-models";
+
+// The dated version of the watsonx.ai API that requests are made against
+const WATSONX_API_VERSION: &str = "2024-10-08";
+
+// Tokens are refreshed a little before they expire, so none expires mid request
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+#[derive(Debug, Clone)]
+struct BearerToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+pub struct WatsonxProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    iam_host: String,
+    #[serde(skip)]
+    api_key: String,
+    project_id: String,
+    #[serde(skip)]
+    token: TokioMutex<Option<BearerToken>>,
+    model: ModelConfig,
+}
+
+impl Default for WatsonxProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(WatsonxProvider::metadata().default_model);
+        WatsonxProvider::from_env(model).expect("Failed to initialize watsonx.ai provider")
+    }
+}
+
+impl WatsonxProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("WATSONX_API_KEY")?;
+        let project_id: String = config.get("WATSONX_PROJECT_ID")?;
+        let host: String = config
+            .get("WATSONX_URL")
+            .unwrap_or_else(|_| WATSONX_API_HOST.to_string());
+        let iam_host: String = config
+            .get("WATSONX_IAM_URL")
+            .unwrap_or_else(|_| WATSONX_IAM_HOST.to_string());
+
+        let client = http_client("WATSONX")?;
+
+        Ok(Self {
+            client,
+            host,
+            iam_host,
+            api_key,
+            project_id,
+            token: TokioMutex::new(None),
+            model,
+        })
+    }
+
+    /// A bearer token for the API key, exchanged with IBM Cloud IAM and cached until
+    /// shortly before it expires
+    async fn bearer_token(&self) -> Result<String> {
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if token.expires_at - ChronoDuration::seconds(EXPIRY_MARGIN_SECS) > Utc::now() {
+                return Ok(token.token.clone());
+            }
+        }
+
+        let url = Url::parse(&self.iam_host)?.join("identity/token")?;
+        let response = self
+            .client
+            .post(url)
+            .form(&[
+                ("grant_type", "urn:ibm:params:oauth:grant-type:apikey"),
+                ("apikey", &self.api_key),
+            ])
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body: Value = response.json().await?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Failed to exchange the API key for an IAM token ({}): {}",
+                status,
+                body["errorMessage"].as_str().unwrap_or("Unknown error")
+            ));
+        }
+
+        let token = body["access_token"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No access token in IAM response"))?
+            .to_string();
+        let expires_in = body["expires_in"].as_i64().unwrap_or(3600);
+        *cached = Some(BearerToken {
+            token: token.clone(),
+            expires_at: Utc::now() + ChronoDuration::seconds(expires_in),
+        });
+        Ok(token)
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let mut url = base_url.join("ml/v1/text/chat").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+        url.query_pairs_mut()
+            .append_pair("version", WATSONX_API_VERSION);
+
+        let token = self
+            .bearer_token()
+            .await
+            .map_err(|e| ProviderError::Authentication(e.to_string()))?;
+        let request = self.client.post(url).bearer_auth(token).json(&payload);
+        let response = retry::send(request).await?;
+
+        let status = response.status();
+        let payload: Option<Value> = response.json().await.ok();
+        // Errors come as a list under `errors`, each with a code and a message
+        let message = payload
+            .as_ref()
+            .and_then(|p| p["errors"].as_array())
+            .map(|errors| {
+                errors
+                    .iter()
+                    .filter_map(|e| e["message"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })
+            .filter(|message| !message.is_empty())
+            .unwrap_or_else(|| "Unknown error".to_string());
+
+        // https://cloud.ibm.com/apidocs/watsonx-ai#error-handling
+        match status {
+            StatusCode::OK => payload.ok_or_else(|| {
+                ProviderError::RequestFailed("Response body is not valid JSON".to_string())
+            }),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                // Drop the token in case it was revoked, so the next request gets a new one
+                self.token.lock().await.take();
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API key is valid and has access to the project. \
+                    Status: {}. Message: {}", status, message)))
+            }
+            StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND => {
+                if message.contains("maximum sequence length") || message.contains("context length")
+                {
+                    return Err(ProviderError::ContextLengthExceeded(message));
+                }
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}. Message: {}",
+                    status, message
+                )))
+            }
+            StatusCode::TOO_MANY_REQUESTS => Err(ProviderError::RateLimitExceeded(message)),
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                Err(ProviderError::ServerError(message))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}",
+                    format!(
+                        "Provider request failed with status: {}. Payload: {:?}",
+                        status, payload
+                    )
+                );
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}. Message: {}",
+                    status, message
+                )))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for WatsonxProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "watsonx",
+            "IBM watsonx.ai",
+            "Granite and third party models hosted on IBM watsonx.ai",
+            WATSONX_DEFAULT_MODEL,
+            WATSONX_KNOWN_MODELS
+                .iter()
+                .map(|&s| s.to_string())
+                .collect(),
+            WATSONX_DOC_URL,
+            vec![
+                ConfigKey::new("WATSONX_API_KEY", true, true, None),
+                ConfigKey::new("WATSONX_PROJECT_ID", true, false, None),
+                ConfigKey::new("WATSONX_URL", false, false, Some(WATSONX_API_HOST)),
+                ConfigKey::new("WATSONX_IAM_URL", false, false, Some(WATSONX_IAM_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, &self.project_id, system, messages, tools)?;
+
+        let response = vcr::post("watsonx", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = response["model_id"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| model_id(&self.model.model_name));
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_exchanges_api_key_once() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/identity/token"))
+            .and(body_string_contains("apikey=secret-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "iam-token",
+                "refresh_token": "not_supported",
+                "token_type": "Bearer",
+                "expires_in": 3600
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/ml/v1/text/chat"))
+            .and(query_param("version", WATSONX_API_VERSION))
+            .and(header("authorization", "Bearer iam-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chat-1",
+                "model_id": "ibm/granite-3-3-8b-instruct",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "Hello!" },
+                    "finish_reason": "stop"
+                }],
+                "usage": { "prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15 }
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let provider = WatsonxProvider {
+            client: Client::new(),
+            host: server.uri(),
+            iam_host: server.uri(),
+            api_key: "secret-key".to_string(),
+            project_id: "b7e4b1c6".to_string(),
+            token: TokioMutex::new(None),
+            model: ModelConfig::new("granite-3.3-8b-instruct".to_string()),
+        };
+        let messages = [Message::user().with_text("Hi")];
+        for _ in 0..2 {
+            let (message, usage) = provider.complete("system", &messages, &[]).await?;
+            assert_eq!(message.content[0].as_text(), Some("Hello!"));
+            assert_eq!(usage.model, "ibm/granite-3-3-8b-instruct");
+            assert_eq!(usage.usage.total_tokens, Some(15));
+        }
+        Ok(())
+    }
+}
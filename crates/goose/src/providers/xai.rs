@@ -0,0 +1,131 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::keys::KeyPool;
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const XAI_API_HOST: &str = "https://api.x.ai";
+pub const XAI_DEFAULT_MODEL: &str = "grok-2-latest";
+pub const XAI_KNOWN_MODELS: &[&str] = &[
+    "grok-2-latest",
+    "grok-2-1212",
+    "grok-2-vision-1212",
+    "grok-beta",
+];
+
+pub const XAI_DOC_URL: &str = "https://docs.x.ai/docs/models";
+
+#[derive(serde::Serialize)]
+pub struct XaiProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_keys: KeyPool,
+    model: ModelConfig,
+}
+
+impl Default for XaiProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(XaiProvider::metadata().default_model);
+        XaiProvider::from_env(model).expect("Failed to initialize xAI provider")
+    }
+}
+
+impl XaiProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_keys = KeyPool::from_secret("XAI_API_KEY")?;
+        let host: String = config
+            .get("XAI_HOST")
+            .unwrap_or_else(|_| XAI_API_HOST.to_string());
+
+        let client = http_client("XAI")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_keys,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("v1/chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let request = |key: &str| {
+            self.client
+                .post(url.clone())
+                .header("Authorization", format!("Bearer {}", key))
+                .json(&payload)
+        };
+        let response = retry::send_keyed(&self.api_keys, request).await?;
+
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for XaiProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "xai",
+            "xAI",
+            "Open source models hosted by Together AI",
+            XAI_DEFAULT_MODEL,
+            XAI_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            XAI_DOC_URL,
+            vec![
+                ConfigKey::new("XAI_API_KEY", true, true, None),
+                ConfigKey::new("XAI_HOST", false, false, Some(XAI_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        let response = vcr::post("xai", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
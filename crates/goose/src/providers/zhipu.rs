@@ -0,0 +1,220 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::Engine;
+use jsonwebtoken::{Algorithm, EncodingKey};
+use reqwest::{Client, Response, StatusCode};
+use serde_json::{json, Value};
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, http_client, ImageFormat,
+};
+use super::{retry, vcr};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+// The mainland China platform; the international one is https://api.z.ai/api/paas/v4/
+pub const ZHIPU_API_HOST: &str = "https://open.bigmodel.cn/api/paas/v4/";
+pub const ZHIPU_DEFAULT_MODEL: &str = "glm-4-plus";
+pub const ZHIPU_KNOWN_MODELS: &[&str] = &[
+    "glm-4-plus",
+    "glm-4-air-250414",
+    "glm-4-airx",
+    "glm-4-long",
+    "glm-4-flash-250414",
+    "glm-4.5",
+    "glm-4.5-air",
+];
+
+pub const ZHIPU_DOC_URL: &str = "https://bigmodel.cn/dev/api/normal-model/glm-4";
+
+// How long a signed token is valid for; a new one is signed for every request
+const TOKEN_TTL_MS: i64 = 3 * 60 * 1000;
+
+#[derive(serde::Serialize)]
+pub struct ZhipuProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_key: String,
+    model: ModelConfig,
+}
+
+impl Default for ZhipuProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(ZhipuProvider::metadata().default_model);
+        ZhipuProvider::from_env(model).expect("Failed to initialize Zhipu provider")
+    }
+}
+
+impl ZhipuProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("ZHIPU_API_KEY")?;
+        let host: String = config
+            .get("ZHIPU_HOST")
+            .unwrap_or_else(|_| ZHIPU_API_HOST.to_string());
+
+        let client = http_client("ZHIPU")?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let token = sign_api_key(&self.api_key, chrono::Utc::now().timestamp_millis())
+            .map_err(|e| ProviderError::Authentication(e.to_string()))?;
+        let request = self.client.post(url).bearer_auth(token).json(&payload);
+        let response = retry::send(request).await?;
+
+        handle_response(response).await
+    }
+}
+
+/// Sign a Zhipu API key, which has the form `{id}.{secret}`, into the short lived JWT the
+/// API authenticates with
+///
+/// The header carries a non standard `sign_type`, so the token is put together by hand
+/// rather than with `jsonwebtoken::encode`.
+fn sign_api_key(api_key: &str, now_ms: i64) -> Result<String> {
+    let (id, secret) = api_key
+        .split_once('.')
+        .ok_or_else(|| anyhow!("Invalid ZHIPU_API_KEY, expected the form {{id}}.{{secret}}"))?;
+
+    let encode = |value: Value| {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(value.to_string().as_bytes())
+    };
+    let message = format!(
+        "{}.{}",
+        encode(json!({ "alg": "HS256", "sign_type": "SIGN" })),
+        encode(json!({ "api_key": id, "exp": now_ms + TOKEN_TTL_MS, "timestamp": now_ms })),
+    );
+    let signature = jsonwebtoken::crypto::sign(
+        message.as_bytes(),
+        &EncodingKey::from_secret(secret.as_bytes()),
+        Algorithm::HS256,
+    )?;
+    Ok(format!("{}.{}", message, signature))
+}
+
+/// Zhipu reports errors in the OpenAI shape but with numeric codes, so a prompt that doesn't
+/// fit is recognized by its code instead
+async fn handle_response(response: Response) -> Result<Value, ProviderError> {
+    if response.status() != StatusCode::BAD_REQUEST {
+        return handle_response_openai_compat(response).await;
+    }
+
+    let payload: Value = response
+        .json()
+        .await
+        .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
+    let error = &payload["error"];
+    let message = error["message"]
+        .as_str()
+        .unwrap_or("Unknown error")
+        .to_string();
+    // https://bigmodel.cn/dev/api/error-code/service-error
+    if error["code"] == "1261" {
+        return Err(ProviderError::ContextLengthExceeded(message));
+    }
+    Err(ProviderError::RequestFailed(format!(
+        "{} (status 400)",
+        message
+    )))
+}
+
+#[async_trait]
+impl Provider for ZhipuProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "zhipu",
+            "Zhipu AI",
+            "GLM models from Zhipu AI",
+            ZHIPU_DEFAULT_MODEL,
+            ZHIPU_KNOWN_MODELS.iter().map(|&s| s.to_string()).collect(),
+            ZHIPU_DOC_URL,
+            vec![
+                ConfigKey::new("ZHIPU_API_KEY", true, true, None),
+                ConfigKey::new("ZHIPU_HOST", false, false, Some(ZHIPU_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        let response = vcr::post("zhipu", &payload, || self.post(payload.clone())).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(self, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{DecodingKey, Validation};
+
+    #[test]
+    fn test_sign_api_key() -> Result<()> {
+        let now_ms = 1_750_000_000_000;
+        let token = sign_api_key("a1b2c3.s3cr3t", now_ms)?;
+
+        let header = token.split('.').next().unwrap();
+        let header: Value = serde_json::from_slice(
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(header)?,
+        )?;
+        assert_eq!(header, json!({ "alg": "HS256", "sign_type": "SIGN" }));
+
+        // The timestamps are in milliseconds, which a validator reads as far in the future
+        let claims = jsonwebtoken::decode::<Value>(
+            &token,
+            &DecodingKey::from_secret(b"s3cr3t"),
+            &Validation::new(Algorithm::HS256),
+        )?
+        .claims;
+        assert_eq!(claims["api_key"], "a1b2c3");
+        assert_eq!(claims["timestamp"], now_ms);
+        assert_eq!(claims["exp"], now_ms + TOKEN_TTL_MS);
+
+        assert!(sign_api_key("no-secret", now_ms).is_err());
+        Ok(())
+    }
+}
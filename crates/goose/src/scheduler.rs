@@ -0,0 +1,534 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use futures::StreamExt;
+use mcp_core::role::Role;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::agents::AgentFactory;
+use crate::config::{Config, ExtensionManager};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::prompt_template::load_prompt_file;
+use crate::providers;
+use crate::session::{Session, SessionStore};
+use crate::usage::UsageTracker;
+
+/// Config key holding the scheduled jobs, as a map of `JobConfig` by name
+pub const SCHEDULE_CONFIG_KEY: &str = "GOOSE_SCHEDULE";
+
+const EVENT_CAPACITY: usize = 64;
+
+/// When a job runs
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trigger {
+    /// Every time the cron schedule matches, in local time
+    Cron(Box<cron::Schedule>),
+    /// Once, at the given time
+    Once(DateTime<Utc>),
+}
+
+impl Trigger {
+    /// Parse a cron expression such as `0 9 * * MON-FRI`
+    ///
+    /// Seconds and years can be added as an extra first and last field. Days of the week are
+    /// numbered from 1 for Sunday, unlike in crontab, so names are less surprising.
+    pub fn cron(expression: &str) -> Result<Self> {
+        let expression = match expression.split_whitespace().count() {
+            5 => format!("0 {}", expression),
+            _ => expression.to_string(),
+        };
+        let schedule = cron::Schedule::from_str(&expression)
+            .map_err(|e| anyhow!("Invalid schedule {:?}: {}", expression, e))?;
+        Ok(Self::Cron(Box::new(schedule)))
+    }
+
+    /// Run once, the given time from now
+    pub fn after(delay: Duration) -> Self {
+        Self::Once(Utc::now() + delay)
+    }
+
+    /// The next time to run, None once the trigger is done
+    pub fn next(&self, runs: usize) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Cron(schedule) => schedule
+                .upcoming(Local)
+                .next()
+                .map(|at| at.with_timezone(&Utc)),
+            Self::Once(at) => (runs == 0).then_some(*at),
+        }
+    }
+}
+
+/// A job as configured under `GOOSE_SCHEDULE`
+///
+/// A job runs either on a cron `schedule` or once, `after` the given number of seconds. It
+/// sends its `prompt`, or the prompt library `template` rendered with the job name and the
+/// current date, to a fresh agent with the configured provider and model unless it has its
+/// own. The agent gets the listed extensions, or every enabled one when the list is left out.
+///
+/// ```yaml
+/// GOOSE_SCHEDULE:
+///   triage:
+///     schedule: "0 9 * * MON-FRI"
+///     template: triage.md
+///     extensions: [github]
+///   reminder:
+///     after: 3600
+///     prompt: Summarize what changed in ~/notes today
+///     model: gpt-4o-mini
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobConfig {
+    #[serde(default)]
+    pub schedule: Option<String>,
+    #[serde(default)]
+    pub after: Option<u64>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub extensions: Option<Vec<String>>,
+}
+
+/// What a job asks the agent
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobPrompt {
+    Text(String),
+    /// A prompt library template, rendered when the job runs
+    Template(String),
+}
+
+/// A prompt to run in the background whenever its trigger fires
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    pub trigger: Trigger,
+    pub prompt: JobPrompt,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub extensions: Option<Vec<String>>,
+}
+
+impl Job {
+    pub fn new(trigger: Trigger, prompt: JobPrompt) -> Self {
+        Self {
+            trigger,
+            prompt,
+            provider: None,
+            model: None,
+            extensions: None,
+        }
+    }
+
+    pub fn from_config(config: &JobConfig) -> Result<Self> {
+        let trigger = match (&config.schedule, config.after) {
+            (Some(schedule), None) => Trigger::cron(schedule)?,
+            (None, Some(after)) => Trigger::after(Duration::from_secs(after)),
+            _ => return Err(anyhow!("A job needs either a schedule or a delay")),
+        };
+        let prompt = match (&config.prompt, &config.template) {
+            (Some(prompt), None) => JobPrompt::Text(prompt.clone()),
+            (None, Some(template)) => JobPrompt::Template(template.clone()),
+            _ => return Err(anyhow!("A job needs either a prompt or a template")),
+        };
+        Ok(Self {
+            trigger,
+            prompt,
+            provider: config.provider.clone(),
+            model: config.model.clone(),
+            extensions: config.extensions.clone(),
+        })
+    }
+
+    fn render_prompt(&self, name: &str) -> Result<String> {
+        match &self.prompt {
+            JobPrompt::Text(text) => Ok(text.clone()),
+            JobPrompt::Template(template) => Ok(load_prompt_file(
+                template.as_str(),
+                &json!({
+                    "job": name,
+                    "current_date_time": Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                }),
+            )?),
+        }
+    }
+}
+
+/// What happened to a job run, sent to every subscriber of the `Scheduler`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobEvent {
+    Started {
+        job: String,
+        session_id: String,
+    },
+    /// The run finished; `result` is the agent's last reply
+    Completed {
+        job: String,
+        session_id: String,
+        result: String,
+    },
+    Failed {
+        job: String,
+        session_id: String,
+        error: String,
+    },
+}
+
+/// Runs jobs in the background, each run in a new session of the session store
+///
+/// Every job gets its own task, which runs the job when its trigger fires and waits for the
+/// run to finish before looking for the next time, so a run that takes longer than the
+/// schedule skips the times it missed. Nobody is around to confirm tool calls, so they are
+/// declined when the agent asks; set `GOOSE_MODE` or the tool policy to allow what jobs need.
+/// Usage is attributed to the run's session while it runs.
+pub struct Scheduler {
+    store: Arc<SessionStore>,
+    config: Arc<Config>,
+    events: broadcast::Sender<JobEvent>,
+    tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl Scheduler {
+    /// A scheduler without jobs, creating agents from `config`
+    pub fn new(store: Arc<SessionStore>, config: Arc<Config>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            store,
+            config,
+            events,
+            tasks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A scheduler for the global session store running the jobs of `GOOSE_SCHEDULE`
+    pub fn from_config() -> Result<Self> {
+        Ok(Self::load(
+            SessionStore::global()?,
+            Arc::new(Config::default()),
+        ))
+    }
+
+    /// A scheduler running the jobs that `config` has under `GOOSE_SCHEDULE`
+    ///
+    /// Jobs with an invalid config are skipped with a warning.
+    pub fn load(store: Arc<SessionStore>, config: Arc<Config>) -> Self {
+        let jobs: BTreeMap<String, JobConfig> = config.get(SCHEDULE_CONFIG_KEY).unwrap_or_default();
+        let scheduler = Self::new(store, config);
+        for (name, config) in jobs {
+            match Job::from_config(&config) {
+                Ok(job) => scheduler.schedule(&name, job),
+                Err(e) => warn!("Skipping scheduled job {}: {}", name, e),
+            }
+        }
+        scheduler
+    }
+
+    /// Receive the events of every run from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    /// Start running a job, replacing any job with the same name
+    pub fn schedule(&self, name: &str, job: Job) {
+        let runner = self.runner();
+        let job_name = name.to_string();
+        let task = tokio::spawn(async move {
+            let mut runs = 0;
+            while let Some(at) = job.trigger.next(runs) {
+                let delay = (at - Utc::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(delay).await;
+                // Failures are reported as events, and the job runs again next time
+                let _ = runner.run(&job_name, &job).await;
+                runs += 1;
+            }
+        });
+        if let Some(previous) = self.tasks.lock().unwrap().insert(name.to_string(), task) {
+            previous.abort();
+        }
+    }
+
+    /// Stop a job, including a run in progress; false if there is no job of that name
+    pub fn cancel(&self, name: &str) -> bool {
+        match self.tasks.lock().unwrap().remove(name) {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The names of the jobs that may still run
+    pub fn jobs(&self) -> Vec<String> {
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|_, task| !task.is_finished());
+        let mut names: Vec<String> = tasks.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Run a job right away, returning the id of the session it ran in
+    pub async fn run(&self, name: &str, job: &Job) -> Result<String> {
+        self.runner().run(name, job).await
+    }
+
+    fn runner(&self) -> Runner {
+        Runner {
+            store: Arc::clone(&self.store),
+            config: Arc::clone(&self.config),
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        for task in self.tasks.lock().unwrap().values() {
+            task.abort();
+        }
+    }
+}
+
+struct Runner {
+    store: Arc<SessionStore>,
+    config: Arc<Config>,
+    events: broadcast::Sender<JobEvent>,
+}
+
+impl Runner {
+    async fn run(&self, name: &str, job: &Job) -> Result<String> {
+        let session_id = format!("{}-{}", name, Local::now().format("%Y%m%d-%H%M%S%3f"));
+        info!("Running scheduled job {} in session {}", name, session_id);
+        // Nobody may be subscribed, which is fine
+        let _ = self.events.send(JobEvent::Started {
+            job: name.to_string(),
+            session_id: session_id.clone(),
+        });
+
+        // Attribute the run's usage to its session, then give the session back to whoever had it
+        let tracker = UsageTracker::global();
+        let previous = tracker.session();
+        tracker.set_session(Some(session_id.clone()));
+        let outcome = self.run_session(name, job, &session_id).await;
+        tracker.set_session(previous);
+
+        let event = match outcome {
+            Ok(result) => JobEvent::Completed {
+                job: name.to_string(),
+                session_id: session_id.clone(),
+                result,
+            },
+            Err(e) => {
+                warn!("Scheduled job {} failed: {}", name, e);
+                JobEvent::Failed {
+                    job: name.to_string(),
+                    session_id: session_id.clone(),
+                    error: e.to_string(),
+                }
+            }
+        };
+        let failed = matches!(event, JobEvent::Failed { .. });
+        let _ = self.events.send(event);
+        if failed {
+            return Err(anyhow!("Scheduled job {} failed", name));
+        }
+        Ok(session_id)
+    }
+
+    async fn run_session(&self, name: &str, job: &Job, session_id: &str) -> Result<String> {
+        let config = &self.config;
+        let provider_name = match &job.provider {
+            Some(provider) => provider.clone(),
+            None => config.get("GOOSE_PROVIDER")?,
+        };
+        let model = match &job.model {
+            Some(model) => model.clone(),
+            None => config.get("GOOSE_MODEL")?,
+        };
+        let provider = providers::create(&provider_name, ModelConfig::new(model.clone()))?;
+        let version: String = config
+            .get("GOOSE_AGENT")
+            .unwrap_or_else(|_| AgentFactory::default_version().to_string());
+        let mut agent = AgentFactory::create(&version, provider)
+            .ok_or_else(|| anyhow!("No agent version {}", version))?;
+
+        for extension in ExtensionManager::get_all_in(config)? {
+            let wanted = match &job.extensions {
+                Some(names) => names.iter().any(|n| n == extension.config.name()),
+                None => extension.enabled,
+            };
+            if wanted {
+                agent.add_extension(extension.config).await?;
+            }
+        }
+
+        let mut session =
+            Session::create_in(Arc::clone(&self.store), session_id, &provider_name, &model)?;
+        session.push(Message::user().with_text(job.render_prompt(name)?))?;
+
+        let mut result = String::new();
+        let mut stream = agent.reply(session.messages()).await?;
+        while let Some(message) = stream.next().await {
+            let message = message?;
+            if let Some(request) = message
+                .content
+                .first()
+                .and_then(|c| c.as_tool_confirmation_request())
+            {
+                warn!(
+                    "Scheduled job {} declined to run {}",
+                    name, request.tool_name
+                );
+                agent.handle_confirmation(request.id.clone(), false).await;
+                continue;
+            }
+            if message.role == Role::Assistant {
+                result = message.as_concat_text();
+            }
+            session.push(message)?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+    use crate::usage::UsageFilter;
+    use tempfile::NamedTempFile;
+
+    // A config of its own, so tests never read or write the user's
+    fn scratch_config(file: &NamedTempFile) -> Arc<Config> {
+        Arc::new(Config::new(file.path(), "goose-test").unwrap())
+    }
+
+    fn job(provider: &str) -> Job {
+        Job {
+            provider: Some(provider.to_string()),
+            model: Some("gpt-4o".to_string()),
+            extensions: Some(vec![]),
+            ..Job::new(
+                Trigger::after(Duration::ZERO),
+                JobPrompt::Text("Triage the inbox".to_string()),
+            )
+        }
+    }
+
+    #[test]
+    fn test_job_from_config() {
+        let config: JobConfig = serde_yaml::from_str(
+            "schedule: '0 9 * * MON-FRI'\ntemplate: triage.md\nextensions: [github]",
+        )
+        .unwrap();
+        let job = Job::from_config(&config).unwrap();
+        assert!(matches!(job.trigger, Trigger::Cron(_)));
+        assert_eq!(job.prompt, JobPrompt::Template("triage.md".to_string()));
+        assert!(job.trigger.next(5).unwrap() > Utc::now());
+
+        let once = Trigger::after(Duration::from_secs(60));
+        assert!(once.next(0).is_some());
+        assert!(once.next(1).is_none());
+
+        assert!(Trigger::cron("every day").is_err());
+        let both = JobConfig {
+            schedule: Some("0 9 * * *".to_string()),
+            after: Some(60),
+            prompt: Some("hi".to_string()),
+            ..Default::default()
+        };
+        assert!(Job::from_config(&both).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_schedules_configured_jobs() {
+        let file = NamedTempFile::new().unwrap();
+        let config = scratch_config(&file);
+        config
+            .set(
+                SCHEDULE_CONFIG_KEY,
+                json!({
+                    "triage": {"schedule": "0 9 * * MON-FRI", "prompt": "Triage the inbox"},
+                    "broken": {"prompt": "Never runs"},
+                }),
+            )
+            .unwrap();
+
+        let store = Arc::new(SessionStore::open_in_memory().unwrap());
+        let scheduler = Scheduler::load(store, config);
+        assert_eq!(scheduler.jobs(), vec!["triage".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_run_persists_session() {
+        let mock = MockProvider::default().with_text("Nothing needs attention");
+        mock.register("scheduler-run");
+        let store = Arc::new(SessionStore::open_in_memory().unwrap());
+        let file = NamedTempFile::new().unwrap();
+        let scheduler = Scheduler::new(Arc::clone(&store), scratch_config(&file));
+        let mut events = scheduler.subscribe();
+
+        let session_id = scheduler
+            .run("triage", &job("scheduler-run"))
+            .await
+            .unwrap();
+        MockProvider::unregister("scheduler-run");
+
+        let messages = store.messages(&session_id).unwrap();
+        assert_eq!(messages.len(), 2);
+        let usage = UsageTracker::global().records(&UsageFilter {
+            session_id: Some(session_id.clone()),
+            ..Default::default()
+        });
+        assert_eq!(usage.len(), 1);
+        assert_eq!(messages[0].as_concat_text(), "Triage the inbox");
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            JobEvent::Started { .. }
+        ));
+        assert_eq!(
+            events.recv().await.unwrap(),
+            JobEvent::Completed {
+                job: "triage".to_string(),
+                session_id,
+                result: "Nothing needs attention".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schedule_reports_failures() {
+        let store = Arc::new(SessionStore::open_in_memory().unwrap());
+        let file = NamedTempFile::new().unwrap();
+        let scheduler = Scheduler::new(store, scratch_config(&file));
+        let mut events = scheduler.subscribe();
+
+        scheduler.schedule("broken", job("scheduler-unknown"));
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            JobEvent::Started { .. }
+        ));
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            JobEvent::Failed { job, .. } if job == "broken"
+        ));
+
+        // The one-shot job is done after its run
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(scheduler.jobs().is_empty());
+        assert!(!scheduler.cancel("broken"));
+    }
+}
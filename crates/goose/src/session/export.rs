@@ -0,0 +1,539 @@
+use chrono::{DateTime, Utc};
+use mcp_core::content::Content;
+use mcp_core::role::Role;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use super::store::SessionMetadata;
+use super::Session;
+use crate::message::{Message, MessageContent};
+use crate::usage::UsageSummary;
+
+/// The format a transcript is rendered to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    Markdown,
+    Html,
+}
+
+impl TranscriptFormat {
+    /// The usual file extension for the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl FromStr for TranscriptFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "md" | "markdown" => Ok(Self::Markdown),
+            "html" | "htm" => Ok(Self::Html),
+            _ => Err(anyhow::anyhow!("Unknown transcript format {}", s)),
+        }
+    }
+}
+
+/// The output of a tool call
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolOutput {
+    pub text: String,
+    pub is_error: bool,
+}
+
+/// A tool call together with its output, if it got one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallBlock {
+    pub id: String,
+    pub name: String,
+    /// The arguments of the call, or why they could not be parsed
+    pub arguments: Result<Value, String>,
+    pub output: Option<ToolOutput>,
+}
+
+/// A piece of a turn in the transcript
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Text(String),
+    /// The model's reasoning, collapsed when rendered
+    Thinking(String),
+    Image {
+        mime_type: String,
+    },
+    ToolCall(ToolCallBlock),
+}
+
+/// The content of one message, with tool responses folded into the calls they answer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Turn {
+    pub role: Role,
+    pub created: DateTime<Utc>,
+    pub blocks: Vec<Block>,
+}
+
+/// A session laid out for reading, which can be rendered to Markdown or HTML
+///
+/// Tool responses are attached to the call they belong to instead of showing up as messages
+/// of their own, and their output is collapsed when rendered.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub metadata: SessionMetadata,
+    pub turns: Vec<Turn>,
+    pub usage: Option<UsageSummary>,
+}
+
+impl Transcript {
+    /// Build the transcript of a session, including the usage recorded for it
+    pub fn from_session(session: &Session) -> Self {
+        Self::new(
+            session.metadata().clone(),
+            session.messages(),
+            Some(session.usage()),
+        )
+    }
+
+    pub fn new(
+        metadata: SessionMetadata,
+        messages: &[Message],
+        usage: Option<UsageSummary>,
+    ) -> Self {
+        let outputs: HashMap<&str, ToolOutput> = messages
+            .iter()
+            .flat_map(|m| m.content.iter())
+            .filter_map(|c| c.as_tool_response())
+            .map(|response| {
+                let output = match &response.tool_result {
+                    Ok(contents) => ToolOutput {
+                        text: contents_to_text(contents),
+                        is_error: false,
+                    },
+                    Err(e) => ToolOutput {
+                        text: e.to_string(),
+                        is_error: true,
+                    },
+                };
+                (response.id.as_str(), output)
+            })
+            .collect();
+
+        let turns = messages
+            .iter()
+            .map(|message| Turn {
+                role: message.role.clone(),
+                created: DateTime::from_timestamp(message.created, 0).unwrap_or_default(),
+                blocks: message
+                    .content
+                    .iter()
+                    .filter_map(|content| to_block(content, &outputs))
+                    .collect(),
+            })
+            // Messages that only carried tool responses are now empty
+            .filter(|turn| !turn.blocks.is_empty())
+            .collect();
+
+        Self {
+            metadata,
+            turns,
+            usage,
+        }
+    }
+
+    pub fn render(&self, format: TranscriptFormat) -> String {
+        match format {
+            TranscriptFormat::Markdown => self.to_markdown(),
+            TranscriptFormat::Html => self.to_html(),
+        }
+    }
+
+    fn summary(&self) -> Vec<(&'static str, String)> {
+        let mut summary = vec![
+            ("Provider", self.metadata.provider.clone()),
+            ("Model", self.metadata.model.clone()),
+            (
+                "Started",
+                self.metadata
+                    .created
+                    .format("%Y-%m-%d %H:%M UTC")
+                    .to_string(),
+            ),
+            ("Messages", self.metadata.message_count.to_string()),
+        ];
+        if let Some(parent) = &self.metadata.parent {
+            summary.push((
+                "Forked from",
+                format!(
+                    "{} at message {}",
+                    parent,
+                    self.metadata.fork_point.unwrap_or(0)
+                ),
+            ));
+        }
+        if let Some(usage) = self.usage.filter(|u| u.requests > 0) {
+            summary.push((
+                "Tokens",
+                format!(
+                    "{} input, {} output",
+                    usage.input_tokens, usage.output_tokens
+                ),
+            ));
+            summary.push(("Cost", format!("${:.4}", usage.cost)));
+        }
+        summary
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Session {}\n", self.metadata.id);
+        for (label, value) in self.summary() {
+            let _ = writeln!(out, "- **{}:** {}", label, value);
+        }
+
+        for turn in &self.turns {
+            let _ = writeln!(out, "\n---\n\n### {}\n", role_name(&turn.role));
+            for block in &turn.blocks {
+                match block {
+                    Block::Text(text) => {
+                        let _ = writeln!(out, "{}\n", text.trim_end());
+                    }
+                    Block::Thinking(thinking) => {
+                        let _ = writeln!(
+                            out,
+                            "<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>\n",
+                            thinking.trim_end()
+                        );
+                    }
+                    Block::Image { mime_type } => {
+                        let _ = writeln!(out, "*[image: {}]*\n", mime_type);
+                    }
+                    Block::ToolCall(call) => {
+                        let _ = writeln!(out, "**Tool call:** `{}`\n", call.name);
+                        match &call.arguments {
+                            Ok(arguments) => {
+                                let json =
+                                    serde_json::to_string_pretty(arguments).unwrap_or_default();
+                                let _ = writeln!(out, "{}\n", fenced(&json, "json"));
+                            }
+                            Err(e) => {
+                                let _ = writeln!(out, "*Invalid tool call: {}*\n", e);
+                            }
+                        }
+                        if let Some(output) = &call.output {
+                            let _ = writeln!(
+                                out,
+                                "<details>\n<summary>{}</summary>\n\n{}\n\n</details>\n",
+                                output_label(output),
+                                fenced(&output.text, "")
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let title = format!("Session {}", self.metadata.id);
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+            <style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<dl class=\"summary\">",
+            escape_html(&title),
+            HTML_STYLE,
+            escape_html(&title)
+        );
+        for (label, value) in self.summary() {
+            let _ = writeln!(out, "<dt>{}</dt><dd>{}</dd>", label, escape_html(&value));
+        }
+        let _ = writeln!(out, "</dl>");
+
+        for turn in &self.turns {
+            let role = role_name(&turn.role);
+            let _ = writeln!(
+                out,
+                "<section class=\"turn {}\">\n<h2>{}</h2>",
+                role.to_lowercase(),
+                role
+            );
+            for block in &turn.blocks {
+                match block {
+                    Block::Text(text) => {
+                        let _ = writeln!(out, "<div class=\"text\">{}</div>", escape_html(text));
+                    }
+                    Block::Thinking(thinking) => {
+                        let _ = writeln!(
+                            out,
+                            "<details class=\"thinking\">\n<summary>Thinking</summary>\n<div class=\"text\">{}</div>\n</details>",
+                            escape_html(thinking)
+                        );
+                    }
+                    Block::Image { mime_type } => {
+                        let _ = writeln!(
+                            out,
+                            "<p class=\"image\">[image: {}]</p>",
+                            escape_html(mime_type)
+                        );
+                    }
+                    Block::ToolCall(call) => {
+                        let _ = writeln!(
+                            out,
+                            "<div class=\"tool-call\">\n<p>Tool call: <code>{}</code></p>",
+                            escape_html(&call.name)
+                        );
+                        match &call.arguments {
+                            Ok(arguments) => {
+                                let json =
+                                    serde_json::to_string_pretty(arguments).unwrap_or_default();
+                                let _ = writeln!(out, "<pre>{}</pre>", escape_html(&json));
+                            }
+                            Err(e) => {
+                                let _ = writeln!(
+                                    out,
+                                    "<p class=\"error\">Invalid tool call: {}</p>",
+                                    escape_html(e)
+                                );
+                            }
+                        }
+                        if let Some(output) = &call.output {
+                            let _ = writeln!(
+                                out,
+                                "<details{}>\n<summary>{}</summary>\n<pre>{}</pre>\n</details>",
+                                if output.is_error {
+                                    " class=\"error\""
+                                } else {
+                                    ""
+                                },
+                                output_label(output),
+                                escape_html(&output.text)
+                            );
+                        }
+                        let _ = writeln!(out, "</div>");
+                    }
+                }
+            }
+            let _ = writeln!(out, "</section>");
+        }
+        let _ = writeln!(out, "</body>\n</html>");
+        out
+    }
+}
+
+const HTML_STYLE: &str =
+    "body{font-family:sans-serif;max-width:50em;margin:2em auto;padding:0 1em}\
+    dl.summary{display:grid;grid-template-columns:max-content auto;gap:.2em 1em}\
+    dt{font-weight:bold}dd{margin:0}\
+    section.turn{border-top:1px solid #ddd;padding:.5em 0}\
+    .text{white-space:pre-wrap}\
+    pre{background:#f5f5f5;padding:.5em;overflow-x:auto}\
+    .error,.error summary{color:#b00}";
+
+fn to_block(content: &MessageContent, outputs: &HashMap<&str, ToolOutput>) -> Option<Block> {
+    match content {
+        MessageContent::Text(text) => Some(Block::Text(text.text.clone())),
+        MessageContent::Thinking(thinking) => Some(Block::Thinking(thinking.thinking.clone())),
+        MessageContent::Image(image) => Some(Block::Image {
+            mime_type: image.mime_type.clone(),
+        }),
+        MessageContent::ToolRequest(request) => {
+            let (name, arguments) = match &request.tool_call {
+                Ok(call) => (call.name.clone(), Ok(call.arguments.clone())),
+                Err(e) => ("unknown".to_string(), Err(e.to_string())),
+            };
+            Some(Block::ToolCall(ToolCallBlock {
+                id: request.id.clone(),
+                name,
+                arguments,
+                output: outputs.get(request.id.as_str()).cloned(),
+            }))
+        }
+        // Shown with the call they answer
+        MessageContent::ToolResponse(_) => None,
+        // Only shown while the session runs
+        MessageContent::ToolConfirmationRequest(_) => None,
+    }
+}
+
+fn contents_to_text(contents: &[Content]) -> String {
+    contents
+        .iter()
+        .map(|content| match content {
+            Content::Text(text) => text.text.clone(),
+            Content::Image(image) => format!("[image: {}]", image.mime_type),
+            Content::Resource(resource) => resource.get_text(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn role_name(role: &Role) -> &'static str {
+    match role {
+        Role::User => "User",
+        Role::Assistant => "Goose",
+    }
+}
+
+fn output_label(output: &ToolOutput) -> &'static str {
+    if output.is_error {
+        "Error"
+    } else {
+        "Output"
+    }
+}
+
+// A code block whose fence can't be closed by backticks in the text itself
+fn fenced(text: &str, language: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, text.trim_end(), fence)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use mcp_core::ToolError;
+    use serde_json::json;
+
+    fn metadata() -> SessionMetadata {
+        SessionMetadata {
+            id: "demo".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            created: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            updated: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            message_count: 4,
+            parent: None,
+            fork_point: None,
+        }
+    }
+
+    fn messages() -> Vec<Message> {
+        vec![
+            Message::user().with_text("List the <src> files"),
+            Message::assistant()
+                .with_text("Let me look.")
+                .with_tool_request(
+                    "call_1",
+                    Ok(ToolCall::new(
+                        "developer__shell",
+                        json!({"command": "ls src"}),
+                    )),
+                )
+                .with_tool_request(
+                    "call_2",
+                    Err(ToolError::InvalidParameters("bad json".to_string())),
+                ),
+            Message::user()
+                .with_tool_response("call_1", Ok(vec![Content::text("main.rs\n```\n")]))
+                .with_tool_response(
+                    "call_2",
+                    Err(ToolError::ExecutionError("not run".to_string())),
+                ),
+            Message::assistant().with_text("There is one file, main.rs."),
+        ]
+    }
+
+    fn usage() -> UsageSummary {
+        UsageSummary {
+            requests: 2,
+            input_tokens: 1200,
+            output_tokens: 80,
+            total_tokens: 1280,
+            cost: 0.0123,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tool_responses_attach_to_calls() {
+        let transcript = Transcript::new(metadata(), &messages(), None);
+        // The message with only tool responses is folded into the calls
+        assert_eq!(transcript.turns.len(), 3);
+
+        let calls: Vec<&ToolCallBlock> = transcript.turns[1]
+            .blocks
+            .iter()
+            .filter_map(|b| match b {
+                Block::ToolCall(call) => Some(call),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].name, "developer__shell");
+        assert_eq!(
+            calls[0].output,
+            Some(ToolOutput {
+                text: "main.rs\n```\n".to_string(),
+                is_error: false
+            })
+        );
+        assert!(calls[1].arguments.is_err());
+        assert!(calls[1].output.as_ref().unwrap().is_error);
+    }
+
+    #[test]
+    fn test_markdown() {
+        let transcript = Transcript::new(metadata(), &messages(), Some(usage()));
+        let markdown = transcript.render(TranscriptFormat::Markdown);
+
+        assert!(markdown.starts_with("# Session demo\n"));
+        assert!(markdown.contains("- **Model:** gpt-4o"));
+        assert!(markdown.contains("- **Cost:** $0.0123"));
+        assert!(markdown.contains("### Goose\n\nLet me look."));
+        assert!(markdown.contains("**Tool call:** `developer__shell`"));
+        // The output holds a fence of three backticks, so it is wrapped in four
+        assert!(markdown.contains("<summary>Output</summary>\n\n````\nmain.rs\n```\n````"));
+        assert!(markdown.contains("<summary>Error</summary>"));
+    }
+
+    #[test]
+    fn test_html_is_escaped() {
+        let transcript = Transcript::new(metadata(), &messages(), None);
+        let html = transcript.render(TranscriptFormat::Html);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("List the &lt;src&gt; files"));
+        assert!(!html.contains("<src>"));
+        assert!(html.contains("<summary>Output</summary>"));
+        assert!(html.contains("&quot;command&quot;: &quot;ls src&quot;"));
+        // Without usage there is no cost in the summary
+        assert!(!html.contains("Cost"));
+    }
+
+    #[test]
+    fn test_format_from_str() {
+        assert_eq!(
+            "md".parse::<TranscriptFormat>().unwrap(),
+            TranscriptFormat::Markdown
+        );
+        assert_eq!(
+            "HTML".parse::<TranscriptFormat>().unwrap(),
+            TranscriptFormat::Html
+        );
+        assert!("pdf".parse::<TranscriptFormat>().is_err());
+    }
+}
@@ -0,0 +1,402 @@
+mod export;
+mod store;
+
+pub use export::{Block, ToolCallBlock, ToolOutput, Transcript, TranscriptFormat, Turn};
+pub use store::{Checkpoint, SessionMetadata, SessionStore};
+
+use anyhow::Result;
+use chrono::Utc;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::agents::Plan;
+use crate::message::{Message, ToolRequest};
+use crate::usage::{UsageFilter, UsageSummary, UsageTracker};
+
+/// A conversation that is persisted to a `SessionStore` as it progresses
+///
+/// Every message pushed to the session is written to the store straight away, so
+/// `Session::resume` restores the conversation exactly as it was left, including tool calls
+/// that were requested but never answered. Usage is recorded by the `UsageTracker` under the
+/// session id.
+///
+/// A session can be forked at any message into a new branch that shares the history up to
+/// that point, to explore an alternative without losing the original thread.
+///
+/// Checkpoints taken at turn boundaries let a bad turn be undone by rolling the session back,
+/// rather than editing its history by hand.
+pub struct Session {
+    store: Arc<SessionStore>,
+    metadata: SessionMetadata,
+    messages: Vec<Message>,
+}
+
+impl Session {
+    /// Start a new session in the global store
+    pub fn create(id: &str, provider: &str, model: &str) -> Result<Self> {
+        Self::create_in(SessionStore::global()?, id, provider, model)
+    }
+
+    /// Resume a session from the global store
+    pub fn resume(id: &str) -> Result<Self> {
+        Self::resume_in(SessionStore::global()?, id)
+    }
+
+    pub fn create_in(
+        store: Arc<SessionStore>,
+        id: &str,
+        provider: &str,
+        model: &str,
+    ) -> Result<Self> {
+        let metadata = store.create(id, provider, model)?;
+        Ok(Self {
+            store,
+            metadata,
+            messages: Vec::new(),
+        })
+    }
+
+    pub fn resume_in(store: Arc<SessionStore>, id: &str) -> Result<Self> {
+        let metadata = store
+            .metadata(id)?
+            .ok_or_else(|| anyhow::anyhow!("No session named {}", id))?;
+        let messages = store.messages(id)?;
+        Ok(Self {
+            store,
+            metadata,
+            messages,
+        })
+    }
+
+    /// Start a new session from the first `at` messages of this one
+    ///
+    /// The branch continues with the same provider and model, and is stored in the same
+    /// store. Usage in the branch is recorded under its own id.
+    pub fn fork(&self, new_id: &str, at: usize) -> Result<Self> {
+        self.store.fork(self.id(), at, new_id)?;
+        Self::resume_in(self.store.clone(), new_id)
+    }
+
+    /// The sessions forked directly from this one
+    pub fn branches(&self) -> Result<Vec<SessionMetadata>> {
+        self.store.branches(self.id())
+    }
+
+    pub fn id(&self) -> &str {
+        &self.metadata.id
+    }
+
+    pub fn metadata(&self) -> &SessionMetadata {
+        &self.metadata
+    }
+
+    pub fn store(&self) -> &SessionStore {
+        &self.store
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Append a message to the conversation and persist it
+    pub fn push(&mut self, message: Message) -> Result<()> {
+        self.store
+            .append(self.id(), self.messages.len(), &message)?;
+        self.messages.push(message);
+        self.metadata.message_count = self.messages.len();
+        Ok(())
+    }
+
+    /// Drop every message after the first `len`
+    pub fn truncate(&mut self, len: usize) -> Result<()> {
+        self.store.truncate(self.id(), len)?;
+        self.messages.truncate(len);
+        self.metadata.message_count = self.messages.len();
+        Ok(())
+    }
+
+    /// Pin or unpin the message at `index`, so truncation and summarization keep it verbatim
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) -> Result<()> {
+        if index < self.metadata.fork_point.unwrap_or(0) {
+            return Err(anyhow::anyhow!(
+                "Message {} is shared with the session this one was forked from",
+                index
+            ));
+        }
+        let mut message = self
+            .messages
+            .get(index)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Session {} has no message {}", self.id(), index))?;
+        message.pinned = pinned;
+        self.store.append(self.id(), index, &message)?;
+        self.messages[index] = message;
+        Ok(())
+    }
+
+    /// Record that the conversation continues with a different provider or model
+    pub fn set_model(&mut self, provider: &str, model: &str) -> Result<()> {
+        self.store.set_model(self.id(), provider, model)?;
+        self.metadata.provider = provider.to_string();
+        self.metadata.model = model.to_string();
+        Ok(())
+    }
+
+    /// The plan the conversation is following in plan mode, if any
+    pub fn plan(&self) -> Result<Option<Plan>> {
+        self.store.plan(self.id())
+    }
+
+    /// Record the agent's latest plan, so it survives the session
+    pub fn set_plan(&mut self, plan: Option<&Plan>) -> Result<()> {
+        self.store.set_plan(self.id(), plan)
+    }
+
+    /// Snapshot the session as it is now, to roll back to later
+    pub fn checkpoint(&mut self, label: Option<&str>) -> Result<Checkpoint> {
+        let checkpoint = Checkpoint {
+            id: 0,
+            label: label.map(str::to_string),
+            created: Utc::now(),
+            message_count: self.messages.len(),
+            pending_tool_calls: self
+                .pending_tool_requests()
+                .iter()
+                .map(|r| r.id.clone())
+                .collect(),
+            plan: self.plan()?,
+            usage: self.usage(),
+        };
+        self.store.save_checkpoint(self.id(), checkpoint)
+    }
+
+    /// The checkpoints of this session, oldest first
+    pub fn checkpoints(&self) -> Result<Vec<Checkpoint>> {
+        self.store.checkpoints(self.id())
+    }
+
+    /// Restore the session to a checkpoint, dropping everything that came after it
+    pub fn rollback(&mut self, checkpoint: usize) -> Result<Checkpoint> {
+        let target = self.store.rollback(self.id(), checkpoint)?;
+        self.messages.truncate(target.message_count);
+        self.metadata.message_count = self.messages.len();
+        Ok(target)
+    }
+
+    /// Roll back to the last checkpoint before the latest message, undoing the last turn
+    ///
+    /// Returns None when there is nothing to undo.
+    pub fn undo(&mut self) -> Result<Option<Checkpoint>> {
+        let len = self.messages.len();
+        match self
+            .checkpoints()?
+            .into_iter()
+            .rev()
+            .find(|c| c.message_count < len)
+        {
+            Some(checkpoint) => self.rollback(checkpoint.id).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Tool requests that have no response anywhere after them in the conversation
+    ///
+    /// These are left behind when a session ends while a tool is running, and must be
+    /// answered before the conversation is sent to a provider again.
+    pub fn pending_tool_requests(&self) -> Vec<&ToolRequest> {
+        let answered: HashSet<&str> = self
+            .messages
+            .iter()
+            .flat_map(|m| m.content.iter())
+            .filter_map(|c| c.as_tool_response())
+            .map(|r| r.id.as_str())
+            .collect();
+        self.messages
+            .iter()
+            .flat_map(|m| m.content.iter())
+            .filter_map(|c| c.as_tool_request())
+            .filter(|r| !answered.contains(r.id.as_str()))
+            .collect()
+    }
+
+    /// Usage attributed to this session, across restarts when usage history is enabled
+    ///
+    /// For a branch this only covers requests made after the fork; the shared history was
+    /// paid for by the parent.
+    pub fn usage(&self) -> UsageSummary {
+        session_usage(self.id())
+    }
+
+    /// Usage of this session together with every branch forked from it, recursively
+    pub fn tree_usage(&self) -> Result<UsageSummary> {
+        let mut summary = UsageSummary::default();
+        let mut pending = vec![self.id().to_string()];
+        while let Some(id) = pending.pop() {
+            summary.merge(&session_usage(&id));
+            pending.extend(self.store.branches(&id)?.into_iter().map(|b| b.id));
+        }
+        Ok(summary)
+    }
+}
+
+fn session_usage(id: &str) -> UsageSummary {
+    let tracker = UsageTracker::global();
+    let filter = UsageFilter {
+        session_id: Some(id.to_string()),
+        ..Default::default()
+    };
+    match tracker.store().map(|store| store.totals(&filter)) {
+        Some(Ok(summary)) => summary,
+        Some(Err(e)) => {
+            tracing::warn!("Failed to read usage history: {}", e);
+            tracker.totals(&filter)
+        }
+        None => tracker.totals(&filter),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::content::Content;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    fn store() -> Arc<SessionStore> {
+        Arc::new(SessionStore::open_in_memory().unwrap())
+    }
+
+    #[test]
+    fn test_resume_restores_messages() -> Result<()> {
+        let store = store();
+        let mut session = Session::create_in(store.clone(), "session", "openai", "gpt-4o")?;
+        session.push(Message::user().with_text("List the files"))?;
+        session.push(Message::assistant().with_tool_request(
+            "call_1",
+            Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+        ))?;
+        session.push(Message::user().with_tool_response("call_1", Ok(vec![Content::text("a")])))?;
+        session.push(Message::assistant().with_text("There is one file"))?;
+
+        let resumed = Session::resume_in(store, "session")?;
+        assert_eq!(resumed.messages(), session.messages());
+        assert_eq!(resumed.metadata().message_count, 4);
+        assert_eq!(resumed.metadata().provider, "openai");
+        assert!(resumed.pending_tool_requests().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume_with_pending_tool_calls() -> Result<()> {
+        let store = store();
+        let mut session = Session::create_in(store.clone(), "session", "openai", "gpt-4o")?;
+        session.push(Message::user().with_text("Check both"))?;
+        session.push(
+            Message::assistant()
+                .with_tool_request("call_1", Ok(ToolCall::new("a", json!({}))))
+                .with_tool_request("call_2", Ok(ToolCall::new("b", json!({})))),
+        )?;
+        session.push(Message::user().with_tool_response("call_1", Ok(vec![])))?;
+        drop(session);
+
+        let resumed = Session::resume_in(store, "session")?;
+        let pending = resumed.pending_tool_requests();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, "call_2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_and_set_model() -> Result<()> {
+        let store = store();
+        let mut session = Session::create_in(store.clone(), "session", "openai", "gpt-4o")?;
+        session.push(Message::user().with_text("one"))?;
+        session.push(Message::user().with_text("two"))?;
+        session.truncate(1)?;
+        session.set_model("anthropic", "claude-3-5-sonnet-latest")?;
+        // Pushing after a truncate reuses the dropped position
+        session.push(Message::user().with_text("three"))?;
+
+        let resumed = Session::resume_in(store, "session")?;
+        let texts: Vec<String> = resumed
+            .messages()
+            .iter()
+            .map(|m| m.as_concat_text())
+            .collect();
+        assert_eq!(texts, vec!["one", "three"]);
+        assert_eq!(resumed.metadata().model, "claude-3-5-sonnet-latest");
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_pinned() -> Result<()> {
+        let store = store();
+        let mut session = Session::create_in(store.clone(), "main", "openai", "gpt-4o")?;
+        session.push(Message::user().with_text("Always answer in French"))?;
+        session.push(Message::assistant().with_text("D'accord"))?;
+        session.set_pinned(0, true)?;
+        assert!(session.set_pinned(2, true).is_err());
+
+        let resumed = Session::resume_in(store, "main")?;
+        assert!(resumed.messages()[0].pinned);
+        assert!(!resumed.messages()[1].pinned);
+
+        // The parent's messages can't be changed from a branch
+        let mut branch = session.fork("branch", 1)?;
+        assert!(branch.set_pinned(0, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fork() -> Result<()> {
+        let store = store();
+        let mut session = Session::create_in(store.clone(), "main", "openai", "gpt-4o")?;
+        session.push(Message::user().with_text("one"))?;
+        session.push(Message::assistant().with_text("two"))?;
+
+        let mut branch = session.fork("branch", 1)?;
+        branch.push(Message::assistant().with_text("another two"))?;
+        assert_eq!(branch.metadata().parent.as_deref(), Some("main"));
+        assert_eq!(branch.metadata().model, "gpt-4o");
+        assert_eq!(branch.messages()[0], session.messages()[0]);
+        assert_eq!(branch.messages()[1].as_concat_text(), "another two");
+        assert_eq!(session.messages()[1].as_concat_text(), "two");
+        assert_eq!(session.branches()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo() -> Result<()> {
+        let store = store();
+        let mut session = Session::create_in(store.clone(), "session", "openai", "gpt-4o")?;
+        assert!(session.undo()?.is_none());
+
+        for turn in ["one", "two"] {
+            session.checkpoint(Some(turn))?;
+            session.push(Message::user().with_text(turn))?;
+            session.push(
+                Message::assistant()
+                    .with_tool_request(turn, Ok(ToolCall::new("developer__shell", json!({})))),
+            )?;
+        }
+        let checkpoints = session.checkpoints()?;
+        assert_eq!(checkpoints[1].message_count, 2);
+        assert_eq!(checkpoints[1].pending_tool_calls, vec!["one"]);
+
+        let undone = session.undo()?.unwrap();
+        assert_eq!(undone.label.as_deref(), Some("two"));
+        assert_eq!(session.messages().len(), 2);
+        assert_eq!(Session::resume_in(store, "session")?.messages().len(), 2);
+
+        session.undo()?;
+        assert!(session.messages().is_empty());
+        assert_eq!(session.checkpoints()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume_missing_session() {
+        let result = Session::resume_in(store(), "missing");
+        assert!(result.is_err());
+    }
+}
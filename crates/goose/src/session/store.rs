@@ -0,0 +1,701 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use once_cell::sync::OnceCell;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::agents::Plan;
+use crate::config::APP_STRATEGY;
+use crate::message::Message;
+use crate::usage::UsageSummary;
+
+static GLOBAL_STORE: OnceCell<Arc<SessionStore>> = OnceCell::new();
+
+/// Everything about a session except its messages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionMetadata {
+    pub id: String,
+    pub provider: String,
+    pub model: String,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub message_count: usize,
+    /// The session this one was forked from
+    pub parent: Option<String>,
+    /// How many messages of the parent the branch starts with
+    pub fork_point: Option<usize>,
+}
+
+/// The state of a session at a turn boundary, which the session can be rolled back to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Numbered from 1 within the session
+    pub id: usize,
+    pub label: Option<String>,
+    pub created: DateTime<Utc>,
+    /// The length of the conversation at the checkpoint
+    pub message_count: usize,
+    /// Ids of the tool requests that had no response yet
+    pub pending_tool_calls: Vec<String>,
+    pub plan: Option<Plan>,
+    /// The session's usage so far; rolling back can't undo what later turns cost
+    pub usage: UsageSummary,
+}
+
+/// Sessions persisted in a local SQLite database
+///
+/// Every message is stored as its own row, keyed by its position in the conversation, so
+/// appending is cheap and a session can be restored exactly as it was left.
+///
+/// A branch forked from another session only stores the messages added after the fork; the
+/// ones before it are read from its parent, so forking is cheap however long the history is.
+pub struct SessionStore {
+    conn: Mutex<Connection>,
+    path: Option<PathBuf>,
+}
+
+impl SessionStore {
+    /// Open (or create) the session database at the given path
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut store = Self::init(Connection::open(&path)?)?;
+        store.path = Some(path.as_ref().to_path_buf());
+        Ok(store)
+    }
+
+    /// Open a database that only lives as long as the store, mostly useful for testing
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    /// The default location of the session database, which needs a home dir
+    ///
+    /// - macOS/Linux: ~/.local/share/goose/sessions.db
+    /// - Windows:     ~\AppData\Roaming\Block\goose\data\sessions.db
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(choose_app_strategy(APP_STRATEGY.clone())?.in_data_dir("sessions.db"))
+    }
+
+    /// The process wide store at the default path, opened on first use
+    pub fn global() -> Result<Arc<SessionStore>> {
+        GLOBAL_STORE
+            .get_or_try_init(|| Self::open(Self::default_path()?).map(Arc::new))
+            .cloned()
+    }
+
+    /// Where the database lives, None for an in-memory store
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "PRAGMA foreign_keys = ON;
+            CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                created INTEGER NOT NULL,
+                updated INTEGER NOT NULL,
+                parent_id TEXT,
+                fork_point INTEGER,
+                plan TEXT
+            );
+            CREATE TABLE IF NOT EXISTS messages (
+                session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+                seq INTEGER NOT NULL,
+                message TEXT NOT NULL,
+                PRIMARY KEY (session_id, seq)
+            );
+            CREATE TABLE IF NOT EXISTS checkpoints (
+                session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+                id INTEGER NOT NULL,
+                message_count INTEGER NOT NULL,
+                checkpoint TEXT NOT NULL,
+                PRIMARY KEY (session_id, id)
+            );",
+        )?;
+
+        // Databases created before sessions could be forked or planned lack these columns
+        let columns: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('sessions')")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        for (column, kind) in [
+            ("parent_id", "TEXT"),
+            ("fork_point", "INTEGER"),
+            ("plan", "TEXT"),
+        ] {
+            if !columns.iter().any(|c| c == column) {
+                conn.execute(
+                    &format!("ALTER TABLE sessions ADD COLUMN {} {}", column, kind),
+                    [],
+                )?;
+            }
+        }
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS sessions_parent ON sessions (parent_id)",
+            [],
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            path: None,
+        })
+    }
+
+    /// Create an empty session, failing if one with the same id exists
+    pub fn create(&self, id: &str, provider: &str, model: &str) -> Result<SessionMetadata> {
+        let now = Utc::now();
+        let conn = self.conn.lock().unwrap();
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sessions WHERE id = ?1)",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Err(anyhow::anyhow!("Session {} already exists", id));
+        }
+
+        conn.execute(
+            "INSERT INTO sessions (id, provider, model, created, updated)
+            VALUES (?1, ?2, ?3, ?4, ?4)",
+            params![id, provider, model, now.timestamp_millis()],
+        )?;
+        Ok(SessionMetadata {
+            id: id.to_string(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            created: now,
+            updated: now,
+            message_count: 0,
+            parent: None,
+            fork_point: None,
+        })
+    }
+
+    /// Start a new session that shares the first `at` messages of an existing one
+    pub fn fork(&self, id: &str, at: usize, new_id: &str) -> Result<SessionMetadata> {
+        let source = self
+            .metadata(id)?
+            .ok_or_else(|| anyhow::anyhow!("No session named {}", id))?;
+        if at > source.message_count {
+            return Err(anyhow::anyhow!(
+                "Cannot fork session {} at message {}, it only has {}",
+                id,
+                at,
+                source.message_count
+            ));
+        }
+
+        let now = Utc::now();
+        let conn = self.conn.lock().unwrap();
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sessions WHERE id = ?1)",
+            params![new_id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Err(anyhow::anyhow!("Session {} already exists", new_id));
+        }
+
+        conn.execute(
+            "INSERT INTO sessions (id, provider, model, created, updated, parent_id, fork_point)
+            VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6)",
+            params![
+                new_id,
+                source.provider,
+                source.model,
+                now.timestamp_millis(),
+                id,
+                at as i64
+            ],
+        )?;
+        Ok(SessionMetadata {
+            id: new_id.to_string(),
+            provider: source.provider,
+            model: source.model,
+            created: now,
+            updated: now,
+            message_count: at,
+            parent: Some(id.to_string()),
+            fork_point: Some(at),
+        })
+    }
+
+    /// The sessions forked directly from a session, oldest first
+    pub fn branches(&self, id: &str) -> Result<Vec<SessionMetadata>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE s.parent_id = ?1 ORDER BY s.created, s.rowid",
+            METADATA_QUERY
+        ))?;
+        let branches = stmt
+            .query_map(params![id], metadata_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(branches)
+    }
+
+    /// The metadata of a session, None if it doesn't exist
+    pub fn metadata(&self, id: &str) -> Result<Option<SessionMetadata>> {
+        let conn = self.conn.lock().unwrap();
+        let metadata = conn
+            .query_row(
+                &format!("{} WHERE s.id = ?1", METADATA_QUERY),
+                params![id],
+                metadata_from_row,
+            )
+            .optional()?;
+        Ok(metadata)
+    }
+
+    /// All sessions, most recently updated first
+    pub fn list(&self) -> Result<Vec<SessionMetadata>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "{} ORDER BY s.updated DESC, s.rowid DESC",
+            METADATA_QUERY
+        ))?;
+        let sessions = stmt
+            .query_map([], metadata_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(sessions)
+    }
+
+    /// The messages of a session in order, including those shared with the sessions it was
+    /// forked from
+    pub fn messages(&self, id: &str) -> Result<Vec<Message>> {
+        let conn = self.conn.lock().unwrap();
+
+        // Walk up to the root, noting how much of each ancestor the branch below it shares
+        let mut segments = vec![(id.to_string(), i64::MAX)];
+        let mut current = id.to_string();
+        while let Some((parent, fork_point)) = conn
+            .query_row(
+                "SELECT parent_id, fork_point FROM sessions WHERE id = ?1",
+                params![current],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, Option<i64>>(1)?,
+                    ))
+                },
+            )
+            .optional()?
+            .and_then(|(parent, fork_point)| Some((parent?, fork_point.unwrap_or(0))))
+        {
+            if segments.iter().any(|(id, _)| *id == parent) {
+                return Err(anyhow::anyhow!("Session {} is forked from itself", id));
+            }
+            segments.push((parent.clone(), fork_point));
+            current = parent;
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT message FROM messages WHERE session_id = ?1 AND seq < ?2 ORDER BY seq",
+        )?;
+        let mut messages = Vec::new();
+        for (session_id, limit) in segments.iter().rev() {
+            let rows = stmt
+                .query_map(params![session_id, limit], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            for json in rows {
+                messages.push(serde_json::from_str(&json)?);
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Store a message at the given position, replacing whatever was there
+    pub fn append(&self, id: &str, seq: usize, message: &Message) -> Result<()> {
+        let json = serde_json::to_string(message)?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO messages (session_id, seq, message) VALUES (?1, ?2, ?3)",
+            params![id, seq as i64, json],
+        )?;
+        touch(&conn, id)
+    }
+
+    /// Drop every message from position `len` onwards
+    ///
+    /// Truncating a branch to before its fork point moves the fork point back, leaving the
+    /// parent untouched. Messages shared with a branch can't be dropped.
+    pub fn truncate(&self, id: &str, len: usize) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let shared: Option<String> = conn
+            .query_row(
+                "SELECT id FROM sessions WHERE parent_id = ?1 AND fork_point > ?2 LIMIT 1",
+                params![id, len as i64],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(branch) = shared {
+            return Err(anyhow::anyhow!(
+                "Cannot truncate session {} to {} messages, branch {} shares them",
+                id,
+                len,
+                branch
+            ));
+        }
+
+        conn.execute(
+            "DELETE FROM messages WHERE session_id = ?1 AND seq >= ?2",
+            params![id, len as i64],
+        )?;
+        conn.execute(
+            "UPDATE sessions SET fork_point = ?2 WHERE id = ?1 AND fork_point > ?2",
+            params![id, len as i64],
+        )?;
+        // Checkpoints past the end would restore messages that are gone
+        conn.execute(
+            "DELETE FROM checkpoints WHERE session_id = ?1 AND message_count > ?2",
+            params![id, len as i64],
+        )?;
+        touch(&conn, id)
+    }
+
+    /// Store a checkpoint of a session, numbering it after the session's last one
+    pub fn save_checkpoint(&self, id: &str, mut checkpoint: Checkpoint) -> Result<Checkpoint> {
+        let conn = self.conn.lock().unwrap();
+        let last: i64 = conn.query_row(
+            "SELECT COALESCE(MAX(id), 0) FROM checkpoints WHERE session_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        checkpoint.id = last as usize + 1;
+        conn.execute(
+            "INSERT INTO checkpoints (session_id, id, message_count, checkpoint)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![
+                id,
+                checkpoint.id as i64,
+                checkpoint.message_count as i64,
+                serde_json::to_string(&checkpoint)?
+            ],
+        )?;
+        Ok(checkpoint)
+    }
+
+    /// The checkpoints of a session, oldest first
+    ///
+    /// Checkpoints belong to the session they were taken in, so a branch starts without any.
+    pub fn checkpoints(&self, id: &str) -> Result<Vec<Checkpoint>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT checkpoint FROM checkpoints WHERE session_id = ?1 ORDER BY id")?;
+        let rows = stmt
+            .query_map(params![id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows
+            .iter()
+            .map(|json| serde_json::from_str(json))
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Restore a session to one of its checkpoints, dropping the messages and checkpoints
+    /// that came after it
+    pub fn rollback(&self, id: &str, checkpoint: usize) -> Result<Checkpoint> {
+        let target = self
+            .checkpoints(id)?
+            .into_iter()
+            .find(|c| c.id == checkpoint)
+            .ok_or_else(|| anyhow::anyhow!("Session {} has no checkpoint {}", id, checkpoint))?;
+
+        self.truncate(id, target.message_count)?;
+        self.set_plan(id, target.plan.as_ref())?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM checkpoints WHERE session_id = ?1 AND id > ?2",
+            params![id, checkpoint as i64],
+        )?;
+        Ok(target)
+    }
+
+    /// Record that the session continues with a different provider or model
+    pub fn set_model(&self, id: &str, provider: &str, model: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sessions SET provider = ?2, model = ?3 WHERE id = ?1",
+            params![id, provider, model],
+        )?;
+        touch(&conn, id)
+    }
+
+    /// Record the plan the session is following, or that it has none
+    pub fn set_plan(&self, id: &str, plan: Option<&Plan>) -> Result<()> {
+        let plan = plan.map(serde_json::to_string).transpose()?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sessions SET plan = ?2 WHERE id = ?1",
+            params![id, plan],
+        )?;
+        touch(&conn, id)
+    }
+
+    /// The plan of a session, None if it has none
+    pub fn plan(&self, id: &str) -> Result<Option<Plan>> {
+        let conn = self.conn.lock().unwrap();
+        let plan: Option<String> = conn
+            .query_row(
+                "SELECT plan FROM sessions WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(plan.map(|plan| serde_json::from_str(&plan)).transpose()?)
+    }
+
+    /// Remove a session and all of its messages
+    ///
+    /// Sessions that other sessions were forked from can't be deleted, as the branches still
+    /// share their messages.
+    pub fn delete(&self, id: &str) -> Result<()> {
+        let branches = self.branches(id)?;
+        if !branches.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Cannot delete session {}, it has {} branch(es)",
+                id,
+                branches.len()
+            ));
+        }
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+}
+
+// A branch only stores messages from its fork point on, so those before it are counted too
+const METADATA_QUERY: &str = "SELECT s.id, s.provider, s.model, s.created, s.updated,
+        COALESCE(s.fork_point, 0) + (SELECT COUNT(*) FROM messages m WHERE m.session_id = s.id),
+        s.parent_id, s.fork_point
+    FROM sessions s";
+
+fn metadata_from_row(row: &Row) -> rusqlite::Result<SessionMetadata> {
+    Ok(SessionMetadata {
+        id: row.get(0)?,
+        provider: row.get(1)?,
+        model: row.get(2)?,
+        created: DateTime::from_timestamp_millis(row.get(3)?).unwrap_or_default(),
+        updated: DateTime::from_timestamp_millis(row.get(4)?).unwrap_or_default(),
+        message_count: row.get::<_, i64>(5)? as usize,
+        parent: row.get(6)?,
+        fork_point: row.get::<_, Option<i64>>(7)?.map(|n| n as usize),
+    })
+}
+
+fn touch(conn: &Connection, id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE sessions SET updated = ?2 WHERE id = ?1",
+        params![id, Utc::now().timestamp_millis()],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::plan::{PlanStatus, PlanStep};
+
+    #[test]
+    fn test_create_and_list() -> Result<()> {
+        let store = SessionStore::open_in_memory()?;
+        store.create("first", "openai", "gpt-4o")?;
+        store.create("second", "anthropic", "claude-3-5-sonnet-latest")?;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        store.append("first", 0, &Message::user().with_text("Hello"))?;
+
+        let sessions = store.list()?;
+        assert_eq!(sessions.len(), 2);
+        // Appending to the first session makes it the most recent
+        assert_eq!(sessions[0].id, "first");
+        assert_eq!(sessions[0].message_count, 1);
+        assert_eq!(sessions[1].model, "claude-3-5-sonnet-latest");
+
+        assert!(store.create("first", "openai", "gpt-4o").is_err());
+        assert!(store.metadata("missing")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_and_delete() -> Result<()> {
+        let store = SessionStore::open_in_memory()?;
+        store.create("session", "openai", "gpt-4o")?;
+        for (i, text) in ["one", "two", "three"].iter().enumerate() {
+            store.append("session", i, &Message::user().with_text(*text))?;
+        }
+
+        store.truncate("session", 1)?;
+        let messages = store.messages("session")?;
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].as_concat_text(), "one");
+
+        store.delete("session")?;
+        assert!(store.metadata("session")?.is_none());
+        assert!(store.messages("session")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fork_shares_history() -> Result<()> {
+        let store = SessionStore::open_in_memory()?;
+        store.create("main", "openai", "gpt-4o")?;
+        for (i, text) in ["one", "two", "three"].iter().enumerate() {
+            store.append("main", i, &Message::user().with_text(*text))?;
+        }
+
+        let branch = store.fork("main", 2, "branch")?;
+        assert_eq!(branch.parent.as_deref(), Some("main"));
+        assert_eq!(branch.message_count, 2);
+        store.append("branch", 2, &Message::user().with_text("other"))?;
+        // A branch of a branch reads through both parents
+        store.fork("branch", 3, "nested")?;
+        store.append("nested", 3, &Message::user().with_text("deeper"))?;
+
+        let texts = |id: &str| -> Result<Vec<String>> {
+            Ok(store
+                .messages(id)?
+                .iter()
+                .map(|m| m.as_concat_text())
+                .collect())
+        };
+        assert_eq!(texts("main")?, vec!["one", "two", "three"]);
+        assert_eq!(texts("branch")?, vec!["one", "two", "other"]);
+        assert_eq!(texts("nested")?, vec!["one", "two", "other", "deeper"]);
+        assert_eq!(store.metadata("nested")?.unwrap().message_count, 4);
+
+        // Only the forked messages are stored again
+        let conn = store.conn.lock().unwrap();
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM messages", [], |row| row.get(0))?;
+        assert_eq!(rows, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fork_protects_shared_messages() -> Result<()> {
+        let store = SessionStore::open_in_memory()?;
+        store.create("main", "openai", "gpt-4o")?;
+        for (i, text) in ["one", "two"].iter().enumerate() {
+            store.append("main", i, &Message::user().with_text(*text))?;
+        }
+        assert!(store.fork("main", 3, "too-far").is_err());
+        store.fork("main", 1, "branch")?;
+
+        assert!(store.truncate("main", 0).is_err());
+        assert!(store.delete("main").is_err());
+        store.truncate("main", 1)?;
+
+        // Truncating the branch before its fork point moves the fork point instead
+        store.truncate("branch", 0)?;
+        let branch = store.metadata("branch")?.unwrap();
+        assert_eq!(branch.fork_point, Some(0));
+        assert_eq!(branch.message_count, 0);
+        assert_eq!(store.branches("main")?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrates_sessions_without_branches() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("sessions.db");
+        Connection::open(&path)?.execute_batch(
+            "CREATE TABLE sessions (
+                id TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                created INTEGER NOT NULL,
+                updated INTEGER NOT NULL
+            );
+            INSERT INTO sessions VALUES ('old', 'openai', 'gpt-4o', 0, 0);",
+        )?;
+
+        let store = SessionStore::open(&path)?;
+        let old = store.metadata("old")?.unwrap();
+        assert_eq!(old.parent, None);
+        assert_eq!(store.plan("old")?, None);
+        store.fork("old", 0, "branch")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_plan() -> Result<()> {
+        let store = SessionStore::open_in_memory()?;
+        store.create("session", "openai", "gpt-4o")?;
+        let mut plan = Plan::new(vec![PlanStep {
+            description: "list the files".to_string(),
+        }]);
+        plan.status = PlanStatus::Approved;
+
+        store.set_plan("session", Some(&plan))?;
+        assert_eq!(store.plan("session")?, Some(plan));
+        store.set_plan("session", None)?;
+        assert_eq!(store.plan("session")?, None);
+        Ok(())
+    }
+
+    fn checkpoint(message_count: usize) -> Checkpoint {
+        Checkpoint {
+            id: 0,
+            label: None,
+            created: Utc::now(),
+            message_count,
+            pending_tool_calls: Vec::new(),
+            plan: None,
+            usage: UsageSummary::default(),
+        }
+    }
+
+    #[test]
+    fn test_rollback() -> Result<()> {
+        let store = SessionStore::open_in_memory()?;
+        store.create("session", "openai", "gpt-4o")?;
+        let plan = Plan::new(vec![PlanStep {
+            description: "list the files".to_string(),
+        }]);
+        for (i, text) in ["one", "two", "three", "four"].iter().enumerate() {
+            let saved = store.save_checkpoint(
+                "session",
+                Checkpoint {
+                    plan: Some(plan.clone()),
+                    ..checkpoint(i)
+                },
+            )?;
+            assert_eq!(saved.id, i + 1);
+            store.append("session", i, &Message::user().with_text(*text))?;
+        }
+        store.set_plan("session", None)?;
+
+        let target = store.rollback("session", 2)?;
+        assert_eq!(target.message_count, 1);
+        assert_eq!(store.messages("session")?.len(), 1);
+        assert_eq!(store.plan("session")?, Some(plan));
+        assert_eq!(store.checkpoints("session")?.len(), 2);
+        assert!(store.rollback("session", 3).is_err());
+
+        // Truncating drops the checkpoints it passes
+        store.truncate("session", 0)?;
+        assert_eq!(store.checkpoints("session")?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_persists_across_connections() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("sessions.db");
+        {
+            let store = SessionStore::open(&path)?;
+            store.create("session", "openai", "gpt-4o")?;
+            store.append("session", 0, &Message::user().with_text("Hello"))?;
+        }
+
+        let store = SessionStore::open(&path)?;
+        assert_eq!(store.path(), Some(path.as_path()));
+        assert_eq!(store.messages("session")?.len(), 1);
+        Ok(())
+    }
+}
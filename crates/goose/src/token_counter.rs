@@ -1,19 +1,46 @@
 use include_dir::{include_dir, Dir};
 use mcp_core::Tool;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use tokenizers::tokenizer::Tokenizer;
 
 use crate::message::Message;
+use crate::model::ModelConfig;
 
 // The embedded directory with all possible tokenizer files.
 // If one of them doesn’t exist, we’ll download it at startup.
 static TOKENIZER_FILES: Dir = include_dir!("$CARGO_MANIFEST_DIR/../../tokenizer_files");
 
-/// The `TokenCounter` now stores exactly one `Tokenizer`.
+// Rough characters-per-token ratio used when no real tokenizer is available.
+const APPROXIMATE_CHARS_PER_TOKEN: usize = 4;
+
+// Counters are expensive to build (the HF tokenizer files are several MB), so we keep
+// one per model around for the lifetime of the process.
+static COUNTERS: Lazy<Mutex<HashMap<String, Arc<TokenCounter>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn approximate_count(text: &str) -> usize {
+    text.chars().count().div_ceil(APPROXIMATE_CHARS_PER_TOKEN)
+}
+
+/// The tokenizer implementation backing a `TokenCounter`.
+enum Backend {
+    /// A HuggingFace `tokenizer.json`, embedded or downloaded.
+    HuggingFace(Box<Tokenizer>),
+    /// A tiktoken BPE, available for OpenAI models when the `tiktoken` feature is enabled.
+    #[cfg(feature = "tiktoken")]
+    Tiktoken(tiktoken_rs::CoreBPE),
+    /// A character-based estimate used when no tokenizer could be loaded.
+    Approximate,
+}
+
+/// The `TokenCounter` now stores exactly one tokenizer backend.
 pub struct TokenCounter {
-    tokenizer: Tokenizer,
+    backend: Backend,
 }
 
 impl TokenCounter {
@@ -23,7 +50,7 @@ impl TokenCounter {
     ///   or "Qwen--Qwen2.5-Coder-32B-Instruct", etc.
     pub fn new(tokenizer_name: &str) -> Self {
         match Self::load_from_embedded(tokenizer_name) {
-            Ok(tokenizer) => Self { tokenizer },
+            Ok(tokenizer) => Self::from_tokenizer(tokenizer),
             Err(e) => {
                 println!(
                     "Tokenizer '{}' not found in embedded dir: {}",
@@ -39,6 +66,73 @@ impl TokenCounter {
         }
     }
 
+    /// Returns the shared `TokenCounter` for a model, building it on first use.
+    ///
+    /// OpenAI models use tiktoken when the `tiktoken` feature is enabled; everything else
+    /// uses the HuggingFace tokenizer named in the config. Unlike `new`, this never panics:
+    /// if no tokenizer can be loaded we fall back to an approximate count so callers can
+    /// still make truncation and cost decisions offline.
+    pub fn for_model(model: &ModelConfig) -> Arc<TokenCounter> {
+        if let Some(counter) = COUNTERS.lock().unwrap().get(&model.model_name) {
+            return Arc::clone(counter);
+        }
+
+        // Building may download a tokenizer, so it happens without holding the lock; if
+        // another thread built one for the same model meanwhile, theirs is kept
+        let counter = Arc::new(Self::build_for_model(model));
+        COUNTERS
+            .lock()
+            .unwrap()
+            .entry(model.model_name.clone())
+            .or_insert(counter)
+            .clone()
+    }
+
+    /// A counter that estimates tokens from character counts.
+    pub fn approximate() -> Self {
+        Self {
+            backend: Backend::Approximate,
+        }
+    }
+
+    /// Whether this counter is estimating rather than using a real tokenizer.
+    pub fn is_approximate(&self) -> bool {
+        matches!(self.backend, Backend::Approximate)
+    }
+
+    fn from_tokenizer(tokenizer: Tokenizer) -> Self {
+        Self {
+            backend: Backend::HuggingFace(Box::new(tokenizer)),
+        }
+    }
+
+    fn build_for_model(model: &ModelConfig) -> Self {
+        #[cfg(feature = "tiktoken")]
+        if let Ok(bpe) = tiktoken_rs::get_bpe_from_model(&model.model_name) {
+            return Self {
+                backend: Backend::Tiktoken(bpe),
+            };
+        }
+
+        let tokenizer_name = model.tokenizer_name();
+        if let Ok(tokenizer) = Self::load_from_embedded(tokenizer_name) {
+            return Self::from_tokenizer(tokenizer);
+        }
+
+        match Self::download_and_load(tokenizer_name) {
+            Ok(counter) => counter,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to load tokenizer '{}' for model '{}', using approximate counts: {}",
+                    tokenizer_name,
+                    model.model_name,
+                    e
+                );
+                Self::approximate()
+            }
+        }
+    }
+
     /// Load tokenizer bytes from the embedded directory (via `include_dir!`).
     fn load_from_embedded(tokenizer_name: &str) -> Result<Tokenizer, Box<dyn Error>> {
         let tokenizer_file_path = format!("{}/tokenizer.json", tokenizer_name);
@@ -75,7 +169,7 @@ impl TokenCounter {
         let tokenizer = Tokenizer::from_bytes(&file_content)
             .map_err(|e| format!("Failed to parse tokenizer after download: {}", e))?;
 
-        Ok(Self { tokenizer })
+        Ok(Self::from_tokenizer(tokenizer))
     }
 
     /// Download from Hugging Face into the local directory if not already present.
@@ -108,8 +202,18 @@ impl TokenCounter {
 
     /// Count tokens for a piece of text using our single tokenizer.
     pub fn count_tokens(&self, text: &str) -> usize {
-        let encoding = self.tokenizer.encode(text, false).unwrap();
-        encoding.len()
+        match &self.backend {
+            Backend::HuggingFace(tokenizer) => match tokenizer.encode(text, false) {
+                Ok(encoding) => encoding.len(),
+                Err(e) => {
+                    tracing::warn!("Failed to tokenize text, using an approximate count: {}", e);
+                    approximate_count(text)
+                }
+            },
+            #[cfg(feature = "tiktoken")]
+            Backend::Tiktoken(bpe) => bpe.encode_with_special_tokens(text).len(),
+            Backend::Approximate => approximate_count(text),
+        }
     }
 
     pub fn count_tokens_for_tools(&self, tools: &[Tool]) -> usize {
@@ -272,25 +376,25 @@ mod tests {
             "You are a helpful assistant that can answer questions about the weather.";
 
         let messages = vec![
-            Message {
-                role: Role::User,
-                created: 0,
-                content: vec![MessageContent::text(
+            Message::new(
+                Role::User,
+                0,
+                vec![MessageContent::text(
                     "What's the weather like in San Francisco?",
                 )],
-            },
-            Message {
-                role: Role::Assistant,
-                created: 1,
-                content: vec![MessageContent::text(
+            ),
+            Message::new(
+                Role::Assistant,
+                1,
+                vec![MessageContent::text(
                     "Looks like it's 60 degrees Fahrenheit in San Francisco.",
                 )],
-            },
-            Message {
-                role: Role::User,
-                created: 2,
-                content: vec![MessageContent::text("How about New York?")],
-            },
+            ),
+            Message::new(
+                Role::User,
+                2,
+                vec![MessageContent::text("How about New York?")],
+            ),
         ];
 
         let tools = vec![Tool {
@@ -332,6 +436,33 @@ mod tests {
         TokenCounter::new("nonexistent-tokenizer");
     }
 
+    #[test]
+    fn test_approximate_counter() {
+        let counter = TokenCounter::approximate();
+        assert!(counter.is_approximate());
+
+        assert_eq!(counter.count_tokens(""), 0);
+        assert_eq!(counter.count_tokens("abcd"), 1);
+        assert_eq!(counter.count_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_for_model_is_cached_per_model() {
+        let config = ModelConfig::new("gpt-4o".to_string());
+        let first = TokenCounter::for_model(&config);
+        let second = TokenCounter::for_model(&config);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(first.count_tokens("Hey there!") > 0);
+    }
+
+    #[cfg(feature = "tiktoken")]
+    #[test]
+    fn test_tiktoken_for_openai_models() {
+        let counter = TokenCounter::for_model(&ModelConfig::new("gpt-4o".to_string()));
+        assert!(!counter.is_approximate());
+        assert_eq!(counter.count_tokens("Hey there!"), 3);
+    }
+
     // Optional test to confirm that fallback download works if not found in embedded:
     // Ignored cause this actually downloads a tokenizer from Hugging Face
     #[test]
@@ -1,9 +1,25 @@
-use crate::message::Message;
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
 use anyhow::{anyhow, Result};
+use mcp_core::content::Content;
 use mcp_core::Role;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use tracing::debug;
 
+/// Default number of messages kept by `SlidingWindowTruncation`
+const DEFAULT_WINDOW_SIZE: usize = 50;
+
+/// Default number of recent messages `ElideToolResultsTruncation` never touches
+const DEFAULT_KEEP_RECENT: usize = 4;
+
+/// What an elided tool result is replaced with
+const ELIDED_TOOL_RESULT: &str = "[tool result elided to fit the context window]";
+
+/// A conservative estimate of the tokens an elided tool result message takes up
+const ELIDED_TOOL_RESULT_TOKENS: usize = 16;
+
 /// Trait representing a truncation strategy
 pub trait TruncationStrategy {
     /// Determines the indices of messages to remove to fit within the context limit.
@@ -19,6 +35,12 @@ pub trait TruncationStrategy {
         token_counts: &[usize],
         context_limit: usize,
     ) -> Result<HashSet<usize>>;
+
+    /// The tool result messages whose content can be elided, in the order to elide them,
+    /// before any messages are removed. Nothing is elided by default.
+    fn elision_candidates(&self, _messages: &[Message]) -> Vec<usize> {
+        Vec::new()
+    }
 }
 
 /// Strategy to truncate messages by removing the oldest first
@@ -41,6 +63,9 @@ impl TruncationStrategy for OldestFirstTruncation {
             if total_tokens <= context_limit {
                 break;
             }
+            if !is_removable(messages, i) {
+                continue;
+            }
 
             // Remove the message
             indices_to_remove.insert(i);
@@ -75,13 +100,270 @@ impl TruncationStrategy for OldestFirstTruncation {
     }
 }
 
+/// Strategy that keeps at most `max_messages` of the most recent messages, then drops
+/// the oldest of those until the rest fit
+pub struct SlidingWindowTruncation {
+    pub max_messages: usize,
+}
+
+impl Default for SlidingWindowTruncation {
+    fn default() -> Self {
+        Self {
+            max_messages: DEFAULT_WINDOW_SIZE,
+        }
+    }
+}
+
+impl TruncationStrategy for SlidingWindowTruncation {
+    fn determine_indices_to_remove(
+        &self,
+        messages: &[Message],
+        token_counts: &[usize],
+        context_limit: usize,
+    ) -> Result<HashSet<usize>> {
+        let mut indices_to_remove = HashSet::new();
+        for i in 0..messages.len().saturating_sub(self.max_messages) {
+            if is_removable(messages, i) {
+                indices_to_remove.extend(with_tool_pair(messages, i));
+            }
+        }
+
+        remove_until_fits(
+            messages,
+            token_counts,
+            context_limit,
+            0..messages.len(),
+            &mut indices_to_remove,
+        );
+        Ok(indices_to_remove)
+    }
+}
+
+/// Strategy that always keeps the first `keep_first` messages (usually the original
+/// request) and drops the oldest messages after them
+pub struct KeepFirstAndLastTruncation {
+    pub keep_first: usize,
+}
+
+impl Default for KeepFirstAndLastTruncation {
+    fn default() -> Self {
+        Self { keep_first: 1 }
+    }
+}
+
+impl TruncationStrategy for KeepFirstAndLastTruncation {
+    fn determine_indices_to_remove(
+        &self,
+        messages: &[Message],
+        token_counts: &[usize],
+        context_limit: usize,
+    ) -> Result<HashSet<usize>> {
+        let mut indices_to_remove = HashSet::new();
+        remove_until_fits(
+            messages,
+            token_counts,
+            context_limit,
+            self.keep_first..messages.len(),
+            &mut indices_to_remove,
+        );
+        Ok(indices_to_remove)
+    }
+}
+
+/// Strategy that drops the oldest tool requests and results first, since those tend to be
+/// the largest messages, before falling back to dropping the oldest messages
+pub struct ToolResultsFirstTruncation;
+
+impl TruncationStrategy for ToolResultsFirstTruncation {
+    fn determine_indices_to_remove(
+        &self,
+        messages: &[Message],
+        token_counts: &[usize],
+        context_limit: usize,
+    ) -> Result<HashSet<usize>> {
+        let mut indices_to_remove = HashSet::new();
+        let tool_messages = messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.is_tool_call() || m.is_tool_response())
+            .map(|(i, _)| i);
+
+        remove_until_fits(
+            messages,
+            token_counts,
+            context_limit,
+            tool_messages,
+            &mut indices_to_remove,
+        );
+        remove_until_fits(
+            messages,
+            token_counts,
+            context_limit,
+            0..messages.len(),
+            &mut indices_to_remove,
+        );
+        Ok(indices_to_remove)
+    }
+}
+
+/// Strategy that first replaces the content of the oldest tool results with a placeholder,
+/// then drops the oldest turns, never touching the `keep_recent` most recent messages
+pub struct ElideToolResultsTruncation {
+    pub keep_recent: usize,
+}
+
+impl Default for ElideToolResultsTruncation {
+    fn default() -> Self {
+        Self {
+            keep_recent: DEFAULT_KEEP_RECENT,
+        }
+    }
+}
+
+impl TruncationStrategy for ElideToolResultsTruncation {
+    fn determine_indices_to_remove(
+        &self,
+        messages: &[Message],
+        token_counts: &[usize],
+        context_limit: usize,
+    ) -> Result<HashSet<usize>> {
+        let mut indices_to_remove = HashSet::new();
+        let recent = messages.len().saturating_sub(self.keep_recent);
+        let old = (0..recent).filter(|&i| with_tool_pair(messages, i).iter().all(|&j| j < recent));
+        remove_until_fits(
+            messages,
+            token_counts,
+            context_limit,
+            old,
+            &mut indices_to_remove,
+        );
+        Ok(indices_to_remove)
+    }
+
+    fn elision_candidates(&self, messages: &[Message]) -> Vec<usize> {
+        let recent = messages.len().saturating_sub(self.keep_recent);
+        (0..recent)
+            .filter(|&i| messages[i].is_tool_response())
+            .collect()
+    }
+}
+
+/// The built-in truncation strategies, selected per model or with `GOOSE_TRUNCATION_STRATEGY`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStrategyKind {
+    #[default]
+    OldestFirst,
+    SlidingWindow,
+    KeepFirstAndLast,
+    ToolResultsFirst,
+    ElideToolResults,
+}
+
+impl TruncationStrategyKind {
+    pub fn from_config() -> Self {
+        Config::global()
+            .get("GOOSE_TRUNCATION_STRATEGY")
+            .unwrap_or_default()
+    }
+
+    /// The strategy set on the model config, or the configured one if there is none
+    pub fn for_model(model: &ModelConfig) -> Self {
+        model.truncation_strategy.unwrap_or_else(Self::from_config)
+    }
+
+    pub fn strategy(&self) -> Box<dyn TruncationStrategy + Send + Sync> {
+        match self {
+            Self::OldestFirst => Box::new(OldestFirstTruncation),
+            Self::SlidingWindow => Box::new(SlidingWindowTruncation::default()),
+            Self::KeepFirstAndLast => Box::new(KeepFirstAndLastTruncation::default()),
+            Self::ToolResultsFirst => Box::new(ToolResultsFirstTruncation),
+            Self::ElideToolResults => Box::new(ElideToolResultsTruncation::default()),
+        }
+    }
+}
+
+/// The message at `index` along with the other half of any tool request/response pair in it
+pub(crate) fn with_tool_pair(messages: &[Message], index: usize) -> Vec<usize> {
+    let tool_ids = messages[index].get_tool_ids();
+    let mut indices = vec![index];
+    if !tool_ids.is_empty() {
+        for (i, message) in messages.iter().enumerate() {
+            if i != index
+                && message
+                    .get_tool_ids()
+                    .iter()
+                    .any(|id| tool_ids.contains(id))
+            {
+                indices.push(i);
+            }
+        }
+    }
+    indices
+}
+
+/// Whether the message at `index` can be removed, which it can't when it or the other half
+/// of its tool request/response pair is pinned
+fn is_removable(messages: &[Message], index: usize) -> bool {
+    with_tool_pair(messages, index)
+        .iter()
+        .all(|&i| !messages[i].pinned)
+}
+
+/// Replace the content of the tool results in the message with a placeholder
+fn elide_tool_results(message: &mut Message) {
+    for content in message.content.iter_mut() {
+        if let MessageContent::ToolResponse(response) = content {
+            if let Ok(result) = &mut response.tool_result {
+                *result = vec![Content::text(ELIDED_TOOL_RESULT)];
+            }
+        }
+    }
+}
+
+/// Marks candidates for removal, in order, until the remaining messages fit in the context
+/// limit. Tool request/response pairs are always removed together, and pinned messages
+/// are never removed.
+fn remove_until_fits(
+    messages: &[Message],
+    token_counts: &[usize],
+    context_limit: usize,
+    candidates: impl IntoIterator<Item = usize>,
+    indices_to_remove: &mut HashSet<usize>,
+) {
+    let mut total_tokens: usize = token_counts
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !indices_to_remove.contains(i))
+        .map(|(_, count)| count)
+        .sum();
+
+    for i in candidates {
+        if total_tokens <= context_limit {
+            break;
+        }
+        if !is_removable(messages, i) {
+            continue;
+        }
+        for index in with_tool_pair(messages, i) {
+            if indices_to_remove.insert(index) {
+                total_tokens -= token_counts[index];
+                debug!(
+                    "Removing message at index {}. Tokens removed: {}",
+                    index, token_counts[index]
+                );
+            }
+        }
+    }
+}
+
 /// Truncates the messages to fit within the model's context window.
 /// Mutates the input messages and token counts in place.
 /// Returns an error if it's impossible to truncate the messages within the context limit.
 /// - messages: The vector of messages in the conversation.
 /// - token_counts: A parallel vector containing the token count for each message.
 /// - context_limit: The maximum allowed context length in tokens.
-/// - strategy: The truncation strategy to use. Only option is OldestFirstTruncation.
+/// - strategy: The truncation strategy to use, see `TruncationStrategyKind` for the built-ins.
 pub fn truncate_messages(
     messages: &mut Vec<Message>,
     token_counts: &mut Vec<usize>,
@@ -117,11 +399,30 @@ pub fn truncate_messages(
         return Ok(()); // No truncation needed
     }
 
-    // Step 2: Determine indices to remove based on strategy
-    let indices_to_remove =
+    // Step 2: Elide the tool results the strategy can spare, which may be enough on its own
+    for i in strategy.elision_candidates(messages) {
+        if total_tokens <= context_limit {
+            return Ok(());
+        }
+        if i >= messages.len() || messages[i].pinned || !messages[i].is_tool_response() {
+            continue;
+        }
+        elide_tool_results(&mut messages[i]);
+        let elided_tokens = token_counts[i].min(ELIDED_TOOL_RESULT_TOKENS);
+        total_tokens -= token_counts[i] - elided_tokens;
+        token_counts[i] = elided_tokens;
+    }
+    if total_tokens <= context_limit {
+        return Ok(());
+    }
+
+    // Step 3: Determine indices to remove based on strategy, keeping pinned messages whatever
+    // the strategy
+    let mut indices_to_remove =
         strategy.determine_indices_to_remove(messages, token_counts, context_limit)?;
+    indices_to_remove.retain(|&i| i < messages.len() && is_removable(messages, i));
 
-    // Step 3: Remove the marked messages
+    // Step 4: Remove the marked messages
     // Vectorize the set and sort in reverse order to avoid shifting indices when removing
     let mut indices_to_remove = indices_to_remove.iter().cloned().collect::<Vec<usize>>();
     indices_to_remove.sort_unstable_by(|a, b| b.cmp(a));
@@ -134,7 +435,7 @@ pub fn truncate_messages(
         }
     }
 
-    // Step 4: Ensure the last message is a user message with TextContent only
+    // Step 5: Ensure the last message is a user message with TextContent only
     while let Some(last_msg) = messages.last() {
         if last_msg.role != Role::User || !last_msg.has_only_text_content() {
             let _ = messages.pop().ok_or(anyhow!("Failed to pop message"))?;
@@ -147,7 +448,7 @@ pub fn truncate_messages(
         }
     }
 
-    // Step 5: Check first msg is a User message with TextContent only
+    // Step 6: Check first msg is a User message with TextContent only
     while let Some(first_msg) = messages.first() {
         if first_msg.role != Role::User || !first_msg.has_only_text_content() {
             let _ = messages.remove(0);
@@ -257,6 +558,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_pinned_messages_are_kept() -> Result<()> {
+        let (mut messages, mut token_counts) = create_messages_with_counts(2, 10, false);
+        messages[0].pinned = true;
+        messages.push(user_text(4, 10).0);
+        token_counts.push(10);
+        let pinned = messages[0].clone();
+        let last = messages[4].clone();
+
+        for strategy in [
+            TruncationStrategyKind::OldestFirst,
+            TruncationStrategyKind::SlidingWindow,
+            TruncationStrategyKind::ToolResultsFirst,
+            TruncationStrategyKind::ElideToolResults,
+        ] {
+            let mut messages = messages.clone();
+            let mut token_counts = token_counts.clone();
+            truncate_messages(
+                &mut messages,
+                &mut token_counts,
+                25,
+                strategy.strategy().as_ref(),
+            )?;
+            assert_eq!(messages, vec![pinned.clone(), last.clone()]);
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_complex_conversation_with_tools() -> Result<()> {
         // Simulating a real conversation with multiple tool interactions
@@ -440,6 +769,147 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_sliding_window() -> Result<()> {
+        let (mut messages, mut token_counts) = create_messages_with_counts(5, 10, true);
+        let strategy = SlidingWindowTruncation { max_messages: 5 };
+
+        // Once truncating, everything outside the window goes even if more would fit
+        truncate_messages(&mut messages, &mut token_counts, 80, &strategy)?;
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0].as_concat_text(), "User message 4");
+
+        let (mut messages, mut token_counts) = create_messages_with_counts(5, 10, true);
+        truncate_messages(&mut messages, &mut token_counts, 30, &strategy)?;
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].as_concat_text(), "User message 6");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_first_and_last() -> Result<()> {
+        let (mut messages, mut token_counts) = create_messages_with_counts(5, 10, true);
+        truncate_messages(
+            &mut messages,
+            &mut token_counts,
+            30,
+            &KeepFirstAndLastTruncation::default(),
+        )?;
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].as_concat_text(), "User message 0");
+        assert_eq!(messages[2].as_concat_text(), "User message 8");
+        Ok(())
+    }
+
+    #[test]
+    fn test_tool_results_first() -> Result<()> {
+        let tool_call = ToolCall::new("file_read", json!({"path": "/tmp/test.txt"}));
+        let (mut messages, mut token_counts): (Vec<Message>, Vec<usize>) = vec![
+            user_text(0, 10),
+            assistant_tool_request("tool1", tool_call, 10),
+            user_tool_response("tool1", vec![Content::text("contents")], 50),
+            assistant_text(1, 10),
+            user_text(2, 10),
+        ]
+        .into_iter()
+        .unzip();
+
+        truncate_messages(
+            &mut messages,
+            &mut token_counts,
+            40,
+            &ToolResultsFirstTruncation,
+        )?;
+
+        // The tool pair goes first, so the original request is kept
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].as_concat_text(), "User message 0");
+        assert!(messages.iter().all(|m| m.get_tool_ids().is_empty()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_elide_tool_results() -> Result<()> {
+        let tool_call = ToolCall::new("file_read", json!({"path": "/tmp/test.txt"}));
+        let (mut messages, mut token_counts): (Vec<Message>, Vec<usize>) = vec![
+            user_text(0, 10),
+            assistant_tool_request("tool1", tool_call.clone(), 10),
+            user_tool_response("tool1", vec![Content::text("contents")], 100),
+            assistant_tool_request("tool2", tool_call, 10),
+            user_tool_response("tool2", vec![Content::text("contents")], 10),
+            assistant_text(1, 10),
+            user_text(2, 10),
+        ]
+        .into_iter()
+        .unzip();
+        let recent = messages[3..].to_vec();
+
+        truncate_messages(
+            &mut messages,
+            &mut token_counts,
+            90,
+            &ElideToolResultsTruncation::default(),
+        )?;
+
+        // Eliding the old result is enough, so every message is kept
+        assert_eq!(messages.len(), 7);
+        assert_eq!(
+            messages[2].content[0].as_tool_response_text().unwrap(),
+            ELIDED_TOOL_RESULT
+        );
+        assert_eq!(messages[3..], recent);
+        Ok(())
+    }
+
+    #[test]
+    fn test_elide_tool_results_drops_oldest_turns() -> Result<()> {
+        let (mut messages, mut token_counts) = create_messages_with_counts(5, 10, true);
+        let strategy = ElideToolResultsTruncation { keep_recent: 2 };
+        truncate_messages(&mut messages, &mut token_counts, 30, &strategy)?;
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].as_concat_text(), "User message 6");
+        assert_eq!(messages[2].as_concat_text(), "User message 8");
+        Ok(())
+    }
+
+    #[test]
+    fn test_strategy_kind_deserialization() {
+        let kind: TruncationStrategyKind =
+            serde_json::from_value(json!("tool_results_first")).unwrap();
+        assert_eq!(kind, TruncationStrategyKind::ToolResultsFirst);
+        assert_eq!(
+            TruncationStrategyKind::default(),
+            TruncationStrategyKind::OldestFirst
+        );
+    }
+
+    #[test]
+    fn test_strategy_kind_for_model() {
+        let model = ModelConfig::new("gpt-4o".to_string())
+            .with_truncation_strategy(Some(TruncationStrategyKind::SlidingWindow));
+        assert_eq!(
+            TruncationStrategyKind::for_model(&model),
+            TruncationStrategyKind::SlidingWindow
+        );
+
+        let model: ModelConfig = serde_json::from_value(json!({
+            "model_name": "gpt-4o",
+            "tokenizer_name": "Xenova--gpt-4o",
+            "context_limit": null,
+            "temperature": null,
+            "max_tokens": null,
+        }))
+        .unwrap();
+        assert_eq!(model.truncation_strategy, None);
+        assert_eq!(
+            TruncationStrategyKind::for_model(&model),
+            TruncationStrategyKind::from_config()
+        );
+    }
+
     #[test]
     fn test_error_cases() -> Result<()> {
         // Test impossibly small context window
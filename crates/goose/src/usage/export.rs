@@ -0,0 +1,562 @@
+use anyhow::{anyhow, Result};
+use aws_credential_types::provider::ProvideCredentials;
+use aws_credential_types::Credentials;
+use aws_sigv4::http_request::{
+    sign, PayloadChecksumKind, SignableBody, SignableRequest, SigningSettings,
+};
+use aws_sigv4::sign::v4;
+use chrono::{DateTime, TimeDelta, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use super::tracker::{UsageFilter, UsageRecord, UsageTracker};
+use crate::config::{Config, APP_STRATEGY};
+
+/// Config key holding the `ExportConfig`
+pub const EXPORT_CONFIG_KEY: &str = "GOOSE_USAGE_EXPORT";
+
+/// Version of the `LineItem` schema, bumped on any incompatible change to its fields
+pub const EXPORT_SCHEMA_VERSION: u32 = 2;
+
+/// Column order of the CSV export, matching the fields of `LineItem`
+pub const CSV_COLUMNS: &[&str] = &[
+    "schema_version",
+    "timestamp",
+    "org",
+    "user",
+    "session_id",
+    "provider",
+    "model",
+    "input_tokens",
+    "output_tokens",
+    "total_tokens",
+    "cache_read_tokens",
+    "cache_write_tokens",
+    "reasoning_tokens",
+    "audio_input_tokens",
+    "audio_output_tokens",
+    "input_cost",
+    "output_cost",
+    "cache_cost",
+    "total_cost",
+    "latency_ms",
+];
+
+fn default_interval_minutes() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Jsonl,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Tags added to every line item so spend can be charged back to the right owner
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExportTags {
+    #[serde(default)]
+    pub org: Option<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+/// A bucket that exports are pushed to, e.g. `s3://my-bucket/goose` or `gs://my-bucket/goose`
+///
+/// S3 uploads use the standard AWS credential chain. GCS uploads use the XML API with
+/// HMAC keys stored as the `GCS_HMAC_ACCESS_KEY` and `GCS_HMAC_SECRET` secrets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportDestination {
+    pub url: String,
+    /// The bucket region, defaults to the AWS config region for S3
+    #[serde(default)]
+    pub region: Option<String>,
+}
+
+/// Settings for exporting usage, read from `GOOSE_USAGE_EXPORT`
+///
+/// ```yaml
+/// GOOSE_USAGE_EXPORT:
+///   format: csv
+///   tags:
+///     org: platform
+///     user: alice
+///   destination:
+///     url: s3://finops-usage/goose
+///   interval_minutes: 60
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExportConfig {
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub tags: ExportTags,
+    #[serde(default)]
+    pub destination: Option<ExportDestination>,
+    /// How often to push to the destination
+    #[serde(default = "default_interval_minutes")]
+    pub interval_minutes: u64,
+}
+
+impl ExportConfig {
+    pub fn from_config() -> Option<Self> {
+        Config::global().get(EXPORT_CONFIG_KEY).ok()
+    }
+}
+
+/// One provider request in the export schema
+///
+/// Token counts are always present (zero when the provider did not report them), costs
+/// are empty when the model's pricing is unknown.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineItem {
+    pub schema_version: u32,
+    pub timestamp: DateTime<Utc>,
+    pub org: Option<String>,
+    pub user: Option<String>,
+    pub session_id: Option<String>,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub reasoning_tokens: i64,
+    pub audio_input_tokens: i64,
+    pub audio_output_tokens: i64,
+    pub input_cost: Option<f64>,
+    pub output_cost: Option<f64>,
+    pub cache_cost: Option<f64>,
+    pub total_cost: Option<f64>,
+    pub latency_ms: u64,
+}
+
+impl LineItem {
+    pub fn new(record: &UsageRecord, tags: &ExportTags) -> Self {
+        let tokens = |value: Option<i32>| value.unwrap_or(0) as i64;
+        Self {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            timestamp: record.timestamp,
+            org: tags.org.clone(),
+            user: tags.user.clone(),
+            session_id: record.session_id.clone(),
+            provider: record.provider.clone(),
+            model: record.model.clone(),
+            input_tokens: tokens(record.usage.input_tokens),
+            output_tokens: tokens(record.usage.output_tokens),
+            total_tokens: tokens(record.usage.total_tokens),
+            cache_read_tokens: tokens(record.usage.cache_read_tokens),
+            cache_write_tokens: tokens(record.usage.cache_write_tokens),
+            reasoning_tokens: tokens(record.usage.reasoning_tokens),
+            audio_input_tokens: tokens(record.usage.audio_input_tokens),
+            audio_output_tokens: tokens(record.usage.audio_output_tokens),
+            input_cost: record.cost.map(|c| c.input),
+            output_cost: record.cost.map(|c| c.output),
+            cache_cost: record.cost.map(|c| c.cache),
+            total_cost: record.cost.map(|c| c.total),
+            latency_ms: record.latency_ms,
+        }
+    }
+
+    fn csv_row(&self) -> String {
+        let text = |value: &Option<String>| csv_escape(value.as_deref().unwrap_or_default());
+        let cost = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        [
+            self.schema_version.to_string(),
+            self.timestamp.to_rfc3339(),
+            text(&self.org),
+            text(&self.user),
+            text(&self.session_id),
+            csv_escape(&self.provider),
+            csv_escape(&self.model),
+            self.input_tokens.to_string(),
+            self.output_tokens.to_string(),
+            self.total_tokens.to_string(),
+            self.cache_read_tokens.to_string(),
+            self.cache_write_tokens.to_string(),
+            self.reasoning_tokens.to_string(),
+            self.audio_input_tokens.to_string(),
+            self.audio_output_tokens.to_string(),
+            cost(self.input_cost),
+            cost(self.output_cost),
+            cost(self.cache_cost),
+            cost(self.total_cost),
+            self.latency_ms.to_string(),
+        ]
+        .join(",")
+    }
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write the records as line items in the given format
+pub fn write_export<W: Write>(
+    records: &[UsageRecord],
+    tags: &ExportTags,
+    format: ExportFormat,
+    mut writer: W,
+) -> Result<()> {
+    if format == ExportFormat::Csv {
+        writeln!(writer, "{}", CSV_COLUMNS.join(","))?;
+    }
+    for record in records {
+        let item = LineItem::new(record, tags);
+        match format {
+            ExportFormat::Jsonl => writeln!(writer, "{}", serde_json::to_string(&item)?)?,
+            ExportFormat::Csv => writeln!(writer, "{}", item.csv_row())?,
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Pushes new usage records to the configured bucket
+pub struct UsageExporter {
+    config: ExportConfig,
+    destination: ExportDestination,
+    client: reqwest::Client,
+}
+
+impl UsageExporter {
+    /// Create an exporter, or None if the config has no destination
+    pub fn new(config: ExportConfig) -> Option<Self> {
+        let destination = config.destination.clone()?;
+        Some(Self {
+            config,
+            destination,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Where the time of the last pushed record is kept between runs
+    pub fn watermark_path() -> PathBuf {
+        choose_app_strategy(APP_STRATEGY.clone())
+            .expect("goose requires a home dir")
+            .in_data_dir("usage-export.watermark")
+    }
+
+    /// Push all records newer than the last push, returning the object key if anything was
+    /// uploaded
+    pub async fn push(&self, tracker: &UsageTracker) -> Result<Option<String>> {
+        let watermark = read_watermark();
+        let filter = UsageFilter {
+            since: watermark.map(|w| w + TimeDelta::milliseconds(1)),
+            ..Default::default()
+        };
+
+        // Prefer the persisted history so records from earlier runs are included
+        let mut records = match tracker.store() {
+            Some(store) => store.records(&filter, None)?,
+            None => tracker.records(&filter),
+        };
+        if records.is_empty() {
+            return Ok(None);
+        }
+        records.sort_by_key(|r| r.timestamp);
+        let latest = records.last().map(|r| r.timestamp).unwrap_or_else(Utc::now);
+
+        let mut body = Vec::new();
+        write_export(&records, &self.config.tags, self.config.format, &mut body)?;
+
+        let bucket = Bucket::parse(&self.destination.url)?;
+        let key = bucket.object_key(latest, self.config.format);
+        self.put_object(&bucket, &key, body).await?;
+
+        write_watermark(latest)?;
+        Ok(Some(key))
+    }
+
+    /// Push on the configured interval until the task is dropped
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let period = Duration::from_secs(self.config.interval_minutes.max(1) * 60);
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                match self.push(UsageTracker::global()).await {
+                    Ok(Some(key)) => tracing::debug!("Exported usage to {}", key),
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("Failed to export usage: {}", e),
+                }
+            }
+        })
+    }
+
+    async fn put_object(&self, bucket: &Bucket, key: &str, body: Vec<u8>) -> Result<()> {
+        let (url, region, credentials) = match bucket.kind {
+            BucketKind::S3 => {
+                let sdk_config = aws_config::load_from_env().await;
+                let region = self
+                    .destination
+                    .region
+                    .clone()
+                    .or_else(|| sdk_config.region().map(|r| r.to_string()))
+                    .unwrap_or_else(|| "us-east-1".to_string());
+                let credentials = sdk_config
+                    .credentials_provider()
+                    .ok_or_else(|| anyhow!("No AWS credentials found"))?
+                    .provide_credentials()
+                    .await?;
+                let url = format!(
+                    "https://{}.s3.{}.amazonaws.com/{}",
+                    bucket.name, region, key
+                );
+                (url, region, credentials)
+            }
+            BucketKind::Gcs => {
+                let config = Config::global();
+                let credentials = Credentials::new(
+                    config.get_secret::<String>("GCS_HMAC_ACCESS_KEY")?,
+                    config.get_secret::<String>("GCS_HMAC_SECRET")?,
+                    None,
+                    None,
+                    "goose",
+                );
+                let region = self
+                    .destination
+                    .region
+                    .clone()
+                    .unwrap_or_else(|| "auto".to_string());
+                let url = format!("https://storage.googleapis.com/{}/{}", bucket.name, key);
+                (url, region, credentials)
+            }
+        };
+
+        let content_type = match self.config.format {
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv",
+        };
+        let headers = [("content-type", content_type)];
+
+        let identity = credentials.into();
+        let mut settings = SigningSettings::default();
+        settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+        let params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(&region)
+            .name("s3")
+            .time(SystemTime::now())
+            .settings(settings)
+            .build()?
+            .into();
+        let signable = SignableRequest::new(
+            "PUT",
+            &url,
+            headers.iter().copied(),
+            SignableBody::Bytes(&body),
+        )?;
+        let (instructions, _) = sign(signable, &params)?.into_parts();
+
+        let mut request = self.client.put(&url).body(body.clone());
+        for (name, value) in headers.iter().copied().chain(instructions.headers()) {
+            request = request.header(name, value);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Upload to {} failed ({}): {}", url, status, text));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BucketKind {
+    S3,
+    Gcs,
+}
+
+#[derive(Debug, PartialEq)]
+struct Bucket {
+    kind: BucketKind,
+    name: String,
+    prefix: String,
+}
+
+impl Bucket {
+    fn parse(url: &str) -> Result<Self> {
+        let (kind, rest) = if let Some(rest) = url.strip_prefix("s3://") {
+            (BucketKind::S3, rest)
+        } else if let Some(rest) = url.strip_prefix("gs://") {
+            (BucketKind::Gcs, rest)
+        } else {
+            return Err(anyhow!(
+                "Unsupported export destination {}, expected s3:// or gs://",
+                url
+            ));
+        };
+
+        let (name, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if name.is_empty() {
+            return Err(anyhow!("Missing bucket name in {}", url));
+        }
+        Ok(Self {
+            kind,
+            name: name.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+
+    fn object_key(&self, timestamp: DateTime<Utc>, format: ExportFormat) -> String {
+        let file = format!(
+            "usage-{}.{}",
+            timestamp.format("%Y%m%dT%H%M%S%3fZ"),
+            format.extension()
+        );
+        if self.prefix.is_empty() {
+            file
+        } else {
+            format!("{}/{}", self.prefix, file)
+        }
+    }
+}
+
+fn read_watermark() -> Option<DateTime<Utc>> {
+    let contents = std::fs::read_to_string(UsageExporter::watermark_path()).ok()?;
+    DateTime::from_timestamp_millis(contents.trim().parse().ok()?)
+}
+
+fn write_watermark(timestamp: DateTime<Utc>) -> Result<()> {
+    let path = UsageExporter::watermark_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, timestamp.timestamp_millis().to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use crate::providers::pricing::Cost;
+
+    fn records() -> Vec<UsageRecord> {
+        vec![
+            UsageRecord {
+                timestamp: DateTime::from_timestamp_millis(1_700_000_000_000).unwrap(),
+                provider: "openai".to_string(),
+                model: "gpt-4o".to_string(),
+                session_id: Some("project, x".to_string()),
+                usage: Usage::new(Some(100), Some(20), Some(120)).with_cache_tokens(Some(50), None),
+                cost: Some(Cost {
+                    input: 0.5,
+                    output: 0.25,
+                    cache: 0.125,
+                    total: 0.875,
+                }),
+                latency_ms: 300,
+            },
+            UsageRecord {
+                timestamp: DateTime::from_timestamp_millis(1_700_000_001_000).unwrap(),
+                provider: "ollama".to_string(),
+                model: "qwen2.5".to_string(),
+                session_id: None,
+                usage: Usage::default(),
+                cost: None,
+                latency_ms: 40,
+            },
+        ]
+    }
+
+    fn tags() -> ExportTags {
+        ExportTags {
+            org: Some("platform".to_string()),
+            user: Some("alice".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_csv_export() -> Result<()> {
+        let mut out = Vec::new();
+        write_export(&records(), &tags(), ExportFormat::Csv, &mut out)?;
+        let out = String::from_utf8(out)?;
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            "2,2023-11-14T22:13:20+00:00,platform,alice,\"project, x\",openai,gpt-4o,100,20,120,50,0,0,0,0,0.5,0.25,0.125,0.875,300"
+        );
+        assert_eq!(
+            lines[2],
+            "2,2023-11-14T22:13:21+00:00,platform,alice,,ollama,qwen2.5,0,0,0,0,0,0,0,0,,,,,40"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_jsonl_export() -> Result<()> {
+        let mut out = Vec::new();
+        write_export(&records(), &tags(), ExportFormat::Jsonl, &mut out)?;
+        let out = String::from_utf8(out)?;
+
+        let items: Vec<LineItem> = out
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0], LineItem::new(&records()[0], &tags()));
+        assert_eq!(items[1].total_cost, None);
+
+        // Both formats carry the same fields
+        let value: serde_json::Value = serde_json::from_str(out.lines().next().unwrap())?;
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        let mut columns = CSV_COLUMNS.to_vec();
+        keys.sort();
+        columns.sort();
+        assert_eq!(keys, columns);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bucket_parse() -> Result<()> {
+        let bucket = Bucket::parse("s3://finops/goose/usage/")?;
+        assert_eq!(bucket.kind, BucketKind::S3);
+        assert_eq!(bucket.name, "finops");
+        assert_eq!(
+            bucket.object_key(
+                DateTime::from_timestamp_millis(1_700_000_000_123).unwrap(),
+                ExportFormat::Csv
+            ),
+            "goose/usage/usage-20231114T221320123Z.csv"
+        );
+
+        let bucket = Bucket::parse("gs://finops")?;
+        assert_eq!(bucket.kind, BucketKind::Gcs);
+        assert_eq!(bucket.prefix, "");
+
+        assert!(Bucket::parse("https://example.com").is_err());
+        assert!(Bucket::parse("s3://").is_err());
+        Ok(())
+    }
+}
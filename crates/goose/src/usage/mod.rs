@@ -0,0 +1,12 @@
+mod export;
+mod store;
+mod tools;
+mod tracker;
+
+pub use export::{
+    write_export, ExportConfig, ExportDestination, ExportFormat, ExportTags, LineItem,
+    UsageExporter, CSV_COLUMNS, EXPORT_CONFIG_KEY, EXPORT_SCHEMA_VERSION,
+};
+pub use store::{PrunePolicy, UsageStore};
+pub use tools::{tool_token_usage, ToolTokenUsage};
+pub use tracker::{UsageFilter, UsageRecord, UsageSummary, UsageTracker};
@@ -0,0 +1,420 @@
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection, Row};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::tracker::{UsageFilter, UsageRecord, UsageSummary};
+use crate::config::{Config, APP_STRATEGY};
+use crate::providers::base::Usage;
+use crate::providers::pricing::Cost;
+
+const DEFAULT_RETENTION_DAYS: i64 = 90;
+
+/// Which records to drop when pruning the history
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrunePolicy {
+    /// Drop records older than this
+    pub max_age: Option<TimeDelta>,
+    /// Keep at most this many of the most recent records
+    pub max_records: Option<usize>,
+}
+
+impl PrunePolicy {
+    /// Read the policy from `GOOSE_USAGE_RETENTION_DAYS` (default 90) and
+    /// `GOOSE_USAGE_MAX_RECORDS` (default unlimited)
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        let days: i64 = config
+            .get("GOOSE_USAGE_RETENTION_DAYS")
+            .unwrap_or(DEFAULT_RETENTION_DAYS);
+        Self {
+            max_age: Some(TimeDelta::days(days)),
+            max_records: config.get("GOOSE_USAGE_MAX_RECORDS").ok(),
+        }
+    }
+}
+
+/// Usage history persisted in a local SQLite database
+pub struct UsageStore {
+    conn: Mutex<Connection>,
+}
+
+impl UsageStore {
+    /// Open (or create) the usage database at the given path
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Open a database that only lives as long as the store, mostly useful for testing
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    /// The default location of the usage database
+    ///
+    /// - macOS/Linux: ~/.local/share/goose/usage.db
+    /// - Windows:     ~\AppData\Roaming\Block\goose\data\usage.db
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(choose_app_strategy(APP_STRATEGY.clone())?.in_data_dir("usage.db"))
+    }
+
+    /// Open the default database unless `GOOSE_USAGE_HISTORY` is disabled, pruning it
+    /// according to the configured policy
+    ///
+    /// When the database can't be opened, e.g. without a home dir, the history is kept in
+    /// memory for the lifetime of the process instead.
+    pub fn from_config() -> Option<Self> {
+        let enabled: bool = Config::global().get("GOOSE_USAGE_HISTORY").unwrap_or(true);
+        if !enabled {
+            return None;
+        }
+
+        Self::default_path()
+            .and_then(Self::open)
+            .and_then(|store| {
+                store.prune(&PrunePolicy::from_config())?;
+                Ok(store)
+            })
+            .or_else(|e| {
+                tracing::warn!("Failed to open usage history, keeping it in memory: {}", e);
+                Self::open_in_memory()
+            })
+            .ok()
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                session_id TEXT,
+                input_tokens INTEGER,
+                output_tokens INTEGER,
+                total_tokens INTEGER,
+                cache_read_tokens INTEGER,
+                cache_write_tokens INTEGER,
+                reasoning_tokens INTEGER,
+                audio_input_tokens INTEGER,
+                audio_output_tokens INTEGER,
+                input_cost REAL,
+                output_cost REAL,
+                cache_cost REAL,
+                total_cost REAL,
+                latency_ms INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS usage_timestamp ON usage (timestamp);
+            CREATE INDEX IF NOT EXISTS usage_session ON usage (session_id);",
+        )?;
+
+        // Databases created before cache, reasoning and audio tokens were tracked lack these columns
+        let columns: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('usage')")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        for column in [
+            "cache_read_tokens",
+            "cache_write_tokens",
+            "reasoning_tokens",
+            "audio_input_tokens",
+            "audio_output_tokens",
+        ] {
+            if !columns.iter().any(|c| c == column) {
+                conn.execute(
+                    &format!("ALTER TABLE usage ADD COLUMN {} INTEGER", column),
+                    [],
+                )?;
+            }
+        }
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Append a record to the history
+    pub fn insert(&self, record: &UsageRecord) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO usage (
+                timestamp, provider, model, session_id,
+                input_tokens, output_tokens, total_tokens, cache_read_tokens, cache_write_tokens,
+                input_cost, output_cost, cache_cost, total_cost, latency_ms,
+                reasoning_tokens, audio_input_tokens, audio_output_tokens
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            params![
+                record.timestamp.timestamp_millis(),
+                record.provider,
+                record.model,
+                record.session_id,
+                record.usage.input_tokens,
+                record.usage.output_tokens,
+                record.usage.total_tokens,
+                record.usage.cache_read_tokens,
+                record.usage.cache_write_tokens,
+                record.cost.map(|c| c.input),
+                record.cost.map(|c| c.output),
+                record.cost.map(|c| c.cache),
+                record.cost.map(|c| c.total),
+                record.latency_ms as i64,
+                record.usage.reasoning_tokens,
+                record.usage.audio_input_tokens,
+                record.usage.audio_output_tokens,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Records matching the filter, most recent first
+    pub fn records(&self, filter: &UsageFilter, limit: Option<usize>) -> Result<Vec<UsageRecord>> {
+        let (clause, values) = where_clause(filter);
+        let mut sql = format!(
+            "SELECT timestamp, provider, model, session_id, input_tokens, output_tokens,
+                total_tokens, input_cost, output_cost, cache_cost, total_cost, latency_ms,
+                cache_read_tokens, cache_write_tokens, reasoning_tokens, audio_input_tokens,
+                audio_output_tokens
+            FROM usage {} ORDER BY timestamp DESC, id DESC",
+            clause
+        );
+        if let Some(limit) = limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let params: Vec<&dyn ToSql> = values.iter().map(|v| v.as_ref()).collect();
+        let records = stmt
+            .query_map(params.as_slice(), record_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
+    }
+
+    /// Totals over all records matching the filter
+    pub fn totals(&self, filter: &UsageFilter) -> Result<UsageSummary> {
+        let (clause, values) = where_clause(filter);
+        let sql = format!(
+            "SELECT COUNT(*), COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0),
+                COALESCE(SUM(total_tokens), 0), COALESCE(SUM(total_cost), 0.0),
+                COALESCE(SUM(cache_read_tokens), 0), COALESCE(SUM(cache_write_tokens), 0),
+                COALESCE(SUM(reasoning_tokens), 0)
+            FROM usage {}",
+            clause
+        );
+
+        let conn = self.conn.lock().unwrap();
+        let params: Vec<&dyn ToSql> = values.iter().map(|v| v.as_ref()).collect();
+        let summary = conn.query_row(&sql, params.as_slice(), |row| {
+            Ok(UsageSummary {
+                requests: row.get::<_, i64>(0)? as u64,
+                input_tokens: row.get(1)?,
+                output_tokens: row.get(2)?,
+                total_tokens: row.get(3)?,
+                cache_read_tokens: row.get(5)?,
+                cache_write_tokens: row.get(6)?,
+                reasoning_tokens: row.get(7)?,
+                cost: row.get(4)?,
+            })
+        })?;
+        Ok(summary)
+    }
+
+    /// Delete records according to the policy, returning how many were removed
+    pub fn prune(&self, policy: &PrunePolicy) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let mut removed = 0;
+
+        if let Some(max_age) = policy.max_age {
+            let cutoff = (Utc::now() - max_age).timestamp_millis();
+            removed += conn.execute("DELETE FROM usage WHERE timestamp < ?1", [cutoff])?;
+        }
+
+        if let Some(max_records) = policy.max_records {
+            removed += conn.execute(
+                "DELETE FROM usage WHERE id NOT IN (
+                    SELECT id FROM usage ORDER BY timestamp DESC, id DESC LIMIT ?1
+                )",
+                [max_records as i64],
+            )?;
+        }
+
+        Ok(removed)
+    }
+}
+
+fn where_clause(filter: &UsageFilter) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+    if let Some(provider) = &filter.provider {
+        values.push(Box::new(provider.clone()));
+        conditions.push(format!("provider = ?{}", values.len()));
+    }
+    if let Some(model) = &filter.model {
+        values.push(Box::new(model.clone()));
+        conditions.push(format!("model = ?{}", values.len()));
+    }
+    if let Some(session_id) = &filter.session_id {
+        values.push(Box::new(session_id.clone()));
+        conditions.push(format!("session_id = ?{}", values.len()));
+    }
+    if let Some(since) = filter.since {
+        values.push(Box::new(since.timestamp_millis()));
+        conditions.push(format!("timestamp >= ?{}", values.len()));
+    }
+
+    if conditions.is_empty() {
+        (String::new(), values)
+    } else {
+        (format!("WHERE {}", conditions.join(" AND ")), values)
+    }
+}
+
+fn record_from_row(row: &Row) -> rusqlite::Result<UsageRecord> {
+    let total_cost: Option<f64> = row.get(10)?;
+    let cost = match total_cost {
+        Some(total) => Some(Cost {
+            input: row.get::<_, Option<f64>>(7)?.unwrap_or(0.0),
+            output: row.get::<_, Option<f64>>(8)?.unwrap_or(0.0),
+            cache: row.get::<_, Option<f64>>(9)?.unwrap_or(0.0),
+            total,
+        }),
+        None => None,
+    };
+
+    Ok(UsageRecord {
+        timestamp: DateTime::from_timestamp_millis(row.get(0)?).unwrap_or_default(),
+        provider: row.get(1)?,
+        model: row.get(2)?,
+        session_id: row.get(3)?,
+        usage: Usage::new(row.get(4)?, row.get(5)?, row.get(6)?)
+            .with_cache_tokens(row.get(12)?, row.get(13)?)
+            .with_reasoning_tokens(row.get(14)?)
+            .with_audio_tokens(row.get(15)?, row.get(16)?),
+        cost,
+        latency_ms: row.get::<_, i64>(11)? as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(model: &str, session_id: Option<&str>, age: TimeDelta) -> UsageRecord {
+        UsageRecord {
+            timestamp: Utc::now() - age,
+            provider: "openai".to_string(),
+            model: model.to_string(),
+            session_id: session_id.map(|s| s.to_string()),
+            usage: Usage::new(Some(10), Some(5), Some(15))
+                .with_cache_tokens(Some(4), None)
+                .with_reasoning_tokens(Some(2)),
+            cost: Some(Cost {
+                input: 0.1,
+                output: 0.2,
+                cache: 0.0,
+                total: 0.3,
+            }),
+            latency_ms: 120,
+        }
+    }
+
+    #[test]
+    fn test_insert_and_query() -> Result<()> {
+        let store = UsageStore::open_in_memory()?;
+        store.insert(&record("gpt-4o", Some("a"), TimeDelta::hours(2)))?;
+        store.insert(&record("gpt-4o-mini", Some("b"), TimeDelta::zero()))?;
+
+        let records = store.records(&UsageFilter::default(), None)?;
+        assert_eq!(records.len(), 2);
+        // Most recent first
+        assert_eq!(records[0].model, "gpt-4o-mini");
+        assert_eq!(records[0].latency_ms, 120);
+        assert_eq!(records[0].cost.unwrap().total, 0.3);
+        assert_eq!(records[0].usage.cache_read_tokens, Some(4));
+        assert_eq!(records[0].usage.cache_write_tokens, None);
+        assert_eq!(records[0].usage.reasoning_tokens, Some(2));
+        assert_eq!(records[0].usage.audio_input_tokens, None);
+
+        let filter = UsageFilter {
+            session_id: Some("a".to_string()),
+            ..Default::default()
+        };
+        let totals = store.totals(&filter)?;
+        assert_eq!(totals.requests, 1);
+        assert_eq!(totals.total_tokens, 15);
+        assert_eq!(totals.cache_read_tokens, 4);
+        assert_eq!(totals.reasoning_tokens, 2);
+
+        let filter = UsageFilter {
+            since: Some(Utc::now() - TimeDelta::hours(1)),
+            ..Default::default()
+        };
+        assert_eq!(store.records(&filter, Some(10))?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune() -> Result<()> {
+        let store = UsageStore::open_in_memory()?;
+        store.insert(&record("gpt-4o", None, TimeDelta::days(100)))?;
+        for _ in 0..3 {
+            store.insert(&record("gpt-4o", None, TimeDelta::zero()))?;
+        }
+
+        let removed = store.prune(&PrunePolicy {
+            max_age: Some(TimeDelta::days(90)),
+            max_records: Some(2),
+        })?;
+        assert_eq!(removed, 2);
+        assert_eq!(store.totals(&UsageFilter::default())?.requests, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("nested").join("usage.db");
+        {
+            let store = UsageStore::open(&path)?;
+            store.insert(&record("gpt-4o", None, TimeDelta::zero()))?;
+        }
+
+        let store = UsageStore::open(&path)?;
+        assert_eq!(store.totals(&UsageFilter::default())?.requests, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrates_missing_cache_columns() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("usage.db");
+        Connection::open(&path)?.execute_batch(
+            "CREATE TABLE usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                session_id TEXT,
+                input_tokens INTEGER,
+                output_tokens INTEGER,
+                total_tokens INTEGER,
+                input_cost REAL,
+                output_cost REAL,
+                cache_cost REAL,
+                total_cost REAL,
+                latency_ms INTEGER NOT NULL
+            );",
+        )?;
+
+        let store = UsageStore::open(&path)?;
+        store.insert(&record("gpt-4o", None, TimeDelta::zero()))?;
+        assert_eq!(store.totals(&UsageFilter::default())?.cache_read_tokens, 4);
+        Ok(())
+    }
+}
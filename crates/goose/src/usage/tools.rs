@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::message::{Message, MessageContent};
+use crate::token_counter::TokenCounter;
+
+/// How much of the conversation's input is taken up by a single tool's results
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolTokenUsage {
+    pub tool: String,
+    /// Number of results from this tool in the conversation
+    pub calls: usize,
+    /// Tokens those results add to every request's input
+    pub tokens: usize,
+}
+
+/// Attribute the tokens of each tool result in the conversation to the tool that produced
+/// it, heaviest tools first
+///
+/// Each result is counted the same way as a message on its own, so the totals line up
+/// with the per-message counts used for truncation. Results whose request is no longer in
+/// the conversation (e.g. it was truncated) are grouped under "unknown".
+pub fn tool_token_usage(messages: &[Message], token_counter: &TokenCounter) -> Vec<ToolTokenUsage> {
+    let mut tool_names: HashMap<&str, &str> = HashMap::new();
+    for message in messages {
+        for content in &message.content {
+            if let Some(request) = content.as_tool_request() {
+                if let Ok(call) = &request.tool_call {
+                    tool_names.insert(request.id.as_str(), call.name.as_str());
+                }
+            }
+        }
+    }
+
+    let mut usage: HashMap<&str, ToolTokenUsage> = HashMap::new();
+    for message in messages {
+        for content in &message.content {
+            let MessageContent::ToolResponse(response) = content else {
+                continue;
+            };
+
+            let tool = tool_names
+                .get(response.id.as_str())
+                .copied()
+                .unwrap_or("unknown");
+            let single = Message::new(message.role.clone(), message.created, vec![content.clone()]);
+            let tokens = token_counter.count_chat_tokens("", &[single], &[]);
+
+            let entry = usage.entry(tool).or_insert_with(|| ToolTokenUsage {
+                tool: tool.to_string(),
+                calls: 0,
+                tokens: 0,
+            });
+            entry.calls += 1;
+            entry.tokens += tokens;
+        }
+    }
+
+    let mut usage: Vec<ToolTokenUsage> = usage.into_values().collect();
+    usage.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.tool.cmp(&b.tool)));
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::content::Content;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_token_usage() {
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            Message::assistant()
+                .with_tool_request("1", Ok(ToolCall::new("developer__shell", json!({}))))
+                .with_tool_request("2", Ok(ToolCall::new("developer__shell", json!({})))),
+            Message::user()
+                .with_tool_response("1", Ok(vec![Content::text("a".repeat(400))]))
+                .with_tool_response("2", Ok(vec![Content::text("b".repeat(40))])),
+            Message::assistant()
+                .with_tool_request("3", Ok(ToolCall::new("memory__remember", json!({})))),
+            Message::user().with_tool_response("3", Ok(vec![Content::text("ok")])),
+            Message::user().with_tool_response("gone", Ok(vec![Content::text("orphan")])),
+        ];
+
+        let usage = tool_token_usage(&messages, &TokenCounter::approximate());
+
+        assert_eq!(usage.len(), 3);
+        assert_eq!(usage[0].tool, "developer__shell");
+        assert_eq!(usage[0].calls, 2);
+        assert!(usage[0].tokens > 110);
+        let unknown = usage.iter().find(|u| u.tool == "unknown").unwrap();
+        assert_eq!(unknown.calls, 1);
+        assert!(usage[1].tokens >= usage[2].tokens);
+    }
+}
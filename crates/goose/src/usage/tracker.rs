@@ -0,0 +1,292 @@
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::store::UsageStore;
+use crate::providers::base::{ProviderUsage, Usage};
+use crate::providers::pricing::Cost;
+
+#[cfg(not(test))]
+static GLOBAL_TRACKER: Lazy<UsageTracker> = Lazy::new(|| match UsageStore::from_config() {
+    Some(store) => UsageTracker::default().with_store(store),
+    None => UsageTracker::default(),
+});
+
+// Tests record through the global tracker too, which must not touch the user's history
+#[cfg(test)]
+static GLOBAL_TRACKER: Lazy<UsageTracker> = Lazy::new(|| {
+    UsageTracker::default().with_store(UsageStore::open_in_memory().expect("in-memory store"))
+});
+
+/// A single provider request as seen by the tracker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    pub provider: String,
+    pub model: String,
+    pub session_id: Option<String>,
+    pub usage: Usage,
+    pub cost: Option<Cost>,
+    pub latency_ms: u64,
+}
+
+/// Aggregated usage over a set of records
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub requests: u64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub reasoning_tokens: i64,
+    pub cost: f64,
+}
+
+impl UsageSummary {
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.input_tokens += record.usage.input_tokens.unwrap_or(0) as i64;
+        self.output_tokens += record.usage.output_tokens.unwrap_or(0) as i64;
+        self.total_tokens += record.usage.total_tokens.unwrap_or(0) as i64;
+        self.cache_read_tokens += record.usage.cache_read_tokens.unwrap_or(0) as i64;
+        self.cache_write_tokens += record.usage.cache_write_tokens.unwrap_or(0) as i64;
+        self.reasoning_tokens += record.usage.reasoning_tokens.unwrap_or(0) as i64;
+        self.cost += record.cost.map(|c| c.total).unwrap_or(0.0);
+    }
+
+    /// Add the totals of another summary to this one
+    pub fn merge(&mut self, other: &UsageSummary) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
+        self.reasoning_tokens += other.reasoning_tokens;
+        self.cost += other.cost;
+    }
+}
+
+/// Restricts which records a query looks at; empty fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct UsageFilter {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub session_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl UsageFilter {
+    fn matches(&self, record: &UsageRecord) -> bool {
+        self.provider.as_ref().is_none_or(|p| *p == record.provider)
+            && self.model.as_ref().is_none_or(|m| *m == record.model)
+            && self
+                .session_id
+                .as_ref()
+                .is_none_or(|s| record.session_id.as_ref() == Some(s))
+            && self.since.is_none_or(|since| record.timestamp >= since)
+    }
+}
+
+/// Aggregates provider usage over the lifetime of the process
+///
+/// Every provider created through `providers::create` reports to `UsageTracker::global()`,
+/// so embedders can query it at any time, e.g. to show a running cost ticker.
+/// The global tracker also persists every record to the `UsageStore` for reporting
+/// across restarts.
+#[derive(Default)]
+pub struct UsageTracker {
+    records: Mutex<Vec<UsageRecord>>,
+    session_id: Mutex<Option<String>>,
+    store: Option<UsageStore>,
+}
+
+impl UsageTracker {
+    /// Get the process wide tracker
+    pub fn global() -> &'static UsageTracker {
+        &GLOBAL_TRACKER
+    }
+
+    /// Persist records to the given store in addition to keeping them in memory
+    pub fn with_store(mut self, store: UsageStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// The persisted usage history, if enabled
+    pub fn store(&self) -> Option<&UsageStore> {
+        self.store.as_ref()
+    }
+
+    /// Set the session that subsequent requests are attributed to
+    pub fn set_session(&self, session_id: Option<String>) {
+        *self.session_id.lock().unwrap() = session_id;
+    }
+
+    /// The session that requests are currently attributed to
+    pub fn session(&self) -> Option<String> {
+        self.session_id.lock().unwrap().clone()
+    }
+
+    /// Record a completed request for the current session
+    pub fn record(&self, provider: &str, usage: &ProviderUsage, latency: Duration) {
+        let record = UsageRecord {
+            timestamp: Utc::now(),
+            provider: provider.to_string(),
+            model: usage.model.clone(),
+            session_id: self.session(),
+            usage: usage.usage.clone(),
+            cost: usage.cost,
+            latency_ms: latency.as_millis() as u64,
+        };
+        if let Some(store) = &self.store {
+            if let Err(e) = store.insert(&record) {
+                tracing::warn!("Failed to persist usage record: {}", e);
+            }
+        }
+        self.records.lock().unwrap().push(record);
+    }
+
+    /// All records matching the filter, oldest first
+    pub fn records(&self, filter: &UsageFilter) -> Vec<UsageRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| filter.matches(r))
+            .cloned()
+            .collect()
+    }
+
+    /// Totals over all records matching the filter
+    pub fn totals(&self, filter: &UsageFilter) -> UsageSummary {
+        let mut summary = UsageSummary::default();
+        for record in self.records.lock().unwrap().iter() {
+            if filter.matches(record) {
+                summary.add(record);
+            }
+        }
+        summary
+    }
+
+    /// Totals per model
+    pub fn by_model(&self, filter: &UsageFilter) -> BTreeMap<String, UsageSummary> {
+        self.group_by(filter, |r| r.model.clone())
+    }
+
+    /// Totals per provider
+    pub fn by_provider(&self, filter: &UsageFilter) -> BTreeMap<String, UsageSummary> {
+        self.group_by(filter, |r| r.provider.clone())
+    }
+
+    /// Totals per session; requests made outside of a session are grouped under ""
+    pub fn by_session(&self, filter: &UsageFilter) -> BTreeMap<String, UsageSummary> {
+        self.group_by(filter, |r| r.session_id.clone().unwrap_or_default())
+    }
+
+    /// Totals per hour, keyed by the start of the hour
+    pub fn by_hour(&self, filter: &UsageFilter) -> BTreeMap<DateTime<Utc>, UsageSummary> {
+        self.group_by(filter, |r| {
+            r.timestamp
+                .duration_trunc(TimeDelta::hours(1))
+                .unwrap_or(r.timestamp)
+        })
+    }
+
+    /// Drop all recorded usage
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear();
+    }
+
+    fn group_by<K: Ord>(
+        &self,
+        filter: &UsageFilter,
+        key: impl Fn(&UsageRecord) -> K,
+    ) -> BTreeMap<K, UsageSummary> {
+        let mut groups: BTreeMap<K, UsageSummary> = BTreeMap::new();
+        for record in self.records.lock().unwrap().iter() {
+            if filter.matches(record) {
+                groups.entry(key(record)).or_default().add(record);
+            }
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(model: &str, input: i32, output: i32) -> ProviderUsage {
+        ProviderUsage::new(
+            model.to_string(),
+            Usage::new(Some(input), Some(output), Some(input + output)),
+        )
+    }
+
+    #[test]
+    fn test_aggregation() {
+        let tracker = UsageTracker::default();
+        tracker.record("openai", &usage("gpt-4o", 100, 50), Duration::ZERO);
+        tracker.set_session(Some("abc".to_string()));
+        tracker.record("openai", &usage("gpt-4o", 10, 5), Duration::ZERO);
+        tracker.record(
+            "anthropic",
+            &usage("claude-3-5-sonnet", 20, 10),
+            Duration::ZERO,
+        );
+
+        let totals = tracker.totals(&UsageFilter::default());
+        assert_eq!(totals.requests, 3);
+        assert_eq!(totals.input_tokens, 130);
+        assert_eq!(totals.total_tokens, 195);
+        assert!(totals.cost > 0.0);
+
+        let by_model = tracker.by_model(&UsageFilter::default());
+        assert_eq!(by_model["gpt-4o"].requests, 2);
+        assert_eq!(by_model["claude-3-5-sonnet"].output_tokens, 10);
+
+        let by_session = tracker.by_session(&UsageFilter::default());
+        assert_eq!(by_session[""].requests, 1);
+        assert_eq!(by_session["abc"].requests, 2);
+
+        let filter = UsageFilter {
+            provider: Some("openai".to_string()),
+            session_id: Some("abc".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(tracker.totals(&filter).total_tokens, 15);
+    }
+
+    #[test]
+    fn test_persists_to_store() {
+        let tracker = UsageTracker::default().with_store(UsageStore::open_in_memory().unwrap());
+        tracker.record("openai", &usage("gpt-4o", 1, 1), Duration::from_millis(42));
+
+        let records = tracker
+            .store()
+            .unwrap()
+            .records(&UsageFilter::default(), None)
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].latency_ms, 42);
+    }
+
+    #[test]
+    fn test_by_hour() {
+        let tracker = UsageTracker::default();
+        tracker.record("openai", &usage("gpt-4o", 1, 1), Duration::ZERO);
+        tracker.record("openai", &usage("gpt-4o", 1, 1), Duration::ZERO);
+
+        let by_hour = tracker.by_hour(&UsageFilter::default());
+        let total: u64 = by_hour.values().map(|s| s.requests).sum();
+        assert_eq!(total, 2);
+        for hour in by_hour.keys() {
+            assert_eq!(hour.timestamp() % 3600, 0);
+        }
+    }
+}
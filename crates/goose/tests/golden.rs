@@ -0,0 +1,178 @@
+//! Golden-file tests for the request payloads built by each provider format
+//!
+//! Every case serializes `create_request` for the same conversation and compares it with
+//! `tests/golden/<case>.json`. After an intended change to a payload, regenerate the files
+//! with `GOOSE_UPDATE_GOLDEN=1 cargo test -p goose --test golden` and review the diff.
+
+use anyhow::Result;
+use goose::message::{Message, MessageContent};
+use goose::model::ModelConfig;
+use goose::providers::formats::{anthropic, bedrock, google, openai};
+use goose::providers::utils::ImageFormat;
+use mcp_core::content::Content;
+use mcp_core::tool::{Tool, ToolCall};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+// A 1x1 red PNG
+const PIXEL_PNG: &str =
+    "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8BQDwAEhQGAhKmMIQAAAABJRU5ErkJggg==";
+
+const SYSTEM: &str = "You are a helpful assistant.";
+
+fn conversation() -> Vec<Message> {
+    vec![
+        Message::user().with_text("What's the weather in Paris and in Tokyo?"),
+        Message::assistant()
+            .with_text("Let me check both cities.")
+            .with_tool_request(
+                "call_1",
+                Ok(ToolCall::new("get_weather", json!({"location": "Paris"}))),
+            )
+            .with_tool_request(
+                "call_2",
+                Ok(ToolCall::new("get_weather", json!({"location": "Tokyo"}))),
+            ),
+        Message::user()
+            .with_tool_response("call_1", Ok(vec![Content::text("18°C and sunny")]))
+            .with_tool_response("call_2", Ok(vec![Content::text("24°C and raining")])),
+        Message::assistant().with_text("Paris is 18°C and sunny, Tokyo is 24°C and raining."),
+        Message::user()
+            .with_text("Here is a picture of the sky right now, does it match?")
+            .with_image(PIXEL_PNG, "image/png"),
+    ]
+}
+
+fn tools() -> Vec<Tool> {
+    vec![
+        Tool::new(
+            "get_weather",
+            "Get the current weather for a location",
+            json!({
+                "type": "object",
+                "required": ["location"],
+                "properties": {
+                    "location": {"type": "string", "description": "The city"}
+                }
+            }),
+        ),
+        Tool::new(
+            "get_time",
+            "Get the current time",
+            json!({"type": "object", "properties": {}}),
+        ),
+    ]
+}
+
+fn model(name: &str) -> ModelConfig {
+    ModelConfig::new(name.to_string())
+        .with_temperature(Some(0.5))
+        .with_max_tokens(Some(1024))
+}
+
+fn assert_golden(name: &str, payload: Value) -> Result<()> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.json", name));
+    let actual = serde_json::to_string_pretty(&payload)? + "\n";
+
+    if std::env::var("GOOSE_UPDATE_GOLDEN").is_ok() {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, actual)?;
+        return Ok(());
+    }
+
+    let expected = std::fs::read_to_string(&path).map_err(|e| {
+        anyhow::anyhow!(
+            "Missing golden file {} ({}), run with GOOSE_UPDATE_GOLDEN=1 to create it",
+            path.display(),
+            e
+        )
+    })?;
+    assert!(
+        expected == actual,
+        "Payload for {} differs from {}, run with GOOSE_UPDATE_GOLDEN=1 to update it if the \
+        change is intended\n\nexpected:\n{}\nactual:\n{}",
+        name,
+        path.display(),
+        expected,
+        actual
+    );
+    Ok(())
+}
+
+#[test]
+fn test_openai_request() -> Result<()> {
+    let payload = openai::create_request(
+        &model("gpt-4o"),
+        SYSTEM,
+        &conversation(),
+        &tools(),
+        &ImageFormat::OpenAi,
+    )?;
+    assert_golden("openai", payload)
+}
+
+#[test]
+fn test_openai_reasoning_request() -> Result<()> {
+    let payload = openai::create_request(
+        &model("o3-mini-high"),
+        SYSTEM,
+        &conversation(),
+        &tools(),
+        &ImageFormat::OpenAi,
+    )?;
+    assert_golden("openai_reasoning", payload)
+}
+
+#[test]
+fn test_openai_with_anthropic_images_request() -> Result<()> {
+    let payload = openai::create_request(
+        &model("claude-3-5-sonnet"),
+        SYSTEM,
+        &conversation(),
+        &tools(),
+        &ImageFormat::Anthropic,
+    )?;
+    assert_golden("openai_anthropic_images", payload)
+}
+
+#[test]
+fn test_anthropic_request() -> Result<()> {
+    let payload = anthropic::create_request(
+        &model("claude-3-5-sonnet-latest"),
+        SYSTEM,
+        &conversation(),
+        &tools(),
+    )?;
+    assert_golden("anthropic", payload)
+}
+
+#[test]
+fn test_bedrock_request() -> Result<()> {
+    // Bedrock doesn't take images yet
+    let messages: Vec<Message> = conversation()
+        .into_iter()
+        .map(|mut message| {
+            message
+                .content
+                .retain(|content| !matches!(content, MessageContent::Image(_)));
+            message
+        })
+        .collect();
+    let model = model("anthropic.claude-3-5-sonnet-20240620-v1:0");
+    let request = bedrock::create_request(&model, SYSTEM, &messages, &tools())?;
+    assert_golden("bedrock", request.to_json(&model.model_name)?)
+}
+
+#[test]
+fn test_google_request() -> Result<()> {
+    let payload = google::create_request(
+        &model("gemini-2.0-flash"),
+        SYSTEM,
+        &conversation(),
+        &tools(),
+    )?;
+    assert_golden("google", payload)
+}
@@ -4,7 +4,10 @@ use goose::message::{Message, MessageContent};
 use goose::providers::base::Provider;
 use goose::providers::errors::ProviderError;
 use goose::providers::{
-    anthropic, azure, bedrock, databricks, google, groq, ollama, openai, openrouter,
+    ai21, anthropic, anthropic_vertex, azure, azure_ai, bedrock, cloudflare, cohere, databricks,
+    deepseek, fireworks, github, google, groq, huggingface, koboldcpp, llamacpp, lmstudio,
+    moonshot, nvidia, oci, ollama, openai, openrouter, perplexity, qwen, replicate, sagemaker,
+    snowflake, tgi, together, vertexai, vllm, watsonx, xai, zhipu,
 };
 use mcp_core::content::Content;
 use mcp_core::tool::Tool;
@@ -256,7 +259,11 @@ impl ProviderTester {
     /// Run all provider tests
     async fn run_test_suite(&self) -> Result<()> {
         self.test_basic_response().await?;
-        self.test_tool_usage().await?;
+        // Perplexity's models search the web instead of calling tools, Replicate's only
+        // generate text
+        if self.name != "Perplexity" && self.name != "Replicate" {
+            self.test_tool_usage().await?;
+        }
         self.test_context_length_exceeded_error().await?;
         Ok(())
     }
@@ -350,6 +357,28 @@ where
     }
 }
 
+#[tokio::test]
+async fn test_moonshot_provider() -> Result<()> {
+    test_provider(
+        "Moonshot",
+        &["MOONSHOT_API_KEY"],
+        None,
+        moonshot::MoonshotProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_nvidia_provider() -> Result<()> {
+    test_provider(
+        "NVIDIA",
+        &["NVIDIA_API_KEY"],
+        None,
+        nvidia::NvidiaProvider::default,
+    )
+    .await
+}
+
 #[tokio::test]
 async fn test_openai_provider() -> Result<()> {
     test_provider(
@@ -376,6 +405,17 @@ async fn test_azure_provider() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_azure_ai_provider() -> Result<()> {
+    test_provider(
+        "Azure AI Foundry",
+        &["AZURE_AI_API_KEY", "AZURE_AI_ENDPOINT"],
+        None,
+        azure_ai::AzureAiProvider::default,
+    )
+    .await
+}
+
 #[tokio::test]
 async fn test_bedrock_provider_long_term_credentials() -> Result<()> {
     test_provider(
@@ -440,11 +480,261 @@ async fn test_ollama_provider() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_github_models_provider() -> Result<()> {
+    test_provider(
+        "GitHub Models",
+        &["GITHUB_TOKEN"],
+        None,
+        github::GitHubModelsProvider::default,
+    )
+    .await
+}
+
 #[tokio::test]
 async fn test_groq_provider() -> Result<()> {
     test_provider("Groq", &["GROQ_API_KEY"], None, groq::GroqProvider::default).await
 }
 
+#[tokio::test]
+async fn test_cohere_provider() -> Result<()> {
+    test_provider(
+        "Cohere",
+        &["COHERE_API_KEY"],
+        None,
+        cohere::CohereProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_together_provider() -> Result<()> {
+    test_provider(
+        "Together",
+        &["TOGETHER_API_KEY"],
+        None,
+        together::TogetherProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_fireworks_provider() -> Result<()> {
+    test_provider(
+        "Fireworks",
+        &["FIREWORKS_API_KEY"],
+        None,
+        fireworks::FireworksProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_deepseek_provider() -> Result<()> {
+    test_provider(
+        "DeepSeek",
+        &["DEEPSEEK_API_KEY"],
+        None,
+        deepseek::DeepSeekProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_watsonx_provider() -> Result<()> {
+    test_provider(
+        "watsonx",
+        &["WATSONX_API_KEY", "WATSONX_PROJECT_ID"],
+        None,
+        watsonx::WatsonxProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_snowflake_provider() -> Result<()> {
+    test_provider(
+        "Snowflake",
+        &[
+            "SNOWFLAKE_ACCOUNT",
+            "SNOWFLAKE_USER",
+            "SNOWFLAKE_PRIVATE_KEY",
+            "SNOWFLAKE_PUBLIC_KEY_FP",
+        ],
+        None,
+        snowflake::SnowflakeProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_oci_provider() -> Result<()> {
+    test_provider(
+        "OCI",
+        &[
+            "OCI_TENANCY_OCID",
+            "OCI_USER_OCID",
+            "OCI_FINGERPRINT",
+            "OCI_PRIVATE_KEY",
+        ],
+        None,
+        oci::OciProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_xai_provider() -> Result<()> {
+    test_provider("xAI", &["XAI_API_KEY"], None, xai::XaiProvider::default).await
+}
+
+#[tokio::test]
+async fn test_zhipu_provider() -> Result<()> {
+    test_provider(
+        "Zhipu",
+        &["ZHIPU_API_KEY"],
+        None,
+        zhipu::ZhipuProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_perplexity_provider() -> Result<()> {
+    test_provider(
+        "Perplexity",
+        &["PERPLEXITY_API_KEY"],
+        None,
+        perplexity::PerplexityProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_huggingface_provider() -> Result<()> {
+    test_provider(
+        "Hugging Face",
+        &["HF_TOKEN"],
+        None,
+        huggingface::HuggingFaceProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_llamacpp_provider() -> Result<()> {
+    test_provider(
+        "llama.cpp",
+        &["LLAMACPP_HOST"],
+        None,
+        llamacpp::LlamaCppProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_koboldcpp_provider() -> Result<()> {
+    test_provider(
+        "KoboldCpp",
+        &["KOBOLDCPP_HOST"],
+        None,
+        koboldcpp::KoboldCppProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_lmstudio_provider() -> Result<()> {
+    test_provider(
+        "LM Studio",
+        &["LMSTUDIO_HOST"],
+        None,
+        lmstudio::LmStudioProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_qwen_provider() -> Result<()> {
+    test_provider(
+        "Qwen",
+        &["DASHSCOPE_API_KEY"],
+        None,
+        qwen::QwenProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_replicate_provider() -> Result<()> {
+    test_provider(
+        "Replicate",
+        &["REPLICATE_API_TOKEN"],
+        None,
+        replicate::ReplicateProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_sagemaker_provider() -> Result<()> {
+    test_provider(
+        "SageMaker",
+        &["SAGEMAKER_ENDPOINT_NAME"],
+        None,
+        sagemaker::SageMakerProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_cloudflare_provider() -> Result<()> {
+    test_provider(
+        "Cloudflare",
+        &["CLOUDFLARE_ACCOUNT_ID", "CLOUDFLARE_API_TOKEN"],
+        None,
+        cloudflare::CloudflareProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_vertex_ai_provider() -> Result<()> {
+    test_provider(
+        "Vertex AI",
+        &["GCP_PROJECT_ID"],
+        None,
+        vertexai::VertexAiProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_anthropic_vertex_provider() -> Result<()> {
+    test_provider(
+        "Anthropic on Vertex AI",
+        &["GCP_PROJECT_ID"],
+        None,
+        anthropic_vertex::AnthropicVertexProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_tgi_provider() -> Result<()> {
+    test_provider("TGI", &["TGI_HOST"], None, tgi::TgiProvider::default).await
+}
+
+#[tokio::test]
+async fn test_vllm_provider() -> Result<()> {
+    test_provider("vLLM", &["VLLM_HOST"], None, vllm::VllmProvider::default).await
+}
+
+#[tokio::test]
+async fn test_ai21_provider() -> Result<()> {
+    test_provider("AI21", &["AI21_API_KEY"], None, ai21::Ai21Provider::default).await
+}
+
 #[tokio::test]
 async fn test_anthropic_provider() -> Result<()> {
     test_provider(
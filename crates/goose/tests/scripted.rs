@@ -0,0 +1,50 @@
+use anyhow::Result;
+use futures::StreamExt;
+use goose::agents::AgentFactory;
+use goose::message::{Message, MessageContent};
+use goose::model::ModelConfig;
+use goose::providers::scripted::{Scenario, ScriptedProvider};
+use std::path::PathBuf;
+
+fn scenario(name: &str) -> Result<Scenario> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("scenarios")
+        .join(name);
+    Scenario::load(path)
+}
+
+#[tokio::test]
+async fn test_agent_follows_scenario() -> Result<()> {
+    let provider = ScriptedProvider::new(
+        scenario("tool_then_answer.yaml")?,
+        ModelConfig::new("scripted".to_string()),
+    );
+    let agent = AgentFactory::create("truncate", Box::new(provider)).unwrap();
+
+    let messages = vec![Message::user().with_text("What's the weather in San Francisco?")];
+    let reply_stream = agent.reply(&messages).await?;
+    tokio::pin!(reply_stream);
+
+    let mut responses = Vec::new();
+    while let Some(response) = reply_stream.next().await {
+        responses.push(response?);
+    }
+
+    // The tool request, its (failed) result since no extension provides it, and the answer
+    assert_eq!(responses.len(), 3);
+    let request = responses[0].content[1].as_tool_request().unwrap();
+    assert_eq!(
+        request.tool_call.as_ref().unwrap().name,
+        "weather__get_forecast"
+    );
+    assert!(matches!(
+        &responses[1].content[0],
+        MessageContent::ToolResponse(response) if response.tool_result.is_err()
+    ));
+    assert_eq!(
+        responses[2].as_concat_text(),
+        "It's sunny in San Francisco today."
+    );
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use goose::message::Message;
+use goose::model::ModelConfig;
+use goose::providers::base::Provider;
+use goose::providers::openai::OpenAiProvider;
+use goose::providers::vcr::Vcr;
+use mcp_core::tool::Tool;
+use serde_json::json;
+use std::path::PathBuf;
+
+fn cassette(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("cassettes")
+        .join(name)
+}
+
+#[tokio::test]
+async fn test_openai_tool_call_replay() -> Result<()> {
+    // Replay never reaches the network, but the provider still needs a key to be created
+    std::env::set_var("OPENAI_API_KEY", "test");
+    Vcr::install(Some(Vcr::replay(cassette("openai_tool_call.json"))?));
+
+    let provider = OpenAiProvider::from_env(ModelConfig::new("gpt-4o".to_string()))?;
+    let tool = Tool::new(
+        "get_weather",
+        "Get the weather for a location",
+        json!({
+            "type": "object",
+            "required": ["location"],
+            "properties": {
+                "location": {"type": "string", "description": "The city"}
+            }
+        }),
+    );
+
+    let (message, usage) = provider
+        .complete(
+            "You are a helpful assistant.",
+            &[Message::user().with_text("What's the weather in San Francisco?")],
+            &[tool],
+        )
+        .await?;
+
+    let request = message.content[0].as_tool_request().unwrap();
+    let call = request.tool_call.as_ref().unwrap();
+    assert_eq!(call.name, "get_weather");
+    assert_eq!(call.arguments, json!({"location": "San Francisco, CA"}));
+    assert_eq!(usage.model, "gpt-4o-2024-08-06");
+    assert_eq!(usage.usage.input_tokens, Some(62));
+    assert_eq!(usage.usage.cache_read_tokens, Some(0));
+    Ok(())
+}
@@ -7,9 +7,11 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::atomic::{AtomicU64, Ordering};
 use thiserror::Error;
-use tokio::sync::Mutex;
+use tokio::sync::broadcast;
 use tower::{Service, ServiceExt}; // for Service::ready()
 
+use crate::transport::{Notifications, ServerRequests};
+
 pub type BoxError = Box<dyn std::error::Error + Sync + Send>;
 
 /// Error type for MCP client operations.
@@ -63,9 +65,15 @@ pub struct ClientInfo {
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct ClientCapabilities {
-    // Add fields as needed. For now, empty capabilities are fine.
+    /// Set when the client answers `sampling/createMessage` requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingCapability>,
+    // Add other capabilities as needed
 }
 
+#[derive(Serialize, Deserialize, Default)]
+pub struct SamplingCapability {}
+
 #[derive(Serialize, Deserialize)]
 pub struct InitializeParams {
     #[serde(rename = "protocolVersion")]
@@ -90,9 +98,24 @@ pub trait McpClientTrait: Send + Sync {
 
     async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, Error>;
 
+    /// Ask the server to send `notifications/resources/updated` when the resource changes
+    async fn subscribe_resource(&self, uri: &str) -> Result<(), Error>;
+
+    async fn unsubscribe_resource(&self, uri: &str) -> Result<(), Error>;
+
     async fn list_tools(&self, next_cursor: Option<String>) -> Result<ListToolsResult, Error>;
 
     async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, Error>;
+
+    /// Receive the notifications the server sends, if the transport delivers them
+    fn notifications(&self) -> Option<broadcast::Receiver<JsonRpcNotification>> {
+        None
+    }
+
+    /// Take the requests the server sends, if the transport delivers them
+    fn server_requests(&mut self) -> Option<ServerRequests> {
+        None
+    }
 }
 
 /// The MCP client is the interface for MCP operations.
@@ -102,10 +125,12 @@ where
     S::Error: Into<Error>,
     S::Future: Send,
 {
-    service: Mutex<S>,
+    service: S,
     next_id: AtomicU64,
     server_capabilities: Option<ServerCapabilities>,
     server_info: Option<Implementation>,
+    notifications: Option<Notifications>,
+    server_requests: Option<ServerRequests>,
 }
 
 impl<S> McpClient<S>
@@ -116,29 +141,51 @@ where
 {
     pub fn new(service: S) -> Self {
         Self {
-            service: Mutex::new(service),
+            service,
             next_id: AtomicU64::new(1),
             server_capabilities: None,
             server_info: None,
+            notifications: None,
+            server_requests: None,
         }
     }
 
+    /// Deliver the notifications the transport receives, see `TransportHandle::notifications`
+    pub fn with_notifications(mut self, notifications: Option<Notifications>) -> Self {
+        self.notifications = notifications;
+        self
+    }
+
+    /// Deliver the requests the transport receives, see `TransportHandle::server_requests`
+    pub fn with_server_requests(mut self, server_requests: Option<ServerRequests>) -> Self {
+        self.server_requests = server_requests;
+        self
+    }
+
     /// Send a JSON-RPC request and check we don't get an error response.
+    ///
+    /// The transport matches responses to requests by id, so each request goes through its
+    /// own clone of the service and several can be in flight at once.
     async fn send_request<R>(&self, method: &str, params: Value) -> Result<R, Error>
     where
         R: for<'de> Deserialize<'de>,
     {
-        let mut service = self.service.lock().await;
+        let mut service = self.service.clone();
         service.ready().await.map_err(|_| Error::NotReady)?;
 
-        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let request_id = self.next_id.fetch_add(1, Ordering::SeqCst);
         let request = JsonRpcMessage::Request(JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
-            id: Some(id),
+            id: Some(request_id),
             method: method.to_string(),
             params: Some(params.clone()),
         });
 
+        // Servers must not be asked to cancel their initialization
+        let cancel = CancelOnDrop {
+            service: (method != "initialize").then(|| self.service.clone()),
+            request_id,
+        };
         let response_msg = service
             .call(request)
             .await
@@ -152,13 +199,14 @@ where
                 // we don't need include params because it can be really large
                 source: Box::new(e.into()),
             })?;
+        cancel.disarm();
 
         match response_msg {
             JsonRpcMessage::Response(JsonRpcResponse {
                 id, result, error, ..
             }) => {
                 // Verify id matches
-                if id != Some(self.next_id.load(Ordering::SeqCst) - 1) {
+                if id != Some(request_id) {
                     return Err(Error::UnexpectedResponse(
                         "id mismatch for JsonRpcResponse".to_string(),
                     ));
@@ -175,7 +223,7 @@ where
                 }
             }
             JsonRpcMessage::Error(JsonRpcError { id, error, .. }) => {
-                if id != Some(self.next_id.load(Ordering::SeqCst) - 1) {
+                if id != Some(request_id) {
                     return Err(Error::UnexpectedResponse(
                         "id mismatch for JsonRpcError".to_string(),
                     ));
@@ -196,7 +244,7 @@ where
 
     /// Send a JSON-RPC notification.
     async fn send_notification(&self, method: &str, params: Value) -> Result<(), Error> {
-        let mut service = self.service.lock().await;
+        let mut service = self.service.clone();
         service.ready().await.map_err(|_| Error::NotReady)?;
 
         let notification = JsonRpcMessage::Notification(JsonRpcNotification {
@@ -226,6 +274,79 @@ where
     fn completed_initialization(&self) -> bool {
         self.server_capabilities.is_some()
     }
+
+    fn supports_resource_subscriptions(&self) -> Result<(), Error> {
+        if !self.completed_initialization() {
+            return Err(Error::NotInitialized);
+        }
+        let subscribe = self
+            .server_capabilities
+            .as_ref()
+            .unwrap()
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.subscribe)
+            .unwrap_or(false);
+        if !subscribe {
+            return Err(Error::RpcError {
+                code: METHOD_NOT_FOUND,
+                message: "Server does not support resource subscriptions".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Sends `notifications/cancelled` for a request when dropped before it is disarmed
+///
+/// That happens when the caller stops waiting for the response, such as for a tool call
+/// that ran past its timeout, or when the transport gives up on it, so the server can stop
+/// working on it.
+struct CancelOnDrop<S>
+where
+    S: Service<JsonRpcMessage> + Send + 'static,
+    S::Future: Send,
+{
+    service: Option<S>,
+    request_id: u64,
+}
+
+impl<S> CancelOnDrop<S>
+where
+    S: Service<JsonRpcMessage> + Send + 'static,
+    S::Future: Send,
+{
+    fn disarm(mut self) {
+        self.service = None;
+    }
+}
+
+impl<S> Drop for CancelOnDrop<S>
+where
+    S: Service<JsonRpcMessage> + Send + 'static,
+    S::Future: Send,
+{
+    fn drop(&mut self) {
+        let Some(mut service) = self.service.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/cancelled".to_string(),
+            params: Some(serde_json::json!({
+                "requestId": self.request_id,
+                "reason": "The client stopped waiting for the response",
+            })),
+        });
+        runtime.spawn(async move {
+            if service.ready().await.is_ok() {
+                let _ = service.call(notification).await;
+            }
+        });
+    }
 }
 
 #[async_trait::async_trait]
@@ -309,6 +430,20 @@ where
         self.send_request("resources/read", params).await
     }
 
+    async fn subscribe_resource(&self, uri: &str) -> Result<(), Error> {
+        self.supports_resource_subscriptions()?;
+        let params = serde_json::json!({ "uri": uri });
+        let _: Value = self.send_request("resources/subscribe", params).await?;
+        Ok(())
+    }
+
+    async fn unsubscribe_resource(&self, uri: &str) -> Result<(), Error> {
+        self.supports_resource_subscriptions()?;
+        let params = serde_json::json!({ "uri": uri });
+        let _: Value = self.send_request("resources/unsubscribe", params).await?;
+        Ok(())
+    }
+
     async fn list_tools(&self, next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
         if !self.completed_initialization() {
             return Err(Error::NotInitialized);
@@ -346,4 +481,98 @@ where
         // https://modelcontextprotocol.io/docs/concepts/tools#error-handling-2
         self.send_request("tools/call", params).await
     }
+
+    fn notifications(&self) -> Option<broadcast::Receiver<JsonRpcNotification>> {
+        self.notifications.as_ref().map(Notifications::subscribe)
+    }
+
+    fn server_requests(&mut self) -> Option<ServerRequests> {
+        self.server_requests.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::BoxFuture;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    // Answers the initialization, never answers any other request, and records what is sent
+    #[derive(Clone, Default)]
+    struct ScriptedService {
+        sent: Arc<Mutex<Vec<JsonRpcMessage>>>,
+    }
+
+    impl Service<JsonRpcMessage> for ScriptedService {
+        type Response = JsonRpcMessage;
+        type Error = crate::transport::Error;
+        type Future = BoxFuture<'static, Result<JsonRpcMessage, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, message: JsonRpcMessage) -> Self::Future {
+            self.sent.lock().unwrap().push(message.clone());
+            Box::pin(async move {
+                match message {
+                    JsonRpcMessage::Request(JsonRpcRequest { id, method, .. })
+                        if method == "initialize" =>
+                    {
+                        Ok(JsonRpcMessage::Response(JsonRpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            id,
+                            result: Some(serde_json::json!({
+                                "protocolVersion": "1.0.0",
+                                "capabilities": { "tools": {} },
+                                "serverInfo": { "name": "scripted", "version": "1.0.0" },
+                            })),
+                            error: None,
+                        }))
+                    }
+                    JsonRpcMessage::Request(_) => futures::future::pending().await,
+                    message => Ok(message),
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_request_is_cancelled() {
+        let service = ScriptedService::default();
+        let mut client = McpClient::new(service.clone());
+        client
+            .initialize(
+                ClientInfo {
+                    name: "test".to_string(),
+                    version: "1.0.0".to_string(),
+                },
+                ClientCapabilities::default(),
+            )
+            .await
+            .unwrap();
+
+        let call = client.call_tool("slow", serde_json::json!({}));
+        assert!(tokio::time::timeout(Duration::from_millis(10), call)
+            .await
+            .is_err());
+        // The notification is sent from a task of its own
+        tokio::task::yield_now().await;
+
+        let sent = service.sent.lock().unwrap();
+        let cancelled: Vec<_> = sent
+            .iter()
+            .filter_map(|message| match message {
+                JsonRpcMessage::Notification(n) if n.method == "notifications/cancelled" => {
+                    n.params.clone()
+                }
+                _ => None,
+            })
+            .collect();
+        // Only the tool call, the second request, was cancelled
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0]["requestId"], 2);
+    }
 }
@@ -1,8 +1,9 @@
 use async_trait::async_trait;
-use mcp_core::protocol::JsonRpcMessage;
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcNotification, JsonRpcRequest};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
-use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
 
 pub type BoxError = Box<dyn std::error::Error + Sync + Send>;
 /// A generic error type for transport operations.
@@ -20,7 +21,7 @@ pub enum Error {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
-    #[error("Unsupported message type. JsonRpcMessage can only be Request or Notification.")]
+    #[error("Unsupported message type. JsonRpcMessage::Nil cannot be sent.")]
     UnsupportedMessage,
 
     #[error("Stdio process error: {0}")]
@@ -29,8 +30,14 @@ pub enum Error {
     #[error("SSE connection error: {0}")]
     SseConnection(String),
 
+    #[error("Streamable HTTP error: {0}")]
+    StreamableHttpError(String),
+
     #[error("HTTP error: {status} - {message}")]
     HttpError { status: u16, message: String },
+
+    #[error("Authorization failed: {0}")]
+    Authorization(String),
 }
 
 /// A message that can be sent through the transport
@@ -58,6 +65,136 @@ pub trait Transport {
 #[async_trait]
 pub trait TransportHandle: Send + Sync + Clone + 'static {
     async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error>;
+
+    /// The notifications the server sends, for transports that deliver them
+    fn notifications(&self) -> Option<Notifications> {
+        None
+    }
+
+    /// The requests the server sends, for transports that deliver them; only the first call
+    /// gets them
+    fn server_requests(&self) -> Option<ServerRequests> {
+        None
+    }
+}
+
+/// Authorizes the requests of HTTP transports, e.g. with OAuth tokens that expire
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// The `Authorization` header for the next request, None to send none
+    async fn authorization(&self) -> Option<String>;
+
+    /// The server rejected the request with 401; get a new header, e.g. by refreshing the
+    /// token or signing in again
+    async fn reauthorize(&self) -> Result<String, Error>;
+}
+
+/// Fans out the notifications a server sends to every subscriber
+///
+/// Notifications are dropped when nobody is subscribed, and a subscriber that falls too far
+/// behind skips the oldest ones.
+#[derive(Clone)]
+pub struct Notifications {
+    sender: broadcast::Sender<JsonRpcNotification>,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(64);
+        Self { sender }
+    }
+
+    pub fn publish(&self, notification: JsonRpcNotification) {
+        // An error only means there are no subscribers
+        let _ = self.sender.send(notification);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.sender.subscribe()
+    }
+}
+
+/// Requests the server sends to the client, such as `sampling/createMessage`
+///
+/// Each one must be answered through the `Responder`, or the server waits until it gives up.
+pub struct ServerRequests {
+    receiver: mpsc::Receiver<JsonRpcRequest>,
+    responder: Responder,
+}
+
+impl ServerRequests {
+    /// Create the channel a transport actor forwards server requests on, answered by sending
+    /// on `outgoing`
+    pub fn channel(outgoing: &mpsc::Sender<TransportMessage>) -> (ServerRequestSender, Self) {
+        let (sender, receiver) = mpsc::channel(32);
+        let requests = Self {
+            receiver,
+            responder: Responder {
+                outgoing: outgoing.downgrade(),
+            },
+        };
+        (ServerRequestSender { sender }, requests)
+    }
+
+    /// The next request, None once the transport has closed
+    pub async fn recv(&mut self) -> Option<JsonRpcRequest> {
+        self.receiver.recv().await
+    }
+
+    pub fn responder(&self) -> Responder {
+        self.responder.clone()
+    }
+}
+
+/// Answers server requests without keeping the transport open
+#[derive(Clone)]
+pub struct Responder {
+    outgoing: mpsc::WeakSender<TransportMessage>,
+}
+
+impl Responder {
+    pub async fn respond(&self, message: JsonRpcMessage) -> Result<(), Error> {
+        let outgoing = self.outgoing.upgrade().ok_or(Error::NotConnected)?;
+        send_message(&outgoing, message).await.map(|_| ())
+    }
+}
+
+/// The transport actor's end of `ServerRequests`
+#[derive(Clone)]
+pub struct ServerRequestSender {
+    sender: mpsc::Sender<JsonRpcRequest>,
+}
+
+impl ServerRequestSender {
+    pub fn forward(&self, request: JsonRpcRequest) {
+        if let Err(e) = self.sender.try_send(request) {
+            tracing::warn!("Dropped a request from the server: {e}");
+        }
+    }
+}
+
+/// Holds `ServerRequests` for a transport handle until the client takes them
+#[derive(Clone, Default)]
+pub struct PendingServerRequests {
+    requests: Arc<Mutex<Option<ServerRequests>>>,
+}
+
+impl PendingServerRequests {
+    pub fn new(requests: ServerRequests) -> Self {
+        Self {
+            requests: Arc::new(Mutex::new(Some(requests))),
+        }
+    }
+
+    pub fn take(&self) -> Option<ServerRequests> {
+        self.requests.lock().unwrap().take()
+    }
 }
 
 // Helper function that contains the common send implementation
@@ -75,9 +212,12 @@ pub async fn send_message(
             sender.send(msg).await.map_err(|_| Error::ChannelClosed)?;
             Ok(response.await.map_err(|_| Error::ChannelClosed)??)
         }
-        JsonRpcMessage::Notification(notification) => {
+        // Notifications, and answers to requests from the server, get no response
+        message @ (JsonRpcMessage::Notification(_)
+        | JsonRpcMessage::Response(_)
+        | JsonRpcMessage::Error(_)) => {
             let msg = TransportMessage {
-                message: JsonRpcMessage::Notification(notification),
+                message,
                 response_tx: None,
             };
             sender.send(msg).await.map_err(|_| Error::ChannelClosed)?;
@@ -125,3 +265,6 @@ pub use stdio::StdioTransport;
 
 pub mod sse;
 pub use sse::SseTransport;
+
+pub mod streamable_http;
+pub use streamable_http::StreamableHttpTransport;
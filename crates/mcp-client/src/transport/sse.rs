@@ -1,4 +1,7 @@
-use crate::transport::{Error, PendingRequests, TransportMessage};
+use crate::transport::{
+    Error, Notifications, PendingRequests, PendingServerRequests, ServerRequestSender,
+    ServerRequests, TransportMessage,
+};
 use async_trait::async_trait;
 use eventsource_client::{Client, SSE};
 use futures::TryStreamExt;
@@ -30,6 +33,12 @@ pub struct SseActor {
     http_client: HttpClient,
     /// The discovered endpoint for POST requests (once "endpoint" SSE event arrives)
     post_endpoint: Arc<RwLock<Option<String>>>,
+    /// Extra headers sent with the SSE connection and every POST, e.g. for authorization
+    headers: HashMap<String, String>,
+    /// Where notifications from the server are published
+    notifications: Notifications,
+    /// Where requests from the server are forwarded
+    server_requests: ServerRequestSender,
 }
 
 impl SseActor {
@@ -38,12 +47,18 @@ impl SseActor {
         pending_requests: Arc<PendingRequests>,
         sse_url: String,
         post_endpoint: Arc<RwLock<Option<String>>>,
+        headers: HashMap<String, String>,
+        notifications: Notifications,
+        server_requests: ServerRequestSender,
     ) -> Self {
         Self {
             receiver,
             pending_requests,
             sse_url,
             post_endpoint,
+            headers,
+            notifications,
+            server_requests,
             http_client: HttpClient::new(),
         }
     }
@@ -55,12 +70,16 @@ impl SseActor {
         tokio::join!(
             Self::handle_incoming_messages(
                 self.sse_url.clone(),
+                self.headers.clone(),
                 Arc::clone(&self.pending_requests),
-                Arc::clone(&self.post_endpoint)
+                Arc::clone(&self.post_endpoint),
+                self.notifications,
+                self.server_requests,
             ),
             Self::handle_outgoing_messages(
                 self.receiver,
                 self.http_client.clone(),
+                self.headers,
                 Arc::clone(&self.post_endpoint),
                 Arc::clone(&self.pending_requests),
             )
@@ -73,10 +92,17 @@ impl SseActor {
     ///   and respond to pending requests if it's a `Response`.
     async fn handle_incoming_messages(
         sse_url: String,
+        headers: HashMap<String, String>,
         pending_requests: Arc<PendingRequests>,
         post_endpoint: Arc<RwLock<Option<String>>>,
+        notifications: Notifications,
+        server_requests: ServerRequestSender,
     ) {
-        let client = match eventsource_client::ClientBuilder::for_url(&sse_url) {
+        let builder = headers.iter().fold(
+            eventsource_client::ClientBuilder::for_url(&sse_url),
+            |builder, (name, value)| builder.and_then(|b| b.header(name, value)),
+        );
+        let client = match builder {
             Ok(builder) => builder.build(),
             Err(e) => {
                 pending_requests.clear().await;
@@ -110,15 +136,19 @@ impl SseActor {
                 SSE::Event(e) if e.event_type == "message" => {
                     // Attempt to parse the SSE data as a JsonRpcMessage
                     match serde_json::from_str::<JsonRpcMessage>(&e.data) {
-                        Ok(message) => {
+                        Ok(message) => match message {
                             // If it's a response, complete the pending request
-                            if let JsonRpcMessage::Response(resp) = &message {
+                            JsonRpcMessage::Response(ref resp) => {
                                 if let Some(id) = &resp.id {
                                     pending_requests.respond(&id.to_string(), Ok(message)).await;
                                 }
                             }
-                            // If it's something else (notification, etc.), handle as needed
-                        }
+                            JsonRpcMessage::Notification(notification) => {
+                                notifications.publish(notification)
+                            }
+                            JsonRpcMessage::Request(request) => server_requests.forward(request),
+                            _ => {}
+                        },
                         Err(err) => {
                             warn!("Failed to parse SSE message: {err}");
                         }
@@ -139,6 +169,7 @@ impl SseActor {
     async fn handle_outgoing_messages(
         mut receiver: mpsc::Receiver<TransportMessage>,
         http_client: HttpClient,
+        headers: HashMap<String, String>,
         post_endpoint: Arc<RwLock<Option<String>>>,
         pending_requests: Arc<PendingRequests>,
     ) {
@@ -174,8 +205,12 @@ impl SseActor {
             }
 
             // Perform the HTTP POST
-            match http_client
-                .post(&post_url)
+            let request = headers
+                .iter()
+                .fold(http_client.post(&post_url), |request, (name, value)| {
+                    request.header(name, value)
+                });
+            match request
                 .header("Content-Type", "application/json")
                 .body(message_str)
                 .send()
@@ -208,6 +243,8 @@ impl SseActor {
 #[derive(Clone)]
 pub struct SseTransportHandle {
     sender: mpsc::Sender<TransportMessage>,
+    notifications: Notifications,
+    server_requests: PendingServerRequests,
 }
 
 #[async_trait::async_trait]
@@ -215,12 +252,21 @@ impl TransportHandle for SseTransportHandle {
     async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
         send_message(&self.sender, message).await
     }
+
+    fn notifications(&self) -> Option<Notifications> {
+        Some(self.notifications.clone())
+    }
+
+    fn server_requests(&self) -> Option<ServerRequests> {
+        self.server_requests.take()
+    }
 }
 
 #[derive(Clone)]
 pub struct SseTransport {
     sse_url: String,
     env: HashMap<String, String>,
+    headers: HashMap<String, String>,
 }
 
 /// The SSE transport spawns an `SseActor` on `start()`.
@@ -229,9 +275,17 @@ impl SseTransport {
         Self {
             sse_url: sse_url.into(),
             env,
+            headers: HashMap::new(),
         }
     }
 
+    /// Send these headers when connecting to the server and with every message, e.g. an
+    /// `Authorization` header for a hosted server
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers = headers;
+        self
+    }
+
     /// Waits for the endpoint to be set, up to 10 attempts.
     async fn wait_for_endpoint(
         post_endpoint: Arc<RwLock<Option<String>>>,
@@ -267,6 +321,8 @@ impl Transport for SseTransport {
 
         let post_endpoint: Arc<RwLock<Option<String>>> = Arc::new(RwLock::new(None));
         let post_endpoint_clone = Arc::clone(&post_endpoint);
+        let notifications = Notifications::new();
+        let (request_sender, server_requests) = ServerRequests::channel(&tx);
 
         // Build the actor
         let actor = SseActor::new(
@@ -274,6 +330,9 @@ impl Transport for SseTransport {
             Arc::new(PendingRequests::new()),
             self.sse_url.clone(),
             post_endpoint,
+            self.headers.clone(),
+            notifications.clone(),
+            request_sender,
         );
 
         // Spawn the actor task
@@ -286,7 +345,11 @@ impl Transport for SseTransport {
         )
         .await
         {
-            Ok(_) => Ok(SseTransportHandle { sender: tx }),
+            Ok(_) => Ok(SseTransportHandle {
+                sender: tx,
+                notifications,
+                server_requests: PendingServerRequests::new(server_requests),
+            }),
             Err(e) => Err(Error::SseConnection(e.to_string())),
         }
     }
@@ -7,7 +7,10 @@ use mcp_core::protocol::JsonRpcMessage;
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::{mpsc, Mutex};
 
-use super::{send_message, Error, PendingRequests, Transport, TransportHandle, TransportMessage};
+use super::{
+    send_message, Error, Notifications, PendingRequests, PendingServerRequests,
+    ServerRequestSender, ServerRequests, Transport, TransportHandle, TransportMessage,
+};
 
 /// A `StdioTransport` uses a child process's stdin/stdout as a communication channel.
 ///
@@ -15,6 +18,8 @@ use super::{send_message, Error, PendingRequests, Transport, TransportHandle, Tr
 pub struct StdioActor {
     receiver: mpsc::Receiver<TransportMessage>,
     pending_requests: Arc<PendingRequests>,
+    notifications: Notifications,
+    server_requests: ServerRequestSender,
     _process: Child, // we store the process to keep it alive
     error_sender: mpsc::Sender<Error>,
     stdin: ChildStdin,
@@ -26,7 +31,12 @@ impl StdioActor {
     pub async fn run(mut self) {
         use tokio::pin;
 
-        let incoming = Self::handle_incoming_messages(self.stdout, self.pending_requests.clone());
+        let incoming = Self::handle_incoming_messages(
+            self.stdout,
+            self.pending_requests.clone(),
+            self.notifications.clone(),
+            self.server_requests.clone(),
+        );
         let outgoing = Self::handle_outgoing_messages(
             self.receiver,
             self.stdin,
@@ -71,7 +81,12 @@ impl StdioActor {
         self.pending_requests.clear().await;
     }
 
-    async fn handle_incoming_messages(stdout: ChildStdout, pending_requests: Arc<PendingRequests>) {
+    async fn handle_incoming_messages(
+        stdout: ChildStdout,
+        pending_requests: Arc<PendingRequests>,
+        notifications: Notifications,
+        server_requests: ServerRequestSender,
+    ) {
         let mut reader = BufReader::new(stdout);
         let mut line = String::new();
         loop {
@@ -87,10 +102,17 @@ impl StdioActor {
                             "Received incoming message"
                         );
 
-                        if let JsonRpcMessage::Response(response) = &message {
-                            if let Some(id) = &response.id {
-                                pending_requests.respond(&id.to_string(), Ok(message)).await;
+                        match message {
+                            JsonRpcMessage::Response(ref response) => {
+                                if let Some(id) = &response.id {
+                                    pending_requests.respond(&id.to_string(), Ok(message)).await;
+                                }
+                            }
+                            JsonRpcMessage::Notification(notification) => {
+                                notifications.publish(notification)
                             }
+                            JsonRpcMessage::Request(request) => server_requests.forward(request),
+                            _ => {}
                         }
                     }
                     line.clear();
@@ -151,6 +173,8 @@ impl StdioActor {
 pub struct StdioTransportHandle {
     sender: mpsc::Sender<TransportMessage>,
     error_receiver: Arc<Mutex<mpsc::Receiver<Error>>>,
+    notifications: Notifications,
+    server_requests: PendingServerRequests,
 }
 
 #[async_trait::async_trait]
@@ -161,6 +185,14 @@ impl TransportHandle for StdioTransportHandle {
         self.check_for_errors().await?;
         result
     }
+
+    fn notifications(&self) -> Option<Notifications> {
+        Some(self.notifications.clone())
+    }
+
+    fn server_requests(&self) -> Option<ServerRequests> {
+        self.server_requests.take()
+    }
 }
 
 impl StdioTransportHandle {
@@ -244,10 +276,14 @@ impl Transport for StdioTransport {
         let (process, stdin, stdout, stderr) = self.spawn_process().await?;
         let (message_tx, message_rx) = mpsc::channel(32);
         let (error_tx, error_rx) = mpsc::channel(1);
+        let notifications = Notifications::new();
+        let (request_sender, server_requests) = ServerRequests::channel(&message_tx);
 
         let actor = StdioActor {
             receiver: message_rx,
             pending_requests: Arc::new(PendingRequests::new()),
+            notifications: notifications.clone(),
+            server_requests: request_sender,
             _process: process,
             error_sender: error_tx,
             stdin,
@@ -260,6 +296,8 @@ impl Transport for StdioTransport {
         let handle = StdioTransportHandle {
             sender: message_tx,
             error_receiver: Arc::new(Mutex::new(error_rx)),
+            notifications,
+            server_requests: PendingServerRequests::new(server_requests),
         };
         Ok(handle)
     }
@@ -0,0 +1,541 @@
+use crate::transport::{
+    AuthProvider, Error, Notifications, PendingServerRequests, ServerRequestSender, ServerRequests,
+    TransportMessage,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcRequest};
+use reqwest::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Client as HttpClient, RequestBuilder, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use super::{send_message, Transport, TransportHandle};
+
+const SESSION_ID_HEADER: &str = "Mcp-Session-Id";
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+// How many times to resume an event stream that ends before the response arrives
+const MAX_RESUME_ATTEMPTS: usize = 3;
+// Pause before reconnecting to the stream of server messages
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Everything needed to talk to the server, shared by the concurrent requests
+#[derive(Clone)]
+struct Connection {
+    http_client: HttpClient,
+    url: String,
+    headers: HashMap<String, String>,
+    auth: Option<Arc<dyn AuthProvider>>,
+    /// Assigned by the server in the response to `initialize`, sent with every later request
+    session_id: Arc<RwLock<Option<String>>>,
+    notifications: Notifications,
+    /// Where requests from the server are forwarded, None outside of the actor
+    server_requests: Option<ServerRequestSender>,
+}
+
+/// What a single event stream produced before it ended
+enum StreamOutcome {
+    Response(JsonRpcMessage),
+    Ended { last_event_id: Option<String> },
+}
+
+impl Connection {
+    async fn request(&self, builder: RequestBuilder) -> Result<Response, Error> {
+        let builder = self.headers.iter().fold(builder, |builder, (name, value)| {
+            builder.header(name, value)
+        });
+        let builder = match self.session_id.read().await.as_ref() {
+            Some(session_id) => builder.header(SESSION_ID_HEADER, session_id),
+            None => builder,
+        };
+        let retry = builder.try_clone();
+        let builder = match &self.auth {
+            Some(auth) => match auth.authorization().await {
+                Some(authorization) => builder.header(AUTHORIZATION, authorization),
+                None => builder,
+            },
+            None => builder,
+        };
+
+        let mut response = builder
+            .send()
+            .await
+            .map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+
+        // The server wants credentials, or new ones; try once more with them
+        if response.status() == StatusCode::UNAUTHORIZED {
+            if let (Some(auth), Some(retry)) = (&self.auth, retry) {
+                let authorization = auth.reauthorize().await?;
+                response = retry
+                    .header(AUTHORIZATION, authorization)
+                    .send()
+                    .await
+                    .map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+            }
+        }
+
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            *self.session_id.write().await = Some(session_id.to_string());
+        }
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND && self.session_id.write().await.take().is_some() {
+            // The server forgot the session; the client has to initialize again
+            return Err(Error::StreamableHttpError(
+                "Session expired or was terminated by the server".to_string(),
+            ));
+        }
+        if !status.is_success() {
+            return Err(Error::HttpError {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_else(|_| status.to_string()),
+            });
+        }
+        Ok(response)
+    }
+
+    async fn post(&self, message: &JsonRpcMessage) -> Result<Response, Error> {
+        let body = serde_json::to_string(message)?;
+        self.request(
+            self.http_client
+                .post(&self.url)
+                .header(ACCEPT, "application/json, text/event-stream")
+                .header(CONTENT_TYPE, "application/json")
+                .body(body),
+        )
+        .await
+    }
+
+    /// Reconnect to an event stream that was cut off, replaying events after the last one seen
+    async fn resume(&self, last_event_id: &str) -> Result<Response, Error> {
+        self.request(
+            self.http_client
+                .get(&self.url)
+                .header(ACCEPT, "text/event-stream")
+                .header(LAST_EVENT_ID_HEADER, last_event_id),
+        )
+        .await
+    }
+
+    /// Listen on the stream the server offers for messages outside of any request, such as
+    /// notifications that a resource changed
+    async fn listen(self) {
+        let mut last_event_id: Option<String> = None;
+        let mut failures = 0;
+        while self.session_id.read().await.is_some() {
+            let request = self
+                .http_client
+                .get(&self.url)
+                .header(ACCEPT, "text/event-stream");
+            let request = match &last_event_id {
+                Some(id) => request.header(LAST_EVENT_ID_HEADER, id),
+                None => request,
+            };
+            match self.request(request).await {
+                Ok(response) => {
+                    failures = 0;
+                    if let StreamOutcome::Ended {
+                        last_event_id: Some(id),
+                    } = read_event_stream(response, None, &self).await
+                    {
+                        last_event_id = Some(id);
+                    }
+                }
+                // The server does not offer the stream
+                Err(Error::HttpError { status: 405, .. }) => return,
+                Err(e) => {
+                    failures += 1;
+                    if failures > MAX_RESUME_ATTEMPTS {
+                        warn!("Giving up on the server message stream: {e}");
+                        return;
+                    }
+                }
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// Send a notification, or the answer to a request from the server
+    async fn send_notification(&self, message: JsonRpcMessage) {
+        if let Err(e) = self.post(&message).await {
+            warn!("Failed to send notification: {e}");
+        }
+    }
+
+    async fn send_request(
+        self,
+        message: JsonRpcMessage,
+        response_tx: oneshot::Sender<Result<JsonRpcMessage, Error>>,
+    ) {
+        let result = self.exchange(&message).await;
+        let _ = response_tx.send(result);
+    }
+
+    /// POST a request and wait for its response, which comes back either as the JSON body or
+    /// as an event on a stream the server opens for it
+    async fn exchange(&self, message: &JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        let id = match message {
+            JsonRpcMessage::Request(JsonRpcRequest { id: Some(id), .. }) => *id,
+            _ => return Err(Error::UnsupportedMessage),
+        };
+
+        let mut response = self.post(message).await?;
+        let mut attempts = 0;
+        loop {
+            let outcome = if is_event_stream(&response) {
+                read_event_stream(response, Some(id), self).await
+            } else {
+                let body: serde_json::Value = response
+                    .json()
+                    .await
+                    .map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+                // The body is a single message, or a batch of them
+                let messages = match body {
+                    serde_json::Value::Array(values) => values,
+                    value => vec![value],
+                };
+                let found = messages
+                    .into_iter()
+                    .filter_map(|value| serde_json::from_value::<JsonRpcMessage>(value).ok())
+                    .find(|message| response_id(message) == Some(id));
+                match found {
+                    Some(message) => StreamOutcome::Response(message),
+                    None => StreamOutcome::Ended {
+                        last_event_id: None,
+                    },
+                }
+            };
+
+            match outcome {
+                StreamOutcome::Response(message) => return Ok(message),
+                StreamOutcome::Ended {
+                    last_event_id: Some(last_event_id),
+                } if attempts < MAX_RESUME_ATTEMPTS => {
+                    attempts += 1;
+                    debug!("Resuming event stream after event {last_event_id}");
+                    response = self.resume(&last_event_id).await?;
+                }
+                StreamOutcome::Ended { .. } => {
+                    return Err(Error::StreamableHttpError(format!(
+                        "The server did not respond to request {}",
+                        id
+                    )))
+                }
+            }
+        }
+    }
+}
+
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"))
+}
+
+fn response_id(message: &JsonRpcMessage) -> Option<u64> {
+    match message {
+        JsonRpcMessage::Response(response) => response.id,
+        JsonRpcMessage::Error(error) => error.id,
+        _ => None,
+    }
+}
+
+/// Read events until the response to request `id` arrives, passing on any notifications and
+/// requests from the server
+async fn read_event_stream(
+    response: Response,
+    id: Option<u64>,
+    connection: &Connection,
+) -> StreamOutcome {
+    let mut parser = EventParser::default();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                warn!("Event stream interrupted: {e}");
+                break;
+            }
+        };
+        for event in parser.feed(&chunk) {
+            if event.event != "message" {
+                continue;
+            }
+            match serde_json::from_str::<JsonRpcMessage>(&event.data) {
+                Ok(message) if id.is_some() && response_id(&message) == id => {
+                    return StreamOutcome::Response(message)
+                }
+                Ok(JsonRpcMessage::Notification(notification)) => {
+                    connection.notifications.publish(notification)
+                }
+                Ok(JsonRpcMessage::Request(request)) => match &connection.server_requests {
+                    Some(server_requests) => server_requests.forward(request),
+                    None => debug!(request = ?request, "Ignoring server request"),
+                },
+                Ok(message) => debug!(message = ?message, "Ignoring server message"),
+                Err(err) => warn!("Failed to parse event stream message: {err}"),
+            }
+        }
+    }
+    StreamOutcome::Ended {
+        last_event_id: parser.last_event_id,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Event {
+    event: String,
+    data: String,
+}
+
+/// Incremental parser for `text/event-stream` bodies, which may be split at any byte
+#[derive(Default)]
+struct EventParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    last_event_id: Option<String>,
+}
+
+impl EventParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<Event> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                // A blank line dispatches the event
+                if !self.data.is_empty() {
+                    events.push(Event {
+                        event: self.event.take().unwrap_or_else(|| "message".to_string()),
+                        data: std::mem::take(&mut self.data).join("\n"),
+                    });
+                }
+                self.event = None;
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                "id" => self.last_event_id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// The actor posts each outgoing message; requests run concurrently, each waiting for its
+/// own response, while notifications are sent in order. Once the session is initialized it
+/// also listens for messages the server sends on its own.
+pub struct StreamableHttpActor {
+    receiver: mpsc::Receiver<TransportMessage>,
+    connection: Connection,
+}
+
+impl StreamableHttpActor {
+    pub async fn run(mut self) {
+        let mut listener: Option<JoinHandle<()>> = None;
+        while let Some(transport_msg) = self.receiver.recv().await {
+            let initialized = matches!(
+                &transport_msg.message,
+                JsonRpcMessage::Notification(n) if n.method == "notifications/initialized"
+            );
+            match transport_msg.response_tx {
+                Some(response_tx) => {
+                    tokio::spawn(
+                        self.connection
+                            .clone()
+                            .send_request(transport_msg.message, response_tx),
+                    );
+                }
+                None => {
+                    self.connection
+                        .send_notification(transport_msg.message)
+                        .await
+                }
+            }
+            if initialized && listener.is_none() {
+                listener = Some(tokio::spawn(self.connection.clone().listen()));
+            }
+        }
+        if let Some(listener) = listener {
+            listener.abort();
+        }
+        debug!("StreamableHttpActor: outgoing message loop ended");
+    }
+}
+
+#[derive(Clone)]
+pub struct StreamableHttpTransportHandle {
+    sender: mpsc::Sender<TransportMessage>,
+    notifications: Notifications,
+    server_requests: PendingServerRequests,
+}
+
+#[async_trait::async_trait]
+impl TransportHandle for StreamableHttpTransportHandle {
+    async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        send_message(&self.sender, message).await
+    }
+
+    fn notifications(&self) -> Option<Notifications> {
+        Some(self.notifications.clone())
+    }
+
+    fn server_requests(&self) -> Option<ServerRequests> {
+        self.server_requests.take()
+    }
+}
+
+/// The streamable HTTP transport of current MCP servers
+///
+/// Every message is POSTed to a single endpoint. The server answers with the response as
+/// JSON, or upgrades to an event stream that carries it; a stream that drops before the
+/// response arrives is resumed from the last event it delivered. The session id the server
+/// assigns during initialization is sent with every later request, and the session is
+/// ended on `close()`. While the session lasts, notifications the server sends outside of a
+/// request arrive on a separate stream.
+#[derive(Clone)]
+pub struct StreamableHttpTransport {
+    url: String,
+    env: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    auth: Option<Arc<dyn AuthProvider>>,
+    http_client: HttpClient,
+    session_id: Arc<RwLock<Option<String>>>,
+    notifications: Notifications,
+}
+
+impl StreamableHttpTransport {
+    pub fn new<S: Into<String>>(url: S, env: HashMap<String, String>) -> Self {
+        Self {
+            url: url.into(),
+            env,
+            headers: HashMap::new(),
+            auth: None,
+            http_client: HttpClient::new(),
+            session_id: Arc::new(RwLock::new(None)),
+            notifications: Notifications::new(),
+        }
+    }
+
+    /// Send these headers with every request, e.g. an `Authorization` header
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Authorize requests with this provider, asking it for new credentials when the server
+    /// answers 401
+    pub fn with_auth(mut self, auth: Arc<dyn AuthProvider>) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// The session id assigned by the server, once initialized
+    pub async fn session_id(&self) -> Option<String> {
+        self.session_id.read().await.clone()
+    }
+
+    fn connection(&self) -> Connection {
+        Connection {
+            http_client: self.http_client.clone(),
+            url: self.url.clone(),
+            headers: self.headers.clone(),
+            auth: self.auth.clone(),
+            session_id: Arc::clone(&self.session_id),
+            notifications: self.notifications.clone(),
+            server_requests: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for StreamableHttpTransport {
+    type Handle = StreamableHttpTransportHandle;
+
+    async fn start(&self) -> Result<Self::Handle, Error> {
+        // Set environment variables
+        for (key, value) in &self.env {
+            std::env::set_var(key, value);
+        }
+
+        let (tx, rx) = mpsc::channel(32);
+        let (request_sender, server_requests) = ServerRequests::channel(&tx);
+        let actor = StreamableHttpActor {
+            receiver: rx,
+            connection: Connection {
+                server_requests: Some(request_sender),
+                ..self.connection()
+            },
+        };
+        tokio::spawn(actor.run());
+
+        Ok(StreamableHttpTransportHandle {
+            sender: tx,
+            notifications: self.notifications.clone(),
+            server_requests: PendingServerRequests::new(server_requests),
+        })
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        if self.session_id.read().await.is_none() {
+            return Ok(());
+        }
+        // Servers that don't let clients end sessions answer 405, which is fine
+        match self
+            .connection()
+            .request(self.http_client.delete(&self.url))
+            .await
+        {
+            Ok(_) | Err(Error::HttpError { status: 405, .. }) => {}
+            Err(e) => return Err(e),
+        }
+        *self.session_id.write().await = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_parser_handles_split_chunks() {
+        let mut parser = EventParser::default();
+        assert!(parser.feed(b"id: 1\nevent: mess").is_empty());
+        let events = parser.feed(b"age\ndata: {\"a\":\r\ndata: 1}\r\n\r\n: ping\n\nid: 2\n");
+        assert_eq!(
+            events,
+            vec![Event {
+                event: "message".to_string(),
+                data: "{\"a\":\n1}".to_string(),
+            }]
+        );
+        assert_eq!(parser.last_event_id.as_deref(), Some("2"));
+
+        let events = parser.feed(b"data: x\n\n");
+        assert_eq!(events[0].event, "message");
+        assert_eq!(events[0].data, "x");
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     prompt::{Prompt, PromptMessage},
     resource::Resource,
     resource::ResourceContents,
+    role::Role,
     tool::Tool,
 };
 use serde::{Deserialize, Serialize};
@@ -237,6 +238,69 @@ pub struct GetPromptResult {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmptyResult {}
 
+/// A message in a `sampling/createMessage` request
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SamplingMessage {
+    pub role: Role,
+    pub content: Content,
+}
+
+/// A model name the server would like to be used, matched as a substring
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModelHint {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// The server's preferences for the model that answers a sampling request, priorities go
+/// from 0 to 1
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPreferences {
+    #[serde(default)]
+    pub hints: Vec<ModelHint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_priority: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_priority: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intelligence_priority: Option<f32>,
+}
+
+/// The params of a `sampling/createMessage` request, in which the server asks the client
+/// for a completion
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageParams {
+    pub messages: Vec<SamplingMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<ModelPreferences>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Which conversation context to include: "none", "thisServer" or "allServers"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    pub max_tokens: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: Role,
+    pub content: Content,
+    /// The model that produced the message
+    pub model: String,
+    /// "endTurn", "stopSequence" or "maxTokens"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;